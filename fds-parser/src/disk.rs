@@ -0,0 +1,199 @@
+//!
+//! File system of a disk side
+//!
+//! [Disk structure documentation](https://www.nesdev.org/wiki/FDS_disk_format)
+//!
+
+use {
+    crate::{Error, Result},
+    alloc::{borrow::Cow, string::String, vec::Vec},
+};
+
+const VERIFICATION: &[u8] = b"*NINTENDO-HVC*";
+
+const DISK_INFO_BLOCK: u8 = 1;
+const FILE_AMOUNT_BLOCK: u8 = 2;
+const FILE_HEADER_BLOCK: u8 = 3;
+const FILE_DATA_BLOCK: u8 = 4;
+
+// Sizes of the blocks, including the block code
+const DISK_INFO_SIZE: usize = 56;
+const FILE_AMOUNT_SIZE: usize = 2;
+const FILE_HEADER_SIZE: usize = 16;
+
+/// Contents of the disk info block at the start of every side
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DiskInfo {
+    /// Licensee code of the publisher
+    pub manufacturer: u8,
+    /// Three letter code of the game
+    pub game_name: String,
+    pub game_type: u8,
+    pub revision: u8,
+    /// Side of the disk, 0 for side A and 1 for side B
+    pub side: u8,
+    pub disk: u8,
+    pub disk_type: u8,
+    /// Files with an ID up to this one are loaded when booting
+    pub boot_file: u8,
+    /// Year (of the Shōwa era), month and day in BCD
+    pub manufacturing_date: [u8; 3],
+    pub country: u8,
+}
+
+/// What the file gets loaded into
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FileKind {
+    /// CPU memory
+    Prg,
+    /// Pattern tables of the PPU
+    Chr,
+    /// Nametables of the PPU
+    Nametable,
+    Unknown(u8),
+}
+
+impl From<u8> for FileKind {
+    fn from(kind: u8) -> Self {
+        match kind {
+            0 => Self::Prg,
+            1 => Self::Chr,
+            2 => Self::Nametable,
+            _ => Self::Unknown(kind),
+        }
+    }
+}
+
+/// File on a disk side
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct File<'a> {
+    /// Position of the file on the side
+    pub number: u8,
+    /// ID the BIOS loads files by
+    pub id: u8,
+    /// Name padded to eight bytes, usually ASCII
+    pub name: [u8; 8],
+    /// Address the file gets loaded to
+    pub address: u16,
+    pub kind: FileKind,
+    pub data: &'a [u8],
+    /// The file is past the amount of files stated in the file amount block,
+    /// which some games use as copy protection
+    pub hidden: bool,
+}
+
+impl File<'_> {
+    /// The name, without the padding
+    #[must_use]
+    pub fn name(&self) -> Cow<'_, str> {
+        let end = self
+            .name
+            .iter()
+            .rposition(|byte| *byte != 0 && *byte != b' ')
+            .map_or(0, |last| last + 1);
+
+        String::from_utf8_lossy(&self.name[..end])
+    }
+}
+
+/// Listing of the files on a disk side
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Disk<'a> {
+    pub info: DiskInfo,
+    /// Amount of files stated in the file amount block
+    pub file_amount: u8,
+    pub files: Vec<File<'a>>,
+}
+
+impl<'a> Disk<'a> {
+    /// Parse the blocks of a disk side
+    pub fn parse(side: &'a [u8]) -> Result<Self> {
+        let mut reader = BlockReader {
+            data: side,
+            offset: 0,
+        };
+
+        let info = reader.block(DISK_INFO_BLOCK, DISK_INFO_SIZE)?;
+        if &info[1..=VERIFICATION.len()] != VERIFICATION {
+            return Err(Error::InvalidVerification);
+        }
+
+        let info = DiskInfo {
+            manufacturer: info[15],
+            game_name: String::from_utf8_lossy(&info[16..19]).into_owned(),
+            game_type: info[19],
+            revision: info[20],
+            side: info[21],
+            disk: info[22],
+            disk_type: info[23],
+            boot_file: info[25],
+            manufacturing_date: [info[31], info[32], info[33]],
+            country: info[34],
+        };
+
+        let file_amount = reader.block(FILE_AMOUNT_BLOCK, FILE_AMOUNT_SIZE)?[1];
+
+        // Files continue as long as there are file headers, which includes hidden files
+        let mut files = Vec::new();
+        while reader.peek() == Some(FILE_HEADER_BLOCK) {
+            let header = reader.block(FILE_HEADER_BLOCK, FILE_HEADER_SIZE)?;
+            let mut name = [0; 8];
+            name.copy_from_slice(&header[3..11]);
+            let length = usize::from(u16::from_le_bytes([header[13], header[14]]));
+
+            let data = &reader.block(FILE_DATA_BLOCK, length + 1)?[1..];
+            files.push(File {
+                number: header[1],
+                id: header[2],
+                name,
+                address: u16::from_le_bytes([header[11], header[12]]),
+                kind: FileKind::from(header[15]),
+                data,
+                hidden: files.len() >= usize::from(file_amount),
+            });
+        }
+
+        Ok(Self {
+            info,
+            file_amount,
+            files,
+        })
+    }
+
+    /// Files loaded by the BIOS when booting from this side
+    pub fn boot_files(&self) -> impl Iterator<Item = &File<'a>> {
+        self.files
+            .iter()
+            .filter(move |file| file.id <= self.info.boot_file)
+    }
+}
+
+struct BlockReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> BlockReader<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.offset).copied()
+    }
+
+    fn block(&mut self, code: u8, size: usize) -> Result<&'a [u8]> {
+        let found = self.peek().ok_or(Error::UnexpectedEof)?;
+        if found != code {
+            return Err(Error::UnexpectedBlock {
+                offset: self.offset,
+                expected: code,
+                found,
+            });
+        }
+
+        let block = self
+            .data
+            .get(self.offset..self.offset + size)
+            .ok_or(Error::UnexpectedEof)?;
+        self.offset += size;
+
+        Ok(block)
+    }
+}
@@ -0,0 +1,185 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![warn(clippy::all, clippy::pedantic)]
+#![allow(clippy::missing_errors_doc)]
+
+//!
+//! Parser for the disk images of the Famicom Disk System
+//!
+//! [File format documentation](https://www.nesdev.org/wiki/FDS_file_format)
+//!
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+use std::io::{self, Read};
+
+use alloc::{borrow::Cow, vec::Vec};
+
+pub mod disk;
+
+pub use disk::Disk;
+
+// The word "FDS" followed by the MS-DOS EOF delimiter
+const MAGIC_BYTES: [u8; 4] = [0x46, 0x44, 0x53, 0x1A];
+
+/// Size of the fwNES header some images start with
+pub const HEADER_SIZE: usize = 16;
+
+/// Size of one side of a disk
+pub const SIDE_SIZE: usize = 65500;
+
+type Result<T> = core::result::Result<T, Error>;
+
+#[derive(Debug)]
+#[cfg_attr(feature = "std", derive(thiserror::Error))]
+pub enum Error {
+    #[cfg(feature = "std")]
+    #[error("IO error: {:?}", .0)]
+    Io(#[from] io::Error),
+
+    #[cfg_attr(feature = "std", error("The image size {} isn't a multiple of the side size", .0))]
+    InvalidSize(usize),
+
+    #[cfg_attr(feature = "std", error("The image doesn't contain any sides"))]
+    NoSides,
+
+    #[cfg_attr(feature = "std", error("Images can contain at most 255 sides, got {}", .0))]
+    TooManySides(usize),
+
+    #[cfg_attr(feature = "std", error("The disk side ended in the middle of a block"))]
+    UnexpectedEof,
+
+    #[cfg_attr(
+        feature = "std",
+        error("The disk info block doesn't start with *NINTENDO-HVC*")
+    )]
+    InvalidVerification,
+
+    #[cfg_attr(
+        feature = "std",
+        error("Expected block {} at offset {}, found block {}", .expected, .offset, .found)
+    )]
+    UnexpectedBlock {
+        offset: usize,
+        expected: u8,
+        found: u8,
+    },
+}
+
+/// Whether the image starts with a fwNES header
+#[must_use]
+pub fn has_header(data: &[u8]) -> bool {
+    data.starts_with(&MAGIC_BYTES)
+}
+
+/// The image without its fwNES header, if it has one
+#[must_use]
+pub fn strip_header(data: &[u8]) -> &[u8] {
+    if has_header(data) {
+        &data[HEADER_SIZE.min(data.len())..]
+    } else {
+        data
+    }
+}
+
+/// The image with a fwNES header, replacing the existing one
+pub fn add_header(data: &[u8]) -> Result<Vec<u8>> {
+    Fds::from_bytes(data).map(|fds| fds.to_bytes(true))
+}
+
+// Same as with INES ROMs, the data can either be borrowed or owned
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fds<'a> {
+    /// Sides of the disks, each exactly `SIDE_SIZE` bytes long
+    pub sides: Vec<Cow<'a, [u8]>>,
+}
+
+fn validate_side_count(count: usize) -> Result<()> {
+    match count {
+        0 => Err(Error::NoSides),
+        1..=255 => Ok(()),
+        _ => Err(Error::TooManySides(count)),
+    }
+}
+
+impl<'a> Fds<'a> {
+    /// Parse an image from a byte slice, with or without a fwNES header
+    ///
+    /// The amount of sides comes from the size of the image, as the header isn't always accurate
+    pub fn from_bytes(data: &'a [u8]) -> Result<Self> {
+        let data = strip_header(data);
+        if !data.len().is_multiple_of(SIDE_SIZE) {
+            return Err(Error::InvalidSize(data.len()));
+        }
+        validate_side_count(data.len() / SIDE_SIZE)?;
+
+        Ok(Fds {
+            sides: data.chunks(SIDE_SIZE).map(Cow::Borrowed).collect(),
+        })
+    }
+
+    #[cfg(feature = "std")]
+    /// Parse an image from a file stream, with or without a fwNES header
+    pub fn from_reader<T: Read>(input_stream: &mut T) -> Result<Fds<'static>> {
+        let mut data = Vec::new();
+        input_stream.read_to_end(&mut data)?;
+
+        let sides = Fds::from_bytes(&data)?
+            .sides
+            .into_iter()
+            .map(|side| Cow::Owned(side.into_owned()))
+            .collect();
+
+        Ok(Fds { sides })
+    }
+
+    /// Merge single sides or images into one image
+    pub fn merge<I>(images: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = &'a [u8]>,
+    {
+        let mut sides = Vec::new();
+        for image in images {
+            sides.extend(Self::from_bytes(image)?.sides);
+        }
+        validate_side_count(sides.len())?;
+
+        Ok(Fds { sides })
+    }
+
+    /// Split the image into one image per side
+    #[must_use]
+    pub fn split(&self) -> Vec<Fds<'_>> {
+        self.sides
+            .iter()
+            .map(|side| Fds {
+                sides: alloc::vec![Cow::Borrowed(&**side)],
+            })
+            .collect()
+    }
+
+    /// Write the image, optionally with a fwNES header
+    #[must_use]
+    pub fn to_bytes(&self, with_header: bool) -> Vec<u8> {
+        let mut data = Vec::with_capacity(HEADER_SIZE + self.sides.len() * SIDE_SIZE);
+
+        if with_header {
+            data.extend_from_slice(&MAGIC_BYTES);
+            // The amount of sides was validated when parsing
+            #[allow(clippy::cast_possible_truncation)]
+            data.push(self.sides.len() as u8);
+            data.resize(HEADER_SIZE, 0);
+        }
+
+        for side in &self.sides {
+            data.extend_from_slice(side);
+        }
+
+        data
+    }
+
+    /// List the files of every side
+    pub fn disks(&self) -> Result<Vec<Disk<'_>>> {
+        self.sides.iter().map(|side| Disk::parse(side)).collect()
+    }
+}
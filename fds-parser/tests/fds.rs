@@ -0,0 +1,60 @@
+use fds_parser::{add_header, has_header, strip_header, Error, Fds, HEADER_SIZE, SIDE_SIZE};
+
+// Headerless image where every byte of a side holds its number
+fn image(sides: u8) -> Vec<u8> {
+    (0..sides).flat_map(|side| vec![side; SIDE_SIZE]).collect()
+}
+
+#[test]
+fn parse() {
+    let image = image(2);
+    let fds = Fds::from_bytes(&image).unwrap();
+
+    assert_eq!(fds.sides.len(), 2);
+    assert!(fds.sides[1].iter().all(|byte| *byte == 1));
+    assert_eq!(fds.to_bytes(false), image);
+}
+
+#[test]
+fn header() {
+    let image = image(2);
+    let with_header = add_header(&image).unwrap();
+
+    assert!(has_header(&with_header));
+    assert_eq!(with_header[..5], [b'F', b'D', b'S', 0x1A, 2]);
+    assert_eq!(with_header.len(), HEADER_SIZE + image.len());
+    assert_eq!(strip_header(&with_header), &image[..]);
+    assert!(!has_header(&image));
+
+    // The amount of sides comes from the size, not the header
+    let mut wrong_count = with_header.clone();
+    wrong_count[4] = 5;
+    assert_eq!(Fds::from_bytes(&wrong_count).unwrap().sides.len(), 2);
+}
+
+#[test]
+fn split_and_merge() {
+    let image = image(3);
+    let fds = Fds::from_bytes(&image).unwrap();
+
+    let sides: Vec<Vec<u8>> = fds.split().iter().map(|side| side.to_bytes(true)).collect();
+    assert_eq!(sides.len(), 3);
+    assert_eq!(sides[2][HEADER_SIZE], 2);
+
+    // Headers of the single sides get stripped when merging
+    let merged = Fds::merge(sides.iter().map(Vec::as_slice)).unwrap();
+    assert_eq!(merged, fds);
+}
+
+#[test]
+fn reject_invalid_images() {
+    assert!(matches!(
+        Fds::from_bytes(&[0; 100]),
+        Err(Error::InvalidSize(100))
+    ));
+    assert!(matches!(Fds::from_bytes(&[]), Err(Error::NoSides)));
+    assert!(matches!(
+        Fds::merge(std::iter::empty()),
+        Err(Error::NoSides)
+    ));
+}
@@ -0,0 +1,335 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![warn(clippy::all, clippy::pedantic)]
+#![allow(clippy::missing_errors_doc)]
+
+//!
+//! Parser for the metadata of `FamiTracker` modules (FTM)
+//!
+//! [File format documentation](http://famitracker.com/wiki/index.php?title=FamiTracker_module)
+//!
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+use std::io::{self, Read};
+
+use {
+    alloc::{borrow::Cow, string::String, vec::Vec},
+    core::convert::TryInto,
+};
+
+const MAGIC_BYTES: &[u8] = b"FamiTracker Module";
+const END_MARKER: &[u8] = b"END";
+
+// Versions before 0.2 used a different layout
+const MIN_VERSION: u32 = 0x0200;
+
+const BLOCK_ID_SIZE: usize = 16;
+
+const PARAMS: &str = "PARAMS";
+const INFO: &str = "INFO";
+const HEADER: &str = "HEADER";
+const INSTRUMENTS: &str = "INSTRUMENTS";
+const FRAMES: &str = "FRAMES";
+
+const TEXT_SIZE: usize = 32;
+
+/// Expansion chip flag of Namco's N163, which has a configurable amount of channels
+const N163: u8 = 0x10;
+
+type Result<T> = core::result::Result<T, Error>;
+
+#[derive(Debug)]
+#[cfg_attr(feature = "std", derive(thiserror::Error))]
+pub enum Error {
+    #[cfg(feature = "std")]
+    #[error("IO error: {:?}", .0)]
+    Io(#[from] io::Error),
+
+    #[cfg_attr(
+        feature = "std",
+        error("The file doesn't start with \"FamiTracker Module\"")
+    )]
+    MagicBytesMismatch,
+
+    #[cfg_attr(feature = "std", error("The file ended in the middle of a block"))]
+    UnexpectedEof,
+
+    #[cfg_attr(feature = "std", error("Module version {:#06X} isn't supported", .0))]
+    UnsupportedVersion(u32),
+
+    #[cfg_attr(feature = "std", error("The module doesn't contain the block {}", .0))]
+    MissingBlock(&'static str),
+}
+
+/// TV system the module was written for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Machine {
+    Ntsc,
+    Pal,
+}
+
+/// Block of the module
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Block<'a> {
+    pub id: Cow<'a, str>,
+    pub version: u32,
+    pub data: &'a [u8],
+}
+
+/// Song (called track in `FamiTracker`) of the module
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Song {
+    pub name: String,
+    /// Ticks per row
+    pub speed: u32,
+    /// Tempo in beats per minute, if the module version stores it
+    pub tempo: Option<u32>,
+    /// Rows per pattern
+    pub rows: u32,
+    pub frames: u32,
+}
+
+/// Metadata of a module
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Module {
+    pub version: u32,
+
+    pub title: String,
+    pub author: String,
+    pub copyright: String,
+
+    pub machine: Machine,
+    /// Custom play routine rate in Hz; 0 uses the rate of the machine
+    pub engine_speed: u32,
+    /// Expansion audio chips, with the same flags as in NSF files
+    pub expansion_audio: u8,
+    pub channels: u32,
+    /// Amount of N163 channels, if the module uses the chip
+    pub n163_channels: Option<u32>,
+
+    pub songs: Vec<Song>,
+    pub instruments: u32,
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8]> {
+        if self.data.len() < length {
+            return Err(Error::UnexpectedEof);
+        }
+
+        let (taken, rest) = self.data.split_at(length);
+        self.data = rest;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn int(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(
+            self.take(4)?.try_into().map_err(|_| Error::UnexpectedEof)?,
+        ))
+    }
+
+    // Song names are terminated with a null byte
+    fn string(&mut self) -> Result<String> {
+        let end = self
+            .data
+            .iter()
+            .position(|byte| *byte == 0)
+            .ok_or(Error::UnexpectedEof)?;
+        let text = parse_text(self.take(end)?);
+        self.take(1)?;
+
+        Ok(text)
+    }
+}
+
+fn parse_text(data: &[u8]) -> String {
+    // The fields are padded with null bytes
+    let end = data
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).into_owned()
+}
+
+/// Split the module into its blocks, returning the version of the module
+pub fn blocks(data: &[u8]) -> Result<(u32, Vec<Block<'_>>)> {
+    let mut reader = Reader { data };
+    if reader
+        .take(MAGIC_BYTES.len())
+        .map_err(|_| Error::MagicBytesMismatch)?
+        != MAGIC_BYTES
+    {
+        return Err(Error::MagicBytesMismatch);
+    }
+
+    let version = reader.int()?;
+    if version < MIN_VERSION {
+        return Err(Error::UnsupportedVersion(version));
+    }
+
+    let mut blocks = Vec::new();
+    while !reader.data.starts_with(END_MARKER) {
+        let id = parse_text(reader.take(BLOCK_ID_SIZE)?);
+        let version = reader.int()?;
+        let size = reader.int()? as usize;
+        let data = reader.take(size)?;
+
+        blocks.push(Block {
+            id: Cow::Owned(id),
+            version,
+            data,
+        });
+    }
+
+    Ok((version, blocks))
+}
+
+impl Module {
+    /// Parse the metadata of a module from a byte slice
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let (version, blocks) = blocks(data)?;
+        let find = |id: &'static str| {
+            blocks
+                .iter()
+                .find(|block| block.id == id)
+                .ok_or(Error::MissingBlock(id))
+        };
+
+        let mut module = Self::parse_params(version, find(PARAMS)?)?;
+
+        if let Ok(info) = find(INFO) {
+            let mut reader = Reader { data: info.data };
+            module.title = parse_text(reader.take(TEXT_SIZE)?);
+            module.author = parse_text(reader.take(TEXT_SIZE)?);
+            module.copyright = parse_text(reader.take(TEXT_SIZE)?);
+        }
+
+        module.parse_header(find(HEADER)?)?;
+        module.parse_frames(find(FRAMES)?)?;
+
+        if let Ok(instruments) = find(INSTRUMENTS) {
+            module.instruments = Reader {
+                data: instruments.data,
+            }
+            .int()?;
+        }
+
+        Ok(module)
+    }
+
+    #[cfg(feature = "std")]
+    /// Parse the metadata of a module from a file stream
+    pub fn from_reader<T: Read>(input_stream: &mut T) -> Result<Self> {
+        let mut data = Vec::new();
+        input_stream.read_to_end(&mut data)?;
+
+        Self::from_bytes(&data)
+    }
+
+    fn parse_params(version: u32, block: &Block<'_>) -> Result<Self> {
+        let mut reader = Reader { data: block.data };
+
+        // The first version stored the speed of the only song here instead of the expansion chips
+        let (expansion_audio, speed) = if block.version == 1 {
+            (0, Some(reader.int()?))
+        } else {
+            (reader.byte()?, None)
+        };
+
+        let channels = reader.int()?;
+        let machine = if reader.int()? == 1 {
+            Machine::Pal
+        } else {
+            Machine::Ntsc
+        };
+        let engine_speed = reader.int()?;
+
+        if block.version >= 3 {
+            // Vibrato style
+            reader.int()?;
+        }
+        if block.version >= 4 {
+            // Row highlights
+            reader.take(8)?;
+        }
+        let n163_channels = if block.version >= 5 && expansion_audio & N163 != 0 {
+            Some(reader.int()?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            version,
+            title: String::new(),
+            author: String::new(),
+            copyright: String::new(),
+            machine,
+            engine_speed,
+            expansion_audio,
+            channels,
+            n163_channels,
+            songs: speed
+                .into_iter()
+                .map(|speed| Song {
+                    speed,
+                    ..Song::default()
+                })
+                .collect(),
+            instruments: 0,
+        })
+    }
+
+    fn parse_header(&mut self, block: &Block<'_>) -> Result<()> {
+        // The first version only had a single song
+        if block.version == 1 {
+            self.songs.resize_with(1, Song::default);
+            return Ok(());
+        }
+
+        let mut reader = Reader { data: block.data };
+        let songs = usize::from(reader.byte()?) + 1;
+        self.songs.resize_with(songs, Song::default);
+
+        if block.version >= 3 {
+            for song in &mut self.songs {
+                song.name = reader.string()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn parse_frames(&mut self, block: &Block<'_>) -> Result<()> {
+        let mut reader = Reader { data: block.data };
+
+        if block.version == 1 {
+            let song = self.songs.first_mut().ok_or(Error::MissingBlock(HEADER))?;
+            song.frames = reader.int()?;
+            return Ok(());
+        }
+
+        let channels = self.channels as usize;
+        for song in &mut self.songs {
+            song.frames = reader.int()?;
+            song.speed = reader.int()?;
+            if block.version >= 3 {
+                song.tempo = Some(reader.int()?);
+            }
+            song.rows = reader.int()?;
+
+            // Pattern indices of every channel in every frame
+            reader.take(song.frames as usize * channels)?;
+        }
+
+        Ok(())
+    }
+}
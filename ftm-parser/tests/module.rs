@@ -0,0 +1,111 @@
+use ftm_parser::{blocks, Error, Machine, Module};
+
+fn block(module: &mut Vec<u8>, id: &str, version: u32, data: &[u8]) {
+    let mut block_id = [0; 16];
+    block_id[..id.len()].copy_from_slice(id.as_bytes());
+
+    module.extend_from_slice(&block_id);
+    module.extend_from_slice(&version.to_le_bytes());
+    module.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    module.extend_from_slice(data);
+}
+
+fn ints(values: &[u32]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+fn text(value: &str) -> [u8; 32] {
+    let mut text = [0; 32];
+    text[..value.len()].copy_from_slice(value.as_bytes());
+    text
+}
+
+// Module with two songs on the five channels of the 2A03 plus the VRC6
+fn module() -> Vec<u8> {
+    let mut module = b"FamiTracker Module".to_vec();
+    module.extend_from_slice(&0x0440_u32.to_le_bytes());
+
+    let mut params = vec![0x01];
+    params.extend(ints(&[8, 1, 50, 0]));
+    params.extend_from_slice(&[0; 8]);
+    block(&mut module, "PARAMS", 6, &params);
+
+    let info: Vec<u8> = [text("Title"), text("Author"), text("2024")].concat();
+    block(&mut module, "INFO", 1, &info);
+
+    block(&mut module, "HEADER", 3, b"\x01Intro\0Main loop\0");
+    block(&mut module, "INSTRUMENTS", 6, &ints(&[3]));
+
+    let mut frames = ints(&[2, 6, 150, 64]);
+    frames.extend_from_slice(&[0; 2 * 8]);
+    frames.extend(ints(&[1, 3, 120, 32]));
+    frames.extend_from_slice(&[0; 8]);
+    block(&mut module, "FRAMES", 3, &frames);
+
+    module.extend_from_slice(b"END");
+    module
+}
+
+#[test]
+fn parse() {
+    let module = Module::from_bytes(&module()).unwrap();
+
+    assert_eq!(module.version, 0x0440);
+    assert_eq!(
+        (module.title.as_str(), module.author.as_str()),
+        ("Title", "Author")
+    );
+    assert_eq!(module.copyright, "2024");
+    assert_eq!(module.machine, Machine::Pal);
+    assert_eq!(module.engine_speed, 50);
+    assert_eq!((module.expansion_audio, module.channels), (0x01, 8));
+    assert_eq!(module.n163_channels, None);
+    assert_eq!(module.instruments, 3);
+
+    assert_eq!(module.songs.len(), 2);
+    assert_eq!(module.songs[0].name, "Intro");
+    assert_eq!(
+        (
+            module.songs[0].frames,
+            module.songs[0].speed,
+            module.songs[0].tempo
+        ),
+        (2, 6, Some(150))
+    );
+    assert_eq!(module.songs[1].name, "Main loop");
+    assert_eq!(module.songs[1].rows, 32);
+}
+
+#[test]
+fn list_blocks() {
+    let module = module();
+    let (version, blocks) = blocks(&module).unwrap();
+
+    assert_eq!(version, 0x0440);
+    let ids: Vec<&str> = blocks.iter().map(|block| &*block.id).collect();
+    assert_eq!(ids, ["PARAMS", "INFO", "HEADER", "INSTRUMENTS", "FRAMES"]);
+}
+
+#[test]
+fn reject_invalid_modules() {
+    let module = module();
+
+    assert!(matches!(
+        Module::from_bytes(b"Not a module"),
+        Err(Error::MagicBytesMismatch)
+    ));
+    assert!(matches!(
+        Module::from_bytes(&module[..module.len() - 10]),
+        Err(Error::UnexpectedEof)
+    ));
+
+    let mut old = module.clone();
+    old[18..22].copy_from_slice(&0x0100_u32.to_le_bytes());
+    assert!(matches!(
+        Module::from_bytes(&old),
+        Err(Error::UnsupportedVersion(0x0100))
+    ));
+}
@@ -0,0 +1,46 @@
+use {
+    criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput},
+    ines_parser::{Header, Ines},
+};
+
+// An MMC3 ROM with a trainer, 256 KiB of PRG ROM and 128 KiB of CHR ROM
+fn rom() -> Vec<u8> {
+    let mut rom = vec![b'N', b'E', b'S', 0x1A, 16, 16, 0x46, 0x08, 0, 0, 0x07, 0x07];
+    rom.resize(16 + 512 + 16 * 16_384 + 16 * 8192, 0);
+    for (index, byte) in rom.iter_mut().enumerate().skip(16) {
+        *byte = (index % 251) as u8;
+    }
+
+    rom
+}
+
+fn header(c: &mut Criterion) {
+    let rom = rom();
+
+    c.bench_function("header", |b| {
+        b.iter(|| Header::from_bytes(black_box(&rom)).unwrap())
+    });
+}
+
+fn full_rom(c: &mut Criterion) {
+    let rom = rom();
+
+    let mut group = c.benchmark_group("full_rom");
+    group.throughput(Throughput::Bytes(rom.len() as u64));
+    group.bench_function("from_bytes", |b| {
+        b.iter(|| {
+            Ines::from_bytes(black_box(&rom))
+                .unwrap()
+                .header
+                .mapper_number
+        })
+    });
+    group.bench_function("to_bytes", |b| {
+        let ines = Ines::from_bytes(&rom).unwrap();
+        b.iter(|| black_box(&ines).to_bytes())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, header, full_rom);
+criterion_main!(benches);
@@ -0,0 +1,78 @@
+//!
+//! `quickcheck` generators for valid headers, ROM files and CHR data
+//!
+//! [`Header`] implements `Arbitrary` directly, ROM files and CHR data are generated through the [`Rom`] and [`ChrData`] wrappers.
+//! The limits are the same as the ones of the `proptest` strategies: up to 512 KiB of PRG ROM and 256 KiB of CHR ROM.
+//!
+
+use {
+    crate::{generate, Header, HEADER_SIZE},
+    alloc::{boxed::Box, vec::Vec},
+    core::fmt,
+    quickcheck::{Arbitrary, Gen},
+};
+
+fn header_bytes(g: &mut Gen) -> [u8; HEADER_SIZE] {
+    let is_nes2 = bool::arbitrary(g);
+    let prg_banks = u8::arbitrary(g) % generate::MAX_PRG_BANKS + 1;
+    let chr_banks = u8::arbitrary(g) % (generate::MAX_CHR_BANKS + 1);
+    let choices = core::array::from_fn(|_| u8::arbitrary(g));
+
+    generate::header_bytes(is_nes2, prg_banks, chr_banks, choices)
+}
+
+impl Arbitrary for Header {
+    fn arbitrary(g: &mut Gen) -> Self {
+        generate::header(&header_bytes(g))
+    }
+}
+
+/// ROM file whose header is valid and whose trainer, PRG ROM and CHR ROM have random contents
+#[derive(Clone)]
+pub struct Rom(pub Vec<u8>);
+
+// Printing megabytes of random contents in failure messages would bury the header
+impl fmt::Debug for Rom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Rom")
+            .field("header", &&self.0[..HEADER_SIZE])
+            .field("size", &self.0.len())
+            .finish()
+    }
+}
+
+impl Arbitrary for Rom {
+    fn arbitrary(g: &mut Gen) -> Self {
+        let header_bytes = header_bytes(g);
+
+        Self(generate::rom(&header_bytes, u64::arbitrary(g)))
+    }
+}
+
+/// CHR data consisting of up to `Gen::size` 16 byte tiles
+#[derive(Clone, Debug)]
+pub struct ChrData(pub Vec<u8>);
+
+impl Arbitrary for ChrData {
+    fn arbitrary(g: &mut Gen) -> Self {
+        let tiles = usize::arbitrary(g) % (g.size() + 1);
+
+        Self(
+            (0..tiles * generate::SPRITE_SIZE)
+                .map(|_| u8::arbitrary(g))
+                .collect(),
+        )
+    }
+
+    // Shrink towards fewer tiles, keeping the length a multiple of the tile size
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        let data = self.0.clone();
+        let tiles = data.len() / generate::SPRITE_SIZE;
+
+        Box::new(
+            tiles
+                .shrink()
+                .map(move |tiles| Self(data[..tiles * generate::SPRITE_SIZE].to_vec())),
+        )
+    }
+}
@@ -0,0 +1,422 @@
+//!
+//! Field by field explanation of headers
+//!
+//! [NES 2.0 documentation](https://www.nesdev.org/wiki/NES_2.0)
+//!
+
+use {
+    crate::{
+        Header, Timing, VramLayout, CHR_ROM_CHUNK_SIZE, HEADER_SIZE, PRG_RAM_CHUNK_SIZE,
+        PRG_ROM_CHUNK_SIZE,
+    },
+    alloc::{format, string::String, vec::Vec},
+    core::fmt,
+};
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+// Mappers setting the mirroring at runtime, which makes the mirroring bit meaningless
+const MIRRORING_MAPPERS: &[u8] = &[
+    1, 4, 5, 7, 9, 10, 16, 18, 19, 21, 22, 23, 24, 25, 26, 30, 33, 48, 64, 68, 69, 85, 118, 159,
+    210,
+];
+
+/// One field of a [`HeaderReport`], occupying some of the bits of a header byte
+///
+/// Fields spanning several bytes, like the mapper number, are reported once per byte.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct HeaderField {
+    pub name: &'static str,
+    /// Offset of the byte in the header
+    pub offset: usize,
+    /// Bits of the byte belonging to the field
+    pub mask: u8,
+    /// Value of the bits, shifted down to bit 0
+    pub value: u8,
+    /// What the value means for the ROM
+    pub meaning: String,
+    /// Pitfalls of the field, like values emulators commonly ignore
+    pub caveats: Vec<&'static str>,
+}
+
+impl HeaderField {
+    fn new(name: &'static str, bytes: &[u8], offset: usize, mask: u8, meaning: String) -> Self {
+        Self {
+            name,
+            offset,
+            mask,
+            value: (bytes[offset] & mask) >> mask.trailing_zeros(),
+            meaning,
+            caveats: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    fn caveat(mut self, condition: bool, caveat: &'static str) -> Self {
+        if condition {
+            self.caveats.push(caveat);
+        }
+        self
+    }
+
+    /// Bits of the byte from bit 7 down to bit 0, with `-` for the ones belonging to other fields
+    #[must_use]
+    pub fn bits(&self, byte: u8) -> String {
+        (0..8)
+            .rev()
+            .map(|bit| match (self.mask >> bit & 1, byte >> bit & 1) {
+                (0, _) => '-',
+                (_, 0) => '0',
+                _ => '1',
+            })
+            .collect()
+    }
+}
+
+/// Explanation of what each field of a header does, for tools teaching the format
+///
+/// It's created with [`Header::explain`] and describes the header as the parser understood it, so the bytes are
+/// the ones [`Header::to_bytes`] writes. The [`Display`](fmt::Display) implementation renders it as text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct HeaderReport {
+    pub bytes: [u8; HEADER_SIZE],
+    /// Fields ordered by their offset
+    pub fields: Vec<HeaderField>,
+}
+
+// Size in the largest unit it's a whole multiple of
+fn format_size(size: usize) -> String {
+    match size {
+        0 => String::from("none"),
+        _ if size.is_multiple_of(0x10_0000) => format!("{} MiB", size >> 20),
+        _ if size.is_multiple_of(0x400) => format!("{} KiB", size >> 10),
+        _ => format!("{size} bytes"),
+    }
+}
+
+fn format_chunks(size: usize, chunk_size: usize) -> String {
+    format!(
+        "{} x {} = {}",
+        size / chunk_size,
+        format_size(chunk_size),
+        format_size(size)
+    )
+}
+
+// Name of a NES 2.0 default expansion device
+fn expansion_device_name(device: u8) -> &'static str {
+    match device {
+        0x00 => "unspecified",
+        0x01 => "standard controllers",
+        0x02 => "NES Four Score or Satellite",
+        0x03 => "Famicom four player adapter",
+        0x04 => "Vs. System controllers, player 1 at $4016",
+        0x05 => "Vs. System controllers, player 1 at $4017",
+        0x06 => "Vs. Pinball controls",
+        0x07 => "Vs. System Zapper",
+        0x08 => "Zapper",
+        0x09 => "two Zappers",
+        0x0A => "Bandai Hyper Shot",
+        0x0B => "Power Pad, side A",
+        0x0C => "Power Pad, side B",
+        0x0F => "Arkanoid controller (NES)",
+        0x10 => "Arkanoid controller (Famicom)",
+        0x23 => "Family BASIC keyboard",
+        _ => "other device",
+    }
+}
+
+impl Header {
+    /// Report of every field the header sets, with its raw bits, what it means and its caveats
+    #[must_use]
+    // The report lists the fields in order, which reads best in one function
+    #[allow(clippy::too_many_lines)]
+    pub fn explain(&self) -> HeaderReport {
+        let bytes = self.to_bytes();
+        let mut fields = Vec::new();
+
+        let prg_meaning = if self.is_nes2 && bytes[9] & 0x0F == 0x0F {
+            format!("{} (see byte 9)", format_size(self.prg_rom_size))
+        } else {
+            format_chunks(self.prg_rom_size, PRG_ROM_CHUNK_SIZE)
+        };
+        fields.push(
+            HeaderField::new("PRG ROM size", &bytes, 4, 0xFF, prg_meaning)
+                .caveat(self.prg_rom_size == 0, "A ROM without PRG ROM can't run"),
+        );
+
+        let chr_meaning = if self.chr_rom_size == 0 {
+            String::from("none, the board has CHR RAM instead")
+        } else if self.is_nes2 && bytes[9] >> 4 == 0x0F {
+            format!("{} (see byte 9)", format_size(self.chr_rom_size))
+        } else {
+            format_chunks(self.chr_rom_size, CHR_ROM_CHUNK_SIZE)
+        };
+        fields.push(HeaderField::new(
+            "CHR ROM size",
+            &bytes,
+            5,
+            0xFF,
+            chr_meaning,
+        ));
+
+        let four_screen = self.vram_layout == VramLayout::FourScreen;
+        let mirroring = match (four_screen, self.mirroring_flag) {
+            (true, _) => "ignored, the nametables aren't mirrored",
+            (false, true) => "vertical (horizontally arranged nametables)",
+            (false, false) => "horizontal (vertically arranged nametables)",
+        };
+        fields.push(
+            HeaderField::new("Mirroring", &bytes, 6, 0x01, String::from(mirroring))
+                .caveat(
+                    MIRRORING_MAPPERS.contains(&self.mapper_number),
+                    "The mapper switches the mirroring at runtime, which overrides the bit",
+                )
+                .caveat(
+                    four_screen && self.mirroring_flag,
+                    "Some mappers combine the bit with the four-screen bit to select another layout",
+                ),
+        );
+
+        let battery = if self.has_persistent_memory {
+            "the cartridge keeps its RAM with a battery or other non-volatile memory"
+        } else {
+            "no non-volatile memory"
+        };
+        fields.push(HeaderField::new(
+            "Battery",
+            &bytes,
+            6,
+            0x02,
+            String::from(battery),
+        ));
+
+        let trainer = if self.has_trainer {
+            "512 bytes loaded to $7000 are located between the header and the PRG ROM"
+        } else {
+            "none"
+        };
+        fields.push(
+            HeaderField::new("Trainer", &bytes, 6, 0x04, String::from(trainer)).caveat(
+                self.has_trainer,
+                "Trainers were added by copier devices and aren't part of the original cartridge",
+            ),
+        );
+
+        let vram = if four_screen {
+            "the cartridge has VRAM for four separate nametables"
+        } else {
+            "the console's 2 KiB of VRAM hold two nametables"
+        };
+        fields.push(HeaderField::new(
+            "Four-screen VRAM",
+            &bytes,
+            6,
+            0x08,
+            String::from(vram),
+        ));
+
+        fields.push(HeaderField::new(
+            "Mapper number, lower nibble",
+            &bytes,
+            6,
+            0xF0,
+            format!("mapper {}, together with byte 7", self.mapper()),
+        ));
+
+        let format_name = if self.is_nes2 { "NES 2.0" } else { "iNES 1" };
+        fields.push(HeaderField::new(
+            "Header format",
+            &bytes,
+            7,
+            0x0C,
+            String::from(format_name),
+        ));
+
+        fields.push(
+            HeaderField::new(
+                "Mapper number, upper nibble",
+                &bytes,
+                7,
+                0xF0,
+                format!("mapper {}, together with byte 6", self.mapper()),
+            )
+            .caveat(
+                !self.is_nes2 && self.mapper_number > 0x0F,
+                "Old dumps with text like \"DiskDude!\" in bytes 7-15 have garbage in this nibble",
+            ),
+        );
+
+        if self.is_nes2 {
+            self.explain_nes2(&bytes, &mut fields);
+        } else {
+            self.explain_ines1(&bytes, &mut fields);
+        }
+
+        HeaderReport { bytes, fields }
+    }
+
+    fn explain_ines1(&self, bytes: &[u8], fields: &mut Vec<HeaderField>) {
+        let prg_ram_size = self.prg_ram_size + self.prg_nvram_size;
+        fields.push(
+            HeaderField::new(
+                "PRG RAM size",
+                bytes,
+                8,
+                0xFF,
+                format_chunks(prg_ram_size, PRG_RAM_CHUNK_SIZE),
+            )
+            .caveat(
+                true,
+                "0 means 8 KiB, and most emulators size the RAM from the mapper instead",
+            ),
+        );
+
+        let timing = if self.timing == Timing::Ntsc {
+            "NTSC"
+        } else {
+            "PAL"
+        };
+        fields.push(
+            HeaderField::new("TV system", bytes, 9, 0x01, String::from(timing)).caveat(
+                true,
+                "Hardly any dumps set this bit, so emulators look the region up in databases",
+            ),
+        );
+    }
+
+    fn explain_nes2(&self, bytes: &[u8], fields: &mut Vec<HeaderField>) {
+        fields.push(HeaderField::new(
+            "Mapper number, bits 8-11",
+            bytes,
+            8,
+            0x0F,
+            format!("mapper {}", self.mapper()),
+        ));
+        fields.push(
+            HeaderField::new(
+                "Submapper",
+                bytes,
+                8,
+                0xF0,
+                format!("submapper {}", self.submapper),
+            )
+            .caveat(
+                self.submapper == 0,
+                "0 is the default behavior of the mapper or an unknown variant",
+            ),
+        );
+
+        for (name, mask, size, chunk_size) in [
+            (
+                "PRG ROM size, upper bits",
+                0x0F,
+                self.prg_rom_size,
+                PRG_ROM_CHUNK_SIZE,
+            ),
+            (
+                "CHR ROM size, upper bits",
+                0xF0,
+                self.chr_rom_size,
+                CHR_ROM_CHUNK_SIZE,
+            ),
+        ] {
+            let meaning = if bytes[9] & mask == mask {
+                format!(
+                    "exponent-multiplier notation, the lower byte encodes {} as 2^E x (2M + 1)",
+                    format_size(size)
+                )
+            } else {
+                format!(
+                    "{}, together with the lower byte",
+                    format_chunks(size, chunk_size)
+                )
+            };
+            fields.push(HeaderField::new(name, bytes, 9, mask, meaning));
+        }
+
+        for (name, offset, mask, size) in [
+            ("PRG RAM size", 10, 0x0F, self.prg_ram_size),
+            ("PRG NVRAM size", 10, 0xF0, self.prg_nvram_size),
+            ("CHR RAM size", 11, 0x0F, self.chr_ram_size),
+            ("CHR NVRAM size", 11, 0xF0, self.chr_nvram_size),
+        ] {
+            let meaning = match size {
+                0 => String::from("none"),
+                _ => format!("64 << shift count = {}", format_size(size)),
+            };
+            let non_volatile = mask == 0xF0;
+            fields.push(
+                HeaderField::new(name, bytes, offset, mask, meaning)
+                    .caveat(
+                        non_volatile && size > 0 && !self.has_persistent_memory,
+                        "The battery bit is clear although the cartridge has non-volatile memory",
+                    )
+                    .caveat(
+                        size > 0 && !size.is_power_of_two(),
+                        "Only powers of two can be stored, the size was rounded down",
+                    ),
+            );
+        }
+
+        let timing = match self.timing {
+            Timing::Ntsc => "NTSC (RP2C02)",
+            Timing::Pal => "PAL (RP2C07)",
+            Timing::MultiRegion => "multiple regions, the game adapts to the console",
+            Timing::Dendy => "Dendy (UMC 6527P famiclones)",
+        };
+        fields.push(HeaderField::new(
+            "CPU/PPU timing",
+            bytes,
+            12,
+            0x03,
+            String::from(timing),
+        ));
+
+        fields.push(HeaderField::new(
+            "Default expansion device",
+            bytes,
+            15,
+            0x3F,
+            format!(
+                "{} ({})",
+                self.default_expansion_device,
+                expansion_device_name(self.default_expansion_device)
+            ),
+        ));
+    }
+}
+
+impl fmt::Display for HeaderReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format_name = if self.bytes[7] & 0x0C == 0x08 {
+            "NES 2.0"
+        } else {
+            "iNES 1"
+        };
+        write!(f, "{format_name} header:")?;
+        for byte in &self.bytes {
+            write!(f, " {byte:02X}")?;
+        }
+        writeln!(f)?;
+
+        for field in &self.fields {
+            writeln!(
+                f,
+                "Byte {:<2} {}  {}: {}",
+                field.offset,
+                field.bits(self.bytes[field.offset]),
+                field.name,
+                field.meaning
+            )?;
+            for caveat in &field.caveats {
+                writeln!(f, "                  Note: {caveat}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,90 @@
+// Building blocks of the `proptest` strategies and `quickcheck` generators
+//
+// Both pick the ROM sizes and a handful of random bytes, which are turned into a header here.
+// The random bytes are masked so every combination is a header the parser accepts and `Header::to_bytes` reproduces.
+
+use {
+    crate::{
+        parse_header, Header, CHR_ROM_CHUNK_SIZE, HEADER_SIZE, MAGIC_BYTES, PRG_ROM_CHUNK_SIZE,
+        TRAINER_SIZE,
+    },
+    alloc::vec::Vec,
+};
+
+// The generated ROMs are limited to 512 KiB of PRG ROM and 256 KiB of CHR ROM by default, so tests stay fast
+pub(crate) const MAX_PRG_BANKS: u8 = 32;
+pub(crate) const MAX_CHR_BANKS: u8 = 32;
+
+// Size of one 8x8 tile of the CHR data
+pub(crate) const SPRITE_SIZE: usize = 16;
+
+// Bytes 6, 7, 8, 10, 11, 12 and 15 of the header, the rest is derived from the sizes and the format
+pub(crate) type Choices = [u8; 7];
+
+pub(crate) fn header_bytes(
+    is_nes2: bool,
+    prg_banks: u8,
+    chr_banks: u8,
+    choices: Choices,
+) -> [u8; HEADER_SIZE] {
+    let [flags6, flags7, byte8, prg_ram, chr_ram, timing, expansion_device] = choices;
+
+    let mut header = [0; HEADER_SIZE];
+    header[..4].copy_from_slice(&MAGIC_BYTES);
+    header[4] = prg_banks.max(1);
+    header[5] = chr_banks;
+    header[6] = flags6;
+    // The lower nibble is the console type (always a NES here) and the format
+    header[7] = flags7 & 0xF0;
+
+    if is_nes2 {
+        header[7] |= 0x08;
+        // The lower nibble holds bits 8-11 of the mapper number, which `Header` doesn't have
+        header[8] = byte8 & 0xF0;
+        header[10] = prg_ram;
+        header[11] = chr_ram;
+        header[12] = timing & 0x03;
+        header[15] = expansion_device & 0x3F;
+    } else {
+        // Amount of 8 KiB PRG RAM chunks, where zero means one as well
+        header[8] = byte8.max(1);
+        header[9] = timing & 0x01;
+    }
+
+    header
+}
+
+pub(crate) fn header(header_bytes: &[u8; HEADER_SIZE]) -> Header {
+    parse_header(header_bytes).expect("Generated headers are always valid")
+}
+
+// The ROM file of the header, with pseudo-random contents derived from the seed
+//
+// The contents aren't picked byte by byte, which would make generating and shrinking megabytes of data slow
+pub(crate) fn rom(header_bytes: &[u8; HEADER_SIZE], seed: u64) -> Vec<u8> {
+    let trainer_size = if header_bytes[6] & 0x04 == 0 {
+        0
+    } else {
+        TRAINER_SIZE
+    };
+    let size = HEADER_SIZE
+        + trainer_size
+        + usize::from(header_bytes[4]) * PRG_ROM_CHUNK_SIZE
+        + usize::from(header_bytes[5]) * CHR_ROM_CHUNK_SIZE;
+
+    let mut rom = Vec::with_capacity(size);
+    rom.extend_from_slice(header_bytes);
+
+    // xorshift64, which mustn't start at zero
+    let mut state = seed | 1;
+    while rom.len() < size {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+
+        let remaining = size - rom.len();
+        rom.extend_from_slice(&state.to_le_bytes()[..remaining.min(8)]);
+    }
+
+    rom
+}
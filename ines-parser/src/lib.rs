@@ -7,15 +7,33 @@
 //!
 //! [File format documentation](http://wiki.nesdev.com/w/index.php/INES)
 //!
+//! The `serde` feature implements `Serialize` and `Deserialize` for the header, and `Serialize` for [`HeaderReport`].
+//! The `proptest` and `quickcheck` features add generators of valid headers, ROM files and CHR data for property tests
+//! (`strategy` and `arbitrary`).
+//!
 
 extern crate alloc;
 
+#[cfg(feature = "quickcheck")]
+pub mod arbitrary;
+pub mod explain;
+#[cfg(any(feature = "proptest", feature = "quickcheck"))]
+mod generate;
+pub mod region;
+#[cfg(feature = "proptest")]
+pub mod strategy;
+
+pub use explain::{HeaderField, HeaderReport};
+pub use region::{convert_region, RegionConversion, Timing};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 #[cfg(feature = "std")]
 use std::io::{self, Read};
 
 use {
-    alloc::borrow::Cow,
-    core::{array::TryFromSliceError, convert::TryInto},
+    alloc::{borrow::Cow, vec::Vec},
+    core::{array::TryFromSliceError, convert::TryInto, ops::Range},
 };
 
 // The word "NES" followed by the MS-DOS EOF delimiter
@@ -26,6 +44,13 @@ const TRAINER_SIZE: usize = 512;
 
 const PRG_ROM_CHUNK_SIZE: usize = 16_384;
 const CHR_ROM_CHUNK_SIZE: usize = 8192;
+const PRG_RAM_CHUNK_SIZE: usize = 8192;
+const CHR_RAM_SIZE: usize = 8192;
+
+// The interrupt vectors are the last six bytes of the address space
+const VECTORS_SIZE: usize = 6;
+const ADDRESS_SPACE_END: usize = 0x1_0000;
+const PRG_ROM_WINDOW_SIZE: usize = 0x8000;
 
 type Result<T> = core::result::Result<T, Error>;
 
@@ -41,6 +66,15 @@ pub enum Error {
 
     #[cfg_attr(feature = "std", error("TryFromSliceError"))]
     TryFromSlice(TryFromSliceError),
+
+    #[cfg_attr(feature = "std", error("The data ended before the end of the ROM"))]
+    UnexpectedEof,
+
+    #[cfg_attr(
+        feature = "std",
+        error("The ROM sizes of the header don't fit into memory")
+    )]
+    RomSizeOverflow,
 }
 
 impl From<TryFromSliceError> for Error {
@@ -49,23 +83,92 @@ impl From<TryFromSliceError> for Error {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum VramLayout {
     HorizontalMirroring,
     VerticalMirroring,
     FourScreen,
 }
 
-#[derive(Debug)]
+// The flags mirror the bits of the header
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Header {
     pub prg_rom_size: usize,
     pub chr_rom_size: usize,
     pub vram_layout: VramLayout,
+    /// Mirroring bit of the header, which some mappers combine with the four-screen bit to mean something else
+    pub mirroring_flag: bool,
     pub has_persistent_memory: bool,
 
     has_trainer: bool,
+    // INES 1 headers mostly leave the PRG RAM size at zero, which still means one chunk
+    unspecified_prg_ram: bool,
 
     pub mapper_number: u8,
+    /// Bits 8-11 of the mapper number (NES 2.0 only), see [`Header::mapper`]
+    pub mapper_high_bits: u8,
+    /// Console type bits: 0 for the NES/Famicom, 1 for the Vs. System, 2 for the PlayChoice-10, 3 for an extended console type
+    pub console_type: u8,
+
+    /// Whether the header uses the NES 2.0 extensions
+    pub is_nes2: bool,
+    /// Submapper number (NES 2.0 only)
+    pub submapper: u8,
+    /// Size of the volatile PRG RAM
+    pub prg_ram_size: usize,
+    /// Size of the battery-backed PRG RAM
+    pub prg_nvram_size: usize,
+    /// Size of the volatile CHR RAM
+    pub chr_ram_size: usize,
+    /// Size of the battery-backed CHR RAM
+    pub chr_nvram_size: usize,
+    /// CPU/PPU timing; INES 1 headers only tell NTSC and PAL apart
+    pub timing: Timing,
+    /// Vs. System PPU and hardware type, or the extended console type (NES 2.0 only)
+    pub console_type_details: u8,
+    /// Number of miscellaneous ROMs following the CHR ROM (NES 2.0 only)
+    pub misc_roms: u8,
+    /// Input device the game expects, numbered like on the nesdev wiki (NES 2.0 only, 0 if unspecified)
+    pub default_expansion_device: u8,
+}
+
+/// Interrupt vector
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Vector {
+    /// Address the CPU jumps to
+    pub address: u16,
+    /// Offset of the target into the PRG ROM, if it's inside of the fixed bank
+    pub offset: Option<usize>,
+}
+
+/// The interrupt vectors at `$FFFA`-`$FFFF`
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Vectors {
+    pub nmi: Vector,
+    pub reset: Vector,
+    pub irq: Vector,
+    /// Section of the PRG ROM mapped to the end of the address space at power-on
+    pub fixed_bank: Range<usize>,
+}
+
+// Size of the window at the end of the address space containing the last PRG ROM bank at power-on
+//
+// Mappers switching all 32 KiB at once don't have a fixed bank, but games put their vectors into every bank
+// since the power-on state is undefined, so the last bank is as good as any
+fn fixed_window_size(mapper_number: u8) -> usize {
+    match mapper_number {
+        // NROM, CNROM, AxROM, BNROM, Color Dreams, GxROM and other discrete logic boards without PRG banking
+        0 | 3 | 7 | 11 | 13 | 34 | 38 | 66 | 79 | 87 | 140 | 185 => 0x8000,
+        // MMC1, UxROM and their relatives
+        1 | 2 | 10 | 16 | 70 | 71 | 89 | 93 | 94 | 152 => 0x4000,
+        // Most ASIC mappers (MMC3, MMC5, VRC, FME-7, Namco 163, ...) fix the last 8 KiB
+        _ => 0x2000,
+    }
 }
 
 // We use the `Cow` type here to avoid unnecessary allocations
@@ -87,23 +190,88 @@ pub struct Ines<'a> {
     pub chr_rom: Option<Cow<'a, [u8]>>,
 }
 
+// Section of the ROM data, which might be cut off
+fn section(data: &[u8], start: usize, size: usize) -> Result<&[u8]> {
+    start
+        .checked_add(size)
+        .and_then(|end| data.get(start..end))
+        .ok_or(Error::UnexpectedEof)
+}
+
+// Read a section of a stream, which only grows the buffer as far as the stream actually goes
+#[cfg(feature = "std")]
+fn read_section<T: Read>(input_stream: &mut T, size: usize) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    input_stream.take(size as u64).read_to_end(&mut data)?;
+
+    if data.len() == size {
+        Ok(data)
+    } else {
+        Err(Error::UnexpectedEof)
+    }
+}
+
 fn bit_at(num: u8, offset: u8) -> bool {
     (num >> offset) & 1 == 1
 }
 
+// NES 2.0 stores RAM sizes as shift counts; zero means there's no RAM
+fn nes2_ram_size(shift_count: u8) -> usize {
+    if shift_count == 0 {
+        0
+    } else {
+        64 << shift_count
+    }
+}
+
+// NES 2.0 ROM sizes either extend the chunk count with a high nibble or use an exponent-multiplier notation
+//
+// The exponent goes up to 63, so the size doesn't necessarily fit into a `usize`
+fn nes2_rom_size(low_byte: u8, high_nibble: u8, chunk_size: usize) -> Option<usize> {
+    if high_nibble == 0x0F {
+        let exponent = low_byte >> 2;
+        let multiplier = usize::from(low_byte & 0x03) * 2 + 1;
+
+        1_usize
+            .checked_shl(u32::from(exponent))
+            .and_then(|size| size.checked_mul(multiplier))
+    } else {
+        (usize::from(high_nibble) << 8 | usize::from(low_byte)).checked_mul(chunk_size)
+    }
+}
+
+// The ROM and RAM sizes are named after the fields of the header
+#[allow(clippy::similar_names)]
 fn parse_header(header_data: &[u8]) -> Result<Header> {
+    if header_data.len() < HEADER_SIZE {
+        return Err(Error::UnexpectedEof);
+    }
+
     let magic_bytes = header_data[0..4].try_into()?;
     if magic_bytes != MAGIC_BYTES {
         return Err(Error::MagicBytesMismatch(magic_bytes));
     }
 
+    let is_nes2 = header_data[7] & 0x0C == 0x08;
+
     // Get the required bytes from the byte slice
     let num_prg_rom_chunk = header_data[4];
     let num_chr_rom_chunk = header_data[5];
 
     // Calculate the actual size in bytes
-    let prg_rom_size = (num_prg_rom_chunk as usize) * PRG_ROM_CHUNK_SIZE;
-    let chr_rom_size = (num_chr_rom_chunk as usize) * CHR_ROM_CHUNK_SIZE;
+    let (prg_rom_size, chr_rom_size) = if is_nes2 {
+        (
+            nes2_rom_size(num_prg_rom_chunk, header_data[9] & 0x0F, PRG_ROM_CHUNK_SIZE)
+                .ok_or(Error::RomSizeOverflow)?,
+            nes2_rom_size(num_chr_rom_chunk, header_data[9] >> 4, CHR_ROM_CHUNK_SIZE)
+                .ok_or(Error::RomSizeOverflow)?,
+        )
+    } else {
+        (
+            (num_prg_rom_chunk as usize) * PRG_ROM_CHUNK_SIZE,
+            (num_chr_rom_chunk as usize) * CHR_ROM_CHUNK_SIZE,
+        )
+    };
 
     // Check if the appropriate bits are set
     let four_screen_vram = bit_at(header_data[6], 3);
@@ -115,22 +283,212 @@ fn parse_header(header_data: &[u8]) -> Result<Header> {
     } else {
         VramLayout::HorizontalMirroring
     };
+    let mirroring_flag = bit_at(header_data[6], 0);
     let has_persistent_memory = bit_at(header_data[6], 1);
     let has_trainer = bit_at(header_data[6], 2);
 
     // Combine the upper bits of each byte to one mapper number
-    let mapper_number = (header_data[7] & 0x0F) | (header_data[6] >> 4);
+    let mapper_number = (header_data[7] & 0xF0) | (header_data[6] >> 4);
+    let mapper_high_bits = if is_nes2 { header_data[8] & 0x0F } else { 0 };
+    let console_type = header_data[7] & 0x03;
+
+    let (submapper, prg_ram_size, prg_nvram_size, chr_ram_size, chr_nvram_size) = if is_nes2 {
+        (
+            header_data[8] >> 4,
+            nes2_ram_size(header_data[10] & 0x0F),
+            nes2_ram_size(header_data[10] >> 4),
+            nes2_ram_size(header_data[11] & 0x0F),
+            nes2_ram_size(header_data[11] >> 4),
+        )
+    } else {
+        // INES 1 only knows the amount of 8 KiB PRG RAM chunks, where zero still means one chunk
+        // Whether it's battery-backed is up to the flag, and CHR RAM is assumed when there's no CHR ROM
+        let prg_ram_size = usize::from(header_data[8].max(1)) * PRG_RAM_CHUNK_SIZE;
+        let chr_ram_size = if chr_rom_size == 0 { CHR_RAM_SIZE } else { 0 };
+
+        if has_persistent_memory {
+            (0, 0, prg_ram_size, chr_ram_size, 0)
+        } else {
+            (0, prg_ram_size, 0, chr_ram_size, 0)
+        }
+    };
+
+    let timing = if is_nes2 {
+        Timing::from_bits(header_data[12])
+    } else if bit_at(header_data[9], 0) {
+        Timing::Pal
+    } else {
+        Timing::Ntsc
+    };
+    let (console_type_details, misc_roms, default_expansion_device) = if is_nes2 {
+        (header_data[13], header_data[14] & 0x03, header_data[15] & 0x3F)
+    } else {
+        (0, 0, 0)
+    };
 
     Ok(Header {
         prg_rom_size,
         chr_rom_size,
         vram_layout,
+        mirroring_flag,
         has_persistent_memory,
         has_trainer,
+        unspecified_prg_ram: !is_nes2 && header_data[8] == 0,
         mapper_number,
+        mapper_high_bits,
+        console_type,
+        is_nes2,
+        submapper,
+        prg_ram_size,
+        prg_nvram_size,
+        chr_ram_size,
+        chr_nvram_size,
+        timing,
+        console_type_details,
+        misc_roms,
+        default_expansion_device,
     })
 }
 
+// Inverse of `nes2_ram_size`; sizes which aren't a power of two are rounded down
+fn nes2_ram_shift_count(size: usize) -> u8 {
+    match size >> 6 {
+        0 => 0,
+        // The logarithm of a `usize` always fits
+        #[allow(clippy::cast_possible_truncation)]
+        chunks => (usize::BITS - 1 - chunks.leading_zeros()) as u8,
+    }
+}
+
+// Inverse of `nes2_rom_size`, returning the low byte and the high nibble
+fn nes2_rom_chunks(size: usize, chunk_size: usize) -> (u8, u8) {
+    let chunks = size / chunk_size;
+    if size.is_multiple_of(chunk_size) && chunks < 0xF00 {
+        // Masked to the byte and the nibble
+        #[allow(clippy::cast_possible_truncation)]
+        return ((chunks & 0xFF) as u8, (chunks >> 8) as u8);
+    }
+
+    // Sizes which aren't a multiple of the chunk size use the exponent-multiplier notation
+    let (exponent, multiplier) = [1, 3, 5, 7]
+        .iter()
+        .filter(|multiplier| size.is_multiple_of(**multiplier))
+        .map(|multiplier| ((size / multiplier).trailing_zeros(), *multiplier))
+        .find(|(exponent, multiplier)| (multiplier << exponent) == size && *exponent < 64)
+        .unwrap_or((0, 1));
+
+    // The exponent is below 64 and the multiplier encodes to two bits
+    #[allow(clippy::cast_possible_truncation)]
+    ((exponent << 2) as u8 | (multiplier / 2) as u8, 0x0F)
+}
+
+impl Header {
+    /// Header of an INES 1 ROM without a trainer, with the defaults INES 1 assumes for the RAM
+    #[must_use]
+    pub fn new(
+        prg_rom_size: usize,
+        chr_rom_size: usize,
+        mapper_number: u8,
+        vram_layout: VramLayout,
+    ) -> Self {
+        Self {
+            prg_rom_size,
+            chr_rom_size,
+            vram_layout,
+            mirroring_flag: vram_layout == VramLayout::VerticalMirroring,
+            has_persistent_memory: false,
+            has_trainer: false,
+            unspecified_prg_ram: true,
+            mapper_number,
+            mapper_high_bits: 0,
+            console_type: 0,
+            is_nes2: false,
+            submapper: 0,
+            prg_ram_size: PRG_RAM_CHUNK_SIZE,
+            prg_nvram_size: 0,
+            chr_ram_size: if chr_rom_size == 0 { CHR_RAM_SIZE } else { 0 },
+            chr_nvram_size: 0,
+            timing: Timing::Ntsc,
+            console_type_details: 0,
+            misc_roms: 0,
+            default_expansion_device: 0,
+        }
+    }
+
+    /// Parse only the header, which takes up the first 16 bytes of a ROM
+    ///
+    /// Unlike [`Ines::from_bytes`] this doesn't check whether the data contains the ROMs the header describes.
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        parse_header(data)
+    }
+
+    /// Whether a trainer is located between the header and the PRG ROM
+    #[must_use]
+    pub fn has_trainer(&self) -> bool {
+        self.has_trainer
+    }
+
+    /// Full mapper number, including the bits 8-11 of NES 2.0 headers
+    #[must_use]
+    pub fn mapper(&self) -> u16 {
+        u16::from(self.mapper_high_bits & 0x0F) << 8 | u16::from(self.mapper_number)
+    }
+
+    /// Serialize the header, in the NES 2.0 format if `is_nes2` is set
+    ///
+    /// INES 1 headers only store the ROM sizes in whole chunks and the PRG RAM in 8 KiB chunks
+    #[must_use]
+    pub fn to_bytes(&self) -> [u8; HEADER_SIZE] {
+        let mut header = [0; HEADER_SIZE];
+        header[..4].copy_from_slice(&MAGIC_BYTES);
+
+        header[6] = (self.mapper_number << 4)
+            | u8::from(self.has_trainer) << 2
+            | u8::from(self.has_persistent_memory) << 1;
+        match self.vram_layout {
+            VramLayout::HorizontalMirroring => (),
+            VramLayout::VerticalMirroring => header[6] |= 0x01,
+            VramLayout::FourScreen => header[6] |= 0x08 | u8::from(self.mirroring_flag),
+        }
+        header[7] = self.mapper_number & 0xF0 | self.console_type & 0x03;
+
+        if self.is_nes2 {
+            let (prg_low, prg_high) = nes2_rom_chunks(self.prg_rom_size, PRG_ROM_CHUNK_SIZE);
+            let (chr_low, chr_high) = nes2_rom_chunks(self.chr_rom_size, CHR_ROM_CHUNK_SIZE);
+
+            header[4] = prg_low;
+            header[5] = chr_low;
+            header[7] |= 0x08;
+            header[8] = self.submapper << 4 | self.mapper_high_bits & 0x0F;
+            header[9] = chr_high << 4 | prg_high;
+            header[10] = nes2_ram_shift_count(self.prg_nvram_size) << 4
+                | nes2_ram_shift_count(self.prg_ram_size);
+            header[11] = nes2_ram_shift_count(self.chr_nvram_size) << 4
+                | nes2_ram_shift_count(self.chr_ram_size);
+            header[12] = self.timing.bits();
+            header[13] = self.console_type_details;
+            header[14] = self.misc_roms & 0x03;
+            header[15] = self.default_expansion_device & 0x3F;
+        } else {
+            let prg_ram_chunks = (self.prg_ram_size + self.prg_nvram_size) / PRG_RAM_CHUNK_SIZE;
+            // INES 1 can't store larger sizes
+            #[allow(clippy::cast_possible_truncation)]
+            {
+                header[4] = (self.prg_rom_size / PRG_ROM_CHUNK_SIZE).min(0xFF) as u8;
+                header[5] = (self.chr_rom_size / CHR_ROM_CHUNK_SIZE).min(0xFF) as u8;
+                header[8] = if self.unspecified_prg_ram && prg_ram_chunks == 1 {
+                    0
+                } else {
+                    prg_ram_chunks.min(0xFF) as u8
+                };
+            }
+            header[9] = u8::from(matches!(self.timing, Timing::Pal | Timing::Dendy));
+        }
+
+        header
+    }
+}
+
 impl<'a> Ines<'a> {
     /// Parse an INES ROM from a byte slice
     pub fn from_bytes(data: &'a [u8]) -> Result<Self> {
@@ -140,7 +498,7 @@ impl<'a> Ines<'a> {
 
         // Get a reference to the trainer (if the ROM even has one)
         let (after_position, trainer) = if header.has_trainer {
-            let trainer = &data[HEADER_SIZE..HEADER_SIZE + TRAINER_SIZE];
+            let trainer = section(data, HEADER_SIZE, TRAINER_SIZE)?;
 
             (HEADER_SIZE + TRAINER_SIZE, Some(Cow::Borrowed(trainer)))
         } else {
@@ -148,15 +506,20 @@ impl<'a> Ines<'a> {
         };
 
         // Get a reference to the PRG ROM
-        let prg_rom = Cow::Borrowed(&data[after_position..after_position + header.prg_rom_size]);
+        let prg_rom = Cow::Borrowed(section(data, after_position, header.prg_rom_size)?);
 
         // Get a reference to the CHR ROM
         let chr_rom = if header.chr_rom_size > 0 {
-            let after_prg_rom = after_position + header.prg_rom_size;
-
-            Some(Cow::Borrowed(
-                &data[after_prg_rom..after_prg_rom + header.chr_rom_size],
-            ))
+            // The sections already ended if the sum doesn't fit
+            let after_prg_rom = after_position
+                .checked_add(header.prg_rom_size)
+                .ok_or(Error::UnexpectedEof)?;
+
+            Some(Cow::Borrowed(section(
+                data,
+                after_prg_rom,
+                header.chr_rom_size,
+            )?))
         } else {
             None
         };
@@ -188,16 +551,11 @@ impl<'a> Ines<'a> {
         };
 
         // Read the PRG ROM
-        let mut prg_rom = vec![0; header.prg_rom_size as usize];
-        input_stream.read_exact(&mut prg_rom)?;
-        let prg_rom = Cow::Owned(prg_rom);
+        let prg_rom = Cow::Owned(read_section(input_stream, header.prg_rom_size)?);
 
         // Read the CHR ROM
         let chr_rom = if header.chr_rom_size > 0 {
-            let mut chr_rom = vec![0; header.chr_rom_size as usize];
-            input_stream.read_exact(&mut chr_rom)?;
-
-            Some(Cow::Owned(chr_rom))
+            Some(Cow::Owned(read_section(input_stream, header.chr_rom_size)?))
         } else {
             None
         };
@@ -209,4 +567,108 @@ impl<'a> Ines<'a> {
             chr_rom,
         })
     }
+
+    /// Serialize the ROM, with the sizes and the trainer flag of the header taken from the contents
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut header = self.header.clone();
+        header.prg_rom_size = self.prg_rom.len();
+        header.chr_rom_size = self.chr_rom.as_ref().map_or(0, |chr_rom| chr_rom.len());
+        header.has_trainer = self.trainer.is_some();
+
+        let mut data = header.to_bytes().to_vec();
+        if let Some(trainer) = &self.trainer {
+            data.extend_from_slice(trainer);
+        }
+        data.extend_from_slice(&self.prg_rom);
+        if let Some(chr_rom) = &self.chr_rom {
+            data.extend_from_slice(chr_rom);
+        }
+
+        data
+    }
+
+    /// Read the interrupt vectors from the bank mapped to `$E000`-`$FFFF` at power-on
+    ///
+    /// For nearly all mappers that's the end of the PRG ROM. Targets outside of the fixed bank depend on the
+    /// banks switched in at runtime, so they don't have an offset.
+    pub fn vectors(&self) -> Result<Vectors> {
+        let prg_rom = &self.prg_rom;
+        if prg_rom.len() < VECTORS_SIZE {
+            return Err(Error::UnexpectedEof);
+        }
+
+        // ROMs smaller than the window are mirrored across it
+        let window = fixed_window_size(self.header.mapper_number).min(prg_rom.len());
+        let fixed_bank = prg_rom.len() - window..prg_rom.len();
+        let window_start = if window < fixed_window_size(self.header.mapper_number) {
+            ADDRESS_SPACE_END - PRG_ROM_WINDOW_SIZE.max(window)
+        } else {
+            ADDRESS_SPACE_END - window
+        };
+
+        let vector = |index: usize| {
+            let position = prg_rom.len() - VECTORS_SIZE + index * 2;
+            let address = u16::from_le_bytes([prg_rom[position], prg_rom[position + 1]]);
+            let offset = (usize::from(address) >= window_start)
+                .then(|| fixed_bank.start + usize::from(address) % window);
+
+            Vector { address, offset }
+        };
+
+        Ok(Vectors {
+            nmi: vector(0),
+            reset: vector(1),
+            irq: vector(2),
+            fixed_bank: fixed_bank.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nes2_header(prg_low: u8, chr_low: u8, sizes_high: u8) -> [u8; HEADER_SIZE] {
+        let mut header = [0; HEADER_SIZE];
+        header[..4].copy_from_slice(&MAGIC_BYTES);
+        header[4] = prg_low;
+        header[5] = chr_low;
+        header[7] = 0x08;
+        header[9] = sizes_high;
+        header
+    }
+
+    #[test]
+    fn reject_overflowing_rom_size() {
+        // 7 * 2^63 bytes of PRG ROM
+        let header = nes2_header(63 << 2 | 3, 0, 0x0F);
+
+        assert!(matches!(
+            Header::from_bytes(&header),
+            Err(Error::RomSizeOverflow)
+        ));
+        assert!(matches!(
+            Ines::from_bytes(&header),
+            Err(Error::RomSizeOverflow)
+        ));
+    }
+
+    #[test]
+    fn reject_truncated_sections() {
+        // 2^63 bytes of PRG ROM and 3 * 2^62 bytes of CHR ROM
+        let mut data = nes2_header(63 << 2, 62 << 2 | 1, 0xFF).to_vec();
+        data.extend_from_slice(&[0; 64]);
+
+        assert!(matches!(Ines::from_bytes(&data), Err(Error::UnexpectedEof)));
+        assert!(matches!(
+            section(&data, 16, usize::MAX),
+            Err(Error::UnexpectedEof)
+        ));
+        #[cfg(feature = "std")]
+        assert!(matches!(
+            Ines::from_reader(&mut data.as_slice()),
+            Err(Error::UnexpectedEof)
+        ));
+    }
 }
@@ -0,0 +1,178 @@
+//!
+//! Region conversion of headers
+//!
+//! [Timing documentation](https://www.nesdev.org/wiki/Cycle_reference_chart)
+//!
+
+use {
+    crate::{parse_header, Result, HEADER_SIZE},
+    core::convert::TryInto,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// Master clocks of the consoles in Hz
+const NTSC_MASTER_CLOCK: f64 = 236_250_000.0 / 11.0;
+const PAL_MASTER_CLOCK: f64 = 26_601_712.5;
+
+/// CPU/PPU timing of the console a game is made for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Timing {
+    /// RP2C02 (North America, Japan, South Korea, Taiwan)
+    Ntsc,
+    /// RP2C07 (Western Europe, Australia)
+    Pal,
+    /// Games adapting to either console; the figures are the ones of NTSC
+    MultiRegion,
+    /// UMC 6527P famiclones (Eastern Europe, Russia, mainland China, India, Africa)
+    Dendy,
+}
+
+impl Timing {
+    /// Timing from the value of the NES 2.0 timing field
+    #[must_use]
+    pub fn from_bits(bits: u8) -> Self {
+        match bits & 0x03 {
+            0 => Self::Ntsc,
+            1 => Self::Pal,
+            2 => Self::MultiRegion,
+            _ => Self::Dendy,
+        }
+    }
+
+    /// Value of the NES 2.0 timing field
+    #[must_use]
+    pub fn bits(self) -> u8 {
+        match self {
+            Self::Ntsc => 0,
+            Self::Pal => 1,
+            Self::MultiRegion => 2,
+            Self::Dendy => 3,
+        }
+    }
+
+    // Master clock with the dividers of the CPU and the PPU
+    fn clocks(self) -> (f64, f64, f64) {
+        match self {
+            Self::Ntsc | Self::MultiRegion => (NTSC_MASTER_CLOCK, 12.0, 4.0),
+            Self::Pal => (PAL_MASTER_CLOCK, 16.0, 5.0),
+            Self::Dendy => (PAL_MASTER_CLOCK, 15.0, 5.0),
+        }
+    }
+
+    /// Clock rate of the CPU in Hz, which the APU and with it the pitch of the music is derived from
+    #[must_use]
+    pub fn cpu_clock(self) -> f64 {
+        let (master_clock, cpu_divider, _) = self.clocks();
+        master_clock / cpu_divider
+    }
+
+    /// Amount of PPU dots per CPU cycle
+    #[must_use]
+    pub fn ppu_dots_per_cpu_cycle(self) -> f64 {
+        let (_, cpu_divider, ppu_divider) = self.clocks();
+        cpu_divider / ppu_divider
+    }
+
+    /// Scanlines per frame, including the vertical blanking interval
+    #[must_use]
+    pub fn scanlines(self) -> u16 {
+        match self {
+            Self::Ntsc | Self::MultiRegion => 262,
+            Self::Pal | Self::Dendy => 312,
+        }
+    }
+
+    /// Scanlines of the vertical blanking interval, the time games have to update the PPU after the NMI
+    ///
+    /// The Dendy has as many lines as PAL, but idles for most of them before raising the NMI
+    #[must_use]
+    pub fn vblank_scanlines(self) -> u16 {
+        match self {
+            Self::Ntsc | Self::MultiRegion | Self::Dendy => 20,
+            Self::Pal => 70,
+        }
+    }
+
+    /// Average amount of CPU cycles per frame
+    #[must_use]
+    pub fn cpu_cycles_per_frame(self) -> f64 {
+        // NTSC skips a dot on every other frame
+        let dots = f64::from(self.scanlines()) * 341.0
+            - match self {
+                Self::Ntsc | Self::MultiRegion => 0.5,
+                Self::Pal | Self::Dendy => 0.0,
+            };
+
+        dots / self.ppu_dots_per_cpu_cycle()
+    }
+
+    /// Frames per second
+    #[must_use]
+    pub fn frame_rate(self) -> f64 {
+        self.cpu_clock() / self.cpu_cycles_per_frame()
+    }
+}
+
+/// Header converted to another region, with the effects on the game
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RegionConversion {
+    pub header: [u8; HEADER_SIZE],
+    pub from: Timing,
+    pub to: Timing,
+}
+
+impl RegionConversion {
+    /// Factor the pitch of the music changes by, since the APU runs off the CPU clock
+    #[must_use]
+    pub fn cpu_clock_ratio(&self) -> f64 {
+        self.to.cpu_clock() / self.from.cpu_clock()
+    }
+
+    /// Factor the speed of the game changes by, for games advancing their logic once per frame
+    #[must_use]
+    pub fn frame_rate_ratio(&self) -> f64 {
+        self.to.frame_rate() / self.from.frame_rate()
+    }
+
+    /// Factor the CPU time per frame changes by; games running out of time lag, and raster effects timed in cycles break
+    #[must_use]
+    pub fn cpu_cycles_per_frame_ratio(&self) -> f64 {
+        self.to.cpu_cycles_per_frame() / self.from.cpu_cycles_per_frame()
+    }
+
+    /// Whether the vertical blanking interval gets shorter, which breaks games relying on the time for PPU updates
+    #[must_use]
+    pub fn shortens_vblank(&self) -> bool {
+        self.to.vblank_scanlines() < self.from.vblank_scanlines()
+    }
+}
+
+/// Convert the header at the start of the data to the timing
+///
+/// NES 2.0 headers get their timing field set. INES 1 headers get both the TV system flag of byte 9
+/// and the unofficial TV system bits of byte 10 set, which only know NTSC, PAL and dual-compatible games.
+pub fn convert_region(data: &[u8], timing: Timing) -> Result<RegionConversion> {
+    let header = parse_header(data)?;
+    let mut converted: [u8; HEADER_SIZE] = data[..HEADER_SIZE].try_into()?;
+
+    if header.is_nes2 {
+        converted[12] = (converted[12] & !0x03) | timing.bits();
+    } else {
+        let (pal_flag, tv_system) = match timing {
+            Timing::Ntsc => (0, 0),
+            Timing::Pal | Timing::Dendy => (1, 2),
+            Timing::MultiRegion => (0, 1),
+        };
+        converted[9] = (converted[9] & !0x01) | pal_flag;
+        converted[10] = (converted[10] & !0x03) | tv_system;
+    }
+
+    Ok(RegionConversion {
+        header: converted,
+        from: header.timing,
+        to: timing,
+    })
+}
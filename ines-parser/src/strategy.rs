@@ -0,0 +1,65 @@
+//!
+//! `proptest` strategies generating valid headers, ROM files and CHR data
+//!
+//! Every generated header is accepted by the parser, and every generated ROM contains exactly the sections its header describes.
+//! By default the ROMs have up to 512 KiB of PRG ROM and 256 KiB of CHR ROM, the `*_with_banks` variants change the limits.
+//!
+
+use {
+    crate::{generate, Header, HEADER_SIZE},
+    alloc::vec::Vec,
+    core::ops::RangeInclusive,
+    proptest::{
+        collection::{self, SizeRange},
+        prelude::*,
+    },
+};
+
+fn header_bytes(
+    prg_banks: RangeInclusive<u8>,
+    chr_banks: RangeInclusive<u8>,
+) -> impl Strategy<Value = [u8; HEADER_SIZE]> {
+    (
+        any::<bool>(),
+        prg_banks,
+        chr_banks,
+        any::<generate::Choices>(),
+    )
+        .prop_map(|(is_nes2, prg_banks, chr_banks, choices)| {
+            generate::header_bytes(is_nes2, prg_banks, chr_banks, choices)
+        })
+}
+
+/// INES 1 and NES 2.0 headers with up to 32 PRG ROM and CHR ROM banks
+pub fn header() -> impl Strategy<Value = Header> {
+    header_with_banks(1..=generate::MAX_PRG_BANKS, 0..=generate::MAX_CHR_BANKS)
+}
+
+/// Headers with an amount of 16 KiB PRG ROM banks and 8 KiB CHR ROM banks in the ranges
+///
+/// Zero CHR ROM banks mean the game uses CHR RAM, zero PRG ROM banks are raised to one
+pub fn header_with_banks(
+    prg_banks: RangeInclusive<u8>,
+    chr_banks: RangeInclusive<u8>,
+) -> impl Strategy<Value = Header> {
+    header_bytes(prg_banks, chr_banks).prop_map(|header_bytes| generate::header(&header_bytes))
+}
+
+/// ROM files with the headers of [`header`], followed by the trainer, PRG ROM and CHR ROM with random contents
+pub fn rom() -> impl Strategy<Value = Vec<u8>> {
+    rom_with_banks(1..=generate::MAX_PRG_BANKS, 0..=generate::MAX_CHR_BANKS)
+}
+
+/// ROM files with the headers of [`header_with_banks`]
+pub fn rom_with_banks(
+    prg_banks: RangeInclusive<u8>,
+    chr_banks: RangeInclusive<u8>,
+) -> impl Strategy<Value = Vec<u8>> {
+    (header_bytes(prg_banks, chr_banks), any::<u64>())
+        .prop_map(|(header_bytes, seed)| generate::rom(&header_bytes, seed))
+}
+
+/// CHR data consisting of an amount of 16 byte tiles in the range, which shrinks tile by tile
+pub fn chr_data(tiles: impl Into<SizeRange>) -> impl Strategy<Value = Vec<u8>> {
+    collection::vec(any::<[u8; generate::SPRITE_SIZE]>(), tiles).prop_map(|tiles| tiles.concat())
+}
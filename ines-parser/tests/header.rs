@@ -0,0 +1,112 @@
+use ines_parser::{Header, Ines, Timing, VramLayout};
+
+fn nes2_header(prg_low: u8, chr_low: u8, sizes_high: u8) -> [u8; 16] {
+    let mut header = [0; 16];
+    header[..4].copy_from_slice(b"NES\x1A");
+    header[4] = prg_low;
+    header[5] = chr_low;
+    header[7] = 0x08;
+    header[9] = sizes_high;
+    header
+}
+
+// Parse a header followed by as much ROM data as it announces
+fn parse_header(header: &[u8], rom_size: usize) -> Header {
+    let mut rom = header.to_vec();
+    rom.resize(header.len() + rom_size, 0);
+    Ines::from_bytes(&rom).unwrap().header
+}
+
+#[test]
+fn nes2_round_trip() {
+    let header = [
+        0x4E, 0x45, 0x53, 0x1A, 0x20, 0x20, 0x43, 0x08, 0x10, 0x00, 0x70, 0x07, 0x01, 0x00, 0x00,
+        0x01,
+    ];
+    let parsed = parse_header(&header, 768 * 1024);
+
+    assert!(parsed.is_nes2);
+    assert_eq!(parsed.prg_rom_size, 512 * 1024);
+    assert_eq!(parsed.chr_rom_size, 256 * 1024);
+    assert_eq!(parsed.vram_layout, VramLayout::VerticalMirroring);
+    assert!(parsed.has_persistent_memory);
+    assert_eq!(parsed.mapper_number, 4);
+    assert_eq!(parsed.submapper, 1);
+    assert_eq!(parsed.prg_ram_size, 0);
+    assert_eq!(parsed.prg_nvram_size, 8192);
+    assert_eq!(parsed.chr_ram_size, 8192);
+    assert_eq!(parsed.chr_nvram_size, 0);
+    assert_eq!(parsed.timing, Timing::Pal);
+    assert_eq!(parsed.default_expansion_device, 1);
+    assert_eq!(parsed.to_bytes(), header);
+}
+
+#[test]
+fn nes2_rom_size_round_trip() {
+    let cases = [
+        // Chunk counts extended by the high nibble
+        (0x01, 0xFF, 0x10, 0x4000, 0x1FF * 0x2000),
+        // Exponent-multiplier notation for sizes which aren't a multiple of the chunk size
+        (10 << 2 | 1, 5 << 2 | 3, 0xFF, 3 << 10, 7 << 5),
+        (2 << 2 | 2, 1 << 2 | 1, 0xFF, 5 << 2, 3 << 1),
+    ];
+
+    for (prg_low, chr_low, sizes_high, prg_rom_size, chr_rom_size) in cases {
+        let header = nes2_header(prg_low, chr_low, sizes_high);
+        let parsed = parse_header(&header, prg_rom_size + chr_rom_size);
+
+        assert_eq!(parsed.prg_rom_size, prg_rom_size);
+        assert_eq!(parsed.chr_rom_size, chr_rom_size);
+        assert_eq!(parsed.to_bytes(), header);
+    }
+}
+
+#[test]
+fn nes2_extended_fields_round_trip() {
+    // Vs. System game on mapper 260, with a Vs. PPU type, one miscellaneous ROM and the zapper
+    let header = [
+        0x4E, 0x45, 0x53, 0x1A, 0x02, 0x01, 0x41, 0x09, 0x21, 0x00, 0x07, 0x00, 0x00, 0x13, 0x01,
+        0x08,
+    ];
+    let parsed = parse_header(&header, 0xA000);
+
+    assert_eq!(parsed.mapper_number, 4);
+    assert_eq!(parsed.mapper_high_bits, 1);
+    assert_eq!(parsed.mapper(), 260);
+    assert_eq!(parsed.submapper, 2);
+    assert_eq!(parsed.console_type, 1);
+    assert_eq!(parsed.console_type_details, 0x13);
+    assert_eq!(parsed.misc_roms, 1);
+    assert_eq!(parsed.default_expansion_device, 8);
+    assert_eq!(parsed.to_bytes(), header);
+}
+
+#[test]
+fn ines_round_trip() {
+    let header = Header::new(0x8000, 0x2000, 1, VramLayout::HorizontalMirroring).to_bytes();
+    assert_eq!(parse_header(&header, 0xA000).to_bytes(), header);
+
+    // Super Mario Bros., with the PRG RAM size left at zero
+    let header = [
+        0x4E, 0x45, 0x53, 0x1A, 0x02, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00,
+    ];
+    let parsed = parse_header(&header, 0xA000);
+    assert_eq!(parsed.prg_ram_size, 0x2000);
+    assert_eq!(parsed.to_bytes(), header);
+
+    // PlayChoice-10 game with an explicit PRG RAM size of one and of two chunks
+    let mut header = [
+        0x4E, 0x45, 0x53, 0x1A, 0x08, 0x10, 0x42, 0x12, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00,
+    ];
+    let parsed = parse_header(&header, 0x100000);
+    assert_eq!(parsed.mapper_number, 0x14);
+    assert_eq!(parsed.console_type, 2);
+    assert_eq!(parsed.to_bytes(), header);
+
+    header[8] = 0x02;
+    let parsed = parse_header(&header, 0x100000);
+    assert_eq!(parsed.prg_nvram_size, 0x4000);
+    assert_eq!(parsed.to_bytes(), header);
+}
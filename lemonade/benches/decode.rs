@@ -0,0 +1,74 @@
+use {
+    criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput},
+    lemonade::{ColourPalette, Lemonade, PixelFormat, SPRITE_SIZE, SPRITE_WIDTH_HEIGHT},
+};
+
+// 8 KiB of CHR data, the size of one CHR bank of NROM games
+const SPRITE_COUNT: usize = 512;
+const SPRITES_PER_ROW: usize = 16;
+
+fn chr_data() -> Vec<u8> {
+    // Pseudo-random bytes, so every row has different bit patterns
+    let mut state = 0x2A03_u32;
+    (0..SPRITE_COUNT * SPRITE_SIZE)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect()
+}
+
+fn indices(c: &mut Criterion) {
+    let chr_data = chr_data();
+
+    let mut group = c.benchmark_group("indices");
+    group.throughput(Throughput::Elements(SPRITE_COUNT as u64));
+    group.bench_function("to_indices", |b| {
+        b.iter(|| {
+            Lemonade::new(black_box(&chr_data))
+                .map(|sprite| sprite.to_indices()[7][7])
+                .fold(0, u8::wrapping_add)
+        })
+    });
+    group.finish();
+}
+
+fn decode_all(c: &mut Criterion) {
+    let chr_data = chr_data();
+
+    let mut group = c.benchmark_group("decode_all_into");
+    group.throughput(Throughput::Elements(SPRITE_COUNT as u64));
+    for (name, pixel_format) in [
+        ("indexed", PixelFormat::Indexed),
+        ("rgb8", PixelFormat::Rgb8),
+        ("rgba8", PixelFormat::Rgba8),
+        ("bgra8", PixelFormat::Bgra8),
+    ] {
+        let mut buffer = vec![
+            0;
+            SPRITE_COUNT
+                * SPRITE_WIDTH_HEIGHT
+                * SPRITE_WIDTH_HEIGHT
+                * pixel_format.bytes_per_pixel()
+        ];
+
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                Lemonade::new(black_box(&chr_data))
+                    .decode_all_into(
+                        &mut buffer,
+                        ColourPalette::CLASSIC_MARIO,
+                        pixel_format,
+                        SPRITES_PER_ROW,
+                    )
+                    .unwrap();
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, indices, decode_all);
+criterion_main!(benches);
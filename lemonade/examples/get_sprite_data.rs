@@ -5,8 +5,7 @@ fn main() {
     let mut file = File::open(rom_path).unwrap();
 
     let ines = ines_parser::Ines::from_reader(&mut file).unwrap();
-    let chr_rom = ines.chr_rom.unwrap();
-    let sprites = lemonade::Lemonade::new(&chr_rom);
+    let sprites = lemonade::from_ines(&ines).unwrap();
 
     println!("Sprite count: {}", sprites.num_sprites());
 }
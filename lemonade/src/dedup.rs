@@ -0,0 +1,128 @@
+use {
+    crate::{Lemonade, PlaneLayout, SPRITE_SIZE, SPRITE_WIDTH_HEIGHT},
+    alloc::{collections::BTreeMap, vec::Vec},
+};
+
+/// How a tile has to be transformed to match another tile
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Transform {
+    /// The tiles are identical
+    Identity,
+    /// The tile is mirrored horizontally
+    FlipHorizontal,
+    /// The tile is mirrored vertically
+    FlipVertical,
+    /// The tile is mirrored horizontally and vertically (rotated by 180 degrees)
+    FlipBoth,
+}
+
+impl Transform {
+    /// Whether the horizontal flip bit has to be set in the OAM attributes
+    #[must_use]
+    pub const fn flip_horizontal(self) -> bool {
+        matches!(self, Self::FlipHorizontal | Self::FlipBoth)
+    }
+
+    /// Whether the vertical flip bit has to be set in the OAM attributes
+    #[must_use]
+    pub const fn flip_vertical(self) -> bool {
+        matches!(self, Self::FlipVertical | Self::FlipBoth)
+    }
+
+    /// Apply the transform to raw sprite data
+    #[must_use]
+    pub fn apply(self, raw_sprite_data: &[u8; SPRITE_SIZE]) -> [u8; SPRITE_SIZE] {
+        self.apply_with(raw_sprite_data, PlaneLayout::Planar)
+    }
+
+    /// Apply the transform to raw sprite data stored in the given plane layout
+    #[must_use]
+    pub fn apply_with(
+        self,
+        raw_sprite_data: &[u8; SPRITE_SIZE],
+        plane_layout: PlaneLayout,
+    ) -> [u8; SPRITE_SIZE] {
+        let mut transformed = *raw_sprite_data;
+
+        if self.flip_horizontal() {
+            // The pixels are stored as bits, so mirroring a row means reversing the bits
+            for byte in &mut transformed {
+                *byte = byte.reverse_bits();
+            }
+        }
+
+        if self.flip_vertical() {
+            match plane_layout {
+                // Both bit planes have to be mirrored separately
+                PlaneLayout::Planar => {
+                    for plane in transformed.chunks_exact_mut(SPRITE_WIDTH_HEIGHT) {
+                        plane.reverse();
+                    }
+                }
+                // Every row consists of two bytes, so the rows have to be swapped as a whole
+                PlaneLayout::Interleaved => {
+                    for row in 0..SPRITE_WIDTH_HEIGHT / 2 {
+                        let mirrored_row = SPRITE_WIDTH_HEIGHT - 1 - row;
+                        transformed.swap(row * 2, mirrored_row * 2);
+                        transformed.swap(row * 2 + 1, mirrored_row * 2 + 1);
+                    }
+                }
+            }
+        }
+
+        transformed
+    }
+}
+
+/// A tile which can be replaced by another tile
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Duplicate {
+    /// Index of the duplicated tile
+    pub tile: usize,
+    /// Index of the first tile it's a duplicate of
+    pub original: usize,
+    /// Transform which turns the original tile into the duplicate
+    pub transform: Transform,
+}
+
+/// Find all tiles which are identical to, or flipped versions of, an earlier tile
+///
+/// Exact duplicates are always preferred over flipped duplicates
+#[must_use]
+pub fn find_duplicates(sprites: Lemonade<'_>) -> Vec<Duplicate> {
+    let mut originals: BTreeMap<[u8; SPRITE_SIZE], usize> = BTreeMap::new();
+    let mut duplicates = Vec::new();
+
+    for (tile, sprite) in sprites.enumerate() {
+        let mut raw_sprite_data = [0; SPRITE_SIZE];
+        raw_sprite_data.copy_from_slice(sprite.buffer());
+
+        // Flipping is its own inverse, so the transform turning this tile into the original
+        // also turns the original into this tile
+        let duplicate = [
+            Transform::Identity,
+            Transform::FlipHorizontal,
+            Transform::FlipVertical,
+            Transform::FlipBoth,
+        ]
+        .iter()
+        .find_map(|transform| {
+            originals
+                .get(&transform.apply_with(&raw_sprite_data, sprite.plane_layout()))
+                .map(|original| Duplicate {
+                    tile,
+                    original: *original,
+                    transform: *transform,
+                })
+        });
+
+        match duplicate {
+            Some(duplicate) => duplicates.push(duplicate),
+            None => {
+                originals.insert(raw_sprite_data, tile);
+            }
+        }
+    }
+
+    duplicates
+}
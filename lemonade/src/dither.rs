@@ -0,0 +1,117 @@
+use {
+    crate::{quantize::nearest_palette_index, Colour, ColourPalette},
+    alloc::vec::Vec,
+};
+
+// 4x4 Bayer threshold matrix
+const BAYER_MATRIX: [[i32; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+// Maximum distance a channel gets moved by the ordered dithering (in both directions)
+const ORDERED_SPREAD: i32 = 32;
+
+/// How the colours of an image get reduced to the four colours of a palette
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Dithering {
+    /// Every pixel gets the closest palette colour
+    #[default]
+    None,
+    /// Ordered dithering with a 4x4 Bayer matrix; produces regular patterns which compress well
+    Ordered,
+    /// Floyd-Steinberg error diffusion; produces the most accurate results for photographic images
+    FloydSteinberg,
+}
+
+/// Move every channel of the colour by the given amount, clamping at the limits
+fn offset_colour(colour: Colour, [r, g, b]: [i32; 3]) -> Colour {
+    // The values are clamped into the range of a byte, so the casts are lossless
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let offset = |value: u8, offset: i32| (i32::from(value) + offset).clamp(0, 255) as u8;
+
+    Colour::new(
+        offset(colour.r, r),
+        offset(colour.g, g),
+        offset(colour.b, b),
+    )
+}
+
+/// Difference of every channel between two colours
+fn colour_error(wanted: Colour, actual: Colour) -> [i32; 3] {
+    [
+        i32::from(wanted.r) - i32::from(actual.r),
+        i32::from(wanted.g) - i32::from(actual.g),
+        i32::from(wanted.b) - i32::from(actual.b),
+    ]
+}
+
+/// Map every pixel of the image (row by row) to a palette index (0 to 3)
+///
+/// The palette used for a pixel is looked up via its coordinates
+pub(crate) fn map_pixels<'a, F>(
+    pixels: &[Colour],
+    width: usize,
+    dithering: Dithering,
+    palette_at: F,
+) -> Vec<u8>
+where
+    F: Fn(usize, usize) -> &'a ColourPalette,
+{
+    match dithering {
+        Dithering::None => pixels
+            .iter()
+            .enumerate()
+            .map(|(index, colour)| {
+                nearest_palette_index(*colour, palette_at(index % width, index / width))
+            })
+            .collect(),
+        Dithering::Ordered => pixels
+            .iter()
+            .enumerate()
+            .map(|(index, colour)| {
+                let (x, y) = (index % width, index / width);
+
+                // Map the threshold from 0..16 to -ORDERED_SPREAD..ORDERED_SPREAD
+                let threshold = (BAYER_MATRIX[y % 4][x % 4] * 2 + 1 - 16) * ORDERED_SPREAD / 16;
+                let colour = offset_colour(*colour, [threshold; 3]);
+
+                nearest_palette_index(colour, palette_at(x, y))
+            })
+            .collect(),
+        Dithering::FloydSteinberg => floyd_steinberg(pixels, width, palette_at),
+    }
+}
+
+fn floyd_steinberg<'a, F>(pixels: &[Colour], width: usize, palette_at: F) -> Vec<u8>
+where
+    F: Fn(usize, usize) -> &'a ColourPalette,
+{
+    let mut indices = Vec::with_capacity(pixels.len());
+
+    // Accumulated errors of the current and the next row (with one pixel of padding on each side)
+    let mut current_errors = alloc::vec![[0; 3]; width + 2];
+    let mut next_errors = alloc::vec![[0; 3]; width + 2];
+
+    for (y, row) in pixels.chunks(width.max(1)).enumerate() {
+        for (x, colour) in row.iter().enumerate() {
+            let error = current_errors[x + 1].map(|value: i32| value / 16);
+            let wanted = offset_colour(*colour, error);
+
+            let colour_palette = palette_at(x, y);
+            let index = nearest_palette_index(wanted, colour_palette);
+            indices.push(index);
+
+            // Distribute the error onto the neighbouring pixels
+            let error = colour_error(wanted, colour_palette.colour(index));
+            for (channel, value) in error.iter().enumerate() {
+                current_errors[x + 2][channel] += value * 7;
+                next_errors[x][channel] += value * 3;
+                next_errors[x + 1][channel] += value * 5;
+                next_errors[x + 2][channel] += value;
+            }
+        }
+
+        core::mem::swap(&mut current_errors, &mut next_errors);
+        next_errors.fill([0; 3]);
+    }
+
+    indices
+}
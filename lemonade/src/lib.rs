@@ -1,15 +1,119 @@
 #![no_std]
 #![warn(clippy::all, clippy::pedantic)]
 
-use core::slice::ChunksExact;
+#[cfg(feature = "alloc")]
+extern crate alloc;
 
-// One sprite has the size of 16 bytes
-const SPRITE_SIZE: usize = 16;
-const SPRITE_WIDTH_HEIGHT: usize = 8;
+use core::{fmt, ops::Range};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "ines")]
+use ines_parser::Ines;
+
+/// Size of one sprite in bytes
+pub const SPRITE_SIZE: usize = 16;
+/// Width and height of one sprite in pixels
+pub const SPRITE_WIDTH_HEIGHT: usize = 8;
+
+#[cfg(feature = "alloc")]
+mod dedup;
+#[cfg(feature = "alloc")]
+mod dither;
+mod master_palette;
+#[cfg(feature = "alloc")]
+mod metasprite;
+mod mirroring;
+#[cfg(feature = "alloc")]
+mod palette_ram;
+mod pixel;
+#[cfg(feature = "alloc")]
+mod ppu_dump;
+#[cfg(feature = "alloc")]
+mod quantize;
+mod screen;
+#[cfg(feature = "alloc")]
+mod sheet;
+#[cfg(feature = "alloc")]
+mod usage;
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use {
+    master_palette::{nearest_nes_colour, nes_colour, NES_PALETTE},
+    mirroring::Mirroring,
+    pixel::PixelFormat,
+    screen::{
+        Screen, NAMETABLE_HEIGHT, NAMETABLE_SIZE, NAMETABLE_WIDTH, SCREEN_HEIGHT, SCREEN_WIDTH,
+        TILE_DATA_SIZE,
+    },
+};
+
+#[cfg(feature = "alloc")]
+pub use {
+    dedup::{find_duplicates, Duplicate, Transform},
+    dither::Dithering,
+    metasprite::{Metasprite, MetaspriteTile},
+    palette_ram::{PaletteRam, Swatch, SwatchLayout, PALETTE_RAM_SIZE},
+    ppu_dump::{ExtendedAttributes, PpuDump},
+    quantize::{quantize, quantize_with, QuantizedImage, REGION_SIZE},
+    sheet::Sheet,
+    usage::{tile_usage, TileUsage},
+};
+
+#[derive(Debug)]
+pub enum Error {
+    /// The ROM has no CHR ROM (the game uses CHR RAM instead)
+    NoChrRom,
+
+    /// The output buffer is too small to hold the decoded pixels
+    BufferTooSmall { required: usize, actual: usize },
+
+    /// The amount of pixels doesn't match the dimensions of the image
+    ImageSizeMismatch { expected: usize, actual: usize },
+
+    /// The memory dump has neither the size of one, two nor four nametables
+    InvalidDumpSize(usize),
+
+    /// The VRAM is smaller than the nametables of the mirroring need
+    VramTooSmall { required: usize, actual: usize },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoChrRom => f.write_str("The ROM doesn't contain a CHR ROM"),
+            Self::BufferTooSmall { required, actual } => write!(
+                f,
+                "Output buffer too small; expected at least {required} bytes, got {actual}"
+            ),
+            Self::ImageSizeMismatch { expected, actual } => write!(
+                f,
+                "Image size mismatch; expected {expected} pixels, got {actual}"
+            ),
+            Self::InvalidDumpSize(size) => {
+                write!(f, "Invalid size of the memory dump ({size} bytes)")
+            }
+            Self::VramTooSmall { required, actual } => write!(
+                f,
+                "VRAM too small for the mirroring; expected at least {required} bytes, got {actual}"
+            ),
+        }
+    }
+}
+
+/// Tiles which usually contain the printable ASCII characters in games which store their font in ASCII order
+pub const ASCII_FONT_TILES: Range<usize> = 0x20..0x80;
+
+/// 2-bit palette indices of every pixel of a sprite, row by row
+pub type IndexedSprite = [[u8; SPRITE_WIDTH_HEIGHT]; SPRITE_WIDTH_HEIGHT];
 
 pub type RgbSprite = [[Colour; SPRITE_WIDTH_HEIGHT]; SPRITE_WIDTH_HEIGHT];
 
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ColourPalette {
     background: Colour,
     colours: [Colour; 3],
@@ -32,9 +136,22 @@ impl ColourPalette {
             colours,
         }
     }
+
+    /// Look up the colour for a 2-bit palette index
+    ///
+    /// Index 0 is the background colour, indices 1 to 3 are the palette colours.
+    /// Only the lower two bits of the index are taken into account.
+    #[must_use]
+    pub const fn colour(&self, index: u8) -> Colour {
+        match index & 0b11 {
+            0 => self.background,
+            index => self.colours[index as usize - 1],
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Colour {
     r: u8,
     g: u8,
@@ -51,6 +168,21 @@ impl Colour {
     pub const fn raw_colour(self) -> [u8; 3] {
         [self.r, self.g, self.b]
     }
+
+    /// Perceptually weighted squared distance between two colours
+    ///
+    /// The human eye is the most sensitive to green and the least sensitive to blue, so the channels are weighted accordingly
+    #[must_use]
+    pub fn distance(self, other: Colour) -> u32 {
+        let channel_distance = |a: u8, b: u8| {
+            let difference = u32::from(a.abs_diff(b));
+            difference * difference
+        };
+
+        3 * channel_distance(self.r, other.r)
+            + 4 * channel_distance(self.g, other.g)
+            + 2 * channel_distance(self.b, other.b)
+    }
 }
 
 impl From<[u8; 3]> for Colour {
@@ -59,18 +191,55 @@ impl From<[u8; 3]> for Colour {
     }
 }
 
-impl Into<[u8; 3]> for Colour {
-    fn into(self) -> [u8; 3] {
-        [self.r, self.g, self.b]
+impl From<Colour> for [u8; 3] {
+    fn from(colour: Colour) -> Self {
+        colour.raw_colour()
+    }
+}
+
+/// How the two bit planes of a 2bpp tile are arranged
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PlaneLayout {
+    /// The 8 bytes of the low bit plane are followed by the 8 bytes of the high bit plane (NES)
+    #[default]
+    Planar,
+    /// The low and high bytes of each row alternate (Game Boy, SNES 2bpp)
+    Interleaved,
+}
+
+impl PlaneLayout {
+    /// Byte offsets of the low and high plane bytes of a row
+    const fn row_offsets(self, row: usize) -> (usize, usize) {
+        match self {
+            Self::Planar => (row, row + SPRITE_WIDTH_HEIGHT),
+            Self::Interleaved => (row * 2, row * 2 + 1),
+        }
     }
 }
 
-fn bit_at(num: u8, idx: u8) -> bool {
-    (num >> idx) & 1 == 1
+// Every byte of a bit plane spread out to one bit per byte, so both planes of a row decode with two lookups
+const PLANE_BITS: [u64; 256] = spread_bits();
+
+const fn spread_bits() -> [u64; 256] {
+    let mut table = [0; 256];
+    let mut byte = 0;
+    while byte < table.len() {
+        let mut x = 0;
+        while x < SPRITE_WIDTH_HEIGHT {
+            // The leftmost pixel is stored in the most significant bit and ends up in the lowest byte
+            let bit = (byte >> (SPRITE_WIDTH_HEIGHT - 1 - x)) & 1;
+            table[byte] |= (bit as u64) << (x * 8);
+            x += 1;
+        }
+        byte += 1;
+    }
+
+    table
 }
 
 pub struct Sprite<'a> {
     raw_sprite_data: &'a [u8],
+    plane_layout: PlaneLayout,
 }
 
 impl<'a> Sprite<'a> {
@@ -79,70 +248,222 @@ impl<'a> Sprite<'a> {
         self.raw_sprite_data
     }
 
+    /// The plane layout the sprite is stored in
+    #[must_use]
+    pub fn plane_layout(&self) -> PlaneLayout {
+        self.plane_layout
+    }
+
+    /// Decode the sprite into its 2-bit palette indices (0 being the background)
+    #[must_use]
+    pub fn to_indices(&self) -> IndexedSprite {
+        let mut indices = [[0; SPRITE_WIDTH_HEIGHT]; SPRITE_WIDTH_HEIGHT];
+        for (y, row) in indices.iter_mut().enumerate() {
+            let (low_offset, high_offset) = self.plane_layout.row_offsets(y);
+            let (low_byte, high_byte) = (
+                self.raw_sprite_data[low_offset],
+                self.raw_sprite_data[high_offset],
+            );
+
+            let row_indices =
+                PLANE_BITS[usize::from(low_byte)] | (PLANE_BITS[usize::from(high_byte)] << 1);
+            *row = row_indices.to_le_bytes();
+        }
+
+        indices
+    }
+
     #[must_use]
     pub fn to_rgb(&self, colour_palette: ColourPalette) -> RgbSprite {
-        let mut byte_chunks = self.raw_sprite_data.chunks_exact(SPRITE_WIDTH_HEIGHT);
+        let indices = self.to_indices();
 
-        let mut rgb_iterator = byte_chunks
-            .next()
-            .unwrap()
-            .iter()
-            .zip(byte_chunks.next().unwrap())
-            .map(|(first_byte, second_byte)| {
-                let mut colour_data = [Colour::default(); SPRITE_WIDTH_HEIGHT];
-
-                // Won't be truncated because 8 fits easily into a byte
-                #[allow(clippy::cast_possible_truncation)]
-                for i in 0..SPRITE_WIDTH_HEIGHT as u8 {
-                    // None of the bits is set => Background colour
-                    // The bit of the first byte is set => First colour
-                    // The bit of the second byte is set => Second colour
-                    // The bit if the first and second byte is set => Third colour
-
-                    if bit_at(*first_byte, i) && bit_at(*second_byte, i) {
-                        // Colour 3
-                        colour_data[i as usize] = colour_palette.colours[2];
-                    } else if bit_at(*second_byte, i) {
-                        // Colour 2
-                        colour_data[i as usize] = colour_palette.colours[1];
-                    } else if bit_at(*first_byte, i) {
-                        // Colour 1
-                        colour_data[i as usize] = colour_palette.colours[0];
-                    } else {
-                        // Background
-                        colour_data[i as usize] = colour_palette.background;
-                    }
-                }
-
-                colour_data
-            });
-
-        // We have to do this to avoid having to use alloc
         let mut rgb_data = [[Colour::default(); SPRITE_WIDTH_HEIGHT]; SPRITE_WIDTH_HEIGHT];
-        rgb_data
-            .iter_mut()
-            .for_each(|data_ref| *data_ref = rgb_iterator.next().unwrap());
+        for (rgb_row, index_row) in rgb_data.iter_mut().zip(&indices) {
+            for (colour, index) in rgb_row.iter_mut().zip(index_row) {
+                *colour = colour_palette.colour(*index);
+            }
+        }
 
         rgb_data
     }
+
+    /// Decode the sprite directly into an 8x8 pixel buffer in the given pixel format
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::BufferTooSmall`] if the buffer can't hold 64 pixels in the given format
+    pub fn decode_into(
+        &self,
+        buffer: &mut [u8],
+        colour_palette: ColourPalette,
+        pixel_format: PixelFormat,
+    ) -> Result<(), Error> {
+        let stride = SPRITE_WIDTH_HEIGHT * pixel_format.bytes_per_pixel();
+        pixel::check_buffer_size(buffer, stride * SPRITE_WIDTH_HEIGHT)?;
+
+        pixel::write_sprite(
+            buffer,
+            stride,
+            &self.to_indices(),
+            &colour_palette,
+            pixel_format,
+        );
+
+        Ok(())
+    }
+}
+
+/// Encode the 2-bit palette indices of a sprite into the 16 byte planar format used by the CHR ROM
+#[must_use]
+pub fn encode_indices(indices: &IndexedSprite) -> [u8; SPRITE_SIZE] {
+    encode_indices_with(indices, PlaneLayout::Planar)
+}
+
+/// Encode the 2-bit palette indices of a sprite into 16 bytes with the given plane layout
+#[must_use]
+pub fn encode_indices_with(
+    indices: &IndexedSprite,
+    plane_layout: PlaneLayout,
+) -> [u8; SPRITE_SIZE] {
+    let mut data = [0; SPRITE_SIZE];
+
+    for (y, row) in indices.iter().enumerate() {
+        let (low_offset, high_offset) = plane_layout.row_offsets(y);
+
+        for (x, index) in row.iter().enumerate() {
+            // The leftmost pixel is stored in the most significant bit
+            let shift = SPRITE_WIDTH_HEIGHT - 1 - x;
+
+            data[low_offset] |= (index & 1) << shift;
+            data[high_offset] |= ((index >> 1) & 1) << shift;
+        }
+    }
+
+    data
+}
+
+/// Decode a tile of a pattern table, treating tiles which are out of bounds as empty
+pub(crate) fn decode_tile(pattern_table: &[u8], tile: u8) -> IndexedSprite {
+    let start = tile as usize * SPRITE_SIZE;
+
+    pattern_table.get(start..start + SPRITE_SIZE).map_or(
+        [[0; SPRITE_WIDTH_HEIGHT]; SPRITE_WIDTH_HEIGHT],
+        |raw_sprite_data| {
+            Sprite {
+                raw_sprite_data,
+                plane_layout: PlaneLayout::Planar,
+            }
+            .to_indices()
+        },
+    )
 }
 
 #[derive(Clone)]
 pub struct Lemonade<'a> {
-    sprites: ChunksExact<'a, u8>,
+    // Raw data of the remaining sprites (trailing bytes which don't form a whole sprite are cut off)
+    data: &'a [u8],
+    plane_layout: PlaneLayout,
 }
 
 impl<'a> Lemonade<'a> {
     #[must_use]
     pub fn new(data: &'a [u8]) -> Self {
-        let sprites = data.chunks_exact(SPRITE_SIZE);
+        Self::with_plane_layout(data, PlaneLayout::Planar)
+    }
 
-        Self { sprites }
+    /// Iterate over sprites stored in the given plane layout, for example Game Boy tiles
+    #[must_use]
+    pub fn with_plane_layout(data: &'a [u8], plane_layout: PlaneLayout) -> Self {
+        let data = &data[..data.len() - data.len() % SPRITE_SIZE];
+
+        Self { data, plane_layout }
+    }
+
+    /// The plane layout of the sprites
+    #[must_use]
+    pub fn plane_layout(&self) -> PlaneLayout {
+        self.plane_layout
     }
 
     #[must_use]
     pub fn num_sprites(&self) -> usize {
-        self.sprites.len()
+        self.data.len() / SPRITE_SIZE
+    }
+
+    /// Raw data of the remaining sprites
+    #[must_use]
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Restrict the iterator to a range of the remaining sprites
+    ///
+    /// Combined with [`Lemonade::as_bytes`] this extracts the tiles into their own bank.
+    /// Returns `None` if the range is out of bounds.
+    #[must_use]
+    pub fn range(&self, range: Range<usize>) -> Option<Self> {
+        let data = self
+            .data
+            .get(range.start.checked_mul(SPRITE_SIZE)?..range.end.checked_mul(SPRITE_SIZE)?)?;
+
+        Some(Self {
+            data,
+            plane_layout: self.plane_layout,
+        })
+    }
+
+    /// Render a range of the remaining sprites into their own sheet
+    ///
+    /// Returns `None` if the range is out of bounds
+    #[cfg(feature = "alloc")]
+    #[must_use]
+    pub fn extract_sheet(
+        &self,
+        range: Range<usize>,
+        colour_palette: ColourPalette,
+        sprites_per_row: usize,
+    ) -> Option<Sheet> {
+        let sprites = self.range(range)?;
+
+        Some(Sheet::render(sprites, colour_palette, sprites_per_row))
+    }
+
+    /// Decode all remaining sprites into one image buffer which is `sprites_per_row` sprites wide
+    ///
+    /// The image is `sprites_per_row * 8` pixels wide and `ceil(num_sprites / sprites_per_row) * 8` pixels high.
+    /// Empty spaces in the last row are left untouched.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::BufferTooSmall`] if the buffer can't hold the whole image in the given format
+    pub fn decode_all_into(
+        self,
+        buffer: &mut [u8],
+        colour_palette: ColourPalette,
+        pixel_format: PixelFormat,
+        sprites_per_row: usize,
+    ) -> Result<(), Error> {
+        let sprites_per_row = sprites_per_row.max(1);
+        let rows = self.num_sprites().div_ceil(sprites_per_row);
+
+        let sprite_row_length = SPRITE_WIDTH_HEIGHT * pixel_format.bytes_per_pixel();
+        let stride = sprites_per_row * sprite_row_length;
+        pixel::check_buffer_size(buffer, rows * SPRITE_WIDTH_HEIGHT * stride)?;
+
+        for (index, sprite) in self.enumerate() {
+            let offset = (index / sprites_per_row) * SPRITE_WIDTH_HEIGHT * stride
+                + (index % sprites_per_row) * sprite_row_length;
+
+            pixel::write_sprite(
+                &mut buffer[offset..],
+                stride,
+                &sprite.to_indices(),
+                &colour_palette,
+                pixel_format,
+            );
+        }
+
+        Ok(())
     }
 }
 
@@ -150,8 +471,104 @@ impl<'a> Iterator for Lemonade<'a> {
     type Item = Sprite<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.sprites
-            .next()
-            .map(|raw_sprite_data| Sprite { raw_sprite_data })
+        if self.data.is_empty() {
+            return None;
+        }
+
+        let (raw_sprite_data, rest) = self.data.split_at(SPRITE_SIZE);
+        self.data = rest;
+
+        Some(Sprite {
+            raw_sprite_data,
+            plane_layout: self.plane_layout,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.num_sprites(), Some(self.num_sprites()))
+    }
+}
+
+impl ExactSizeIterator for Lemonade<'_> {}
+
+/// Create a sprite iterator over the pattern tables stored in the CHR ROM of an INES ROM
+///
+/// # Errors
+///
+/// Returns [`Error::NoChrRom`] if the ROM uses CHR RAM instead of a CHR ROM
+#[cfg(feature = "ines")]
+pub fn from_ines<'a>(ines: &'a Ines<'_>) -> Result<Lemonade<'a>, Error> {
+    let chr_rom = ines.chr_rom.as_deref().ok_or(Error::NoChrRom)?;
+
+    Ok(Lemonade::new(chr_rom))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The "½" tile from the nesdev wiki, with the leftmost pixel in the most significant bit
+    const HALF_TILE: [u8; SPRITE_SIZE] = [
+        0x41, 0xC2, 0x44, 0x48, 0x10, 0x20, 0x40, 0x80, // Low bit plane
+        0x01, 0x02, 0x04, 0x08, 0x16, 0x21, 0x42, 0x87, // High bit plane
+    ];
+
+    const HALF_INDICES: IndexedSprite = [
+        [0, 1, 0, 0, 0, 0, 0, 3],
+        [1, 1, 0, 0, 0, 0, 3, 0],
+        [0, 1, 0, 0, 0, 3, 0, 0],
+        [0, 1, 0, 0, 3, 0, 0, 0],
+        [0, 0, 0, 3, 0, 2, 2, 0],
+        [0, 0, 3, 0, 0, 0, 0, 2],
+        [0, 3, 0, 0, 0, 0, 2, 0],
+        [3, 0, 0, 0, 0, 2, 2, 2],
+    ];
+
+    #[test]
+    fn pixel_order() {
+        let sprite = Lemonade::new(&HALF_TILE).next().unwrap();
+
+        assert_eq!(sprite.to_indices(), HALF_INDICES);
+        assert_eq!(encode_indices(&HALF_INDICES), HALF_TILE);
+    }
+
+    #[test]
+    fn pixel_order_in_colours() {
+        let sprite = Lemonade::new(&HALF_TILE).next().unwrap();
+        let palette = ColourPalette::CLASSIC_MARIO;
+
+        for (colours, indices) in sprite.to_rgb(palette).iter().zip(&HALF_INDICES) {
+            for (colour, index) in colours.iter().zip(indices) {
+                assert_eq!(colour.raw_colour(), palette.colour(*index).raw_colour());
+            }
+        }
+    }
+
+    #[test]
+    fn pixel_order_in_pixel_formats() {
+        let sprite = Lemonade::new(&HALF_TILE).next().unwrap();
+        let palette = ColourPalette::CLASSIC_MARIO;
+
+        let mut buffer = [0; SPRITE_WIDTH_HEIGHT * SPRITE_WIDTH_HEIGHT * 3];
+        sprite
+            .decode_into(&mut buffer, palette, PixelFormat::Rgb8)
+            .unwrap();
+
+        let expected = HALF_INDICES
+            .iter()
+            .flatten()
+            .flat_map(|index| palette.colour(*index).raw_colour());
+        assert!(buffer.iter().copied().eq(expected));
+    }
+
+    #[test]
+    fn range() {
+        let mut data = HALF_TILE.to_vec();
+        data.extend_from_slice(&[0; SPRITE_SIZE]);
+        let sprites = Lemonade::new(&data);
+
+        assert_eq!(sprites.range(1..2).unwrap().count(), 1);
+        assert!(sprites.range(1..3).is_none());
+        assert!(sprites.range(usize::MAX..usize::MAX).is_none());
     }
 }
@@ -0,0 +1,105 @@
+use crate::Colour;
+
+/// The 64 colours the NES PPU (2C02) is able to output
+///
+/// The hardware doesn't output RGB values directly, so these values are only an approximation
+pub const NES_PALETTE: [Colour; 64] = [
+    // $00 - $0F
+    Colour::new(84, 84, 84),
+    Colour::new(0, 30, 116),
+    Colour::new(8, 16, 144),
+    Colour::new(48, 0, 136),
+    Colour::new(68, 0, 100),
+    Colour::new(92, 0, 48),
+    Colour::new(84, 4, 0),
+    Colour::new(60, 24, 0),
+    Colour::new(32, 42, 0),
+    Colour::new(8, 58, 0),
+    Colour::new(0, 64, 0),
+    Colour::new(0, 60, 0),
+    Colour::new(0, 50, 60),
+    Colour::new(0, 0, 0),
+    Colour::new(0, 0, 0),
+    Colour::new(0, 0, 0),
+    // $10 - $1F
+    Colour::new(152, 150, 152),
+    Colour::new(8, 76, 196),
+    Colour::new(48, 50, 236),
+    Colour::new(92, 30, 228),
+    Colour::new(136, 20, 176),
+    Colour::new(160, 20, 100),
+    Colour::new(152, 34, 32),
+    Colour::new(120, 60, 0),
+    Colour::new(84, 90, 0),
+    Colour::new(40, 114, 0),
+    Colour::new(8, 124, 0),
+    Colour::new(0, 118, 40),
+    Colour::new(0, 102, 120),
+    Colour::new(0, 0, 0),
+    Colour::new(0, 0, 0),
+    Colour::new(0, 0, 0),
+    // $20 - $2F
+    Colour::new(236, 238, 236),
+    Colour::new(76, 154, 236),
+    Colour::new(120, 124, 236),
+    Colour::new(176, 98, 236),
+    Colour::new(228, 84, 236),
+    Colour::new(236, 88, 180),
+    Colour::new(236, 106, 100),
+    Colour::new(212, 136, 32),
+    Colour::new(160, 170, 0),
+    Colour::new(116, 196, 0),
+    Colour::new(76, 208, 32),
+    Colour::new(56, 204, 108),
+    Colour::new(56, 180, 204),
+    Colour::new(60, 60, 60),
+    Colour::new(0, 0, 0),
+    Colour::new(0, 0, 0),
+    // $30 - $3F
+    Colour::new(236, 238, 236),
+    Colour::new(168, 204, 236),
+    Colour::new(188, 188, 236),
+    Colour::new(212, 178, 236),
+    Colour::new(236, 174, 236),
+    Colour::new(236, 174, 212),
+    Colour::new(236, 180, 176),
+    Colour::new(228, 196, 144),
+    Colour::new(204, 210, 120),
+    Colour::new(180, 222, 120),
+    Colour::new(168, 226, 144),
+    Colour::new(152, 226, 180),
+    Colour::new(160, 214, 228),
+    Colour::new(160, 162, 160),
+    Colour::new(0, 0, 0),
+    Colour::new(0, 0, 0),
+];
+
+/// Whether the colour index is the canonical index of its colour
+///
+/// Duplicate blacks, the duplicate white at $20 and the "blacker than black" colour at $0D
+/// (which confuses some TVs) are never chosen when converting colours
+const fn is_canonical(index: u8) -> bool {
+    !matches!(
+        index,
+        0x0D | 0x0E | 0x1D | 0x1E | 0x1F | 0x20 | 0x2E | 0x2F | 0x3E | 0x3F
+    )
+}
+
+/// Look up the colour of an index into the NES master palette
+///
+/// Only the lower six bits of the index are taken into account
+#[must_use]
+pub const fn nes_colour(index: u8) -> Colour {
+    NES_PALETTE[(index & 0x3F) as usize]
+}
+
+/// Find the index of the colour of the NES master palette which is the closest to the given colour
+// Only 64 entries, the truncation can't happen
+#[allow(clippy::cast_possible_truncation)]
+#[must_use]
+pub fn nearest_nes_colour(colour: Colour) -> u8 {
+    (0..NES_PALETTE.len() as u8)
+        .filter(|index| is_canonical(*index))
+        .min_by_key(|index| colour.distance(nes_colour(*index)))
+        .unwrap_or(0x0F)
+}
@@ -0,0 +1,145 @@
+use {
+    crate::{decode_tile, Colour, ColourPalette, SPRITE_WIDTH_HEIGHT},
+    alloc::vec::Vec,
+    core::convert::TryFrom,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// Bits of the OAM attribute byte
+const PALETTE_MASK: u8 = 0b0000_0011;
+const BEHIND_BACKGROUND_BIT: u8 = 5;
+const FLIP_HORIZONTAL_BIT: u8 = 6;
+const FLIP_VERTICAL_BIT: u8 = 7;
+
+/// A single hardware sprite which is part of a metasprite
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MetaspriteTile {
+    /// Horizontal offset relative to the origin of the metasprite
+    pub x: i8,
+    /// Vertical offset relative to the origin of the metasprite
+    pub y: i8,
+    /// Index of the tile in the pattern table
+    pub tile: u8,
+    /// Index of the sprite palette (0 to 3)
+    pub palette: u8,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub behind_background: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub flip_horizontal: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub flip_vertical: bool,
+}
+
+impl MetaspriteTile {
+    /// Build the tile from its offsets, the tile index and the OAM attribute byte
+    #[must_use]
+    pub const fn from_attributes(x: i8, y: i8, tile: u8, attributes: u8) -> Self {
+        Self {
+            x,
+            y,
+            tile,
+            palette: attributes & PALETTE_MASK,
+            behind_background: (attributes >> BEHIND_BACKGROUND_BIT) & 1 == 1,
+            flip_horizontal: (attributes >> FLIP_HORIZONTAL_BIT) & 1 == 1,
+            flip_vertical: (attributes >> FLIP_VERTICAL_BIT) & 1 == 1,
+        }
+    }
+
+    /// Encode the palette, priority and flip flags into an OAM attribute byte
+    #[must_use]
+    pub const fn attributes(&self) -> u8 {
+        (self.palette & PALETTE_MASK)
+            | ((self.behind_background as u8) << BEHIND_BACKGROUND_BIT)
+            | ((self.flip_horizontal as u8) << FLIP_HORIZONTAL_BIT)
+            | ((self.flip_vertical as u8) << FLIP_VERTICAL_BIT)
+    }
+}
+
+/// A group of hardware sprites which are drawn together to form a bigger object
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Metasprite {
+    pub tiles: Vec<MetaspriteTile>,
+}
+
+impl Metasprite {
+    #[must_use]
+    pub fn new(tiles: Vec<MetaspriteTile>) -> Self {
+        Self { tiles }
+    }
+
+    /// Write the metasprite into OAM entries (Y, tile, attributes, X) placed at the given screen position
+    ///
+    /// Tiles which would end up outside of the screen get wrapped around, just like they would on the hardware.
+    #[must_use]
+    pub fn to_oam(&self, x: u8, y: u8) -> Vec<[u8; 4]> {
+        self.tiles
+            .iter()
+            .map(|tile| {
+                // The casts are intentional; OAM positions wrap around
+                #[allow(clippy::cast_sign_loss)]
+                let (tile_x, tile_y) = (x.wrapping_add(tile.x as u8), y.wrapping_add(tile.y as u8));
+
+                // The Y coordinate in OAM is stored minus one
+                [tile_y.wrapping_sub(1), tile.tile, tile.attributes(), tile_x]
+            })
+            .collect()
+    }
+
+    /// Draw the metasprite onto an image (row by row) which is `width` pixels wide, with its origin at the given position
+    ///
+    /// Pixels with the palette index 0 are transparent and tiles earlier in the list are drawn on top of later ones,
+    /// just like the hardware does with OAM entries. The background priority flag is ignored.
+    pub fn render(
+        &self,
+        pixels: &mut [Colour],
+        width: usize,
+        (x, y): (i32, i32),
+        pattern_table: &[u8],
+        colour_palettes: &[ColourPalette; 4],
+    ) {
+        let height = pixels.len() / width.max(1);
+
+        for tile in self.tiles.iter().rev() {
+            let indices = decode_tile(pattern_table, tile.tile);
+            let colour_palette = &colour_palettes[(tile.palette & PALETTE_MASK) as usize];
+
+            for (row, index_row) in indices.iter().enumerate() {
+                for (column, index) in index_row.iter().enumerate() {
+                    if *index == 0 {
+                        continue;
+                    }
+
+                    let row = if tile.flip_vertical {
+                        SPRITE_WIDTH_HEIGHT - 1 - row
+                    } else {
+                        row
+                    };
+                    let column = if tile.flip_horizontal {
+                        SPRITE_WIDTH_HEIGHT - 1 - column
+                    } else {
+                        column
+                    };
+
+                    // Positions of at most a few hundred pixels, the casts can't wrap
+                    #[allow(clippy::cast_possible_wrap, clippy::cast_possible_truncation)]
+                    let (pixel_x, pixel_y) = (
+                        x + i32::from(tile.x) + column as i32,
+                        y + i32::from(tile.y) + row as i32,
+                    );
+
+                    if let (Ok(pixel_x), Ok(pixel_y)) =
+                        (usize::try_from(pixel_x), usize::try_from(pixel_y))
+                    {
+                        if pixel_x < width && pixel_y < height {
+                            pixels[pixel_y * width + pixel_x] = colour_palette.colour(*index);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use crate::{
+    screen::{Screen, NAMETABLE_SIZE},
+    Error,
+};
+
+#[cfg(feature = "ines")]
+use ines_parser::VramLayout;
+
+// The PPU addresses four nametables, laid out in a 2x2 grid
+pub(crate) const NAMETABLES: usize = 4;
+
+// Size of the VRAM inside of the console
+const CONSOLE_VRAM_SIZE: usize = NAMETABLE_SIZE * 2;
+
+/// How the 2 KiB of VRAM are mirrored into the four nametables
+///
+/// Besides the layouts soldered onto the cartridge, mappers like the MMC1 switch to showing
+/// a single kilobyte in all four places, and four-screen boards add 2 KiB of VRAM so every nametable is distinct.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mirroring {
+    Horizontal,
+    Vertical,
+    /// Every nametable shows the first 1 KiB
+    SingleScreenLower,
+    /// Every nametable shows the second 1 KiB
+    SingleScreenUpper,
+    /// The cartridge provides 2 KiB of additional VRAM, which follow the VRAM of the console
+    FourScreen,
+}
+
+impl Mirroring {
+    /// Which kilobyte of the VRAM a nametable shows
+    #[must_use]
+    pub const fn page(self, nametable: usize) -> usize {
+        match self {
+            Self::Horizontal => nametable / 2,
+            Self::Vertical => nametable % 2,
+            Self::SingleScreenLower => 0,
+            Self::SingleScreenUpper => 1,
+            Self::FourScreen => nametable,
+        }
+    }
+
+    /// Size of the VRAM the nametables are mapped onto
+    #[must_use]
+    pub const fn vram_size(self) -> usize {
+        match self {
+            Self::FourScreen => NAMETABLE_SIZE * NAMETABLES,
+            _ => CONSOLE_VRAM_SIZE,
+        }
+    }
+
+    /// Offset into the VRAM the PPU accesses for an address in the nametable area (`$2000`-`$3EFF`)
+    ///
+    /// `$3000`-`$3EFF` mirror the nametables at `$2000`-`$2EFF`.
+    #[must_use]
+    pub const fn vram_offset(self, address: u16) -> usize {
+        let offset = (address & 0x0FFF) as usize;
+
+        self.page(offset / NAMETABLE_SIZE) * NAMETABLE_SIZE + offset % NAMETABLE_SIZE
+    }
+
+    /// The nametables at `$2000`, `$2400`, `$2800` and `$2C00`, as the PPU sees them in the VRAM
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::VramTooSmall`] if the VRAM is smaller than [`Mirroring::vram_size`]
+    pub fn nametables(self, vram: &[u8]) -> Result<[[u8; NAMETABLE_SIZE]; NAMETABLES], Error> {
+        if vram.len() < self.vram_size() {
+            return Err(Error::VramTooSmall {
+                required: self.vram_size(),
+                actual: vram.len(),
+            });
+        }
+
+        Ok([0, 1, 2, 3].map(|nametable| {
+            let start = self.page(nametable) * NAMETABLE_SIZE;
+
+            let mut data = [0; NAMETABLE_SIZE];
+            data.copy_from_slice(&vram[start..start + NAMETABLE_SIZE]);
+            data
+        }))
+    }
+
+    /// The nametables as parsed screens, see [`Mirroring::nametables`]
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::VramTooSmall`] if the VRAM is smaller than [`Mirroring::vram_size`]
+    pub fn screens(self, vram: &[u8]) -> Result<[Screen; NAMETABLES], Error> {
+        Ok(self
+            .nametables(vram)?
+            .map(|nametable| Screen::from_nametable(&nametable)))
+    }
+}
+
+#[cfg(feature = "ines")]
+impl From<VramLayout> for Mirroring {
+    fn from(layout: VramLayout) -> Self {
+        match layout {
+            VramLayout::HorizontalMirroring => Self::Horizontal,
+            VramLayout::VerticalMirroring => Self::Vertical,
+            VramLayout::FourScreen => Self::FourScreen,
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use {
+    crate::{master_palette::nes_colour, Colour, ColourPalette, Sheet},
+    alloc::vec::Vec,
+};
+
+/// Size of the palette RAM of the PPU ($3F00 - $3F1F)
+pub const PALETTE_RAM_SIZE: usize = 32;
+
+/// Contents of the palette RAM of the PPU
+///
+/// The palette RAM contains four background palettes followed by four sprite palettes.
+/// Every palette consists of four colour indices into the NES master palette.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PaletteRam {
+    data: [u8; PALETTE_RAM_SIZE],
+}
+
+// Four background and four sprite palettes with four entries each
+const PALETTES: usize = 8;
+const PALETTE_SIZE: usize = 4;
+
+/// One entry of the palette RAM, as shown by the palette viewers of emulators
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Swatch {
+    /// Address of the entry ($3F00 - $3F1F)
+    pub address: u16,
+    /// Colour index stored in the entry (mirrored entries show the value of the entry they mirror)
+    pub stored: u8,
+    /// Colour index actually drawn for the entry
+    pub displayed: u8,
+    /// The first entry of every palette draws the universal background colour instead of its own value
+    pub shows_backdrop: bool,
+}
+
+impl Swatch {
+    #[must_use]
+    pub const fn colour(&self) -> Colour {
+        nes_colour(self.displayed)
+    }
+}
+
+/// The palette RAM laid out as swatches, one row per palette
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwatchLayout {
+    pub background: [[Swatch; PALETTE_SIZE]; 4],
+    pub sprites: [[Swatch; PALETTE_SIZE]; 4],
+}
+
+impl SwatchLayout {
+    /// All 32 swatches in the order of their addresses
+    pub fn iter(&self) -> impl Iterator<Item = &Swatch> {
+        self.background.iter().chain(&self.sprites).flatten()
+    }
+}
+
+impl PaletteRam {
+    #[must_use]
+    pub const fn new(data: [u8; PALETTE_RAM_SIZE]) -> Self {
+        Self { data }
+    }
+
+    /// The raw palette RAM dump
+    #[must_use]
+    pub const fn as_bytes(&self) -> &[u8; PALETTE_RAM_SIZE] {
+        &self.data
+    }
+
+    /// Read an entry of the palette RAM, just like the PPU would
+    ///
+    /// The first entries of the sprite palettes ($3F10, $3F14, $3F18, $3F1C) are mirrors of the
+    /// first entries of the background palettes. Only the lower five bits of the address are taken into account.
+    #[must_use]
+    pub const fn entry(&self, address: u8) -> u8 {
+        let address = address & 0x1F;
+        let address = if address & 0x13 == 0x10 {
+            address & 0x0F
+        } else {
+            address
+        };
+
+        self.data[address as usize] & 0x3F
+    }
+
+    /// The universal background colour ($3F00) which is shown wherever a pixel has the palette index 0
+    #[must_use]
+    pub const fn backdrop(&self) -> u8 {
+        self.entry(0)
+    }
+
+    fn palette(&self, start: u8) -> ColourPalette {
+        // Every palette shares the universal background colour
+        ColourPalette::new(
+            nes_colour(self.backdrop()),
+            [
+                nes_colour(self.entry(start + 1)),
+                nes_colour(self.entry(start + 2)),
+                nes_colour(self.entry(start + 3)),
+            ],
+        )
+    }
+
+    /// The four background palettes ($3F00 - $3F0F)
+    #[must_use]
+    pub fn background_palettes(&self) -> [ColourPalette; 4] {
+        [0x00, 0x04, 0x08, 0x0C].map(|start| self.palette(start))
+    }
+
+    /// The four sprite palettes ($3F10 - $3F1F)
+    #[must_use]
+    pub fn sprite_palettes(&self) -> [ColourPalette; 4] {
+        [0x10, 0x14, 0x18, 0x1C].map(|start| self.palette(start))
+    }
+
+    /// Lay the entries out as swatches
+    #[must_use]
+    pub fn swatches(&self) -> SwatchLayout {
+        let palette = |start: u8| {
+            [0, 1, 2, 3].map(|index| {
+                let address = start + index;
+                let shows_backdrop = index == 0;
+
+                Swatch {
+                    address: 0x3F00 | u16::from(address),
+                    stored: self.entry(address),
+                    displayed: if shows_backdrop {
+                        self.backdrop()
+                    } else {
+                        self.entry(address)
+                    },
+                    shows_backdrop,
+                }
+            })
+        };
+
+        SwatchLayout {
+            background: [0x00, 0x04, 0x08, 0x0C].map(palette),
+            sprites: [0x10, 0x14, 0x18, 0x1C].map(palette),
+        }
+    }
+
+    /// Render the 32 swatches into a strip of squares with the given size in pixels
+    ///
+    /// Every entry shows the colour the PPU draws for it, so the first entry of every palette shows the backdrop
+    #[must_use]
+    pub fn render_swatches(&self, swatch_size: usize) -> Sheet {
+        let swatch_size = swatch_size.max(1);
+        let width = PALETTES * PALETTE_SIZE * swatch_size;
+
+        let row: Vec<Colour> = self
+            .swatches()
+            .iter()
+            .flat_map(|swatch| core::iter::repeat_n(swatch.colour(), swatch_size))
+            .collect();
+        let pixels = row.repeat(swatch_size);
+
+        Sheet::from_pixels(width, swatch_size, pixels)
+    }
+}
+
+impl From<[u8; PALETTE_RAM_SIZE]> for PaletteRam {
+    fn from(data: [u8; PALETTE_RAM_SIZE]) -> Self {
+        Self::new(data)
+    }
+}
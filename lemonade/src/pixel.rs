@@ -0,0 +1,93 @@
+use crate::{ColourPalette, Error, IndexedSprite, SPRITE_WIDTH_HEIGHT};
+
+/// Memory layout of a single pixel in an output buffer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// One byte containing the 2-bit palette index (the palette is ignored)
+    Indexed,
+    /// Three bytes (red, green, blue)
+    Rgb8,
+    /// Four bytes (red, green, blue, alpha); the alpha channel is always fully opaque
+    Rgba8,
+    /// Four bytes (blue, green, red, alpha); the alpha channel is always fully opaque
+    Bgra8,
+}
+
+impl PixelFormat {
+    #[must_use]
+    pub const fn bytes_per_pixel(self) -> usize {
+        match self {
+            Self::Indexed => 1,
+            Self::Rgb8 => 3,
+            Self::Rgba8 | Self::Bgra8 => 4,
+        }
+    }
+
+    // The four pixels a sprite can consist of, encoded in this format and padded to four bytes
+    fn encode_palette(self, colour_palette: &ColourPalette) -> [[u8; 4]; 4] {
+        let mut pixels = [[0; 4]; 4];
+        for (index, pixel) in (0..).zip(&mut pixels) {
+            let [r, g, b] = colour_palette.colour(index).raw_colour();
+
+            *pixel = match self {
+                Self::Indexed => [index, 0, 0, 0],
+                Self::Rgb8 => [r, g, b, 0],
+                Self::Rgba8 => [r, g, b, u8::MAX],
+                Self::Bgra8 => [b, g, r, u8::MAX],
+            };
+        }
+
+        pixels
+    }
+}
+
+/// Check whether the buffer is large enough to hold the required amount of bytes
+pub(crate) fn check_buffer_size(buffer: &[u8], required: usize) -> Result<(), Error> {
+    if buffer.len() < required {
+        return Err(Error::BufferTooSmall {
+            required,
+            actual: buffer.len(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Write the pixels of a decoded sprite into a buffer which is `stride` bytes wide
+///
+/// The caller has to make sure the buffer is large enough
+pub(crate) fn write_sprite(
+    buffer: &mut [u8],
+    stride: usize,
+    indices: &IndexedSprite,
+    colour_palette: &ColourPalette,
+    pixel_format: PixelFormat,
+) {
+    let pixels = pixel_format.encode_palette(colour_palette);
+
+    match pixel_format {
+        PixelFormat::Indexed => write_rows::<1>(buffer, stride, indices, &pixels),
+        PixelFormat::Rgb8 => write_rows::<3>(buffer, stride, indices, &pixels),
+        PixelFormat::Rgba8 | PixelFormat::Bgra8 => {
+            write_rows::<4>(buffer, stride, indices, &pixels);
+        }
+    }
+}
+
+// Copy the encoded pixels into the rows; the pixel size is a constant so every copy has a fixed length
+fn write_rows<const BYTES_PER_PIXEL: usize>(
+    buffer: &mut [u8],
+    stride: usize,
+    indices: &IndexedSprite,
+    pixels: &[[u8; 4]; 4],
+) {
+    let row_length = SPRITE_WIDTH_HEIGHT * BYTES_PER_PIXEL;
+
+    for (y, index_row) in indices.iter().enumerate() {
+        let row = &mut buffer[y * stride..y * stride + row_length];
+
+        for (pixel, index) in row.chunks_exact_mut(BYTES_PER_PIXEL).zip(index_row) {
+            pixel.copy_from_slice(&pixels[usize::from(index & 0b11)][..BYTES_PER_PIXEL]);
+        }
+    }
+}
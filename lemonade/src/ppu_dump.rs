@@ -0,0 +1,164 @@
+use {
+    crate::{
+        mirroring::{Mirroring, NAMETABLES},
+        palette_ram::PaletteRam,
+        screen::{Screen, NAMETABLE_SIZE, NAMETABLE_WIDTH, SCREEN_HEIGHT, SCREEN_WIDTH},
+        Colour, Error,
+    },
+    alloc::vec::Vec,
+};
+
+const PATTERN_TABLE_SIZE: usize = 0x1000;
+
+// Bits of an ExRAM byte in the extended attribute mode of the MMC5
+const EXRAM_CHR_BANK_MASK: u8 = 0x3F;
+const EXRAM_PALETTE_SHIFT: u8 = 6;
+
+// PPUCTRL bits selecting the base nametable and the pattern table of the background
+const CTRL_NAMETABLE_MASK: u8 = 0b11;
+const CTRL_BACKGROUND_PATTERN_TABLE: u8 = 0x10;
+
+/// `ExRAM` of the MMC5 in its extended attribute mode (`$5104` set to 1)
+///
+/// Every byte of the `ExRAM` belongs to the tile at the same position in the nametables, and selects the palette
+/// of the tile (bits 6-7) and the 4 KiB CHR bank its pattern is taken from (bits 0-5), instead of the attribute table.
+#[derive(Clone, Copy, Debug)]
+pub struct ExtendedAttributes<'a> {
+    /// The 1 KiB of `ExRAM`
+    pub exram: &'a [u8],
+    /// The whole CHR ROM, since the banks are selected per tile
+    pub chr_rom: &'a [u8],
+    /// Value of `$5130`, the upper two bits of the CHR bank
+    pub upper_chr_bank: u8,
+}
+
+impl<'a> ExtendedAttributes<'a> {
+    // Pattern table and palette of the tile at the tile coordinates
+    fn attributes(&self, x: usize, y: usize) -> (&'a [u8], u8) {
+        let attribute = self
+            .exram
+            .get(y * NAMETABLE_WIDTH + x)
+            .copied()
+            .unwrap_or_default();
+        let bank = usize::from(self.upper_chr_bank & 0b11) << 6
+            | usize::from(attribute & EXRAM_CHR_BANK_MASK);
+
+        // Banks past the end of the CHR ROM wrap around like on the cartridge
+        let banks = (self.chr_rom.len() / PATTERN_TABLE_SIZE).max(1);
+        let start = (bank % banks) * PATTERN_TABLE_SIZE;
+        let pattern_table = self.chr_rom.get(start..).unwrap_or_default();
+
+        (pattern_table, attribute >> EXRAM_PALETTE_SHIFT)
+    }
+}
+
+/// Memory dump of the PPU, as exported by the debuggers of emulators
+///
+/// Renders the background the way it was visible on the screen, regenerating screenshots from the dump.
+#[derive(Clone, Debug)]
+pub struct PpuDump<'a> {
+    /// The nametables at $2000, $2400, $2800 and $2C00
+    pub nametables: [Screen; NAMETABLES],
+    pub palette_ram: PaletteRam,
+    /// Both pattern tables (8 KiB, usually the CHR ROM or the banks mapped at the time of the dump)
+    pub pattern_tables: &'a [u8],
+    /// Value of PPUCTRL, selecting the base nametable and the pattern table of the background
+    pub ctrl: u8,
+    pub scroll_x: u8,
+    pub scroll_y: u8,
+    /// Per-tile patterns and palettes of MMC5 games using the extended attribute mode
+    pub extended_attributes: Option<ExtendedAttributes<'a>>,
+}
+
+impl<'a> PpuDump<'a> {
+    /// Create a dump from the nametable memory
+    ///
+    /// The memory can either be the 4 KiB at $2000-$2FFF (used as is), or the 2 KiB of VRAM of the console
+    /// (mirrored as given) or a single nametable (shown in all four places).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidDumpSize`] if the memory has none of those sizes
+    pub fn new(
+        memory: &[u8],
+        mirroring: Mirroring,
+        palette_ram: PaletteRam,
+        pattern_tables: &'a [u8],
+    ) -> Result<Self, Error> {
+        let pages = match memory.len() {
+            size if size == NAMETABLE_SIZE * NAMETABLES => None,
+            size if size == NAMETABLE_SIZE * 2 || size == NAMETABLE_SIZE => {
+                Some(size / NAMETABLE_SIZE)
+            }
+            size => return Err(Error::InvalidDumpSize(size)),
+        };
+
+        let nametables = [0, 1, 2, 3].map(|nametable| {
+            let page = pages.map_or(nametable, |pages| mirroring.page(nametable) % pages);
+            let start = page * NAMETABLE_SIZE;
+
+            let mut data = [0; NAMETABLE_SIZE];
+            data.copy_from_slice(&memory[start..start + NAMETABLE_SIZE]);
+            Screen::from_nametable(&data)
+        });
+
+        Ok(Self {
+            nametables,
+            palette_ram,
+            pattern_tables,
+            ctrl: 0,
+            scroll_x: 0,
+            scroll_y: 0,
+            extended_attributes: None,
+        })
+    }
+
+    /// Render the visible 256x240 pixels of the background (row by row)
+    ///
+    /// The scroll position is relative to the base nametable and wraps around the four nametables.
+    /// With extended attributes, the pattern table selected by PPUCTRL and the attribute tables are ignored.
+    /// Sprites aren't part of the nametables, so they aren't drawn.
+    #[must_use]
+    pub fn render(&self) -> Vec<Colour> {
+        let pattern_table_start = if self.ctrl & CTRL_BACKGROUND_PATTERN_TABLE == 0 {
+            0
+        } else {
+            PATTERN_TABLE_SIZE
+        };
+        let pattern_table = self
+            .pattern_tables
+            .get(pattern_table_start..)
+            .unwrap_or_default();
+
+        let colour_palettes = self.palette_ram.background_palettes();
+        let rendered: Vec<Vec<Colour>> = self
+            .nametables
+            .iter()
+            .map(|nametable| match &self.extended_attributes {
+                Some(extended_attributes) => nametable.render_with(&colour_palettes, |x, y| {
+                    extended_attributes.attributes(x, y)
+                }),
+                None => nametable.render(pattern_table, &colour_palettes),
+            })
+            .collect();
+
+        let base = usize::from(self.ctrl & CTRL_NAMETABLE_MASK);
+        let origin_x = (base % 2) * SCREEN_WIDTH + usize::from(self.scroll_x);
+        let origin_y = (base / 2) * SCREEN_HEIGHT + usize::from(self.scroll_y);
+
+        let mut pixels = Vec::with_capacity(SCREEN_WIDTH * SCREEN_HEIGHT);
+        for y in 0..SCREEN_HEIGHT {
+            let y = (origin_y + y) % (SCREEN_HEIGHT * 2);
+            for x in 0..SCREEN_WIDTH {
+                let x = (origin_x + x) % (SCREEN_WIDTH * 2);
+                let nametable = (y / SCREEN_HEIGHT) * 2 + x / SCREEN_WIDTH;
+
+                pixels.push(
+                    rendered[nametable][(y % SCREEN_HEIGHT) * SCREEN_WIDTH + x % SCREEN_WIDTH],
+                );
+            }
+        }
+
+        pixels
+    }
+}
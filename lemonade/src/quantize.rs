@@ -0,0 +1,342 @@
+use {
+    crate::{
+        dither::{self, Dithering},
+        encode_indices,
+        master_palette::{nearest_nes_colour, nes_colour, NES_PALETTE},
+        Colour, ColourPalette, Error, IndexedSprite, SPRITE_WIDTH_HEIGHT,
+    },
+    alloc::vec::Vec,
+};
+
+/// Width and height of the area sharing one background palette
+pub const REGION_SIZE: usize = 16;
+
+const NUM_PALETTES: usize = 4;
+const NUM_COLOURS: usize = NES_PALETTE.len();
+
+// How often the sub-palettes get recalculated from the regions which ended up using them
+const REFINEMENT_ITERATIONS: usize = 4;
+
+type Histogram = [u32; NUM_COLOURS];
+type DistanceTable = [[u32; NUM_COLOURS]; NUM_COLOURS];
+
+/// An image converted to the colour constraints of the NES background
+///
+/// Every 16x16 region uses one of four sub-palettes, which all share one background colour.
+/// All colours are indices into the NES master palette.
+#[derive(Clone, Debug)]
+pub struct QuantizedImage {
+    width: usize,
+    height: usize,
+    background: u8,
+    palettes: [[u8; 3]; NUM_PALETTES],
+    attributes: Vec<u8>,
+    indices: Vec<u8>,
+}
+
+fn distance_table() -> DistanceTable {
+    let mut table = [[0; NUM_COLOURS]; NUM_COLOURS];
+    for (first, row) in NES_PALETTE.iter().zip(table.iter_mut()) {
+        for (second, distance) in NES_PALETTE.iter().zip(row.iter_mut()) {
+            *distance = first.distance(*second);
+        }
+    }
+
+    table
+}
+
+/// Index of the largest entry of the histogram
+// Only 64 entries, the truncation can't happen
+#[allow(clippy::cast_possible_truncation)]
+fn most_common_colour(histogram: &Histogram) -> u8 {
+    (0..NUM_COLOURS)
+        .rev()
+        .max_by_key(|index| histogram[*index])
+        .unwrap_or(0) as u8
+}
+
+/// The (up to) three most common colours apart from the background, padded with the background colour
+fn top_colours(histogram: &Histogram, background: u8) -> [u8; 3] {
+    let mut histogram = *histogram;
+    histogram[background as usize] = 0;
+
+    let mut palette = [background; 3];
+    for entry in &mut palette {
+        let colour = most_common_colour(&histogram);
+        if histogram[colour as usize] == 0 {
+            break;
+        }
+
+        *entry = colour;
+        histogram[colour as usize] = 0;
+    }
+
+    palette
+}
+
+/// Total error when drawing the colours of the histogram with the palette
+fn palette_cost(
+    histogram: &Histogram,
+    background: u8,
+    palette: [u8; 3],
+    distances: &DistanceTable,
+) -> u64 {
+    histogram
+        .iter()
+        .zip(distances.iter())
+        .filter(|(count, _)| **count > 0)
+        .map(|(count, distances)| {
+            let distance = palette
+                .iter()
+                .map(|entry| distances[*entry as usize])
+                .fold(distances[background as usize], u32::min);
+
+            u64::from(*count) * u64::from(distance)
+        })
+        .sum()
+}
+
+/// Index of the palette which draws the colours of the histogram with the least error
+// Only four palettes, the truncation can't happen
+#[allow(clippy::cast_possible_truncation)]
+fn best_palette(
+    histogram: &Histogram,
+    background: u8,
+    palettes: &[[u8; 3]; NUM_PALETTES],
+    distances: &DistanceTable,
+) -> u8 {
+    (0..NUM_PALETTES)
+        .min_by_key(|index| palette_cost(histogram, background, palettes[*index], distances))
+        .unwrap_or(0) as u8
+}
+
+/// Pick the four sub-palettes which are wanted by the largest amount of pixels
+fn initial_palettes(region_histograms: &[Histogram], background: u8) -> [[u8; 3]; NUM_PALETTES] {
+    let mut candidates: Vec<([u8; 3], u32)> = Vec::new();
+    for histogram in region_histograms {
+        // Sort the colours so the same sets of colours compare as equal
+        let mut palette = top_colours(histogram, background);
+        palette.sort_unstable();
+        let weight: u32 = histogram.iter().sum();
+
+        match candidates
+            .iter_mut()
+            .find(|(candidate, _)| *candidate == palette)
+        {
+            Some((_, total_weight)) => *total_weight += weight,
+            None => candidates.push((palette, weight)),
+        }
+    }
+
+    candidates.sort_by(|(_, first), (_, second)| second.cmp(first));
+
+    let mut palettes = [[background; 3]; NUM_PALETTES];
+    for (palette, (candidate, _)) in palettes.iter_mut().zip(candidates) {
+        *palette = candidate;
+    }
+
+    palettes
+}
+
+/// Index (0 to 3) of the palette entry which is the closest to the colour
+pub(crate) fn nearest_palette_index(colour: Colour, colour_palette: &ColourPalette) -> u8 {
+    (0..4)
+        .min_by_key(|index| colour.distance(colour_palette.colour(*index)))
+        .unwrap_or(0)
+}
+
+/// Convert an RGB image (row by row) to the NES background colour constraints
+///
+/// First every pixel gets mapped to the closest colour of the NES master palette.
+/// The most common colour becomes the shared background colour, afterwards the four sub-palettes
+/// get selected so they minimise the error over all 16x16 regions.
+///
+/// # Errors
+///
+/// Returns [`Error::ImageSizeMismatch`] if the amount of pixels doesn't match the dimensions
+pub fn quantize(pixels: &[Colour], width: usize, height: usize) -> Result<QuantizedImage, Error> {
+    quantize_with(pixels, width, height, Dithering::None)
+}
+
+/// Same as [`quantize`] but uses the given dithering mode when mapping the pixels to the sub-palettes
+///
+/// # Errors
+///
+/// Returns [`Error::ImageSizeMismatch`] if the amount of pixels doesn't match the dimensions
+pub fn quantize_with(
+    pixels: &[Colour],
+    width: usize,
+    height: usize,
+    dithering: Dithering,
+) -> Result<QuantizedImage, Error> {
+    if pixels.len() != width * height {
+        return Err(Error::ImageSizeMismatch {
+            expected: width * height,
+            actual: pixels.len(),
+        });
+    }
+
+    let distances = distance_table();
+    let regions_per_row = width.div_ceil(REGION_SIZE);
+    let regions_per_column = height.div_ceil(REGION_SIZE);
+
+    // Map every pixel to the master palette and count the colours per region
+    let mut global_histogram = [0; NUM_COLOURS];
+    let mut region_histograms = alloc::vec![[0; NUM_COLOURS]; regions_per_row * regions_per_column];
+    for (index, colour) in pixels.iter().enumerate() {
+        let (x, y) = (index % width, index / width);
+        let region = (y / REGION_SIZE) * regions_per_row + x / REGION_SIZE;
+        let nes_index = nearest_nes_colour(*colour) as usize;
+
+        global_histogram[nes_index] += 1;
+        region_histograms[region][nes_index] += 1;
+    }
+
+    let background = most_common_colour(&global_histogram);
+
+    // Refine the palettes by recalculating them from the regions which were assigned to them
+    let mut palettes = initial_palettes(&region_histograms, background);
+    for _ in 0..REFINEMENT_ITERATIONS {
+        let mut palette_histograms = [[0; NUM_COLOURS]; NUM_PALETTES];
+        for histogram in &region_histograms {
+            let palette = best_palette(histogram, background, &palettes, &distances);
+
+            for (total, count) in palette_histograms[palette as usize]
+                .iter_mut()
+                .zip(histogram)
+            {
+                *total += count;
+            }
+        }
+
+        for (palette, histogram) in palettes.iter_mut().zip(&palette_histograms) {
+            if histogram.iter().any(|count| *count > 0) {
+                *palette = top_colours(histogram, background);
+            }
+        }
+    }
+
+    let attributes: Vec<u8> = region_histograms
+        .iter()
+        .map(|histogram| best_palette(histogram, background, &palettes, &distances))
+        .collect();
+
+    let mut image = QuantizedImage {
+        width,
+        height,
+        background,
+        palettes,
+        attributes,
+        indices: Vec::new(),
+    };
+
+    let colour_palettes = image.colour_palettes();
+    image.indices = dither::map_pixels(pixels, width, dithering, |x, y| {
+        &colour_palettes[image.palette_at(x, y) as usize]
+    });
+
+    Ok(image)
+}
+
+impl QuantizedImage {
+    /// Width of the image in pixels
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Height of the image in pixels
+    #[must_use]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Index of the shared background colour in the NES master palette
+    #[must_use]
+    pub fn background(&self) -> u8 {
+        self.background
+    }
+
+    /// The three colours of each sub-palette as indices into the NES master palette
+    #[must_use]
+    pub fn palettes(&self) -> [[u8; 3]; NUM_PALETTES] {
+        self.palettes
+    }
+
+    /// The sub-palette used by each 16x16 region, row by row
+    #[must_use]
+    pub fn attributes(&self) -> &[u8] {
+        &self.attributes
+    }
+
+    /// The palette index (0 to 3) of every pixel, row by row
+    #[must_use]
+    pub fn indices(&self) -> &[u8] {
+        &self.indices
+    }
+
+    /// Sub-palette used by the region containing the pixel
+    #[must_use]
+    pub fn palette_at(&self, x: usize, y: usize) -> u8 {
+        let regions_per_row = self.width.div_ceil(REGION_SIZE);
+        self.attributes[(y / REGION_SIZE) * regions_per_row + x / REGION_SIZE]
+    }
+
+    /// The sub-palettes converted to RGB colours
+    #[must_use]
+    pub fn colour_palettes(&self) -> [ColourPalette; NUM_PALETTES] {
+        let background = nes_colour(self.background);
+
+        self.palettes.map(|[first, second, third]| {
+            ColourPalette::new(
+                background,
+                [nes_colour(first), nes_colour(second), nes_colour(third)],
+            )
+        })
+    }
+
+    /// The background palette RAM contents ($3F00 - $3F0F) for this image
+    #[must_use]
+    pub fn palette_bytes(&self) -> [u8; 16] {
+        let mut bytes = [self.background; 16];
+        for (chunk, palette) in bytes.chunks_exact_mut(4).zip(&self.palettes) {
+            chunk[1..].copy_from_slice(palette);
+        }
+
+        bytes
+    }
+
+    /// Split the image into 8x8 tiles, row by row
+    ///
+    /// Pixels outside of the image are filled with the background
+    #[must_use]
+    pub fn tiles(&self) -> Vec<IndexedSprite> {
+        let tiles_per_row = self.width.div_ceil(SPRITE_WIDTH_HEIGHT);
+        let tiles_per_column = self.height.div_ceil(SPRITE_WIDTH_HEIGHT);
+
+        (0..tiles_per_row * tiles_per_column)
+            .map(|tile| {
+                let origin_x = (tile % tiles_per_row) * SPRITE_WIDTH_HEIGHT;
+                let origin_y = (tile / tiles_per_row) * SPRITE_WIDTH_HEIGHT;
+
+                let mut indices = [[0; SPRITE_WIDTH_HEIGHT]; SPRITE_WIDTH_HEIGHT];
+                for (y, row) in indices.iter_mut().enumerate() {
+                    for (x, index) in row.iter_mut().enumerate() {
+                        let (x, y) = (origin_x + x, origin_y + y);
+                        if x < self.width && y < self.height {
+                            *index = self.indices[y * self.width + x];
+                        }
+                    }
+                }
+
+                indices
+            })
+            .collect()
+    }
+
+    /// Encode the tiles of the image into CHR data
+    #[must_use]
+    pub fn to_chr(&self) -> Vec<u8> {
+        self.tiles().iter().flat_map(encode_indices).collect()
+    }
+}
@@ -0,0 +1,236 @@
+use crate::{
+    decode_tile,
+    pixel::{self, PixelFormat},
+    ColourPalette, Error, SPRITE_WIDTH_HEIGHT,
+};
+
+#[cfg(feature = "alloc")]
+use {crate::Colour, alloc::vec::Vec};
+
+/// Amount of tiles in one row of the nametable
+pub const NAMETABLE_WIDTH: usize = 32;
+/// Amount of tile rows in the nametable
+pub const NAMETABLE_HEIGHT: usize = 30;
+
+/// Width of the rendered screen in pixels
+pub const SCREEN_WIDTH: usize = NAMETABLE_WIDTH * SPRITE_WIDTH_HEIGHT;
+/// Height of the rendered screen in pixels
+pub const SCREEN_HEIGHT: usize = NAMETABLE_HEIGHT * SPRITE_WIDTH_HEIGHT;
+
+/// Size of the tile indices of a nametable in bytes
+pub const TILE_DATA_SIZE: usize = NAMETABLE_WIDTH * NAMETABLE_HEIGHT;
+/// Size of a nametable including the attribute table in bytes
+pub const NAMETABLE_SIZE: usize = 1024;
+
+// Every palette selection covers 2x2 tiles
+const BLOCKS_PER_ROW: usize = NAMETABLE_WIDTH / 2;
+const BLOCKS_PER_COLUMN: usize = NAMETABLE_HEIGHT / 2;
+
+// Every attribute byte covers 2x2 blocks
+const ATTRIBUTES_PER_ROW: usize = BLOCKS_PER_ROW / 2;
+
+/// One screen of background tiles, as described by a nametable
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Screen {
+    tiles: [u8; TILE_DATA_SIZE],
+    block_palettes: [u8; BLOCKS_PER_ROW * BLOCKS_PER_COLUMN],
+}
+
+impl Default for Screen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Screen {
+    /// Create a screen filled with tile 0 and palette 0
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            tiles: [0; TILE_DATA_SIZE],
+            block_palettes: [0; BLOCKS_PER_ROW * BLOCKS_PER_COLUMN],
+        }
+    }
+
+    /// Parse a nametable (tile indices followed by the attribute table)
+    #[must_use]
+    pub fn from_nametable(nametable: &[u8; NAMETABLE_SIZE]) -> Self {
+        let mut screen = Self::new();
+        screen.tiles.copy_from_slice(&nametable[..TILE_DATA_SIZE]);
+
+        for (block, palette) in screen.block_palettes.iter_mut().enumerate() {
+            let (block_x, block_y) = (block % BLOCKS_PER_ROW, block / BLOCKS_PER_ROW);
+            let attribute =
+                nametable[TILE_DATA_SIZE + (block_y / 2) * ATTRIBUTES_PER_ROW + block_x / 2];
+
+            *palette = (attribute >> attribute_shift(block_x, block_y)) & 0b11;
+        }
+
+        screen
+    }
+
+    /// Set the tile at the given tile coordinates
+    ///
+    /// # Panics
+    ///
+    /// Panics if the coordinates are outside of the 32x30 tile grid
+    pub fn set_tile(&mut self, x: usize, y: usize, tile: u8) -> &mut Self {
+        assert!(x < NAMETABLE_WIDTH && y < NAMETABLE_HEIGHT);
+
+        self.tiles[y * NAMETABLE_WIDTH + x] = tile;
+        self
+    }
+
+    /// Set all tiles at once, row by row
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::ImageSizeMismatch`] if there aren't exactly 960 tiles
+    pub fn set_tiles(&mut self, tiles: &[u8]) -> Result<&mut Self, Error> {
+        if tiles.len() != TILE_DATA_SIZE {
+            return Err(Error::ImageSizeMismatch {
+                expected: TILE_DATA_SIZE,
+                actual: tiles.len(),
+            });
+        }
+
+        self.tiles.copy_from_slice(tiles);
+        Ok(self)
+    }
+
+    /// Set the palette (0 to 3) of the 16x16 pixel block at the given block coordinates
+    ///
+    /// # Panics
+    ///
+    /// Panics if the coordinates are outside of the 16x15 block grid
+    pub fn set_block_palette(&mut self, block_x: usize, block_y: usize, palette: u8) -> &mut Self {
+        assert!(block_x < BLOCKS_PER_ROW && block_y < BLOCKS_PER_COLUMN);
+
+        self.block_palettes[block_y * BLOCKS_PER_ROW + block_x] = palette & 0b11;
+        self
+    }
+
+    /// The tile indices, row by row
+    #[must_use]
+    pub fn tiles(&self) -> &[u8; TILE_DATA_SIZE] {
+        &self.tiles
+    }
+
+    /// Palette used by the tile at the given tile coordinates
+    #[must_use]
+    pub fn palette_at(&self, x: usize, y: usize) -> u8 {
+        self.block_palettes[(y / 2) * BLOCKS_PER_ROW + x / 2]
+    }
+
+    /// Pack the block palettes into the 64 byte attribute table
+    #[must_use]
+    pub fn attribute_table(&self) -> [u8; NAMETABLE_SIZE - TILE_DATA_SIZE] {
+        let mut attribute_table = [0; NAMETABLE_SIZE - TILE_DATA_SIZE];
+
+        for (block, palette) in self.block_palettes.iter().enumerate() {
+            let (block_x, block_y) = (block % BLOCKS_PER_ROW, block / BLOCKS_PER_ROW);
+            attribute_table[(block_y / 2) * ATTRIBUTES_PER_ROW + block_x / 2] |=
+                palette << attribute_shift(block_x, block_y);
+        }
+
+        attribute_table
+    }
+
+    /// The nametable bytes (tile indices followed by the attribute table)
+    #[must_use]
+    pub fn nametable(&self) -> [u8; NAMETABLE_SIZE] {
+        let mut nametable = [0; NAMETABLE_SIZE];
+        nametable[..TILE_DATA_SIZE].copy_from_slice(&self.tiles);
+        nametable[TILE_DATA_SIZE..].copy_from_slice(&self.attribute_table());
+
+        nametable
+    }
+
+    /// Render the screen into a 256x240 image (row by row)
+    ///
+    /// `pattern_table` is the 4 KiB pattern table used for the background.
+    /// Tiles which aren't contained in the pattern table are drawn with the background colour.
+    #[cfg(feature = "alloc")]
+    #[must_use]
+    pub fn render(
+        &self,
+        pattern_table: &[u8],
+        colour_palettes: &[ColourPalette; 4],
+    ) -> Vec<Colour> {
+        self.render_with(colour_palettes, |x, y| {
+            (pattern_table, self.palette_at(x, y))
+        })
+    }
+
+    /// Render the screen with the pattern table and palette of every tile chosen by its tile coordinates
+    #[cfg(feature = "alloc")]
+    pub(crate) fn render_with<'p, F>(
+        &self,
+        colour_palettes: &[ColourPalette; 4],
+        attributes: F,
+    ) -> Vec<Colour>
+    where
+        F: Fn(usize, usize) -> (&'p [u8], u8),
+    {
+        let mut pixels = alloc::vec![Colour::default(); SCREEN_WIDTH * SCREEN_HEIGHT];
+
+        for (index, tile) in self.tiles.iter().enumerate() {
+            let (x, y) = (index % NAMETABLE_WIDTH, index / NAMETABLE_WIDTH);
+            let (pattern_table, palette) = attributes(x, y);
+            let colour_palette = &colour_palettes[usize::from(palette & 0b11)];
+            let indices = decode_tile(pattern_table, *tile);
+
+            for (row, index_row) in indices.iter().enumerate() {
+                let start =
+                    (y * SPRITE_WIDTH_HEIGHT + row) * SCREEN_WIDTH + x * SPRITE_WIDTH_HEIGHT;
+
+                for (pixel, index) in pixels[start..start + SPRITE_WIDTH_HEIGHT]
+                    .iter_mut()
+                    .zip(index_row)
+                {
+                    *pixel = colour_palette.colour(*index);
+                }
+            }
+        }
+
+        pixels
+    }
+
+    /// Render the screen directly into a 256x240 pixel buffer in the given pixel format
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::BufferTooSmall`] if the buffer can't hold the whole screen in the given format
+    pub fn render_into(
+        &self,
+        buffer: &mut [u8],
+        pattern_table: &[u8],
+        colour_palettes: &[ColourPalette; 4],
+        pixel_format: PixelFormat,
+    ) -> Result<(), Error> {
+        let bytes_per_pixel = pixel_format.bytes_per_pixel();
+        let stride = SCREEN_WIDTH * bytes_per_pixel;
+        pixel::check_buffer_size(buffer, stride * SCREEN_HEIGHT)?;
+
+        for (index, tile) in self.tiles.iter().enumerate() {
+            let (x, y) = (index % NAMETABLE_WIDTH, index / NAMETABLE_WIDTH);
+            let offset =
+                y * SPRITE_WIDTH_HEIGHT * stride + x * SPRITE_WIDTH_HEIGHT * bytes_per_pixel;
+
+            pixel::write_sprite(
+                &mut buffer[offset..],
+                stride,
+                &decode_tile(pattern_table, *tile),
+                &colour_palettes[self.palette_at(x, y) as usize],
+                pixel_format,
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Position of the two palette bits of a block inside of its attribute byte
+fn attribute_shift(block_x: usize, block_y: usize) -> usize {
+    ((block_y % 2) * 2 + block_x % 2) * 2
+}
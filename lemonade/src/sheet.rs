@@ -0,0 +1,118 @@
+use {
+    crate::{Colour, ColourPalette, Lemonade, SPRITE_WIDTH_HEIGHT},
+    alloc::vec::Vec,
+};
+
+/// Image containing multiple sprites laid out in a grid
+#[derive(Clone, Debug)]
+pub struct Sheet {
+    width: usize,
+    height: usize,
+    pixels: Vec<Colour>,
+}
+
+impl Sheet {
+    /// Render the sprites into a sheet which is `sprites_per_row` sprites wide
+    ///
+    /// Empty spaces in the last row are filled with the background colour of the palette
+    #[must_use]
+    pub fn render(
+        sprites: Lemonade<'_>,
+        colour_palette: ColourPalette,
+        sprites_per_row: usize,
+    ) -> Self {
+        let sprites_per_row = sprites_per_row.max(1);
+        let rows = sprites.num_sprites().div_ceil(sprites_per_row);
+
+        let width = sprites_per_row * SPRITE_WIDTH_HEIGHT;
+        let height = rows * SPRITE_WIDTH_HEIGHT;
+        let mut pixels = alloc::vec![colour_palette.colour(0); width * height];
+
+        for (index, sprite) in sprites.enumerate() {
+            let origin_x = (index % sprites_per_row) * SPRITE_WIDTH_HEIGHT;
+            let origin_y = (index / sprites_per_row) * SPRITE_WIDTH_HEIGHT;
+
+            for (y, row) in sprite.to_rgb(colour_palette).iter().enumerate() {
+                let start = (origin_y + y) * width + origin_x;
+                pixels[start..start + SPRITE_WIDTH_HEIGHT].copy_from_slice(row);
+            }
+        }
+
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub(crate) fn from_pixels(width: usize, height: usize, pixels: Vec<Colour>) -> Self {
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Width of the sheet in pixels
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Height of the sheet in pixels
+    #[must_use]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Colours of the sheet, row by row
+    #[must_use]
+    pub fn pixels(&self) -> &[Colour] {
+        &self.pixels
+    }
+
+    /// Enlarge the sheet by an integer factor, every pixel becomes a `factor` x `factor` square
+    #[must_use]
+    pub fn scale(&self, factor: usize) -> Self {
+        let factor = factor.max(1);
+        let width = self.width * factor;
+        let pixels = self
+            .pixels
+            .chunks(self.width.max(1))
+            .flat_map(|row| {
+                let row = row
+                    .iter()
+                    .flat_map(|&colour| core::iter::repeat_n(colour, factor))
+                    .collect::<Vec<_>>();
+                core::iter::repeat_n(row, factor).flatten()
+            })
+            .collect();
+
+        Self {
+            width,
+            height: self.height * factor,
+            pixels,
+        }
+    }
+
+    /// Raw RGB values of the sheet, row by row
+    #[must_use]
+    pub fn to_rgb(&self) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|colour| colour.raw_colour())
+            .collect()
+    }
+
+    /// Raw RGBA values of the sheet, row by row (every pixel is fully opaque)
+    #[must_use]
+    pub fn to_rgba(&self) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|colour| {
+                let [r, g, b] = colour.raw_colour();
+                [r, g, b, u8::MAX]
+            })
+            .collect()
+    }
+}
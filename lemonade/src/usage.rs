@@ -0,0 +1,80 @@
+use {
+    crate::{Screen, TILE_DATA_SIZE},
+    alloc::vec::Vec,
+};
+
+const NUM_TILES: usize = 256;
+
+/// Statistics about how often each tile of a pattern table is referenced by nametables
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TileUsage {
+    counts: [u32; NUM_TILES],
+}
+
+impl Default for TileUsage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TileUsage {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            counts: [0; NUM_TILES],
+        }
+    }
+
+    /// Count the tiles of a raw nametable
+    ///
+    /// Only the 960 bytes of tile indices are counted, the attribute table is ignored
+    pub fn add_nametable(&mut self, nametable: &[u8]) -> &mut Self {
+        for tile in nametable.iter().take(TILE_DATA_SIZE) {
+            self.counts[*tile as usize] += 1;
+        }
+
+        self
+    }
+
+    /// Count the tiles of a screen
+    pub fn add_screen(&mut self, screen: &Screen) -> &mut Self {
+        self.add_nametable(screen.tiles())
+    }
+
+    /// How often the tile is referenced
+    #[must_use]
+    pub fn count(&self, tile: u8) -> u32 {
+        self.counts[tile as usize]
+    }
+
+    /// The tiles which are referenced at least once, most used tile first
+    #[must_use]
+    pub fn used_tiles(&self) -> Vec<(u8, u32)> {
+        let mut used_tiles: Vec<(u8, u32)> = (0..=u8::MAX)
+            .map(|tile| (tile, self.count(tile)))
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        used_tiles.sort_by(|(_, first), (_, second)| second.cmp(first));
+
+        used_tiles
+    }
+
+    /// The tiles which are never referenced and can be safely overwritten
+    pub fn unused_tiles(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..=u8::MAX).filter(move |tile| self.count(*tile) == 0)
+    }
+}
+
+/// Collect the tile usage statistics of multiple screens
+#[must_use]
+pub fn tile_usage<'a, I>(screens: I) -> TileUsage
+where
+    I: IntoIterator<Item = &'a Screen>,
+{
+    let mut usage = TileUsage::new();
+    for screen in screens {
+        usage.add_screen(screen);
+    }
+
+    usage
+}
@@ -0,0 +1,106 @@
+//!
+//! JavaScript bindings via `wasm-bindgen`
+//!
+//! Palettes are passed as 12 bytes (the RGB values of the background colour followed by the three palette colours)
+//! All functions return an error if the passed sprite or palette data has the wrong length
+//!
+
+#![allow(clippy::missing_errors_doc)]
+
+use {
+    crate::{
+        Colour, ColourPalette, Lemonade, PixelFormat, PlaneLayout, Sprite, SPRITE_SIZE,
+        SPRITE_WIDTH_HEIGHT,
+    },
+    alloc::{string::ToString, vec, vec::Vec},
+    wasm_bindgen::prelude::*,
+};
+
+fn parse_palette(palette: &[u8]) -> Result<ColourPalette, JsError> {
+    if palette.len() != 12 {
+        return Err(JsError::new(
+            "The palette has to consist of exactly 12 bytes",
+        ));
+    }
+
+    let colour = |index: usize| {
+        Colour::new(
+            palette[index * 3],
+            palette[index * 3 + 1],
+            palette[index * 3 + 2],
+        )
+    };
+
+    Ok(ColourPalette::new(
+        colour(0),
+        [colour(1), colour(2), colour(3)],
+    ))
+}
+
+fn parse_sprite(data: &[u8]) -> Result<Sprite<'_>, JsError> {
+    if data.len() != SPRITE_SIZE {
+        return Err(JsError::new("A sprite has to consist of exactly 16 bytes"));
+    }
+
+    Ok(Sprite {
+        raw_sprite_data: data,
+        plane_layout: PlaneLayout::Planar,
+    })
+}
+
+/// Decode a sprite into its 64 palette indices
+#[wasm_bindgen(js_name = decodeSprite)]
+pub fn decode_sprite(data: &[u8]) -> Result<Vec<u8>, JsError> {
+    let indices = parse_sprite(data)?.to_indices();
+
+    Ok(indices.iter().flatten().copied().collect())
+}
+
+/// Decode a sprite and apply the palette, returning 8x8 RGBA pixels
+#[wasm_bindgen(js_name = spriteToRgba)]
+pub fn sprite_to_rgba(data: &[u8], palette: &[u8]) -> Result<Vec<u8>, JsError> {
+    let rgb_sprite = parse_sprite(data)?.to_rgb(parse_palette(palette)?);
+
+    Ok(rgb_sprite
+        .iter()
+        .flatten()
+        .flat_map(|colour| {
+            let [r, g, b] = colour.raw_colour();
+            [r, g, b, u8::MAX]
+        })
+        .collect())
+}
+
+/// Count the sprites contained in the CHR data
+#[wasm_bindgen(js_name = numSprites)]
+#[must_use]
+pub fn num_sprites(chr_data: &[u8]) -> usize {
+    Lemonade::new(chr_data).num_sprites()
+}
+
+/// Render all sprites of the CHR data into one RGBA sheet which is `sprites_per_row` sprites wide
+///
+/// The height of the sheet is `ceil(numSprites / spritesPerRow) * 8` pixels
+#[wasm_bindgen(js_name = renderSheet)]
+pub fn render_sheet(
+    chr_data: &[u8],
+    palette: &[u8],
+    sprites_per_row: usize,
+) -> Result<Vec<u8>, JsError> {
+    let sprites = Lemonade::new(chr_data);
+    let sprites_per_row = sprites_per_row.max(1);
+    let rows = sprites.num_sprites().div_ceil(sprites_per_row);
+
+    let pixel_count = rows * sprites_per_row * SPRITE_WIDTH_HEIGHT * SPRITE_WIDTH_HEIGHT;
+    let mut buffer = vec![0; pixel_count * PixelFormat::Rgba8.bytes_per_pixel()];
+    sprites
+        .decode_all_into(
+            &mut buffer,
+            parse_palette(palette)?,
+            PixelFormat::Rgba8,
+            sprites_per_row,
+        )
+        .map_err(|err| JsError::new(&err.to_string()))?;
+
+    Ok(buffer)
+}
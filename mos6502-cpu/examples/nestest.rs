@@ -0,0 +1,80 @@
+use {
+    mos6502_cpu::{compare::Column, Bus, Cpu, TraceComparator},
+    std::{
+        env,
+        fs::{self, File},
+    },
+};
+
+/// NROM cartridge with 2 KiB of RAM; everything else reads as zero
+struct Nrom {
+    ram: [u8; 0x800],
+    prg_rom: Vec<u8>,
+}
+
+impl Bus for Nrom {
+    fn read(&mut self, address: u16) -> u8 {
+        match address {
+            0x0000..=0x1FFF => self.ram[usize::from(address) % self.ram.len()],
+            0x8000..=0xFFFF => self.prg_rom[usize::from(address - 0x8000) % self.prg_rom.len()],
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        if address < 0x2000 {
+            self.ram[usize::from(address) % self.ram.len()] = value;
+        }
+    }
+}
+
+// Usage: nestest <rom> [start address in hex] [instructions] [expected log]
+// `nestest nestest.nes C000 8991` produces a log comparable to the one of nestest,
+// `nestest nestest.nes C000 8991 nestest.log` compares it against the log line by line
+fn main() {
+    let mut args = env::args().skip(1);
+    let mut file = File::open(args.next().unwrap()).unwrap();
+    let start = args
+        .next()
+        .map(|start| u16::from_str_radix(&start, 16).unwrap());
+    let instructions = args.next().map_or(100, |count| count.parse().unwrap());
+    let expected_log = args.next().map(|path| fs::read_to_string(path).unwrap());
+    let mut expected_lines = expected_log.as_deref().map(str::lines);
+    // There's no PPU to tell its position
+    let mut comparator = TraceComparator::new().ignore(Column::Ppu);
+
+    let ines = ines_parser::Ines::from_reader(&mut file).unwrap();
+    let mut bus = Nrom {
+        ram: [0; 0x800],
+        prg_rom: ines.prg_rom.into_owned(),
+    };
+
+    let mut cpu = Cpu::new();
+    cpu.reset(&mut bus);
+    if let Some(start) = start {
+        cpu.pc = start;
+    }
+
+    for _ in 0..instructions {
+        let line = cpu.trace(|address| bus.read(address)).to_string();
+        match expected_lines.as_mut().map(Iterator::next) {
+            Some(Some(expected)) => {
+                if let Err(divergence) = comparator.check(expected, &line) {
+                    println!("{divergence}");
+                    return;
+                }
+            }
+            Some(None) => break,
+            None => println!("{line}"),
+        }
+
+        if let Err(err) = cpu.step(&mut bus) {
+            println!("{err}");
+            break;
+        }
+    }
+
+    if expected_log.is_some() {
+        println!("{} lines match", comparator.lines());
+    }
+}
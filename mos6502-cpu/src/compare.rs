@@ -0,0 +1,289 @@
+//!
+//! Comparison of trace logs in the format of [`TraceLine`](crate::TraceLine)
+//!
+
+use {
+    alloc::{
+        collections::VecDeque,
+        string::{String, ToString},
+        vec::Vec,
+    },
+    core::fmt,
+};
+
+// The disassembly starts after the address and the bytes; the registers follow it
+const BYTES_START: usize = 6;
+const DISASSEMBLY_START: usize = 15;
+
+const DEFAULT_CONTEXT: usize = 5;
+
+/// Column of a trace log line
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Column {
+    Address,
+    Bytes,
+    /// Disassembled instruction including the values of the memory it accesses
+    Disassembly,
+    A,
+    X,
+    Y,
+    P,
+    Sp,
+    Ppu,
+    Cycles,
+}
+
+impl Column {
+    pub const ALL: [Self; 10] = [
+        Self::Address,
+        Self::Bytes,
+        Self::Disassembly,
+        Self::A,
+        Self::X,
+        Self::Y,
+        Self::P,
+        Self::Sp,
+        Self::Ppu,
+        Self::Cycles,
+    ];
+
+    // Label of the column in the register part of the line
+    fn label(self) -> Option<&'static str> {
+        match self {
+            Self::Address | Self::Bytes | Self::Disassembly => None,
+            Self::A => Some("A:"),
+            Self::X => Some("X:"),
+            Self::Y => Some("Y:"),
+            Self::P => Some("P:"),
+            Self::Sp => Some("SP:"),
+            Self::Ppu => Some("PPU:"),
+            Self::Cycles => Some("CYC:"),
+        }
+    }
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Address => "address",
+            Self::Bytes => "bytes",
+            Self::Disassembly => "disassembly",
+            Self::A => "A",
+            Self::X => "X",
+            Self::Y => "Y",
+            Self::P => "P",
+            Self::Sp => "SP",
+            Self::Ppu => "PPU",
+            Self::Cycles => "CYC",
+        })
+    }
+}
+
+// Start of the registers; the first `A:` after the disassembly
+fn registers_start(line: &str) -> usize {
+    line.get(DISASSEMBLY_START..)
+        .and_then(|rest| rest.find("A:"))
+        .map_or(line.len(), |offset| DISASSEMBLY_START + offset)
+}
+
+/// Value of the column in the line, without the label and surrounding whitespace
+///
+/// Returns `None` if the line doesn't contain the column, like lines without the position of the PPU
+#[must_use]
+pub fn column_value(line: &str, column: Column) -> Option<&str> {
+    let registers_start = registers_start(line);
+    let text = |range: core::ops::Range<usize>| {
+        line.get(range.start.min(registers_start)..range.end.min(registers_start))
+            .map(str::trim)
+            .filter(|text| !text.is_empty())
+    };
+
+    match column.label() {
+        None => match column {
+            Column::Address => text(0..4),
+            Column::Bytes => text(BYTES_START..DISASSEMBLY_START),
+            _ => text(DISASSEMBLY_START..registers_start),
+        },
+        Some(label) => {
+            let registers = &line[registers_start..];
+            // Labels are preceded by a space, except for the first one
+            let start = if registers.starts_with(label) {
+                0
+            } else {
+                registers.find(&[" ", label].concat())? + 1
+            } + label.len();
+            let value = &registers[start..];
+
+            // Values end where the next label starts; the one of the PPU contains spaces itself
+            let end = Column::ALL
+                .iter()
+                .filter_map(|column| column.label())
+                .filter_map(|label| value.find(&[" ", label].concat()))
+                .min()
+                .unwrap_or(value.len());
+            Some(value[..end].trim())
+        }
+    }
+}
+
+/// First line where two trace logs differ
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    /// Line number, starting at 1
+    pub line: usize,
+    /// Line of the expected log; `None` if the log ended before the other one
+    pub expected: Option<String>,
+    /// Line of the compared log; `None` if the log ended before the other one
+    pub actual: Option<String>,
+    /// Columns which differ, empty if one of the logs ended
+    pub columns: Vec<Column>,
+    /// Lines of the expected log preceding the divergence
+    pub context: Vec<String>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Trace logs diverge at line {}", self.line)?;
+        if !self.columns.is_empty() {
+            f.write_str(" (")?;
+            for (index, column) in self.columns.iter().enumerate() {
+                if index > 0 {
+                    f.write_str(", ")?;
+                }
+                write!(f, "{column}")?;
+            }
+            f.write_str(")")?;
+        }
+        writeln!(f)?;
+
+        for line in &self.context {
+            writeln!(f, "           {line}")?;
+        }
+        let end = "<end of log>";
+        writeln!(f, "Expected:  {}", self.expected.as_deref().unwrap_or(end))?;
+        write!(f, "Actual:    {}", self.actual.as_deref().unwrap_or(end))
+    }
+}
+
+/// Line by line comparison of a trace log against an expected one
+///
+/// Lines can either be checked one at a time, to compare a running emulator against a golden log,
+/// or two complete logs can be compared with [`TraceComparator::compare`].
+#[derive(Clone, Debug)]
+pub struct TraceComparator {
+    ignored: Vec<Column>,
+    context_size: usize,
+    context: VecDeque<String>,
+    line: usize,
+}
+
+impl Default for TraceComparator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TraceComparator {
+    /// Comparator checking all columns, reporting the last 5 lines before a divergence
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            ignored: Vec::new(),
+            context_size: DEFAULT_CONTEXT,
+            context: VecDeque::new(),
+            line: 0,
+        }
+    }
+
+    /// Don't compare the column, for example the PPU position of a log generated without a PPU
+    #[must_use]
+    pub fn ignore(mut self, column: Column) -> Self {
+        if !self.ignored.contains(&column) {
+            self.ignored.push(column);
+        }
+        self
+    }
+
+    /// Amount of lines preceding the divergence to report
+    #[must_use]
+    pub fn context(mut self, lines: usize) -> Self {
+        self.context_size = lines;
+        self
+    }
+
+    /// Amount of lines checked so far
+    #[must_use]
+    pub fn lines(&self) -> usize {
+        self.line
+    }
+
+    /// Columns of the line which differ, leaving out the ignored ones
+    #[must_use]
+    pub fn differences(&self, expected: &str, actual: &str) -> Vec<Column> {
+        Column::ALL
+            .iter()
+            .copied()
+            .filter(|column| !self.ignored.contains(column))
+            .filter(|column| column_value(expected, *column) != column_value(actual, *column))
+            .collect()
+    }
+
+    fn divergence(
+        &self,
+        expected: Option<&str>,
+        actual: Option<&str>,
+        columns: Vec<Column>,
+    ) -> Divergence {
+        Divergence {
+            line: self.line,
+            expected: expected.map(ToString::to_string),
+            actual: actual.map(ToString::to_string),
+            columns,
+            context: self.context.iter().cloned().collect(),
+        }
+    }
+
+    /// Check the next line of the log
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`Divergence`] if any of the compared columns differ
+    pub fn check(&mut self, expected: &str, actual: &str) -> Result<(), Divergence> {
+        self.line += 1;
+
+        let columns = self.differences(expected, actual);
+        if !columns.is_empty() {
+            return Err(self.divergence(Some(expected), Some(actual), columns));
+        }
+
+        if self.context_size > 0 {
+            if self.context.len() == self.context_size {
+                self.context.pop_front();
+            }
+            self.context.push_back(expected.to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Compare two complete logs, returning the amount of matching lines
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`Divergence`] at the first line which differs, or where one of the logs ends before the other
+    pub fn compare(&mut self, expected: &str, actual: &str) -> Result<usize, Divergence> {
+        let mut expected_lines = expected.lines();
+        let mut actual_lines = actual.lines();
+
+        loop {
+            match (expected_lines.next(), actual_lines.next()) {
+                (Some(expected), Some(actual)) => self.check(expected, actual)?,
+                (None, None) => return Ok(self.line),
+                (expected, actual) => {
+                    self.line += 1;
+                    return Err(self.divergence(expected, actual, Vec::new()));
+                }
+            }
+        }
+    }
+}
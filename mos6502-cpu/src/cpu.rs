@@ -0,0 +1,592 @@
+use {
+    crate::{Bus, Error, Status},
+    mos6502_dasm::{lookup, AddressingMode, Mnemonic, Opcode},
+    nes_state::{Savestate, StateReader, StateWriter},
+};
+
+/// Address of the NMI vector
+pub const NMI_VECTOR: u16 = 0xFFFA;
+
+/// Address of the RESET vector
+pub const RESET_VECTOR: u16 = 0xFFFC;
+
+/// Address of the IRQ/BRK vector
+pub const IRQ_VECTOR: u16 = 0xFFFE;
+
+const STACK_PAGE: u16 = 0x0100;
+
+/// State of the CPU
+///
+/// The registers are public so debuggers and tests can inspect and modify them directly
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cpu {
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    /// Stack pointer (offset into page 1)
+    pub s: u8,
+    pub p: Status,
+    pub pc: u16,
+    cycles: u64,
+    nmi_pending: bool,
+    irq_line: bool,
+}
+
+impl Default for Cpu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Cpu {
+    /// Create a CPU in the power-up state; call [`Cpu::reset`] to load the program counter
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            a: 0,
+            x: 0,
+            y: 0,
+            s: 0,
+            p: Status::default(),
+            pc: 0,
+            cycles: 0,
+            nmi_pending: false,
+            irq_line: false,
+        }
+    }
+
+    /// Amount of cycles executed since power-up
+    #[must_use]
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Account for cycles the CPU was halted for, like during a DMA, which the rest of the system performed
+    pub fn stall(&mut self, cycles: u64) {
+        self.cycles += cycles;
+    }
+
+    /// Signal a falling edge on the NMI line; the NMI is taken before the next instruction
+    pub fn nmi(&mut self) {
+        self.nmi_pending = true;
+    }
+
+    /// Set the level of the IRQ line; the IRQ is taken before the next instruction while
+    /// the line is asserted and interrupts aren't disabled
+    pub fn set_irq(&mut self, asserted: bool) {
+        self.irq_line = asserted;
+    }
+
+    /// Run the 7 cycle reset sequence and jump to the address in the RESET vector
+    pub fn reset<B: Bus + ?Sized>(&mut self, bus: &mut B) {
+        self.read(bus, self.pc);
+        self.read(bus, self.pc);
+
+        // The reset sequence is an interrupt with the writes to the stack turned into reads
+        for _ in 0..3 {
+            self.read(bus, STACK_PAGE | u16::from(self.s));
+            self.s = self.s.wrapping_sub(1);
+        }
+
+        self.p |= Status::INTERRUPT_DISABLE;
+        self.nmi_pending = false;
+        self.pc = self.read_word(bus, RESET_VECTOR);
+    }
+
+    /// Handle a pending interrupt or execute the next instruction, returning the amount of cycles it took
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UnknownOpcode`] if the opcode isn't part of the official instruction set.
+    /// The program counter stays at the opcode in that case.
+    pub fn step<B: Bus + ?Sized>(&mut self, bus: &mut B) -> Result<u64, Error> {
+        let start = self.cycles;
+
+        if self.nmi_pending {
+            self.nmi_pending = false;
+            self.interrupt(bus, NMI_VECTOR);
+        } else if self.irq_line && !self.p.contains(Status::INTERRUPT_DISABLE) {
+            self.interrupt(bus, IRQ_VECTOR);
+        } else {
+            self.execute(bus)?;
+        }
+
+        Ok(self.cycles - start)
+    }
+
+    fn read<B: Bus + ?Sized>(&mut self, bus: &mut B, address: u16) -> u8 {
+        self.cycles += 1;
+        bus.read(address)
+    }
+
+    fn write<B: Bus + ?Sized>(&mut self, bus: &mut B, address: u16, value: u8) {
+        self.cycles += 1;
+        bus.write(address, value);
+    }
+
+    fn read_word<B: Bus + ?Sized>(&mut self, bus: &mut B, address: u16) -> u16 {
+        let low = self.read(bus, address);
+        let high = self.read(bus, address.wrapping_add(1));
+        u16::from_le_bytes([low, high])
+    }
+
+    fn fetch<B: Bus + ?Sized>(&mut self, bus: &mut B) -> u8 {
+        let value = self.read(bus, self.pc);
+        self.pc = self.pc.wrapping_add(1);
+        value
+    }
+
+    fn fetch_word<B: Bus + ?Sized>(&mut self, bus: &mut B) -> u16 {
+        let low = self.fetch(bus);
+        let high = self.fetch(bus);
+        u16::from_le_bytes([low, high])
+    }
+
+    fn push<B: Bus + ?Sized>(&mut self, bus: &mut B, value: u8) {
+        self.write(bus, STACK_PAGE | u16::from(self.s), value);
+        self.s = self.s.wrapping_sub(1);
+    }
+
+    fn pull<B: Bus + ?Sized>(&mut self, bus: &mut B) -> u8 {
+        self.s = self.s.wrapping_add(1);
+        self.read(bus, STACK_PAGE | u16::from(self.s))
+    }
+
+    fn push_word<B: Bus + ?Sized>(&mut self, bus: &mut B, value: u16) {
+        let [low, high] = value.to_le_bytes();
+        self.push(bus, high);
+        self.push(bus, low);
+    }
+
+    fn interrupt<B: Bus + ?Sized>(&mut self, bus: &mut B, vector: u16) {
+        self.read(bus, self.pc);
+        self.read(bus, self.pc);
+        self.push_word(bus, self.pc);
+        self.push(bus, (self.p | Status::UNUSED).0);
+        self.p |= Status::INTERRUPT_DISABLE;
+        self.pc = self.read_word(bus, vector);
+    }
+
+    fn set_zero_negative(&mut self, value: u8) {
+        self.p.set(Status::ZERO, value == 0);
+        self.p.set(Status::NEGATIVE, value & 0x80 != 0);
+    }
+
+    /// Effective address of the operand, performing the dummy reads of the indexed addressing modes
+    ///
+    /// Indexed reads only perform a dummy read from the wrong page if the index crosses a page boundary,
+    /// while writes and read-modify-write instructions always perform it
+    fn operand_address<B: Bus + ?Sized>(
+        &mut self,
+        bus: &mut B,
+        mode: AddressingMode,
+        is_write: bool,
+    ) -> u16 {
+        match mode {
+            AddressingMode::ZeroPage => u16::from(self.fetch(bus)),
+            AddressingMode::ZeroPageX | AddressingMode::ZeroPageY => {
+                let base = self.fetch(bus);
+                self.read(bus, u16::from(base));
+
+                let index = if mode == AddressingMode::ZeroPageX {
+                    self.x
+                } else {
+                    self.y
+                };
+                u16::from(base.wrapping_add(index))
+            }
+            AddressingMode::Absolute => self.fetch_word(bus),
+            AddressingMode::AbsoluteX => {
+                let base = self.fetch_word(bus);
+                self.indexed(bus, base, self.x, is_write)
+            }
+            AddressingMode::AbsoluteY => {
+                let base = self.fetch_word(bus);
+                self.indexed(bus, base, self.y, is_write)
+            }
+            AddressingMode::IndirectX => {
+                let pointer = self.fetch(bus);
+                self.read(bus, u16::from(pointer));
+
+                let pointer = pointer.wrapping_add(self.x);
+                let low = self.read(bus, u16::from(pointer));
+                let high = self.read(bus, u16::from(pointer.wrapping_add(1)));
+                u16::from_le_bytes([low, high])
+            }
+            AddressingMode::IndirectY => {
+                let pointer = self.fetch(bus);
+                let low = self.read(bus, u16::from(pointer));
+                let high = self.read(bus, u16::from(pointer.wrapping_add(1)));
+                self.indexed(bus, u16::from_le_bytes([low, high]), self.y, is_write)
+            }
+            AddressingMode::Implied
+            | AddressingMode::Accumulator
+            | AddressingMode::Immediate
+            | AddressingMode::Indirect
+            | AddressingMode::Relative => {
+                unreachable!("{:?} doesn't address memory", mode)
+            }
+        }
+    }
+
+    fn indexed<B: Bus + ?Sized>(
+        &mut self,
+        bus: &mut B,
+        base: u16,
+        index: u8,
+        is_write: bool,
+    ) -> u16 {
+        let address = base.wrapping_add(u16::from(index));
+        if is_write || address & 0xFF00 != base & 0xFF00 {
+            self.read(bus, (base & 0xFF00) | (address & 0x00FF));
+        }
+
+        address
+    }
+
+    fn read_operand<B: Bus + ?Sized>(&mut self, bus: &mut B, mode: AddressingMode) -> u8 {
+        if mode == AddressingMode::Immediate {
+            return self.fetch(bus);
+        }
+
+        let address = self.operand_address(bus, mode, false);
+        self.read(bus, address)
+    }
+
+    fn write_operand<B: Bus + ?Sized>(&mut self, bus: &mut B, mode: AddressingMode, value: u8) {
+        let address = self.operand_address(bus, mode, true);
+        self.write(bus, address, value);
+    }
+
+    /// Read-modify-write instruction; the unmodified value gets written back before the modified one
+    fn modify<B: Bus + ?Sized>(
+        &mut self,
+        bus: &mut B,
+        mode: AddressingMode,
+        operation: fn(&mut Self, u8) -> u8,
+    ) {
+        if mode == AddressingMode::Accumulator {
+            self.read(bus, self.pc);
+            self.a = operation(self, self.a);
+            return;
+        }
+
+        let address = self.operand_address(bus, mode, true);
+        let value = self.read(bus, address);
+        self.write(bus, address, value);
+
+        let value = operation(self, value);
+        self.write(bus, address, value);
+    }
+
+    fn branch<B: Bus + ?Sized>(&mut self, bus: &mut B, condition: bool) {
+        let offset = self.fetch(bus);
+        if !condition {
+            return;
+        }
+
+        self.read(bus, self.pc);
+
+        let target = self
+            .pc
+            .wrapping_add(u16::from(offset))
+            .wrapping_sub(if offset & 0x80 == 0 { 0 } else { 0x100 });
+        if target & 0xFF00 != self.pc & 0xFF00 {
+            self.read(bus, (self.pc & 0xFF00) | (target & 0x00FF));
+        }
+
+        self.pc = target;
+    }
+
+    fn add(&mut self, value: u8) {
+        let sum = u16::from(self.a) + u16::from(value) + u16::from(self.p.contains(Status::CARRY));
+        let [result, carry] = sum.to_le_bytes();
+
+        self.p.set(Status::CARRY, carry != 0);
+        self.p.set(
+            Status::OVERFLOW,
+            !(self.a ^ value) & (self.a ^ result) & 0x80 != 0,
+        );
+        self.a = result;
+        self.set_zero_negative(result);
+    }
+
+    fn compare(&mut self, register: u8, value: u8) {
+        self.p.set(Status::CARRY, register >= value);
+        self.set_zero_negative(register.wrapping_sub(value));
+    }
+
+    fn shift_left(&mut self, value: u8, carry_in: bool) -> u8 {
+        let result = (value << 1) | u8::from(carry_in);
+        self.p.set(Status::CARRY, value & 0x80 != 0);
+        self.set_zero_negative(result);
+        result
+    }
+
+    fn shift_right(&mut self, value: u8, carry_in: bool) -> u8 {
+        let result = (value >> 1) | (u8::from(carry_in) << 7);
+        self.p.set(Status::CARRY, value & 0x01 != 0);
+        self.set_zero_negative(result);
+        result
+    }
+
+    // Only advances over the dummy read of single byte instructions
+    fn implied<B: Bus + ?Sized>(&mut self, bus: &mut B) {
+        self.read(bus, self.pc);
+    }
+
+    // One arm per mnemonic reads better than splitting the dispatch up
+    #[allow(clippy::too_many_lines)]
+    fn execute<B: Bus + ?Sized>(&mut self, bus: &mut B) -> Result<(), Error> {
+        let address = self.pc;
+        let opcode = self.fetch(bus);
+        let Some(Opcode { mnemonic, mode }) = lookup(opcode) else {
+            self.pc = address;
+            return Err(Error::UnknownOpcode { opcode, address });
+        };
+
+        match mnemonic {
+            Mnemonic::Lda => {
+                self.a = self.read_operand(bus, mode);
+                self.set_zero_negative(self.a);
+            }
+            Mnemonic::Ldx => {
+                self.x = self.read_operand(bus, mode);
+                self.set_zero_negative(self.x);
+            }
+            Mnemonic::Ldy => {
+                self.y = self.read_operand(bus, mode);
+                self.set_zero_negative(self.y);
+            }
+            Mnemonic::Sta => self.write_operand(bus, mode, self.a),
+            Mnemonic::Stx => self.write_operand(bus, mode, self.x),
+            Mnemonic::Sty => self.write_operand(bus, mode, self.y),
+
+            Mnemonic::Adc => {
+                let value = self.read_operand(bus, mode);
+                self.add(value);
+            }
+            Mnemonic::Sbc => {
+                let value = self.read_operand(bus, mode);
+                self.add(!value);
+            }
+            Mnemonic::And => {
+                self.a &= self.read_operand(bus, mode);
+                self.set_zero_negative(self.a);
+            }
+            Mnemonic::Ora => {
+                self.a |= self.read_operand(bus, mode);
+                self.set_zero_negative(self.a);
+            }
+            Mnemonic::Eor => {
+                self.a ^= self.read_operand(bus, mode);
+                self.set_zero_negative(self.a);
+            }
+            Mnemonic::Bit => {
+                let value = self.read_operand(bus, mode);
+                self.p.set(Status::ZERO, self.a & value == 0);
+                self.p.set(Status::OVERFLOW, value & 0x40 != 0);
+                self.p.set(Status::NEGATIVE, value & 0x80 != 0);
+            }
+            Mnemonic::Cmp => {
+                let value = self.read_operand(bus, mode);
+                self.compare(self.a, value);
+            }
+            Mnemonic::Cpx => {
+                let value = self.read_operand(bus, mode);
+                self.compare(self.x, value);
+            }
+            Mnemonic::Cpy => {
+                let value = self.read_operand(bus, mode);
+                self.compare(self.y, value);
+            }
+
+            Mnemonic::Asl => self.modify(bus, mode, |cpu, value| cpu.shift_left(value, false)),
+            Mnemonic::Rol => self.modify(bus, mode, |cpu, value| {
+                cpu.shift_left(value, cpu.p.contains(Status::CARRY))
+            }),
+            Mnemonic::Lsr => self.modify(bus, mode, |cpu, value| cpu.shift_right(value, false)),
+            Mnemonic::Ror => self.modify(bus, mode, |cpu, value| {
+                cpu.shift_right(value, cpu.p.contains(Status::CARRY))
+            }),
+            Mnemonic::Inc => self.modify(bus, mode, |cpu, value| {
+                let value = value.wrapping_add(1);
+                cpu.set_zero_negative(value);
+                value
+            }),
+            Mnemonic::Dec => self.modify(bus, mode, |cpu, value| {
+                let value = value.wrapping_sub(1);
+                cpu.set_zero_negative(value);
+                value
+            }),
+
+            Mnemonic::Bcc => self.branch(bus, !self.p.contains(Status::CARRY)),
+            Mnemonic::Bcs => self.branch(bus, self.p.contains(Status::CARRY)),
+            Mnemonic::Bne => self.branch(bus, !self.p.contains(Status::ZERO)),
+            Mnemonic::Beq => self.branch(bus, self.p.contains(Status::ZERO)),
+            Mnemonic::Bpl => self.branch(bus, !self.p.contains(Status::NEGATIVE)),
+            Mnemonic::Bmi => self.branch(bus, self.p.contains(Status::NEGATIVE)),
+            Mnemonic::Bvc => self.branch(bus, !self.p.contains(Status::OVERFLOW)),
+            Mnemonic::Bvs => self.branch(bus, self.p.contains(Status::OVERFLOW)),
+
+            Mnemonic::Jmp => {
+                let target = self.fetch_word(bus);
+                self.pc = if mode == AddressingMode::Indirect {
+                    // The high byte of the pointer doesn't get incremented
+                    let low = self.read(bus, target);
+                    let high =
+                        self.read(bus, (target & 0xFF00) | (target.wrapping_add(1) & 0x00FF));
+                    u16::from_le_bytes([low, high])
+                } else {
+                    target
+                };
+            }
+            Mnemonic::Jsr => {
+                let low = self.fetch(bus);
+                self.read(bus, STACK_PAGE | u16::from(self.s));
+                // The pushed address points to the last byte of the instruction
+                self.push_word(bus, self.pc);
+                let high = self.read(bus, self.pc);
+                self.pc = u16::from_le_bytes([low, high]);
+            }
+            Mnemonic::Rts => {
+                self.implied(bus);
+                self.read(bus, STACK_PAGE | u16::from(self.s));
+                let low = self.pull(bus);
+                let high = self.pull(bus);
+                self.pc = u16::from_le_bytes([low, high]);
+                self.fetch(bus);
+            }
+            Mnemonic::Rti => {
+                self.implied(bus);
+                self.read(bus, STACK_PAGE | u16::from(self.s));
+                self.p = Status(self.pull(bus) & !Status::BREAK.0) | Status::UNUSED;
+                let low = self.pull(bus);
+                let high = self.pull(bus);
+                self.pc = u16::from_le_bytes([low, high]);
+            }
+            Mnemonic::Brk => {
+                // The byte after the opcode gets skipped
+                self.fetch(bus);
+                self.push_word(bus, self.pc);
+                self.push(bus, (self.p | Status::BREAK | Status::UNUSED).0);
+                self.p |= Status::INTERRUPT_DISABLE;
+                self.pc = self.read_word(bus, IRQ_VECTOR);
+            }
+
+            Mnemonic::Pha => {
+                self.implied(bus);
+                self.push(bus, self.a);
+            }
+            Mnemonic::Php => {
+                self.implied(bus);
+                self.push(bus, (self.p | Status::BREAK | Status::UNUSED).0);
+            }
+            Mnemonic::Pla => {
+                self.implied(bus);
+                self.read(bus, STACK_PAGE | u16::from(self.s));
+                self.a = self.pull(bus);
+                self.set_zero_negative(self.a);
+            }
+            Mnemonic::Plp => {
+                self.implied(bus);
+                self.read(bus, STACK_PAGE | u16::from(self.s));
+                self.p = Status(self.pull(bus) & !Status::BREAK.0) | Status::UNUSED;
+            }
+
+            Mnemonic::Clc => self.set_flag(bus, Status::CARRY, false),
+            Mnemonic::Sec => self.set_flag(bus, Status::CARRY, true),
+            Mnemonic::Cli => self.set_flag(bus, Status::INTERRUPT_DISABLE, false),
+            Mnemonic::Sei => self.set_flag(bus, Status::INTERRUPT_DISABLE, true),
+            Mnemonic::Cld => self.set_flag(bus, Status::DECIMAL, false),
+            Mnemonic::Sed => self.set_flag(bus, Status::DECIMAL, true),
+            Mnemonic::Clv => self.set_flag(bus, Status::OVERFLOW, false),
+
+            Mnemonic::Tax => {
+                self.implied(bus);
+                self.x = self.a;
+                self.set_zero_negative(self.x);
+            }
+            Mnemonic::Tay => {
+                self.implied(bus);
+                self.y = self.a;
+                self.set_zero_negative(self.y);
+            }
+            Mnemonic::Txa => {
+                self.implied(bus);
+                self.a = self.x;
+                self.set_zero_negative(self.a);
+            }
+            Mnemonic::Tya => {
+                self.implied(bus);
+                self.a = self.y;
+                self.set_zero_negative(self.a);
+            }
+            Mnemonic::Tsx => {
+                self.implied(bus);
+                self.x = self.s;
+                self.set_zero_negative(self.x);
+            }
+            Mnemonic::Txs => {
+                self.implied(bus);
+                self.s = self.x;
+            }
+            Mnemonic::Inx => {
+                self.implied(bus);
+                self.x = self.x.wrapping_add(1);
+                self.set_zero_negative(self.x);
+            }
+            Mnemonic::Iny => {
+                self.implied(bus);
+                self.y = self.y.wrapping_add(1);
+                self.set_zero_negative(self.y);
+            }
+            Mnemonic::Dex => {
+                self.implied(bus);
+                self.x = self.x.wrapping_sub(1);
+                self.set_zero_negative(self.x);
+            }
+            Mnemonic::Dey => {
+                self.implied(bus);
+                self.y = self.y.wrapping_sub(1);
+                self.set_zero_negative(self.y);
+            }
+            Mnemonic::Nop => self.implied(bus),
+        }
+
+        Ok(())
+    }
+
+    fn set_flag<B: Bus + ?Sized>(&mut self, bus: &mut B, flag: Status, value: bool) {
+        self.implied(bus);
+        self.p.set(flag, value);
+    }
+}
+
+impl Savestate for Cpu {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.a);
+        writer.write_u8(self.x);
+        writer.write_u8(self.y);
+        writer.write_u8(self.s);
+        writer.write_u8(self.p.0);
+        writer.write_u16(self.pc);
+        writer.write_u64(self.cycles);
+        writer.write_bool(self.nmi_pending);
+        writer.write_bool(self.irq_line);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), nes_state::Error> {
+        self.a = reader.read_u8()?;
+        self.x = reader.read_u8()?;
+        self.y = reader.read_u8()?;
+        self.s = reader.read_u8()?;
+        self.p = Status(reader.read_u8()?);
+        self.pc = reader.read_u16()?;
+        self.cycles = reader.read_u64()?;
+        self.nmi_pending = reader.read_bool()?;
+        self.irq_line = reader.read_bool()?;
+
+        Ok(())
+    }
+}
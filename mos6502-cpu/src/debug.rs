@@ -0,0 +1,292 @@
+//!
+//! Breakpoints, watchpoints and conditions to stop the execution at
+//!
+//! The [`Debugger`] wraps [`Cpu::step`] and watches the bus accesses of the instruction,
+//! so debugger frontends don't need any support from the rest of the system.
+//!
+
+use {
+    crate::{Bus, Cpu, Error},
+    alloc::{boxed::Box, collections::BTreeSet, vec::Vec},
+    core::{fmt, ops::RangeInclusive},
+};
+
+/// Kind of bus access
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+/// Range of addresses to stop at when they're read, written or both
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Watchpoint {
+    pub addresses: RangeInclusive<u16>,
+    pub read: bool,
+    pub write: bool,
+}
+
+impl Watchpoint {
+    /// Stop when any of the addresses is read
+    #[must_use]
+    pub fn read(addresses: RangeInclusive<u16>) -> Self {
+        Self {
+            addresses,
+            read: true,
+            write: false,
+        }
+    }
+
+    /// Stop when any of the addresses is written
+    #[must_use]
+    pub fn write(addresses: RangeInclusive<u16>) -> Self {
+        Self {
+            addresses,
+            read: false,
+            write: true,
+        }
+    }
+
+    /// Stop when any of the addresses is accessed
+    #[must_use]
+    pub fn access(addresses: RangeInclusive<u16>) -> Self {
+        Self {
+            addresses,
+            read: true,
+            write: true,
+        }
+    }
+
+    fn matches(&self, address: u16, access: Access) -> bool {
+        let kind = match access {
+            Access::Read => self.read,
+            Access::Write => self.write,
+        };
+        kind && self.addresses.contains(&address)
+    }
+}
+
+/// Reason the execution stopped
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Break {
+    /// The program counter reached a breakpoint; the instruction there wasn't executed yet
+    Breakpoint(u16),
+    /// The instruction accessed a watched address; it was executed completely
+    Watchpoint {
+        address: u16,
+        value: u8,
+        access: Access,
+    },
+    /// The condition with the ID became true after the instruction
+    Condition(usize),
+}
+
+impl fmt::Display for Break {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Breakpoint(address) => write!(f, "Breakpoint at ${address:04X}"),
+            Self::Watchpoint {
+                address,
+                value,
+                access: Access::Read,
+            } => write!(f, "Read of ${value:02X} from ${address:04X}"),
+            Self::Watchpoint {
+                address,
+                value,
+                access: Access::Write,
+            } => write!(f, "Write of ${value:02X} to ${address:04X}"),
+            Self::Condition(id) => write!(f, "Condition {id} met"),
+        }
+    }
+}
+
+/// Outcome of [`Debugger::step`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Step {
+    /// CPU cycles the step took; 0 if it stopped at a breakpoint
+    pub cycles: u64,
+    pub stop: Option<Break>,
+}
+
+type Condition = Box<dyn FnMut(&Cpu) -> bool>;
+
+// Bus passing every access on while looking out for watched addresses
+struct WatchedBus<'a, B: ?Sized> {
+    bus: &'a mut B,
+    watchpoints: &'a [Watchpoint],
+    hit: Option<Break>,
+}
+
+impl<B: Bus + ?Sized> WatchedBus<'_, B> {
+    fn check(&mut self, address: u16, value: u8, access: Access) {
+        if self.hit.is_none()
+            && self
+                .watchpoints
+                .iter()
+                .any(|watchpoint| watchpoint.matches(address, access))
+        {
+            self.hit = Some(Break::Watchpoint {
+                address,
+                value,
+                access,
+            });
+        }
+    }
+}
+
+impl<B: Bus + ?Sized> Bus for WatchedBus<'_, B> {
+    fn read(&mut self, address: u16) -> u8 {
+        let value = self.bus.read(address);
+        self.check(address, value, Access::Read);
+        value
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.check(address, value, Access::Write);
+        self.bus.write(address, value);
+    }
+}
+
+/// Execution control for a [`Cpu`], stopping at breakpoints, watchpoints and arbitrary conditions
+#[derive(Default)]
+pub struct Debugger {
+    breakpoints: BTreeSet<u16>,
+    watchpoints: Vec<Watchpoint>,
+    conditions: Vec<(usize, Condition)>,
+    next_condition: usize,
+    /// Breakpoint the execution stopped at, which the next step executes instead of stopping again
+    resume_at: Option<u16>,
+}
+
+impl fmt::Debug for Debugger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Debugger")
+            .field("breakpoints", &self.breakpoints)
+            .field("watchpoints", &self.watchpoints)
+            .field("conditions", &self.conditions.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Debugger {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stop before the instruction at the address gets executed
+    pub fn add_breakpoint(&mut self, address: u16) {
+        self.breakpoints.insert(address);
+    }
+
+    /// Returns whether the breakpoint existed
+    pub fn remove_breakpoint(&mut self, address: u16) -> bool {
+        self.breakpoints.remove(&address)
+    }
+
+    #[must_use]
+    pub fn breakpoints(&self) -> &BTreeSet<u16> {
+        &self.breakpoints
+    }
+
+    /// Stop after the instruction accessing one of the watched addresses
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        self.watchpoints.push(watchpoint);
+    }
+
+    /// Returns whether the watchpoint existed
+    pub fn remove_watchpoint(&mut self, watchpoint: &Watchpoint) -> bool {
+        let length = self.watchpoints.len();
+        self.watchpoints.retain(|existing| existing != watchpoint);
+        self.watchpoints.len() != length
+    }
+
+    #[must_use]
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    /// Stop after the first instruction leaving the CPU in a state the condition is true for
+    ///
+    /// Returns the ID of the condition, which is part of [`Break::Condition`]
+    pub fn add_condition<F>(&mut self, condition: F) -> usize
+    where
+        F: FnMut(&Cpu) -> bool + 'static,
+    {
+        let id = self.next_condition;
+        self.next_condition += 1;
+        self.conditions.push((id, Box::new(condition)));
+        id
+    }
+
+    /// Returns whether the condition existed
+    pub fn remove_condition(&mut self, id: usize) -> bool {
+        let length = self.conditions.len();
+        self.conditions.retain(|(existing, _)| *existing != id);
+        self.conditions.len() != length
+    }
+
+    /// Remove all breakpoints, watchpoints and conditions
+    pub fn clear(&mut self) {
+        self.breakpoints.clear();
+        self.watchpoints.clear();
+        self.conditions.clear();
+        self.resume_at = None;
+    }
+
+    /// Execute the next instruction (or interrupt) unless the program counter is at a breakpoint
+    ///
+    /// Stepping again after stopping at a breakpoint executes the instruction there.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of [`Cpu::step`]
+    pub fn step<B: Bus + ?Sized>(&mut self, cpu: &mut Cpu, bus: &mut B) -> Result<Step, Error> {
+        let resume_at = self.resume_at.take();
+        if self.breakpoints.contains(&cpu.pc) && resume_at != Some(cpu.pc) {
+            self.resume_at = Some(cpu.pc);
+            return Ok(Step {
+                cycles: 0,
+                stop: Some(Break::Breakpoint(cpu.pc)),
+            });
+        }
+
+        let mut bus = WatchedBus {
+            bus,
+            watchpoints: &self.watchpoints,
+            hit: None,
+        };
+        let cycles = cpu.step(&mut bus)?;
+
+        let stop = bus.hit.or_else(|| {
+            self.conditions
+                .iter_mut()
+                .find_map(|(id, condition)| condition(cpu).then_some(Break::Condition(*id)))
+        });
+
+        Ok(Step { cycles, stop })
+    }
+
+    /// Step until the execution stops, for at most the amount of CPU cycles
+    ///
+    /// Returns `None` if the cycles ran out first
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of [`Cpu::step`]
+    pub fn run<B: Bus + ?Sized>(
+        &mut self,
+        cpu: &mut Cpu,
+        bus: &mut B,
+        max_cycles: u64,
+    ) -> Result<Option<Break>, Error> {
+        let target = cpu.cycles() + max_cycles;
+        while cpu.cycles() < target {
+            if let Some(stop) = self.step(cpu, bus)?.stop {
+                return Ok(Some(stop));
+            }
+        }
+
+        Ok(None)
+    }
+}
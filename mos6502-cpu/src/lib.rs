@@ -0,0 +1,62 @@
+#![no_std]
+#![warn(clippy::all, clippy::pedantic)]
+
+//!
+//! Emulation core for the MOS 6502 as used by the Ricoh 2A03 of the NES
+//!
+//! Only the official instruction set is supported and, like on the 2A03, the decimal mode doesn't exist.
+//! Every cycle performs exactly one bus access, including the dummy accesses of the real hardware.
+//!
+
+extern crate alloc;
+
+use core::fmt;
+
+pub mod compare;
+mod cpu;
+pub mod debug;
+mod status;
+mod trace;
+
+pub use {
+    compare::{Divergence, TraceComparator},
+    cpu::{Cpu, IRQ_VECTOR, NMI_VECTOR, RESET_VECTOR},
+    debug::{Break, Debugger, Watchpoint},
+    status::Status,
+    trace::TraceLine,
+};
+
+/// Memory the CPU is connected to
+///
+/// Every call corresponds to one CPU cycle
+pub trait Bus {
+    fn read(&mut self, address: u16) -> u8;
+
+    fn write(&mut self, address: u16, value: u8);
+}
+
+impl<B: Bus + ?Sized> Bus for &mut B {
+    fn read(&mut self, address: u16) -> u8 {
+        (**self).read(address)
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        (**self).write(address, value);
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// The opcode isn't part of the official instruction set
+    UnknownOpcode { opcode: u8, address: u16 },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownOpcode { opcode, address } => {
+                write!(f, "Unknown opcode ${opcode:02X} at ${address:04X}")
+            }
+        }
+    }
+}
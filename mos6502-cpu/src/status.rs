@@ -0,0 +1,53 @@
+use core::ops::{BitOr, BitOrAssign};
+
+/// Processor status register (P)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Status(pub u8);
+
+impl Status {
+    pub const CARRY: Self = Self(0x01);
+    pub const ZERO: Self = Self(0x02);
+    pub const INTERRUPT_DISABLE: Self = Self(0x04);
+    /// Can be set and cleared, but has no effect on the 2A03
+    pub const DECIMAL: Self = Self(0x08);
+    /// Only exists in the copies pushed onto the stack by `BRK` and `PHP`
+    pub const BREAK: Self = Self(0x10);
+    /// Always reads back as set
+    pub const UNUSED: Self = Self(0x20);
+    pub const OVERFLOW: Self = Self(0x40);
+    pub const NEGATIVE: Self = Self(0x80);
+
+    #[must_use]
+    pub const fn contains(self, flag: Self) -> bool {
+        self.0 & flag.0 == flag.0
+    }
+
+    pub fn set(&mut self, flag: Self, value: bool) {
+        if value {
+            self.0 |= flag.0;
+        } else {
+            self.0 &= !flag.0;
+        }
+    }
+}
+
+impl Default for Status {
+    /// State after power-up
+    fn default() -> Self {
+        Self::UNUSED | Self::INTERRUPT_DISABLE
+    }
+}
+
+impl BitOr for Status {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for Status {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
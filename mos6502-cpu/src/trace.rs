@@ -0,0 +1,230 @@
+use {
+    crate::{Cpu, Status},
+    alloc::{format, string::String, vec::Vec},
+    core::fmt,
+    mos6502_dasm::{AddressingMode, Instruction, Mnemonic},
+};
+
+// Width of the column with the instruction bytes and the one with the disassembly
+const BYTES_WIDTH: usize = 8;
+const DISASSEMBLY_WIDTH: usize = 32;
+
+/// Memory accessed by the operand, printed after the disassembly
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Annotation {
+    None,
+    /// `LDA $0200 = 00`
+    Value(u8),
+    /// `LDA $0300,X @ 0301 = 89`
+    Indexed {
+        address: u16,
+        value: u8,
+    },
+    /// `JMP ($0200) = DB7E`
+    Pointer(u16),
+    /// `LDA ($80,X) @ 82 = 0200 = 5A`
+    IndexedIndirect {
+        pointer: u8,
+        address: u16,
+        value: u8,
+    },
+    /// `LDA ($89),Y = 0300 @ 0301 = 89`
+    IndirectIndexed {
+        base: u16,
+        address: u16,
+        value: u8,
+    },
+}
+
+/// Line of a trace log in the format of Nintendulator, which is the one of the log of `nestest`
+///
+/// `C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7`
+///
+/// The line describes the state before the instruction is executed. Operands accessing memory are followed
+/// by the effective address and the value found there, like `LDA ($80,X) @ 82 = 0200 = 5A`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TraceLine {
+    pub pc: u16,
+    /// Bytes of the instruction; only the first [`Instruction::len`] are part of it
+    pub bytes: [u8; 3],
+    pub instruction: Instruction,
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub p: Status,
+    pub s: u8,
+    pub cycles: u64,
+    /// Scanline and dot of the PPU, which the CPU doesn't know about
+    pub ppu: Option<(u16, u16)>,
+    annotation: Annotation,
+}
+
+// Word stored at the address, with the high byte wrapping around inside of the page like the CPU does
+fn peek_word<F: FnMut(u16) -> u8>(peek: &mut F, address: u16) -> u16 {
+    let high_address = (address & 0xFF00) | (address.wrapping_add(1) & 0x00FF);
+    u16::from_le_bytes([peek(address), peek(high_address)])
+}
+
+impl TraceLine {
+    /// Capture the instruction at the program counter and the state of the CPU
+    ///
+    /// `peek` reads memory for the disassembly and the annotations. It shouldn't have any side effects,
+    /// since the values of registers like `$2002` would otherwise change before the instruction reads them.
+    pub fn capture<F: FnMut(u16) -> u8>(cpu: &Cpu, mut peek: F) -> Self {
+        let bytes = [
+            peek(cpu.pc),
+            peek(cpu.pc.wrapping_add(1)),
+            peek(cpu.pc.wrapping_add(2)),
+        ];
+        // The slice is never empty
+        let instruction =
+            Instruction::decode(&bytes, cpu.pc).unwrap_or(Instruction::Unknown(bytes[0]));
+
+        let annotation = match instruction {
+            Instruction::Official { opcode, operand } => match opcode.mode {
+                AddressingMode::ZeroPage => Annotation::Value(peek(operand)),
+                AddressingMode::Absolute
+                    if !matches!(opcode.mnemonic, Mnemonic::Jmp | Mnemonic::Jsr) =>
+                {
+                    Annotation::Value(peek(operand))
+                }
+                AddressingMode::ZeroPageX | AddressingMode::ZeroPageY => {
+                    let index = if opcode.mode == AddressingMode::ZeroPageX {
+                        cpu.x
+                    } else {
+                        cpu.y
+                    };
+                    let address = u16::from(operand.to_le_bytes()[0].wrapping_add(index));
+                    Annotation::Indexed {
+                        address,
+                        value: peek(address),
+                    }
+                }
+                AddressingMode::AbsoluteX | AddressingMode::AbsoluteY => {
+                    let index = if opcode.mode == AddressingMode::AbsoluteX {
+                        cpu.x
+                    } else {
+                        cpu.y
+                    };
+                    let address = operand.wrapping_add(u16::from(index));
+                    Annotation::Indexed {
+                        address,
+                        value: peek(address),
+                    }
+                }
+                AddressingMode::Indirect => Annotation::Pointer(peek_word(&mut peek, operand)),
+                AddressingMode::IndirectX => {
+                    let pointer = operand.to_le_bytes()[0].wrapping_add(cpu.x);
+                    let address = peek_word(&mut peek, u16::from(pointer));
+                    Annotation::IndexedIndirect {
+                        pointer,
+                        address,
+                        value: peek(address),
+                    }
+                }
+                AddressingMode::IndirectY => {
+                    let base = peek_word(&mut peek, operand);
+                    let address = base.wrapping_add(u16::from(cpu.y));
+                    Annotation::IndirectIndexed {
+                        base,
+                        address,
+                        value: peek(address),
+                    }
+                }
+                _ => Annotation::None,
+            },
+            Instruction::Unknown(..) => Annotation::None,
+        };
+
+        Self {
+            pc: cpu.pc,
+            bytes,
+            instruction,
+            a: cpu.a,
+            x: cpu.x,
+            y: cpu.y,
+            p: cpu.p,
+            s: cpu.s,
+            cycles: cpu.cycles(),
+            ppu: None,
+            annotation,
+        }
+    }
+
+    /// Add the position of the PPU to the line
+    #[must_use]
+    pub fn with_ppu(mut self, scanline: u16, dot: u16) -> Self {
+        self.ppu = Some((scanline, dot));
+        self
+    }
+
+    fn disassembly(&self) -> String {
+        let zero_page = matches!(
+            self.instruction.mode(),
+            Some(AddressingMode::ZeroPageX | AddressingMode::ZeroPageY)
+        );
+
+        match self.annotation {
+            Annotation::None => format!("{}", self.instruction),
+            Annotation::Value(value) => format!("{} = {value:02X}", self.instruction),
+            Annotation::Indexed { address, value } if zero_page => {
+                format!("{} @ {address:02X} = {value:02X}", self.instruction)
+            }
+            Annotation::Indexed { address, value } => {
+                format!("{} @ {address:04X} = {value:02X}", self.instruction)
+            }
+            Annotation::Pointer(address) => format!("{} = {address:04X}", self.instruction),
+            Annotation::IndexedIndirect {
+                pointer,
+                address,
+                value,
+            } => format!(
+                "{} @ {pointer:02X} = {address:04X} = {value:02X}",
+                self.instruction
+            ),
+            Annotation::IndirectIndexed {
+                base,
+                address,
+                value,
+            } => format!(
+                "{} = {base:04X} @ {address:04X} = {value:02X}",
+                self.instruction
+            ),
+        }
+    }
+}
+
+impl fmt::Display for TraceLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes = self.bytes[..self.instruction.len()]
+            .iter()
+            .map(|byte| format!("{byte:02X}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        write!(
+            f,
+            "{:04X}  {bytes:<BYTES_WIDTH$}  {:<DISASSEMBLY_WIDTH$}A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X}",
+            self.pc,
+            self.disassembly(),
+            self.a,
+            self.x,
+            self.y,
+            self.p.0,
+            self.s,
+        )?;
+
+        if let Some((scanline, dot)) = self.ppu {
+            write!(f, " PPU:{scanline:>3},{dot:>3}")?;
+        }
+
+        write!(f, " CYC:{}", self.cycles)
+    }
+}
+
+impl Cpu {
+    /// Trace log line of the instruction at the program counter, see [`TraceLine::capture`]
+    pub fn trace<F: FnMut(u16) -> u8>(&self, peek: F) -> TraceLine {
+        TraceLine::capture(self, peek)
+    }
+}
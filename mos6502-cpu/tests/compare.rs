@@ -0,0 +1,73 @@
+use mos6502_cpu::{
+    compare::{column_value, Column},
+    TraceComparator,
+};
+
+const LINE: &str =
+    "C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7";
+const NEXT_LINE: &str =
+    "C5F5  A2 00     LDX #$00                        A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 30 CYC:10";
+
+#[test]
+fn columns() {
+    let value = |column| column_value(LINE, column);
+
+    assert_eq!(value(Column::Address), Some("C000"));
+    assert_eq!(value(Column::Bytes), Some("4C F5 C5"));
+    assert_eq!(value(Column::Disassembly), Some("JMP $C5F5"));
+    assert_eq!(value(Column::P), Some("24"));
+    assert_eq!(value(Column::Ppu), Some("0, 21"));
+    assert_eq!(value(Column::Cycles), Some("7"));
+
+    // Logs of a CPU on its own don't have the position of the PPU
+    let without_ppu = LINE.replace("PPU:  0, 21 ", "");
+    assert_eq!(column_value(&without_ppu, Column::Ppu), None);
+    assert_eq!(column_value(&without_ppu, Column::Cycles), Some("7"));
+}
+
+#[test]
+fn differences() {
+    let actual = LINE.replace("P:24", "P:25").replace("CYC:7", "CYC:8");
+
+    assert_eq!(
+        TraceComparator::new().differences(LINE, &actual),
+        [Column::P, Column::Cycles]
+    );
+    assert_eq!(
+        TraceComparator::new()
+            .ignore(Column::Cycles)
+            .differences(LINE, &actual),
+        [Column::P]
+    );
+}
+
+#[test]
+fn check() {
+    let mut comparator = TraceComparator::new().ignore(Column::Ppu).context(1);
+    comparator
+        .check(LINE, &LINE.replace("0, 21", "1, 42"))
+        .unwrap();
+
+    let divergence = comparator
+        .check(NEXT_LINE, &NEXT_LINE.replace("X:00", "X:01"))
+        .unwrap_err();
+    assert_eq!(divergence.line, 2);
+    assert_eq!(divergence.columns, [Column::X]);
+    assert_eq!(divergence.context, [LINE]);
+    assert!(divergence
+        .to_string()
+        .starts_with("Trace logs diverge at line 2 (X)\n"));
+}
+
+#[test]
+fn compare() {
+    let log = [LINE, NEXT_LINE].join("\n");
+
+    assert_eq!(TraceComparator::new().compare(&log, &log), Ok(2));
+
+    // One log ending early is a divergence as well
+    let divergence = TraceComparator::new().compare(&log, LINE).unwrap_err();
+    assert_eq!(divergence.line, 2);
+    assert_eq!(divergence.actual, None);
+    assert!(divergence.columns.is_empty());
+}
@@ -0,0 +1,205 @@
+use mos6502_cpu::{Bus, Cpu, Error, Status, IRQ_VECTOR, NMI_VECTOR, RESET_VECTOR};
+
+// 64 KiB of RAM, with the program at $8000 and all vectors pointing to it
+struct Ram([u8; 0x10000]);
+
+impl Ram {
+    fn new(program: &[u8]) -> Self {
+        let mut ram = [0; 0x10000];
+        ram[0x8000..0x8000 + program.len()].copy_from_slice(program);
+        for vector in [NMI_VECTOR, RESET_VECTOR, IRQ_VECTOR] {
+            ram[usize::from(vector) + 1] = 0x80;
+        }
+
+        Self(ram)
+    }
+}
+
+impl Bus for Ram {
+    fn read(&mut self, address: u16) -> u8 {
+        self.0[usize::from(address)]
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.0[usize::from(address)] = value;
+    }
+}
+
+fn run(program: &[u8], instructions: usize) -> (Cpu, Ram) {
+    let mut ram = Ram::new(program);
+    let mut cpu = Cpu::new();
+    cpu.reset(&mut ram);
+
+    for _ in 0..instructions {
+        cpu.step(&mut ram).unwrap();
+    }
+
+    (cpu, ram)
+}
+
+#[test]
+fn reset() {
+    let (cpu, _) = run(&[], 0);
+
+    assert_eq!(cpu.pc, 0x8000);
+    assert_eq!(cpu.s, 0xFD);
+    assert_eq!(cpu.cycles(), 7);
+    assert!(cpu.p.contains(Status::INTERRUPT_DISABLE));
+}
+
+#[test]
+fn arithmetic_flags() {
+    let (cpu, _) = run(
+        &[
+            0xA9, 0x50, // LDA #$50
+            0x69, 0x50, // ADC #$50
+        ],
+        2,
+    );
+    assert_eq!(cpu.a, 0xA0);
+    assert!(cpu.p.contains(Status::OVERFLOW | Status::NEGATIVE));
+    assert!(!cpu.p.contains(Status::CARRY));
+
+    let (cpu, _) = run(
+        &[
+            0xA9, 0x10, // LDA #$10
+            0x38, // SEC
+            0xE9, 0x20, // SBC #$20
+        ],
+        3,
+    );
+    assert_eq!(cpu.a, 0xF0);
+    // The carry is the inverted borrow
+    assert!(!cpu.p.contains(Status::CARRY));
+    assert!(!cpu.p.contains(Status::OVERFLOW));
+
+    let (cpu, _) = run(
+        &[
+            0xA2, 0x40, // LDX #$40
+            0xE0, 0x40, // CPX #$40
+        ],
+        2,
+    );
+    assert!(cpu.p.contains(Status::CARRY | Status::ZERO));
+}
+
+#[test]
+fn read_modify_write() {
+    let (cpu, ram) = run(
+        &[
+            0xA9, 0x81, // LDA #$81
+            0x85, 0x10, // STA $10
+            0x06, 0x10, // ASL $10
+            0x66, 0x10, // ROR $10
+        ],
+        4,
+    );
+
+    // ASL shifts the high bit into the carry, which ROR shifts back in
+    assert_eq!(ram.0[0x10], 0x81);
+    assert!(!cpu.p.contains(Status::CARRY));
+    assert_eq!(cpu.cycles(), 7 + 2 + 3 + 5 + 5);
+}
+
+#[test]
+fn page_crossing_cycles() {
+    let program = [
+        0xA2, 0x01, // LDX #$01
+        0xBD, 0xFE, 0x80, // LDA $80FE,X
+        0xBD, 0xFF, 0x80, // LDA $80FF,X
+        0x9D, 0xFE, 0x80, // STA $80FE,X
+    ];
+    let mut ram = Ram::new(&program);
+    let mut cpu = Cpu::new();
+    cpu.reset(&mut ram);
+
+    let cycles: Vec<u64> = (0..4).map(|_| cpu.step(&mut ram).unwrap()).collect();
+    // Only the read crossing into the next page takes an extra cycle, writes always take it
+    assert_eq!(cycles, [2, 4, 5, 5]);
+}
+
+#[test]
+fn branches() {
+    let (cpu, _) = run(
+        &[
+            0xA2, 0x03, // LDX #$03
+            0xCA, // DEX
+            0xD0, 0xFD, // BNE $8002
+        ],
+        1 + 3 * 2,
+    );
+
+    assert_eq!(cpu.x, 0);
+    assert_eq!(cpu.pc, 0x8005);
+    // Taken branches take 3 cycles, the one falling through 2
+    assert_eq!(cpu.cycles(), 7 + 2 + 3 * 2 + 2 * 3 + 2);
+}
+
+#[test]
+fn subroutines() {
+    let mut program = vec![0xEA; 0x100];
+    program[..3].copy_from_slice(&[0x20, 0x80, 0x80]); // JSR $8080
+    program[0x80] = 0x60; // RTS
+
+    let mut ram = Ram::new(&program);
+    let mut cpu = Cpu::new();
+    cpu.reset(&mut ram);
+
+    assert_eq!(cpu.step(&mut ram).unwrap(), 6);
+    assert_eq!(cpu.pc, 0x8080);
+    // The return address points to the last byte of the JSR
+    assert_eq!(ram.0[0x1FC..=0x1FD], [0x02, 0x80]);
+
+    assert_eq!(cpu.step(&mut ram).unwrap(), 6);
+    assert_eq!(cpu.pc, 0x8003);
+    assert_eq!(cpu.s, 0xFD);
+}
+
+#[test]
+fn indirect_jump_wraps_within_page() {
+    let mut program = vec![0; 0x100];
+    program[..3].copy_from_slice(&[0x6C, 0xFF, 0x80]); // JMP ($80FF)
+    program[0xFF] = 0x34;
+
+    let mut ram = Ram::new(&program);
+    ram.0[0x8100] = 0x12;
+    let mut cpu = Cpu::new();
+    cpu.reset(&mut ram);
+    cpu.step(&mut ram).unwrap();
+
+    // The high byte comes from $8000 instead of $8100
+    assert_eq!(cpu.pc, 0x6C34);
+}
+
+#[test]
+fn interrupts() {
+    let (mut cpu, mut ram) = run(&[0xEA; 4], 0);
+
+    // Interrupts are disabled after the reset
+    cpu.set_irq(true);
+    assert_eq!(cpu.step(&mut ram).unwrap(), 2);
+    assert_eq!(cpu.pc, 0x8001);
+
+    cpu.nmi();
+    assert_eq!(cpu.step(&mut ram).unwrap(), 7);
+    assert_eq!(cpu.pc, 0x8000);
+    // The pushed status doesn't have the break flag set
+    assert_eq!(ram.0[0x1FB], (Status::UNUSED | Status::INTERRUPT_DISABLE).0);
+    assert_eq!(ram.0[0x1FC..=0x1FD], [0x01, 0x80]);
+}
+
+#[test]
+fn unknown_opcode() {
+    let mut ram = Ram::new(&[0x02]);
+    let mut cpu = Cpu::new();
+    cpu.reset(&mut ram);
+
+    assert!(matches!(
+        cpu.step(&mut ram),
+        Err(Error::UnknownOpcode {
+            opcode: 0x02,
+            address: 0x8000
+        })
+    ));
+    assert_eq!(cpu.pc, 0x8000);
+}
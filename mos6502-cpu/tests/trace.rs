@@ -0,0 +1,71 @@
+use mos6502_cpu::{Bus, Cpu, RESET_VECTOR};
+
+// 64 KiB of RAM with the program at $8000 and data for the addressing modes in the zero page and at $0200
+struct Ram([u8; 0x10000]);
+
+impl Bus for Ram {
+    fn read(&mut self, address: u16) -> u8 {
+        self.0[usize::from(address)]
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.0[usize::from(address)] = value;
+    }
+}
+
+#[test]
+fn trace_lines() {
+    let program = [
+        0xA2, 0x02, // LDX #$02
+        0xA0, 0x01, // LDY #$01
+        0xA1, 0x80, // LDA ($80,X)
+        0xB1, 0x89, // LDA ($89),Y
+        0xBD, 0xFF, 0x02, // LDA $02FF,X
+        0xB5, 0x80, // LDA $80,X
+        0xAD, 0x00, 0x02, // LDA $0200
+        0x6C, 0x82, 0x00, // JMP ($0082)
+    ];
+    let mut ram = Ram([0; 0x10000]);
+    ram.0[0x8000..0x8000 + program.len()].copy_from_slice(&program);
+    ram.0[usize::from(RESET_VECTOR) + 1] = 0x80;
+    ram.0[0x82..0x84].copy_from_slice(&[0x00, 0x02]);
+    ram.0[0x89..0x8B].copy_from_slice(&[0x00, 0x03]);
+    ram.0[0x0200] = 0x5A;
+    ram.0[0x0301] = 0x89;
+
+    let mut cpu = Cpu::new();
+    cpu.reset(&mut ram);
+
+    let mut log = Vec::new();
+    for _ in 0..8 {
+        log.push(cpu.trace(|address| ram.read(address)).to_string());
+        cpu.step(&mut ram).unwrap();
+    }
+
+    assert_eq!(
+        log,
+        [
+            "8000  A2 02     LDX #$02                        A:00 X:00 Y:00 P:24 SP:FD CYC:7",
+            "8002  A0 01     LDY #$01                        A:00 X:02 Y:00 P:24 SP:FD CYC:9",
+            "8004  A1 80     LDA ($80,X) @ 82 = 0200 = 5A    A:00 X:02 Y:01 P:24 SP:FD CYC:11",
+            "8006  B1 89     LDA ($89),Y = 0300 @ 0301 = 89  A:5A X:02 Y:01 P:24 SP:FD CYC:17",
+            "8008  BD FF 02  LDA $02FF,X @ 0301 = 89         A:89 X:02 Y:01 P:A4 SP:FD CYC:22",
+            "800B  B5 80     LDA $80,X @ 82 = 00             A:89 X:02 Y:01 P:A4 SP:FD CYC:27",
+            "800D  AD 00 02  LDA $0200 = 5A                  A:00 X:02 Y:01 P:26 SP:FD CYC:31",
+            "8010  6C 82 00  JMP ($0082) = 0200              A:5A X:02 Y:01 P:24 SP:FD CYC:35",
+        ]
+    );
+}
+
+#[test]
+fn ppu_position() {
+    let mut ram = Ram([0xEA; 0x10000]);
+    let mut cpu = Cpu::new();
+    cpu.reset(&mut ram);
+
+    let line = cpu.trace(|address| ram.read(address)).with_ppu(0, 21);
+    assert_eq!(
+        line.to_string(),
+        "EAEA  EA        NOP                             A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7"
+    );
+}
@@ -0,0 +1,25 @@
+use std::{env, fs::File};
+
+fn main() {
+    let rom_path = env::args().nth(1).unwrap();
+    let mut file = File::open(rom_path).unwrap();
+
+    let ines = ines_parser::Ines::from_reader(&mut file).unwrap();
+
+    // A single 16 KiB bank is mirrored into $C000 - $FFFF
+    let origin = if ines.prg_rom.len() > 16_384 {
+        0x8000
+    } else {
+        0xC000
+    };
+
+    for (address, instruction, bytes) in mos6502_dasm::Disassembler::new(&ines.prg_rom, origin) {
+        let bytes = bytes
+            .iter()
+            .map(|byte| format!("{byte:02X}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        println!("{address:04X}  {bytes:<8}  {instruction}");
+    }
+}
@@ -0,0 +1,16 @@
+use std::{env, fs::File};
+
+fn main() {
+    let rom_path = env::args().nth(1).unwrap();
+    let mut file = File::open(rom_path).unwrap();
+
+    let ines = ines_parser::Ines::from_reader(&mut file).unwrap();
+
+    // Only the last 16 KiB bank is guaranteed to be mapped at the end of the address space
+    let last_bank = &ines.prg_rom[ines.prg_rom.len() - 16_384..];
+    let listing = mos6502_dasm::Tracer::new(last_bank, 0xC000)
+        .with_vectors()
+        .run();
+
+    print!("{listing}");
+}
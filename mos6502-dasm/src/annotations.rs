@@ -0,0 +1,324 @@
+//!
+//! Sidecar files with the knowledge gathered about a ROM
+//!
+//! Everything inside of the ROM is located by its PRG ROM offset instead of a CPU address,
+//! which stays the same no matter which bank the mapper switches in.
+//!
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use {
+    crate::{Error, Listing},
+    alloc::{collections::BTreeMap, string::String, vec::Vec},
+    core::{convert::TryFrom, ops::Range},
+};
+
+/// Bank of the PRG ROM with the address it's disassembled at
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BankAnnotation {
+    pub offset: usize,
+    pub size: usize,
+    /// CPU address the bank is mapped to, if it's known
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub address: Option<u16>,
+    pub name: String,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub comment: Option<String>,
+}
+
+impl BankAnnotation {
+    /// Section of the PRG ROM holding the bank
+    #[must_use]
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.offset + self.size
+    }
+}
+
+/// Known subroutine or other piece of code
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Routine {
+    pub offset: usize,
+    pub name: String,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub comment: Option<String>,
+}
+
+/// What a data region holds
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DataKind {
+    Bytes,
+    /// Table of little-endian addresses, like the jump tables of state machines
+    Pointers,
+    Text,
+    Graphics,
+    Music,
+}
+
+/// Section of the PRG ROM which holds data instead of code
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DataRegion {
+    pub offset: usize,
+    pub size: usize,
+    pub kind: DataKind,
+    pub name: String,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub comment: Option<String>,
+}
+
+impl DataRegion {
+    #[must_use]
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.offset + self.size
+    }
+}
+
+/// Comment on a byte of the PRG ROM; multiple lines are separated by `\n`
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Comment {
+    pub offset: usize,
+    pub text: String,
+}
+
+/// Name of an address outside of the PRG ROM, like a variable in RAM or a register
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Variable {
+    pub address: u16,
+    pub name: String,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub comment: Option<String>,
+}
+
+/// Annotations of a ROM: its banks, known routines, data regions, comments and variables
+///
+/// They're keyed by the CRC32 of the PRG ROM, so a sidecar file can be shared and checked against the ROM it's
+/// loaded for. Work split between people is combined with [`Annotations::merge`], and
+/// [`Tracer::annotations`](crate::Tracer::annotations) and [`Listing::apply_annotations`] feed them into the disassembly.
+/// With the `serde` feature they can be stored in any format; the `json` feature adds `Annotations::from_json` and
+/// `Annotations::to_json`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Annotations {
+    /// CRC32 of the PRG ROM the annotations belong to
+    pub prg_crc32: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub banks: Vec<BankAnnotation>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub routines: Vec<Routine>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub data: Vec<DataRegion>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub comments: Vec<Comment>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub variables: Vec<Variable>,
+}
+
+// Replace the item with the same key or add it, keeping the items ordered by their key
+fn upsert<T, K: Ord>(items: &mut Vec<T>, item: T, key: impl Fn(&T) -> K) {
+    match items.binary_search_by_key(&key(&item), &key) {
+        Ok(index) => items[index] = item,
+        Err(index) => items.insert(index, item),
+    }
+}
+
+// CPU address of a PRG ROM offset inside of a program starting at the PRG ROM offset and the origin
+pub(crate) fn address_in(offset: usize, prg_offset: usize, origin: u16) -> Option<u16> {
+    let index = u16::try_from(offset.checked_sub(prg_offset)?).ok()?;
+    origin.checked_add(index)
+}
+
+impl Annotations {
+    /// Empty annotations for the PRG ROM
+    #[must_use]
+    pub fn new(prg_rom: &[u8]) -> Self {
+        Self {
+            prg_crc32: crc32fast::hash(prg_rom),
+            ..Self::default()
+        }
+    }
+
+    /// Whether the annotations were made for the PRG ROM
+    #[must_use]
+    pub fn matches(&self, prg_rom: &[u8]) -> bool {
+        self.prg_crc32 == crc32fast::hash(prg_rom)
+    }
+
+    /// Add a bank, replacing the one at the same offset
+    pub fn set_bank(&mut self, bank: BankAnnotation) {
+        upsert(&mut self.banks, bank, |bank| bank.offset);
+    }
+
+    /// Add a routine, replacing the one at the same offset
+    pub fn set_routine(&mut self, routine: Routine) {
+        upsert(&mut self.routines, routine, |routine| routine.offset);
+    }
+
+    /// Add a data region, replacing the one at the same offset
+    pub fn set_data_region(&mut self, region: DataRegion) {
+        upsert(&mut self.data, region, |region| region.offset);
+    }
+
+    /// Add a comment, replacing the one at the same offset
+    pub fn set_comment(&mut self, comment: Comment) {
+        upsert(&mut self.comments, comment, |comment| comment.offset);
+    }
+
+    /// Add a variable, replacing the one at the same address
+    pub fn set_variable(&mut self, variable: Variable) {
+        upsert(&mut self.variables, variable, |variable| variable.address);
+    }
+
+    /// Bank containing the PRG ROM offset, if any
+    #[must_use]
+    pub fn bank(&self, offset: usize) -> Option<&BankAnnotation> {
+        self.banks
+            .iter()
+            .find(|bank| bank.range().contains(&offset))
+    }
+
+    /// Add the annotations of another file for the same ROM, which win over the existing ones at the same location
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the other annotations belong to a different PRG ROM
+    pub fn merge(&mut self, other: Self) -> Result<(), Error> {
+        if other.prg_crc32 != self.prg_crc32 {
+            return Err(Error::AnnotationsMismatch {
+                expected: self.prg_crc32,
+                actual: other.prg_crc32,
+            });
+        }
+
+        self.normalize();
+        for bank in other.banks {
+            self.set_bank(bank);
+        }
+        for routine in other.routines {
+            self.set_routine(routine);
+        }
+        for region in other.data {
+            self.set_data_region(region);
+        }
+        for comment in other.comments {
+            self.set_comment(comment);
+        }
+        for variable in other.variables {
+            self.set_variable(variable);
+        }
+
+        Ok(())
+    }
+
+    // Order everything by location, since hand-edited files might not be
+    fn normalize(&mut self) {
+        self.banks.sort_by_key(|bank| bank.offset);
+        self.routines.sort_by_key(|routine| routine.offset);
+        self.data.sort_by_key(|region| region.offset);
+        self.comments.sort_by_key(|comment| comment.offset);
+        self.variables.sort_by_key(|variable| variable.address);
+    }
+
+    /// Load annotations from JSON
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the JSON doesn't describe annotations
+    #[cfg(feature = "json")]
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let mut annotations: Self = serde_json::from_str(json).map_err(Error::Json)?;
+        annotations.normalize();
+
+        Ok(annotations)
+    }
+
+    /// Pretty-printed JSON, for sidecar files which are edited by hand and kept under version control
+    #[cfg(feature = "json")]
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        // Serializing plain structs to a string can't fail
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+}
+
+impl Listing<'_> {
+    /// Name and comment the routines, data regions and variables of the annotations
+    ///
+    /// `prg_offset` is the offset of the listed program inside of the PRG ROM, like the one passed to
+    /// [`Tracer::annotations`](crate::Tracer::annotations). The name of a bank is written as a comment at its start.
+    pub fn apply_annotations(&mut self, annotations: &Annotations, prg_offset: usize) {
+        let address_of = |listing: &Self, offset: usize| {
+            address_in(offset, prg_offset, listing.origin())
+                .filter(|address| listing.contains(*address))
+        };
+        // Comments at the same address are joined, like the one of a bank and its first routine
+        let mut comments = BTreeMap::<u16, Vec<&str>>::new();
+
+        for variable in &annotations.variables {
+            self.set_name(variable.address, variable.name.clone());
+            if let Some(comment) = &variable.comment {
+                comments.entry(variable.address).or_default().push(comment);
+            }
+        }
+
+        for bank in &annotations.banks {
+            if let Some(address) = address_of(self, bank.offset) {
+                let bank_comments = comments.entry(address).or_default();
+                bank_comments.push(&bank.name);
+                bank_comments.extend(bank.comment.as_deref());
+            }
+        }
+
+        let named = annotations
+            .routines
+            .iter()
+            .map(|routine| (routine.offset, &routine.name, &routine.comment))
+            .chain(
+                annotations
+                    .data
+                    .iter()
+                    .map(|region| (region.offset, &region.name, &region.comment)),
+            );
+        for (offset, name, comment) in named {
+            if let Some(address) = address_of(self, offset) {
+                self.set_name(address, name.clone());
+                comments
+                    .entry(address)
+                    .or_default()
+                    .extend(comment.as_deref());
+            }
+        }
+
+        for comment in &annotations.comments {
+            if let Some(address) = address_of(self, comment.offset) {
+                comments.entry(address).or_default().push(&comment.text);
+            }
+        }
+
+        for (address, lines) in comments {
+            if lines.is_empty() {
+                continue;
+            }
+            self.set_comment(address, lines.join("\n"));
+        }
+    }
+}
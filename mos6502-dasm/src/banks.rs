@@ -0,0 +1,261 @@
+use {
+    crate::{AddressingMode, ByteKind, Instruction, LabelKind, Listing, Mnemonic, Tracer},
+    alloc::{collections::BTreeSet, vec, vec::Vec},
+    core::{convert::TryFrom, ops::Range},
+};
+
+const PRG_ROM_START: usize = 0x8000;
+const ADDRESS_SPACE_END: usize = 0x1_0000;
+const PRG_ROM_WINDOW_SIZE: usize = 0x8000;
+
+/// How the PRG ROM banks are mapped into `$8000`-`$FFFF`
+///
+/// The last banks of the PRG ROM are fixed to the end of the address space,
+/// every other bank can be switched into the windows below them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BankLayout {
+    pub bank_size: usize,
+    /// Amount of banks fixed to the end of the address space; without any, the last bank is mapped at power-on
+    pub fixed_banks: usize,
+}
+
+impl BankLayout {
+    /// Layout of the common mappers at power-on
+    #[must_use]
+    pub fn for_mapper(mapper_number: u8, prg_rom_size: usize) -> Self {
+        match mapper_number {
+            // NROM and CNROM don't switch PRG banks at all
+            0 | 3 | 185 => Self {
+                bank_size: prg_rom_size.clamp(1, PRG_ROM_WINDOW_SIZE),
+                fixed_banks: 1,
+            },
+            // AxROM, BNROM, Color Dreams, GxROM and others switch all 32 KiB at once
+            7 | 11 | 34 | 38 | 66 | 79 | 87 | 140 => Self {
+                bank_size: PRG_ROM_WINDOW_SIZE,
+                fixed_banks: 0,
+            },
+            // MMC1, UxROM and their relatives switch 16 KiB at $8000
+            1 | 2 | 10 | 16 | 70 | 71 | 89 | 93 | 94 | 152 => Self {
+                bank_size: 0x4000,
+                fixed_banks: 1,
+            },
+            // MMC3 and most other ASIC mappers switch 8 KiB banks and fix the last 16 KiB
+            _ => Self {
+                bank_size: 0x2000,
+                fixed_banks: 2,
+            },
+        }
+    }
+
+    fn fixed_size(self) -> usize {
+        self.fixed_banks * self.bank_size
+    }
+
+    // Windows the switchable banks can be mapped to
+    fn slots(self) -> impl Iterator<Item = usize> {
+        let end = if self.fixed_banks == 0 {
+            ADDRESS_SPACE_END
+        } else {
+            ADDRESS_SPACE_END - self.fixed_size()
+        };
+
+        (PRG_ROM_START..end).step_by(self.bank_size)
+    }
+}
+
+/// How a bank can be reached by the program
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Reachability {
+    /// Mapped at power-on
+    Fixed,
+    /// Switched in by storing its number to a mapper register
+    Switched,
+    /// Never switched in as far as the analysis can tell
+    Unreachable,
+}
+
+/// Result of the analysis for a single bank
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Bank {
+    pub index: usize,
+    /// Section of the PRG ROM
+    pub range: Range<usize>,
+    pub reachability: Reachability,
+    /// Amount of bytes reached as code
+    pub code_bytes: usize,
+    /// Every byte of the bank has the same value
+    pub is_padding: bool,
+}
+
+impl Bank {
+    /// The bank is neither reachable nor contains anything, so it's free space
+    #[must_use]
+    pub fn is_free(&self) -> bool {
+        self.reachability == Reachability::Unreachable && self.is_padding
+    }
+}
+
+#[derive(Default)]
+struct Collected {
+    // Jump targets below the fixed banks
+    targets: BTreeSet<u16>,
+    // Values stored to the mapper registers right after loading them
+    bank_numbers: BTreeSet<u8>,
+}
+
+impl Collected {
+    fn collect(&mut self, listing: &Listing<'_>, switchable: &Range<usize>) {
+        let mut immediate = None;
+
+        for (_, instruction) in listing.instructions() {
+            if let Some(target) = instruction.target() {
+                if switchable.contains(&usize::from(target)) {
+                    self.targets.insert(target);
+                }
+            }
+
+            let (opcode, operand) = match instruction {
+                Instruction::Official { opcode, operand } => (opcode, operand),
+                Instruction::Unknown(..) => continue,
+            };
+
+            match (opcode.mnemonic, opcode.mode) {
+                (Mnemonic::Lda | Mnemonic::Ldx | Mnemonic::Ldy, AddressingMode::Immediate) => {
+                    // Immediate operands are a single byte
+                    #[allow(clippy::cast_possible_truncation)]
+                    {
+                        immediate = Some(operand as u8);
+                    }
+                }
+                (Mnemonic::Sta | Mnemonic::Stx | Mnemonic::Sty, AddressingMode::Absolute)
+                    if usize::from(operand) >= PRG_ROM_START =>
+                {
+                    self.bank_numbers.extend(immediate.take());
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+fn mark_code(listing: &Listing<'_>, origin: usize, code: &mut [bool]) {
+    for (index, is_code) in code.iter_mut().enumerate() {
+        let kind = u16::try_from(origin + index)
+            .ok()
+            .and_then(|address| listing.byte_kind(address));
+
+        if matches!(kind, Some(ByteKind::Opcode | ByteKind::Operand)) {
+            *is_code = true;
+        }
+    }
+}
+
+/// Find out which PRG ROM banks are reachable from the interrupt vectors
+///
+/// The code of the fixed banks gets traced from the vectors. Immediate values stored to `$8000`-`$FFFF`
+/// are taken as bank numbers, and switched-in banks are traced from the jumps into their windows.
+/// Bank numbers computed at runtime (like through tables or the serial writes of the MMC1) can't be followed,
+/// so unreachable banks may still be used.
+#[must_use]
+pub fn analyze_banks(prg_rom: &[u8], layout: BankLayout) -> Vec<Bank> {
+    let bank_size = layout.bank_size.max(1);
+    let bank_count = prg_rom.len().div_ceil(bank_size);
+    let bank_range = |index: usize| index * bank_size..((index + 1) * bank_size).min(prg_rom.len());
+
+    let fixed_banks = layout.fixed_banks.clamp(1, bank_count.max(1));
+    let fixed_start = bank_count.saturating_sub(fixed_banks);
+    let fixed_origin = if layout.fixed_banks == 0 {
+        PRG_ROM_START
+    } else {
+        ADDRESS_SPACE_END - (bank_count - fixed_start) * bank_size
+    };
+    let switchable = PRG_ROM_START
+        ..layout
+            .slots()
+            .last()
+            .map_or(PRG_ROM_START, |slot| slot + bank_size);
+
+    let mut code = vec![false; prg_rom.len()];
+    let mut collected = Collected::default();
+
+    // The fixed banks are traced as a single program
+    let fixed = &prg_rom[bank_range(fixed_start).start..];
+    let origin = u16::try_from(fixed_origin).unwrap_or(u16::MAX);
+    let listing = Tracer::new(fixed, origin).with_vectors().run();
+    mark_code(
+        &listing,
+        fixed_origin,
+        &mut code[bank_range(fixed_start).start..],
+    );
+    collected.collect(&listing, &switchable);
+
+    // New jump targets and bank numbers can show up in every switched-in bank, so repeat until nothing changes
+    loop {
+        let before = (collected.targets.len(), collected.bank_numbers.len());
+        let switched: BTreeSet<usize> = collected
+            .bank_numbers
+            .iter()
+            .map(|number| usize::from(*number) % bank_count.max(1))
+            .filter(|index| *index < fixed_start)
+            .collect();
+
+        for index in switched {
+            let range = bank_range(index);
+            for slot in layout.slots() {
+                let mut tracer = Tracer::new(
+                    &prg_rom[range.clone()],
+                    u16::try_from(slot).unwrap_or(u16::MAX),
+                );
+                for target in &collected.targets {
+                    let target_offset = usize::from(*target);
+                    if (slot..slot + bank_size).contains(&target_offset) {
+                        tracer = tracer.entry_point(*target, LabelKind::Location);
+                    }
+                }
+                // Banks switched in as a whole need their own vectors
+                if layout.fixed_banks == 0 {
+                    tracer = tracer.with_vectors();
+                }
+
+                let listing = tracer.run();
+                mark_code(&listing, slot, &mut code[range.clone()]);
+                collected.collect(&listing, &switchable);
+            }
+        }
+
+        if before == (collected.targets.len(), collected.bank_numbers.len()) {
+            break;
+        }
+    }
+
+    let switched: BTreeSet<usize> = collected
+        .bank_numbers
+        .iter()
+        .map(|number| usize::from(*number) % bank_count.max(1))
+        .collect();
+
+    (0..bank_count)
+        .map(|index| {
+            let range = bank_range(index);
+            let bytes = &prg_rom[range.clone()];
+            let reachability = if index >= fixed_start {
+                Reachability::Fixed
+            } else if switched.contains(&index) {
+                Reachability::Switched
+            } else {
+                Reachability::Unreachable
+            };
+
+            Bank {
+                index,
+                reachability,
+                code_bytes: code[range.clone()]
+                    .iter()
+                    .filter(|is_code| **is_code)
+                    .count(),
+                is_padding: bytes.iter().all(|byte| *byte == bytes[0]),
+                range,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,203 @@
+use {
+    crate::Error,
+    alloc::vec::Vec,
+    core::{
+        convert::TryInto,
+        ops::{BitOr, BitOrAssign},
+    },
+};
+
+/// Magic bytes at the start of CDL files written by Mesen
+pub const MESEN_MAGIC: [u8; 5] = *b"CDLv2";
+
+const MESEN_HEADER_SIZE: usize = MESEN_MAGIC.len() + 4;
+
+/// Flags of a PRG ROM byte in a code/data log
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PrgFlags(pub u8);
+
+impl PrgFlags {
+    /// The byte was executed as part of an instruction
+    pub const CODE: Self = Self(0x01);
+    /// The byte was read as data
+    pub const DATA: Self = Self(0x02);
+    /// The byte was executed after an indirect jump
+    pub const INDIRECT_CODE: Self = Self(0x10);
+    /// The byte was read via an indirect access
+    pub const INDIRECT_DATA: Self = Self(0x20);
+    /// The byte was played back as a DMC sample
+    pub const PCM_DATA: Self = Self(0x40);
+    /// The byte is the start of a subroutine (only written by Mesen)
+    pub const SUB_ENTRY_POINT: Self = Self(0x80);
+
+    #[must_use]
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Which of the four 8 KiB windows ($8000, $A000, $C000, $E000) the byte was mapped to (only written by FCEUX)
+    #[must_use]
+    pub const fn bank_window(self) -> u8 {
+        (self.0 >> 2) & 0b11
+    }
+}
+
+impl BitOr for PrgFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for PrgFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Flags of a CHR ROM byte in a code/data log
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ChrFlags(pub u8);
+
+impl ChrFlags {
+    /// The byte was fetched by the PPU for rendering
+    pub const DRAWN: Self = Self(0x01);
+    /// The byte was read by the CPU via $2007
+    pub const READ: Self = Self(0x02);
+
+    #[must_use]
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+/// Which emulator format a code/data log is stored in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CdlFormat {
+    /// Raw flags (PRG followed by CHR) as written by FCEUX
+    Fceux,
+    /// Raw flags preceded by a header containing the CRC32 of the ROM, as written by Mesen
+    Mesen { crc32: u32 },
+}
+
+/// Code/data log recorded by an emulator, marking which ROM bytes were executed or read
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CodeDataLog {
+    format: CdlFormat,
+    prg: Vec<PrgFlags>,
+    chr: Vec<ChrFlags>,
+}
+
+impl CodeDataLog {
+    /// Create an empty log for a ROM with the given PRG and CHR ROM sizes
+    #[must_use]
+    pub fn new(prg_rom_size: usize, chr_rom_size: usize) -> Self {
+        Self {
+            format: CdlFormat::Fceux,
+            prg: alloc::vec![PrgFlags::default(); prg_rom_size],
+            chr: alloc::vec![ChrFlags::default(); chr_rom_size],
+        }
+    }
+
+    /// Parse a CDL file in either the FCEUX or the Mesen format for a ROM with the given PRG and CHR ROM sizes
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::CdlSizeMismatch`] if the file doesn't match the ROM sizes
+    pub fn parse(data: &[u8], prg_rom_size: usize, chr_rom_size: usize) -> Result<Self, Error> {
+        let (format, flags) = match data.strip_prefix(&MESEN_MAGIC[..]) {
+            Some(rest) if rest.len() >= 4 => {
+                let (crc32, flags) = rest.split_at(4);
+                let crc32 = u32::from_le_bytes(crc32.try_into()?);
+
+                (CdlFormat::Mesen { crc32 }, flags)
+            }
+            _ => (CdlFormat::Fceux, data),
+        };
+
+        if flags.len() != prg_rom_size + chr_rom_size {
+            return Err(Error::CdlSizeMismatch {
+                expected: prg_rom_size + chr_rom_size,
+                actual: flags.len(),
+            });
+        }
+
+        let (prg, chr) = flags.split_at(prg_rom_size);
+
+        Ok(Self {
+            format,
+            prg: prg.iter().map(|flags| PrgFlags(*flags)).collect(),
+            chr: chr.iter().map(|flags| ChrFlags(*flags)).collect(),
+        })
+    }
+
+    /// The format the log was parsed from
+    #[must_use]
+    pub fn format(&self) -> CdlFormat {
+        self.format
+    }
+
+    /// Serialise the log in the given format
+    #[must_use]
+    pub fn to_bytes(&self, format: CdlFormat) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(MESEN_HEADER_SIZE + self.prg.len() + self.chr.len());
+
+        if let CdlFormat::Mesen { crc32 } = format {
+            bytes.extend_from_slice(&MESEN_MAGIC);
+            bytes.extend_from_slice(&crc32.to_le_bytes());
+        }
+
+        bytes.extend(self.prg.iter().map(|flags| flags.0));
+        bytes.extend(self.chr.iter().map(|flags| flags.0));
+
+        bytes
+    }
+
+    /// Flags of every PRG ROM byte
+    #[must_use]
+    pub fn prg_flags(&self) -> &[PrgFlags] {
+        &self.prg
+    }
+
+    /// Flags of every PRG ROM byte
+    pub fn prg_flags_mut(&mut self) -> &mut [PrgFlags] {
+        &mut self.prg
+    }
+
+    /// Flags of every CHR ROM byte
+    #[must_use]
+    pub fn chr_flags(&self) -> &[ChrFlags] {
+        &self.chr
+    }
+
+    /// Flags of every CHR ROM byte
+    pub fn chr_flags_mut(&mut self) -> &mut [ChrFlags] {
+        &mut self.chr
+    }
+
+    /// Whether the PRG ROM byte was executed
+    #[must_use]
+    pub fn is_code(&self, prg_offset: usize) -> bool {
+        self.prg
+            .get(prg_offset)
+            .is_some_and(|flags| flags.contains(PrgFlags::CODE))
+    }
+
+    /// Whether the PRG ROM byte was only read as data and never executed
+    #[must_use]
+    pub fn is_data(&self, prg_offset: usize) -> bool {
+        self.prg
+            .get(prg_offset)
+            .is_some_and(|flags| flags.contains(PrgFlags::DATA) && !flags.contains(PrgFlags::CODE))
+    }
+
+    /// Amount of PRG ROM bytes which were neither executed nor read
+    #[must_use]
+    pub fn unaccessed_prg_bytes(&self) -> usize {
+        self.prg
+            .iter()
+            .filter(|flags| flags.0 & (PrgFlags::CODE.0 | PrgFlags::DATA.0) == 0)
+            .count()
+    }
+}
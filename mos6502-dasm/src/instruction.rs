@@ -0,0 +1,164 @@
+use {
+    crate::opcode::{self, AddressingMode, Mnemonic, Opcode},
+    core::fmt,
+};
+
+/// A decoded instruction
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Instruction {
+    /// An official instruction
+    ///
+    /// For relative addressing the operand already contains the absolute branch target
+    Official { opcode: Opcode, operand: u16 },
+
+    /// A byte which isn't an official opcode (or the start of an instruction which got cut off)
+    Unknown(u8),
+}
+
+impl Instruction {
+    /// Decode the instruction at the start of the byte slice, which is located at the given address
+    ///
+    /// Returns `None` if the slice is empty
+    #[must_use]
+    pub fn decode(bytes: &[u8], address: u16) -> Option<Self> {
+        let opcode_byte = *bytes.first()?;
+
+        let opcode = match opcode::lookup(opcode_byte) {
+            Some(opcode) if bytes.len() > opcode.mode.operand_len() => opcode,
+            _ => return Some(Self::Unknown(opcode_byte)),
+        };
+
+        let operand = match opcode.mode.operand_len() {
+            0 => 0,
+            1 => u16::from(bytes[1]),
+            _ => u16::from_le_bytes([bytes[1], bytes[2]]),
+        };
+
+        let operand = if opcode.mode == AddressingMode::Relative {
+            // The offset is a signed byte relative to the address of the next instruction
+            #[allow(clippy::cast_possible_wrap)]
+            let offset = i16::from(bytes[1] as i8);
+            address.wrapping_add(2).wrapping_add_signed(offset)
+        } else {
+            operand
+        };
+
+        Some(Self::Official { opcode, operand })
+    }
+
+    /// Length of the instruction in bytes
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        match self {
+            Self::Official { opcode, .. } => 1 + opcode.mode.operand_len(),
+            Self::Unknown(..) => 1,
+        }
+    }
+
+    /// Always `false`, an instruction consists of at least one byte
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        false
+    }
+
+    #[must_use]
+    pub const fn mnemonic(&self) -> Option<Mnemonic> {
+        match self {
+            Self::Official { opcode, .. } => Some(opcode.mnemonic),
+            Self::Unknown(..) => None,
+        }
+    }
+
+    #[must_use]
+    pub const fn mode(&self) -> Option<AddressingMode> {
+        match self {
+            Self::Official { opcode, .. } => Some(opcode.mode),
+            Self::Unknown(..) => None,
+        }
+    }
+
+    /// Address the instruction reads from, writes to or jumps to, if it's known statically
+    ///
+    /// For indexed and indirect modes this is the base address (or pointer location)
+    #[must_use]
+    pub fn address_operand(&self) -> Option<u16> {
+        match self {
+            Self::Official { opcode, operand } => match opcode.mode {
+                AddressingMode::Implied
+                | AddressingMode::Accumulator
+                | AddressingMode::Immediate => None,
+                _ => Some(*operand),
+            },
+            Self::Unknown(..) => None,
+        }
+    }
+
+    /// Jump, subroutine or branch target of the instruction
+    #[must_use]
+    pub fn target(&self) -> Option<u16> {
+        match self {
+            Self::Official { opcode, operand }
+                if opcode.mnemonic.is_branch()
+                    || (matches!(opcode.mnemonic, Mnemonic::Jmp | Mnemonic::Jsr)
+                        && opcode.mode == AddressingMode::Absolute) =>
+            {
+                Some(*operand)
+            }
+            _ => None,
+        }
+    }
+
+    /// Write the operand of the instruction, replacing the address with a symbol
+    pub(crate) fn fmt_operand<W: fmt::Write>(
+        mode: AddressingMode,
+        operand: u16,
+        symbol: Option<&dyn fmt::Display>,
+        f: &mut W,
+    ) -> fmt::Result {
+        struct Value<'a>(u16, bool, Option<&'a dyn fmt::Display>);
+
+        impl fmt::Display for Value<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match self {
+                    Value(_, _, Some(symbol)) => write!(f, "{symbol}"),
+                    Value(value, false, None) => write!(f, "${value:02X}"),
+                    Value(value, true, None) => write!(f, "${value:04X}"),
+                }
+            }
+        }
+
+        let value = |wide| Value(operand, wide, symbol);
+        match mode {
+            AddressingMode::Implied => Ok(()),
+            AddressingMode::Accumulator => f.write_str("A"),
+            AddressingMode::Immediate => write!(f, "#${operand:02X}"),
+            AddressingMode::ZeroPage => write!(f, "{}", value(false)),
+            AddressingMode::ZeroPageX => write!(f, "{},X", value(false)),
+            AddressingMode::ZeroPageY => write!(f, "{},Y", value(false)),
+            AddressingMode::Absolute | AddressingMode::Relative => write!(f, "{}", value(true)),
+            AddressingMode::AbsoluteX => write!(f, "{},X", value(true)),
+            AddressingMode::AbsoluteY => write!(f, "{},Y", value(true)),
+            AddressingMode::Indirect => write!(f, "({})", value(true)),
+            AddressingMode::IndirectX => write!(f, "({},X)", value(false)),
+            AddressingMode::IndirectY => write!(f, "({}),Y", value(false)),
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Official { opcode, operand } => {
+                f.write_str(opcode.mnemonic.as_str())?;
+
+                if opcode.mode != AddressingMode::Implied {
+                    f.write_str(" ")?;
+                    Self::fmt_operand(opcode.mode, *operand, None, f)?;
+                }
+
+                Ok(())
+            }
+            Self::Unknown(byte) => write!(f, ".byte ${byte:02X}"),
+        }
+    }
+}
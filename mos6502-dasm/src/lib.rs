@@ -0,0 +1,133 @@
+#![no_std]
+#![warn(clippy::all, clippy::pedantic)]
+
+//!
+//! Disassembler for the official instruction set of the MOS 6502
+//!
+//! [Instruction reference](http://www.6502.org/tutorials/6502opcodes.html)
+//!
+
+extern crate alloc;
+
+use core::{array::TryFromSliceError, fmt};
+
+mod annotations;
+mod banks;
+mod cdl;
+mod instruction;
+mod listing;
+mod mlb;
+mod music;
+mod nl;
+mod opcode;
+mod trainer;
+
+pub use {
+    annotations::{Annotations, BankAnnotation, Comment, DataKind, DataRegion, Routine, Variable},
+    banks::{analyze_banks, Bank, BankLayout, Reachability},
+    cdl::{CdlFormat, ChrFlags, CodeDataLog, PrgFlags, MESEN_MAGIC},
+    instruction::Instruction,
+    listing::{ByteKind, Label, LabelKind, Listing, Tracer, Vectors, VECTORS_ADDRESS},
+    mlb::{parse_mlb, write_mlb, MemoryType, MlbLabel},
+    music::{locate_music_engine, MusicEngine, MusicTable, SoundEntryKind, SoundEntryPoint},
+    nl::{parse_nl, write_nl, NlEntry, NlFile},
+    opcode::{lookup, AddressingMode, Mnemonic, Opcode},
+    trainer::{analyze_trainer, TrainerAnalysis, TrainerSignature, TRAINER_ADDRESS},
+};
+
+#[derive(Debug)]
+pub enum Error {
+    /// The size of the CDL file doesn't match the size of the ROM
+    CdlSizeMismatch {
+        expected: usize,
+        actual: usize,
+    },
+
+    /// A line of a label file couldn't be parsed
+    InvalidLabelFile {
+        line: usize,
+    },
+
+    /// The annotations belong to a different PRG ROM
+    AnnotationsMismatch {
+        expected: u32,
+        actual: u32,
+    },
+
+    #[cfg(feature = "json")]
+    Json(serde_json::Error),
+
+    TryFromSlice(TryFromSliceError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CdlSizeMismatch { expected, actual } => write!(
+                f,
+                "CDL size mismatch; expected {expected} bytes of flags, got {actual}"
+            ),
+            Self::InvalidLabelFile { line } => write!(f, "Invalid label on line {line}"),
+            Self::AnnotationsMismatch { expected, actual } => write!(
+                f,
+                "The annotations belong to another ROM; expected a PRG ROM CRC32 of {expected:08X}, got {actual:08X}"
+            ),
+            #[cfg(feature = "json")]
+            Self::Json(err) => write!(f, "Invalid annotations: {err}"),
+            Self::TryFromSlice(..) => f.write_str("TryFromSliceError"),
+        }
+    }
+}
+
+impl From<TryFromSliceError> for Error {
+    fn from(err: TryFromSliceError) -> Self {
+        Self::TryFromSlice(err)
+    }
+}
+
+/// Iterator over the instructions of a piece of machine code
+///
+/// Yields the address, the decoded instruction and the raw bytes of every instruction
+#[derive(Clone, Debug)]
+pub struct Disassembler<'a> {
+    data: &'a [u8],
+    address: u16,
+}
+
+impl<'a> Disassembler<'a> {
+    /// Disassemble the machine code, assuming it's located at the given address
+    ///
+    /// For PRG ROM this is $8000 (or $C000 if the ROM only consists of one 16 KiB bank)
+    #[must_use]
+    pub fn new(data: &'a [u8], origin: u16) -> Self {
+        Self {
+            data,
+            address: origin,
+        }
+    }
+
+    /// Address of the next instruction
+    #[must_use]
+    pub fn address(&self) -> u16 {
+        self.address
+    }
+}
+
+impl<'a> Iterator for Disassembler<'a> {
+    type Item = (u16, Instruction, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let instruction = Instruction::decode(self.data, self.address)?;
+        let (bytes, rest) = self.data.split_at(instruction.len());
+
+        let address = self.address;
+        // The length of an instruction is at most three bytes
+        #[allow(clippy::cast_possible_truncation)]
+        {
+            self.address = self.address.wrapping_add(instruction.len() as u16);
+        }
+        self.data = rest;
+
+        Some((address, instruction, bytes))
+    }
+}
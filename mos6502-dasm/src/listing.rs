@@ -0,0 +1,621 @@
+use {
+    crate::{
+        annotations::address_in, AddressingMode, Annotations, CodeDataLog, Instruction, Mnemonic,
+        PrgFlags,
+    },
+    alloc::{collections::BTreeMap, string::String, vec, vec::Vec},
+    core::{convert::TryFrom, fmt},
+};
+
+/// Address of the NMI vector; the RESET and IRQ vectors follow directly after it
+pub const VECTORS_ADDRESS: u16 = 0xFFFA;
+
+// Maximum amount of data bytes per `.byte` line
+const BYTES_PER_LINE: usize = 8;
+
+/// The three interrupt vectors at the end of the address space
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Vectors {
+    pub nmi: u16,
+    pub reset: u16,
+    pub irq: u16,
+}
+
+/// What a byte of the program was identified as
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ByteKind {
+    /// The byte wasn't reached as code
+    Data,
+    /// The first byte of an instruction
+    Opcode,
+    /// An operand byte of an instruction
+    Operand,
+}
+
+/// Why an address got a label; the variants are ordered by priority
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LabelKind {
+    /// Target of the RESET vector
+    Reset,
+    /// Target of the NMI vector
+    Nmi,
+    /// Target of the IRQ vector
+    Irq,
+    /// Target of a `JSR`
+    Subroutine,
+    /// Target of a jump or branch
+    Location,
+    /// Address accessed by a load or store
+    Data,
+}
+
+/// Automatically generated label for an address
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Label {
+    pub address: u16,
+    pub kind: LabelKind,
+}
+
+impl fmt::Display for Label {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            LabelKind::Reset => f.write_str("reset"),
+            LabelKind::Nmi => f.write_str("nmi"),
+            LabelKind::Irq => f.write_str("irq"),
+            LabelKind::Subroutine => write!(f, "sub_{:04X}", self.address),
+            LabelKind::Location => write!(f, "loc_{:04X}", self.address),
+            LabelKind::Data => write!(f, "data_{:04X}", self.address),
+        }
+    }
+}
+
+// Name of an address as written to the output
+enum Symbol<'a> {
+    Name(&'a str),
+    Label(Label),
+}
+
+impl fmt::Display for Symbol<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Name(name) => f.write_str(name),
+            Self::Label(label) => label.fmt(f),
+        }
+    }
+}
+
+/// Recursive-traversal disassembler
+///
+/// Starting from the entry points, every reachable instruction gets decoded by following
+/// jumps, branches and subroutine calls. Everything which isn't reached is treated as data.
+#[derive(Clone, Debug)]
+pub struct Tracer<'a> {
+    data: &'a [u8],
+    origin: u16,
+    entry_points: Vec<(u16, LabelKind)>,
+    // Flags from a code/data log and the annotations for every byte of the program (empty if there are none)
+    flags: Vec<PrgFlags>,
+}
+
+/// Index of the address inside of a program located at the origin
+fn index_of(data: &[u8], origin: u16, address: u16) -> Option<usize> {
+    let index = usize::from(address.checked_sub(origin)?);
+
+    (index < data.len()).then_some(index)
+}
+
+impl<'a> Tracer<'a> {
+    /// Trace the program, assuming it's located at the given address
+    #[must_use]
+    pub fn new(data: &'a [u8], origin: u16) -> Self {
+        Self {
+            data,
+            origin,
+            entry_points: Vec::new(),
+            flags: Vec::new(),
+        }
+    }
+
+    /// Read the interrupt vectors, if the program reaches up to the end of the address space
+    #[must_use]
+    pub fn vectors(&self) -> Option<Vectors> {
+        let index = index_of(self.data, self.origin, VECTORS_ADDRESS)?;
+        let vectors = self.data.get(index..index + 6)?;
+        let word = |offset: usize| u16::from_le_bytes([vectors[offset], vectors[offset + 1]]);
+
+        Some(Vectors {
+            nmi: word(0),
+            reset: word(2),
+            irq: word(4),
+        })
+    }
+
+    /// Use the targets of the interrupt vectors as entry points
+    #[must_use]
+    pub fn with_vectors(mut self) -> Self {
+        if let Some(vectors) = self.vectors() {
+            self.entry_points.push((vectors.reset, LabelKind::Reset));
+            self.entry_points.push((vectors.nmi, LabelKind::Nmi));
+            self.entry_points.push((vectors.irq, LabelKind::Irq));
+        }
+
+        self
+    }
+
+    /// Add an additional entry point
+    #[must_use]
+    pub fn entry_point(mut self, address: u16, kind: LabelKind) -> Self {
+        self.entry_points.push((address, kind));
+        self
+    }
+
+    /// Use a code/data log to improve the listing
+    ///
+    /// Every byte the log marks as executed gets disassembled, even if it isn't reachable statically,
+    /// and bytes which were only read as data are never disassembled.
+    /// `prg_offset` is the offset of the traced program inside of the PRG ROM.
+    #[must_use]
+    pub fn code_data_log(mut self, code_data_log: &CodeDataLog, prg_offset: usize) -> Self {
+        let logged = code_data_log.prg_flags().iter().skip(prg_offset).copied();
+        for (flags, logged) in self.flags_mut().iter_mut().zip(logged) {
+            *flags |= logged;
+        }
+
+        self
+    }
+
+    /// Use the annotations of the ROM to improve the listing
+    ///
+    /// Known routines become entry points and data regions are never disassembled,
+    /// unless a code/data log saw them being executed.
+    /// `prg_offset` is the offset of the traced program inside of the PRG ROM.
+    #[must_use]
+    pub fn annotations(mut self, annotations: &Annotations, prg_offset: usize) -> Self {
+        for routine in &annotations.routines {
+            if let Some(address) = address_in(routine.offset, prg_offset, self.origin) {
+                if index_of(self.data, self.origin, address).is_some() {
+                    self.entry_points.push((address, LabelKind::Subroutine));
+                }
+            }
+        }
+
+        for region in &annotations.data {
+            let flags = self.flags_mut();
+            let start = region.offset.saturating_sub(prg_offset).min(flags.len());
+            let end = (region.offset + region.size)
+                .saturating_sub(prg_offset)
+                .min(flags.len());
+            for flags in &mut flags[start..end] {
+                *flags |= PrgFlags::DATA;
+            }
+        }
+
+        self
+    }
+
+    // Flags of every byte of the program, creating them if there aren't any yet
+    fn flags_mut(&mut self) -> &mut [PrgFlags] {
+        if self.flags.is_empty() {
+            self.flags = vec![PrgFlags::default(); self.data.len()];
+        }
+
+        &mut self.flags
+    }
+
+    /// Follow the code from all entry points
+    #[must_use]
+    pub fn run(self) -> Listing<'a> {
+        let mut listing = Listing {
+            data: self.data,
+            origin: self.origin,
+            kinds: vec![ByteKind::Data; self.data.len()],
+            labels: BTreeMap::new(),
+            names: BTreeMap::new(),
+            comments: BTreeMap::new(),
+        };
+
+        let mut worklist = Vec::new();
+        for (address, kind) in self.entry_points {
+            listing.add_label(address, kind);
+            worklist.push(address);
+        }
+
+        while let Some(address) = worklist.pop() {
+            listing.trace_block(address, &mut worklist, &self.flags);
+        }
+
+        // Disassemble everything the code/data log saw being executed
+        for index in 0..self.flags.len() {
+            let flags = self.flags[index];
+            if !flags.contains(PrgFlags::CODE) || listing.kinds[index] != ByteKind::Data {
+                continue;
+            }
+
+            let address = listing.address_of(index);
+            if flags.contains(PrgFlags::SUB_ENTRY_POINT) {
+                listing.add_label(address, LabelKind::Subroutine);
+            }
+
+            worklist.push(address);
+            while let Some(address) = worklist.pop() {
+                listing.trace_block(address, &mut worklist, &self.flags);
+            }
+        }
+
+        // Labels pointing into the middle of an instruction can't be emitted
+        let kinds = &listing.kinds;
+        let (data, origin) = (listing.data, listing.origin);
+        listing.labels.retain(|address, _| {
+            index_of(data, origin, *address).map(|index| kinds[index]) != Some(ByteKind::Operand)
+        });
+
+        listing
+    }
+}
+
+/// Result of tracing a program
+#[derive(Clone, Debug)]
+pub struct Listing<'a> {
+    data: &'a [u8],
+    origin: u16,
+    kinds: Vec<ByteKind>,
+    labels: BTreeMap<u16, LabelKind>,
+    names: BTreeMap<u16, String>,
+    comments: BTreeMap<u16, String>,
+}
+
+impl Listing<'_> {
+    fn index(&self, address: u16) -> Option<usize> {
+        index_of(self.data, self.origin, address)
+    }
+
+    /// Add a label to an address inside of the program, keeping the label with the higher priority
+    fn add_label(&mut self, address: u16, kind: LabelKind) {
+        if self.index(address).is_none() {
+            return;
+        }
+
+        self.labels
+            .entry(address)
+            .and_modify(|existing| *existing = (*existing).min(kind))
+            .or_insert(kind);
+    }
+
+    /// Decode instructions starting at the address until the control flow ends
+    ///
+    /// Bytes which the code/data log only saw being read as data stop the decoding
+    fn trace_block(&mut self, mut address: u16, worklist: &mut Vec<u16>, flags: &[PrgFlags]) {
+        while let Some(index) = self.index(address) {
+            if self.kinds[index] != ByteKind::Data {
+                // Either already traced or a jump into the middle of an instruction
+                break;
+            }
+
+            let (instruction, mnemonic, mode) =
+                match Instruction::decode(&self.data[index..], address) {
+                    Some(instruction @ Instruction::Official { opcode, .. }) => {
+                        (instruction, opcode.mnemonic, opcode.mode)
+                    }
+                    _ => break,
+                };
+
+            let end = index + instruction.len();
+            if self.kinds[index..end]
+                .iter()
+                .any(|kind| *kind != ByteKind::Data)
+            {
+                break;
+            }
+
+            let is_logged_data = |flags: &PrgFlags| {
+                flags.contains(PrgFlags::DATA) && !flags.contains(PrgFlags::CODE)
+            };
+            if flags
+                .get(index..end)
+                .is_some_and(|flags| flags.iter().any(is_logged_data))
+            {
+                break;
+            }
+
+            self.kinds[index] = ByteKind::Opcode;
+            for kind in &mut self.kinds[index + 1..end] {
+                *kind = ByteKind::Operand;
+            }
+
+            if let Some(target) = instruction.target() {
+                let kind = if mnemonic == Mnemonic::Jsr {
+                    LabelKind::Subroutine
+                } else {
+                    LabelKind::Location
+                };
+
+                self.add_label(target, kind);
+                worklist.push(target);
+            } else if let Some(operand) = instruction.address_operand() {
+                if matches!(
+                    mode,
+                    AddressingMode::Absolute
+                        | AddressingMode::AbsoluteX
+                        | AddressingMode::AbsoluteY
+                        | AddressingMode::Indirect
+                ) {
+                    self.add_label(operand, LabelKind::Data);
+                }
+            }
+
+            if matches!(
+                mnemonic,
+                Mnemonic::Jmp | Mnemonic::Rts | Mnemonic::Rti | Mnemonic::Brk
+            ) {
+                break;
+            }
+
+            // An instruction is at most three bytes long
+            #[allow(clippy::cast_possible_truncation)]
+            {
+                address = address.wrapping_add(instruction.len() as u16);
+            }
+        }
+    }
+
+    /// Address the program is located at
+    #[must_use]
+    pub fn origin(&self) -> u16 {
+        self.origin
+    }
+
+    /// Whether the address is inside of the program
+    #[must_use]
+    pub fn contains(&self, address: u16) -> bool {
+        self.index(address).is_some()
+    }
+
+    /// Assign a name to an address, replacing the generated label
+    ///
+    /// Addresses outside of the program (like RAM or registers) can be named as well,
+    /// they get written as constants at the start of the listing.
+    pub fn set_name<S: Into<String>>(&mut self, address: u16, name: S) {
+        self.names.insert(address, name.into());
+    }
+
+    /// Name assigned to the address, if there is one
+    #[must_use]
+    pub fn name(&self, address: u16) -> Option<&str> {
+        self.names.get(&address).map(String::as_str)
+    }
+
+    /// All assigned names, ordered by address
+    pub fn names(&self) -> impl Iterator<Item = (u16, &str)> + '_ {
+        self.names
+            .iter()
+            .map(|(address, name)| (*address, name.as_str()))
+    }
+
+    /// Attach a comment to an address; multiple lines are separated by `\n`
+    pub fn set_comment<S: Into<String>>(&mut self, address: u16, comment: S) {
+        self.comments.insert(address, comment.into());
+    }
+
+    /// Comment attached to the address, if there is one
+    #[must_use]
+    pub fn comment(&self, address: u16) -> Option<&str> {
+        self.comments.get(&address).map(String::as_str)
+    }
+
+    /// All comments, ordered by address
+    pub fn comments(&self) -> impl Iterator<Item = (u16, &str)> + '_ {
+        self.comments
+            .iter()
+            .map(|(address, comment)| (*address, comment.as_str()))
+    }
+
+    fn symbol(&self, address: u16) -> Option<Symbol<'_>> {
+        match self.name(address) {
+            Some(name) => Some(Symbol::Name(name)),
+            None => self.label(address).map(Symbol::Label),
+        }
+    }
+
+    // Whether the address starts a line of the listing, so a label can be placed there
+    fn is_line_start(&self, address: u16) -> bool {
+        self.byte_kind(address)
+            .is_some_and(|kind| kind != ByteKind::Operand)
+    }
+
+    /// What the byte at the address was identified as
+    #[must_use]
+    pub fn byte_kind(&self, address: u16) -> Option<ByteKind> {
+        self.index(address).map(|index| self.kinds[index])
+    }
+
+    /// Label at the address, if there is one
+    #[must_use]
+    pub fn label(&self, address: u16) -> Option<Label> {
+        self.labels.get(&address).map(|kind| Label {
+            address,
+            kind: *kind,
+        })
+    }
+
+    /// All labels, ordered by address
+    pub fn labels(&self) -> impl Iterator<Item = Label> + '_ {
+        self.labels.iter().map(|(address, kind)| Label {
+            address: *address,
+            kind: *kind,
+        })
+    }
+
+    /// All decoded instructions, ordered by address
+    pub fn instructions(&self) -> impl Iterator<Item = (u16, Instruction)> + '_ {
+        self.kinds
+            .iter()
+            .enumerate()
+            .filter(|(_, kind)| **kind == ByteKind::Opcode)
+            .filter_map(move |(index, _)| {
+                let address = self.address_of(index);
+                Instruction::decode(&self.data[index..], address)
+                    .map(|instruction| (address, instruction))
+            })
+    }
+
+    fn address_of(&self, index: usize) -> u16 {
+        // Indices are always derived from addresses, so they fit
+        #[allow(clippy::cast_possible_truncation)]
+        self.origin.wrapping_add(index as u16)
+    }
+
+    /// Write the listing as ca65-compatible assembly source
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing to the output fails
+    pub fn write_ca65<W: fmt::Write>(&self, out: &mut W) -> fmt::Result {
+        writeln!(out, "; Disassembled by mos6502-dasm")?;
+        writeln!(out, ".setcpu \"6502\"")?;
+
+        // Names which can't be placed as labels become constants
+        let constants = self
+            .names()
+            .filter(|(address, _)| !self.is_line_start(*address))
+            .collect::<Vec<_>>();
+        if !constants.is_empty() {
+            writeln!(out)?;
+        }
+        for (address, name) in constants {
+            self.write_comment(out, address)?;
+            writeln!(out, "{name} = ${address:04X}")?;
+        }
+
+        writeln!(out)?;
+        writeln!(out, ".org ${:04X}", self.origin)?;
+
+        let mut index = 0;
+        while index < self.data.len() {
+            let address = self.address_of(index);
+            let symbol = self.symbol(address);
+            if symbol.is_some() || self.comment(address).is_some() {
+                writeln!(out)?;
+                self.write_comment(out, address)?;
+            }
+            if let Some(symbol) = symbol {
+                writeln!(out, "{symbol}:")?;
+            }
+
+            let instruction = match self.kinds[index] {
+                ByteKind::Opcode => Instruction::decode(&self.data[index..], address),
+                _ => None,
+            };
+
+            if let Some(instruction) = instruction {
+                self.write_instruction(out, instruction)?;
+                index += instruction.len();
+            } else if address == VECTORS_ADDRESS && self.is_plain_data(index, 6) {
+                let vector = |offset: usize| {
+                    u16::from_le_bytes([self.data[index + offset], self.data[index + offset + 1]])
+                };
+
+                write!(out, "    .word ")?;
+                for (position, offset) in [0, 2, 4].iter().enumerate() {
+                    if position > 0 {
+                        write!(out, ", ")?;
+                    }
+
+                    self.write_address(out, vector(*offset))?;
+                }
+                writeln!(out)?;
+
+                index += 6;
+            } else {
+                let mut length = 1;
+                while length < BYTES_PER_LINE && self.is_plain_data(index, length + 1) {
+                    length += 1;
+                }
+
+                write!(out, "    .byte ")?;
+                for (position, byte) in self.data[index..index + length].iter().enumerate() {
+                    if position > 0 {
+                        write!(out, ", ")?;
+                    }
+
+                    write!(out, "${byte:02X}")?;
+                }
+                writeln!(out)?;
+
+                index += length;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether the bytes starting at the index are data without any labels (apart from the first byte)
+    ///
+    /// Runs of data also get split at the interrupt vectors, so they can be written as words
+    fn is_plain_data(&self, index: usize, length: usize) -> bool {
+        index + length <= self.data.len()
+            && (index..index + length).all(|index| self.kinds[index] == ByteKind::Data)
+            && (index + 1..index + length).all(|index| {
+                let address = self.address_of(index);
+                self.symbol(address).is_none()
+                    && self.comment(address).is_none()
+                    && address != VECTORS_ADDRESS
+            })
+    }
+
+    fn write_comment<W: fmt::Write>(&self, out: &mut W, address: u16) -> fmt::Result {
+        if let Some(comment) = self.comment(address) {
+            for line in comment.lines() {
+                writeln!(out, "; {line}")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_address<W: fmt::Write>(&self, out: &mut W, address: u16) -> fmt::Result {
+        match self.symbol(address) {
+            Some(symbol) => write!(out, "{symbol}"),
+            None => write!(out, "${address:04X}"),
+        }
+    }
+
+    fn write_instruction<W: fmt::Write>(
+        &self,
+        out: &mut W,
+        instruction: Instruction,
+    ) -> fmt::Result {
+        let (opcode, operand) = match instruction {
+            Instruction::Official { opcode, operand } => (opcode, operand),
+            Instruction::Unknown(byte) => return writeln!(out, "    .byte ${byte:02X}"),
+        };
+
+        write!(out, "    {}", opcode.mnemonic)?;
+        if opcode.mode == AddressingMode::Implied {
+            return writeln!(out);
+        }
+        write!(out, " ")?;
+
+        let symbol = instruction
+            .address_operand()
+            .and_then(|address| self.symbol(address));
+        let symbol = symbol.as_ref().map(|symbol| symbol as &dyn fmt::Display);
+
+        // ca65 would assemble absolute addresses in the zero page with zero page addressing
+        let is_absolute = matches!(
+            opcode.mode,
+            AddressingMode::Absolute | AddressingMode::AbsoluteX | AddressingMode::AbsoluteY
+        );
+        if is_absolute && symbol.is_none() && u8::try_from(operand).is_ok() {
+            write!(out, "a:")?;
+        }
+
+        Instruction::fmt_operand(opcode.mode, operand, symbol, out)?;
+        writeln!(out)
+    }
+}
+
+impl fmt::Display for Listing<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_ca65(f)
+    }
+}
@@ -0,0 +1,232 @@
+use {
+    crate::{Error, Listing},
+    alloc::{borrow::ToOwned, string::String, vec::Vec},
+    core::{convert::TryFrom, fmt},
+};
+
+// Start of the cartridge RAM in the CPU address space
+const WORK_RAM_ADDRESS: u16 = 0x6000;
+
+/// Memory a Mesen label refers to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MemoryType {
+    /// Offset into the PRG ROM
+    PrgRom,
+    /// Address in the 2 KiB of internal RAM
+    InternalRam,
+    /// Offset into the battery-backed cartridge RAM
+    SaveRam,
+    /// Offset into the cartridge RAM without battery
+    WorkRam,
+    /// Address in the CPU address space, used for registers
+    Register,
+}
+
+impl MemoryType {
+    /// Parse the memory type prefix of a label, accepting both the short and the long (Mesen 2) prefixes
+    #[must_use]
+    pub fn from_prefix(prefix: &str) -> Option<Self> {
+        let memory_type = match prefix {
+            "P" | "NesPrgRom" => Self::PrgRom,
+            "R" | "NesInternalRam" => Self::InternalRam,
+            "S" | "NesSaveRam" => Self::SaveRam,
+            "W" | "NesWorkRam" => Self::WorkRam,
+            "G" | "NesMemory" => Self::Register,
+            _ => return None,
+        };
+
+        Some(memory_type)
+    }
+
+    /// Short prefix of the memory type
+    #[must_use]
+    pub const fn prefix(self) -> &'static str {
+        match self {
+            Self::PrgRom => "P",
+            Self::InternalRam => "R",
+            Self::SaveRam => "S",
+            Self::WorkRam => "W",
+            Self::Register => "G",
+        }
+    }
+}
+
+/// Label of a Mesen label file (`.mlb`)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MlbLabel {
+    pub memory_type: MemoryType,
+    /// Address or offset, depending on the memory type
+    pub address: u32,
+    /// Last address of a label spanning multiple bytes
+    pub end_address: Option<u32>,
+    /// Name of the label; empty if the label only carries a comment
+    pub name: String,
+    /// Comment of the label; multiple lines are separated by `\n`
+    pub comment: String,
+}
+
+impl MlbLabel {
+    /// Address of the label inside of the CPU address space
+    ///
+    /// `prg_offset` is the offset of the program located at the origin of the listing inside of the PRG ROM
+    #[must_use]
+    pub fn cpu_address(&self, listing: &Listing<'_>, prg_offset: usize) -> Option<u16> {
+        match self.memory_type {
+            MemoryType::PrgRom => {
+                let index = usize::try_from(self.address)
+                    .ok()?
+                    .checked_sub(prg_offset)?;
+                let index = u16::try_from(index).ok()?;
+                let address = listing.origin().checked_add(index)?;
+
+                listing.contains(address).then_some(address)
+            }
+            MemoryType::InternalRam | MemoryType::Register => u16::try_from(self.address).ok(),
+            MemoryType::SaveRam | MemoryType::WorkRam => u16::try_from(self.address)
+                .ok()
+                .and_then(|offset| WORK_RAM_ADDRESS.checked_add(offset)),
+        }
+    }
+}
+
+impl fmt::Display for MlbLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{:04X}", self.memory_type.prefix(), self.address)?;
+        if let Some(end_address) = self.end_address {
+            write!(f, "-{end_address:04X}")?;
+        }
+        write!(f, ":{}", self.name)?;
+
+        if !self.comment.is_empty() {
+            f.write_str(":")?;
+            for (position, line) in self.comment.split('\n').enumerate() {
+                if position > 0 {
+                    f.write_str("\\n")?;
+                }
+                f.write_str(line)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Parse the contents of a Mesen label file
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidLabelFile`] if a line isn't a valid label
+pub fn parse_mlb(source: &str) -> Result<Vec<MlbLabel>, Error> {
+    source
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| parse_line(line).ok_or(Error::InvalidLabelFile { line: index + 1 }))
+        .collect()
+}
+
+fn parse_line(line: &str) -> Option<MlbLabel> {
+    // The comment is allowed to contain colons
+    let mut parts = line.trim_end_matches('\r').splitn(4, ':');
+    let memory_type = MemoryType::from_prefix(parts.next()?)?;
+
+    let address = parts.next()?;
+    let (address, end_address) = match address.split_once('-') {
+        Some((start, end)) => (start, Some(u32::from_str_radix(end, 16).ok()?)),
+        None => (address, None),
+    };
+    let address = u32::from_str_radix(address, 16).ok()?;
+
+    let name = parts.next()?.to_owned();
+    let comment = parts.next().unwrap_or_default().replace("\\n", "\n");
+
+    Some(MlbLabel {
+        memory_type,
+        address,
+        end_address,
+        name,
+        comment,
+    })
+}
+
+/// Write labels as a Mesen label file
+///
+/// # Errors
+///
+/// Returns an error if writing to the output fails
+pub fn write_mlb<W: fmt::Write>(labels: &[MlbLabel], out: &mut W) -> fmt::Result {
+    for label in labels {
+        writeln!(out, "{label}")?;
+    }
+
+    Ok(())
+}
+
+impl Listing<'_> {
+    /// Apply the names and comments of Mesen labels to the listing
+    ///
+    /// `prg_offset` is the offset of the traced program inside of the PRG ROM.
+    /// Labels of PRG ROM outside of the traced program are skipped.
+    pub fn import_mlb(&mut self, labels: &[MlbLabel], prg_offset: usize) {
+        for label in labels {
+            let Some(address) = label.cpu_address(self, prg_offset) else {
+                continue;
+            };
+
+            if !label.name.is_empty() {
+                self.set_name(address, label.name.as_str());
+            }
+            if !label.comment.is_empty() {
+                self.set_comment(address, label.comment.as_str());
+            }
+        }
+    }
+
+    /// Export the names, comments and generated labels of the listing as Mesen labels
+    ///
+    /// `prg_offset` is the offset of the traced program inside of the PRG ROM.
+    /// Names outside of the program are exported as internal RAM, cartridge RAM or register labels.
+    #[must_use]
+    pub fn export_mlb(&self, prg_offset: usize) -> Vec<MlbLabel> {
+        let mut addresses = self
+            .labels()
+            .map(|label| label.address)
+            .chain(self.names().map(|(address, _)| address))
+            .chain(self.comments().map(|(address, _)| address))
+            .collect::<Vec<_>>();
+        addresses.sort_unstable();
+        addresses.dedup();
+
+        addresses
+            .into_iter()
+            .map(|address| {
+                let (memory_type, offset) = if self.contains(address) {
+                    let index = usize::from(address.wrapping_sub(self.origin()));
+                    (MemoryType::PrgRom, prg_offset + index)
+                } else if address < 0x2000 {
+                    // Internal RAM is mirrored every 2 KiB
+                    (MemoryType::InternalRam, usize::from(address & 0x07FF))
+                } else if (WORK_RAM_ADDRESS..0x8000).contains(&address) {
+                    (MemoryType::WorkRam, usize::from(address - WORK_RAM_ADDRESS))
+                } else {
+                    (MemoryType::Register, usize::from(address))
+                };
+
+                let name = match (self.name(address), self.label(address)) {
+                    (Some(name), _) => name.to_owned(),
+                    (None, Some(label)) => alloc::format!("{label}"),
+                    (None, None) => String::new(),
+                };
+
+                MlbLabel {
+                    memory_type,
+                    // PRG ROM offsets are far below 4 GiB
+                    address: u32::try_from(offset).unwrap_or(u32::MAX),
+                    end_address: None,
+                    name,
+                    comment: self.comment(address).unwrap_or_default().to_owned(),
+                }
+            })
+            .collect()
+    }
+}
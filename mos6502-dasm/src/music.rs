@@ -0,0 +1,325 @@
+use {
+    crate::{AddressingMode, ByteKind, Instruction, LabelKind, Listing, Mnemonic},
+    alloc::{
+        collections::{BTreeMap, BTreeSet},
+        vec,
+        vec::Vec,
+    },
+    core::{cmp::Reverse, ops::Range},
+};
+
+// Sound channels of the APU; `$4014` is the OAM DMA, and `$4015` gets written by the reset code of most games as well
+const APU_SOUND_REGISTERS: Range<u16> = 0x4000..0x4014;
+// Registers of the graphics, which sound code doesn't touch
+const PPU_REGISTERS: Range<u16> = 0x2000..0x4000;
+const OAM_DMA: u16 = 0x4014;
+
+/// How the sound code is used by the rest of the game
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SoundEntryKind {
+    /// Called with an immediate value in `A`, like the song number passed to the init routine of an NSF
+    Init,
+    /// Called by the NMI handler once per frame, like the play routine of an NSF
+    Play,
+    /// Neither, like routines starting sound effects with the number in `X` or `Y`
+    Other,
+}
+
+/// Topmost routine of the sound code, which the rest of the game calls into
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SoundEntryPoint {
+    pub address: u16,
+    pub kind: SoundEntryKind,
+    /// Addresses of the `JSR`s and `JMP`s calling the routine
+    pub call_sites: Vec<u16>,
+    /// Amount of call sites loading an immediate value into `A` right before the call
+    pub immediate_calls: usize,
+    /// Amount of writes to the APU registers by the routine and the routines it calls
+    pub apu_writes: usize,
+}
+
+/// Table read by the sound code, like the periods of the notes or the pointers to the songs
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MusicTable {
+    pub address: u16,
+    /// Addresses of the instructions reading from the table
+    pub readers: Vec<u16>,
+}
+
+/// Sound code found by [`locate_music_engine`]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MusicEngine {
+    /// Routines writing to the APU registers themselves
+    pub drivers: Vec<u16>,
+    /// Ordered by the amount of APU writes, the routine doing the most first
+    pub entry_points: Vec<SoundEntryPoint>,
+    /// Tables in the program read by the sound code, ordered by address
+    pub tables: Vec<MusicTable>,
+}
+
+impl MusicEngine {
+    /// Likely init routine: the one most often called with an immediate value in `A`
+    #[must_use]
+    pub fn init(&self) -> Option<u16> {
+        self.entry_points
+            .iter()
+            .filter(|entry_point| entry_point.kind == SoundEntryKind::Init)
+            .max_by_key(|entry_point| entry_point.immediate_calls)
+            .map(|entry_point| entry_point.address)
+    }
+
+    /// Likely play routine: the one called by the NMI handler doing the most APU writes
+    #[must_use]
+    pub fn play(&self) -> Option<u16> {
+        self.entry_points
+            .iter()
+            .find(|entry_point| entry_point.kind == SoundEntryKind::Play)
+            .map(|entry_point| entry_point.address)
+    }
+}
+
+// What a routine does by itself, without the routines it calls
+#[derive(Default)]
+struct Routine {
+    // Call sites with the routines they call
+    calls: Vec<(u16, u16)>,
+    // Call sites loading an immediate value into `A` right before the call
+    immediate_calls: BTreeSet<u16>,
+    apu_writes: usize,
+    touches_ppu: bool,
+    // Instructions reading from absolute addresses, with the address they read from
+    reads: Vec<(u16, u16)>,
+}
+
+// Follow the code of a routine until it returns, taking jumps to other routines as tail calls
+fn trace_routine(
+    instructions: &BTreeMap<u16, Instruction>,
+    entries: &BTreeSet<u16>,
+    entry: u16,
+) -> Routine {
+    let mut routine = Routine::default();
+    let mut visited = BTreeSet::new();
+    let mut worklist = vec![entry];
+
+    while let Some(mut address) = worklist.pop() {
+        let mut immediate = false;
+
+        while visited.insert(address) {
+            let Some(instruction @ Instruction::Official { opcode, operand }) =
+                instructions.get(&address).copied()
+            else {
+                break;
+            };
+            let absolute = matches!(
+                opcode.mode,
+                AddressingMode::Absolute | AddressingMode::AbsoluteX | AddressingMode::AbsoluteY
+            );
+
+            match opcode.mnemonic {
+                Mnemonic::Jsr => {
+                    routine.calls.push((address, operand));
+                    if immediate {
+                        routine.immediate_calls.insert(address);
+                    }
+                }
+                Mnemonic::Jmp if opcode.mode == AddressingMode::Absolute => {
+                    if entries.contains(&operand) && operand != entry {
+                        routine.calls.push((address, operand));
+                        if immediate {
+                            routine.immediate_calls.insert(address);
+                        }
+                    } else {
+                        worklist.push(operand);
+                    }
+                    break;
+                }
+                Mnemonic::Jmp | Mnemonic::Rts | Mnemonic::Rti | Mnemonic::Brk => break,
+                mnemonic if mnemonic.is_branch() => worklist.push(operand),
+                Mnemonic::Sta | Mnemonic::Stx | Mnemonic::Sty if absolute => {
+                    if APU_SOUND_REGISTERS.contains(&operand) {
+                        routine.apu_writes += 1;
+                    } else if PPU_REGISTERS.contains(&operand) || operand == OAM_DMA {
+                        routine.touches_ppu = true;
+                    }
+                }
+                _ if absolute => {
+                    // Like polling the status of the PPU
+                    if PPU_REGISTERS.contains(&operand) {
+                        routine.touches_ppu = true;
+                    }
+                    routine.reads.push((address, operand));
+                }
+                _ => (),
+            }
+
+            immediate =
+                opcode.mnemonic == Mnemonic::Lda && opcode.mode == AddressingMode::Immediate;
+            // An instruction is at most three bytes long
+            #[allow(clippy::cast_possible_truncation)]
+            {
+                address = address.wrapping_add(instruction.len() as u16);
+            }
+        }
+    }
+
+    routine
+}
+
+// The routine and every routine it calls, directly or not
+fn call_tree(routines: &BTreeMap<u16, Routine>, entry: u16) -> BTreeSet<u16> {
+    let mut tree = BTreeSet::new();
+    let mut worklist = vec![entry];
+
+    while let Some(address) = worklist.pop() {
+        let Some(routine) = routines.get(&address) else {
+            continue;
+        };
+        if tree.insert(address) {
+            worklist.extend(routine.calls.iter().map(|(_, callee)| *callee));
+        }
+    }
+
+    tree
+}
+
+// Data of the program read by the routines, grouped by the address they read from
+fn find_tables(
+    listing: &Listing<'_>,
+    routines: &BTreeMap<u16, Routine>,
+    engine: &BTreeSet<u16>,
+) -> Vec<MusicTable> {
+    let mut tables = BTreeMap::<u16, Vec<u16>>::new();
+    for routine in engine {
+        for (reader, address) in &routines[routine].reads {
+            if listing.byte_kind(*address) == Some(ByteKind::Data) {
+                tables.entry(*address).or_default().push(*reader);
+            }
+        }
+    }
+
+    tables
+        .into_iter()
+        .map(|(address, mut readers)| {
+            readers.sort_unstable();
+            readers.dedup();
+            MusicTable { address, readers }
+        })
+        .collect()
+}
+
+/// Locate the sound code of a game, to find the init and play routines for an NSF rip
+///
+/// The routines writing to the APU registers at `$4000`-`$4013` are traced back through their callers,
+/// up to the topmost ones which, together with everything they call, only do sound: they write to the APU but
+/// never touch the PPU. Those are the entry points of the engine; the one the NMI handler calls is likely the play
+/// routine, and the one called with an immediate value in `A` (the number of the song) the init routine.
+/// The tables read by the sound code point to the music data.
+///
+/// Only the code of the listing is searched, so sound code in a switchable bank needs a listing of that bank
+/// traced from the calls into it.
+#[must_use]
+pub fn locate_music_engine(listing: &Listing<'_>) -> MusicEngine {
+    let instructions: BTreeMap<u16, Instruction> = listing.instructions().collect();
+    let labels = listing
+        .labels()
+        .filter(|label| label.kind <= LabelKind::Subroutine);
+    let (mut entries, mut roots, mut nmi) = (BTreeSet::new(), BTreeSet::new(), Vec::new());
+    for label in labels {
+        entries.insert(label.address);
+        if label.kind != LabelKind::Subroutine {
+            roots.insert(label.address);
+        }
+        if label.kind == LabelKind::Nmi {
+            nmi.push(label.address);
+        }
+    }
+
+    let routines: BTreeMap<u16, Routine> = entries
+        .iter()
+        .map(|entry| (*entry, trace_routine(&instructions, &entries, *entry)))
+        .collect();
+    let trees: BTreeMap<u16, BTreeSet<u16>> = routines
+        .keys()
+        .map(|address| (*address, call_tree(&routines, *address)))
+        .collect();
+    let apu_writes = |address: u16| -> usize {
+        trees[&address]
+            .iter()
+            .map(|routine| routines[routine].apu_writes)
+            .sum()
+    };
+
+    // The interrupt handlers never return, so they aren't part of the sound code
+    let sound: BTreeSet<u16> = routines
+        .keys()
+        .copied()
+        .filter(|address| {
+            !roots.contains(address)
+                && apu_writes(*address) > 0
+                && !trees[address]
+                    .iter()
+                    .any(|routine| routines[routine].touches_ppu)
+        })
+        .collect();
+
+    // Call sites of every routine, with the routine they're in
+    let mut callers = BTreeMap::<u16, Vec<(u16, u16)>>::new();
+    for (caller, routine) in &routines {
+        for (call_site, callee) in &routine.calls {
+            callers
+                .entry(*callee)
+                .or_default()
+                .push((*call_site, *caller));
+        }
+    }
+    let in_nmi: BTreeSet<u16> = nmi
+        .iter()
+        .flat_map(|address| trees[address].iter().copied())
+        .collect();
+
+    let mut entry_points: Vec<SoundEntryPoint> = sound
+        .iter()
+        .filter_map(|address| {
+            let calls = callers.get(address).map(Vec::as_slice).unwrap_or_default();
+            // Routines only called by other sound code are part of the engine
+            if !calls.is_empty() && calls.iter().all(|(_, caller)| sound.contains(caller)) {
+                return None;
+            }
+
+            let immediate_calls = calls
+                .iter()
+                .filter(|(call_site, caller)| routines[caller].immediate_calls.contains(call_site))
+                .count();
+            let kind = if immediate_calls > 0 {
+                SoundEntryKind::Init
+            } else if calls.iter().any(|(_, caller)| in_nmi.contains(caller)) {
+                SoundEntryKind::Play
+            } else {
+                SoundEntryKind::Other
+            };
+
+            Some(SoundEntryPoint {
+                address: *address,
+                kind,
+                call_sites: calls.iter().map(|(call_site, _)| *call_site).collect(),
+                immediate_calls,
+                apu_writes: apu_writes(*address),
+            })
+        })
+        .collect();
+    entry_points.sort_by_key(|entry_point| Reverse(entry_point.apu_writes));
+
+    let engine: BTreeSet<u16> = entry_points
+        .iter()
+        .flat_map(|entry_point| trees[&entry_point.address].iter().copied())
+        .collect();
+    MusicEngine {
+        drivers: routines
+            .iter()
+            .filter(|(_, routine)| routine.apu_writes > 0)
+            .map(|(address, _)| *address)
+            .collect(),
+        entry_points,
+        tables: find_tables(listing, &routines, &engine),
+    }
+}
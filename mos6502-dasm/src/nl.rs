@@ -0,0 +1,175 @@
+use {
+    crate::{Error, Listing},
+    alloc::{borrow::ToOwned, string::String, vec::Vec},
+    core::fmt,
+};
+
+/// Which FCEUX name list file entries belong to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NlFile {
+    /// Names of RAM and registers (`$0000` - `$7FFF`), stored in `<rom>.ram.nl`
+    Ram,
+    /// Names inside of a 16 KiB PRG ROM bank, stored in `<rom>.<bank>.nl`
+    Bank(usize),
+}
+
+impl NlFile {
+    /// Suffix which gets appended to the file name of the ROM (including its extension)
+    #[must_use]
+    pub fn suffix(self) -> String {
+        match self {
+            Self::Ram => ".ram.nl".to_owned(),
+            Self::Bank(bank) => alloc::format!(".{bank:X}.nl"),
+        }
+    }
+}
+
+/// Entry of an FCEUX name list file (`.nl`)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NlEntry {
+    pub address: u16,
+    /// Length in bytes if the entry names an array
+    pub size: Option<u16>,
+    pub name: String,
+    /// Comment of the entry; multiple lines are separated by `\n`
+    pub comment: String,
+}
+
+impl fmt::Display for NlEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "${:04X}", self.address)?;
+        if let Some(size) = self.size {
+            write!(f, "/{size:X}")?;
+        }
+        write!(f, "#{}#", self.name)?;
+
+        // Comment lines are continued by a trailing backslash
+        for (position, line) in self.comment.split('\n').enumerate() {
+            if position > 0 {
+                f.write_str("\\\n")?;
+            }
+            f.write_str(line)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Parse the contents of an FCEUX name list file
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidLabelFile`] if a line isn't a valid entry
+pub fn parse_nl(source: &str) -> Result<Vec<NlEntry>, Error> {
+    let mut entries = Vec::new();
+    let mut lines = source.lines().enumerate();
+
+    while let Some((index, line)) = lines.next() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let mut entry = parse_line(line).ok_or(Error::InvalidLabelFile { line: index + 1 })?;
+        while entry.comment.ends_with('\\') {
+            entry.comment.pop();
+            entry.comment.push('\n');
+
+            match lines.next() {
+                Some((_, line)) => entry.comment.push_str(line.trim_end_matches('\r')),
+                None => break,
+            }
+        }
+
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+fn parse_line(line: &str) -> Option<NlEntry> {
+    // The comment is allowed to contain hash signs
+    let mut parts = line.strip_prefix('$')?.splitn(3, '#');
+
+    let address = parts.next()?;
+    let (address, size) = match address.split_once('/') {
+        Some((address, size)) => (address, Some(u16::from_str_radix(size, 16).ok()?)),
+        None => (address, None),
+    };
+    let address = u16::from_str_radix(address, 16).ok()?;
+
+    let name = parts.next()?.to_owned();
+    let comment = parts.next().unwrap_or_default().to_owned();
+
+    Some(NlEntry {
+        address,
+        size,
+        name,
+        comment,
+    })
+}
+
+/// Write entries as an FCEUX name list file
+///
+/// # Errors
+///
+/// Returns an error if writing to the output fails
+pub fn write_nl<W: fmt::Write>(entries: &[NlEntry], out: &mut W) -> fmt::Result {
+    for entry in entries {
+        writeln!(out, "{entry}")?;
+    }
+
+    Ok(())
+}
+
+impl Listing<'_> {
+    /// Apply the names and comments of FCEUX name list entries to the listing
+    pub fn import_nl(&mut self, entries: &[NlEntry]) {
+        for entry in entries {
+            if !entry.name.is_empty() {
+                self.set_name(entry.address, entry.name.as_str());
+            }
+            if !entry.comment.is_empty() {
+                self.set_comment(entry.address, entry.comment.as_str());
+            }
+        }
+    }
+
+    /// Export the names, comments and generated labels of the listing as FCEUX name list entries
+    ///
+    /// [`NlFile::Bank`] exports everything inside of the program, while [`NlFile::Ram`] exports
+    /// names and comments of addresses below `$8000` outside of the program.
+    #[must_use]
+    pub fn export_nl(&self, file: NlFile) -> Vec<NlEntry> {
+        let mut addresses = self
+            .labels()
+            .map(|label| label.address)
+            .chain(self.names().map(|(address, _)| address))
+            .chain(self.comments().map(|(address, _)| address))
+            .filter(|address| match file {
+                NlFile::Bank(..) => self.contains(*address),
+                NlFile::Ram => !self.contains(*address) && *address < 0x8000,
+            })
+            .collect::<Vec<_>>();
+        addresses.sort_unstable();
+        addresses.dedup();
+
+        addresses
+            .into_iter()
+            .map(|address| {
+                let name = match (self.name(address), self.label(address)) {
+                    (Some(name), _) => name.to_owned(),
+                    (None, Some(label)) => alloc::format!("{label}"),
+                    (None, None) => String::new(),
+                };
+
+                NlEntry {
+                    address,
+                    size: None,
+                    name,
+                    comment: self.comment(address).unwrap_or_default().to_owned(),
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,393 @@
+use core::fmt;
+
+/// Mnemonics of all official 6502 instructions
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Mnemonic {
+    Adc,
+    And,
+    Asl,
+    Bcc,
+    Bcs,
+    Beq,
+    Bit,
+    Bmi,
+    Bne,
+    Bpl,
+    Brk,
+    Bvc,
+    Bvs,
+    Clc,
+    Cld,
+    Cli,
+    Clv,
+    Cmp,
+    Cpx,
+    Cpy,
+    Dec,
+    Dex,
+    Dey,
+    Eor,
+    Inc,
+    Inx,
+    Iny,
+    Jmp,
+    Jsr,
+    Lda,
+    Ldx,
+    Ldy,
+    Lsr,
+    Nop,
+    Ora,
+    Pha,
+    Php,
+    Pla,
+    Plp,
+    Rol,
+    Ror,
+    Rti,
+    Rts,
+    Sbc,
+    Sec,
+    Sed,
+    Sei,
+    Sta,
+    Stx,
+    Sty,
+    Tax,
+    Tay,
+    Tsx,
+    Txa,
+    Txs,
+    Tya,
+}
+
+impl Mnemonic {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Adc => "ADC",
+            Self::And => "AND",
+            Self::Asl => "ASL",
+            Self::Bcc => "BCC",
+            Self::Bcs => "BCS",
+            Self::Beq => "BEQ",
+            Self::Bit => "BIT",
+            Self::Bmi => "BMI",
+            Self::Bne => "BNE",
+            Self::Bpl => "BPL",
+            Self::Brk => "BRK",
+            Self::Bvc => "BVC",
+            Self::Bvs => "BVS",
+            Self::Clc => "CLC",
+            Self::Cld => "CLD",
+            Self::Cli => "CLI",
+            Self::Clv => "CLV",
+            Self::Cmp => "CMP",
+            Self::Cpx => "CPX",
+            Self::Cpy => "CPY",
+            Self::Dec => "DEC",
+            Self::Dex => "DEX",
+            Self::Dey => "DEY",
+            Self::Eor => "EOR",
+            Self::Inc => "INC",
+            Self::Inx => "INX",
+            Self::Iny => "INY",
+            Self::Jmp => "JMP",
+            Self::Jsr => "JSR",
+            Self::Lda => "LDA",
+            Self::Ldx => "LDX",
+            Self::Ldy => "LDY",
+            Self::Lsr => "LSR",
+            Self::Nop => "NOP",
+            Self::Ora => "ORA",
+            Self::Pha => "PHA",
+            Self::Php => "PHP",
+            Self::Pla => "PLA",
+            Self::Plp => "PLP",
+            Self::Rol => "ROL",
+            Self::Ror => "ROR",
+            Self::Rti => "RTI",
+            Self::Rts => "RTS",
+            Self::Sbc => "SBC",
+            Self::Sec => "SEC",
+            Self::Sed => "SED",
+            Self::Sei => "SEI",
+            Self::Sta => "STA",
+            Self::Stx => "STX",
+            Self::Sty => "STY",
+            Self::Tax => "TAX",
+            Self::Tay => "TAY",
+            Self::Tsx => "TSX",
+            Self::Txa => "TXA",
+            Self::Txs => "TXS",
+            Self::Tya => "TYA",
+        }
+    }
+
+    /// Whether the instruction is a conditional branch
+    #[must_use]
+    pub const fn is_branch(self) -> bool {
+        matches!(
+            self,
+            Self::Bcc
+                | Self::Bcs
+                | Self::Beq
+                | Self::Bmi
+                | Self::Bne
+                | Self::Bpl
+                | Self::Bvc
+                | Self::Bvs
+        )
+    }
+}
+
+impl fmt::Display for Mnemonic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Addressing modes of the 6502
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AddressingMode {
+    /// No operand (`CLC`)
+    Implied,
+    /// Operates on the accumulator (`ASL A`)
+    Accumulator,
+    /// 8-bit constant (`LDA #$10`)
+    Immediate,
+    /// 8-bit address (`LDA $10`)
+    ZeroPage,
+    /// 8-bit address indexed by X (`LDA $10,X`)
+    ZeroPageX,
+    /// 8-bit address indexed by Y (`LDX $10,Y`)
+    ZeroPageY,
+    /// 16-bit address (`LDA $1234`)
+    Absolute,
+    /// 16-bit address indexed by X (`LDA $1234,X`)
+    AbsoluteX,
+    /// 16-bit address indexed by Y (`LDA $1234,Y`)
+    AbsoluteY,
+    /// 16-bit pointer (`JMP ($1234)`)
+    Indirect,
+    /// Zero page pointer indexed by X (`LDA ($10,X)`)
+    IndirectX,
+    /// Zero page pointer, the pointed-to address is indexed by Y (`LDA ($10),Y`)
+    IndirectY,
+    /// Signed 8-bit offset from the next instruction (`BNE $C010`)
+    Relative,
+}
+
+impl AddressingMode {
+    /// Amount of operand bytes following the opcode
+    #[must_use]
+    pub const fn operand_len(self) -> usize {
+        match self {
+            Self::Implied | Self::Accumulator => 0,
+            Self::Immediate
+            | Self::ZeroPage
+            | Self::ZeroPageX
+            | Self::ZeroPageY
+            | Self::IndirectX
+            | Self::IndirectY
+            | Self::Relative => 1,
+            Self::Absolute | Self::AbsoluteX | Self::AbsoluteY | Self::Indirect => 2,
+        }
+    }
+}
+
+/// Mnemonic and addressing mode of an opcode
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Opcode {
+    pub mnemonic: Mnemonic,
+    pub mode: AddressingMode,
+}
+
+// Only exists to keep the table below readable
+#[allow(clippy::unnecessary_wraps)]
+const fn op(mnemonic: Mnemonic, mode: AddressingMode) -> Option<Opcode> {
+    Some(Opcode { mnemonic, mode })
+}
+
+/// Look up an opcode byte, returning `None` for opcodes which aren't part of the official instruction set
+#[must_use]
+pub const fn lookup(opcode: u8) -> Option<Opcode> {
+    OPCODES[opcode as usize]
+}
+
+#[rustfmt::skip]
+#[allow(clippy::enum_glob_use)]
+static OPCODES: [Option<Opcode>; 256] = {
+    use self::{AddressingMode::*, Mnemonic::*};
+
+    let mut table = [None; 256];
+
+    table[0x00] = op(Brk, Implied);
+    table[0x01] = op(Ora, IndirectX);
+    table[0x05] = op(Ora, ZeroPage);
+    table[0x06] = op(Asl, ZeroPage);
+    table[0x08] = op(Php, Implied);
+    table[0x09] = op(Ora, Immediate);
+    table[0x0A] = op(Asl, Accumulator);
+    table[0x0D] = op(Ora, Absolute);
+    table[0x0E] = op(Asl, Absolute);
+
+    table[0x10] = op(Bpl, Relative);
+    table[0x11] = op(Ora, IndirectY);
+    table[0x15] = op(Ora, ZeroPageX);
+    table[0x16] = op(Asl, ZeroPageX);
+    table[0x18] = op(Clc, Implied);
+    table[0x19] = op(Ora, AbsoluteY);
+    table[0x1D] = op(Ora, AbsoluteX);
+    table[0x1E] = op(Asl, AbsoluteX);
+
+    table[0x20] = op(Jsr, Absolute);
+    table[0x21] = op(And, IndirectX);
+    table[0x24] = op(Bit, ZeroPage);
+    table[0x25] = op(And, ZeroPage);
+    table[0x26] = op(Rol, ZeroPage);
+    table[0x28] = op(Plp, Implied);
+    table[0x29] = op(And, Immediate);
+    table[0x2A] = op(Rol, Accumulator);
+    table[0x2C] = op(Bit, Absolute);
+    table[0x2D] = op(And, Absolute);
+    table[0x2E] = op(Rol, Absolute);
+
+    table[0x30] = op(Bmi, Relative);
+    table[0x31] = op(And, IndirectY);
+    table[0x35] = op(And, ZeroPageX);
+    table[0x36] = op(Rol, ZeroPageX);
+    table[0x38] = op(Sec, Implied);
+    table[0x39] = op(And, AbsoluteY);
+    table[0x3D] = op(And, AbsoluteX);
+    table[0x3E] = op(Rol, AbsoluteX);
+
+    table[0x40] = op(Rti, Implied);
+    table[0x41] = op(Eor, IndirectX);
+    table[0x45] = op(Eor, ZeroPage);
+    table[0x46] = op(Lsr, ZeroPage);
+    table[0x48] = op(Pha, Implied);
+    table[0x49] = op(Eor, Immediate);
+    table[0x4A] = op(Lsr, Accumulator);
+    table[0x4C] = op(Jmp, Absolute);
+    table[0x4D] = op(Eor, Absolute);
+    table[0x4E] = op(Lsr, Absolute);
+
+    table[0x50] = op(Bvc, Relative);
+    table[0x51] = op(Eor, IndirectY);
+    table[0x55] = op(Eor, ZeroPageX);
+    table[0x56] = op(Lsr, ZeroPageX);
+    table[0x58] = op(Cli, Implied);
+    table[0x59] = op(Eor, AbsoluteY);
+    table[0x5D] = op(Eor, AbsoluteX);
+    table[0x5E] = op(Lsr, AbsoluteX);
+
+    table[0x60] = op(Rts, Implied);
+    table[0x61] = op(Adc, IndirectX);
+    table[0x65] = op(Adc, ZeroPage);
+    table[0x66] = op(Ror, ZeroPage);
+    table[0x68] = op(Pla, Implied);
+    table[0x69] = op(Adc, Immediate);
+    table[0x6A] = op(Ror, Accumulator);
+    table[0x6C] = op(Jmp, Indirect);
+    table[0x6D] = op(Adc, Absolute);
+    table[0x6E] = op(Ror, Absolute);
+
+    table[0x70] = op(Bvs, Relative);
+    table[0x71] = op(Adc, IndirectY);
+    table[0x75] = op(Adc, ZeroPageX);
+    table[0x76] = op(Ror, ZeroPageX);
+    table[0x78] = op(Sei, Implied);
+    table[0x79] = op(Adc, AbsoluteY);
+    table[0x7D] = op(Adc, AbsoluteX);
+    table[0x7E] = op(Ror, AbsoluteX);
+
+    table[0x81] = op(Sta, IndirectX);
+    table[0x84] = op(Sty, ZeroPage);
+    table[0x85] = op(Sta, ZeroPage);
+    table[0x86] = op(Stx, ZeroPage);
+    table[0x88] = op(Dey, Implied);
+    table[0x8A] = op(Txa, Implied);
+    table[0x8C] = op(Sty, Absolute);
+    table[0x8D] = op(Sta, Absolute);
+    table[0x8E] = op(Stx, Absolute);
+
+    table[0x90] = op(Bcc, Relative);
+    table[0x91] = op(Sta, IndirectY);
+    table[0x94] = op(Sty, ZeroPageX);
+    table[0x95] = op(Sta, ZeroPageX);
+    table[0x96] = op(Stx, ZeroPageY);
+    table[0x98] = op(Tya, Implied);
+    table[0x99] = op(Sta, AbsoluteY);
+    table[0x9A] = op(Txs, Implied);
+    table[0x9D] = op(Sta, AbsoluteX);
+
+    table[0xA0] = op(Ldy, Immediate);
+    table[0xA1] = op(Lda, IndirectX);
+    table[0xA2] = op(Ldx, Immediate);
+    table[0xA4] = op(Ldy, ZeroPage);
+    table[0xA5] = op(Lda, ZeroPage);
+    table[0xA6] = op(Ldx, ZeroPage);
+    table[0xA8] = op(Tay, Implied);
+    table[0xA9] = op(Lda, Immediate);
+    table[0xAA] = op(Tax, Implied);
+    table[0xAC] = op(Ldy, Absolute);
+    table[0xAD] = op(Lda, Absolute);
+    table[0xAE] = op(Ldx, Absolute);
+
+    table[0xB0] = op(Bcs, Relative);
+    table[0xB1] = op(Lda, IndirectY);
+    table[0xB4] = op(Ldy, ZeroPageX);
+    table[0xB5] = op(Lda, ZeroPageX);
+    table[0xB6] = op(Ldx, ZeroPageY);
+    table[0xB8] = op(Clv, Implied);
+    table[0xB9] = op(Lda, AbsoluteY);
+    table[0xBA] = op(Tsx, Implied);
+    table[0xBC] = op(Ldy, AbsoluteX);
+    table[0xBD] = op(Lda, AbsoluteX);
+    table[0xBE] = op(Ldx, AbsoluteY);
+
+    table[0xC0] = op(Cpy, Immediate);
+    table[0xC1] = op(Cmp, IndirectX);
+    table[0xC4] = op(Cpy, ZeroPage);
+    table[0xC5] = op(Cmp, ZeroPage);
+    table[0xC6] = op(Dec, ZeroPage);
+    table[0xC8] = op(Iny, Implied);
+    table[0xC9] = op(Cmp, Immediate);
+    table[0xCA] = op(Dex, Implied);
+    table[0xCC] = op(Cpy, Absolute);
+    table[0xCD] = op(Cmp, Absolute);
+    table[0xCE] = op(Dec, Absolute);
+
+    table[0xD0] = op(Bne, Relative);
+    table[0xD1] = op(Cmp, IndirectY);
+    table[0xD5] = op(Cmp, ZeroPageX);
+    table[0xD6] = op(Dec, ZeroPageX);
+    table[0xD8] = op(Cld, Implied);
+    table[0xD9] = op(Cmp, AbsoluteY);
+    table[0xDD] = op(Cmp, AbsoluteX);
+    table[0xDE] = op(Dec, AbsoluteX);
+
+    table[0xE0] = op(Cpx, Immediate);
+    table[0xE1] = op(Sbc, IndirectX);
+    table[0xE4] = op(Cpx, ZeroPage);
+    table[0xE5] = op(Sbc, ZeroPage);
+    table[0xE6] = op(Inc, ZeroPage);
+    table[0xE8] = op(Inx, Implied);
+    table[0xE9] = op(Sbc, Immediate);
+    table[0xEA] = op(Nop, Implied);
+    table[0xEC] = op(Cpx, Absolute);
+    table[0xED] = op(Sbc, Absolute);
+    table[0xEE] = op(Inc, Absolute);
+
+    table[0xF0] = op(Beq, Relative);
+    table[0xF1] = op(Sbc, IndirectY);
+    table[0xF5] = op(Sbc, ZeroPageX);
+    table[0xF6] = op(Inc, ZeroPageX);
+    table[0xF8] = op(Sed, Implied);
+    table[0xF9] = op(Sbc, AbsoluteY);
+    table[0xFD] = op(Sbc, AbsoluteX);
+    table[0xFE] = op(Inc, AbsoluteX);
+
+    table
+};
@@ -0,0 +1,193 @@
+use {
+    crate::{AddressingMode, Instruction, LabelKind, Listing, Mnemonic, Tracer},
+    alloc::{collections::BTreeSet, vec::Vec},
+    core::ops::Range,
+};
+
+/// Address the 512 bytes of a trainer get loaded to
+pub const TRAINER_ADDRESS: u16 = 0x7000;
+
+const TRAINER_SIZE: usize = 512;
+const PRG_ROM_START: u16 = 0x8000;
+const VECTORS_SIZE: usize = 6;
+
+const JSR_ABSOLUTE: u8 = 0x20;
+const JMP_ABSOLUTE: u8 = 0x4C;
+
+// Registers of the Front Fareast (Magic Card) copiers, with the names written into the listing
+const FRONT_FAREAST_REGISTERS: [(u16, &str); 20] = [
+    (0x42FC, "ffe_mode_0"),
+    (0x42FD, "ffe_mode_1"),
+    (0x42FE, "ffe_mode_2"),
+    (0x42FF, "ffe_mode_3"),
+    (0x4500, "ffe_irq_mode"),
+    (0x4501, "ffe_irq_disable"),
+    (0x4502, "ffe_irq_counter_low"),
+    (0x4503, "ffe_irq_counter_high"),
+    (0x4504, "ffe_prg_bank_0"),
+    (0x4505, "ffe_prg_bank_1"),
+    (0x4506, "ffe_prg_bank_2"),
+    (0x4507, "ffe_prg_bank_3"),
+    (0x4510, "ffe_chr_bank_0"),
+    (0x4511, "ffe_chr_bank_1"),
+    (0x4512, "ffe_chr_bank_2"),
+    (0x4513, "ffe_chr_bank_3"),
+    (0x4514, "ffe_chr_bank_4"),
+    (0x4515, "ffe_chr_bank_5"),
+    (0x4516, "ffe_chr_bank_6"),
+    (0x4517, "ffe_chr_bank_7"),
+];
+const FRONT_FAREAST_MODE: Range<u16> = 0x42FC..0x4300;
+const FRONT_FAREAST_IRQ: Range<u16> = 0x4500..0x4504;
+const FRONT_FAREAST_BANKS: Range<u16> = 0x4504..0x4518;
+
+const PPU_REGISTERS: Range<u16> = 0x2000..0x4000;
+const RAM: Range<u16> = 0x0000..0x2000;
+
+/// Known behaviour found in a trainer
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TrainerSignature {
+    /// Every byte has the same value, so the trainer doesn't do anything
+    Empty,
+    /// Writes to the mode and mirroring registers of the Front Fareast copiers (`$42FC`-`$42FF`)
+    FrontFareastMode,
+    /// Writes to the IRQ counter of the Front Fareast copiers (`$4500`-`$4503`)
+    FrontFareastIrq,
+    /// Writes to the PRG and CHR bank registers of the Front Fareast copiers (`$4504`-`$4517`)
+    FrontFareastBanks,
+    /// Writes to the registers of the original mapper, which the copier code stands in for
+    MapperWrites,
+    /// Writes to the PPU registers
+    PpuWrites,
+    /// Stores constants to RAM, like cheats for infinite lives do
+    RamPatches,
+}
+
+/// Disassembled trainer with what it was found to do
+#[derive(Clone, Debug)]
+pub struct TrainerAnalysis<'a> {
+    /// Listing of the trainer at `$7000`, with the Front Fareast registers named
+    pub listing: Listing<'a>,
+    /// Addresses the trainer was traced from
+    pub entry_points: Vec<u16>,
+    pub signatures: BTreeSet<TrainerSignature>,
+}
+
+impl TrainerAnalysis<'_> {
+    /// Whether the trainer drives the hardware of a Front Fareast copier, so the ROM is a mapper hack for it
+    #[must_use]
+    pub fn is_front_fareast(&self) -> bool {
+        self.signatures.iter().any(|signature| {
+            matches!(
+                signature,
+                TrainerSignature::FrontFareastMode
+                    | TrainerSignature::FrontFareastIrq
+                    | TrainerSignature::FrontFareastBanks
+            )
+        })
+    }
+}
+
+// Calls and jumps from the PRG ROM into the trainer, and interrupt vectors pointing into it
+fn find_entry_points(prg_rom: &[u8], trainer: &Range<u16>) -> Vec<u16> {
+    let calls = prg_rom
+        .windows(3)
+        .filter(|window| matches!(window[0], JSR_ABSOLUTE | JMP_ABSOLUTE))
+        .map(|window| u16::from_le_bytes([window[1], window[2]]));
+    let vectors = prg_rom
+        .get(prg_rom.len().saturating_sub(VECTORS_SIZE)..)
+        .unwrap_or_default()
+        .chunks_exact(2)
+        .map(|word| u16::from_le_bytes([word[0], word[1]]));
+
+    let entry_points: BTreeSet<u16> = calls
+        .chain(vectors)
+        .filter(|address| trainer.contains(address))
+        .collect();
+
+    entry_points.into_iter().collect()
+}
+
+/// Disassemble a trainer and identify what it does
+///
+/// The trainer is traced from the calls and jumps of the PRG ROM into it, as well as from the interrupt vectors.
+/// Without any of those (or if the PRG ROM is empty), the trainer is traced from its start.
+#[must_use]
+pub fn analyze_trainer<'a>(trainer: &'a [u8], prg_rom: &[u8]) -> TrainerAnalysis<'a> {
+    let trainer = &trainer[..trainer.len().min(TRAINER_SIZE)];
+    // The trainer is at most 512 bytes long
+    #[allow(clippy::cast_possible_truncation)]
+    let range = TRAINER_ADDRESS..TRAINER_ADDRESS + trainer.len() as u16;
+
+    let mut entry_points = find_entry_points(prg_rom, &range);
+    if entry_points.is_empty() {
+        entry_points.push(TRAINER_ADDRESS);
+    }
+
+    let mut listing = entry_points
+        .iter()
+        .fold(Tracer::new(trainer, TRAINER_ADDRESS), |tracer, address| {
+            tracer.entry_point(*address, LabelKind::Subroutine)
+        })
+        .run();
+
+    let mut signatures = BTreeSet::new();
+    if trainer.iter().all(|byte| Some(byte) == trainer.first()) {
+        signatures.insert(TrainerSignature::Empty);
+    }
+
+    let mut immediate = false;
+    let mut accessed = BTreeSet::new();
+    for (_, instruction) in listing.instructions() {
+        let Instruction::Official { opcode, .. } = instruction else {
+            continue;
+        };
+
+        // Pointers of the indirect modes don't tell where the data goes
+        let address = match opcode.mode {
+            AddressingMode::IndirectX | AddressingMode::IndirectY => None,
+            _ => instruction.address_operand(),
+        };
+        let Some(address) = address else {
+            immediate = opcode.mode == AddressingMode::Immediate;
+            continue;
+        };
+        accessed.insert(address);
+
+        if matches!(
+            opcode.mnemonic,
+            Mnemonic::Sta | Mnemonic::Stx | Mnemonic::Sty
+        ) {
+            let signature = if FRONT_FAREAST_MODE.contains(&address) {
+                Some(TrainerSignature::FrontFareastMode)
+            } else if FRONT_FAREAST_IRQ.contains(&address) {
+                Some(TrainerSignature::FrontFareastIrq)
+            } else if FRONT_FAREAST_BANKS.contains(&address) {
+                Some(TrainerSignature::FrontFareastBanks)
+            } else if address >= PRG_ROM_START {
+                Some(TrainerSignature::MapperWrites)
+            } else if PPU_REGISTERS.contains(&address) {
+                Some(TrainerSignature::PpuWrites)
+            } else if RAM.contains(&address) && immediate {
+                Some(TrainerSignature::RamPatches)
+            } else {
+                None
+            };
+            signatures.extend(signature);
+        }
+
+        immediate = false;
+    }
+
+    for (address, name) in FRONT_FAREAST_REGISTERS {
+        if accessed.contains(&address) {
+            listing.set_name(address, name);
+        }
+    }
+
+    TrainerAnalysis {
+        listing,
+        entry_points,
+        signatures,
+    }
+}
@@ -0,0 +1,60 @@
+use mos6502_dasm::{lookup, AddressingMode, Disassembler, Instruction, Mnemonic};
+
+#[test]
+fn listing() {
+    let code = [
+        0xA9, 0x01, // LDA #$01
+        0x8D, 0x00, 0x20, // STA $2000
+        0xB1, 0x10, // LDA ($10),Y
+        0x0A, // ASL A
+        0xD0, 0xF6, // BNE $8000
+        0x6C, 0xFC, 0xFF, // JMP ($FFFC)
+        0x02, // Not an official opcode
+        0x4C, 0x00, // JMP which got cut off
+    ];
+
+    let listing: Vec<_> = Disassembler::new(&code, 0x8000)
+        .map(|(address, instruction, bytes)| (address, instruction.to_string(), bytes.len()))
+        .collect();
+    assert_eq!(
+        listing,
+        [
+            (0x8000, "LDA #$01".to_string(), 2),
+            (0x8002, "STA $2000".to_string(), 3),
+            (0x8005, "LDA ($10),Y".to_string(), 2),
+            (0x8007, "ASL A".to_string(), 1),
+            (0x8008, "BNE $8000".to_string(), 2),
+            (0x800A, "JMP ($FFFC)".to_string(), 3),
+            (0x800D, ".byte $02".to_string(), 1),
+            (0x800E, ".byte $4C".to_string(), 1),
+            (0x800F, "BRK".to_string(), 1),
+        ]
+    );
+}
+
+#[test]
+fn operands() {
+    let branch = Instruction::decode(&[0x10, 0x80], 0x8000).unwrap();
+    assert_eq!(branch.mnemonic(), Some(Mnemonic::Bpl));
+    // The offset is relative to the next instruction
+    assert_eq!(branch.target(), Some(0x7F82));
+
+    let call = Instruction::decode(&[0x20, 0x34, 0x12], 0x8000).unwrap();
+    assert_eq!(call.target(), Some(0x1234));
+    assert_eq!(call.address_operand(), Some(0x1234));
+
+    let load = Instruction::decode(&[0xBD, 0x00, 0x03], 0x8000).unwrap();
+    assert_eq!(load.mode(), Some(AddressingMode::AbsoluteX));
+    assert_eq!(load.address_operand(), Some(0x0300));
+    assert_eq!(load.target(), None);
+
+    let immediate = Instruction::decode(&[0xA2, 0xFF], 0x8000).unwrap();
+    assert_eq!(immediate.address_operand(), None);
+
+    assert_eq!(Instruction::decode(&[], 0x8000), None);
+}
+
+#[test]
+fn official_opcodes() {
+    assert_eq!((0..=u8::MAX).filter_map(lookup).count(), 151);
+}
@@ -0,0 +1,195 @@
+use nes_state::{Error, Savestate, StateReader, StateWriter};
+
+const RATE_TABLE: [u16; 16] = [
+    428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54,
+];
+const PAL_RATE_TABLE: [u16; 16] = [
+    398, 354, 316, 298, 276, 236, 210, 198, 176, 148, 132, 118, 98, 78, 66, 50,
+];
+
+/// Delta modulation channel playing 1-bit delta encoded samples from memory
+// The flags mirror the ones of the hardware
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Dmc {
+    pub irq_enabled: bool,
+    pub irq: bool,
+    looping: bool,
+    timer: u16,
+    period: u16,
+    rates: &'static [u16; 16],
+
+    sample_address: u16,
+    sample_length: u16,
+    current_address: u16,
+    pub bytes_remaining: u16,
+    sample_buffer: Option<u8>,
+
+    shift_register: u8,
+    bits_remaining: u8,
+    silence: bool,
+    level: u8,
+}
+
+impl Default for Dmc {
+    fn default() -> Self {
+        Self {
+            irq_enabled: false,
+            irq: false,
+            looping: false,
+            timer: 0,
+            period: RATE_TABLE[0],
+            rates: &RATE_TABLE,
+            sample_address: 0xC000,
+            sample_length: 1,
+            current_address: 0xC000,
+            bytes_remaining: 0,
+            sample_buffer: None,
+            shift_register: 0,
+            bits_remaining: 8,
+            silence: true,
+            level: 0,
+        }
+    }
+}
+
+impl Dmc {
+    /// Use the rates of PAL consoles, whose CPU runs slower
+    pub fn set_pal_rates(&mut self, pal: bool) {
+        self.rates = if pal { &PAL_RATE_TABLE } else { &RATE_TABLE };
+        self.period = self.rates[0];
+    }
+
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.irq_enabled = value & 0x80 != 0;
+                if !self.irq_enabled {
+                    self.irq = false;
+                }
+                self.looping = value & 0x40 != 0;
+                self.period = self.rates[usize::from(value & 0x0F)];
+            }
+            1 => self.level = value & 0x7F,
+            2 => self.sample_address = 0xC000 | (u16::from(value) << 6),
+            _ => self.sample_length = (u16::from(value) << 4) | 1,
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.irq = false;
+
+        if !enabled {
+            self.bytes_remaining = 0;
+        } else if self.bytes_remaining == 0 {
+            self.restart();
+        }
+    }
+
+    fn restart(&mut self) {
+        self.current_address = self.sample_address;
+        self.bytes_remaining = self.sample_length;
+    }
+
+    /// Address of the next sample byte if the sample buffer needs to be refilled
+    pub fn dma_address(&self) -> Option<u16> {
+        (self.sample_buffer.is_none() && self.bytes_remaining > 0).then_some(self.current_address)
+    }
+
+    pub fn load_sample(&mut self, value: u8) {
+        self.sample_buffer = Some(value);
+        // The address wraps around to $8000 instead of $0000
+        self.current_address = self.current_address.checked_add(1).unwrap_or(0x8000);
+        self.bytes_remaining = self.bytes_remaining.saturating_sub(1);
+
+        if self.bytes_remaining == 0 {
+            if self.looping {
+                self.restart();
+            } else if self.irq_enabled {
+                self.irq = true;
+            }
+        }
+    }
+
+    /// Clocked every CPU cycle; the periods are in CPU cycles
+    pub fn clock_timer(&mut self) {
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+
+        self.timer = self.period - 1;
+
+        if !self.silence {
+            if self.shift_register & 0x01 != 0 {
+                if self.level <= 125 {
+                    self.level += 2;
+                }
+            } else if self.level >= 2 {
+                self.level -= 2;
+            }
+        }
+        self.shift_register >>= 1;
+
+        self.bits_remaining -= 1;
+        if self.bits_remaining == 0 {
+            self.bits_remaining = 8;
+            match self.sample_buffer.take() {
+                Some(sample) => {
+                    self.silence = false;
+                    self.shift_register = sample;
+                }
+                None => self.silence = true,
+            }
+        }
+    }
+
+    pub fn output(&self) -> u8 {
+        self.level
+    }
+}
+
+impl Savestate for Dmc {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bool(self.irq_enabled);
+        writer.write_bool(self.irq);
+        writer.write_bool(self.looping);
+        writer.write_u16(self.timer);
+        writer.write_u16(self.period);
+
+        writer.write_u16(self.sample_address);
+        writer.write_u16(self.sample_length);
+        writer.write_u16(self.current_address);
+        writer.write_u16(self.bytes_remaining);
+        writer.write_bool(self.sample_buffer.is_some());
+        writer.write_u8(self.sample_buffer.unwrap_or(0));
+
+        writer.write_u8(self.shift_register);
+        writer.write_u8(self.bits_remaining);
+        writer.write_bool(self.silence);
+        writer.write_u8(self.level);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), Error> {
+        self.irq_enabled = reader.read_bool()?;
+        self.irq = reader.read_bool()?;
+        self.looping = reader.read_bool()?;
+        self.timer = reader.read_u16()?;
+        self.period = reader.read_u16()?;
+
+        self.sample_address = reader.read_u16()?;
+        self.sample_length = reader.read_u16()?;
+        self.current_address = reader.read_u16()?;
+        self.bytes_remaining = reader.read_u16()?;
+        let has_sample = reader.read_bool()?;
+        let sample = reader.read_u8()?;
+        self.sample_buffer = if has_sample { Some(sample) } else { None };
+
+        self.shift_register = reader.read_u8()?;
+        self.bits_remaining = reader.read_u8()?;
+        self.silence = reader.read_bool()?;
+        self.level = reader.read_u8()? & 0x7F;
+
+        Ok(())
+    }
+}
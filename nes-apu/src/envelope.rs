@@ -0,0 +1,134 @@
+use nes_state::{Error, Savestate, StateReader, StateWriter};
+
+const LENGTH_TABLE: [u8; 32] = [
+    10, 254, 20, 2, 40, 4, 80, 6, 160, 8, 60, 10, 14, 12, 26, 14, 12, 16, 24, 18, 48, 20, 96, 22,
+    192, 24, 72, 26, 16, 28, 32, 30,
+];
+
+/// Volume envelope of the pulse and noise channels
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Envelope {
+    start: bool,
+    divider: u8,
+    decay: u8,
+    /// Either the constant volume or the period of the divider
+    pub volume: u8,
+    pub constant_volume: bool,
+    pub looping: bool,
+}
+
+impl Envelope {
+    /// Write the lower six bits of the first register of the channel
+    pub fn write(&mut self, value: u8) {
+        self.looping = value & 0x20 != 0;
+        self.constant_volume = value & 0x10 != 0;
+        self.volume = value & 0x0F;
+    }
+
+    pub fn restart(&mut self) {
+        self.start = true;
+    }
+
+    /// Clocked by the quarter frames of the frame counter
+    pub fn clock(&mut self) {
+        if self.start {
+            self.start = false;
+            self.decay = 15;
+            self.divider = self.volume;
+            return;
+        }
+
+        if self.divider > 0 {
+            self.divider -= 1;
+            return;
+        }
+
+        self.divider = self.volume;
+        if self.decay > 0 {
+            self.decay -= 1;
+        } else if self.looping {
+            self.decay = 15;
+        }
+    }
+
+    pub fn output(&self) -> u8 {
+        if self.constant_volume {
+            self.volume
+        } else {
+            self.decay
+        }
+    }
+}
+
+/// Length counter silencing a channel after a given time
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct LengthCounter {
+    pub value: u8,
+    pub enabled: bool,
+    pub halted: bool,
+}
+
+impl LengthCounter {
+    /// Load the counter from the upper five bits of the last register of the channel
+    pub fn load(&mut self, value: u8) {
+        if self.enabled {
+            self.value = LENGTH_TABLE[usize::from(value >> 3)];
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.value = 0;
+        }
+    }
+
+    /// Clocked by the half frames of the frame counter
+    pub fn clock(&mut self) {
+        if !self.halted && self.value > 0 {
+            self.value -= 1;
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.value > 0
+    }
+}
+
+impl Savestate for Envelope {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bool(self.start);
+        writer.write_u8(self.divider);
+        writer.write_u8(self.decay);
+        writer.write_u8(self.volume);
+        writer.write_bool(self.constant_volume);
+        writer.write_bool(self.looping);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), Error> {
+        self.start = reader.read_bool()?;
+        self.divider = reader.read_u8()?;
+        self.decay = reader.read_u8()?;
+        self.volume = reader.read_u8()?;
+        self.constant_volume = reader.read_bool()?;
+        self.looping = reader.read_bool()?;
+
+        Ok(())
+    }
+}
+
+impl Savestate for LengthCounter {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.value);
+        writer.write_bool(self.enabled);
+        writer.write_bool(self.halted);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), Error> {
+        self.value = reader.read_u8()?;
+        self.enabled = reader.read_bool()?;
+        self.halted = reader.read_bool()?;
+
+        Ok(())
+    }
+}
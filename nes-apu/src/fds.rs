@@ -0,0 +1,309 @@
+use {
+    core::convert::TryFrom,
+    nes_state::{Error, Savestate, StateReader, StateWriter},
+};
+
+const WAVE_SIZE: usize = 64;
+const MODULATION_TABLE_SIZE: usize = 64;
+
+// Steps of the modulation counter per table entry; 4 resets the counter
+const MODULATION_STEPS: [i8; 8] = [0, 1, 2, 4, 0, -4, -2, -1];
+const MODULATION_RESET: u8 = 4;
+
+// Gains above this are stored but don't make the channel any louder
+const MAX_OUTPUT_GAIN: u8 = 32;
+
+// Master volume factors of 2/2, 2/3, 2/4 and 2/5, in thirtieths
+const MASTER_VOLUMES: [u16; 4] = [30, 20, 15, 12];
+
+/// Volume or modulation envelope, either set directly or ramped up or down
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct FdsEnvelope {
+    disabled: bool,
+    increase: bool,
+    speed: u8,
+    gain: u8,
+    timer: u32,
+}
+
+impl FdsEnvelope {
+    fn write(&mut self, value: u8) {
+        self.disabled = value & 0x80 != 0;
+        self.increase = value & 0x40 != 0;
+        self.speed = value & 0x3F;
+        if self.disabled {
+            self.gain = self.speed;
+        }
+    }
+
+    fn clock(&mut self, master_speed: u8) {
+        if self.disabled || master_speed == 0 {
+            return;
+        }
+
+        if self.timer == 0 {
+            self.timer = 8 * (u32::from(master_speed) + 1) * (u32::from(self.speed) + 1);
+            if self.increase {
+                if self.gain < MAX_OUTPUT_GAIN {
+                    self.gain += 1;
+                }
+            } else {
+                self.gain = self.gain.saturating_sub(1);
+            }
+        }
+        self.timer -= 1;
+    }
+}
+
+impl Savestate for FdsEnvelope {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bool(self.disabled);
+        writer.write_bool(self.increase);
+        writer.write_u8(self.speed);
+        writer.write_u8(self.gain);
+        writer.write_u32(self.timer);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), Error> {
+        self.disabled = reader.read_bool()?;
+        self.increase = reader.read_bool()?;
+        self.speed = reader.read_u8()? & 0x3F;
+        self.gain = reader.read_u8()? & 0x3F;
+        self.timer = reader.read_u32()?;
+
+        Ok(())
+    }
+}
+
+/// Wavetable channel of the Famicom Disk System with frequency modulation
+// The flags mirror the ones of the hardware
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Fds {
+    wave: [u8; WAVE_SIZE],
+    wave_write: bool,
+    wave_halted: bool,
+    envelopes_halted: bool,
+    frequency: u16,
+    /// Position in the wave in 1/65536 steps
+    phase: u32,
+    volume: FdsEnvelope,
+    master_volume: u8,
+    master_speed: u8,
+
+    modulation_table: [u8; MODULATION_TABLE_SIZE],
+    modulation_halted: bool,
+    modulation_frequency: u16,
+    modulation_phase: u32,
+    /// Signed 7-bit counter offsetting the frequency
+    modulation_counter: i8,
+    modulation: FdsEnvelope,
+
+    /// Last output, which is held while the wave is written to
+    output: u16,
+}
+
+impl Default for Fds {
+    fn default() -> Self {
+        Self {
+            wave: [0; WAVE_SIZE],
+            wave_write: false,
+            wave_halted: true,
+            envelopes_halted: false,
+            frequency: 0,
+            phase: 0,
+            volume: FdsEnvelope::default(),
+            master_volume: 0,
+            master_speed: 0xE8,
+            modulation_table: [0; MODULATION_TABLE_SIZE],
+            modulation_halted: true,
+            modulation_frequency: 0,
+            modulation_phase: 0,
+            modulation_counter: 0,
+            modulation: FdsEnvelope::default(),
+            output: 0,
+        }
+    }
+}
+
+// Wrap the value into the signed 7-bit range of the modulation counter
+#[allow(clippy::cast_possible_truncation)]
+fn wrap_counter(value: i16) -> i8 {
+    (((value + 64) & 0x7F) - 64) as i8
+}
+
+impl Fds {
+    /// Read one of the registers at `$4040`-`$407F`, `$4090` or `$4092`
+    pub fn read(&self, address: u16) -> Option<u8> {
+        match address {
+            0x4040..=0x407F => Some(self.wave[usize::from(address - 0x4040)]),
+            0x4090 => Some(self.volume.gain),
+            0x4092 => Some(self.modulation.gain),
+            _ => None,
+        }
+    }
+
+    /// Write to one of the registers at `$4040`-`$408A`
+    pub fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x4040..=0x407F if self.wave_write => {
+                self.wave[usize::from(address - 0x4040)] = value & 0x3F;
+            }
+            0x4080 => self.volume.write(value),
+            0x4082 => self.frequency = (self.frequency & 0x0F00) | u16::from(value),
+            0x4083 => {
+                self.frequency = (self.frequency & 0x00FF) | (u16::from(value & 0x0F) << 8);
+                self.wave_halted = value & 0x80 != 0;
+                self.envelopes_halted = value & 0x40 != 0;
+                if self.wave_halted {
+                    self.phase = 0;
+                }
+            }
+            0x4084 => self.modulation.write(value),
+            0x4085 => self.modulation_counter = wrap_counter(i16::from(value & 0x7F)),
+            0x4086 => {
+                self.modulation_frequency = (self.modulation_frequency & 0x0F00) | u16::from(value);
+            }
+            0x4087 => {
+                self.modulation_frequency =
+                    (self.modulation_frequency & 0x00FF) | (u16::from(value & 0x0F) << 8);
+                self.modulation_halted = value & 0x80 != 0;
+            }
+            // Each write fills two entries of the table, which is only writable while the modulation is halted
+            0x4088 if self.modulation_halted => {
+                self.modulation_table.copy_within(2.., 0);
+                self.modulation_table[MODULATION_TABLE_SIZE - 2..].fill(value & 0x07);
+            }
+            0x4089 => {
+                self.wave_write = value & 0x80 != 0;
+                self.master_volume = value & 0x03;
+            }
+            0x408A => self.master_speed = value,
+            _ => {}
+        }
+    }
+
+    // Frequency of the wave after the modulation, following the rounding of the hardware
+    fn modulated_frequency(&self) -> u32 {
+        let frequency = i32::from(self.frequency);
+        let counter = i32::from(self.modulation_counter);
+
+        let product = counter * i32::from(self.modulation.gain);
+        let mut offset = product >> 4;
+        if product & 0x0F != 0 && offset & 0x80 == 0 {
+            offset += if counter < 0 { -1 } else { 2 };
+        }
+        if offset >= 192 {
+            offset -= 256;
+        } else if offset < -64 {
+            offset += 256;
+        }
+
+        let product = frequency * offset;
+        let mut offset = product >> 6;
+        if product & 0x3F >= 32 {
+            offset += 1;
+        }
+
+        u32::try_from(frequency + offset).unwrap_or(0)
+    }
+
+    fn clock_modulation(&mut self) {
+        if self.modulation_halted {
+            return;
+        }
+
+        let previous = self.modulation_phase >> 16;
+        self.modulation_phase =
+            (self.modulation_phase + u32::from(self.modulation_frequency)) & 0x3F_FFFF;
+        if self.modulation_phase >> 16 != previous {
+            let index = (self.modulation_phase >> 16) as usize % MODULATION_TABLE_SIZE;
+            let step = self.modulation_table[index];
+            self.modulation_counter = if step == MODULATION_RESET {
+                0
+            } else {
+                wrap_counter(
+                    i16::from(self.modulation_counter)
+                        + i16::from(MODULATION_STEPS[usize::from(step)]),
+                )
+            };
+        }
+    }
+
+    /// Clocked every CPU cycle
+    pub fn clock_timer(&mut self) {
+        if !self.envelopes_halted && !self.wave_halted {
+            self.volume.clock(self.master_speed);
+            self.modulation.clock(self.master_speed);
+        }
+
+        self.clock_modulation();
+
+        if !self.wave_halted && !self.wave_write {
+            self.phase = (self.phase + self.modulated_frequency()) & 0x3F_FFFF;
+        }
+
+        if !self.wave_write {
+            let sample = self.wave[(self.phase >> 16) as usize % WAVE_SIZE];
+            let gain = self.volume.gain.min(MAX_OUTPUT_GAIN);
+            self.output = u16::from(sample) * u16::from(gain);
+        }
+    }
+
+    /// Output of the channel, between 0 and 2016
+    pub fn output(&self) -> u16 {
+        self.output * MASTER_VOLUMES[usize::from(self.master_volume)] / 30
+    }
+}
+
+impl Savestate for Fds {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bytes(&self.wave);
+        writer.write_bool(self.wave_write);
+        writer.write_bool(self.wave_halted);
+        writer.write_bool(self.envelopes_halted);
+        writer.write_u16(self.frequency);
+        writer.write_u32(self.phase);
+        self.volume.save_state(writer);
+        writer.write_u8(self.master_volume);
+        writer.write_u8(self.master_speed);
+
+        writer.write_bytes(&self.modulation_table);
+        writer.write_bool(self.modulation_halted);
+        writer.write_u16(self.modulation_frequency);
+        writer.write_u32(self.modulation_phase);
+        writer.write_u8(self.modulation_counter.to_le_bytes()[0]);
+        self.modulation.save_state(writer);
+
+        writer.write_u16(self.output);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), Error> {
+        reader.read_bytes_into(&mut self.wave)?;
+        for sample in &mut self.wave {
+            *sample &= 0x3F;
+        }
+        self.wave_write = reader.read_bool()?;
+        self.wave_halted = reader.read_bool()?;
+        self.envelopes_halted = reader.read_bool()?;
+        self.frequency = reader.read_u16()? & 0x0FFF;
+        self.phase = reader.read_u32()? & 0x3F_FFFF;
+        self.volume.load_state(reader)?;
+        self.master_volume = reader.read_u8()? & 0x03;
+        self.master_speed = reader.read_u8()?;
+
+        reader.read_bytes_into(&mut self.modulation_table)?;
+        for step in &mut self.modulation_table {
+            *step &= 0x07;
+        }
+        self.modulation_halted = reader.read_bool()?;
+        self.modulation_frequency = reader.read_u16()? & 0x0FFF;
+        self.modulation_phase = reader.read_u32()? & 0x3F_FFFF;
+        self.modulation_counter = wrap_counter(i16::from(reader.read_u8()?));
+        self.modulation.load_state(reader)?;
+
+        self.output = reader.read_u16()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,575 @@
+#![no_std]
+#![warn(clippy::all, clippy::pedantic)]
+
+//!
+//! Emulation core for the APU of the 2A03
+//!
+//! [APU reference](https://www.nesdev.org/wiki/APU)
+//!
+
+extern crate alloc;
+
+mod dmc;
+mod envelope;
+#[cfg(feature = "fds")]
+mod fds;
+#[cfg(feature = "n163")]
+mod n163;
+mod noise;
+mod pulse;
+mod triangle;
+pub mod vgm;
+#[cfg(feature = "vrc6")]
+mod vrc6;
+
+pub use ines_parser::Timing;
+
+#[cfg(feature = "fds")]
+use fds::Fds;
+#[cfg(feature = "n163")]
+use n163::N163;
+#[cfg(feature = "vrc6")]
+use vrc6::Vrc6;
+
+use {
+    alloc::vec::Vec,
+    dmc::Dmc,
+    nes_state::{Savestate, StateReader, StateWriter},
+    noise::Noise,
+    pulse::Pulse,
+    triangle::Triangle,
+};
+
+/// Clock rate of the CPU (and the APU) of NTSC consoles in Hz
+pub const NTSC_CPU_CLOCK: u32 = 1_789_773;
+
+// Steps of the frame counter in CPU cycles
+#[derive(Debug, PartialEq, Eq)]
+struct FrameSequence {
+    quarter_frames: [u32; 2],
+    half_frames: [u32; 2],
+    five_step_half_frame: u32,
+    four_step_length: u32,
+    five_step_length: u32,
+}
+
+// The Dendy uses the NTSC sequence with its own clock rate
+const NTSC_FRAME_SEQUENCE: FrameSequence = FrameSequence {
+    quarter_frames: [7457, 22371],
+    half_frames: [14913, 29829],
+    five_step_half_frame: 37281,
+    four_step_length: 29830,
+    five_step_length: 37282,
+};
+const PAL_FRAME_SEQUENCE: FrameSequence = FrameSequence {
+    quarter_frames: [8313, 24939],
+    half_frames: [16627, 33253],
+    five_step_half_frame: 41565,
+    four_step_length: 33254,
+    five_step_length: 41566,
+};
+
+// Output per step of the expansion chips, relative to the 2A03 pulse channels (about 0.0075 per step)
+//
+// A VRC6 pulse is as loud as a 2A03 pulse, the FDS at full volume about 2.4 times as loud
+// and a single N163 channel at full volume about 1.5 times as loud.
+#[cfg(feature = "vrc6")]
+const VRC6_LEVEL: f32 = 0.0075;
+#[cfg(feature = "fds")]
+const FDS_LEVEL: f32 = 0.27 / 2016.0;
+#[cfg(feature = "n163")]
+const N163_LEVEL: f32 = 0.17 / 225.0;
+
+/// Write to a register of the APU, as recorded while logging is enabled
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RegisterWrite {
+    /// CPU cycles since the logging was enabled
+    pub cycle: u64,
+    pub address: u16,
+    pub value: u8,
+}
+
+/// State of the APU
+#[derive(Clone, Debug, PartialEq)]
+pub struct Apu {
+    pulse1: Pulse,
+    pulse2: Pulse,
+    triangle: Triangle,
+    noise: Noise,
+    dmc: Dmc,
+
+    #[cfg(feature = "vrc6")]
+    vrc6: Option<Vrc6>,
+    #[cfg(feature = "fds")]
+    fds: Option<Fds>,
+    #[cfg(feature = "n163")]
+    n163: Option<N163>,
+
+    cycle: u64,
+    frame_cycle: u32,
+    five_step_mode: bool,
+    frame_irq_inhibit: bool,
+    frame_irq: bool,
+
+    timing: Timing,
+    frame_sequence: &'static FrameSequence,
+    clock_rate: u32,
+    sample_rate: u32,
+    sample_phase: u32,
+    sample_sum: f32,
+    sample_count: u32,
+    samples: Vec<f32>,
+
+    log: Option<Vec<RegisterWrite>>,
+    log_start: u64,
+}
+
+impl Apu {
+    /// Create an APU in the power-up state, generating samples at the sample rate (in Hz)
+    #[must_use]
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            pulse1: Pulse::new(true),
+            pulse2: Pulse::new(false),
+            triangle: Triangle::default(),
+            noise: Noise::default(),
+            dmc: Dmc::default(),
+            #[cfg(feature = "vrc6")]
+            vrc6: None,
+            #[cfg(feature = "fds")]
+            fds: None,
+            #[cfg(feature = "n163")]
+            n163: None,
+            cycle: 0,
+            frame_cycle: 0,
+            five_step_mode: false,
+            frame_irq_inhibit: false,
+            frame_irq: false,
+            timing: Timing::Ntsc,
+            frame_sequence: &NTSC_FRAME_SEQUENCE,
+            clock_rate: NTSC_CPU_CLOCK,
+            sample_rate,
+            sample_phase: 0,
+            sample_sum: 0.0,
+            sample_count: 0,
+            samples: Vec::new(),
+            log: None,
+            log_start: 0,
+        }
+    }
+
+    /// Use the clock rate and the frame counter of the console; APUs start out with the ones of NTSC
+    ///
+    /// PAL APUs also have their own noise and DMC periods, the Dendy only differs in its clock rate.
+    #[must_use]
+    pub fn with_timing(mut self, timing: Timing) -> Self {
+        let pal = timing == Timing::Pal;
+
+        self.timing = timing;
+        self.frame_sequence = if pal {
+            &PAL_FRAME_SEQUENCE
+        } else {
+            &NTSC_FRAME_SEQUENCE
+        };
+        // The clock rates are far below `u32::MAX`; `f64::round` needs std, the clock rates are positive anyway
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let clock_rate = (timing.cpu_clock() + 0.5) as u32;
+        self.clock_rate = clock_rate;
+        self.noise.set_pal_periods(pal);
+        self.dmc.set_pal_rates(pal);
+        self
+    }
+
+    #[must_use]
+    pub fn timing(&self) -> Timing {
+        self.timing
+    }
+
+    /// Clock rate of the CPU (and the APU) in Hz
+    #[must_use]
+    pub fn clock_rate(&self) -> u32 {
+        self.clock_rate
+    }
+
+    /// Sample rate of the generated audio in Hz
+    #[must_use]
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Whether the frame counter or the DMC is asserting the IRQ line
+    #[must_use]
+    pub fn irq(&self) -> bool {
+        self.frame_irq || self.dmc.irq
+    }
+
+    /// Address of the next DMC sample byte if the DMC needs one
+    ///
+    /// The byte has to be read from the CPU address space and passed to [`Apu::load_dmc_sample`]
+    #[must_use]
+    pub fn dmc_dma_address(&self) -> Option<u16> {
+        self.dmc.dma_address()
+    }
+
+    /// Hand the byte read from [`Apu::dmc_dma_address`] to the DMC
+    pub fn load_dmc_sample(&mut self, value: u8) {
+        self.dmc.load_sample(value);
+    }
+
+    /// Add the sound channels of the VRC6 (`$9000`-`$9003`, `$A000`-`$A002` and `$B000`-`$B002`)
+    #[cfg(feature = "vrc6")]
+    pub fn enable_vrc6(&mut self) {
+        self.vrc6.get_or_insert_with(Vrc6::default);
+    }
+
+    /// Add the sound channel of the Famicom Disk System (`$4040`-`$408A`)
+    #[cfg(feature = "fds")]
+    pub fn enable_fds(&mut self) {
+        self.fds.get_or_insert_with(Fds::default);
+    }
+
+    /// Add the sound channels of the Namco 163 (`$4800` and `$F800`)
+    #[cfg(feature = "n163")]
+    pub fn enable_n163(&mut self) {
+        self.n163.get_or_insert_with(N163::default);
+    }
+
+    /// Read a register of the enabled expansion chips, if the address belongs to one of them
+    ///
+    /// The VRC6 doesn't have any readable registers
+    #[cfg(any(feature = "fds", feature = "n163"))]
+    pub fn read_expansion(&mut self, address: u16) -> Option<u8> {
+        #[cfg(feature = "fds")]
+        if let Some(value) = self.fds.as_ref().and_then(|fds| fds.read(address)) {
+            return Some(value);
+        }
+        #[cfg(feature = "n163")]
+        if let Some(value) = self.n163.as_mut().and_then(|n163| n163.read(address)) {
+            return Some(value);
+        }
+
+        None
+    }
+
+    /// Write to the registers of the enabled expansion chips; addresses not belonging to any of them are ignored
+    #[cfg(any(feature = "vrc6", feature = "fds", feature = "n163"))]
+    pub fn write_expansion(&mut self, address: u16, value: u8) {
+        #[cfg(feature = "fds")]
+        if self.fds.is_some() && (0x4040..=0x408A).contains(&address) {
+            self.log_write(address, value);
+        }
+
+        #[cfg(feature = "vrc6")]
+        if let Some(vrc6) = &mut self.vrc6 {
+            vrc6.write(address, value);
+        }
+        #[cfg(feature = "fds")]
+        if let Some(fds) = &mut self.fds {
+            fds.write(address, value);
+        }
+        #[cfg(feature = "n163")]
+        if let Some(n163) = &mut self.n163 {
+            n163.write(address, value);
+        }
+    }
+
+    /// Record the writes to the registers of the 2A03 and the FDS, for example to export them with [`vgm`]
+    ///
+    /// Enabling the logging discards the writes recorded so far and starts counting the cycles from zero.
+    pub fn set_logging(&mut self, enabled: bool) {
+        self.log = enabled.then(Vec::new);
+        self.log_start = self.cycle;
+    }
+
+    #[must_use]
+    pub fn is_logging(&self) -> bool {
+        self.log.is_some()
+    }
+
+    /// Take the register writes recorded since the last call; the logging stays enabled
+    pub fn take_log(&mut self) -> Vec<RegisterWrite> {
+        self.log.as_mut().map(core::mem::take).unwrap_or_default()
+    }
+
+    fn log_write(&mut self, address: u16, value: u8) {
+        if let Some(log) = &mut self.log {
+            log.push(RegisterWrite {
+                cycle: self.cycle - self.log_start,
+                address,
+                value,
+            });
+        }
+    }
+
+    /// Take all samples generated since the last call; the values are in the range of 0.0 to 1.0
+    pub fn take_samples(&mut self) -> Vec<f32> {
+        core::mem::take(&mut self.samples)
+    }
+
+    /// Read the status register (`$4015`); all other registers are write-only
+    pub fn read_status(&mut self) -> u8 {
+        let status = u8::from(self.pulse1.length.is_active())
+            | u8::from(self.pulse2.length.is_active()) << 1
+            | u8::from(self.triangle.length.is_active()) << 2
+            | u8::from(self.noise.length.is_active()) << 3
+            | u8::from(self.dmc.bytes_remaining > 0) << 4
+            | u8::from(self.frame_irq) << 6
+            | u8::from(self.dmc.irq) << 7;
+
+        self.frame_irq = false;
+        status
+    }
+
+    /// Write to one of the registers at `$4000`-`$4013`, `$4015` or `$4017`
+    pub fn write_register(&mut self, address: u16, value: u8) {
+        if matches!(address, 0x4000..=0x4013 | 0x4015 | 0x4017) {
+            self.log_write(address, value);
+        }
+        let register = address & 0x03;
+
+        match address {
+            0x4000..=0x4003 => self.pulse1.write(register, value),
+            0x4004..=0x4007 => self.pulse2.write(register, value),
+            0x4008..=0x400B => self.triangle.write(register, value),
+            0x400C..=0x400F => self.noise.write(register, value),
+            0x4010..=0x4013 => self.dmc.write(register, value),
+            0x4015 => {
+                self.pulse1.length.set_enabled(value & 0x01 != 0);
+                self.pulse2.length.set_enabled(value & 0x02 != 0);
+                self.triangle.length.set_enabled(value & 0x04 != 0);
+                self.noise.length.set_enabled(value & 0x08 != 0);
+                self.dmc.set_enabled(value & 0x10 != 0);
+            }
+            0x4017 => {
+                self.five_step_mode = value & 0x80 != 0;
+                self.frame_irq_inhibit = value & 0x40 != 0;
+                if self.frame_irq_inhibit {
+                    self.frame_irq = false;
+                }
+
+                self.frame_cycle = 0;
+                if self.five_step_mode {
+                    self.clock_quarter_frame();
+                    self.clock_half_frame();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Advance the APU by one CPU cycle
+    pub fn tick(&mut self) {
+        self.cycle += 1;
+        self.clock_frame_counter();
+
+        self.triangle.clock_timer();
+        self.noise.clock_timer();
+        self.dmc.clock_timer();
+        if self.cycle.is_multiple_of(2) {
+            self.pulse1.clock_timer();
+            self.pulse2.clock_timer();
+        }
+
+        #[cfg(feature = "vrc6")]
+        if let Some(vrc6) = &mut self.vrc6 {
+            vrc6.clock_timer();
+        }
+        #[cfg(feature = "fds")]
+        if let Some(fds) = &mut self.fds {
+            fds.clock_timer();
+        }
+        #[cfg(feature = "n163")]
+        if let Some(n163) = &mut self.n163 {
+            n163.clock_timer();
+        }
+
+        self.sample_sum += self.output();
+        self.sample_count += 1;
+
+        self.sample_phase += self.sample_rate;
+        if self.sample_phase >= self.clock_rate {
+            self.sample_phase -= self.clock_rate;
+
+            // Averaging all cycles of a sample acts as a simple low-pass filter
+            #[allow(clippy::cast_precision_loss)]
+            self.samples
+                .push(self.sample_sum / self.sample_count as f32);
+            self.sample_sum = 0.0;
+            self.sample_count = 0;
+        }
+    }
+
+    fn clock_frame_counter(&mut self) {
+        self.frame_cycle += 1;
+
+        let sequence = self.frame_sequence;
+        let length = if self.five_step_mode {
+            sequence.five_step_length
+        } else {
+            sequence.four_step_length
+        };
+
+        if sequence.quarter_frames.contains(&self.frame_cycle) {
+            self.clock_quarter_frame();
+        }
+        if sequence.half_frames[0] == self.frame_cycle
+            || (!self.five_step_mode && sequence.half_frames[1] == self.frame_cycle)
+            || (self.five_step_mode && sequence.five_step_half_frame == self.frame_cycle)
+        {
+            self.clock_quarter_frame();
+            self.clock_half_frame();
+        }
+
+        if !self.five_step_mode
+            && !self.frame_irq_inhibit
+            && self.frame_cycle >= sequence.half_frames[1] - 1
+        {
+            self.frame_irq = true;
+        }
+
+        if self.frame_cycle == length {
+            self.frame_cycle = 0;
+        }
+    }
+
+    fn clock_quarter_frame(&mut self) {
+        self.pulse1.envelope.clock();
+        self.pulse2.envelope.clock();
+        self.noise.envelope.clock();
+        self.triangle.clock_linear_counter();
+    }
+
+    fn clock_half_frame(&mut self) {
+        self.pulse1.length.clock();
+        self.pulse2.length.clock();
+        self.triangle.length.clock();
+        self.noise.length.clock();
+        self.pulse1.clock_sweep();
+        self.pulse2.clock_sweep();
+    }
+
+    // Expansion chips are mixed linearly
+    #[allow(unused_mut, clippy::unused_self)]
+    fn expansion_output(&self) -> f32 {
+        let mut output = 0.0;
+
+        #[cfg(feature = "vrc6")]
+        if let Some(vrc6) = &self.vrc6 {
+            output += f32::from(vrc6.output()) * VRC6_LEVEL;
+        }
+        #[cfg(feature = "fds")]
+        if let Some(fds) = &self.fds {
+            output += f32::from(fds.output()) * FDS_LEVEL;
+        }
+        #[cfg(feature = "n163")]
+        if let Some(n163) = &self.n163 {
+            output += f32::from(n163.output()) * N163_LEVEL;
+        }
+
+        output
+    }
+
+    /// Current output of the non-linear mixer together with the enabled expansion chips, clipped to 1.0
+    #[must_use]
+    pub fn output(&self) -> f32 {
+        let pulse = f32::from(self.pulse1.output() + self.pulse2.output());
+        let pulse_out = if pulse == 0.0 {
+            0.0
+        } else {
+            95.88 / (8128.0 / pulse + 100.0)
+        };
+
+        let tnd = f32::from(self.triangle.output()) / 8227.0
+            + f32::from(self.noise.output()) / 12241.0
+            + f32::from(self.dmc.output()) / 22638.0;
+        let tnd_out = if tnd == 0.0 {
+            0.0
+        } else {
+            159.79 / (1.0 / tnd + 100.0)
+        };
+
+        (pulse_out + tnd_out + self.expansion_output()).min(1.0)
+    }
+}
+
+// Expansion chips are stored with a flag telling whether they're enabled
+#[cfg(any(feature = "vrc6", feature = "fds", feature = "n163"))]
+fn save_expansion<S: Savestate>(writer: &mut StateWriter, chip: Option<&S>) {
+    writer.write_bool(chip.is_some());
+    if let Some(chip) = chip {
+        chip.save_state(writer);
+    }
+}
+
+#[cfg(any(feature = "vrc6", feature = "fds", feature = "n163"))]
+fn load_expansion<S: Savestate + Default>(
+    reader: &mut StateReader<'_>,
+    chip: &mut Option<S>,
+) -> Result<(), nes_state::Error> {
+    *chip = if reader.read_bool()? {
+        let mut state = S::default();
+        state.load_state(reader)?;
+        Some(state)
+    } else {
+        None
+    };
+
+    Ok(())
+}
+
+impl Savestate for Apu {
+    // The samples which weren't taken yet aren't part of the state
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.pulse1.save_state(writer);
+        self.pulse2.save_state(writer);
+        self.triangle.save_state(writer);
+        self.noise.save_state(writer);
+        self.dmc.save_state(writer);
+
+        #[cfg(feature = "vrc6")]
+        save_expansion(writer, self.vrc6.as_ref());
+        #[cfg(feature = "fds")]
+        save_expansion(writer, self.fds.as_ref());
+        #[cfg(feature = "n163")]
+        save_expansion(writer, self.n163.as_ref());
+
+        writer.write_u64(self.cycle);
+        writer.write_u32(self.frame_cycle);
+        writer.write_bool(self.five_step_mode);
+        writer.write_bool(self.frame_irq_inhibit);
+        writer.write_bool(self.frame_irq);
+
+        writer.write_u32(self.sample_phase);
+        writer.write_f32(self.sample_sum);
+        writer.write_u32(self.sample_count);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), nes_state::Error> {
+        self.pulse1.load_state(reader)?;
+        self.pulse2.load_state(reader)?;
+        self.triangle.load_state(reader)?;
+        self.noise.load_state(reader)?;
+        self.dmc.load_state(reader)?;
+
+        #[cfg(feature = "vrc6")]
+        load_expansion(reader, &mut self.vrc6)?;
+        #[cfg(feature = "fds")]
+        load_expansion(reader, &mut self.fds)?;
+        #[cfg(feature = "n163")]
+        load_expansion(reader, &mut self.n163)?;
+
+        self.cycle = reader.read_u64()?;
+        self.frame_cycle = reader.read_u32()?;
+        self.five_step_mode = reader.read_bool()?;
+        self.frame_irq_inhibit = reader.read_bool()?;
+        self.frame_irq = reader.read_bool()?;
+
+        // The phase depends on the sample rate, which might differ from the one of the saved APU
+        self.sample_phase = reader.read_u32()? % self.clock_rate;
+        self.sample_sum = reader.read_f32()?;
+        self.sample_count = reader.read_u32()?;
+        self.samples.clear();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,161 @@
+use nes_state::{Error, Savestate, StateReader, StateWriter};
+
+const RAM_SIZE: usize = 0x80;
+const MAX_CHANNELS: usize = 8;
+
+// Registers of the eighth channel are at the end of the RAM, the others precede it
+const CHANNEL_REGISTERS: usize = 0x40;
+const CHANNEL_REGISTER_SIZE: usize = 8;
+
+// One channel gets updated every 15 CPU cycles
+const UPDATE_PERIOD: u8 = 15;
+
+/// Namco 163 with up to eight wavetable channels playing 4-bit samples from its internal RAM
+///
+/// The hardware outputs one channel at a time; the output is the average of the enabled channels instead,
+/// which sounds the same without the high-pitched noise of the multiplexing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct N163 {
+    ram: [u8; RAM_SIZE],
+    address: u8,
+    auto_increment: bool,
+    timer: u8,
+    /// Enabled channel updated next, counted from the eighth one downwards
+    channel: usize,
+    outputs: [u8; MAX_CHANNELS],
+}
+
+impl Default for N163 {
+    fn default() -> Self {
+        Self {
+            ram: [0; RAM_SIZE],
+            address: 0,
+            auto_increment: false,
+            timer: 0,
+            channel: 0,
+            outputs: [0; MAX_CHANNELS],
+        }
+    }
+}
+
+impl N163 {
+    // Amount of enabled channels, which are the last ones
+    fn channel_count(&self) -> usize {
+        usize::from((self.ram[RAM_SIZE - 1] >> 4) & 0x07) + 1
+    }
+
+    fn access_data(&mut self) -> usize {
+        let address = usize::from(self.address);
+        if self.auto_increment {
+            self.address = (self.address + 1) & 0x7F;
+        }
+        address
+    }
+
+    /// Read the data port (`$4800`)
+    pub fn read(&mut self, address: u16) -> Option<u8> {
+        match address {
+            0x4800..=0x4FFF => {
+                let address = self.access_data();
+                Some(self.ram[address])
+            }
+            _ => None,
+        }
+    }
+
+    /// Write to the data port (`$4800`) or the address port (`$F800`)
+    pub fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x4800..=0x4FFF => {
+                let address = self.access_data();
+                self.ram[address] = value;
+            }
+            0xF800..=0xFFFF => {
+                self.address = value & 0x7F;
+                self.auto_increment = value & 0x80 != 0;
+            }
+            _ => {}
+        }
+    }
+
+    // Advance the phase of the channel and look up its sample
+    fn update_channel(&mut self, channel: usize) {
+        let base = CHANNEL_REGISTERS + channel * CHANNEL_REGISTER_SIZE;
+        let registers = &self.ram[base..base + CHANNEL_REGISTER_SIZE];
+
+        let frequency = u32::from_le_bytes([registers[0], registers[2], registers[4] & 0x03, 0]);
+        let phase = u32::from_le_bytes([registers[1], registers[3], registers[5], 0]);
+        // Length of the wave in samples
+        let length = (256 - u32::from(registers[4] & 0xFC)) << 16;
+        let wave_address = registers[6];
+        let volume = registers[7] & 0x0F;
+
+        let phase = (phase + frequency) % length;
+        let [_, phase_middle, phase_high, _] = phase.to_le_bytes();
+        // Samples are packed two per byte, the lower nibble first
+        let sample_address = phase_high.wrapping_add(wave_address);
+        let byte = self.ram[usize::from(sample_address >> 1) % RAM_SIZE];
+        let sample = if sample_address & 1 == 0 {
+            byte & 0x0F
+        } else {
+            byte >> 4
+        };
+
+        self.ram[base + 1] = phase.to_le_bytes()[0];
+        self.ram[base + 3] = phase_middle;
+        self.ram[base + 5] = phase_high;
+        self.outputs[channel] = sample * volume;
+    }
+
+    /// Clocked every CPU cycle
+    pub fn clock_timer(&mut self) {
+        self.timer += 1;
+        if self.timer < UPDATE_PERIOD {
+            return;
+        }
+        self.timer = 0;
+
+        let count = self.channel_count();
+        self.channel %= count;
+        self.update_channel(MAX_CHANNELS - 1 - self.channel);
+        self.channel = (self.channel + 1) % count;
+    }
+
+    /// Average of the enabled channels, between 0 and 225
+    pub fn output(&self) -> u8 {
+        let count = self.channel_count();
+        let sum: usize = self.outputs[MAX_CHANNELS - count..]
+            .iter()
+            .map(|output| usize::from(*output))
+            .sum();
+
+        // The average can't be larger than the largest output
+        #[allow(clippy::cast_possible_truncation)]
+        let average = (sum / count) as u8;
+        average
+    }
+}
+
+impl Savestate for N163 {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bytes(&self.ram);
+        writer.write_u8(self.address);
+        writer.write_bool(self.auto_increment);
+        writer.write_u8(self.timer);
+        // There are at most 8 channels
+        #[allow(clippy::cast_possible_truncation)]
+        writer.write_u8(self.channel as u8);
+        writer.write_bytes(&self.outputs);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), Error> {
+        reader.read_bytes_into(&mut self.ram)?;
+        self.address = reader.read_u8()? & 0x7F;
+        self.auto_increment = reader.read_bool()?;
+        self.timer = reader.read_u8()? % UPDATE_PERIOD;
+        self.channel = usize::from(reader.read_u8()?) % MAX_CHANNELS;
+        reader.read_bytes_into(&mut self.outputs)?;
+
+        Ok(())
+    }
+}
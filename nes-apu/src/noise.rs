@@ -0,0 +1,109 @@
+use {
+    crate::envelope::{Envelope, LengthCounter},
+    nes_state::{Error, Savestate, StateReader, StateWriter},
+};
+
+const PERIOD_TABLE: [u16; 16] = [
+    4, 8, 16, 32, 64, 96, 128, 160, 202, 254, 380, 508, 762, 1016, 2034, 4068,
+];
+const PAL_PERIOD_TABLE: [u16; 16] = [
+    4, 8, 14, 30, 60, 88, 118, 148, 188, 236, 354, 472, 708, 944, 1890, 3778,
+];
+
+/// Pseudo-random noise channel
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Noise {
+    shift_register: u16,
+    short_mode: bool,
+    timer: u16,
+    period: u16,
+    periods: &'static [u16; 16],
+    pub envelope: Envelope,
+    pub length: LengthCounter,
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Self {
+            shift_register: 1,
+            short_mode: false,
+            timer: 0,
+            period: PERIOD_TABLE[0],
+            periods: &PERIOD_TABLE,
+            envelope: Envelope::default(),
+            length: LengthCounter::default(),
+        }
+    }
+}
+
+impl Noise {
+    /// Use the periods of PAL consoles, whose CPU runs slower
+    pub fn set_pal_periods(&mut self, pal: bool) {
+        self.periods = if pal {
+            &PAL_PERIOD_TABLE
+        } else {
+            &PERIOD_TABLE
+        };
+        self.period = self.periods[0];
+    }
+
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.length.halted = value & 0x20 != 0;
+                self.envelope.write(value);
+            }
+            1 => {}
+            2 => {
+                self.short_mode = value & 0x80 != 0;
+                self.period = self.periods[usize::from(value & 0x0F)];
+            }
+            _ => {
+                self.length.load(value);
+                self.envelope.restart();
+            }
+        }
+    }
+
+    /// Clocked every CPU cycle; the periods are in CPU cycles
+    pub fn clock_timer(&mut self) {
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+
+        self.timer = self.period - 1;
+
+        let tap = if self.short_mode { 6 } else { 1 };
+        let feedback = (self.shift_register ^ (self.shift_register >> tap)) & 0x01;
+        self.shift_register = (self.shift_register >> 1) | (feedback << 14);
+    }
+
+    pub fn output(&self) -> u8 {
+        if !self.length.is_active() || self.shift_register & 0x01 != 0 {
+            0
+        } else {
+            self.envelope.output()
+        }
+    }
+}
+
+impl Savestate for Noise {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u16(self.shift_register);
+        writer.write_bool(self.short_mode);
+        writer.write_u16(self.timer);
+        writer.write_u16(self.period);
+        self.envelope.save_state(writer);
+        self.length.save_state(writer);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), Error> {
+        self.shift_register = reader.read_u16()?;
+        self.short_mode = reader.read_bool()?;
+        self.timer = reader.read_u16()?;
+        self.period = reader.read_u16()?;
+        self.envelope.load_state(reader)?;
+        self.length.load_state(reader)
+    }
+}
@@ -0,0 +1,156 @@
+use {
+    crate::envelope::{Envelope, LengthCounter},
+    nes_state::{Error, Savestate, StateReader, StateWriter},
+};
+
+const DUTY_TABLE: [[u8; 8]; 4] = [
+    [0, 1, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 1, 0, 0, 0],
+    [1, 0, 0, 1, 1, 1, 1, 1],
+];
+
+/// Square wave channel with a frequency sweep
+// The flags mirror the ones of the hardware
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Pulse {
+    /// The first pulse channel negates with the ones' complement
+    is_first: bool,
+    duty: u8,
+    step: u8,
+    timer: u16,
+    period: u16,
+    pub envelope: Envelope,
+    pub length: LengthCounter,
+
+    sweep_enabled: bool,
+    sweep_period: u8,
+    sweep_negate: bool,
+    sweep_shift: u8,
+    sweep_divider: u8,
+    sweep_reload: bool,
+}
+
+impl Pulse {
+    pub fn new(is_first: bool) -> Self {
+        Self {
+            is_first,
+            ..Self::default()
+        }
+    }
+
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.duty = value >> 6;
+                self.length.halted = value & 0x20 != 0;
+                self.envelope.write(value);
+            }
+            1 => {
+                self.sweep_enabled = value & 0x80 != 0;
+                self.sweep_period = (value >> 4) & 0x07;
+                self.sweep_negate = value & 0x08 != 0;
+                self.sweep_shift = value & 0x07;
+                self.sweep_reload = true;
+            }
+            2 => self.period = (self.period & 0x0700) | u16::from(value),
+            _ => {
+                self.period = (self.period & 0x00FF) | (u16::from(value & 0x07) << 8);
+                self.length.load(value);
+                self.envelope.restart();
+                self.step = 0;
+            }
+        }
+    }
+
+    /// Clocked every APU cycle (every other CPU cycle)
+    pub fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.period;
+            self.step = (self.step + 1) % 8;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    fn sweep_target(&self) -> u16 {
+        let change = self.period >> self.sweep_shift;
+        if self.sweep_negate {
+            let target = self.period.saturating_sub(change);
+            if self.is_first {
+                target.saturating_sub(1)
+            } else {
+                target
+            }
+        } else {
+            self.period + change
+        }
+    }
+
+    fn is_muted(&self) -> bool {
+        self.period < 8 || self.sweep_target() > 0x07FF
+    }
+
+    /// Clocked by the half frames of the frame counter
+    pub fn clock_sweep(&mut self) {
+        if self.sweep_divider == 0 && self.sweep_enabled && self.sweep_shift > 0 && !self.is_muted()
+        {
+            self.period = self.sweep_target();
+        }
+
+        if self.sweep_divider == 0 || self.sweep_reload {
+            self.sweep_divider = self.sweep_period;
+            self.sweep_reload = false;
+        } else {
+            self.sweep_divider -= 1;
+        }
+    }
+
+    pub fn output(&self) -> u8 {
+        if !self.length.is_active()
+            || self.is_muted()
+            || DUTY_TABLE[usize::from(self.duty)][usize::from(self.step)] == 0
+        {
+            0
+        } else {
+            self.envelope.output()
+        }
+    }
+}
+
+impl Savestate for Pulse {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.duty);
+        writer.write_u8(self.step);
+        writer.write_u16(self.timer);
+        writer.write_u16(self.period);
+        self.envelope.save_state(writer);
+        self.length.save_state(writer);
+
+        writer.write_bool(self.sweep_enabled);
+        writer.write_u8(self.sweep_period);
+        writer.write_bool(self.sweep_negate);
+        writer.write_u8(self.sweep_shift);
+        writer.write_u8(self.sweep_divider);
+        writer.write_bool(self.sweep_reload);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), Error> {
+        self.duty = reader.read_u8()? & 0x03;
+        self.step = reader.read_u8()? & 0x07;
+        self.timer = reader.read_u16()?;
+        self.period = reader.read_u16()?;
+        self.envelope.load_state(reader)?;
+        self.length.load_state(reader)?;
+
+        self.sweep_enabled = reader.read_bool()?;
+        self.sweep_period = reader.read_u8()?;
+        self.sweep_negate = reader.read_bool()?;
+        self.sweep_shift = reader.read_u8()?;
+        self.sweep_divider = reader.read_u8()?;
+        self.sweep_reload = reader.read_bool()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+use {
+    crate::envelope::LengthCounter,
+    nes_state::{Error, Savestate, StateReader, StateWriter},
+};
+
+const SEQUENCE: [u8; 32] = [
+    15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12,
+    13, 14, 15,
+];
+
+/// Triangle wave channel
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Triangle {
+    step: u8,
+    timer: u16,
+    period: u16,
+    pub length: LengthCounter,
+
+    linear_counter: u8,
+    linear_reload_value: u8,
+    linear_reload: bool,
+    /// Also halts the length counter
+    control: bool,
+}
+
+impl Triangle {
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.control = value & 0x80 != 0;
+                self.length.halted = self.control;
+                self.linear_reload_value = value & 0x7F;
+            }
+            1 => {}
+            2 => self.period = (self.period & 0x0700) | u16::from(value),
+            _ => {
+                self.period = (self.period & 0x00FF) | (u16::from(value & 0x07) << 8);
+                self.length.load(value);
+                self.linear_reload = true;
+            }
+        }
+    }
+
+    /// Clocked every CPU cycle
+    pub fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.period;
+            if self.length.is_active() && self.linear_counter > 0 {
+                self.step = (self.step + 1) % 32;
+            }
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    /// Clocked by the quarter frames of the frame counter
+    pub fn clock_linear_counter(&mut self) {
+        if self.linear_reload {
+            self.linear_counter = self.linear_reload_value;
+        } else if self.linear_counter > 0 {
+            self.linear_counter -= 1;
+        }
+
+        if !self.control {
+            self.linear_reload = false;
+        }
+    }
+
+    pub fn output(&self) -> u8 {
+        SEQUENCE[usize::from(self.step)]
+    }
+}
+
+impl Savestate for Triangle {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.step);
+        writer.write_u16(self.timer);
+        writer.write_u16(self.period);
+        self.length.save_state(writer);
+
+        writer.write_u8(self.linear_counter);
+        writer.write_u8(self.linear_reload_value);
+        writer.write_bool(self.linear_reload);
+        writer.write_bool(self.control);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), Error> {
+        self.step = reader.read_u8()? & 0x1F;
+        self.timer = reader.read_u16()?;
+        self.period = reader.read_u16()?;
+        self.length.load_state(reader)?;
+
+        self.linear_counter = reader.read_u8()?;
+        self.linear_reload_value = reader.read_u8()?;
+        self.linear_reload = reader.read_bool()?;
+        self.control = reader.read_bool()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,235 @@
+//!
+//! Export of APU register logs to VGM files
+//!
+//! [Format documentation](https://vgmrips.net/wiki/VGM_Specification)
+//!
+
+use {crate::RegisterWrite, alloc::vec::Vec, core::convert::TryFrom};
+
+/// Rate the waits of VGM files are counted in
+pub const VGM_SAMPLE_RATE: u32 = 44100;
+
+const VERSION: u32 = 0x171;
+const HEADER_SIZE: usize = 0x100;
+
+// Offsets into the header
+const EOF_OFFSET: usize = 0x04;
+const VERSION_OFFSET: usize = 0x08;
+const TOTAL_SAMPLES_OFFSET: usize = 0x18;
+const LOOP_OFFSET: usize = 0x1C;
+const LOOP_SAMPLES_OFFSET: usize = 0x20;
+const DATA_OFFSET: usize = 0x34;
+const NES_APU_CLOCK_OFFSET: usize = 0x84;
+
+// Bit of the clock announcing the FDS channel
+const FDS_FLAG: u32 = 0x8000_0000;
+
+// Commands
+const NES_APU_WRITE: u8 = 0xB4;
+const WAIT: u8 = 0x61;
+const WAIT_NTSC_FRAME: u8 = 0x62;
+const WAIT_PAL_FRAME: u8 = 0x63;
+const WAIT_SHORT: u8 = 0x70;
+const DATA_BLOCK: u8 = 0x67;
+const END_OF_DATA: u8 = 0x66;
+// Type of the data blocks written to the address space of the APU, which is where the DMC samples come from
+const NES_APU_RAM: u8 = 0xC2;
+
+// Start of the memory the DMC reads its samples from
+const DPCM_START: u16 = 0xC000;
+
+// Register of the NES APU chip of VGM files for an address, covering the 2A03 and the FDS
+fn register(address: u16) -> Option<u8> {
+    let register = match address {
+        0x4000..=0x401F => address - 0x4000,
+        0x4023 => 0x3F,
+        0x4040..=0x407F => address - 0x4040 + 0x40,
+        0x4080..=0x409E => address - 0x4080 + 0x20,
+        _ => return None,
+    };
+
+    u8::try_from(register).ok()
+}
+
+fn is_fds(address: u16) -> bool {
+    matches!(address, 0x4023 | 0x4040..=0x409E)
+}
+
+/// Settings of a VGM export
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct VgmExport<'a> {
+    clock_rate: u32,
+    loop_cycle: Option<u64>,
+    dpcm_memory: Option<&'a [u8]>,
+}
+
+impl<'a> VgmExport<'a> {
+    /// Export a log recorded at the clock rate of the APU (in Hz)
+    #[must_use]
+    pub fn new(clock_rate: u32) -> Self {
+        Self {
+            clock_rate,
+            loop_cycle: None,
+            dpcm_memory: None,
+        }
+    }
+
+    /// Make players loop back to the cycle once they reach the end
+    #[must_use]
+    pub fn with_loop(mut self, cycle: u64) -> Self {
+        self.loop_cycle = Some(cycle);
+        self
+    }
+
+    /// Include the memory at `$C000`-`$FFFF`, which holds the samples the DMC plays
+    ///
+    /// Players only know the memory the file contains, so the DMC stays silent without it.
+    #[must_use]
+    pub fn with_dpcm_memory(mut self, memory: &'a [u8]) -> Self {
+        self.dpcm_memory = Some(memory);
+        self
+    }
+
+    fn samples(&self, cycle: u64) -> u64 {
+        let samples =
+            u128::from(cycle) * u128::from(VGM_SAMPLE_RATE) / u128::from(self.clock_rate.max(1));
+        u64::try_from(samples).unwrap_or(u64::MAX)
+    }
+
+    /// Create a VGM file playing the writes of the log, which lasts until the end cycle
+    ///
+    /// Writes to registers outside of the 2A03 and the FDS get skipped.
+    #[must_use]
+    pub fn export(&self, log: &[RegisterWrite], end_cycle: u64) -> Vec<u8> {
+        let mut commands = Commands {
+            vgm: alloc::vec![0; HEADER_SIZE],
+            sample: 0,
+            loop_point: self.loop_cycle.map(|cycle| (cycle, self.samples(cycle))),
+            loop_position: None,
+        };
+
+        if let Some(memory) = self.dpcm_memory {
+            let memory = &memory[..memory.len().min(0x4000)];
+            // At most 16 KiB and the address
+            #[allow(clippy::cast_possible_truncation)]
+            let size = (memory.len() + 2) as u32;
+            commands
+                .vgm
+                .extend_from_slice(&[DATA_BLOCK, END_OF_DATA, NES_APU_RAM]);
+            commands.vgm.extend_from_slice(&size.to_le_bytes());
+            commands.vgm.extend_from_slice(&DPCM_START.to_le_bytes());
+            commands.vgm.extend_from_slice(memory);
+        }
+
+        for write in log {
+            if let Some(register) = register(write.address) {
+                commands.wait_until(write.cycle, self.samples(write.cycle));
+                commands
+                    .vgm
+                    .extend_from_slice(&[NES_APU_WRITE, register, write.value]);
+            }
+        }
+        commands.wait_until(end_cycle, self.samples(end_cycle));
+        commands.vgm.push(END_OF_DATA);
+
+        let fds = log.iter().any(|write| is_fds(write.address));
+        let clock = if fds {
+            self.clock_rate | FDS_FLAG
+        } else {
+            self.clock_rate
+        };
+        commands.finish(clock)
+    }
+}
+
+// Commands of the file, with the position in time and where the loop starts
+struct Commands {
+    vgm: Vec<u8>,
+    sample: u64,
+    /// Cycle and sample the loop starts at
+    loop_point: Option<(u64, u64)>,
+    loop_position: Option<usize>,
+}
+
+impl Commands {
+    // The loop starts before the first write at or after its cycle, even if they fall on the same sample
+    fn wait_until(&mut self, cycle: u64, sample: u64) {
+        if let Some((loop_cycle, loop_sample)) = self.loop_point {
+            if self.loop_position.is_none() && loop_cycle <= cycle {
+                self.wait(loop_sample);
+                self.loop_position = Some(self.vgm.len());
+            }
+        }
+        self.wait(sample);
+    }
+
+    fn wait(&mut self, sample: u64) {
+        write_wait(&mut self.vgm, sample.saturating_sub(self.sample));
+        self.sample = self.sample.max(sample);
+    }
+
+    // Fill in the header
+    fn finish(mut self, clock: u32) -> Vec<u8> {
+        let total_samples = u32::try_from(self.sample).unwrap_or(u32::MAX);
+        // A loop starting at the end wouldn't play anything
+        let loop_start = self
+            .loop_position
+            .zip(self.loop_point.map(|(_, sample)| sample))
+            .filter(|&(position, _)| position < self.vgm.len() - 1);
+        let loop_samples = loop_start.map_or(0, |(_, sample)| {
+            total_samples.saturating_sub(u32::try_from(sample).unwrap_or(u32::MAX))
+        });
+
+        // The offsets are relative to their own position in the header, the file is far smaller than 4 GiB
+        #[allow(clippy::cast_possible_truncation)]
+        let relative = |position: usize, field: usize| (position - field) as u32;
+        let eof = relative(self.vgm.len(), EOF_OFFSET);
+        let loop_offset = loop_start.map_or(0, |(position, _)| relative(position, LOOP_OFFSET));
+        let data_offset = relative(HEADER_SIZE, DATA_OFFSET);
+
+        self.vgm[..4].copy_from_slice(b"Vgm ");
+        for (offset, value) in [
+            (EOF_OFFSET, eof),
+            (VERSION_OFFSET, VERSION),
+            (TOTAL_SAMPLES_OFFSET, total_samples),
+            (LOOP_OFFSET, loop_offset),
+            (LOOP_SAMPLES_OFFSET, loop_samples),
+            (DATA_OFFSET, data_offset),
+            (NES_APU_CLOCK_OFFSET, clock),
+        ] {
+            self.vgm[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        }
+
+        self.vgm
+    }
+}
+
+fn write_wait(vgm: &mut Vec<u8>, mut samples: u64) {
+    while samples > 0 {
+        let wait = match samples {
+            735 => {
+                vgm.push(WAIT_NTSC_FRAME);
+                735
+            }
+            882 => {
+                vgm.push(WAIT_PAL_FRAME);
+                882
+            }
+            1..=16 => {
+                // The amount fits into the lower nibble
+                #[allow(clippy::cast_possible_truncation)]
+                vgm.push(WAIT_SHORT | (samples - 1) as u8);
+                samples
+            }
+            _ => {
+                let wait = samples.min(u64::from(u16::MAX));
+                vgm.push(WAIT);
+                // The wait was limited to the range of `u16`
+                #[allow(clippy::cast_possible_truncation)]
+                vgm.extend_from_slice(&(wait as u16).to_le_bytes());
+                wait
+            }
+        };
+        samples -= wait;
+    }
+}
@@ -0,0 +1,225 @@
+use nes_state::{Error, Savestate, StateReader, StateWriter};
+
+/// Pulse channel of the VRC6 with eight duty cycles and a direct volume
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Vrc6Pulse {
+    /// Outputs the volume regardless of the duty cycle
+    constant: bool,
+    duty: u8,
+    volume: u8,
+    enabled: bool,
+    step: u8,
+    timer: u16,
+    period: u16,
+}
+
+impl Vrc6Pulse {
+    fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.constant = value & 0x80 != 0;
+                self.duty = (value >> 4) & 0x07;
+                self.volume = value & 0x0F;
+            }
+            1 => self.period = (self.period & 0x0F00) | u16::from(value),
+            _ => {
+                self.period = (self.period & 0x00FF) | (u16::from(value & 0x0F) << 8);
+                self.enabled = value & 0x80 != 0;
+                // Disabling restarts the duty cycle
+                if !self.enabled {
+                    self.step = 15;
+                }
+            }
+        }
+    }
+
+    fn clock_timer(&mut self, shift: u8) {
+        if !self.enabled {
+            return;
+        }
+
+        if self.timer == 0 {
+            self.timer = self.period >> shift;
+            self.step = self.step.checked_sub(1).unwrap_or(15);
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    fn output(&self) -> u8 {
+        if self.enabled && (self.constant || self.step <= self.duty) {
+            self.volume
+        } else {
+            0
+        }
+    }
+}
+
+impl Savestate for Vrc6Pulse {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bool(self.constant);
+        writer.write_u8(self.duty);
+        writer.write_u8(self.volume);
+        writer.write_bool(self.enabled);
+        writer.write_u8(self.step);
+        writer.write_u16(self.timer);
+        writer.write_u16(self.period);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), Error> {
+        self.constant = reader.read_bool()?;
+        self.duty = reader.read_u8()? & 0x07;
+        self.volume = reader.read_u8()? & 0x0F;
+        self.enabled = reader.read_bool()?;
+        self.step = reader.read_u8()? & 0x0F;
+        self.timer = reader.read_u16()?;
+        self.period = reader.read_u16()?;
+
+        Ok(())
+    }
+}
+
+/// Sawtooth channel of the VRC6, adding the rate to an accumulator on every other step
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Sawtooth {
+    rate: u8,
+    enabled: bool,
+    accumulator: u8,
+    step: u8,
+    timer: u16,
+    period: u16,
+}
+
+impl Sawtooth {
+    fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => self.rate = value & 0x3F,
+            1 => self.period = (self.period & 0x0F00) | u16::from(value),
+            _ => {
+                self.period = (self.period & 0x00FF) | (u16::from(value & 0x0F) << 8);
+                self.enabled = value & 0x80 != 0;
+                if !self.enabled {
+                    self.accumulator = 0;
+                    self.step = 0;
+                }
+            }
+        }
+    }
+
+    fn clock_timer(&mut self, shift: u8) {
+        if !self.enabled {
+            return;
+        }
+
+        if self.timer == 0 {
+            self.timer = self.period >> shift;
+            self.step += 1;
+            // The seventh addition resets the accumulator instead
+            if self.step == 14 {
+                self.step = 0;
+                self.accumulator = 0;
+            } else if self.step.is_multiple_of(2) {
+                self.accumulator = self.accumulator.wrapping_add(self.rate);
+            }
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    fn output(&self) -> u8 {
+        self.accumulator >> 3
+    }
+}
+
+impl Savestate for Sawtooth {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.rate);
+        writer.write_bool(self.enabled);
+        writer.write_u8(self.accumulator);
+        writer.write_u8(self.step);
+        writer.write_u16(self.timer);
+        writer.write_u16(self.period);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), Error> {
+        self.rate = reader.read_u8()? & 0x3F;
+        self.enabled = reader.read_bool()?;
+        self.accumulator = reader.read_u8()?;
+        self.step = reader.read_u8()? % 14;
+        self.timer = reader.read_u16()?;
+        self.period = reader.read_u16()?;
+
+        Ok(())
+    }
+}
+
+/// Konami VRC6 with two pulse channels and a sawtooth channel
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Vrc6 {
+    pulse1: Vrc6Pulse,
+    pulse2: Vrc6Pulse,
+    sawtooth: Sawtooth,
+    /// Frequency control (`$9003`); halts all channels or speeds them up by 16 or 256
+    halted: bool,
+    shift: u8,
+}
+
+impl Vrc6 {
+    /// Write to one of the registers at `$9000`-`$9003`, `$A000`-`$A002` or `$B000`-`$B002`
+    pub fn write(&mut self, address: u16, value: u8) {
+        let register = address & 0x03;
+
+        match address {
+            0x9003 => {
+                self.halted = value & 0x01 != 0;
+                self.shift = if value & 0x04 != 0 {
+                    8
+                } else if value & 0x02 != 0 {
+                    4
+                } else {
+                    0
+                };
+            }
+            0x9000..=0x9002 => self.pulse1.write(register, value),
+            0xA000..=0xA002 => self.pulse2.write(register, value),
+            0xB000..=0xB002 => self.sawtooth.write(register, value),
+            _ => {}
+        }
+    }
+
+    /// Clocked every CPU cycle
+    pub fn clock_timer(&mut self) {
+        if self.halted {
+            return;
+        }
+
+        self.pulse1.clock_timer(self.shift);
+        self.pulse2.clock_timer(self.shift);
+        self.sawtooth.clock_timer(self.shift);
+    }
+
+    /// Sum of the channels; 0-15 for each pulse and 0-31 for the sawtooth
+    pub fn output(&self) -> u8 {
+        self.pulse1.output() + self.pulse2.output() + self.sawtooth.output()
+    }
+}
+
+impl Savestate for Vrc6 {
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.pulse1.save_state(writer);
+        self.pulse2.save_state(writer);
+        self.sawtooth.save_state(writer);
+        writer.write_bool(self.halted);
+        writer.write_u8(self.shift);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), Error> {
+        self.pulse1.load_state(reader)?;
+        self.pulse2.load_state(reader)?;
+        self.sawtooth.load_state(reader)?;
+        self.halted = reader.read_bool()?;
+        self.shift = reader.read_u8()?.min(8);
+
+        Ok(())
+    }
+}
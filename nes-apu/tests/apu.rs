@@ -0,0 +1,91 @@
+use nes_apu::{Apu, NTSC_CPU_CLOCK};
+
+const SAMPLE_RATE: u32 = 44_100;
+
+// Length of one frame of the frame counter in the 4-step mode
+const FRAME_CYCLES: u32 = 29_830;
+
+fn run(apu: &mut Apu, cycles: u32) {
+    for _ in 0..cycles {
+        apu.tick();
+    }
+}
+
+#[test]
+fn length_counter() {
+    let mut apu = Apu::new(SAMPLE_RATE);
+    // Only the first pulse channel is enabled, loading the other one gets ignored
+    apu.write_register(0x4015, 0x01);
+    apu.write_register(0x4003, 0x00);
+    apu.write_register(0x4007, 0x00);
+    assert_eq!(apu.read_status() & 0x03, 0x01);
+
+    // A length of 10 half frames, with two half frames per frame
+    run(&mut apu, FRAME_CYCLES * 4);
+    assert_eq!(apu.read_status() & 0x01, 0x01);
+    run(&mut apu, FRAME_CYCLES);
+    assert_eq!(apu.read_status() & 0x01, 0x00);
+
+    // Disabling the channel clears the length counter right away
+    apu.write_register(0x4003, 0x08);
+    assert_eq!(apu.read_status() & 0x01, 0x01);
+    apu.write_register(0x4015, 0x00);
+    assert_eq!(apu.read_status() & 0x01, 0x00);
+}
+
+#[test]
+fn frame_irq() {
+    let mut apu = Apu::new(SAMPLE_RATE);
+    run(&mut apu, FRAME_CYCLES);
+    assert!(apu.irq());
+
+    // Reading the status acknowledges the IRQ
+    assert_eq!(apu.read_status() & 0x40, 0x40);
+    assert!(!apu.irq());
+
+    apu.write_register(0x4017, 0x40);
+    run(&mut apu, FRAME_CYCLES);
+    assert!(!apu.irq());
+}
+
+#[test]
+fn dmc_dma() {
+    let mut apu = Apu::new(SAMPLE_RATE);
+    // One byte sample at $C040 with the IRQ enabled
+    apu.write_register(0x4010, 0x80);
+    apu.write_register(0x4012, 0x01);
+    apu.write_register(0x4013, 0x00);
+
+    assert_eq!(apu.dmc_dma_address(), None);
+    apu.write_register(0x4015, 0x10);
+    assert_eq!(apu.dmc_dma_address(), Some(0xC040));
+    assert_eq!(apu.read_status() & 0x10, 0x10);
+
+    apu.load_dmc_sample(0xAA);
+    assert_eq!(apu.dmc_dma_address(), None);
+    assert!(apu.irq());
+    assert_eq!(apu.read_status() & 0x90, 0x80);
+}
+
+#[test]
+fn samples() {
+    let mut apu = Apu::new(SAMPLE_RATE);
+    run(&mut apu, NTSC_CPU_CLOCK);
+
+    let silence = apu.take_samples();
+    assert_eq!(silence.len(), SAMPLE_RATE as usize);
+    // Without any channel playing the output stays at the same level
+    assert!(silence.windows(2).all(|pair| pair[0] == pair[1]));
+    assert!(apu.take_samples().is_empty());
+
+    // Square wave with a constant volume of 15 and a period of $100
+    apu.write_register(0x4015, 0x01);
+    apu.write_register(0x4000, 0xBF);
+    apu.write_register(0x4002, 0x00);
+    apu.write_register(0x4003, 0x01);
+    run(&mut apu, NTSC_CPU_CLOCK / 60);
+
+    let square = apu.take_samples();
+    assert!(square.iter().any(|sample| *sample > silence[0]));
+    assert!(square.iter().all(|sample| (0.0..=1.0).contains(sample)));
+}
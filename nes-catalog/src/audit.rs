@@ -0,0 +1,120 @@
+//!
+//! Auditing of scanned collections against a ROM database
+//!
+
+use {
+    crate::{
+        dat::{Dat, Game, Rom},
+        scanner::Record,
+        Hashes,
+    },
+    alloc::{
+        collections::{BTreeMap, BTreeSet},
+        vec::Vec,
+    },
+};
+
+/// Scanned file identified as a file of the database
+#[derive(Clone, Debug)]
+pub struct Match<'a> {
+    pub record: &'a Record,
+    pub game: &'a Game,
+    pub rom: &'a Rom,
+    /// Whether the database checksums include the INES header
+    pub headered: bool,
+}
+
+impl Match<'_> {
+    /// Whether the file is named like in the database
+    #[must_use]
+    pub fn is_named_correctly(&self) -> bool {
+        self.record.file_name() == Some(self.rom.name.as_str())
+    }
+}
+
+/// Result of an audit
+#[derive(Clone, Debug, Default)]
+pub struct Report<'a> {
+    /// Correctly named files of the database
+    pub matched: Vec<Match<'a>>,
+    /// Files of the database whose name differs from the one in the database
+    pub misnamed: Vec<Match<'a>>,
+    /// Files of the database which weren't found in the collection
+    pub missing: Vec<(&'a Game, &'a Rom)>,
+    /// Files of the collection which aren't in the database
+    pub unrecognized: Vec<&'a Record>,
+}
+
+impl Report<'_> {
+    /// Whether the collection contains every file of the database and nothing else, all correctly named
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.misnamed.is_empty() && self.missing.is_empty() && self.unrecognized.is_empty()
+    }
+}
+
+/// Match the scanned files against the database
+///
+/// Files get matched both with and without their INES header, so headered and headerless databases work alike
+#[must_use]
+pub fn audit<'a>(dat: &'a Dat, records: &'a [Record]) -> Report<'a> {
+    // Index the files of the database by CRC32, which practically every entry has
+    let mut index: BTreeMap<Option<u32>, Vec<(usize, usize)>> = BTreeMap::new();
+    for (game_index, game) in dat.games.iter().enumerate() {
+        for (rom_index, rom) in game.roms.iter().enumerate() {
+            index
+                .entry(rom.crc32)
+                .or_default()
+                .push((game_index, rom_index));
+        }
+    }
+
+    let identify = |size: usize, hashes: &Hashes| {
+        index
+            .get(&Some(hashes.crc32))
+            .into_iter()
+            .chain(index.get(&None))
+            .flatten()
+            .copied()
+            .find(|(game, rom)| dat.games[*game].roms[*rom].matches(size, hashes))
+    };
+
+    let mut report = Report::default();
+    let mut found = BTreeSet::new();
+    for record in records {
+        let identified = identify(record.size, &record.hashes)
+            .map(|entry| (entry, true))
+            .or_else(|| {
+                identify(record.rom_size(), &record.rom_hashes).map(|entry| (entry, false))
+            });
+
+        if let Some(((game_index, rom_index), headered)) = identified {
+            found.insert((game_index, rom_index));
+
+            let game = &dat.games[game_index];
+            let entry = Match {
+                record,
+                game,
+                rom: &game.roms[rom_index],
+                headered,
+            };
+            if entry.is_named_correctly() {
+                report.matched.push(entry);
+            } else {
+                report.misnamed.push(entry);
+            }
+        } else {
+            report.unrecognized.push(record);
+        }
+    }
+
+    for (game_index, game) in dat.games.iter().enumerate() {
+        for (rom_index, rom) in game.roms.iter().enumerate() {
+            if !found.contains(&(game_index, rom_index)) {
+                report.missing.push((game, rom));
+            }
+        }
+    }
+
+    report
+}
@@ -0,0 +1,158 @@
+//!
+//! ROM databases in the Logiqx XML format, as published by No-Intro
+//!
+//! [Format documentation](https://github.com/SabreTools/SabreTools/wiki/DatFile-Formats#logiqx-xml-format)
+//!
+
+use {
+    crate::{
+        xml::{child_text, parse_document, parse_hex},
+        Error, Hashes, Result,
+    },
+    alloc::{
+        string::{String, ToString},
+        vec::Vec,
+    },
+    core::str::FromStr,
+    roxmltree::Node,
+};
+
+/// Information about the database itself
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Header {
+    pub name: String,
+    pub description: String,
+    pub version: String,
+    pub author: String,
+    pub homepage: String,
+}
+
+/// File belonging to a game
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rom {
+    pub name: String,
+    pub size: usize,
+    pub crc32: Option<u32>,
+    pub md5: Option<[u8; 16]>,
+    pub sha1: Option<[u8; 20]>,
+    /// Dump status like `verified` or `baddump`
+    pub status: Option<String>,
+}
+
+impl Rom {
+    /// Whether the checksums belong to this file; checksums missing on either side are skipped
+    #[must_use]
+    pub fn matches(&self, size: usize, hashes: &Hashes) -> bool {
+        let any_checksum = self.crc32.is_some() || self.md5.is_some() || self.sha1.is_some();
+
+        any_checksum
+            && self.size == size
+            && self.crc32.is_none_or(|crc32| crc32 == hashes.crc32)
+            && self.md5.is_none_or(|md5| md5 == hashes.md5)
+            && self.sha1.is_none_or(|sha1| sha1 == hashes.sha1)
+    }
+
+    fn from_node(node: Node<'_, '_>) -> Result<Self> {
+        let attribute = |name: &'static str| node.attribute(name);
+
+        Ok(Self {
+            name: attribute("name")
+                .ok_or(Error::MissingAttribute("name"))?
+                .to_string(),
+            size: attribute("size")
+                .ok_or(Error::MissingAttribute("size"))?
+                .trim()
+                .parse()
+                .map_err(|_| Error::InvalidSize)?,
+            crc32: attribute("crc")
+                .map(|crc| parse_hex(crc).map(u32::from_be_bytes))
+                .transpose()?,
+            md5: attribute("md5").map(parse_hex).transpose()?,
+            sha1: attribute("sha1").map(parse_hex).transpose()?,
+            status: attribute("status").map(ToString::to_string),
+        })
+    }
+}
+
+/// Entry of the database
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Game {
+    pub name: String,
+    pub description: String,
+    /// Name of the game this one is a variant of
+    pub clone_of: Option<String>,
+    pub roms: Vec<Rom>,
+}
+
+/// Database of known dumps
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Dat {
+    pub header: Header,
+    pub games: Vec<Game>,
+}
+
+impl Dat {
+    /// Parse a database in the XML format
+    pub fn parse(text: &str) -> Result<Self> {
+        let document = parse_document(text)?;
+
+        let root = document.root_element();
+        if !root.has_tag_name("datafile") {
+            return Err(Error::UnexpectedElement(root.tag_name().name().to_string()));
+        }
+
+        let header = root
+            .children()
+            .find(|node| node.has_tag_name("header"))
+            .map(|node| Header {
+                name: child_text(node, "name"),
+                description: child_text(node, "description"),
+                version: child_text(node, "version"),
+                author: child_text(node, "author"),
+                homepage: child_text(node, "homepage"),
+            })
+            .unwrap_or_default();
+
+        // Older databases call the games machines
+        let games = root
+            .children()
+            .filter(|node| node.has_tag_name("game") || node.has_tag_name("machine"))
+            .map(|node| {
+                Ok(Game {
+                    name: node
+                        .attribute("name")
+                        .ok_or(Error::MissingAttribute("name"))?
+                        .to_string(),
+                    description: child_text(node, "description"),
+                    clone_of: node.attribute("cloneof").map(ToString::to_string),
+                    roms: node
+                        .children()
+                        .filter(|child| child.has_tag_name("rom"))
+                        .map(Rom::from_node)
+                        .collect::<Result<_>>()?,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self { header, games })
+    }
+
+    /// Find the game and file with the checksums
+    #[must_use]
+    pub fn identify(&self, size: usize, hashes: &Hashes) -> Option<(&Game, &Rom)> {
+        self.games.iter().find_map(|game| {
+            game.roms
+                .iter()
+                .find(|rom| rom.matches(size, hashes))
+                .map(|rom| (game, rom))
+        })
+    }
+}
+
+impl FromStr for Dat {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        Self::parse(text)
+    }
+}
@@ -0,0 +1,121 @@
+use {ines_parser::Ines, md5::Md5, sha1::Digest, sha1::Sha1};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Checksums ROM databases identify dumps with
+///
+/// With the `serde` feature they're serialized as lowercase hex strings, like databases write them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Hashes {
+    #[cfg_attr(feature = "serde", serde(with = "hex_crc32"))]
+    pub crc32: u32,
+    #[cfg_attr(feature = "serde", serde(with = "hex"))]
+    pub md5: [u8; 16],
+    #[cfg_attr(feature = "serde", serde(with = "hex"))]
+    pub sha1: [u8; 20],
+}
+
+impl Hashes {
+    /// Calculate the checksums of the data
+    #[must_use]
+    pub fn of(data: &[u8]) -> Self {
+        Self {
+            crc32: crc32fast::hash(data),
+            md5: Md5::digest(data).into(),
+            sha1: Sha1::digest(data).into(),
+        }
+    }
+}
+
+/// Checksums of a file and, for INES ROMs, of the parts ROM databases hash
+///
+/// Databases like No-Intro hash ROMs without their header, which is what `rom` covers (the PRG ROM followed by the CHR ROM).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HashReport {
+    pub file: Hashes,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub rom: Option<Hashes>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub prg_rom: Option<Hashes>,
+    /// Only set for ROMs with a CHR ROM
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub chr_rom: Option<Hashes>,
+}
+
+impl HashReport {
+    /// Calculate the checksums of a file, which doesn't have to be a ROM
+    #[must_use]
+    pub fn of(data: &[u8]) -> Self {
+        let mut report = Self {
+            file: Hashes::of(data),
+            rom: None,
+            prg_rom: None,
+            chr_rom: None,
+        };
+
+        if let Ok(ines) = Ines::from_bytes(data) {
+            let chr_rom = ines.chr_rom.as_deref().unwrap_or_default();
+            report.rom = Some(Hashes::of(&[&*ines.prg_rom, chr_rom].concat()));
+            report.prg_rom = Some(Hashes::of(&ines.prg_rom));
+            report.chr_rom = (!chr_rom.is_empty()).then(|| Hashes::of(chr_rom));
+        }
+
+        report
+    }
+}
+
+#[cfg(feature = "serde")]
+mod hex {
+    use {
+        crate::xml::parse_hex,
+        alloc::string::String,
+        core::fmt::Write,
+        serde::{de::Error, Deserialize, Deserializer, Serializer},
+    };
+
+    pub fn serialize<S: Serializer, const N: usize>(
+        bytes: &[u8; N],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut hex = String::with_capacity(N * 2);
+        for byte in bytes {
+            let _ = write!(hex, "{byte:02x}");
+        }
+
+        serializer.serialize_str(&hex)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+        deserializer: D,
+    ) -> Result<[u8; N], D::Error> {
+        let hex = String::deserialize(deserializer)?;
+
+        parse_hex(&hex).map_err(|_| D::Error::custom("invalid checksum"))
+    }
+}
+
+#[cfg(feature = "serde")]
+mod hex_crc32 {
+    use serde::{Deserializer, Serializer};
+
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    pub fn serialize<S: Serializer>(crc32: &u32, serializer: S) -> Result<S::Ok, S::Error> {
+        super::hex::serialize(&crc32.to_be_bytes(), serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+        super::hex::deserialize(deserializer).map(u32::from_be_bytes)
+    }
+}
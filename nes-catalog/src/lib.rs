@@ -0,0 +1,76 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![warn(clippy::all, clippy::pedantic)]
+#![allow(clippy::missing_errors_doc)]
+
+//!
+//! Building blocks for ROM managers
+//!
+
+extern crate alloc;
+
+use alloc::string::String;
+
+#[cfg(feature = "scanner")]
+pub mod audit;
+pub mod dat;
+mod hash;
+pub mod nes20db;
+pub mod nescartdb;
+pub mod recommend;
+#[cfg(feature = "scanner")]
+pub mod scanner;
+mod xml;
+
+#[cfg(feature = "scanner")]
+pub use audit::{audit, Report};
+#[cfg(feature = "scanner")]
+pub use scanner::{Record, Scanner};
+pub use {
+    dat::Dat,
+    hash::{HashReport, Hashes},
+    nes20db::Nes20Db,
+    nescartdb::CartDb,
+    recommend::{recommend_header, validate_header, HeaderRecommendation, HeaderValidation},
+};
+
+type Result<T> = core::result::Result<T, Error>;
+
+#[derive(Debug)]
+#[cfg_attr(feature = "std", derive(thiserror::Error))]
+pub enum Error {
+    #[cfg(feature = "std")]
+    #[error("IO error in {}: {:?}", .0.display(), .1)]
+    Io(std::path::PathBuf, std::io::Error),
+
+    #[cfg(feature = "scanner")]
+    #[error("Directory traversal error: {}", .0)]
+    Walk(#[from] walkdir::Error),
+
+    #[cfg(feature = "scanner")]
+    #[error("ZIP error in {}: {}", .0.display(), .1)]
+    Zip(std::path::PathBuf, zip::result::ZipError),
+
+    #[cfg_attr(feature = "std", error("Invalid XML: {}", .0))]
+    Xml(roxmltree::Error),
+
+    #[cfg_attr(feature = "std", error("Unexpected element {}", .0))]
+    UnexpectedElement(String),
+
+    #[cfg_attr(feature = "std", error("The attribute {} is missing", .0))]
+    MissingAttribute(&'static str),
+
+    #[cfg_attr(feature = "std", error("Invalid size"))]
+    InvalidSize,
+
+    #[cfg_attr(feature = "std", error("Invalid mapper number"))]
+    InvalidMapper,
+
+    #[cfg_attr(feature = "std", error("Invalid checksum {}", .0))]
+    InvalidHash(String),
+}
+
+impl From<roxmltree::Error> for Error {
+    fn from(err: roxmltree::Error) -> Self {
+        Self::Xml(err)
+    }
+}
@@ -0,0 +1,202 @@
+//!
+//! NES 2.0 header database (nes20db)
+//!
+//! It stores the correct NES 2.0 header of every known dump.
+//! The checksums of an entry cover the ROM data following the header, so they don't depend on the header being correct.
+//!
+
+use {
+    crate::{
+        xml::{children, parse_document, parse_hex},
+        Error, Hashes, Result,
+    },
+    alloc::{
+        collections::BTreeMap,
+        string::{String, ToString},
+        vec::Vec,
+    },
+    core::str::FromStr,
+    ines_parser::VramLayout,
+    roxmltree::Node,
+};
+
+fn number<T: FromStr>(node: Node<'_, '_>, name: &'static str) -> Result<Option<T>> {
+    node.attribute(name)
+        .map(|value| value.trim().parse().map_err(|_| Error::InvalidSize))
+        .transpose()
+}
+
+fn child<'a, 'input: 'a>(node: Node<'a, 'input>, name: &'a str) -> Option<Node<'a, 'input>> {
+    children(node, name).next()
+}
+
+// Size of a RAM element, zero if the element is missing
+fn ram_size(node: Node<'_, '_>, name: &str) -> Result<usize> {
+    child(node, name).map_or(Ok(0), |ram| Ok(number(ram, "size")?.unwrap_or_default()))
+}
+
+/// ROM section with its checksums
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Rom {
+    pub size: usize,
+    pub crc32: Option<u32>,
+    pub sha1: Option<[u8; 20]>,
+}
+
+impl Rom {
+    fn from_node(node: Node<'_, '_>) -> Result<Self> {
+        Ok(Self {
+            size: number(node, "size")?.unwrap_or_default(),
+            crc32: node
+                .attribute("crc32")
+                .map(|crc| parse_hex(crc).map(u32::from_be_bytes))
+                .transpose()?,
+            sha1: node.attribute("sha1").map(parse_hex).transpose()?,
+        })
+    }
+}
+
+/// Header data of a dump
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    /// Path of the dump the entry was made from, taken from the comment of the entry
+    pub name: Option<String>,
+    /// Checksums of all the ROM data following the header
+    pub rom: Option<Rom>,
+    pub prg_rom: Option<Rom>,
+    pub chr_rom: Option<Rom>,
+    pub trainer: Option<Rom>,
+    pub mapper: u16,
+    pub submapper: u8,
+    /// Nametable layout of the header; the database marks mapper-controlled mirroring as horizontal
+    pub vram_layout: VramLayout,
+    pub battery: bool,
+    pub prg_ram_size: usize,
+    pub prg_nvram_size: usize,
+    pub chr_ram_size: usize,
+    pub chr_nvram_size: usize,
+    /// Console type of the header (0 for the NES/Famicom)
+    pub console_type: u8,
+    /// CPU/PPU timing of the header (0 for NTSC, 1 for PAL, 2 for multi-region and 3 for Dendy)
+    pub timing: u8,
+}
+
+impl Entry {
+    fn from_node(node: Node<'_, '_>) -> Result<Self> {
+        let rom = |name| child(node, name).map(Rom::from_node).transpose();
+
+        // The comment is either the first child of the entry or right in front of it
+        let name = node
+            .children()
+            .chain(node.prev_siblings().skip(1))
+            .find(|sibling| sibling.is_comment() || sibling.is_element())
+            .filter(Node::is_comment)
+            .and_then(|comment| comment.text())
+            .map(|text| text.trim().to_string());
+
+        let pcb = child(node, "pcb").ok_or(Error::MissingAttribute("pcb"))?;
+        let vram_layout = match pcb.attribute("mirroring").map(str::trim) {
+            Some("V") => VramLayout::VerticalMirroring,
+            Some("4") => VramLayout::FourScreen,
+            _ => VramLayout::HorizontalMirroring,
+        };
+
+        let console = child(node, "console");
+        let console_attribute = |name| {
+            console
+                .map(|console| number(console, name))
+                .transpose()
+                .map(|value| value.flatten().unwrap_or_default())
+        };
+
+        Ok(Self {
+            name,
+            rom: rom("rom")?,
+            prg_rom: rom("prgrom")?,
+            chr_rom: rom("chrrom")?,
+            trainer: rom("trainer")?,
+            mapper: number(pcb, "mapper")
+                .map_err(|_| Error::InvalidMapper)?
+                .ok_or(Error::MissingAttribute("mapper"))?,
+            submapper: number(pcb, "submapper")
+                .map_err(|_| Error::InvalidMapper)?
+                .unwrap_or_default(),
+            vram_layout,
+            battery: pcb.attribute("battery") == Some("1"),
+            prg_ram_size: ram_size(node, "prgram")?,
+            prg_nvram_size: ram_size(node, "prgnvram")?,
+            chr_ram_size: ram_size(node, "chrram")?,
+            chr_nvram_size: ram_size(node, "chrnvram")?,
+            console_type: console_attribute("type")?,
+            timing: console_attribute("region")?,
+        })
+    }
+}
+
+/// Parsed header database, indexed by the checksums of the ROM data
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Nes20Db {
+    entries: Vec<Entry>,
+    /// Entry indices by the CRC32 of the ROM data
+    index: BTreeMap<u32, Vec<usize>>,
+}
+
+impl Nes20Db {
+    /// Parse the XML file of the database
+    pub fn parse(text: &str) -> Result<Self> {
+        let document = parse_document(text)?;
+
+        let root = document.root_element();
+        if !root.has_tag_name("nes20db") {
+            return Err(Error::UnexpectedElement(root.tag_name().name().to_string()));
+        }
+
+        let entries: Vec<Entry> = children(root, "game")
+            .map(Entry::from_node)
+            .collect::<Result<_>>()?;
+
+        let mut index: BTreeMap<u32, Vec<usize>> = BTreeMap::new();
+        for (entry_index, entry) in entries.iter().enumerate() {
+            if let Some(crc32) = entry.rom.and_then(|rom| rom.crc32) {
+                index.entry(crc32).or_default().push(entry_index);
+            }
+        }
+
+        Ok(Self { entries, index })
+    }
+
+    #[must_use]
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// All entries whose ROM data has the CRC32
+    pub fn find_by_crc32(&self, crc32: u32) -> impl Iterator<Item = &Entry> {
+        self.index
+            .get(&crc32)
+            .into_iter()
+            .flatten()
+            .map(move |entry| &self.entries[*entry])
+    }
+
+    /// Find the entry with the checksums of the ROM data, without the INES header
+    ///
+    /// The SHA-1 has to match as well if the database contains it
+    #[must_use]
+    pub fn identify(&self, hashes: &Hashes) -> Option<&Entry> {
+        self.find_by_crc32(hashes.crc32).find(|entry| {
+            entry
+                .rom
+                .and_then(|rom| rom.sha1)
+                .is_none_or(|sha1| sha1 == hashes.sha1)
+        })
+    }
+}
+
+impl FromStr for Nes20Db {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        Self::parse(text)
+    }
+}
@@ -0,0 +1,285 @@
+//!
+//! Cartridge database of `NesCartDB` (formerly `BootGod`'s database)
+//!
+//! Unlike the No-Intro databases, it documents the hardware of the cartridges.
+//! The checksums of a cartridge cover the PRG ROM followed by the CHR ROM, without any header.
+//!
+
+use {
+    crate::{
+        xml::{children, parse_document, parse_hex},
+        Error, Hashes, Result,
+    },
+    alloc::{
+        collections::BTreeMap,
+        string::{String, ToString},
+        vec::Vec,
+    },
+    core::str::FromStr,
+    roxmltree::Node,
+};
+
+// Sizes are written in KiB with a `k` suffix
+fn parse_size(node: Node<'_, '_>) -> Result<usize> {
+    let size = node.attribute("size").unwrap_or("0").trim();
+    let kibibytes = size.strip_suffix('k').unwrap_or(size);
+
+    kibibytes
+        .parse::<usize>()
+        .map(|kibibytes| kibibytes * 1024)
+        .map_err(|_| Error::InvalidSize)
+}
+
+fn attribute(node: Node<'_, '_>, name: &str) -> String {
+    node.attribute(name).unwrap_or_default().to_string()
+}
+
+fn optional_attribute(node: Node<'_, '_>, name: &str) -> Option<String> {
+    node.attribute(name).map(ToString::to_string)
+}
+
+fn crc32(node: Node<'_, '_>) -> Result<Option<u32>> {
+    node.attribute("crc")
+        .map(|crc| parse_hex(crc).map(u32::from_be_bytes))
+        .transpose()
+}
+
+fn sha1(node: Node<'_, '_>) -> Result<Option<[u8; 20]>> {
+    node.attribute("sha1").map(parse_hex).transpose()
+}
+
+/// ROM chip on the board
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RomChip {
+    /// Label of the chip, like `NES-SM-0 PRG`
+    pub name: String,
+    pub size: usize,
+    pub crc32: Option<u32>,
+    pub sha1: Option<[u8; 20]>,
+}
+
+impl RomChip {
+    fn from_node(node: Node<'_, '_>) -> Result<Self> {
+        Ok(Self {
+            name: attribute(node, "name"),
+            size: parse_size(node)?,
+            crc32: crc32(node)?,
+            sha1: sha1(node)?,
+        })
+    }
+}
+
+/// RAM chip on the board
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RamChip {
+    pub size: usize,
+    pub battery: bool,
+}
+
+impl RamChip {
+    fn from_node(node: Node<'_, '_>) -> Result<Self> {
+        Ok(Self {
+            size: parse_size(node)?,
+            battery: node.attribute("battery") == Some("1"),
+        })
+    }
+}
+
+/// Circuit board of a cartridge
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Board {
+    /// Board name, like `NES-TSROM`
+    pub name: String,
+    /// Revision of the board printed on the PCB, like `NES-TSROM-08`
+    pub pcb: String,
+    pub mapper: Option<u16>,
+    pub prg: Vec<RomChip>,
+    pub chr: Vec<RomChip>,
+    /// Work RAM at `$6000`-`$7FFF`
+    pub wram: Vec<RamChip>,
+    /// Video RAM, either as CHR RAM or additional nametables
+    pub vram: Vec<RamChip>,
+    /// Types of the other chips, like mappers or sound chips
+    pub chips: Vec<String>,
+    /// Type of the lockout chip
+    pub cic: Option<String>,
+}
+
+impl Board {
+    fn from_node(node: Node<'_, '_>) -> Result<Self> {
+        let chips = |name| {
+            children(node, name)
+                .map(RomChip::from_node)
+                .collect::<Result<Vec<_>>>()
+        };
+        let rams = |name| {
+            children(node, name)
+                .map(RamChip::from_node)
+                .collect::<Result<Vec<_>>>()
+        };
+
+        Ok(Self {
+            name: attribute(node, "type"),
+            pcb: attribute(node, "pcb"),
+            mapper: node
+                .attribute("mapper")
+                .map(|mapper| mapper.trim().parse().map_err(|_| Error::InvalidMapper))
+                .transpose()?,
+            prg: chips("prg")?,
+            chr: chips("chr")?,
+            wram: rams("wram")?,
+            vram: rams("vram")?,
+            chips: children(node, "chip")
+                .filter_map(|chip| chip.attribute("type"))
+                .map(ToString::to_string)
+                .collect(),
+            cic: children(node, "cic")
+                .find_map(|cic| cic.attribute("type"))
+                .map(ToString::to_string),
+        })
+    }
+}
+
+/// Released cartridge of a game
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cartridge {
+    /// Console the cartridge is for, like `NES-NTSC`, `NES-PAL-A` or `Famicom`
+    pub system: String,
+    /// Checksum of the PRG ROM followed by the CHR ROM
+    pub crc32: Option<u32>,
+    pub sha1: Option<[u8; 20]>,
+    /// Dump status, usually `ok`
+    pub dump: String,
+    pub boards: Vec<Board>,
+}
+
+impl Cartridge {
+    fn from_node(node: Node<'_, '_>) -> Result<Self> {
+        Ok(Self {
+            system: attribute(node, "system"),
+            crc32: crc32(node)?,
+            sha1: sha1(node)?,
+            dump: attribute(node, "dump"),
+            boards: children(node, "board")
+                .map(Board::from_node)
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    /// Board of the cartridge; practically every cartridge has exactly one
+    #[must_use]
+    pub fn board(&self) -> Option<&Board> {
+        self.boards.first()
+    }
+}
+
+/// Game with all of its releases
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Game {
+    pub name: String,
+    pub alt_name: Option<String>,
+    /// Licensing class, like `Licensed` or `Unlicensed`
+    pub class: String,
+    /// Catalog number, like `NES-SM-USA`
+    pub catalog: String,
+    pub publisher: String,
+    pub developer: String,
+    pub region: String,
+    pub players: Option<u8>,
+    /// Release date, as precise as known (`YYYY-MM-DD`, `YYYY-MM` or `YYYY`)
+    pub date: Option<String>,
+    pub cartridges: Vec<Cartridge>,
+}
+
+impl Game {
+    fn from_node(node: Node<'_, '_>) -> Result<Self> {
+        Ok(Self {
+            name: attribute(node, "name"),
+            alt_name: optional_attribute(node, "altname"),
+            class: attribute(node, "class"),
+            catalog: attribute(node, "catalog"),
+            publisher: attribute(node, "publisher"),
+            developer: attribute(node, "developer"),
+            region: attribute(node, "region"),
+            players: node
+                .attribute("players")
+                .and_then(|players| players.trim().parse().ok()),
+            date: optional_attribute(node, "date"),
+            cartridges: children(node, "cartridge")
+                .map(Cartridge::from_node)
+                .collect::<Result<_>>()?,
+        })
+    }
+}
+
+/// Parsed cartridge database, indexed by the checksums of the cartridges
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CartDb {
+    games: Vec<Game>,
+    /// Game and cartridge indices by the CRC32 of the cartridge
+    index: BTreeMap<u32, Vec<(usize, usize)>>,
+}
+
+impl CartDb {
+    /// Parse the XML dump of the database
+    pub fn parse(text: &str) -> Result<Self> {
+        let document = parse_document(text)?;
+
+        let root = document.root_element();
+        if !root.has_tag_name("database") {
+            return Err(Error::UnexpectedElement(root.tag_name().name().to_string()));
+        }
+
+        let games: Vec<Game> = children(root, "game")
+            .map(Game::from_node)
+            .collect::<Result<_>>()?;
+
+        let mut index: BTreeMap<u32, Vec<(usize, usize)>> = BTreeMap::new();
+        for (game_index, game) in games.iter().enumerate() {
+            for (cartridge_index, cartridge) in game.cartridges.iter().enumerate() {
+                if let Some(crc32) = cartridge.crc32 {
+                    index
+                        .entry(crc32)
+                        .or_default()
+                        .push((game_index, cartridge_index));
+                }
+            }
+        }
+
+        Ok(Self { games, index })
+    }
+
+    #[must_use]
+    pub fn games(&self) -> &[Game] {
+        &self.games
+    }
+
+    /// All cartridges whose PRG and CHR ROM have the CRC32
+    pub fn find_by_crc32(&self, crc32: u32) -> impl Iterator<Item = (&Game, &Cartridge)> {
+        self.index
+            .get(&crc32)
+            .into_iter()
+            .flatten()
+            .map(move |(game, cartridge)| {
+                let game = &self.games[*game];
+                (game, &game.cartridges[*cartridge])
+            })
+    }
+
+    /// Find the cartridge with the checksums of its PRG and CHR ROM, without the INES header
+    ///
+    /// The SHA-1 has to match as well if the database contains it
+    #[must_use]
+    pub fn identify(&self, hashes: &Hashes) -> Option<(&Game, &Cartridge)> {
+        self.find_by_crc32(hashes.crc32)
+            .find(|(_, cartridge)| cartridge.sha1.is_none_or(|sha1| sha1 == hashes.sha1))
+    }
+}
+
+impl FromStr for CartDb {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        Self::parse(text)
+    }
+}
@@ -0,0 +1,354 @@
+//!
+//! Suggestions for the correct header of ROMs with wrong or missing header data
+//!
+//! ROMs known to the NES 2.0 header database get the header stored there.
+//! Everything else falls back to heuristics based on the PRG ROM: ASIC mappers are detected by the registers the code writes to,
+//! discrete logic boards by the ROM sizes, and work RAM by accesses to `$6000`-`$7FFF`.
+//!
+
+use {
+    crate::{nes20db::Entry, Error, Hashes, Nes20Db, Result},
+    alloc::{collections::BTreeSet, string::String, vec::Vec},
+    core::convert::TryFrom,
+    ines_parser::{Header, Ines, VramLayout},
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+const WORK_RAM_SIZE: usize = 0x2000;
+const CHR_RAM_SIZE: usize = 0x2000;
+const PRG_ROM_WINDOW_SIZE: usize = 0x8000;
+const CHR_ROM_WINDOW_SIZE: usize = 0x2000;
+
+const STA_ABSOLUTE: u8 = 0x8D;
+const LSR_ACCUMULATOR: u8 = 0x4A;
+
+// Absolute loads and stores with all index variants
+const WORK_RAM_ACCESSES: [u8; 11] = [
+    0xAD, 0xAE, 0xAC, 0xBD, 0xB9, 0xBE, 0xBC, 0x8D, 0x8E, 0x8C, 0x9D,
+];
+
+// PRG and CHR modes, ExRAM mode, nametable mapping and the PRG bank registers
+const MMC5_REGISTERS: [u16; 8] = [
+    0x5100, 0x5101, 0x5104, 0x5105, 0x5114, 0x5115, 0x5116, 0x5117,
+];
+
+// Bank select and bank data first, then mirroring, PRG RAM protection and the IRQ registers
+const MMC3_REGISTERS: [u16; 8] = [
+    0x8000, 0x8001, 0xA000, 0xA001, 0xC000, 0xC001, 0xE000, 0xE001,
+];
+
+// Mappers the heuristics can tell apart; headers with other mappers are left alone
+const KNOWN_MAPPERS: [u16; 8] = [0, 1, 2, 3, 4, 5, 7, 66];
+
+/// Where a recommendation comes from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Source {
+    /// Entry of the NES 2.0 header database matching the checksums of the ROM
+    Database,
+    /// Static analysis of the ROM data
+    Heuristics,
+}
+
+/// Header field differing from the recommendation
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Field {
+    Mapper,
+    Submapper,
+    VramLayout,
+    Battery,
+    PrgRamSize,
+    PrgNvramSize,
+    ChrRamSize,
+    ChrNvramSize,
+}
+
+/// Suggested header data
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HeaderRecommendation {
+    pub source: Source,
+    /// Name of the matching database entry
+    pub name: Option<String>,
+    pub mapper: u16,
+    pub submapper: u8,
+    pub vram_layout: VramLayout,
+    pub battery: bool,
+    pub prg_ram_size: usize,
+    pub prg_nvram_size: usize,
+    pub chr_ram_size: usize,
+    pub chr_nvram_size: usize,
+}
+
+impl HeaderRecommendation {
+    fn from_entry(entry: &Entry) -> Self {
+        Self {
+            source: Source::Database,
+            name: entry.name.clone(),
+            mapper: entry.mapper,
+            submapper: entry.submapper,
+            vram_layout: entry.vram_layout,
+            battery: entry.battery,
+            prg_ram_size: entry.prg_ram_size,
+            prg_nvram_size: entry.prg_nvram_size,
+            chr_ram_size: entry.chr_ram_size,
+            chr_nvram_size: entry.chr_nvram_size,
+        }
+    }
+
+    /// Fields of the header differing from the recommendation
+    ///
+    /// INES 1 headers can't store the submapper and the exact RAM sizes, so those are only compared for NES 2.0 headers
+    #[must_use]
+    pub fn differences(&self, header: &Header) -> Vec<Field> {
+        let mut differences = Vec::new();
+        let mut compare = |field, differs| {
+            if differs {
+                differences.push(field);
+            }
+        };
+
+        compare(
+            Field::Mapper,
+            u16::from(header.mapper_number) != self.mapper,
+        );
+        compare(Field::VramLayout, header.vram_layout != self.vram_layout);
+        compare(Field::Battery, header.has_persistent_memory != self.battery);
+        if header.is_nes2 {
+            compare(Field::Submapper, header.submapper != self.submapper);
+            compare(Field::PrgRamSize, header.prg_ram_size != self.prg_ram_size);
+            compare(
+                Field::PrgNvramSize,
+                header.prg_nvram_size != self.prg_nvram_size,
+            );
+            compare(Field::ChrRamSize, header.chr_ram_size != self.chr_ram_size);
+            compare(
+                Field::ChrNvramSize,
+                header.chr_nvram_size != self.chr_nvram_size,
+            );
+        }
+
+        differences
+    }
+
+    /// Whether the header already matches the recommendation
+    #[must_use]
+    pub fn matches(&self, header: &Header) -> bool {
+        self.differences(header).is_empty()
+    }
+
+    /// Check the header against the recommendation
+    #[must_use]
+    pub fn validate(self, header: &Header) -> HeaderValidation {
+        HeaderValidation {
+            differences: self.differences(header),
+            recommendation: self,
+        }
+    }
+
+    /// Change the header to match the recommendation
+    ///
+    /// The submapper and the exact RAM sizes are set either way, INES 1 headers just can't store them.
+    /// Returns [`Error::InvalidMapper`] if the mapper doesn't fit into the header.
+    pub fn apply(&self, header: &mut Header) -> Result<()> {
+        header.mapper_number = u8::try_from(self.mapper).map_err(|_| Error::InvalidMapper)?;
+        header.submapper = self.submapper;
+        if header.vram_layout != self.vram_layout {
+            header.vram_layout = self.vram_layout;
+            header.mirroring_flag = self.vram_layout == VramLayout::VerticalMirroring;
+        }
+        header.has_persistent_memory = self.battery;
+        header.prg_ram_size = self.prg_ram_size;
+        header.prg_nvram_size = self.prg_nvram_size;
+        header.chr_ram_size = self.chr_ram_size;
+        header.chr_nvram_size = self.chr_nvram_size;
+
+        Ok(())
+    }
+}
+
+/// Outcome of checking a header against the recommendation for its ROM
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HeaderValidation {
+    pub recommendation: HeaderRecommendation,
+    /// Fields of the header differing from the recommendation
+    pub differences: Vec<Field>,
+}
+
+impl HeaderValidation {
+    /// Whether the header matches the recommendation
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        self.differences.is_empty()
+    }
+}
+
+// Addresses written to by absolute stores
+//
+// Only `STA` is considered, which is what games write their mapper registers with.
+// Any byte of data can look like an instruction, so the signatures below only count exact register addresses.
+fn store_targets(prg_rom: &[u8]) -> BTreeSet<u16> {
+    prg_rom
+        .windows(3)
+        .filter(|window| window[0] == STA_ABSOLUTE)
+        .map(|window| u16::from_le_bytes([window[1], window[2]]))
+        .collect()
+}
+
+// MMC1 registers are loaded serially, one bit per write, with the value shifted right in between
+fn has_serial_writes(prg_rom: &[u8]) -> bool {
+    let serial_writes = prg_rom
+        .windows(4)
+        .filter(|window| {
+            window[0] == STA_ABSOLUTE && window[2] >= 0x80 && window[3] == LSR_ACCUMULATOR
+        })
+        .count();
+
+    serial_writes >= 2
+}
+
+fn accesses_work_ram(prg_rom: &[u8]) -> bool {
+    prg_rom
+        .windows(3)
+        .any(|window| WORK_RAM_ACCESSES.contains(&window[0]) && (0x60..0x80).contains(&window[2]))
+}
+
+// Whether every 32 KiB bank ends with the same vectors, like `AxROM` games which can boot into any bank
+fn has_vectors_in_every_bank(prg_rom: &[u8]) -> bool {
+    let mut vectors = prg_rom
+        .chunks_exact(PRG_ROM_WINDOW_SIZE)
+        .map(|bank| &bank[bank.len() - 4..]);
+
+    vectors
+        .next()
+        .is_some_and(|first| vectors.all(|other| other == first))
+}
+
+fn detect_mapper(ines: &Ines<'_>) -> Option<u16> {
+    let prg_rom = &*ines.prg_rom;
+    let chr_rom_size = ines.chr_rom.as_ref().map_or(0, |chr_rom| chr_rom.len());
+    let targets = store_targets(prg_rom);
+    let writes = |registers: &[u16]| {
+        registers
+            .iter()
+            .filter(|register| targets.contains(*register))
+            .count()
+    };
+
+    if writes(&MMC5_REGISTERS) >= 4 {
+        return Some(5);
+    }
+    if writes(&MMC3_REGISTERS[..2]) == 2 && writes(&MMC3_REGISTERS[2..]) >= 2 {
+        return Some(4);
+    }
+    if has_serial_writes(prg_rom) {
+        return Some(1);
+    }
+
+    // Discrete logic boards have a single register covering the whole ROM window, so only the sizes can tell them apart
+    let prg_rom_is_banked = prg_rom.len() > PRG_ROM_WINDOW_SIZE;
+    let chr_rom_is_banked = chr_rom_size > CHR_ROM_WINDOW_SIZE;
+
+    match (prg_rom_is_banked, chr_rom_is_banked) {
+        (false, false) => Some(0),
+        (false, true) => Some(3),
+        (true, true) => Some(66),
+        (true, false) if chr_rom_size == 0 => {
+            if has_vectors_in_every_bank(prg_rom) {
+                Some(7)
+            } else {
+                Some(2)
+            }
+        }
+        (true, false) => None,
+    }
+}
+
+fn recommend_from_heuristics(ines: &Ines<'_>) -> HeaderRecommendation {
+    let header = &ines.header;
+    let header_mapper = u16::from(header.mapper_number);
+
+    // Unknown mappers in the header are more likely to be right than a guess between the few known ones
+    let mapper = if KNOWN_MAPPERS.contains(&header_mapper) {
+        detect_mapper(ines).unwrap_or(header_mapper)
+    } else {
+        header_mapper
+    };
+    let submapper = if mapper == header_mapper {
+        header.submapper
+    } else {
+        0
+    };
+
+    // Battery-backed RAM is only used by some of the games accessing the work RAM, so the flag is kept
+    let battery = header.has_persistent_memory;
+    let work_ram_size = if accesses_work_ram(&ines.prg_rom) || battery {
+        WORK_RAM_SIZE
+    } else {
+        0
+    };
+    let (prg_ram_size, prg_nvram_size) = if battery {
+        (0, work_ram_size)
+    } else {
+        (work_ram_size, 0)
+    };
+
+    let chr_ram_size = if ines
+        .chr_rom
+        .as_ref()
+        .is_none_or(|chr_rom| chr_rom.is_empty())
+    {
+        CHR_RAM_SIZE
+    } else {
+        0
+    };
+
+    HeaderRecommendation {
+        source: Source::Heuristics,
+        name: None,
+        mapper,
+        submapper,
+        vram_layout: header.vram_layout,
+        battery,
+        prg_ram_size,
+        prg_nvram_size,
+        chr_ram_size,
+        chr_nvram_size: 0,
+    }
+}
+
+/// Recommend the header for the ROM
+///
+/// The ROM data following the header is looked up in the database first, if one is given.
+/// Without a match, the recommendation is based on heuristics and keeps the parts of the header they can't verify,
+/// like the nametable mirroring and mappers other than NROM, MMC1, `UxROM`, CNROM, MMC3, MMC5, `AxROM` and `GxROM`.
+#[must_use]
+pub fn recommend_header(ines: &Ines<'_>, database: Option<&Nes20Db>) -> HeaderRecommendation {
+    let entry = database.and_then(|database| {
+        let mut data = Vec::new();
+        if let Some(trainer) = &ines.trainer {
+            data.extend_from_slice(trainer);
+        }
+        data.extend_from_slice(&ines.prg_rom);
+        if let Some(chr_rom) = &ines.chr_rom {
+            data.extend_from_slice(chr_rom);
+        }
+
+        database.identify(&Hashes::of(&data))
+    });
+
+    entry.map_or_else(
+        || recommend_from_heuristics(ines),
+        HeaderRecommendation::from_entry,
+    )
+}
+
+/// Check the header of the ROM against the recommendation from [`recommend_header`]
+#[must_use]
+pub fn validate_header(ines: &Ines<'_>, database: Option<&Nes20Db>) -> HeaderValidation {
+    recommend_header(ines, database).validate(&ines.header)
+}
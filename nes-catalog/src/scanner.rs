@@ -0,0 +1,193 @@
+//!
+//! Scanning of ROM collections
+//!
+
+use {
+    crate::{Error, Hashes, Result},
+    ines_parser::{Header, Ines},
+    std::{
+        collections::VecDeque,
+        ffi::OsStr,
+        fs::{self, File},
+        io::Read,
+        path::{Path, PathBuf},
+    },
+    walkdir::WalkDir,
+    zip::ZipArchive,
+};
+
+const HEADER_SIZE: usize = 16;
+
+/// Scanned ROM
+#[derive(Clone, Debug)]
+pub struct Record {
+    /// Path of the file, or of the archive containing it
+    pub path: PathBuf,
+    /// Name of the file inside of the archive
+    pub entry: Option<String>,
+    pub size: usize,
+    /// `None` if the file doesn't have a valid INES header
+    pub header: Option<Header>,
+    /// Checksums of the whole file
+    pub hashes: Hashes,
+    /// Checksums of the file without the INES header, which is what most databases use
+    pub rom_hashes: Hashes,
+}
+
+impl Record {
+    fn new(path: PathBuf, entry: Option<String>, data: &[u8]) -> Self {
+        let header = Ines::from_bytes(data).ok().map(|ines| ines.header);
+        let hashes = Hashes::of(data);
+        let rom_hashes = if header.is_some() {
+            Hashes::of(&data[HEADER_SIZE..])
+        } else {
+            hashes
+        };
+
+        Self {
+            path,
+            entry,
+            size: data.len(),
+            header,
+            hashes,
+            rom_hashes,
+        }
+    }
+
+    /// Size of the file without the INES header
+    #[must_use]
+    pub fn rom_size(&self) -> usize {
+        if self.header.is_some() {
+            self.size - HEADER_SIZE
+        } else {
+            self.size
+        }
+    }
+
+    /// Name of the ROM file, without the archive
+    #[must_use]
+    pub fn file_name(&self) -> Option<&str> {
+        match &self.entry {
+            Some(entry) => Path::new(entry).file_name(),
+            None => self.path.file_name(),
+        }
+        .and_then(OsStr::to_str)
+    }
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|found| found.eq_ignore_ascii_case(extension))
+}
+
+/// Recursive scan of a directory for ROMs
+#[derive(Clone, Debug)]
+pub struct Scanner {
+    root: PathBuf,
+    follow_links: bool,
+}
+
+impl Scanner {
+    #[must_use]
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self {
+            root: root.into(),
+            follow_links: false,
+        }
+    }
+
+    /// Follow symbolic links while walking the directory tree
+    #[must_use]
+    pub fn follow_links(mut self, follow_links: bool) -> Self {
+        self.follow_links = follow_links;
+        self
+    }
+
+    /// Walk the directory tree, yielding every `.nes` file and every `.nes` file inside of `.zip` archives
+    ///
+    /// Files which can't be read yield an error, after which the scan continues
+    pub fn scan(&self) -> impl Iterator<Item = Result<Record>> {
+        let files = WalkDir::new(&self.root)
+            .follow_links(self.follow_links)
+            .sort_by_file_name()
+            .into_iter();
+
+        Scan {
+            files,
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+struct Scan {
+    files: walkdir::IntoIter,
+    /// Records of the last archive which weren't yielded yet
+    pending: VecDeque<Result<Record>>,
+}
+
+impl Scan {
+    fn read_file(path: &Path) -> Result<Record> {
+        let data = fs::read(path).map_err(|err| Error::Io(path.to_path_buf(), err))?;
+        Ok(Record::new(path.to_path_buf(), None, &data))
+    }
+
+    fn read_archive(path: &Path) -> Vec<Result<Record>> {
+        let archive = File::open(path)
+            .map_err(|err| Error::Io(path.to_path_buf(), err))
+            .and_then(|file| {
+                ZipArchive::new(file).map_err(|err| Error::Zip(path.to_path_buf(), err))
+            });
+        let mut archive = match archive {
+            Ok(archive) => archive,
+            Err(err) => return vec![Err(err)],
+        };
+
+        (0..archive.len())
+            .filter_map(|index| {
+                let mut entry = match archive.by_index(index) {
+                    Ok(entry) => entry,
+                    Err(err) => return Some(Err(Error::Zip(path.to_path_buf(), err))),
+                };
+                if !entry.is_file() || !has_extension(Path::new(entry.name()), "nes") {
+                    return None;
+                }
+
+                let mut data = Vec::new();
+                if let Err(err) = entry.read_to_end(&mut data) {
+                    return Some(Err(Error::Io(path.to_path_buf(), err)));
+                }
+
+                let name = entry.name().to_string();
+                Some(Ok(Record::new(path.to_path_buf(), Some(name), &data)))
+            })
+            .collect()
+    }
+}
+
+impl Iterator for Scan {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(record) = self.pending.pop_front() {
+                return Some(record);
+            }
+
+            let entry = match self.files.next()? {
+                Ok(entry) => entry,
+                Err(err) => return Some(Err(err.into())),
+            };
+            if !entry.file_type().is_file() {
+                continue;
+            }
+
+            let path = entry.path();
+            if has_extension(path, "nes") {
+                return Some(Self::read_file(path));
+            } else if has_extension(path, "zip") {
+                self.pending.extend(Self::read_archive(path));
+            }
+        }
+    }
+}
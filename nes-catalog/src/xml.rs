@@ -0,0 +1,53 @@
+// Helpers shared by the XML databases
+
+use {
+    crate::{Error, Result},
+    alloc::string::{String, ToString},
+    roxmltree::{Document, Node, ParsingOptions},
+};
+
+/// Parse a document, allowing the document type declarations the databases use
+pub fn parse_document(text: &str) -> Result<Document<'_>> {
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+
+    Ok(Document::parse_with_options(text, options)?)
+}
+
+/// Parse a checksum written in hex
+pub fn parse_hex<const N: usize>(text: &str) -> Result<[u8; N]> {
+    let text = text.trim();
+    if text.len() != N * 2 || !text.is_ascii() {
+        return Err(Error::InvalidHash(text.to_string()));
+    }
+
+    let mut bytes = [0; N];
+    for (byte, digits) in bytes.iter_mut().zip(text.as_bytes().chunks(2)) {
+        // The text was checked to be ASCII, so every pair is a valid string
+        let digits = core::str::from_utf8(digits).unwrap_or_default();
+        *byte = u8::from_str_radix(digits, 16).map_err(|_| Error::InvalidHash(text.to_string()))?;
+    }
+
+    Ok(bytes)
+}
+
+/// Trimmed text of the first child element with the name
+pub fn child_text(node: Node<'_, '_>, name: &str) -> String {
+    node.children()
+        .find(|child| child.has_tag_name(name))
+        .and_then(|child| child.text())
+        .unwrap_or_default()
+        .trim()
+        .to_string()
+}
+
+/// Child elements with the name
+pub fn children<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children()
+        .filter(move |child| child.has_tag_name(name))
+}
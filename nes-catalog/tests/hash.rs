@@ -0,0 +1,17 @@
+use nes_catalog::Hashes;
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[test]
+fn known_hashes() {
+    let hashes = Hashes::of(b"abc");
+
+    assert_eq!(hashes.crc32, 0x3524_41C2);
+    assert_eq!(hex(&hashes.md5), "900150983cd24fb0d6963f7d28e17f72");
+    assert_eq!(
+        hex(&hashes.sha1),
+        "a9993e364706816aba3e25717850c26c9cd0d89d"
+    );
+}
@@ -0,0 +1,66 @@
+#![cfg(feature = "scanner")]
+
+use {
+    nes_catalog::{Hashes, Scanner},
+    std::{env, fs, io::Write, path::PathBuf},
+    zip::{write::FileOptions, ZipWriter},
+};
+
+// iNES ROM with a single 16 KiB PRG ROM bank
+fn rom() -> Vec<u8> {
+    let mut rom = b"NES\x1A\x01".to_vec();
+    rom.resize(16, 0);
+    rom.extend((0..0x4000).map(|index: u32| index.to_le_bytes()[1]));
+    rom
+}
+
+fn directory(name: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("nes-catalog-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&path);
+    fs::create_dir_all(path.join("nested")).unwrap();
+    path
+}
+
+#[test]
+fn scan() {
+    let root = directory("scan");
+    let rom = rom();
+
+    fs::write(root.join("nested").join("game.NES"), &rom).unwrap();
+    fs::write(root.join("headerless.nes"), &rom[16..]).unwrap();
+    fs::write(root.join("readme.txt"), b"not a ROM").unwrap();
+
+    let mut archive = ZipWriter::new(fs::File::create(root.join("games.zip")).unwrap());
+    archive
+        .start_file("inner/game.nes", FileOptions::default())
+        .unwrap();
+    archive.write_all(&rom).unwrap();
+    archive
+        .start_file("notes.txt", FileOptions::default())
+        .unwrap();
+    archive.write_all(b"not a ROM either").unwrap();
+    archive.finish().unwrap();
+
+    let records = Scanner::new(&root)
+        .scan()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    fs::remove_dir_all(&root).unwrap();
+
+    // Sorted by file name, with the archive contributing only its ROM
+    let names: Vec<_> = records.iter().map(|record| record.file_name()).collect();
+    assert_eq!(
+        names,
+        [Some("game.nes"), Some("headerless.nes"), Some("game.NES")]
+    );
+    assert_eq!(records[0].entry.as_deref(), Some("inner/game.nes"));
+
+    // The ROM hashes leave out the header, so they match the headerless dump
+    let rom_hashes = Hashes::of(&rom[16..]);
+    assert_eq!(records[0].hashes, Hashes::of(&rom));
+    for record in &records {
+        assert_eq!(record.rom_hashes, rom_hashes);
+    }
+    assert!(records[1].header.is_none());
+    assert_eq!(records[2].header.as_ref().unwrap().prg_rom_size, 0x4000);
+}
@@ -0,0 +1,108 @@
+use {
+    crate::{game_genie::GameGenie, pro_action_rocky::ProActionRocky, Error},
+    core::{convert::TryFrom, fmt, str::FromStr},
+};
+
+/// Cheat replacing the value the CPU reads from an address
+///
+/// All supported code formats decode into this type, so emulators only need to apply one kind of cheat
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Cheat {
+    pub address: u16,
+    pub value: u8,
+    /// The value only gets replaced if the original value matches
+    pub compare: Option<u8>,
+}
+
+impl Cheat {
+    /// Parse a raw cheat in the `AAAA:VV` or `AAAA?CC:VV` format (all numbers in hex)
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidFormat`] if the cheat doesn't follow the format
+    pub fn parse_raw(code: &str) -> Result<Self, Error> {
+        let (address, value) = code.split_once(':').ok_or(Error::InvalidFormat)?;
+        let (address, compare) = match address.split_once('?') {
+            Some((address, compare)) => (address, Some(compare)),
+            None => (address, None),
+        };
+
+        let hex = |number: &str| {
+            if number.is_empty() || number.len() > 4 {
+                return Err(Error::InvalidFormat);
+            }
+
+            u16::from_str_radix(number, 16).map_err(|_| Error::InvalidFormat)
+        };
+        let byte = |number: &str| u8::try_from(hex(number)?).map_err(|_| Error::InvalidFormat);
+
+        Ok(Self {
+            address: hex(address)?,
+            value: byte(value)?,
+            compare: compare.map(byte).transpose()?,
+        })
+    }
+
+    /// Value the CPU sees when reading from the address with the cheat active
+    #[must_use]
+    pub fn patch(&self, address: u16, value: u8) -> u8 {
+        let compare_matches = self.compare.is_none_or(|compare| compare == value);
+
+        if address == self.address && compare_matches {
+            self.value
+        } else {
+            value
+        }
+    }
+}
+
+impl From<GameGenie> for Cheat {
+    fn from(code: GameGenie) -> Self {
+        Self {
+            address: code.address,
+            value: code.value,
+            compare: code.compare,
+        }
+    }
+}
+
+impl From<ProActionRocky> for Cheat {
+    fn from(code: ProActionRocky) -> Self {
+        Self {
+            address: code.address,
+            value: code.value,
+            compare: Some(code.compare),
+        }
+    }
+}
+
+/// Parse a cheat of any supported format
+///
+/// Codes containing a colon are raw cheats, eight hex digits are a Pro Action Rocky code
+/// and everything else is treated as a Game Genie code
+impl FromStr for Cheat {
+    type Err = Error;
+
+    fn from_str(code: &str) -> Result<Self, Error> {
+        let code = code.trim();
+
+        if code.contains(':') {
+            Self::parse_raw(code)
+        } else if code.len() == 8 && GameGenie::decode(code).is_err() {
+            ProActionRocky::decode(code).map(Self::from)
+        } else {
+            GameGenie::decode(code).map(Self::from)
+        }
+    }
+}
+
+/// Formats the cheat in the raw format
+impl fmt::Display for Cheat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04X}", self.address)?;
+        if let Some(compare) = self.compare {
+            write!(f, "?{compare:02X}")?;
+        }
+        write!(f, ":{:02X}", self.value)
+    }
+}
@@ -0,0 +1,198 @@
+//!
+//! Game Genie codes
+//!
+//! [Code format](https://www.nesdev.org/nesdev_weekly/ggencode.txt)
+//!
+
+use {
+    crate::{Cheat, Error},
+    alloc::{string::String, vec::Vec},
+    core::str::FromStr,
+    ines_parser::Ines,
+};
+
+// Every letter encodes four bits, its index in this alphabet
+const ALPHABET: [char; 16] = [
+    'A', 'P', 'Z', 'L', 'G', 'I', 'T', 'Y', 'E', 'O', 'X', 'U', 'K', 'S', 'V', 'N',
+];
+
+// The CPU address space of the cartridge ROM
+const ROM_START: u16 = 0x8000;
+const ROM_WINDOW_SIZE: usize = 0x8000;
+
+// The smallest PRG ROM bank size of the common mappers; codes for banked ROMs can be in any of those banks
+const BANK_SIZE: usize = 0x2000;
+
+/// Decoded Game Genie code
+///
+/// Whenever the CPU reads from the address, the Game Genie returns the value instead.
+/// Codes with eight letters only do so if the original value matches the compare value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct GameGenie {
+    pub address: u16,
+    pub value: u8,
+    pub compare: Option<u8>,
+}
+
+impl GameGenie {
+    /// Decode a code with six or eight letters
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the code has another length or contains letters outside of the alphabet
+    pub fn decode(code: &str) -> Result<Self, Error> {
+        let n = code
+            .chars()
+            .map(|letter| {
+                ALPHABET
+                    .iter()
+                    .position(|known| *known == letter.to_ascii_uppercase())
+                    .ok_or(Error::InvalidCharacter(letter))
+            })
+            .collect::<Result<Vec<usize>, Error>>()?;
+
+        if n.len() != 6 && n.len() != 8 {
+            return Err(Error::InvalidLength(n.len()));
+        }
+
+        // The positions in the alphabet are nibbles
+        #[allow(clippy::cast_possible_truncation)]
+        let n: Vec<u8> = n.into_iter().map(|nibble| nibble as u8).collect();
+        let nibble = |index: usize| u16::from(n[index]);
+
+        let address = ROM_START
+            | ((nibble(3) & 7) << 12)
+            | ((nibble(5) & 7) << 8)
+            | ((nibble(4) & 8) << 8)
+            | ((nibble(2) & 7) << 4)
+            | ((nibble(1) & 8) << 4)
+            | (nibble(4) & 7)
+            | (nibble(3) & 8);
+
+        let byte = |high: usize, low: usize, last: usize| {
+            ((n[high] & 7) << 4) | ((n[low] & 8) << 4) | (n[low] & 7) | (n[last] & 8)
+        };
+
+        let code = if n.len() == 6 {
+            Self {
+                address,
+                value: byte(1, 0, 5),
+                compare: None,
+            }
+        } else {
+            Self {
+                address,
+                value: byte(1, 0, 7),
+                compare: Some(byte(7, 6, 5)),
+            }
+        };
+
+        Ok(code)
+    }
+
+    /// Encode the code into six letters, or eight letters if it has a compare value
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidAddress`] if the address is outside of the ROM (`$8000`-`$FFFF`)
+    pub fn encode(&self) -> Result<String, Error> {
+        if self.address < ROM_START {
+            return Err(Error::InvalidAddress(self.address));
+        }
+
+        let address = self.address;
+        let value = self.value;
+        let mut n = [0_u8; 8];
+
+        // Address and values are split into the same nibbles as in `decode`
+        #[allow(clippy::cast_possible_truncation)]
+        let address_bits = |shift: u16, mask: u16| ((address >> shift) & mask) as u8;
+
+        n[0] = ((value >> 4) & 8) | (value & 7);
+        n[1] = ((value >> 4) & 7) | address_bits(4, 8);
+        n[2] = address_bits(4, 7);
+        n[3] = address_bits(12, 7) | address_bits(0, 8);
+        n[4] = address_bits(8, 8) | address_bits(0, 7);
+        n[5] = address_bits(8, 7);
+
+        let length = if let Some(compare) = self.compare {
+            // The third letter tells the Game Genie that the code is eight letters long
+            n[2] |= 8;
+            n[5] |= compare & 8;
+            n[6] = ((compare >> 4) & 8) | (compare & 7);
+            n[7] = ((compare >> 4) & 7) | (value & 8);
+            8
+        } else {
+            n[5] |= value & 8;
+            6
+        };
+
+        Ok(n[..length]
+            .iter()
+            .map(|nibble| ALPHABET[usize::from(*nibble)])
+            .collect())
+    }
+
+    /// Value the CPU sees when reading from the address with the Game Genie attached
+    #[must_use]
+    pub fn patch(&self, address: u16, value: u8) -> u8 {
+        Cheat::from(*self).patch(address, value)
+    }
+
+    /// Patch the code permanently into the PRG ROM, returning the amount of changed bytes
+    ///
+    /// ROMs of up to 32 KiB are always mapped to the same place, so the address can be translated directly.
+    /// For larger ROMs the code gets applied to every 8 KiB bank containing the compare value at the address.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::AmbiguousPatch`] if the ROM is banked and the code doesn't have a compare value,
+    /// or [`Error::InvalidAddress`] if the address is outside of the ROM
+    pub fn apply_to(&self, ines: &mut Ines<'_>) -> Result<usize, Error> {
+        let address = self
+            .address
+            .checked_sub(ROM_START)
+            .ok_or(Error::InvalidAddress(self.address))?;
+        let address = usize::from(address);
+
+        let prg_rom = ines.prg_rom.to_mut();
+        if prg_rom.is_empty() {
+            return Ok(0);
+        }
+
+        let offsets: Vec<usize> = if prg_rom.len() <= ROM_WINDOW_SIZE {
+            // Smaller ROMs are mirrored in the window
+            let offset = address % prg_rom.len();
+            let matches = self
+                .compare
+                .is_none_or(|compare| prg_rom[offset] == compare);
+
+            matches.then_some(offset).into_iter().collect()
+        } else {
+            let compare = self.compare.ok_or(Error::AmbiguousPatch)?;
+
+            (address % BANK_SIZE..prg_rom.len())
+                .step_by(BANK_SIZE)
+                .filter(|offset| prg_rom[*offset] == compare)
+                .collect()
+        };
+
+        let mut changed = 0;
+        for offset in offsets {
+            if prg_rom[offset] != self.value {
+                prg_rom[offset] = self.value;
+                changed += 1;
+            }
+        }
+
+        Ok(changed)
+    }
+}
+
+impl FromStr for GameGenie {
+    type Err = Error;
+
+    fn from_str(code: &str) -> Result<Self, Error> {
+        Self::decode(code)
+    }
+}
@@ -0,0 +1,44 @@
+#![no_std]
+#![warn(clippy::all, clippy::pedantic)]
+
+//!
+//! Cheat codes for the NES
+//!
+
+extern crate alloc;
+
+use core::fmt;
+
+mod cheat;
+pub mod game_genie;
+pub mod pro_action_rocky;
+
+pub use {cheat::Cheat, game_genie::GameGenie, pro_action_rocky::ProActionRocky};
+
+#[derive(Debug)]
+pub enum Error {
+    /// The code doesn't have a valid length
+    InvalidLength(usize),
+    /// The code contains a character which isn't part of its alphabet
+    InvalidCharacter(char),
+    /// The code doesn't follow the format
+    InvalidFormat,
+    /// The address can't be encoded in the code
+    InvalidAddress(u16),
+    /// The code can't be patched into the ROM because the address could be in several banks
+    AmbiguousPatch,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLength(length) => write!(f, "Codes can't have {length} characters"),
+            Self::InvalidCharacter(character) => write!(f, "Invalid character {character:?}"),
+            Self::InvalidFormat => f.write_str("Invalid code format"),
+            Self::InvalidAddress(address) => write!(f, "Address ${address:04X} can't be encoded"),
+            Self::AmbiguousPatch => {
+                f.write_str("Codes without a compare value can't be patched into banked ROMs")
+            }
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//!
+//! Pro Action Rocky codes
+//!
+//! The codes are 32-bit numbers written in hex whose bits are scrambled with a rolling key
+//!
+
+use {crate::Error, core::str::FromStr};
+
+// Target bit of every decrypted bit, starting with the lowest one
+const BIT_POSITIONS: [u32; 31] = [
+    3, 13, 14, 1, 6, 9, 5, 0, 12, 7, 2, 8, 10, 11, 4, // Address
+    19, 21, 23, 22, 20, 17, 16, 18, // Compare value
+    29, 31, 24, 26, 25, 30, 27, 28, // Value
+];
+
+const INITIAL_KEY: u32 = 0x7E5E_E93A;
+const KEY_XOR: u32 = 0x5C18_4B91;
+
+/// Decoded Pro Action Rocky code
+///
+/// The codes always compare against the original value
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ProActionRocky {
+    pub address: u16,
+    pub value: u8,
+    pub compare: u8,
+}
+
+impl ProActionRocky {
+    /// Decode a code consisting of eight hex digits
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the code doesn't have eight digits or contains other characters
+    pub fn decode(code: &str) -> Result<Self, Error> {
+        let length = code.chars().count();
+        if length != 8 {
+            return Err(Error::InvalidLength(length));
+        }
+        if let Some(character) = code
+            .chars()
+            .find(|character| !character.is_ascii_hexdigit())
+        {
+            return Err(Error::InvalidCharacter(character));
+        }
+
+        let code = u32::from_str_radix(code, 16).map_err(|_| Error::InvalidFormat)?;
+        Ok(Self::from_number(code))
+    }
+
+    /// Decode the numeric form of a code
+    #[must_use]
+    pub fn from_number(code: u32) -> Self {
+        // The lowest bit isn't used
+        let mut code = code >> 1;
+        let mut key = INITIAL_KEY;
+        let mut decoded = 0_u32;
+
+        for position in BIT_POSITIONS.iter().rev() {
+            if ((key ^ code) >> 30) & 1 != 0 {
+                decoded |= 1 << position;
+                key ^= KEY_XOR;
+            }
+
+            code <<= 1;
+            key <<= 1;
+        }
+
+        let [value, compare, address_high, address_low] = decoded.to_be_bytes();
+        Self {
+            address: u16::from_be_bytes([address_high, address_low]) | 0x8000,
+            value,
+            compare,
+        }
+    }
+}
+
+impl FromStr for ProActionRocky {
+    type Err = Error;
+
+    fn from_str(code: &str) -> Result<Self, Error> {
+        Self::decode(code)
+    }
+}
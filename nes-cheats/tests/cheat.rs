@@ -0,0 +1,101 @@
+use nes_cheats::{Cheat, Error, GameGenie, ProActionRocky};
+
+#[test]
+fn raw_cheats() {
+    assert_eq!(
+        Cheat::parse_raw("075A:09").unwrap(),
+        Cheat {
+            address: 0x075A,
+            value: 0x09,
+            compare: None,
+        }
+    );
+    assert_eq!(
+        Cheat::parse_raw("8B03?01:00").unwrap(),
+        Cheat {
+            address: 0x8B03,
+            value: 0x00,
+            compare: Some(0x01),
+        }
+    );
+
+    for code in [
+        "075A", "075A:", "10000:09", "075A:100", "075A?:09", "07G5:09",
+    ] {
+        assert!(
+            matches!(Cheat::parse_raw(code), Err(Error::InvalidFormat)),
+            "{}",
+            code
+        );
+    }
+}
+
+#[test]
+fn raw_format() {
+    for code in ["075A:09", "8B03?01:00"] {
+        assert_eq!(Cheat::parse_raw(code).unwrap().to_string(), code);
+    }
+}
+
+// Checked against the decoder of Mesen
+#[test]
+fn pro_action_rocky() {
+    assert_eq!(
+        ProActionRocky::decode("394E7E50").unwrap(),
+        ProActionRocky {
+            address: 0xCAB0,
+            value: 0x7D,
+            compare: 0x0D,
+        }
+    );
+    assert_eq!(
+        ProActionRocky::from_number(0x1234_5678),
+        ProActionRocky {
+            address: 0xAD85,
+            value: 0x3C,
+            compare: 0xC1,
+        }
+    );
+
+    assert!(matches!(
+        ProActionRocky::decode("394E7E5"),
+        Err(Error::InvalidLength(7))
+    ));
+    assert!(matches!(
+        ProActionRocky::decode("394E7E5X"),
+        Err(Error::InvalidCharacter('X'))
+    ));
+}
+
+#[test]
+fn parse_any_format() {
+    let parse = |code: &str| code.parse::<Cheat>().unwrap();
+
+    assert_eq!(parse("075A:09"), Cheat::parse_raw("075A:09").unwrap());
+    assert_eq!(
+        parse(" SXIOPO "),
+        Cheat::from(GameGenie::decode("SXIOPO").unwrap())
+    );
+    assert_eq!(
+        parse("AAEAULPA"),
+        Cheat::from(GameGenie::decode("AAEAULPA").unwrap())
+    );
+    // Eight hex digits aren't a valid Game Genie code
+    assert_eq!(
+        parse("394E7E50"),
+        Cheat {
+            address: 0xCAB0,
+            value: 0x7D,
+            compare: Some(0x0D),
+        }
+    );
+}
+
+#[test]
+fn patch() {
+    let cheat = Cheat::parse_raw("8B03?01:00").unwrap();
+
+    assert_eq!(cheat.patch(0x8B03, 0x01), 0x00);
+    assert_eq!(cheat.patch(0x8B03, 0x02), 0x02);
+    assert_eq!(cheat.patch(0x8B04, 0x01), 0x01);
+}
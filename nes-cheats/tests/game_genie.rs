@@ -0,0 +1,108 @@
+use {
+    ines_parser::Ines,
+    nes_cheats::{Error, GameGenie},
+};
+
+// iNES ROM with the PRG ROM filled with zeroes
+fn rom(prg_rom_banks: u8) -> Vec<u8> {
+    let mut rom = b"NES\x1A".to_vec();
+    rom.extend_from_slice(&[prg_rom_banks, 0]);
+    rom.resize(16 + usize::from(prg_rom_banks) * 0x4000, 0);
+    rom
+}
+
+#[test]
+fn decode() {
+    assert_eq!(
+        GameGenie::decode("SXIOPO").unwrap(),
+        GameGenie {
+            address: 0x91D9,
+            value: 0xAD,
+            compare: None,
+        }
+    );
+    assert_eq!(
+        GameGenie::decode("AAEAULPA").unwrap(),
+        GameGenie {
+            address: 0x8B03,
+            value: 0x00,
+            compare: Some(0x01),
+        }
+    );
+
+    // Letters are case-insensitive
+    assert_eq!(
+        GameGenie::decode("sxiopo").unwrap(),
+        GameGenie::decode("SXIOPO").unwrap()
+    );
+}
+
+#[test]
+fn encode() {
+    for code in ["SXIOPO", "AAEAULPA"] {
+        assert_eq!(GameGenie::decode(code).unwrap().encode().unwrap(), code);
+    }
+
+    let code = GameGenie {
+        address: 0x6000,
+        value: 0,
+        compare: None,
+    };
+    assert!(matches!(code.encode(), Err(Error::InvalidAddress(0x6000))));
+}
+
+#[test]
+fn reject_invalid_codes() {
+    assert!(matches!(
+        GameGenie::decode("SXIOP"),
+        Err(Error::InvalidLength(5))
+    ));
+    assert!(matches!(
+        GameGenie::decode("SXIOPB"),
+        Err(Error::InvalidCharacter('B'))
+    ));
+}
+
+#[test]
+fn patch() {
+    let code = GameGenie::decode("AAEAULPA").unwrap();
+
+    // Only reads of the compare value get replaced
+    assert_eq!(code.patch(0x8B03, 0x01), 0x00);
+    assert_eq!(code.patch(0x8B03, 0x02), 0x02);
+    assert_eq!(code.patch(0x8B04, 0x01), 0x01);
+}
+
+#[test]
+fn apply_to_unbanked_rom() {
+    let rom = rom(1);
+    let mut ines = Ines::from_bytes(&rom).unwrap();
+
+    // 16 KiB of PRG ROM are mirrored at $C000
+    let code = GameGenie::decode("SXIOPO").unwrap();
+    assert_eq!(code.apply_to(&mut ines).unwrap(), 1);
+    assert_eq!(ines.prg_rom[0x11D9], 0xAD);
+}
+
+#[test]
+fn apply_to_banked_rom() {
+    let rom = rom(4);
+    let mut ines = Ines::from_bytes(&rom).unwrap();
+
+    let prg_rom = ines.prg_rom.to_mut();
+    prg_rom[0x0B03] = 0x01;
+    prg_rom[0x4B03] = 0x01;
+    prg_rom[0x8B03] = 0x02;
+
+    // Only the banks with the compare value at the address get patched
+    let code = GameGenie::decode("AAEAULPA").unwrap();
+    assert_eq!(code.apply_to(&mut ines).unwrap(), 2);
+    assert_eq!(ines.prg_rom[0x0B03], 0x00);
+    assert_eq!(ines.prg_rom[0x4B03], 0x00);
+    assert_eq!(ines.prg_rom[0x8B03], 0x02);
+
+    assert!(matches!(
+        GameGenie::decode("SXIOPO").unwrap().apply_to(&mut ines),
+        Err(Error::AmbiguousPatch)
+    ));
+}
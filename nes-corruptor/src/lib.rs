@@ -0,0 +1,253 @@
+#![no_std]
+#![warn(clippy::all, clippy::pedantic)]
+
+//!
+//! Controlled corruption of NES ROMs
+//!
+
+extern crate alloc;
+
+use {
+    alloc::{vec, vec::Vec},
+    core::{
+        fmt,
+        ops::{Range, RangeInclusive},
+    },
+    ines_parser::Ines,
+};
+
+mod rng;
+
+pub use rng::Rng;
+
+const HEADER_SIZE: usize = 16;
+const TRAINER_SIZE: usize = 512;
+
+#[derive(Debug)]
+pub enum Error {
+    Ines(ines_parser::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ines(err) => write!(f, "INES error: {err:?}"),
+        }
+    }
+}
+
+impl From<ines_parser::Error> for Error {
+    fn from(err: ines_parser::Error) -> Self {
+        Self::Ines(err)
+    }
+}
+
+/// ROM of the cartridge
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Area {
+    PrgRom,
+    ChrRom,
+}
+
+/// Range of a ROM which may be corrupted
+///
+/// Ranges past the end of the ROM get clamped to it
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Region {
+    pub area: Area,
+    /// Offsets into the ROM, not into the file
+    pub range: Range<usize>,
+}
+
+impl Region {
+    /// The whole ROM
+    #[must_use]
+    pub fn whole(area: Area) -> Self {
+        Self {
+            area,
+            range: 0..usize::MAX,
+        }
+    }
+}
+
+/// How a selected byte gets corrupted
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Strategy {
+    /// Flip one random bit
+    BitFlip,
+    /// Replace the byte with the one the distance away, as long as that one is in the same region
+    Shift(isize),
+    /// Add the amount, wrapping around
+    Add(u8),
+    /// Replace the byte with a random value out of the range
+    Random(RangeInclusive<u8>),
+    /// Replace every occurrence of one value with another
+    Replace { from: u8, to: u8 },
+}
+
+/// Which bytes of the regions get corrupted
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Selection {
+    /// This amount of randomly chosen bytes, spread over all regions
+    Random(usize),
+    /// Every nth byte of every region, starting with the first one
+    Every(usize),
+}
+
+/// Byte changed by the corruptor
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Change {
+    pub area: Area,
+    /// Offset into the ROM
+    pub offset: usize,
+    pub old: u8,
+    pub new: u8,
+}
+
+/// Region resolved to offsets into the file
+struct FileRegion {
+    area: Area,
+    /// Offset of the ROM in the file
+    base: usize,
+    range: Range<usize>,
+}
+
+// Find the region and offset of an index into all regions laid out after each other
+fn locate(regions: &[FileRegion], mut index: usize) -> Option<(&FileRegion, usize)> {
+    regions.iter().find_map(|region| {
+        if index < region.range.len() {
+            Some((region, region.range.start + index))
+        } else {
+            index -= region.range.len();
+            None
+        }
+    })
+}
+
+/// Configured corruption of ROMs
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Corruptor {
+    pub seed: u64,
+    pub regions: Vec<Region>,
+    pub strategy: Strategy,
+    pub selection: Selection,
+}
+
+impl Corruptor {
+    /// Corruptor for the whole PRG ROM
+    #[must_use]
+    pub fn new(seed: u64, strategy: Strategy, selection: Selection) -> Self {
+        Self {
+            seed,
+            regions: vec![Region::whole(Area::PrgRom)],
+            strategy,
+            selection,
+        }
+    }
+
+    /// Corrupt the ROMs of an INES file, returning the changed bytes
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file isn't a valid INES ROM
+    pub fn corrupt(&self, file: &mut [u8]) -> Result<Vec<Change>, Error> {
+        let regions = self.file_regions(file)?;
+        let mut rng = Rng::new(self.seed);
+
+        let selected: Vec<(&FileRegion, usize)> = match self.selection {
+            Selection::Random(count) => {
+                let total = regions.iter().map(|region| region.range.len()).sum();
+                if total == 0 {
+                    Vec::new()
+                } else {
+                    (0..count)
+                        .filter_map(|_| locate(&regions, rng.below(total)))
+                        .collect()
+                }
+            }
+            Selection::Every(step) => regions
+                .iter()
+                .flat_map(|region| {
+                    region
+                        .range
+                        .clone()
+                        .step_by(step.max(1))
+                        .map(move |offset| (region, offset))
+                })
+                .collect(),
+        };
+
+        // Shifted bytes come from the uncorrupted ROM, so changes don't cascade
+        let original = file.to_vec();
+        let mut changes = Vec::new();
+        for (region, offset) in selected {
+            let position = region.base + offset;
+            let old = file[position];
+
+            let new = match &self.strategy {
+                Strategy::BitFlip => old ^ (1 << rng.below(8)),
+                Strategy::Shift(distance) => offset
+                    .checked_add_signed(*distance)
+                    .filter(|source| region.range.contains(source))
+                    .map_or(old, |source| original[region.base + source]),
+                Strategy::Add(amount) => old.wrapping_add(*amount),
+                Strategy::Random(range) if range.start() <= range.end() => {
+                    let span = usize::from(range.end() - range.start()) + 1;
+                    // The span has at most 256 values
+                    #[allow(clippy::cast_possible_truncation)]
+                    let value = rng.below(span) as u8;
+                    range.start() + value
+                }
+                Strategy::Random(_) => old,
+                Strategy::Replace { from, to } => {
+                    if old == *from {
+                        *to
+                    } else {
+                        old
+                    }
+                }
+            };
+
+            if new != old {
+                file[position] = new;
+                changes.push(Change {
+                    area: region.area,
+                    offset,
+                    old,
+                    new,
+                });
+            }
+        }
+
+        Ok(changes)
+    }
+
+    fn file_regions(&self, file: &[u8]) -> Result<Vec<FileRegion>, Error> {
+        let ines = Ines::from_bytes(file)?;
+        let header = &ines.header;
+
+        let prg_rom_start = if ines.trainer.is_some() {
+            HEADER_SIZE + TRAINER_SIZE
+        } else {
+            HEADER_SIZE
+        };
+        let chr_rom_start = prg_rom_start + header.prg_rom_size;
+
+        Ok(self
+            .regions
+            .iter()
+            .map(|region| {
+                let (base, size) = match region.area {
+                    Area::PrgRom => (prg_rom_start, header.prg_rom_size),
+                    Area::ChrRom => (chr_rom_start, header.chr_rom_size),
+                };
+
+                FileRegion {
+                    area: region.area,
+                    base,
+                    range: region.range.start.min(size)..region.range.end.min(size),
+                }
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,31 @@
+use {
+    ines_parser::Ines,
+    nes_emulator::{Harness, InputScript},
+    std::{env, fs, process},
+};
+
+const SAMPLE_RATE: u32 = 44_100;
+
+// Most tests finish within a minute
+const MAX_FRAMES: u64 = 60 * 60;
+
+// Usage: blargg <rom>...
+// Runs each of blargg's test ROMs and prints its result, exiting with 1 if any of them didn't pass
+fn main() {
+    let mut all_passed = true;
+
+    for path in env::args().skip(1) {
+        let rom = fs::read(&path).unwrap();
+        let ines = Ines::from_bytes(&rom).unwrap();
+
+        let mut harness = Harness::from_ines(&ines, SAMPLE_RATE, InputScript::new()).unwrap();
+        let result = harness.run_blargg_test(MAX_FRAMES).unwrap();
+
+        println!("{path}: {result}");
+        all_passed &= result.passed();
+    }
+
+    if !all_passed {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,37 @@
+use {
+    ines_parser::Ines,
+    nes_emulator::{Buttons, Harness, InputScript},
+    std::{env, fs},
+};
+
+const SAMPLE_RATE: u32 = 44_100;
+
+// Usage: headless <rom> <frames> <output>
+// Presses start once after a second and writes the last frame as a PPM image
+fn main() {
+    let mut args = env::args().skip(1);
+    let rom = fs::read(args.next().unwrap()).unwrap();
+    let frames: u64 = args.next().map_or(600, |frames| frames.parse().unwrap());
+    let output = args.next().unwrap_or_else(|| "frame.ppm".into());
+
+    let ines = Ines::from_bytes(&rom).unwrap();
+
+    let mut script = InputScript::new();
+    script.press(60, 5, 0, Buttons::START);
+
+    let mut harness = Harness::from_ines(&ines, SAMPLE_RATE, script).unwrap();
+    harness.run_frames(frames).unwrap();
+
+    let snapshot = harness.snapshot();
+    let audio = harness.take_audio();
+    println!(
+        "Frame {}, {} CPU cycles, {} audio samples",
+        snapshot.frame,
+        snapshot.cycles,
+        audio.len()
+    );
+
+    let mut image = b"P6 256 240 255\n".to_vec();
+    image.extend(harness.nes().ppu().to_rgb());
+    fs::write(output, image).unwrap();
+}
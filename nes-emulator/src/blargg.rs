@@ -0,0 +1,143 @@
+use {
+    crate::{Error, Harness, Nes},
+    alloc::string::String,
+    core::fmt,
+};
+
+/// Address of the status byte of blargg's test ROMs
+pub const BLARGG_STATUS_ADDRESS: u16 = 0x6000;
+
+// Written to `$6001`-`$6003` once the status and the text are valid
+const SIGNATURE_ADDRESS: u16 = 0x6001;
+const SIGNATURE: [u8; 3] = [0xDE, 0xB0, 0x61];
+
+// Zero-terminated text the test prints to the screen
+const TEXT_ADDRESS: u16 = 0x6004;
+const TEXT_END: u16 = 0x7FFF;
+
+const STATUS_RUNNING: u8 = 0x80;
+const STATUS_RESET: u8 = 0x81;
+
+// Tests requesting a reset want it at least 100 ms later
+const RESET_DELAY_FRAMES: u64 = 6;
+
+/// Result of a test ROM
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BlarggStatus {
+    Passed,
+    /// Result code of the failed test; most tests number their subtests starting at 2
+    Failed(u8),
+    /// The test was still running when the frame limit was reached
+    Timeout,
+    /// The ROM never wrote the signature to `$6001`, so it doesn't report its results in memory
+    NoSignature,
+}
+
+/// Outcome of a run of one of blargg's test ROMs
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlarggResult {
+    pub status: BlarggStatus,
+    /// Text the test printed, usually the name of the test and the reason it failed
+    pub text: String,
+    /// Frames the test ran for
+    pub frames: u64,
+}
+
+impl BlarggResult {
+    #[must_use]
+    pub fn passed(&self) -> bool {
+        self.status == BlarggStatus::Passed
+    }
+}
+
+impl fmt::Display for BlarggResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.status {
+            BlarggStatus::Passed => write!(f, "Passed after {} frames", self.frames)?,
+            BlarggStatus::Failed(code) => {
+                write!(f, "Failed with code {code} after {} frames", self.frames)?;
+            }
+            BlarggStatus::Timeout => write!(f, "Still running after {} frames", self.frames)?,
+            BlarggStatus::NoSignature => {
+                write!(f, "No results in memory after {} frames", self.frames)?;
+            }
+        }
+
+        let text = self.text.trim_end();
+        if text.is_empty() {
+            Ok(())
+        } else {
+            write!(f, ":\n{text}")
+        }
+    }
+}
+
+fn read(nes: &Nes, address: u16) -> u8 {
+    nes.cartridge().mapper().memory().read_prg_ram(address)
+}
+
+fn has_signature(nes: &Nes) -> bool {
+    (0..)
+        .zip(SIGNATURE)
+        .all(|(offset, byte)| read(nes, SIGNATURE_ADDRESS + offset) == byte)
+}
+
+fn read_text(nes: &Nes) -> String {
+    (TEXT_ADDRESS..=TEXT_END)
+        .map(|address| read(nes, address))
+        .take_while(|byte| *byte != 0)
+        .map(char::from)
+        .collect()
+}
+
+impl Harness {
+    /// Run one of blargg's test ROMs until it reports its result, for at most the amount of frames
+    ///
+    /// The tests report through the cartridge RAM: `$6001`-`$6003` hold `DE B0 61` once the rest is valid,
+    /// `$6000` is `$80` while the test is running, `$81` when it wants the console to be reset
+    /// (which is done after 100 ms) and the result code afterwards, 0 meaning success.
+    /// The text the test printed is at `$6004`, terminated by a null byte.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the CPU encounters an unknown opcode
+    pub fn run_blargg_test(&mut self, max_frames: u64) -> Result<BlarggResult, Error> {
+        let start = self.nes().ppu().frame();
+        let mut reset_frame = None;
+
+        let status = loop {
+            let frame = self.nes().ppu().frame();
+            if frame - start >= max_frames {
+                break if has_signature(self.nes()) {
+                    BlarggStatus::Timeout
+                } else {
+                    BlarggStatus::NoSignature
+                };
+            }
+
+            self.run_frames(1)?;
+            if !has_signature(self.nes()) {
+                continue;
+            }
+
+            match read(self.nes(), BLARGG_STATUS_ADDRESS) {
+                STATUS_RUNNING => {}
+                STATUS_RESET => {
+                    let requested = *reset_frame.get_or_insert(frame);
+                    if frame - requested >= RESET_DELAY_FRAMES {
+                        self.nes_mut().reset();
+                        reset_frame = None;
+                    }
+                }
+                0 => break BlarggStatus::Passed,
+                code => break BlarggStatus::Failed(code),
+            }
+        };
+
+        Ok(BlarggResult {
+            status,
+            text: read_text(self.nes()),
+            frames: self.nes().ppu().frame() - start,
+        })
+    }
+}
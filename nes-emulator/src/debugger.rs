@@ -0,0 +1,509 @@
+use {
+    crate::{Error, Nes},
+    alloc::{
+        collections::BTreeMap,
+        string::{String, ToString},
+        vec::Vec,
+    },
+    core::{convert::TryFrom, fmt, ops::RangeInclusive},
+    mos6502_cpu::{Break, Debugger as CpuDebugger, Status},
+    mos6502_dasm::{Instruction, MemoryType, MlbLabel, Mnemonic, NlEntry, NlFile},
+    nes_ppu::PpuRegisters,
+};
+
+// Longest instruction, which limits how far back the disassembly has to look for the previous instruction
+const MAX_INSTRUCTION_LENGTH: u16 = 3;
+
+// Size of the PRG ROM banks the FCEUX name list files are split into
+const NL_BANK_SIZE: usize = 0x4000;
+
+// Start of the cartridge RAM in the CPU address space
+const WORK_RAM_ADDRESS: u16 = 0x6000;
+
+const RTS: u8 = 0x60;
+const RTI: u8 = 0x40;
+
+/// Names of addresses, e.g. from the label files of other debuggers
+///
+/// Names of the PRG ROM are stored by their offset into the PRG ROM, so they follow the banks of the mapper.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Symbols {
+    /// Names of the RAMs and registers by their address
+    addresses: BTreeMap<u16, String>,
+    /// Names of the PRG ROM by their offset
+    prg_rom: BTreeMap<usize, String>,
+}
+
+impl Symbols {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the names of the labels of a Mesen label file (`.mlb`)
+    pub fn add_mlb(&mut self, labels: &[MlbLabel]) {
+        for label in labels.iter().filter(|label| !label.name.is_empty()) {
+            let name = label.name.clone();
+            match label.memory_type {
+                MemoryType::PrgRom => {
+                    if let Ok(offset) = usize::try_from(label.address) {
+                        self.prg_rom.insert(offset, name);
+                    }
+                }
+                MemoryType::InternalRam | MemoryType::Register => {
+                    if let Ok(address) = u16::try_from(label.address) {
+                        self.addresses.insert(address, name);
+                    }
+                }
+                MemoryType::SaveRam | MemoryType::WorkRam => {
+                    let address = u16::try_from(label.address)
+                        .ok()
+                        .and_then(|offset| WORK_RAM_ADDRESS.checked_add(offset));
+                    if let Some(address) = address {
+                        self.addresses.insert(address, name);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Add the names of the entries of an FCEUX name list file (`.nl`)
+    pub fn add_nl(&mut self, file: NlFile, entries: &[NlEntry]) {
+        for entry in entries.iter().filter(|entry| !entry.name.is_empty()) {
+            let name = entry.name.clone();
+            match file {
+                NlFile::Ram => {
+                    self.addresses.insert(entry.address, name);
+                }
+                NlFile::Bank(bank) => {
+                    let offset = bank * NL_BANK_SIZE + usize::from(entry.address) % NL_BANK_SIZE;
+                    self.prg_rom.insert(offset, name);
+                }
+            }
+        }
+    }
+
+    /// Name an address outside of the PRG ROM
+    pub fn set_name(&mut self, address: u16, name: &str) {
+        self.addresses.insert(address, name.to_string());
+    }
+
+    /// Name a byte of the PRG ROM
+    pub fn set_prg_rom_name(&mut self, offset: usize, name: &str) {
+        self.prg_rom.insert(offset, name.to_string());
+    }
+
+    /// Name of the address, given the offset into the PRG ROM it's mapped to (see [`Nes::prg_rom_offset`])
+    #[must_use]
+    pub fn name(&self, address: u16, prg_rom_offset: Option<usize>) -> Option<&str> {
+        let name = match prg_rom_offset {
+            Some(offset) => self.prg_rom.get(&offset),
+            None => self.addresses.get(&address),
+        };
+
+        name.map(String::as_str)
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.addresses.is_empty() && self.prg_rom.is_empty()
+    }
+}
+
+/// Registers of the CPU and the PPU
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Registers {
+    pub pc: u16,
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub s: u8,
+    pub p: Status,
+    /// CPU cycles since power-up
+    pub cycles: u64,
+    pub ppu: PpuRegisters,
+    pub frame: u64,
+    pub scanline: u16,
+    pub dot: u16,
+}
+
+impl fmt::Display for Registers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "PC:{:04X} A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} CYC:{}",
+            self.pc, self.a, self.x, self.y, self.p.0, self.s, self.cycles
+        )?;
+        write!(
+            f,
+            "PPU:{:3},{:3} FRAME:{} CTRL:{:02X} MASK:{:02X} STATUS:{:02X} V:{:04X} T:{:04X} X:{} W:{}",
+            self.scanline,
+            self.dot,
+            self.frame,
+            self.ppu.ctrl,
+            self.ppu.mask,
+            self.ppu.status,
+            self.ppu.v,
+            self.ppu.t,
+            self.ppu.fine_x,
+            u8::from(self.ppu.write_toggle)
+        )
+    }
+}
+
+/// Instruction of a disassembly
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Line {
+    pub address: u16,
+    /// Bytes of the instruction
+    pub bytes: Vec<u8>,
+    pub instruction: Instruction,
+    /// Name of the address of the instruction
+    pub label: Option<String>,
+    /// Name of the address the operand refers to
+    pub operand_label: Option<String>,
+    /// Whether the program counter points at the instruction
+    pub is_current: bool,
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(label) = &self.label {
+            writeln!(f, "{label}:")?;
+        }
+
+        let marker = if self.is_current { '>' } else { ' ' };
+        let bytes = self
+            .bytes
+            .iter()
+            .map(|byte| alloc::format!("{byte:02X}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        write!(
+            f,
+            "{marker} {:04X}  {bytes:<8}  {}",
+            self.address, self.instruction
+        )?;
+
+        if let Some(label) = &self.operand_label {
+            write!(f, "  ; {label}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Reason a run of the [`Debugger`] ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Stop {
+    /// The run reached its goal, like the instruction after a stepped over subroutine call
+    Done,
+    /// A breakpoint, watchpoint or condition stopped the execution first
+    Break(Break),
+    /// The cycle limit ran out first
+    Timeout,
+}
+
+impl fmt::Display for Stop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Done => f.write_str("Done"),
+            Self::Break(stop) => write!(f, "{stop}"),
+            Self::Timeout => f.write_str("Cycle limit reached"),
+        }
+    }
+}
+
+/// Debugger for a whole console, the model layer of debugger frontends
+///
+/// Execution is controlled through a [`mos6502_cpu::Debugger`], so its breakpoints, watchpoints and conditions
+/// stop every kind of run. Memory is read without side effects, and disassemblies use the names of the [`Symbols`].
+pub struct Debugger {
+    nes: Nes,
+    control: CpuDebugger,
+    symbols: Symbols,
+}
+
+impl Debugger {
+    #[must_use]
+    pub fn new(nes: Nes) -> Self {
+        Self {
+            nes,
+            control: CpuDebugger::new(),
+            symbols: Symbols::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn nes(&self) -> &Nes {
+        &self.nes
+    }
+
+    pub fn nes_mut(&mut self) -> &mut Nes {
+        &mut self.nes
+    }
+
+    #[must_use]
+    pub fn into_nes(self) -> Nes {
+        self.nes
+    }
+
+    /// Breakpoints, watchpoints and conditions
+    #[must_use]
+    pub fn control(&self) -> &CpuDebugger {
+        &self.control
+    }
+
+    pub fn control_mut(&mut self) -> &mut CpuDebugger {
+        &mut self.control
+    }
+
+    #[must_use]
+    pub fn symbols(&self) -> &Symbols {
+        &self.symbols
+    }
+
+    pub fn symbols_mut(&mut self) -> &mut Symbols {
+        &mut self.symbols
+    }
+
+    #[must_use]
+    pub fn registers(&self) -> Registers {
+        let cpu = self.nes.cpu();
+        let ppu = self.nes.ppu();
+
+        Registers {
+            pc: cpu.pc,
+            a: cpu.a,
+            x: cpu.x,
+            y: cpu.y,
+            s: cpu.s,
+            p: cpu.p,
+            cycles: cpu.cycles(),
+            ppu: ppu.registers(),
+            frame: ppu.frame(),
+            scanline: ppu.scanline(),
+            dot: ppu.dot(),
+        }
+    }
+
+    /// Read memory without side effects (see [`Nes::peek`])
+    #[must_use]
+    pub fn read(&self, address: u16) -> u8 {
+        self.nes.peek(address)
+    }
+
+    /// Read a range of memory without side effects
+    #[must_use]
+    pub fn read_range(&self, addresses: RangeInclusive<u16>) -> Vec<u8> {
+        addresses.map(|address| self.nes.peek(address)).collect()
+    }
+
+    /// Write memory like the CPU would (see [`Nes::poke`])
+    pub fn write(&mut self, address: u16, value: u8) {
+        self.nes.poke(address, value);
+    }
+
+    /// Name of the address in the current bank layout
+    #[must_use]
+    pub fn symbol(&self, address: u16) -> Option<&str> {
+        self.symbols.name(address, self.nes.prg_rom_offset(address))
+    }
+
+    /// Execute the next instruction (or interrupt)
+    ///
+    /// Returns [`Stop::Break`] without executing anything if the program counter is at a breakpoint;
+    /// stepping again executes the instruction there.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the CPU encounters an unknown opcode
+    pub fn step(&mut self) -> Result<Stop, Error> {
+        let stop = self.nes.debug_step(&mut self.control)?.stop;
+        Ok(stop.map_or(Stop::Done, Stop::Break))
+    }
+
+    /// Execute the next instruction, running a called subroutine until it returns
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the CPU encounters an unknown opcode
+    pub fn step_over(&mut self, max_cycles: u64) -> Result<Stop, Error> {
+        let cpu = self.nes.cpu();
+        let (pc, s) = (cpu.pc, cpu.s);
+        if self.decode(pc).mnemonic() != Some(Mnemonic::Jsr) {
+            return self.step();
+        }
+
+        // The stack pointer tells the return from a recursive call of the same subroutine apart
+        let return_address = pc.wrapping_add(3);
+        self.run_until(max_cycles, |nes, _| {
+            nes.cpu().pc == return_address && nes.cpu().s == s
+        })
+    }
+
+    /// Run until the current subroutine or interrupt handler returns
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the CPU encounters an unknown opcode
+    pub fn step_out(&mut self, max_cycles: u64) -> Result<Stop, Error> {
+        let s = self.nes.cpu().s;
+        // Returns of nested calls leave the stack pointer at or below the one of the current frame
+        self.run_until(max_cycles, |nes, opcode| {
+            matches!(opcode, RTS | RTI) && nes.cpu().s > s
+        })
+    }
+
+    /// Run until the program counter reaches the address
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the CPU encounters an unknown opcode
+    pub fn run_to(&mut self, address: u16, max_cycles: u64) -> Result<Stop, Error> {
+        self.run_until(max_cycles, |nes, _| nes.cpu().pc == address)
+    }
+
+    /// Run until the PPU finished the current frame
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the CPU encounters an unknown opcode
+    pub fn run_frame(&mut self) -> Result<Stop, Error> {
+        let frame = self.nes.ppu().frame();
+        self.run_until(u64::MAX, |nes, _| nes.ppu().frame() != frame)
+    }
+
+    /// Run until a breakpoint, watchpoint or condition stops the execution, for at most the amount of CPU cycles
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the CPU encounters an unknown opcode
+    pub fn run(&mut self, max_cycles: u64) -> Result<Stop, Error> {
+        self.run_until(max_cycles, |_, _| false)
+    }
+
+    // Step until the goal is met after an instruction, given the opcode at the program counter before it
+    fn run_until<F>(&mut self, max_cycles: u64, mut goal: F) -> Result<Stop, Error>
+    where
+        F: FnMut(&Nes, u8) -> bool,
+    {
+        let target = self.nes.cpu().cycles().saturating_add(max_cycles);
+        while self.nes.cpu().cycles() < target {
+            let opcode = self.nes.peek(self.nes.cpu().pc);
+            if let Some(stop) = self.nes.debug_step(&mut self.control)?.stop {
+                return Ok(Stop::Break(stop));
+            }
+
+            if goal(&self.nes, opcode) {
+                return Ok(Stop::Done);
+            }
+        }
+
+        Ok(Stop::Timeout)
+    }
+
+    /// Disassemble the instructions around the program counter, with up to `before` instructions before it
+    ///
+    /// The instructions before it are found by disassembling from an earlier address which lines up
+    /// with the program counter, which is ambiguous if there's data in between.
+    #[must_use]
+    pub fn disassemble(&self, before: usize, after: usize) -> Vec<Line> {
+        let pc = self.nes.cpu().pc;
+        let start = (0..=before)
+            .rev()
+            .find_map(|count| self.start_before(pc, count))
+            .unwrap_or(pc);
+
+        self.disassemble_at(start, before + 1 + after)
+    }
+
+    /// Disassemble the amount of instructions starting at the address
+    #[must_use]
+    pub fn disassemble_at(&self, address: u16, count: usize) -> Vec<Line> {
+        let mut lines = Vec::with_capacity(count);
+        let mut address = address;
+        for _ in 0..count {
+            let line = self.line(address);
+            let (next, overflow) = address.overflowing_add(instruction_length(line.instruction));
+            lines.push(line);
+            if overflow {
+                break;
+            }
+            address = next;
+        }
+
+        lines
+    }
+
+    // Address of the instruction `count` instructions before the address, with the instructions lining up with it
+    fn start_before(&self, address: u16, count: usize) -> Option<u16> {
+        if count == 0 {
+            return Some(address);
+        }
+
+        let max_distance = u16::try_from(count)
+            .ok()?
+            .checked_mul(MAX_INSTRUCTION_LENGTH)?;
+        // The furthest start lining up covers the most context
+        (1..=max_distance).rev().find_map(|distance| {
+            let start = address.checked_sub(distance)?;
+            let mut starts = Vec::new();
+            let mut current = start;
+            while current < address {
+                starts.push(current);
+                current = current.checked_add(instruction_length(self.decode(current)))?;
+            }
+
+            (current == address && starts.len() >= count).then(|| starts[starts.len() - count])
+        })
+    }
+
+    fn decode(&self, address: u16) -> Instruction {
+        let bytes = [
+            self.nes.peek(address),
+            self.nes.peek(address.wrapping_add(1)),
+            self.nes.peek(address.wrapping_add(2)),
+        ];
+        // The slice is never empty
+        Instruction::decode(&bytes, address).unwrap_or(Instruction::Unknown(bytes[0]))
+    }
+
+    fn line(&self, address: u16) -> Line {
+        let instruction = self.decode(address);
+        let bytes = (0..instruction_length(instruction))
+            .map(|offset| self.nes.peek(address.wrapping_add(offset)))
+            .collect();
+        let operand_label = instruction
+            .address_operand()
+            .and_then(|operand| self.symbol(operand))
+            .map(ToString::to_string);
+
+        Line {
+            address,
+            bytes,
+            instruction,
+            label: self.symbol(address).map(ToString::to_string),
+            operand_label,
+            is_current: address == self.nes.cpu().pc,
+        }
+    }
+}
+
+impl fmt::Debug for Debugger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Debugger")
+            .field("registers", &self.registers())
+            .field("control", &self.control)
+            .field("symbols", &self.symbols)
+            .finish_non_exhaustive()
+    }
+}
+
+// Length of the instruction as an offset between addresses
+fn instruction_length(instruction: Instruction) -> u16 {
+    // Instructions are at most three bytes long
+    #[allow(clippy::cast_possible_truncation)]
+    let length = instruction.len() as u16;
+    length
+}
@@ -0,0 +1,226 @@
+#[cfg(feature = "movie")]
+use {crate::Recorder, nes_movie::Fm2};
+use {
+    crate::{Buttons, Error, Nes},
+    alloc::{collections::BTreeMap, vec::Vec},
+    ines_parser::Ines,
+};
+
+/// Controller input over the course of a run
+///
+/// Every change is keyed by the frame it happens on; the buttons stay held until the next change of the port
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InputScript {
+    changes: [BTreeMap<u64, Buttons>; 2],
+}
+
+impl InputScript {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hold the buttons on the controller in the port (0 or 1) starting with the frame
+    pub fn set(&mut self, frame: u64, port: usize, buttons: Buttons) {
+        self.changes[port].insert(frame, buttons);
+    }
+
+    /// Hold the buttons for the amount of frames and go back to the previous buttons afterwards
+    pub fn press(&mut self, frame: u64, frames: u64, port: usize, buttons: Buttons) {
+        let released = self.buttons_at(frame + frames)[port];
+        self.set(frame, port, buttons);
+        self.set(frame + frames, port, released);
+    }
+
+    /// Buttons held on both controllers during the frame
+    #[must_use]
+    pub fn buttons_at(&self, frame: u64) -> [Buttons; 2] {
+        let port = |changes: &BTreeMap<u64, Buttons>| {
+            changes
+                .range(..=frame)
+                .next_back()
+                .map_or(Buttons::NONE, |(_, buttons)| *buttons)
+        };
+
+        [port(&self.changes[0]), port(&self.changes[1])]
+    }
+}
+
+/// State of the console at one point of a run
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Frames rendered since power-up
+    pub frame: u64,
+    /// CPU cycles executed since power-up
+    pub cycles: u64,
+    pub ram: Vec<u8>,
+    pub prg_ram: Vec<u8>,
+    /// Indices into the master palette
+    pub framebuffer: Vec<u8>,
+}
+
+/// Runs a ROM without any frontend, feeding it scripted input
+pub struct Harness {
+    nes: Nes,
+    script: InputScript,
+    audio: Vec<f32>,
+    #[cfg(feature = "movie")]
+    recorder: Option<Recorder>,
+}
+
+impl Harness {
+    #[must_use]
+    pub fn new(nes: Nes, script: InputScript) -> Self {
+        Self {
+            nes,
+            script,
+            audio: Vec::new(),
+            #[cfg(feature = "movie")]
+            recorder: None,
+        }
+    }
+
+    /// Power on a console with the ROM inserted
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the mapper of the ROM isn't supported
+    pub fn from_ines(
+        ines: &Ines<'_>,
+        sample_rate: u32,
+        script: InputScript,
+    ) -> Result<Self, Error> {
+        Ok(Self::new(Nes::from_ines(ines, sample_rate)?, script))
+    }
+
+    #[must_use]
+    pub fn nes(&self) -> &Nes {
+        &self.nes
+    }
+
+    pub fn nes_mut(&mut self) -> &mut Nes {
+        &mut self.nes
+    }
+
+    #[must_use]
+    pub fn script(&self) -> &InputScript {
+        &self.script
+    }
+
+    pub fn script_mut(&mut self) -> &mut InputScript {
+        &mut self.script
+    }
+
+    /// Execute one instruction, returning the amount of CPU cycles it took
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the CPU encounters an unknown opcode
+    pub fn step(&mut self) -> Result<u64, Error> {
+        #[cfg(feature = "movie")]
+        let frame = self.nes.ppu().frame();
+        let cycles = self.nes.step_with_input(&mut self.script)?;
+        self.audio.extend(self.nes.take_samples());
+
+        #[cfg(feature = "movie")]
+        if let Some(recorder) = &mut self.recorder {
+            if self.nes.ppu().frame() != frame {
+                let [first, second] = self.script.buttons_at(frame);
+                recorder.finish_frame([first, second, Buttons::NONE, Buttons::NONE]);
+            }
+        }
+
+        Ok(cycles)
+    }
+
+    /// Run for the amount of frames
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the CPU encounters an unknown opcode
+    pub fn run_frames(&mut self, frames: u64) -> Result<(), Error> {
+        let target = self.nes.ppu().frame() + frames;
+        while self.nes.ppu().frame() < target {
+            self.step()?;
+        }
+
+        Ok(())
+    }
+
+    /// Run for at least the amount of CPU cycles; the last instruction is always finished
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the CPU encounters an unknown opcode
+    pub fn run_cycles(&mut self, cycles: u64) -> Result<(), Error> {
+        let target = self.nes.cpu().cycles() + cycles;
+        while self.nes.cpu().cycles() < target {
+            self.step()?;
+        }
+
+        Ok(())
+    }
+
+    /// Run frame by frame until the condition is met, for at most the amount of frames
+    ///
+    /// Returns whether the condition was met
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the CPU encounters an unknown opcode
+    pub fn run_until<F>(&mut self, max_frames: u64, mut condition: F) -> Result<bool, Error>
+    where
+        F: FnMut(&Nes) -> bool,
+    {
+        for _ in 0..max_frames {
+            if condition(&self.nes) {
+                return Ok(true);
+            }
+
+            self.run_frames(1)?;
+        }
+
+        Ok(condition(&self.nes))
+    }
+
+    /// Capture the memory and the framebuffer
+    #[must_use]
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            frame: self.nes.ppu().frame(),
+            cycles: self.nes.cpu().cycles(),
+            ram: self.nes.ram().to_vec(),
+            prg_ram: self.nes.cartridge().mapper().memory().prg_ram.clone(),
+            framebuffer: self.nes.framebuffer().to_vec(),
+        }
+    }
+
+    /// Take the audio samples generated since the last call
+    pub fn take_audio(&mut self) -> Vec<f32> {
+        core::mem::take(&mut self.audio)
+    }
+
+    /// Record the input of the script into an FM2 movie from now on
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the console already ran for a frame, since movies start at power-on
+    #[cfg(feature = "movie")]
+    pub fn start_recording(&mut self, ines: &Ines<'_>, rom_filename: &str) -> Result<(), Error> {
+        self.recorder = Some(Recorder::new(ines, &self.nes, rom_filename)?);
+        Ok(())
+    }
+
+    /// Movie recorded so far
+    #[cfg(feature = "movie")]
+    #[must_use]
+    pub fn recording(&self) -> Option<&Fm2> {
+        self.recorder.as_ref().map(Recorder::movie)
+    }
+
+    /// Stop recording and return the movie
+    #[cfg(feature = "movie")]
+    pub fn stop_recording(&mut self) -> Option<Fm2> {
+        self.recorder.take().map(Recorder::into_movie)
+    }
+}
@@ -0,0 +1,298 @@
+use {
+    crate::{Buttons, InputScript},
+    nes_ppu::Ppu,
+    nes_state::{Savestate, StateReader, StateWriter},
+};
+
+// The photodiode keeps sensing a pixel for a while after the beam drew it
+const LIGHT_SCANLINES: u16 = 20;
+
+// Bits of the Zapper in `$4016`/`$4017`; a cleared light bit means light was sensed
+const ZAPPER_NO_LIGHT: u8 = 0x08;
+const ZAPPER_TRIGGER: u8 = 0x10;
+
+// Keys of the Family BASIC keyboard by row, column 0 and then column 1, in the order of the bits 1-4
+const KEYBOARD_ROWS: usize = 9;
+const KEYBOARD_KEYS: [[&str; 8]; KEYBOARD_ROWS] = [
+    ["]", "[", "RETURN", "F8", "STOP", "¥", "RSHIFT", "KANA"],
+    [";", ":", "@", "F7", "^", "-", "/", "_"],
+    ["K", "L", "O", "F6", "0", "P", ",", "."],
+    ["J", "U", "I", "F5", "8", "9", "N", "M"],
+    ["H", "G", "Y", "F4", "6", "7", "V", "B"],
+    ["D", "R", "T", "F3", "4", "5", "C", "F"],
+    ["A", "S", "W", "F2", "3", "E", "Z", "X"],
+    ["CTR", "Q", "ESC", "F1", "2", "1", "GRPH", "LSHIFT"],
+    [
+        "LEFT", "RIGHT", "UP", "CLR HOME", "INS", "DEL", "SPACE", "DOWN",
+    ],
+];
+const KEYBOARD_DATA: u8 = 0x1E;
+
+/// Devices plugged into the controller ports and the expansion port of the Famicom
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum InputDevices {
+    /// Standard controllers; the second one of a Famicom has a microphone
+    Controllers,
+    /// NES Four Score with two more controllers, read after the ones in the ports
+    FourScore,
+    /// Famicom adapter with two more controllers on the expansion port, read in parallel to the ones in the ports
+    FamicomFourPlayers,
+    /// Zapper in the second port
+    Zapper,
+    /// Zappers in both ports
+    TwoZappers,
+    /// Family BASIC keyboard on the expansion port, next to the controllers
+    FamilyBasicKeyboard,
+}
+
+impl InputDevices {
+    /// Devices of the default expansion device of a NES 2.0 header; `None` for the ones which aren't emulated
+    #[must_use]
+    pub fn from_expansion_device(device: u8) -> Option<Self> {
+        Some(match device {
+            0x00 | 0x01 => Self::Controllers,
+            0x02 => Self::FourScore,
+            0x03 => Self::FamicomFourPlayers,
+            0x08 => Self::Zapper,
+            0x09 => Self::TwoZappers,
+            0x23 => Self::FamilyBasicKeyboard,
+            _ => return None,
+        })
+    }
+
+    /// Amount of standard controllers the devices include
+    #[must_use]
+    pub fn controllers(self) -> usize {
+        match self {
+            Self::FourScore | Self::FamicomFourPlayers => 4,
+            _ => 2,
+        }
+    }
+}
+
+/// Source of the buttons held on the controllers
+///
+/// The console asks for the buttons whenever the game latches the controllers by writing to `$4016`,
+/// so scripted runs, movie playback and frontends polling real input devices all go through the same path.
+pub trait InputProvider {
+    /// Buttons held on the controller (0 or 1, 2 and 3 for the ones of a four player adapter) while the frame is rendered
+    fn buttons(&mut self, frame: u64, port: usize) -> Buttons;
+
+    /// State of the Zapper in the port, asked for before every instruction while one is connected
+    fn zapper(&mut self, _frame: u64, _port: usize) -> ZapperState {
+        ZapperState::default()
+    }
+}
+
+impl InputProvider for InputScript {
+    fn buttons(&mut self, frame: u64, port: usize) -> Buttons {
+        self.buttons_at(frame)
+            .get(port)
+            .copied()
+            .unwrap_or(Buttons::NONE)
+    }
+}
+
+/// The same buttons on every frame
+impl InputProvider for [Buttons; 2] {
+    fn buttons(&mut self, _frame: u64, port: usize) -> Buttons {
+        self.get(port).copied().unwrap_or(Buttons::NONE)
+    }
+}
+
+impl<F> InputProvider for F
+where
+    F: FnMut(u64, usize) -> Buttons,
+{
+    fn buttons(&mut self, frame: u64, port: usize) -> Buttons {
+        self(frame, port)
+    }
+}
+
+/// Trigger and aim of a Zapper light gun
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ZapperState {
+    pub trigger: bool,
+    /// Pixel the Zapper is pointed at, `None` if it's pointed away from the screen
+    pub aim: Option<(u8, u8)>,
+}
+
+impl ZapperState {
+    /// Whether the photodiode senses the aimed pixel
+    ///
+    /// The pixel has to be bright and drawn within the last scanlines, which is how games tell
+    /// the flashed target apart from the rest of the screen.
+    #[must_use]
+    pub fn senses_light(&self, ppu: &Ppu) -> bool {
+        let (x, y) = match self.aim {
+            Some((x, y)) => (u16::from(x), u16::from(y)),
+            None => return false,
+        };
+
+        // The pixel at `x` is output on dot `x + 1`
+        let drawn = ppu.scanline() > y || (ppu.scanline() == y && ppu.dot() > x + 1);
+        if !drawn || ppu.scanline() >= y + LIGHT_SCANLINES {
+            return false;
+        }
+
+        let index = ppu.framebuffer()[usize::from(y) * 256 + usize::from(x)];
+        is_bright(index)
+    }
+
+    pub(crate) fn read(self, ppu: &Ppu) -> u8 {
+        let light = if self.senses_light(ppu) {
+            0
+        } else {
+            ZAPPER_NO_LIGHT
+        };
+        let trigger = if self.trigger { ZAPPER_TRIGGER } else { 0 };
+
+        light | trigger
+    }
+}
+
+// The two brightest rows of the master palette, without the blacks of the last columns
+fn is_bright(index: u8) -> bool {
+    index & 0x30 >= 0x20 && index & 0x0F < 0x0D
+}
+
+/// Standard controller connected to one of the ports
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Controller {
+    pub buttons: Buttons,
+    shift_register: u8,
+    strobe: bool,
+}
+
+impl Controller {
+    /// Change the held buttons; they're latched right away while the strobe is set
+    pub fn set_buttons(&mut self, buttons: Buttons) {
+        self.buttons = buttons;
+        if self.strobe {
+            self.shift_register = buttons.0;
+        }
+    }
+
+    pub fn is_strobed(self) -> bool {
+        self.strobe
+    }
+
+    pub fn read(&mut self) -> u8 {
+        if self.strobe {
+            return self.buttons.0 & 0x01;
+        }
+
+        // After all eight buttons were read, official controllers return ones
+        let bit = self.shift_register & 0x01;
+        self.shift_register = (self.shift_register >> 1) | 0x80;
+        bit
+    }
+
+    pub fn write_strobe(&mut self, strobe: bool) {
+        self.strobe = strobe;
+        if strobe {
+            self.shift_register = self.buttons.0;
+        }
+    }
+}
+
+impl Savestate for Controller {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.buttons.0);
+        writer.write_u8(self.shift_register);
+        writer.write_bool(self.strobe);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), nes_state::Error> {
+        self.buttons = Buttons(reader.read_u8()?);
+        self.shift_register = reader.read_u8()?;
+        self.strobe = reader.read_bool()?;
+
+        Ok(())
+    }
+}
+
+/// Family BASIC keyboard, scanned row by row through `$4016` and `$4017`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FamilyBasicKeyboard {
+    // Pressed keys of both columns of every row, in bits 1-4
+    keys: [[u8; 2]; KEYBOARD_ROWS],
+    row: u8,
+    column: u8,
+    enabled: bool,
+}
+
+impl FamilyBasicKeyboard {
+    /// Press or release the key with the name printed on it (`"A"`, `"RETURN"`, `"F1"`, `"SPACE"`, ...)
+    ///
+    /// Returns whether the keyboard has the key
+    pub fn set_key(&mut self, name: &str, pressed: bool) -> bool {
+        let position = KEYBOARD_KEYS.iter().enumerate().find_map(|(row, keys)| {
+            keys.iter()
+                .position(|key| key.eq_ignore_ascii_case(name))
+                .map(|index| (row, index / 4, 0x02 << (index % 4)))
+        });
+
+        match position {
+            Some((row, column, bit)) => {
+                if pressed {
+                    self.keys[row][column] |= bit;
+                } else {
+                    self.keys[row][column] &= !bit;
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn release_all(&mut self) {
+        self.keys = [[0; 2]; KEYBOARD_ROWS];
+    }
+
+    /// Handle a write to `$4016`: bit 0 resets the scan, bit 1 selects the column and bit 2 enables the keyboard
+    ///
+    /// Switching from the second column back to the first one advances to the next row
+    pub(crate) fn write(&mut self, value: u8) {
+        let column = (value >> 1) & 0x01;
+        if self.column == 1 && column == 0 {
+            self.row = self.row.saturating_add(1);
+        }
+        self.column = column;
+        self.enabled = value & 0x04 != 0;
+
+        if value & 0x01 != 0 {
+            self.row = 0;
+        }
+    }
+
+    /// Bits 1-4 of `$4017`, cleared for pressed keys
+    pub(crate) fn read(&self) -> u8 {
+        if !self.enabled {
+            return 0;
+        }
+
+        let keys = self
+            .keys
+            .get(usize::from(self.row))
+            .map_or(0, |columns| columns[usize::from(self.column)]);
+        !keys & KEYBOARD_DATA
+    }
+}
+
+impl Savestate for FamilyBasicKeyboard {
+    // The pressed keys are input, not state
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.row);
+        writer.write_u8(self.column);
+        writer.write_bool(self.enabled);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), nes_state::Error> {
+        self.row = reader.read_u8()?;
+        self.column = reader.read_u8()?;
+        self.enabled = reader.read_bool()?;
+
+        Ok(())
+    }
+}
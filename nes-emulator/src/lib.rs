@@ -0,0 +1,113 @@
+#![no_std]
+#![warn(clippy::all, clippy::pedantic)]
+
+//!
+//! Emulator for the whole NES, built from the individual emulation cores
+//!
+
+extern crate alloc;
+
+use core::{
+    fmt,
+    ops::{BitOr, BitOrAssign},
+};
+
+mod blargg;
+mod debugger;
+mod harness;
+mod input;
+#[cfg(feature = "movie")]
+mod movie;
+mod nes;
+mod ram_search;
+mod rewind;
+mod state_diff;
+
+pub use {
+    blargg::{BlarggResult, BlarggStatus, BLARGG_STATUS_ADDRESS},
+    debugger::{Debugger, Line, Registers, Stop, Symbols},
+    harness::{Harness, InputScript, Snapshot},
+    input::{FamilyBasicKeyboard, InputDevices, InputProvider, ZapperState},
+    nes::{Nes, STATE_MAGIC, STATE_VERSION},
+    ram_search::{Candidate, Filter, RamSearch},
+    rewind::Rewind,
+    state_diff::{Difference, StateDiff, Subsystem},
+};
+
+#[cfg(feature = "movie")]
+pub use movie::{rom_checksum, verify_movie, Recorder, VerificationReport};
+
+#[derive(Debug)]
+pub enum Error {
+    Cpu(mos6502_cpu::Error),
+    Mapper(nes_mapper::Error),
+    State(nes_state::Error),
+    /// The movie can't be replayed, with the reason
+    #[cfg(feature = "movie")]
+    UnsupportedMovie(&'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cpu(err) => write!(f, "CPU error: {err}"),
+            Self::Mapper(err) => write!(f, "Mapper error: {err}"),
+            Self::State(err) => write!(f, "Save state error: {err}"),
+            #[cfg(feature = "movie")]
+            Self::UnsupportedMovie(reason) => write!(f, "Unsupported movie: {reason}"),
+        }
+    }
+}
+
+impl From<mos6502_cpu::Error> for Error {
+    fn from(err: mos6502_cpu::Error) -> Self {
+        Self::Cpu(err)
+    }
+}
+
+impl From<nes_mapper::Error> for Error {
+    fn from(err: nes_mapper::Error) -> Self {
+        Self::Mapper(err)
+    }
+}
+
+impl From<nes_state::Error> for Error {
+    fn from(err: nes_state::Error) -> Self {
+        Self::State(err)
+    }
+}
+
+/// Buttons of a standard controller, in the order they're shifted out
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Buttons(pub u8);
+
+impl Buttons {
+    pub const NONE: Self = Self(0x00);
+    pub const A: Self = Self(0x01);
+    pub const B: Self = Self(0x02);
+    pub const SELECT: Self = Self(0x04);
+    pub const START: Self = Self(0x08);
+    pub const UP: Self = Self(0x10);
+    pub const DOWN: Self = Self(0x20);
+    pub const LEFT: Self = Self(0x40);
+    pub const RIGHT: Self = Self(0x80);
+
+    #[must_use]
+    pub const fn contains(self, buttons: Self) -> bool {
+        self.0 & buttons.0 == buttons.0
+    }
+}
+
+impl BitOr for Buttons {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for Buttons {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
@@ -0,0 +1,288 @@
+use {
+    crate::{Buttons, Error, InputDevices, InputProvider, Nes, ZapperState},
+    alloc::string::{String, ToString},
+    core::fmt,
+    ines_parser::{Ines, Timing},
+    md5::{Digest, Md5},
+    nes_mapper::Cartridge,
+    nes_movie::{Commands, Fm2, Frame},
+};
+
+// The audio isn't part of the verification, any sample rate does
+const SAMPLE_RATE: u32 = 44100;
+
+// Version of the format and of FCEUX the recorded movies claim
+const FM2_VERSION: u32 = 3;
+const FCEUX_VERSION: u32 = 22020;
+
+// Recorded movies don't need to be told apart, so they all get the same GUID
+const GUID: &str = "00000000-0000-0000-0000-000000000000";
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Outcome of replaying a movie with [`verify_movie`]
+///
+/// Runs of the same movie on the same ROM always end in the same state, so comparing the hashes
+/// with the ones of another run tells whether the movie still plays back the same way.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct VerificationReport {
+    /// Frames of the movie which were played
+    pub frames: u64,
+    /// Frames in which the game didn't read the controllers
+    pub lag_frames: u64,
+    /// CPU cycles executed since the last power-on
+    pub cycles: u64,
+    /// CRC32 of the internal RAM at the end of the movie
+    pub ram_crc32: u32,
+    /// CRC32 of the PRG RAM of the cartridge, which holds the saved progress of battery-backed games
+    pub prg_ram_crc32: u32,
+    /// CRC32 of the last frame as indices into the master palette
+    pub framebuffer_crc32: u32,
+}
+
+impl fmt::Display for VerificationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Frames: {} ({} lag frames)",
+            self.frames, self.lag_frames
+        )?;
+        writeln!(f, "CPU cycles: {}", self.cycles)?;
+        writeln!(f, "RAM: {:08X}", self.ram_crc32)?;
+        writeln!(f, "PRG RAM: {:08X}", self.prg_ram_crc32)?;
+        write!(f, "Framebuffer: {:08X}", self.framebuffer_crc32)
+    }
+}
+
+// Console in the state the movie starts from
+fn power_on(ines: &Ines<'_>, movie: &Fm2) -> Result<Nes, Error> {
+    let timing = if movie.header.pal {
+        Timing::Pal
+    } else {
+        Timing::Ntsc
+    };
+
+    let mut nes = Nes::with_timing(Cartridge::from_ines(ines)?, SAMPLE_RATE, timing);
+    if movie.header.fourscore {
+        nes.set_input_devices(InputDevices::FourScore);
+    }
+
+    Ok(nes)
+}
+
+/// Replay the FM2 movie on the ROM from power-on and hash the state the console ends up in
+///
+/// Every frame of the movie is one frame of the emulator; resets and power cycles of the movie are carried out
+/// at the start of their frame, the commands of the Famicom Disk System and Vs. System are ignored.
+/// The PAL flag and the Four Score of the movie override the ones of the ROM.
+///
+/// # Errors
+///
+/// Returns an error if the movie starts from a save state or is made for the Famicom Disk System,
+/// the mapper of the ROM isn't supported or the CPU encounters an unknown opcode
+pub fn verify_movie(ines: &Ines<'_>, movie: &Fm2) -> Result<VerificationReport, Error> {
+    if movie.header.savestate.is_some() {
+        return Err(Error::UnsupportedMovie(
+            "the movie starts from a save state",
+        ));
+    }
+    if movie.header.fds {
+        return Err(Error::UnsupportedMovie(
+            "Famicom Disk System movies aren't supported",
+        ));
+    }
+
+    let mut nes = power_on(ines, movie)?;
+    let mut lag_frames = 0;
+    for frame in &movie.frames {
+        if frame.commands.contains(Commands::HARD_RESET) {
+            nes = power_on(ines, movie)?;
+        } else if frame.commands.contains(Commands::SOFT_RESET) {
+            nes.reset();
+        }
+
+        let mut polled = false;
+        let mut input = |_frame: u64, port: usize| {
+            polled = true;
+            frame
+                .buttons
+                .get(port)
+                .map_or(Buttons::NONE, |buttons| Buttons(buttons.0))
+        };
+        nes.run_frame_with_input(&mut input)?;
+        // Nobody listens to the audio
+        nes.take_samples();
+
+        if !polled {
+            lag_frames += 1;
+        }
+    }
+
+    Ok(VerificationReport {
+        frames: movie.frames.len() as u64,
+        lag_frames,
+        cycles: nes.cpu().cycles(),
+        ram_crc32: crc32fast::hash(nes.ram()),
+        prg_ram_crc32: crc32fast::hash(&nes.cartridge().mapper().memory().prg_ram),
+        framebuffer_crc32: crc32fast::hash(nes.framebuffer()),
+    })
+}
+
+fn base64(data: &[u8]) -> String {
+    let mut encoded = String::new();
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let bits = u32::from(bytes[0]) << 16 | u32::from(bytes[1]) << 8 | u32::from(bytes[2]);
+
+        for index in 0..4 {
+            if index <= chunk.len() {
+                let sextet = (bits >> (18 - index * 6)) & 0x3F;
+                encoded.push(char::from(BASE64_ALPHABET[sextet as usize]));
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+/// ROM checksum of an FM2 movie; the MD5 of the PRG and CHR ROM like FCEUX calculates it
+#[must_use]
+pub fn rom_checksum(ines: &Ines<'_>) -> String {
+    let mut md5 = Md5::new();
+    md5.update(&ines.prg_rom);
+    if let Some(chr_rom) = &ines.chr_rom {
+        md5.update(chr_rom);
+    }
+
+    let mut checksum = "base64:".to_string();
+    checksum.push_str(&base64(&md5.finalize()));
+    checksum
+}
+
+/// Records the input a game reads into an FM2 movie, which [`verify_movie`] and FCEUX can play back
+///
+/// Recording starts at power-on, since FM2 movies can only start from a save state in the format of FCEUX.
+/// Every frame of the emulator becomes one frame of the movie, holding the buttons the game read last
+/// (or the ones held at the end of the frame, if it didn't read them).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Recorder {
+    movie: Fm2,
+    /// Commands of the next recorded frame
+    commands: Commands,
+    /// Buttons the game read during the current frame
+    read: [Option<Buttons>; 4],
+}
+
+impl Recorder {
+    /// Start recording on a console which was just powered on with the ROM
+    ///
+    /// The name of the ROM file is stored in the movie for the information of its viewers.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the console already ran for a frame
+    pub fn new(ines: &Ines<'_>, nes: &Nes, rom_filename: &str) -> Result<Self, Error> {
+        if nes.ppu().frame() != 0 {
+            return Err(Error::UnsupportedMovie(
+                "recordings have to start at power-on",
+            ));
+        }
+
+        let mut movie = Fm2::new();
+        let header = &mut movie.header;
+        header.version = FM2_VERSION;
+        header.emu_version = FCEUX_VERSION;
+        header.pal = nes.timing() == Timing::Pal;
+        header.fourscore = nes.input_devices() == InputDevices::FourScore;
+        header.rom_filename = rom_filename.to_string();
+        header.rom_checksum = rom_checksum(ines);
+        header.guid = GUID.to_string();
+
+        Ok(Self {
+            movie,
+            commands: Commands::NONE,
+            read: [None; 4],
+        })
+    }
+
+    /// Movie recorded so far
+    #[must_use]
+    pub fn movie(&self) -> &Fm2 {
+        &self.movie
+    }
+
+    #[must_use]
+    pub fn into_movie(self) -> Fm2 {
+        self.movie
+    }
+
+    /// Reset the console, recorded as a reset at the start of the next frame
+    pub fn reset(&mut self, nes: &mut Nes) {
+        nes.reset();
+        self.commands |= Commands::SOFT_RESET;
+    }
+
+    /// Remember the buttons the game read from a port
+    pub fn record_read(&mut self, port: usize, buttons: Buttons) {
+        if let Some(read) = self.read.get_mut(port) {
+            *read = Some(buttons);
+        }
+    }
+
+    /// End the current frame; ports the game didn't read record the held buttons instead
+    pub fn finish_frame(&mut self, held: [Buttons; 4]) {
+        let mut frame = Frame {
+            commands: self.commands,
+            ..Frame::default()
+        };
+        for ((buttons, read), held) in frame.buttons.iter_mut().zip(&mut self.read).zip(held) {
+            *buttons = nes_movie::Buttons(read.take().unwrap_or(held).0);
+        }
+
+        self.movie.frames.push(frame);
+        self.commands = Commands::NONE;
+    }
+
+    /// Run one frame with the input and record it
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the CPU encounters an unknown opcode
+    pub fn run_frame(&mut self, nes: &mut Nes, input: &mut dyn InputProvider) -> Result<(), Error> {
+        let frame = nes.ppu().frame();
+        nes.run_frame_with_input(&mut Recording {
+            recorder: self,
+            input,
+        })?;
+
+        let held = [0, 1, 2, 3].map(|port| input.buttons(frame, port));
+        self.finish_frame(held);
+
+        Ok(())
+    }
+}
+
+// Input provider passing the buttons through while recording them
+struct Recording<'a> {
+    recorder: &'a mut Recorder,
+    input: &'a mut dyn InputProvider,
+}
+
+impl InputProvider for Recording<'_> {
+    fn buttons(&mut self, frame: u64, port: usize) -> Buttons {
+        let buttons = self.input.buttons(frame, port);
+        self.recorder.record_read(port, buttons);
+        buttons
+    }
+
+    fn zapper(&mut self, frame: u64, port: usize) -> ZapperState {
+        self.input.zapper(frame, port)
+    }
+}
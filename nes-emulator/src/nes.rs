@@ -0,0 +1,897 @@
+use {
+    crate::{
+        input::{Controller, FamilyBasicKeyboard, InputDevices, InputProvider, ZapperState},
+        Buttons, Error,
+    },
+    alloc::vec::Vec,
+    fds_parser::Fds,
+    ines_parser::{Ines, Timing},
+    mos6502_cpu::{debug::Step, Bus, Cpu, Debugger as CpuDebugger},
+    nes_apu::Apu,
+    nes_cheats::Cheat,
+    nes_mapper::{Cartridge, DiskSystem, RegionKind},
+    nes_ppu::Ppu,
+    nes_state::{Savestate, StateReader, StateWriter},
+};
+
+const RAM_SIZE: usize = 0x800;
+
+/// Magic bytes at the start of every save state
+pub const STATE_MAGIC: [u8; 4] = *b"NESS";
+
+/// Version of the save state format; states of older versions can still be loaded
+pub const STATE_VERSION: u16 = 1;
+
+const CPU_CHUNK: [u8; 4] = *b"CPU ";
+const PPU_CHUNK: [u8; 4] = *b"PPU ";
+const APU_CHUNK: [u8; 4] = *b"APU ";
+const CARTRIDGE_CHUNK: [u8; 4] = *b"CART";
+const SYSTEM_CHUNK: [u8; 4] = *b"SYS ";
+const CHUNKS: [[u8; 4]; 5] = [
+    CPU_CHUNK,
+    PPU_CHUNK,
+    APU_CHUNK,
+    CARTRIDGE_CHUNK,
+    SYSTEM_CHUNK,
+];
+
+// PPU dots per CPU cycle as a fraction; PAL consoles run 16 dots in 5 cycles, NTSC and Dendy ones 3 dots per cycle
+const NTSC_PPU_CLOCK: (u8, u8) = (3, 1);
+const PAL_PPU_CLOCK: (u8, u8) = (16, 5);
+
+const OAM_DATA: u16 = 0x2004;
+const OAM_DMA: u16 = 0x4014;
+
+// Bits the Four Score shifts out of `$4016` and `$4017` after the buttons of both of its controllers
+const FOUR_SCORE_SIGNATURES: [u8; 2] = [0x08, 0x04];
+
+// The microphone of the second Famicom controller shows up in `$4016`
+const MICROPHONE: u8 = 0x04;
+
+/// Everything the CPU can access
+///
+/// The other chips get clocked whenever the CPU accesses the bus, which happens exactly once per cycle
+struct SystemBus {
+    ram: [u8; RAM_SIZE],
+    ppu: Ppu,
+    apu: Apu,
+    cartridge: Cartridge,
+    /// Dots and CPU cycles of the PPU clock ratio
+    ppu_clock: (u8, u8),
+    /// Dots the PPU is ahead of the CPU, in fractions of the ratio
+    dot_phase: u8,
+    devices: InputDevices,
+    /// The controllers in the ports, followed by the ones of a four player adapter
+    controllers: [Controller; 4],
+    /// Reads of the ports since the Four Score was strobed
+    four_score_reads: [u8; 2],
+    /// Zappers plugged in instead of the controllers
+    zappers: [Option<ZapperState>; 2],
+    microphone: bool,
+    keyboard: FamilyBasicKeyboard,
+    /// The game latched the controllers since the input was last provided
+    strobed: bool,
+    /// Page written to `$4014`; the copy starts once the CPU reaches its next read
+    oam_dma_page: Option<u8>,
+    /// Value of the last access on the data bus, returned for unmapped addresses
+    open_bus: u8,
+    /// Cheats applied to every value the CPU reads, like a Game Genie would
+    cheats: Vec<Cheat>,
+    /// Halt the CPU for the sample fetches of the DMC instead of fetching them for free
+    accurate_dmc_dma: bool,
+    /// CPU cycle of the next bus access, which tells get cycles from put cycles
+    cycle: u64,
+    /// Cycles the CPU was halted for by the DMC during the current instruction
+    stolen_cycles: u64,
+}
+
+impl SystemBus {
+    /// Run the PPU and APU for one CPU cycle
+    fn tick(&mut self) {
+        let (dots, cycles) = self.ppu_clock;
+        self.dot_phase += dots;
+        while self.dot_phase >= cycles {
+            self.dot_phase -= cycles;
+            self.ppu.tick(&mut self.cartridge);
+        }
+
+        self.apu.tick();
+        self.cartridge.clock_cpu();
+        self.cycle += 1;
+        if !self.accurate_dmc_dma {
+            if let Some(address) = self.apu.dmc_dma_address() {
+                let value = self.read_memory(address);
+                self.apu.load_dmc_sample(value);
+            }
+        }
+    }
+
+    /// Fetch the sample byte the DMC waits for, halting the CPU before its read of the address
+    ///
+    /// The CPU can only be halted on read cycles. The halted read is performed once and repeated after the DMA,
+    /// so registers with read side effects see an extra read: a controller loses a bit and `$2007` increments twice.
+    /// A dummy cycle and possibly an alignment cycle follow, since the sample has to be read on a get cycle.
+    fn dmc_dma(&mut self, halted_address: u16) {
+        let Some(address) = self.apu.dmc_dma_address() else {
+            return;
+        };
+
+        let start = self.cycle;
+        self.tick();
+        self.read_memory(halted_address);
+        self.tick();
+        if self.cycle.is_multiple_of(2) {
+            self.tick();
+        }
+        self.tick();
+        let value = self.read_memory(address);
+        self.apu.load_dmc_sample(value);
+
+        self.stolen_cycles += self.cycle - start;
+    }
+
+    fn read_memory(&mut self, address: u16) -> u8 {
+        let value = match address {
+            0x0000..=0x1FFF => self.ram[usize::from(address) % RAM_SIZE],
+            0x2000..=0x3FFF => self.ppu.read_register(&mut self.cartridge, address),
+            0x4015 => self.apu.read_status(),
+            // Only the lowest bit is driven by the controller, the upper bits are open bus
+            0x4016 | 0x4017 => {
+                let port = usize::from(address - 0x4016);
+                let data = match self.zappers[port] {
+                    Some(zapper) => zapper.read(&self.ppu),
+                    None => self.read_port(port),
+                };
+                (self.open_bus & 0xE0) | data
+            }
+            0x4020..=0xFFFF => self.read_cartridge(address),
+            _ => self.open_bus,
+        };
+        let value = self
+            .cheats
+            .iter()
+            .fold(value, |value, cheat| cheat.patch(address, value));
+
+        self.open_bus = value;
+        value
+    }
+
+    // The audio of the Famicom Disk System sits in the cartridge space
+    fn read_cartridge(&mut self, address: u16) -> u8 {
+        #[cfg(feature = "fds-audio")]
+        if let Some(value) = self.apu.read_expansion(address) {
+            return value;
+        }
+
+        self.cartridge.cpu_read(address)
+    }
+
+    /// Data of the controllers and expansion devices in bits 0-4 of `$4016` or `$4017`
+    fn read_port(&mut self, port: usize) -> u8 {
+        let data = match self.devices {
+            InputDevices::FourScore => {
+                let read = self.four_score_reads[port];
+                if !self.controllers[port].is_strobed() {
+                    self.four_score_reads[port] = read.saturating_add(1);
+                }
+
+                match read {
+                    0..=7 => self.controllers[port].read(),
+                    8..=15 => self.controllers[port + 2].read(),
+                    16..=23 => (FOUR_SCORE_SIGNATURES[port] >> (read - 16)) & 0x01,
+                    _ => 0x01,
+                }
+            }
+            InputDevices::FamicomFourPlayers => {
+                self.controllers[port].read() | self.controllers[port + 2].read() << 1
+            }
+            InputDevices::FamilyBasicKeyboard if port == 1 => {
+                self.controllers[port].read() | self.keyboard.read()
+            }
+            _ => self.controllers[port].read(),
+        };
+
+        if port == 0 && self.microphone {
+            data | MICROPHONE
+        } else {
+            data
+        }
+    }
+
+    /// Copy the page to the OAM through `$2004`, returning the amount of cycles the CPU was halted for
+    ///
+    /// The CPU halts for one cycle, plus one more to align to a read cycle, followed by 256 reads and writes
+    fn oam_dma(&mut self, page: u8, odd_cycle: bool) -> u64 {
+        let halt_cycles = 1 + u64::from(odd_cycle);
+        for _ in 0..halt_cycles {
+            self.tick();
+        }
+
+        let mut dmc_cycles = 0;
+        for offset in 0..=0xFF {
+            // The DMC takes over a get cycle, which costs another cycle to realign
+            if let Some(address) = self.apu.dmc_dma_address().filter(|_| self.accurate_dmc_dma) {
+                self.tick();
+                let value = self.read_memory(address);
+                self.apu.load_dmc_sample(value);
+                self.tick();
+                dmc_cycles += 2;
+            }
+
+            self.tick();
+            let value = self.read_memory(u16::from_be_bytes([page, offset]));
+            self.tick();
+            self.ppu
+                .write_register(&mut self.cartridge, OAM_DATA, value);
+        }
+
+        halt_cycles + 2 * 256 + dmc_cycles
+    }
+
+    fn write_memory(&mut self, address: u16, value: u8) {
+        self.open_bus = value;
+
+        match address {
+            0x0000..=0x1FFF => self.ram[usize::from(address) % RAM_SIZE] = value,
+            0x2000..=0x3FFF => self.ppu.write_register(&mut self.cartridge, address, value),
+            OAM_DMA => self.oam_dma_page = Some(value),
+            0x4016 => {
+                let strobe = value & 0x01 != 0;
+                for controller in &mut self.controllers {
+                    controller.write_strobe(strobe);
+                }
+                if strobe {
+                    self.four_score_reads = [0; 2];
+                }
+                if self.devices == InputDevices::FamilyBasicKeyboard {
+                    self.keyboard.write(value);
+                }
+                self.strobed |= strobe;
+            }
+            0x4000..=0x4017 => self.apu.write_register(address, value),
+            0x4020..=0xFFFF => {
+                #[cfg(feature = "fds-audio")]
+                self.apu.write_expansion(address, value);
+                self.cartridge.cpu_write(address, value);
+            }
+            _ => {}
+        }
+    }
+}
+
+// The state of the components outside of the chips and the cartridge
+impl Savestate for SystemBus {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bytes(&self.ram);
+        for controller in &self.controllers[..2] {
+            controller.save_state(writer);
+        }
+        writer.write_u8(self.open_bus);
+
+        // Appended to keep the states of older versions loadable
+        for controller in &self.controllers[2..] {
+            controller.save_state(writer);
+        }
+        writer.write_bytes(&self.four_score_reads);
+        self.keyboard.save_state(writer);
+        writer.write_u8(self.dot_phase);
+        writer.write_u64(self.cycle);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), nes_state::Error> {
+        reader.read_bytes_into(&mut self.ram)?;
+        for controller in &mut self.controllers[..2] {
+            controller.load_state(reader)?;
+        }
+        self.open_bus = reader.read_u8()?;
+
+        if !reader.remaining().is_empty() {
+            for controller in &mut self.controllers[2..] {
+                controller.load_state(reader)?;
+            }
+            reader.read_bytes_into(&mut self.four_score_reads)?;
+            self.keyboard.load_state(reader)?;
+        }
+        if !reader.remaining().is_empty() {
+            self.dot_phase = reader.read_u8()? % self.ppu_clock.1;
+        }
+        if !reader.remaining().is_empty() {
+            self.cycle = reader.read_u64()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Bus for SystemBus {
+    fn read(&mut self, address: u16) -> u8 {
+        if self.accurate_dmc_dma {
+            self.dmc_dma(address);
+        }
+        self.tick();
+        self.read_memory(address)
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.tick();
+        self.write_memory(address, value);
+    }
+}
+
+/// NES console with a cartridge inserted
+pub struct Nes {
+    cpu: Cpu,
+    bus: SystemBus,
+}
+
+impl Nes {
+    /// Insert the cartridge and power an NTSC console on
+    #[must_use]
+    pub fn new(cartridge: Cartridge, sample_rate: u32) -> Self {
+        Self::with_timing(cartridge, sample_rate, Timing::Ntsc)
+    }
+
+    /// Insert the cartridge and power a console of the region on
+    ///
+    /// The timing sets the scanlines per frame, the clock ratio of the CPU and the PPU and the rates of the APU,
+    /// so PAL games run at their intended speed and pitch. Multi-region games run on an NTSC console.
+    #[must_use]
+    pub fn with_timing(cartridge: Cartridge, sample_rate: u32, timing: Timing) -> Self {
+        let ppu_clock = match timing {
+            Timing::Pal => PAL_PPU_CLOCK,
+            Timing::Ntsc | Timing::MultiRegion | Timing::Dendy => NTSC_PPU_CLOCK,
+        };
+
+        let mut nes = Self {
+            cpu: Cpu::new(),
+            bus: SystemBus {
+                ram: [0; RAM_SIZE],
+                ppu: Ppu::new().with_timing(timing),
+                apu: Apu::new(sample_rate).with_timing(timing),
+                cartridge,
+                ppu_clock,
+                dot_phase: 0,
+                devices: InputDevices::Controllers,
+                controllers: [Controller::default(); 4],
+                four_score_reads: [0; 2],
+                zappers: [None; 2],
+                microphone: false,
+                keyboard: FamilyBasicKeyboard::default(),
+                strobed: false,
+                oam_dma_page: None,
+                open_bus: 0,
+                cheats: Vec::new(),
+                accurate_dmc_dma: true,
+                cycle: 0,
+                stolen_cycles: 0,
+            },
+        };
+        nes.reset();
+
+        nes
+    }
+
+    /// Build the cartridge of an INES ROM and power the console of its region on
+    ///
+    /// The timing follows the TV system of the header, the input devices the default expansion device of NES 2.0 headers.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the mapper of the ROM isn't supported
+    pub fn from_ines(ines: &Ines<'_>, sample_rate: u32) -> Result<Self, Error> {
+        let cartridge = Cartridge::from_ines(ines)?;
+        let mut nes = Self::with_timing(cartridge, sample_rate, ines.header.timing);
+        if let Some(devices) =
+            InputDevices::from_expansion_device(ines.header.default_expansion_device)
+        {
+            nes.set_input_devices(devices);
+        }
+
+        Ok(nes)
+    }
+
+    /// Power on a Famicom with the Disk System attached, running the BIOS with the disk image
+    ///
+    /// No disk is inserted at first, [`Nes::insert_disk`] puts one into the drive.
+    /// With the `fds-audio` feature, the sound channel of the Disk System is mixed into the audio.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the BIOS isn't 8 KiB
+    pub fn from_fds(bios: &[u8], fds: &Fds<'_>, sample_rate: u32) -> Result<Self, Error> {
+        let cartridge = Cartridge::from_fds(bios, fds)?;
+        #[allow(unused_mut)]
+        let mut nes = Self::new(cartridge, sample_rate);
+        #[cfg(feature = "fds-audio")]
+        nes.bus.apu.enable_fds();
+
+        Ok(nes)
+    }
+
+    /// Press the reset button
+    pub fn reset(&mut self) {
+        self.bus.cycle = self.cpu.cycles();
+        self.cpu.reset(&mut self.bus);
+        self.cpu.stall(core::mem::take(&mut self.bus.stolen_cycles));
+    }
+
+    /// Execute one instruction (or interrupt), returning the amount of CPU cycles it took
+    ///
+    /// A DMA started by the instruction is part of the step. The controllers keep the buttons of [`Nes::set_buttons`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the CPU encounters an unknown opcode
+    pub fn step(&mut self) -> Result<u64, Error> {
+        self.execute(None)
+    }
+
+    /// Execute one instruction like [`Nes::step`], taking the buttons from the provider when the game latches the controllers
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the CPU encounters an unknown opcode
+    pub fn step_with_input(&mut self, input: &mut dyn InputProvider) -> Result<u64, Error> {
+        self.execute(Some(input))
+    }
+
+    fn execute(&mut self, input: Option<&mut dyn InputProvider>) -> Result<u64, Error> {
+        self.execute_with(input, Cpu::step)
+    }
+
+    /// Execute one instruction under the control of the debugger of the CPU
+    pub(crate) fn debug_step(&mut self, debugger: &mut CpuDebugger) -> Result<Step, Error> {
+        let mut reason = None;
+        let cycles = self.execute_with(None, |cpu, bus| {
+            let step = debugger.step(cpu, bus)?;
+            reason = step.stop;
+            Ok(step.cycles)
+        })?;
+
+        Ok(Step {
+            cycles,
+            stop: reason,
+        })
+    }
+
+    // Run the instruction through `step`, followed by the DMA, the input and the interrupts it caused
+    fn execute_with<F>(
+        &mut self,
+        input: Option<&mut dyn InputProvider>,
+        step: F,
+    ) -> Result<u64, Error>
+    where
+        F: FnOnce(&mut Cpu, &mut SystemBus) -> Result<u64, mos6502_cpu::Error>,
+    {
+        self.bus.cycle = self.cpu.cycles();
+        let mut cycles = step(&mut self.cpu, &mut self.bus)?;
+
+        let stolen_cycles = core::mem::take(&mut self.bus.stolen_cycles);
+        self.cpu.stall(stolen_cycles);
+        cycles += stolen_cycles;
+
+        if let Some(page) = self.bus.oam_dma_page.take() {
+            let odd_cycle = self.cpu.cycles() % 2 == 1;
+            let dma_cycles = self.bus.oam_dma(page, odd_cycle);
+            self.cpu.stall(dma_cycles);
+            cycles += dma_cycles;
+        }
+
+        let strobed = core::mem::take(&mut self.bus.strobed);
+        if let Some(input) = input {
+            let frame = self.bus.ppu.frame();
+            // The controllers latch the buttons for as long as the strobe is set, so the next reads see them
+            if strobed {
+                for port in 0..self.bus.devices.controllers() {
+                    let buttons = input.buttons(frame, port);
+                    self.bus.controllers[port].set_buttons(buttons);
+                }
+            }
+            for port in 0..self.bus.zappers.len() {
+                if self.bus.zappers[port].is_some() {
+                    self.bus.zappers[port] = Some(input.zapper(frame, port));
+                }
+            }
+        }
+
+        if self.bus.ppu.take_nmi() {
+            self.cpu.nmi();
+        }
+        self.cpu
+            .set_irq(self.bus.apu.irq() || self.bus.cartridge.irq());
+
+        Ok(cycles)
+    }
+
+    /// Run until the PPU finished the current frame
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the CPU encounters an unknown opcode
+    pub fn run_frame(&mut self) -> Result<(), Error> {
+        let frame = self.bus.ppu.frame();
+        while self.bus.ppu.frame() == frame {
+            self.step()?;
+        }
+
+        Ok(())
+    }
+
+    /// Run until the PPU finished the current frame, taking the buttons from the provider
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the CPU encounters an unknown opcode
+    pub fn run_frame_with_input(&mut self, input: &mut dyn InputProvider) -> Result<(), Error> {
+        let frame = self.bus.ppu.frame();
+        while self.bus.ppu.frame() == frame {
+            self.step_with_input(input)?;
+        }
+
+        Ok(())
+    }
+
+    /// Serialize the state of the whole console
+    ///
+    /// The state starts with [`STATE_MAGIC`] and [`STATE_VERSION`], followed by one tagged chunk per component.
+    /// The ROM itself isn't part of the state.
+    #[must_use]
+    pub fn serialize_state(&self) -> Vec<u8> {
+        let mut writer = StateWriter::new();
+        for byte in STATE_MAGIC {
+            writer.write_u8(byte);
+        }
+        writer.write_u16(STATE_VERSION);
+
+        writer.write_chunk(CPU_CHUNK, &self.cpu);
+        writer.write_chunk(PPU_CHUNK, &self.bus.ppu);
+        writer.write_chunk(APU_CHUNK, &self.bus.apu);
+        writer.write_chunk(CARTRIDGE_CHUNK, &self.bus.cartridge);
+        writer.write_chunk(SYSTEM_CHUNK, &self.bus);
+
+        writer.into_bytes()
+    }
+
+    /// Restore a state created by [`Nes::serialize_state`]
+    ///
+    /// Unknown chunks get skipped. The console is left untouched if the state is invalid.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the state is invalid, was created by a newer version or doesn't fit the inserted cartridge
+    pub fn deserialize_state(&mut self, state: &[u8]) -> Result<(), Error> {
+        let mut reader = StateReader::new(state);
+
+        let mut magic = [0; 4];
+        for byte in &mut magic {
+            *byte = reader.read_u8()?;
+        }
+        if magic != STATE_MAGIC {
+            return Err(nes_state::Error::MagicBytesMismatch(magic).into());
+        }
+
+        let version = reader.read_u16()?;
+        if version > STATE_VERSION {
+            return Err(nes_state::Error::UnsupportedVersion(version).into());
+        }
+
+        let mut chunks = [None, None, None, None, None];
+        while !reader.remaining().is_empty() {
+            let (tag, chunk) = reader.read_chunk()?;
+            if let Some(index) = CHUNKS.iter().position(|known| *known == tag) {
+                chunks[index] = Some(chunk);
+            }
+        }
+
+        let missing = |tag| nes_state::Error::MissingChunk(tag);
+        let [cpu_state, ppu_state, apu_state, cartridge_state, system_state] = chunks;
+        let mut cpu_state = cpu_state.ok_or_else(|| missing(CPU_CHUNK))?;
+        let mut ppu_state = ppu_state.ok_or_else(|| missing(PPU_CHUNK))?;
+        let mut apu_state = apu_state.ok_or_else(|| missing(APU_CHUNK))?;
+        let mut cartridge_state = cartridge_state.ok_or_else(|| missing(CARTRIDGE_CHUNK))?;
+        let mut system_state = system_state.ok_or_else(|| missing(SYSTEM_CHUNK))?;
+
+        // Load into copies first so a broken state can't leave the console half-restored
+        let mut cpu = self.cpu.clone();
+        let mut ppu = self.bus.ppu.clone();
+        let mut apu = self.bus.apu.clone();
+        cpu.load_state(&mut cpu_state)?;
+        ppu.load_state(&mut ppu_state)?;
+        apu.load_state(&mut apu_state)?;
+
+        // The cartridge can't be cloned, so it gets backed up as a state
+        let mut backup = StateWriter::new();
+        self.bus.cartridge.save_state(&mut backup);
+        self.bus.save_state(&mut backup);
+
+        let result = self
+            .bus
+            .cartridge
+            .load_state(&mut cartridge_state)
+            .and_then(|()| self.bus.load_state(&mut system_state));
+        if let Err(err) = result {
+            // The backup was just created from the same components, so restoring it can't fail
+            let backup = backup.into_bytes();
+            let mut reader = StateReader::new(&backup);
+            let _ = self
+                .bus
+                .cartridge
+                .load_state(&mut reader)
+                .and_then(|()| self.bus.load_state(&mut reader));
+
+            return Err(err.into());
+        }
+
+        self.cpu = cpu;
+        self.bus.ppu = ppu;
+        self.bus.apu = apu;
+
+        Ok(())
+    }
+
+    /// Region the console runs at
+    #[must_use]
+    pub fn timing(&self) -> Timing {
+        self.bus.ppu.timing()
+    }
+
+    /// Switch between the accurate and the fast emulation of the sample fetches of the DMC
+    ///
+    /// Accurate fetches (the default) halt the CPU for up to four cycles, and the halted read gets repeated,
+    /// which some games and test ROMs depend on. Fast fetches read the sample without affecting the CPU.
+    pub fn set_accurate_dmc_dma(&mut self, accurate: bool) {
+        self.bus.accurate_dmc_dma = accurate;
+    }
+
+    #[must_use]
+    pub fn accurate_dmc_dma(&self) -> bool {
+        self.bus.accurate_dmc_dma
+    }
+
+    /// Plug in the devices; Zappers replace the controllers in their ports
+    pub fn set_input_devices(&mut self, devices: InputDevices) {
+        self.bus.devices = devices;
+        self.bus.zappers = match devices {
+            InputDevices::Zapper => [None, Some(ZapperState::default())],
+            InputDevices::TwoZappers => [Some(ZapperState::default()); 2],
+            _ => [None; 2],
+        };
+    }
+
+    #[must_use]
+    pub fn input_devices(&self) -> InputDevices {
+        self.bus.devices
+    }
+
+    /// Whether someone is blowing into the microphone of the second Famicom controller
+    pub fn set_microphone(&mut self, active: bool) {
+        self.bus.microphone = active;
+    }
+
+    /// Family BASIC keyboard, read while it's the plugged in device
+    pub fn keyboard_mut(&mut self) -> &mut FamilyBasicKeyboard {
+        &mut self.bus.keyboard
+    }
+
+    /// Set the buttons held on the controller in the port (0 or 1, 2 and 3 for the ones of a four player adapter)
+    pub fn set_buttons(&mut self, port: usize, buttons: Buttons) {
+        self.bus.controllers[port].set_buttons(buttons);
+    }
+
+    /// Buttons held on the controller in the port (0 or 1)
+    #[must_use]
+    pub fn buttons(&self, port: usize) -> Buttons {
+        self.bus.controllers[port].buttons
+    }
+
+    /// Plug a Zapper into the port (usually 1) instead of the controller, or update the state of the plugged in one
+    pub fn set_zapper(&mut self, port: usize, state: ZapperState) {
+        self.bus.zappers[port] = Some(state);
+    }
+
+    /// Plug the controller back into the port
+    pub fn disconnect_zapper(&mut self, port: usize) {
+        self.bus.zappers[port] = None;
+    }
+
+    /// State of the Zapper plugged into the port, if there's one
+    #[must_use]
+    pub fn zapper(&self, port: usize) -> Option<ZapperState> {
+        self.bus.zappers[port]
+    }
+
+    /// Activate a cheat; it stays active across resets and loaded save states
+    pub fn add_cheat(&mut self, cheat: Cheat) {
+        self.bus.cheats.push(cheat);
+    }
+
+    /// Deactivate all cheats with the address, returning whether any was active
+    pub fn remove_cheat(&mut self, address: u16) -> bool {
+        let count = self.bus.cheats.len();
+        self.bus.cheats.retain(|cheat| cheat.address != address);
+        self.bus.cheats.len() != count
+    }
+
+    pub fn clear_cheats(&mut self) {
+        self.bus.cheats.clear();
+    }
+
+    /// Currently active cheats
+    #[must_use]
+    pub fn cheats(&self) -> &[Cheat] {
+        &self.bus.cheats
+    }
+
+    #[must_use]
+    pub fn cpu(&self) -> &Cpu {
+        &self.cpu
+    }
+
+    pub fn cpu_mut(&mut self) -> &mut Cpu {
+        &mut self.cpu
+    }
+
+    #[must_use]
+    pub fn ppu(&self) -> &Ppu {
+        &self.bus.ppu
+    }
+
+    pub fn ppu_mut(&mut self) -> &mut Ppu {
+        &mut self.bus.ppu
+    }
+
+    #[must_use]
+    pub fn apu(&self) -> &Apu {
+        &self.bus.apu
+    }
+
+    pub fn apu_mut(&mut self) -> &mut Apu {
+        &mut self.bus.apu
+    }
+
+    #[must_use]
+    pub fn cartridge(&self) -> &Cartridge {
+        &self.bus.cartridge
+    }
+
+    pub fn cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.bus.cartridge
+    }
+
+    /// Amount of disk sides of the Famicom Disk System, 0 for cartridges
+    #[must_use]
+    pub fn disk_sides(&self) -> usize {
+        self.bus
+            .cartridge
+            .mapper()
+            .disk_system()
+            .map_or(0, DiskSystem::sides)
+    }
+
+    /// Side in the drive of the Famicom Disk System, if any
+    #[must_use]
+    pub fn inserted_disk_side(&self) -> Option<usize> {
+        self.bus
+            .cartridge
+            .mapper()
+            .disk_system()
+            .and_then(DiskSystem::inserted_side)
+    }
+
+    /// Put a disk side into the drive of the Famicom Disk System, replacing the inserted one
+    ///
+    /// Switching sides takes a moment, so the game notices the previous side getting ejected.
+    /// Returns `false` if the console doesn't have a Disk System or the image doesn't have the side.
+    pub fn insert_disk(&mut self, side: usize) -> bool {
+        self.bus
+            .cartridge
+            .mapper_mut()
+            .disk_system_mut()
+            .is_some_and(|disk_system| disk_system.insert_disk(side))
+    }
+
+    pub fn eject_disk(&mut self) {
+        if let Some(disk_system) = self.bus.cartridge.mapper_mut().disk_system_mut() {
+            disk_system.eject_disk();
+        }
+    }
+
+    /// Disk image of the Famicom Disk System with everything the game saved to it
+    #[must_use]
+    pub fn disk_image(&self) -> Option<Fds<'static>> {
+        self.bus
+            .cartridge
+            .mapper()
+            .disk_system()
+            .map(DiskSystem::to_fds)
+    }
+
+    /// The 2 KiB of internal RAM
+    #[must_use]
+    pub fn ram(&self) -> &[u8; RAM_SIZE] {
+        &self.bus.ram
+    }
+
+    pub fn ram_mut(&mut self) -> &mut [u8; RAM_SIZE] {
+        &mut self.bus.ram
+    }
+
+    /// Read memory like the CPU would, without any side effects
+    ///
+    /// The registers of the PPU, the APU and the controllers can't be read without affecting them, so the last
+    /// value on the data bus is returned for them instead.
+    #[must_use]
+    pub fn peek(&self, address: u16) -> u8 {
+        let memory = self.bus.cartridge.mapper().memory();
+        match address {
+            0x0000..=0x1FFF => self.bus.ram[usize::from(address) % RAM_SIZE],
+            0x6000..=0x7FFF if !memory.prg_ram.is_empty() => memory.read_prg_ram(address),
+            // The RAM adapter of the Disk System extends the RAM up to `$DFFF`
+            0x8000..=0xDFFF if self.bus.cartridge.mapper().disk_system().is_some() => {
+                memory.read_prg_ram(address)
+            }
+            0x8000..=0xFFFF => self.peek_prg_rom(address),
+            _ => self.bus.open_bus,
+        }
+    }
+
+    // Byte of the PRG ROM bank mapped to the address, found through the memory map of the mapper
+    fn peek_prg_rom(&self, address: u16) -> u8 {
+        let offset = self.prg_rom_offset(address);
+        let prg_rom = &self.bus.cartridge.mapper().memory().prg_rom;
+        offset
+            .and_then(|offset| prg_rom.get(offset))
+            .copied()
+            .unwrap_or(self.bus.open_bus)
+    }
+
+    /// Offset into the PRG ROM of the byte mapped to the address, if it's mapped to PRG ROM
+    #[must_use]
+    pub fn prg_rom_offset(&self, address: u16) -> Option<usize> {
+        let prg_rom_size = self.bus.cartridge.mapper().memory().prg_rom.len();
+        self.bus
+            .cartridge
+            .memory_map()
+            .cpu
+            .iter()
+            .find(|region| region.addresses.contains(&address))
+            .and_then(|region| match region.kind {
+                RegionKind::PrgRom { bank, size, .. } if prg_rom_size > 0 => {
+                    let offset = usize::from(address - region.addresses.start());
+                    Some((bank * size + offset) % prg_rom_size)
+                }
+                _ => None,
+            })
+    }
+
+    /// Write memory like the CPU would, without clocking the other chips
+    ///
+    /// Writes to registers take effect like writes of the CPU, e.g. switching banks of the mapper.
+    pub fn poke(&mut self, address: u16, value: u8) {
+        self.bus.write_memory(address, value);
+        // A write to `$4014` isn't executed by the CPU, so there's nobody to halt for the DMA
+        self.bus.oam_dma_page = None;
+    }
+
+    // State of the components outside of the chips and the cartridge, without the RAM which gets compared on its own
+    pub(crate) fn system_state(&self) -> Vec<u8> {
+        let mut writer = StateWriter::new();
+        self.bus.save_state(&mut writer);
+
+        // The RAM comes first, prefixed with its length
+        let mut state = writer.into_bytes();
+        state.drain(..4 + RAM_SIZE);
+        state
+    }
+
+    /// Current frame as indices into the master palette
+    #[must_use]
+    pub fn framebuffer(&self) -> &[u8] {
+        self.bus.ppu.framebuffer()
+    }
+
+    /// Take the audio samples generated since the last call
+    pub fn take_samples(&mut self) -> Vec<f32> {
+        self.bus.apu.take_samples()
+    }
+}
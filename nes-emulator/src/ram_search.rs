@@ -0,0 +1,144 @@
+use {crate::Nes, alloc::vec::Vec, core::ops::Range, nes_cheats::Cheat};
+
+const RAM_ADDRESSES: Range<u16> = 0x0000..0x0800;
+const PRG_RAM_START: u16 = 0x6000;
+const PRG_RAM_WINDOW: usize = 0x2000;
+
+/// Condition the value of an address has to meet to stay in a [`RamSearch`]
+///
+/// The conditions without an operand compare the current value with the one of the previous search step.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Filter {
+    Equal(u8),
+    NotEqual(u8),
+    Greater(u8),
+    Less(u8),
+    Unchanged,
+    Changed,
+    Increased,
+    Decreased,
+    /// The value increased by exactly the amount, wrapping around at 256
+    IncreasedBy(u8),
+    /// The value decreased by exactly the amount, wrapping around at 256
+    DecreasedBy(u8),
+}
+
+impl Filter {
+    /// Whether the value meets the condition, given the value of the previous step
+    #[must_use]
+    pub fn matches(self, previous: u8, value: u8) -> bool {
+        match self {
+            Self::Equal(operand) => value == operand,
+            Self::NotEqual(operand) => value != operand,
+            Self::Greater(operand) => value > operand,
+            Self::Less(operand) => value < operand,
+            Self::Unchanged => value == previous,
+            Self::Changed => value != previous,
+            Self::Increased => value > previous,
+            Self::Decreased => value < previous,
+            Self::IncreasedBy(amount) => value == previous.wrapping_add(amount),
+            Self::DecreasedBy(amount) => value == previous.wrapping_sub(amount),
+        }
+    }
+}
+
+/// Address still matching all filters of a [`RamSearch`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Candidate {
+    pub address: u16,
+    /// Value at the last search step
+    pub value: u8,
+}
+
+impl Candidate {
+    /// Cheat freezing the address at its value, which is how trainers lock a found counter
+    #[must_use]
+    pub fn freeze(self) -> Cheat {
+        Cheat {
+            address: self.address,
+            value: self.value,
+            compare: None,
+        }
+    }
+}
+
+/// Search for the addresses of the internal RAM and PRG RAM holding a value, like the cheat finders of emulators
+///
+/// The search starts with a snapshot of every address. Each filter then drops the addresses whose value doesn't meet
+/// it and remembers the current values for the next step, so something like lives can be found by filtering for
+/// [`Filter::DecreasedBy`] after losing one. Only the first 8 KiB of PRG RAM are searched, the bank at `$6000`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RamSearch {
+    candidates: Vec<Candidate>,
+}
+
+impl RamSearch {
+    /// Start a search with every address and its current value
+    #[must_use]
+    pub fn new(nes: &Nes) -> Self {
+        let prg_ram_size = nes
+            .cartridge()
+            .mapper()
+            .memory()
+            .prg_ram
+            .len()
+            .min(PRG_RAM_WINDOW);
+        // The window is 8 KiB, which always fits
+        #[allow(clippy::cast_possible_truncation)]
+        let prg_ram_addresses = PRG_RAM_START..PRG_RAM_START + prg_ram_size as u16;
+
+        let candidates = RAM_ADDRESSES
+            .chain(prg_ram_addresses)
+            .map(|address| Candidate {
+                address,
+                value: nes.peek(address),
+            })
+            .collect();
+
+        Self { candidates }
+    }
+
+    /// Keep the addresses meeting the filter and update the values of all remaining ones, returning how many remain
+    pub fn filter(&mut self, nes: &Nes, filter: Filter) -> usize {
+        self.candidates.retain_mut(|candidate| {
+            let value = nes.peek(candidate.address);
+            let matches = filter.matches(candidate.value, value);
+            candidate.value = value;
+            matches
+        });
+
+        self.candidates.len()
+    }
+
+    /// Update the values compared against without dropping any addresses
+    pub fn update(&mut self, nes: &Nes) {
+        for candidate in &mut self.candidates {
+            candidate.value = nes.peek(candidate.address);
+        }
+    }
+
+    /// Drop an address from the results, like one which is known to be something else
+    pub fn exclude(&mut self, address: u16) {
+        self.candidates.retain(|candidate| candidate.address != address);
+    }
+
+    /// Remaining addresses together with their values, ordered by address
+    #[must_use]
+    pub fn candidates(&self) -> &[Candidate] {
+        &self.candidates
+    }
+
+    pub fn addresses(&self) -> impl Iterator<Item = u16> + '_ {
+        self.candidates.iter().map(|candidate| candidate.address)
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.candidates.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.candidates.is_empty()
+    }
+}
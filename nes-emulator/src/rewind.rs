@@ -0,0 +1,193 @@
+use {
+    crate::{Error, Nes},
+    alloc::{collections::VecDeque, vec::Vec},
+};
+
+/// History of save states to step back through, for the rewind feature of frontends
+///
+/// Only the newest state is kept as a whole. Every older one is stored as the difference to the state after it,
+/// which is small since most of the console's memory doesn't change from one frame to the next.
+/// Once the history is full, pushing a state drops the oldest one.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Rewind {
+    capacity: usize,
+    newest: Option<Vec<u8>>,
+    /// Older states, the oldest first
+    deltas: VecDeque<Vec<u8>>,
+}
+
+impl Rewind {
+    /// Create a history keeping up to the amount of states
+    ///
+    /// Frontends pushing a state every frame keep a minute of history with a capacity of 3600 (NTSC).
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            newest: None,
+            deltas: VecDeque::new(),
+        }
+    }
+
+    /// Maximum amount of states
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Amount of states in the history
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.deltas.len() + usize::from(self.newest.is_some())
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.newest.is_none()
+    }
+
+    /// Bytes taken up by the stored states
+    #[must_use]
+    pub fn memory_size(&self) -> usize {
+        self.newest.as_ref().map_or(0, Vec::len) + self.deltas.iter().map(Vec::len).sum::<usize>()
+    }
+
+    /// Remove all states
+    pub fn clear(&mut self) {
+        self.newest = None;
+        self.deltas.clear();
+    }
+
+    /// Add the current state of the console as the newest one
+    pub fn push(&mut self, nes: &Nes) {
+        self.push_state(nes.serialize_state());
+    }
+
+    /// Add a state created by [`Nes::serialize_state`] as the newest one
+    pub fn push_state(&mut self, state: Vec<u8>) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if let Some(previous) = self.newest.take() {
+            self.deltas.push_back(encode_delta(&previous, &state));
+        }
+        self.newest = Some(state);
+        while self.len() > self.capacity {
+            self.deltas.pop_front();
+        }
+    }
+
+    /// Restore the newest state and remove it from the history
+    ///
+    /// Returns `false` if the history is empty
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of [`Nes::deserialize_state`]; the history is left untouched then
+    pub fn rewind(&mut self, nes: &mut Nes) -> Result<bool, Error> {
+        let Some(state) = self.newest.take() else {
+            return Ok(false);
+        };
+
+        if let Err(err) = nes.deserialize_state(&state) {
+            self.newest = Some(state);
+            return Err(err);
+        }
+
+        self.newest = self
+            .deltas
+            .pop_back()
+            .map(|delta| decode_delta(&delta, &state));
+        Ok(true)
+    }
+}
+
+// The difference of a state to the next newer one is their XOR, with the runs of zeros
+// (the unchanged bytes) compressed. It consists of the length of the state followed by
+// pairs of the amount of unchanged bytes and the changed bytes, prefixed by their amount.
+fn encode_delta(state: &[u8], newer: &[u8]) -> Vec<u8> {
+    let mut delta = Vec::new();
+    write_length(&mut delta, state.len());
+
+    let difference: Vec<u8> = state
+        .iter()
+        .enumerate()
+        .map(|(index, byte)| byte ^ newer.get(index).copied().unwrap_or(0))
+        .collect();
+
+    let mut position = 0;
+    while position < difference.len() {
+        let unchanged = difference[position..]
+            .iter()
+            .take_while(|&&byte| byte == 0)
+            .count();
+        position += unchanged;
+
+        let changed = difference[position..]
+            .iter()
+            .take_while(|&&byte| byte != 0)
+            .count();
+        write_length(&mut delta, unchanged);
+        write_length(&mut delta, changed);
+        delta.extend_from_slice(&difference[position..position + changed]);
+        position += changed;
+    }
+
+    delta
+}
+
+fn decode_delta(delta: &[u8], newer: &[u8]) -> Vec<u8> {
+    let mut bytes = delta.iter().copied();
+    let length = read_length(&mut bytes);
+
+    let mut state: Vec<u8> = (0..length)
+        .map(|index| newer.get(index).copied().unwrap_or(0))
+        .collect();
+
+    let mut position = 0;
+    while position < length {
+        position += read_length(&mut bytes);
+        let changed = read_length(&mut bytes);
+        if changed == 0 {
+            break;
+        }
+
+        for (byte, change) in state
+            .iter_mut()
+            .skip(position)
+            .zip(bytes.by_ref().take(changed))
+        {
+            *byte ^= change;
+        }
+        position += changed;
+    }
+
+    state
+}
+
+// Lengths are stored in 7 bits per byte, with the highest bit set on all but the last byte
+fn write_length(delta: &mut Vec<u8>, mut length: usize) {
+    while length >= 0x80 {
+        // Truncation keeps the lowest 7 bits, which are the ones getting written
+        #[allow(clippy::cast_possible_truncation)]
+        delta.push(length as u8 | 0x80);
+        length >>= 7;
+    }
+    #[allow(clippy::cast_possible_truncation)]
+    delta.push(length as u8);
+}
+
+fn read_length(bytes: &mut impl Iterator<Item = u8>) -> usize {
+    let mut length = 0;
+    let mut shift = 0;
+    for byte in bytes {
+        length |= usize::from(byte & 0x7F) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 || shift >= usize::BITS {
+            break;
+        }
+    }
+
+    length
+}
@@ -0,0 +1,343 @@
+use {
+    crate::{Error, Nes},
+    alloc::{
+        format,
+        string::{String, ToString},
+        vec::Vec,
+    },
+    core::fmt,
+    nes_mapper::Region,
+    nes_state::{Savestate, StateWriter},
+};
+
+// Runs of differing bytes closer than this get reported as one range
+const MERGE_DISTANCE: usize = 4;
+// Bytes of a range shown in a difference
+const SHOWN_BYTES: usize = 8;
+
+/// Part of the console a difference was found in
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Subsystem {
+    Cpu,
+    Ppu,
+    Apu,
+    /// Bank layout, mirroring and RAMs of the cartridge
+    Mapper,
+    /// The internal RAM of the console
+    Ram,
+    /// Controllers and the bus between the chips
+    System,
+}
+
+impl fmt::Display for Subsystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Cpu => "CPU",
+            Self::Ppu => "PPU",
+            Self::Apu => "APU",
+            Self::Mapper => "Mapper",
+            Self::Ram => "RAM",
+            Self::System => "System",
+        })
+    }
+}
+
+/// Field with different values in two states
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Difference {
+    pub subsystem: Subsystem,
+    /// Name of the register, or the memory and the address range of the differing bytes
+    pub field: String,
+    pub first: String,
+    pub second: String,
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}: {} -> {}",
+            self.subsystem, self.field, self.first, self.second
+        )
+    }
+}
+
+/// Fields which differ between two states of the console, ordered by subsystem
+///
+/// Useful for finding the first point where a replay desyncs from a recording: the CPU registers, the scroll
+/// position of the PPU, the bank layout of the mapper or the RAM ranges show what diverged.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct StateDiff {
+    pub differences: Vec<Difference>,
+}
+
+impl StateDiff {
+    /// Compare two running consoles of the same cartridge
+    #[must_use]
+    pub fn between(first: &Nes, second: &Nes) -> Self {
+        Self::from_summaries(&Summary::capture(first), &Summary::capture(second))
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.differences.is_empty()
+    }
+
+    fn from_summaries(first: &Summary, second: &Summary) -> Self {
+        let mut diff = Self::default();
+
+        for (first, second) in first.registers.iter().zip(&second.registers) {
+            if first.2 != second.2 {
+                diff.push(
+                    first.0,
+                    first.1.to_string(),
+                    first.2.clone(),
+                    second.2.clone(),
+                );
+            }
+        }
+
+        for (first, second) in first.memories.iter().zip(&second.memories) {
+            diff.compare_memory(first, second);
+        }
+
+        let layout_differs = diff.compare_layout(&first.layout, &second.layout);
+        // The state of the mapper includes its RAMs; other differences mean internal registers like IRQ counters
+        let has_mapper_differences = layout_differs
+            || diff
+                .differences
+                .iter()
+                .any(|difference| difference.subsystem == Subsystem::Mapper);
+        for (first, second) in first.states.iter().zip(&second.states) {
+            if first.0 != Subsystem::Mapper || !has_mapper_differences {
+                diff.compare_memory(first, second);
+            }
+        }
+
+        diff.differences
+            .sort_by_key(|difference| difference.subsystem);
+        diff
+    }
+
+    fn push(&mut self, subsystem: Subsystem, field: String, first: String, second: String) {
+        self.differences.push(Difference {
+            subsystem,
+            field,
+            first,
+            second,
+        });
+    }
+
+    fn compare_memory(&mut self, first: &Memory, second: &Memory) {
+        let (subsystem, name, base) = (first.0, first.1, first.2);
+        let (first, second) = (&first.3, &second.3);
+        if first.len() != second.len() {
+            self.push(
+                subsystem,
+                format!("{name} size"),
+                first.len().to_string(),
+                second.len().to_string(),
+            );
+            return;
+        }
+
+        let mut differing = (0..first.len()).filter(|&offset| first[offset] != second[offset]);
+        let Some(mut start) = differing.next() else {
+            return;
+        };
+        let mut end = start;
+
+        let mut ranges = Vec::new();
+        for offset in differing {
+            if offset - end > MERGE_DISTANCE {
+                ranges.push((start, end));
+                start = offset;
+            }
+            end = offset;
+        }
+        ranges.push((start, end));
+
+        for (start, end) in ranges {
+            let mut field = if start == end {
+                format!("${:04X}", base + start)
+            } else {
+                format!("${:04X}-${:04X}", base + start, base + end)
+            };
+            if !name.is_empty() {
+                field = format!("{name} {field}");
+            }
+            self.push(
+                subsystem,
+                field,
+                hex_bytes(&first[start..=end]),
+                hex_bytes(&second[start..=end]),
+            );
+        }
+    }
+
+    // Returns whether the layouts differ
+    fn compare_layout(&mut self, first: &[Region], second: &[Region]) -> bool {
+        let mut differs = false;
+        for index in 0..first.len().max(second.len()) {
+            let (first, second) = (first.get(index), second.get(index));
+            if first == second {
+                continue;
+            }
+
+            differs = true;
+            let addresses = first.or(second).map(|region| &region.addresses);
+            let field = addresses.map_or_else(String::new, |addresses| {
+                format!("${:04X}-${:04X}", addresses.start(), addresses.end())
+            });
+            let kind = |region: Option<&Region>| {
+                region.map_or_else(|| String::from("(none)"), |region| region.kind.to_string())
+            };
+            self.push(Subsystem::Mapper, field, kind(first), kind(second));
+        }
+
+        differs
+    }
+}
+
+impl fmt::Display for StateDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return f.write_str("The states are identical");
+        }
+
+        for difference in &self.differences {
+            writeln!(f, "{difference}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Nes {
+    /// Compare two save states of this console's cartridge
+    ///
+    /// Both states get loaded into the console one after another; the console is restored afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if one of the states can't be loaded
+    pub fn diff_states(&mut self, first: &[u8], second: &[u8]) -> Result<StateDiff, Error> {
+        let backup = self.serialize_state();
+
+        let result = self.deserialize_state(first).and_then(|()| {
+            let first = Summary::capture(self);
+            self.deserialize_state(second)?;
+            Ok(first)
+        });
+        let second = Summary::capture(self);
+        // The backup was just created from the same console, so restoring it can't fail
+        let _ = self.deserialize_state(&backup);
+
+        Ok(StateDiff::from_summaries(&result?, &second))
+    }
+}
+
+// Subsystem, name (empty if the subsystem names the memory already), base address and contents
+type Memory = (Subsystem, &'static str, usize, Vec<u8>);
+
+// Values of a console reduced to what gets compared
+struct Summary {
+    registers: Vec<(Subsystem, &'static str, String)>,
+    memories: Vec<Memory>,
+    layout: Vec<Region>,
+    // Serialized components without accessors for their fields
+    states: Vec<Memory>,
+}
+
+impl Summary {
+    fn capture(nes: &Nes) -> Self {
+        let cpu = nes.cpu();
+        let ppu = nes.ppu();
+        let registers = ppu.registers();
+        let cartridge = nes.cartridge();
+        let memory = cartridge.mapper().memory();
+
+        let hex8 = |value: u8| format!("${value:02X}");
+        let hex16 = |value: u16| format!("${value:04X}");
+        let registers = Vec::from([
+            (Subsystem::Cpu, "PC", hex16(cpu.pc)),
+            (Subsystem::Cpu, "A", hex8(cpu.a)),
+            (Subsystem::Cpu, "X", hex8(cpu.x)),
+            (Subsystem::Cpu, "Y", hex8(cpu.y)),
+            (Subsystem::Cpu, "S", hex8(cpu.s)),
+            (Subsystem::Cpu, "P", hex8(cpu.p.0)),
+            (Subsystem::Cpu, "cycles", cpu.cycles().to_string()),
+            (Subsystem::Ppu, "frame", ppu.frame().to_string()),
+            (Subsystem::Ppu, "scanline", ppu.scanline().to_string()),
+            (Subsystem::Ppu, "dot", ppu.dot().to_string()),
+            (Subsystem::Ppu, "PPUCTRL", hex8(registers.ctrl)),
+            (Subsystem::Ppu, "PPUMASK", hex8(registers.mask)),
+            (Subsystem::Ppu, "PPUSTATUS", hex8(registers.status)),
+            (Subsystem::Ppu, "OAMADDR", hex8(registers.oam_address)),
+            (Subsystem::Ppu, "v", hex16(registers.v)),
+            (Subsystem::Ppu, "t", hex16(registers.t)),
+            (Subsystem::Ppu, "fine X", registers.fine_x.to_string()),
+            (Subsystem::Ppu, "w", registers.write_toggle.to_string()),
+            (
+                Subsystem::Mapper,
+                "mirroring",
+                format!("{:?}", cartridge.mapper().mirroring()),
+            ),
+        ]);
+
+        let mut memories = Vec::from([
+            (Subsystem::Ram, "", 0x0000, nes.ram().to_vec()),
+            (Subsystem::Ppu, "OAM", 0x00, ppu.oam().to_vec()),
+            (
+                Subsystem::Ppu,
+                "palette",
+                0x3F00,
+                ppu.palette_ram().to_vec(),
+            ),
+            (
+                Subsystem::Ppu,
+                "nametables",
+                0x2000,
+                cartridge.vram().to_vec(),
+            ),
+            (Subsystem::Mapper, "PRG RAM", 0x6000, memory.prg_ram.clone()),
+        ]);
+        if memory.chr_is_ram {
+            memories.push((Subsystem::Mapper, "CHR RAM", 0x0000, memory.chr.clone()));
+        }
+
+        let map = cartridge.memory_map();
+        let layout = map.cpu.into_iter().chain(map.ppu).collect();
+
+        let serialize = |component: &dyn Savestate| {
+            let mut writer = StateWriter::new();
+            component.save_state(&mut writer);
+            writer.into_bytes()
+        };
+        let states = Vec::from([
+            (Subsystem::Apu, "state", 0, serialize(nes.apu())),
+            (Subsystem::Mapper, "state", 0, serialize(cartridge.mapper())),
+            (Subsystem::System, "state", 0, nes.system_state()),
+        ]);
+
+        Self {
+            registers,
+            memories,
+            layout,
+            states,
+        }
+    }
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    let mut text = bytes
+        .iter()
+        .take(SHOWN_BYTES)
+        .map(|byte| format!("{byte:02X}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    if bytes.len() > SHOWN_BYTES {
+        text.push_str(" ...");
+    }
+    text
+}
@@ -0,0 +1,113 @@
+use {
+    ines_parser::{Header, Ines, VramLayout},
+    mos6502_cpu::{compare::Column, TraceComparator},
+    nes_emulator::{Harness, InputScript},
+    std::{env, fs, path::PathBuf},
+};
+
+const SAMPLE_RATE: u32 = 44_100;
+
+// NROM ROM with the program at $8000, which all vectors point to
+fn nrom(program: &[u8]) -> Vec<u8> {
+    let mut prg_rom = vec![0xEA; 0x4000];
+    prg_rom[..program.len()].copy_from_slice(program);
+    prg_rom[0x3FFA..].copy_from_slice(&[0x00, 0x80, 0x00, 0x80, 0x00, 0x80]);
+
+    let mut rom = Header::new(0x4000, 0x2000, 0, VramLayout::HorizontalMirroring)
+        .to_bytes()
+        .to_vec();
+    rom.extend_from_slice(&prg_rom);
+    rom.extend_from_slice(&[0; 0x2000]);
+    rom
+}
+
+fn harness(rom: &[u8]) -> Harness {
+    let ines = Ines::from_bytes(rom).unwrap();
+    Harness::from_ines(&ines, SAMPLE_RATE, InputScript::new()).unwrap()
+}
+
+// Trace line of the next instruction, reading the memory it accesses from the RAM and the cartridge
+fn trace(harness: &mut Harness) -> String {
+    let nes = harness.nes_mut();
+    let cpu = nes.cpu().clone();
+
+    cpu.trace(|address| match address {
+        0x0000..=0x1FFF => nes.ram()[usize::from(address) % 0x800],
+        0x4020..=0xFFFF => nes.cartridge_mut().cpu_read(address),
+        _ => 0,
+    })
+    .to_string()
+}
+
+// Step through the program, comparing every instruction against the lines of the log
+fn compare_trace<'a, I: IntoIterator<Item = &'a str>>(harness: &mut Harness, log: I) {
+    // The trace of the CPU doesn't include the position of the PPU
+    let mut comparator = TraceComparator::new().ignore(Column::Ppu);
+
+    for expected in log {
+        if let Err(divergence) = comparator.check(expected, &trace(harness)) {
+            panic!("{}", divergence);
+        }
+
+        harness.step().unwrap();
+    }
+}
+
+#[test]
+fn trace_program() {
+    let mut harness = harness(&nrom(&[
+        0xA2, 0x00, // LDX #$00
+        0xE8, // INX
+        0x86, 0x10, // STX $10
+        0xE0, 0x02, // CPX #$02
+        0xD0, 0xF9, // BNE $8002
+        0x4C, 0x09, 0x80, // JMP $8009
+    ]));
+
+    compare_trace(
+        &mut harness,
+        "\
+8000  A2 00     LDX #$00                        A:00 X:00 Y:00 P:24 SP:FD CYC:7
+8002  E8        INX                             A:00 X:00 Y:00 P:26 SP:FD CYC:9
+8003  86 10     STX $10 = 00                    A:00 X:01 Y:00 P:24 SP:FD CYC:11
+8005  E0 02     CPX #$02                        A:00 X:01 Y:00 P:24 SP:FD CYC:14
+8007  D0 F9     BNE $8002                       A:00 X:01 Y:00 P:A4 SP:FD CYC:16
+8002  E8        INX                             A:00 X:01 Y:00 P:A4 SP:FD CYC:19
+8003  86 10     STX $10 = 01                    A:00 X:02 Y:00 P:24 SP:FD CYC:21
+8005  E0 02     CPX #$02                        A:00 X:02 Y:00 P:24 SP:FD CYC:24
+8007  D0 F9     BNE $8002                       A:00 X:02 Y:00 P:27 SP:FD CYC:26
+8009  4C 09 80  JMP $8009                       A:00 X:02 Y:00 P:27 SP:FD CYC:28
+8009  4C 09 80  JMP $8009                       A:00 X:02 Y:00 P:27 SP:FD CYC:31"
+            .lines(),
+    );
+    assert_eq!(harness.nes().ram()[0x10], 2);
+}
+
+// Kevin Horton's CPU test ROM isn't redistributable, so it has to be put next to its log
+// (or pointed to with `NESTEST_ROM` and `NESTEST_LOG`) and the test run with `cargo test -- --ignored`
+#[test]
+#[ignore = "needs nestest.nes and nestest.log in nes-emulator/tests/roms"]
+fn nestest() {
+    let path = |variable: &str, file_name: &str| {
+        env::var_os(variable).map_or_else(
+            || {
+                [env!("CARGO_MANIFEST_DIR"), "tests", "roms", file_name]
+                    .iter()
+                    .collect()
+            },
+            PathBuf::from,
+        )
+    };
+    let rom = fs::read(path("NESTEST_ROM", "nestest.nes")).unwrap();
+    let log = fs::read_to_string(path("NESTEST_LOG", "nestest.log")).unwrap();
+
+    let mut harness = harness(&rom);
+    // The automated mode starts at $C000 instead of the reset vector
+    harness.nes_mut().cpu_mut().pc = 0xC000;
+
+    // The CPU only implements the official opcodes, whose tests end where the log marks the first unofficial one
+    let official = log.lines().take_while(|line| !line.contains('*'));
+    compare_trace(&mut harness, official);
+    // Result of the official opcodes, zero if every test passed
+    assert_eq!(harness.nes().ram()[0x02], 0);
+}
@@ -0,0 +1,75 @@
+use {
+    ines_parser::Ines,
+    nes_emulator::{Harness, InputScript, Nes},
+};
+
+const SAMPLE_RATE: u32 = 44_100;
+
+// NROM ROM counting up at $10 in the main loop and at $11 in the NMI handler, which runs once per frame
+fn counter_rom() -> Vec<u8> {
+    let program = [
+        0xA9, 0x80, // LDA #$80
+        0x8D, 0x00, 0x20, // STA $2000
+        0xE6, 0x10, // INC $10
+        0x4C, 0x05, 0x80, // JMP $8005
+        0xEA, 0xEA, 0xEA, 0xEA, 0xEA, 0xEA, // Padding up to $8010
+        0xE6, 0x11, // INC $11
+        0x40, // RTI
+    ];
+    let mut prg_rom = vec![0xEA; 0x4000];
+    prg_rom[..program.len()].copy_from_slice(&program);
+    // NMI at $8010, reset and IRQ at $8000
+    prg_rom[0x3FFA..].copy_from_slice(&[0x10, 0x80, 0x00, 0x80, 0x00, 0x80]);
+
+    // One 16 KiB PRG ROM bank and one 8 KiB CHR ROM bank
+    let mut rom = b"NES\x1A\x01\x01".to_vec();
+    rom.resize(16, 0);
+    rom.extend_from_slice(&prg_rom);
+    rom.extend_from_slice(&[0; 0x2000]);
+    rom
+}
+
+fn harness(rom: &[u8]) -> Harness {
+    let ines = Ines::from_bytes(rom).unwrap();
+    Harness::from_ines(&ines, SAMPLE_RATE, InputScript::new()).unwrap()
+}
+
+#[test]
+fn state_round_trip() {
+    let rom = counter_rom();
+    let mut harness = harness(&rom);
+    harness.run_frames(3).unwrap();
+    assert!(harness.nes().ram()[0x11] >= 2);
+
+    let state = harness.nes().serialize_state();
+    let mut restored = self::harness(&rom);
+    restored.nes_mut().deserialize_state(&state).unwrap();
+    assert_eq!(restored.nes().serialize_state(), state);
+
+    // Both consoles carry on exactly the same way
+    harness.run_frames(2).unwrap();
+    restored.run_frames(2).unwrap();
+    assert_eq!(
+        restored.nes().serialize_state(),
+        harness.nes().serialize_state()
+    );
+    assert_eq!(restored.snapshot(), harness.snapshot());
+}
+
+#[test]
+fn state_rejects_garbage() {
+    let rom = counter_rom();
+    let mut harness = harness(&rom);
+    harness.run_frames(1).unwrap();
+    let state = harness.nes().serialize_state();
+
+    let mut console = Nes::from_ines(&Ines::from_bytes(&rom).unwrap(), SAMPLE_RATE).unwrap();
+    let before = console.serialize_state();
+    assert!(console
+        .deserialize_state(&state[..state.len() / 2])
+        .is_err());
+    assert!(console.deserialize_state(b"not a state").is_err());
+
+    // A rejected state leaves the console untouched
+    assert_eq!(console.serialize_state(), before);
+}
@@ -0,0 +1,11 @@
+// Regenerates the C header with the `header` feature, the checked in one is used otherwise
+fn main() {
+    #[cfg(feature = "header")]
+    {
+        let crate_dir = std::env::var("CARGO_MANIFEST_DIR").expect("Cargo sets the manifest directory");
+
+        cbindgen::generate(&crate_dir)
+            .expect("Failed to generate the C header")
+            .write_to_file(std::path::Path::new(&crate_dir).join("include/nes_ffi.h"));
+    }
+}
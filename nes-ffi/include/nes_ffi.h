@@ -0,0 +1,176 @@
+#ifndef NES_FFI_H
+#define NES_FFI_H
+
+/* Generated with cbindgen from the sources of nes-ffi (cargo build -p nes-ffi --features header), don't edit it by hand */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#define NES_VRAM_LAYOUT_HORIZONTAL 0
+
+#define NES_VRAM_LAYOUT_VERTICAL 1
+
+#define NES_VRAM_LAYOUT_FOUR_SCREEN 2
+
+#define NES_TIMING_NTSC 0
+
+#define NES_TIMING_PAL 1
+
+#define NES_TIMING_MULTI_REGION 2
+
+#define NES_TIMING_DENDY 3
+
+// One byte per pixel containing the 2-bit palette index (the palette is ignored)
+#define NES_PIXEL_FORMAT_INDEXED 0
+
+// Three bytes per pixel (red, green, blue)
+#define NES_PIXEL_FORMAT_RGB8 1
+
+// Four bytes per pixel (red, green, blue, alpha), always fully opaque
+#define NES_PIXEL_FORMAT_RGBA8 2
+
+// Four bytes per pixel (blue, green, red, alpha), always fully opaque
+#define NES_PIXEL_FORMAT_BGRA8 3
+
+// Result of the functions, one of the `NES_STATUS_*` constants
+typedef unsigned int NesStatus;
+
+// Parsed header of an INES or NES 2.0 ROM
+typedef struct NesHeader {
+  size_t prg_rom_size;
+  size_t chr_rom_size;
+  // Size of the volatile PRG RAM
+  size_t prg_ram_size;
+  // Size of the battery-backed PRG RAM
+  size_t prg_nvram_size;
+  // Size of the volatile CHR RAM
+  size_t chr_ram_size;
+  // Size of the battery-backed CHR RAM
+  size_t chr_nvram_size;
+  // One of the `NES_VRAM_LAYOUT_*` constants
+  unsigned int vram_layout;
+  // One of the `NES_TIMING_*` constants; INES 1 headers only tell NTSC and PAL apart
+  unsigned int timing;
+  uint8_t mapper;
+  // Submapper number (NES 2.0 only)
+  uint8_t submapper;
+  // Input device the game expects, numbered like on the nesdev wiki (NES 2.0 only)
+  uint8_t default_expansion_device;
+  bool is_nes2;
+  bool has_battery;
+  bool has_trainer;
+} NesHeader;
+
+// Location of a section of the ROM file
+typedef struct NesSection {
+  // Offset from the start of the file
+  size_t offset;
+  // Size in bytes, 0 if the ROM doesn't have the section
+  size_t size;
+} NesSection;
+
+// Locations of the sections following the header
+typedef struct NesSections {
+  struct NesSection trainer;
+  struct NesSection prg_rom;
+  // Empty for games using CHR RAM
+  struct NesSection chr_rom;
+} NesSections;
+
+typedef struct NesColour {
+  uint8_t r;
+  uint8_t g;
+  uint8_t b;
+} NesColour;
+
+#define NES_STATUS_OK 0
+
+// A required pointer was null
+#define NES_STATUS_NULL_POINTER 1
+
+// The data doesn't start with the magic bytes of an INES ROM
+#define NES_STATUS_INVALID_MAGIC 2
+
+// The data ended before the end of the header or of the ROMs it describes
+#define NES_STATUS_UNEXPECTED_EOF 3
+
+// The output buffer is too small for the pixels
+#define NES_STATUS_BUFFER_TOO_SMALL 4
+
+// The sprites to decode are out of bounds of the CHR data
+#define NES_STATUS_OUT_OF_BOUNDS 5
+
+// An argument has an unknown value, like a pixel format which doesn't exist
+#define NES_STATUS_INVALID_ARGUMENT 6
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Description of a status, as a static null-terminated string
+const char *nes_status_message(NesStatus status);
+
+// Parse the header of a ROM, which only needs the first 16 bytes of the file
+//
+// # Safety
+//
+// `data` has to point to `size` readable bytes and `header` to a writable `NesHeader`
+NesStatus nes_parse_header(const uint8_t *data, size_t size, struct NesHeader *header);
+
+// Locate the trainer, PRG ROM and CHR ROM of a ROM, checking that the file contains all of them
+//
+// # Safety
+//
+// `data` has to point to `size` readable bytes and `sections` to a writable `NesSections`
+NesStatus nes_rom_sections(const uint8_t *data, size_t size, struct NesSections *sections);
+
+// Amount of 8x8 sprites in the CHR data (16 bytes each)
+size_t nes_sprite_count(size_t chr_size);
+
+// Colour of the NES master palette (only the lower six bits of the index are taken into account)
+struct NesColour nes_master_palette_colour(uint8_t index);
+
+// Size in bytes of a buffer holding `count` sprites laid out `sprites_per_row` sprites wide, 0 for unknown pixel formats
+size_t nes_sprites_buffer_size(size_t count,
+                               size_t sprites_per_row,
+                               unsigned int format);
+
+// Decode one sprite into 8x8 pixels in the pixel format, applying the palette of four colours
+//
+// # Safety
+//
+// `chr_data` has to point to `chr_size` readable bytes, `buffer` to `buffer_size` writable bytes,
+// and `palette` to four colours (it may be null for `NES_PIXEL_FORMAT_INDEXED`)
+NesStatus nes_decode_sprite(const uint8_t *chr_data,
+                            size_t chr_size,
+                            size_t index,
+                            const struct NesColour *palette,
+                            unsigned int format,
+                            uint8_t *buffer,
+                            size_t buffer_size);
+
+// Decode `count` sprites starting at `first` into one image which is `sprites_per_row` sprites wide
+//
+// The image is `sprites_per_row * 8` pixels wide and `ceil(count / sprites_per_row) * 8` pixels high,
+// [`nes_sprites_buffer_size`] returns the size of the buffer it needs. Empty spaces in the last row are left untouched.
+//
+// # Safety
+//
+// `chr_data` has to point to `chr_size` readable bytes, `buffer` to `buffer_size` writable bytes,
+// and `palette` to four colours (it may be null for `NES_PIXEL_FORMAT_INDEXED`)
+NesStatus nes_render_sprites(const uint8_t *chr_data,
+                             size_t chr_size,
+                             size_t first,
+                             size_t count,
+                             size_t sprites_per_row,
+                             const struct NesColour *palette,
+                             unsigned int format,
+                             uint8_t *buffer,
+                             size_t buffer_size);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* NES_FFI_H */
@@ -0,0 +1,81 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+//!
+//! C bindings to `ines-parser` and `lemonade`
+//!
+//! Every function works on memory owned by the caller: ROMs and CHR data are passed as a pointer and a size,
+//! results are written through output pointers. Nothing is allocated, so nothing has to be freed.
+//! Functions which can fail return a [`NesStatus`], `NES_STATUS_OK` on success.
+//!
+
+use std::os::raw::{c_char, c_uint};
+
+mod rom;
+mod sprite;
+
+pub use {
+    rom::{
+        nes_parse_header, nes_rom_sections, NesHeader, NesSection, NesSections, NES_TIMING_DENDY,
+        NES_TIMING_MULTI_REGION, NES_TIMING_NTSC, NES_TIMING_PAL, NES_VRAM_LAYOUT_FOUR_SCREEN,
+        NES_VRAM_LAYOUT_HORIZONTAL, NES_VRAM_LAYOUT_VERTICAL,
+    },
+    sprite::{
+        nes_decode_sprite, nes_master_palette_colour, nes_render_sprites, nes_sprite_count,
+        nes_sprites_buffer_size, NesColour, NES_PIXEL_FORMAT_BGRA8, NES_PIXEL_FORMAT_INDEXED,
+        NES_PIXEL_FORMAT_RGB8, NES_PIXEL_FORMAT_RGBA8,
+    },
+};
+
+/// Result of the functions, one of the `NES_STATUS_*` constants
+pub type NesStatus = c_uint;
+
+pub const NES_STATUS_OK: NesStatus = 0;
+/// A required pointer was null
+pub const NES_STATUS_NULL_POINTER: NesStatus = 1;
+/// The data doesn't start with the magic bytes of an INES ROM
+pub const NES_STATUS_INVALID_MAGIC: NesStatus = 2;
+/// The data ended before the end of the header or of the ROMs it describes
+pub const NES_STATUS_UNEXPECTED_EOF: NesStatus = 3;
+/// The output buffer is too small for the pixels
+pub const NES_STATUS_BUFFER_TOO_SMALL: NesStatus = 4;
+/// The sprites to decode are out of bounds of the CHR data
+pub const NES_STATUS_OUT_OF_BOUNDS: NesStatus = 5;
+/// An argument has an unknown value, like a pixel format which doesn't exist
+pub const NES_STATUS_INVALID_ARGUMENT: NesStatus = 6;
+
+/// Description of a status, as a static null-terminated string
+#[no_mangle]
+pub extern "C" fn nes_status_message(status: NesStatus) -> *const c_char {
+    let message: &'static str = match status {
+        NES_STATUS_OK => "Success\0",
+        NES_STATUS_NULL_POINTER => "A required pointer was null\0",
+        NES_STATUS_INVALID_MAGIC => "The data isn't an INES ROM\0",
+        NES_STATUS_UNEXPECTED_EOF => "The data ended before the end of the ROM\0",
+        NES_STATUS_BUFFER_TOO_SMALL => "The output buffer is too small\0",
+        NES_STATUS_OUT_OF_BOUNDS => "The sprites are out of bounds\0",
+        NES_STATUS_INVALID_ARGUMENT => "Invalid argument\0",
+        _ => "Unknown status\0",
+    };
+
+    message.as_ptr().cast()
+}
+
+fn ines_status(err: &ines_parser::Error) -> NesStatus {
+    match err {
+        ines_parser::Error::MagicBytesMismatch(_) => NES_STATUS_INVALID_MAGIC,
+        // Everything else means the data is truncated
+        _ => NES_STATUS_UNEXPECTED_EOF,
+    }
+}
+
+fn lemonade_status(err: &lemonade::Error) -> NesStatus {
+    match err {
+        lemonade::Error::BufferTooSmall { .. } => NES_STATUS_BUFFER_TOO_SMALL,
+        _ => NES_STATUS_INVALID_ARGUMENT,
+    }
+}
+
+// Borrow the memory of the caller, `None` if the pointer is null
+unsafe fn bytes<'a>(data: *const u8, size: usize) -> Option<&'a [u8]> {
+    (!data.is_null()).then(|| std::slice::from_raw_parts(data, size))
+}
@@ -0,0 +1,162 @@
+use {
+    crate::{bytes, ines_status, NesStatus, NES_STATUS_NULL_POINTER, NES_STATUS_OK},
+    ines_parser::{Header, Ines, Timing, VramLayout},
+    std::os::raw::c_uint,
+};
+
+// The sections follow the 16 byte header in the order trainer, PRG ROM, CHR ROM
+const HEADER_SIZE: usize = 16;
+
+pub const NES_VRAM_LAYOUT_HORIZONTAL: c_uint = 0;
+pub const NES_VRAM_LAYOUT_VERTICAL: c_uint = 1;
+pub const NES_VRAM_LAYOUT_FOUR_SCREEN: c_uint = 2;
+
+pub const NES_TIMING_NTSC: c_uint = 0;
+pub const NES_TIMING_PAL: c_uint = 1;
+pub const NES_TIMING_MULTI_REGION: c_uint = 2;
+pub const NES_TIMING_DENDY: c_uint = 3;
+
+/// Parsed header of an INES or NES 2.0 ROM
+#[repr(C)]
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NesHeader {
+    pub prg_rom_size: usize,
+    pub chr_rom_size: usize,
+    /// Size of the volatile PRG RAM
+    pub prg_ram_size: usize,
+    /// Size of the battery-backed PRG RAM
+    pub prg_nvram_size: usize,
+    /// Size of the volatile CHR RAM
+    pub chr_ram_size: usize,
+    /// Size of the battery-backed CHR RAM
+    pub chr_nvram_size: usize,
+    /// One of the `NES_VRAM_LAYOUT_*` constants
+    pub vram_layout: c_uint,
+    /// One of the `NES_TIMING_*` constants; INES 1 headers only tell NTSC and PAL apart
+    pub timing: c_uint,
+    pub mapper: u8,
+    /// Submapper number (NES 2.0 only)
+    pub submapper: u8,
+    /// Input device the game expects, numbered like on the nesdev wiki (NES 2.0 only)
+    pub default_expansion_device: u8,
+    pub is_nes2: bool,
+    pub has_battery: bool,
+    pub has_trainer: bool,
+}
+
+impl From<&Header> for NesHeader {
+    fn from(header: &Header) -> Self {
+        Self {
+            prg_rom_size: header.prg_rom_size,
+            chr_rom_size: header.chr_rom_size,
+            prg_ram_size: header.prg_ram_size,
+            prg_nvram_size: header.prg_nvram_size,
+            chr_ram_size: header.chr_ram_size,
+            chr_nvram_size: header.chr_nvram_size,
+            vram_layout: match header.vram_layout {
+                VramLayout::HorizontalMirroring => NES_VRAM_LAYOUT_HORIZONTAL,
+                VramLayout::VerticalMirroring => NES_VRAM_LAYOUT_VERTICAL,
+                VramLayout::FourScreen => NES_VRAM_LAYOUT_FOUR_SCREEN,
+            },
+            timing: match header.timing {
+                Timing::Ntsc => NES_TIMING_NTSC,
+                Timing::Pal => NES_TIMING_PAL,
+                Timing::MultiRegion => NES_TIMING_MULTI_REGION,
+                Timing::Dendy => NES_TIMING_DENDY,
+            },
+            mapper: header.mapper_number,
+            submapper: header.submapper,
+            default_expansion_device: header.default_expansion_device,
+            is_nes2: header.is_nes2,
+            has_battery: header.has_persistent_memory,
+            has_trainer: header.has_trainer(),
+        }
+    }
+}
+
+/// Location of a section of the ROM file
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NesSection {
+    /// Offset from the start of the file
+    pub offset: usize,
+    /// Size in bytes, 0 if the ROM doesn't have the section
+    pub size: usize,
+}
+
+/// Locations of the sections following the header
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NesSections {
+    pub trainer: NesSection,
+    pub prg_rom: NesSection,
+    /// Empty for games using CHR RAM
+    pub chr_rom: NesSection,
+}
+
+/// Parse the header of a ROM, which only needs the first 16 bytes of the file
+///
+/// # Safety
+///
+/// `data` has to point to `size` readable bytes and `header` to a writable `NesHeader`
+#[no_mangle]
+pub unsafe extern "C" fn nes_parse_header(
+    data: *const u8,
+    size: usize,
+    header: *mut NesHeader,
+) -> NesStatus {
+    let Some(data) = bytes(data, size) else {
+        return NES_STATUS_NULL_POINTER;
+    };
+    if header.is_null() {
+        return NES_STATUS_NULL_POINTER;
+    }
+
+    match Header::from_bytes(data) {
+        Ok(parsed) => {
+            header.write(NesHeader::from(&parsed));
+            NES_STATUS_OK
+        }
+        Err(err) => ines_status(&err),
+    }
+}
+
+/// Locate the trainer, PRG ROM and CHR ROM of a ROM, checking that the file contains all of them
+///
+/// # Safety
+///
+/// `data` has to point to `size` readable bytes and `sections` to a writable `NesSections`
+#[no_mangle]
+pub unsafe extern "C" fn nes_rom_sections(
+    data: *const u8,
+    size: usize,
+    sections: *mut NesSections,
+) -> NesStatus {
+    let Some(data) = bytes(data, size) else {
+        return NES_STATUS_NULL_POINTER;
+    };
+    if sections.is_null() {
+        return NES_STATUS_NULL_POINTER;
+    }
+
+    let ines = match Ines::from_bytes(data) {
+        Ok(ines) => ines,
+        Err(err) => return ines_status(&err),
+    };
+    let section = |offset: usize, data: Option<&[u8]>| NesSection {
+        offset,
+        size: data.map_or(0, <[u8]>::len),
+    };
+
+    let trainer = section(HEADER_SIZE, ines.trainer.as_deref());
+    let prg_rom = section(trainer.offset + trainer.size, Some(&ines.prg_rom));
+    let chr_rom = section(prg_rom.offset + prg_rom.size, ines.chr_rom.as_deref());
+    sections.write(NesSections {
+        trainer,
+        prg_rom,
+        chr_rom,
+    });
+
+    NES_STATUS_OK
+}
@@ -0,0 +1,158 @@
+use {
+    crate::{
+        bytes, lemonade_status, NesStatus, NES_STATUS_INVALID_ARGUMENT, NES_STATUS_NULL_POINTER,
+        NES_STATUS_OK, NES_STATUS_OUT_OF_BOUNDS,
+    },
+    lemonade::{Colour, ColourPalette, Lemonade, PixelFormat, SPRITE_WIDTH_HEIGHT},
+    std::{os::raw::c_uint, slice},
+};
+
+/// One byte per pixel containing the 2-bit palette index (the palette is ignored)
+pub const NES_PIXEL_FORMAT_INDEXED: c_uint = 0;
+/// Three bytes per pixel (red, green, blue)
+pub const NES_PIXEL_FORMAT_RGB8: c_uint = 1;
+/// Four bytes per pixel (red, green, blue, alpha), always fully opaque
+pub const NES_PIXEL_FORMAT_RGBA8: c_uint = 2;
+/// Four bytes per pixel (blue, green, red, alpha), always fully opaque
+pub const NES_PIXEL_FORMAT_BGRA8: c_uint = 3;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NesColour {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+fn pixel_format(format: c_uint) -> Option<PixelFormat> {
+    match format {
+        NES_PIXEL_FORMAT_INDEXED => Some(PixelFormat::Indexed),
+        NES_PIXEL_FORMAT_RGB8 => Some(PixelFormat::Rgb8),
+        NES_PIXEL_FORMAT_RGBA8 => Some(PixelFormat::Rgba8),
+        NES_PIXEL_FORMAT_BGRA8 => Some(PixelFormat::Bgra8),
+        _ => None,
+    }
+}
+
+// The palette is the background colour followed by the three sprite colours, and only optional for indexed pixels
+unsafe fn colour_palette(palette: *const NesColour, format: PixelFormat) -> Option<ColourPalette> {
+    if palette.is_null() {
+        return (format == PixelFormat::Indexed)
+            .then(|| ColourPalette::new(Colour::default(), [Colour::default(); 3]));
+    }
+
+    let colours = slice::from_raw_parts(palette, 4);
+    let colour = |index: usize| Colour::new(colours[index].r, colours[index].g, colours[index].b);
+
+    Some(ColourPalette::new(
+        colour(0),
+        [colour(1), colour(2), colour(3)],
+    ))
+}
+
+/// Amount of 8x8 sprites in the CHR data (16 bytes each)
+#[no_mangle]
+pub extern "C" fn nes_sprite_count(chr_size: usize) -> usize {
+    chr_size / lemonade::SPRITE_SIZE
+}
+
+/// Colour of the NES master palette (only the lower six bits of the index are taken into account)
+#[no_mangle]
+pub extern "C" fn nes_master_palette_colour(index: u8) -> NesColour {
+    let [r, g, b] = lemonade::nes_colour(index).raw_colour();
+
+    NesColour { r, g, b }
+}
+
+/// Size in bytes of a buffer holding `count` sprites laid out `sprites_per_row` sprites wide, 0 for unknown pixel formats
+#[no_mangle]
+pub extern "C" fn nes_sprites_buffer_size(
+    count: usize,
+    sprites_per_row: usize,
+    format: c_uint,
+) -> usize {
+    let Some(format) = pixel_format(format) else {
+        return 0;
+    };
+    let sprites_per_row = sprites_per_row.max(1);
+    let rows = count.div_ceil(sprites_per_row);
+
+    rows * sprites_per_row * SPRITE_WIDTH_HEIGHT * SPRITE_WIDTH_HEIGHT * format.bytes_per_pixel()
+}
+
+/// Decode one sprite into 8x8 pixels in the pixel format, applying the palette of four colours
+///
+/// # Safety
+///
+/// `chr_data` has to point to `chr_size` readable bytes, `buffer` to `buffer_size` writable bytes,
+/// and `palette` to four colours (it may be null for `NES_PIXEL_FORMAT_INDEXED`)
+#[no_mangle]
+pub unsafe extern "C" fn nes_decode_sprite(
+    chr_data: *const u8,
+    chr_size: usize,
+    index: usize,
+    palette: *const NesColour,
+    format: c_uint,
+    buffer: *mut u8,
+    buffer_size: usize,
+) -> NesStatus {
+    nes_render_sprites(
+        chr_data,
+        chr_size,
+        index,
+        1,
+        1,
+        palette,
+        format,
+        buffer,
+        buffer_size,
+    )
+}
+
+/// Decode `count` sprites starting at `first` into one image which is `sprites_per_row` sprites wide
+///
+/// The image is `sprites_per_row * 8` pixels wide and `ceil(count / sprites_per_row) * 8` pixels high,
+/// [`nes_sprites_buffer_size`] returns the size of the buffer it needs. Empty spaces in the last row are left untouched.
+///
+/// # Safety
+///
+/// `chr_data` has to point to `chr_size` readable bytes, `buffer` to `buffer_size` writable bytes,
+/// and `palette` to four colours (it may be null for `NES_PIXEL_FORMAT_INDEXED`)
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn nes_render_sprites(
+    chr_data: *const u8,
+    chr_size: usize,
+    first: usize,
+    count: usize,
+    sprites_per_row: usize,
+    palette: *const NesColour,
+    format: c_uint,
+    buffer: *mut u8,
+    buffer_size: usize,
+) -> NesStatus {
+    let Some(chr_data) = bytes(chr_data, chr_size) else {
+        return NES_STATUS_NULL_POINTER;
+    };
+    if buffer.is_null() {
+        return NES_STATUS_NULL_POINTER;
+    }
+    let Some(format) = pixel_format(format) else {
+        return NES_STATUS_INVALID_ARGUMENT;
+    };
+    let Some(colour_palette) = colour_palette(palette, format) else {
+        return NES_STATUS_NULL_POINTER;
+    };
+    let sprites = first
+        .checked_add(count)
+        .and_then(|end| Lemonade::new(chr_data).range(first..end));
+    let Some(sprites) = sprites else {
+        return NES_STATUS_OUT_OF_BOUNDS;
+    };
+
+    let buffer = slice::from_raw_parts_mut(buffer, buffer_size);
+    match sprites.decode_all_into(buffer, colour_palette, format, sprites_per_row) {
+        Ok(()) => NES_STATUS_OK,
+        Err(err) => lemonade_status(&err),
+    }
+}
@@ -0,0 +1,155 @@
+use {
+    crate::Error,
+    ines_parser::{Ines, Timing},
+    lemonade::nes_colour,
+    nes_cheats::Cheat,
+    nes_emulator::{Buttons, Nes},
+    std::collections::BTreeMap,
+};
+
+/// Rate the audio is generated at
+pub const SAMPLE_RATE: u32 = 48000;
+
+// Pole of the high-pass filter removing the DC offset, like the one of the console's audio output
+const DC_FILTER_POLE: f32 = 0.997;
+
+// Character separating the codes of a cheat which consists of several codes
+const CHEAT_SEPARATOR: char = '+';
+
+/// Emulator together with the output of its last frame, in the formats the libretro API expects
+pub struct Core {
+    nes: Nes,
+    palette: [u32; 64],
+    video: Vec<u32>,
+    audio: Vec<i16>,
+    previous_input: f32,
+    previous_output: f32,
+    cheats: BTreeMap<u32, Vec<Cheat>>,
+}
+
+impl Core {
+    /// Power on the console of the ROM's region with the ROM inserted
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the ROM is invalid or its mapper isn't supported
+    pub fn load(rom: &[u8]) -> Result<Self, Error> {
+        let ines = Ines::from_bytes(rom)?;
+        let nes = Nes::from_ines(&ines, SAMPLE_RATE)?;
+
+        let mut palette = [0; 64];
+        for (index, colour) in (0..).zip(palette.iter_mut()) {
+            let [red, green, blue] = nes_colour(index).raw_colour();
+            *colour = u32::from_be_bytes([0, red, green, blue]);
+        }
+
+        Ok(Self {
+            nes,
+            palette,
+            video: Vec::new(),
+            audio: Vec::new(),
+            previous_input: 0.0,
+            previous_output: 0.0,
+            cheats: BTreeMap::new(),
+        })
+    }
+
+    #[must_use]
+    pub fn nes(&self) -> &Nes {
+        &self.nes
+    }
+
+    pub fn nes_mut(&mut self) -> &mut Nes {
+        &mut self.nes
+    }
+
+    /// Region of the console
+    #[must_use]
+    pub fn timing(&self) -> Timing {
+        self.nes.timing()
+    }
+
+    /// Run one frame with the buttons held on the two controllers
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the CPU encounters an unknown opcode
+    pub fn run_frame(&mut self, buttons: [Buttons; 2]) -> Result<(), Error> {
+        for (port, buttons) in buttons.iter().enumerate() {
+            self.nes.set_buttons(port, *buttons);
+        }
+        let result = self.nes.run_frame();
+
+        let palette = &self.palette;
+        self.video.clear();
+        self.video.extend(
+            self.nes
+                .framebuffer()
+                .iter()
+                .map(|index| palette[usize::from(index & 0x3F)]),
+        );
+
+        // The APU only outputs positive values, so they get centred around zero before being scaled to the full range
+        self.audio.clear();
+        for sample in self.nes.take_samples() {
+            let output = sample - self.previous_input + DC_FILTER_POLE * self.previous_output;
+            self.previous_input = sample;
+            self.previous_output = output;
+
+            // The value is clamped into the range of `i16` first
+            #[allow(clippy::cast_possible_truncation)]
+            let value = (output * f32::from(i16::MAX))
+                .clamp(f32::from(i16::MIN), f32::from(i16::MAX)) as i16;
+            self.audio.extend_from_slice(&[value, value]);
+        }
+
+        result.map_err(Error::from)
+    }
+
+    /// Last frame as XRGB8888 pixels (256×240)
+    #[must_use]
+    pub fn video(&self) -> &[u32] {
+        &self.video
+    }
+
+    /// Audio of the last frame as interleaved stereo samples
+    #[must_use]
+    pub fn audio(&self) -> &[i16] {
+        &self.audio
+    }
+
+    /// Activate the cheat at the index, or deactivate it if there's no code
+    ///
+    /// A cheat can consist of several codes separated by `+`, each of them in any format `nes-cheats` supports.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if one of the codes is invalid, in which case the cheat is left untouched
+    pub fn set_cheat(&mut self, index: u32, code: Option<&str>) -> Result<(), Error> {
+        match code {
+            Some(code) => {
+                let codes = code
+                    .split(CHEAT_SEPARATOR)
+                    .map(str::parse)
+                    .collect::<Result<_, _>>()?;
+                self.cheats.insert(index, codes);
+            }
+            None => {
+                self.cheats.remove(&index);
+            }
+        }
+
+        self.nes.clear_cheats();
+        for cheat in self.cheats.values().flatten() {
+            self.nes.add_cheat(*cheat);
+        }
+
+        Ok(())
+    }
+
+    /// Deactivate all cheats
+    pub fn reset_cheats(&mut self) {
+        self.cheats.clear();
+        self.nes.clear_cheats();
+    }
+}
@@ -0,0 +1,389 @@
+//!
+//! Functions of the libretro API, exported with their C names
+//!
+//! Frontends call all of them from the same thread, so the state lives in thread locals.
+//!
+
+use {
+    crate::{
+        ffi::{
+            AudioSampleBatchFn, AudioSampleFn, EnvironmentFn, GameGeometry, GameInfo,
+            InputDescriptor, InputPollFn, InputStateFn, SystemAvInfo, SystemInfo, SystemTiming,
+            VideoRefreshFn, JOYPAD_MAPPING, RETRO_API_VERSION, RETRO_DEVICE_JOYPAD,
+            RETRO_ENVIRONMENT_SET_INPUT_DESCRIPTORS, RETRO_ENVIRONMENT_SET_PIXEL_FORMAT,
+            RETRO_MEMORY_SAVE_RAM, RETRO_MEMORY_SYSTEM_RAM, RETRO_PIXEL_FORMAT_XRGB8888,
+            RETRO_REGION_NTSC, RETRO_REGION_PAL,
+        },
+        Core, SAMPLE_RATE,
+    },
+    ines_parser::Timing,
+    lemonade::{SCREEN_HEIGHT, SCREEN_WIDTH},
+    nes_emulator::Buttons,
+    std::{
+        cell::RefCell,
+        convert::TryFrom,
+        ffi::CStr,
+        os::raw::{c_char, c_uint, c_void},
+        ptr, slice,
+    },
+};
+
+const LIBRARY_NAME: &str = "nes-utils\0";
+const LIBRARY_VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "\0");
+const VALID_EXTENSIONS: &str = "nes\0";
+
+// Pixels of the NES are slightly wider than high on NTSC TVs
+const PIXEL_ASPECT_RATIO: f32 = 8.0 / 7.0;
+
+#[derive(Clone, Copy)]
+struct Frontend {
+    environment: Option<EnvironmentFn>,
+    video_refresh: Option<VideoRefreshFn>,
+    audio_sample_batch: Option<AudioSampleBatchFn>,
+    input_poll: Option<InputPollFn>,
+    input_state: Option<InputStateFn>,
+    port_devices: [c_uint; 2],
+}
+
+thread_local! {
+    static FRONTEND: RefCell<Frontend> = RefCell::new(Frontend {
+        environment: None,
+        video_refresh: None,
+        audio_sample_batch: None,
+        input_poll: None,
+        input_state: None,
+        port_devices: [RETRO_DEVICE_JOYPAD; 2],
+    });
+    static CORE: RefCell<Option<Core>> = const { RefCell::new(None) };
+}
+
+fn frontend() -> Frontend {
+    FRONTEND.with(|frontend| *frontend.borrow())
+}
+
+fn with_frontend(update: impl FnOnce(&mut Frontend)) {
+    FRONTEND.with(|frontend| update(&mut frontend.borrow_mut()));
+}
+
+fn with_core<T>(default: T, action: impl FnOnce(&mut Core) -> T) -> T {
+    CORE.with(|core| core.borrow_mut().as_mut().map_or(default, action))
+}
+
+fn c_str(text: &'static str) -> *const c_char {
+    text.as_ptr().cast()
+}
+
+#[no_mangle]
+pub extern "C" fn retro_api_version() -> c_uint {
+    RETRO_API_VERSION
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_environment(callback: EnvironmentFn) {
+    with_frontend(|frontend| frontend.environment = Some(callback));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_video_refresh(callback: VideoRefreshFn) {
+    with_frontend(|frontend| frontend.video_refresh = Some(callback));
+}
+
+// All audio is passed in batches
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample(_callback: AudioSampleFn) {}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample_batch(callback: AudioSampleBatchFn) {
+    with_frontend(|frontend| frontend.audio_sample_batch = Some(callback));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_poll(callback: InputPollFn) {
+    with_frontend(|frontend| frontend.input_poll = Some(callback));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_state(callback: InputStateFn) {
+    with_frontend(|frontend| frontend.input_state = Some(callback));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_init() {}
+
+#[no_mangle]
+pub extern "C" fn retro_deinit() {
+    CORE.with(|core| core.borrow_mut().take());
+}
+
+/// # Safety
+///
+/// The pointer has to point to a writable `retro_system_info`
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut SystemInfo) {
+    info.write(SystemInfo {
+        library_name: c_str(LIBRARY_NAME),
+        library_version: c_str(LIBRARY_VERSION),
+        valid_extensions: c_str(VALID_EXTENSIONS),
+        need_fullpath: false,
+        block_extract: false,
+    });
+}
+
+/// # Safety
+///
+/// The pointer has to point to a writable `retro_system_av_info`
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut SystemAvInfo) {
+    let timing = with_core(Timing::Ntsc, |core| core.timing());
+
+    // The screen dimensions are constants far below `u32::MAX`
+    #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+    info.write(SystemAvInfo {
+        geometry: GameGeometry {
+            base_width: SCREEN_WIDTH as c_uint,
+            base_height: SCREEN_HEIGHT as c_uint,
+            max_width: SCREEN_WIDTH as c_uint,
+            max_height: SCREEN_HEIGHT as c_uint,
+            aspect_ratio: SCREEN_WIDTH as f32 * PIXEL_ASPECT_RATIO / SCREEN_HEIGHT as f32,
+        },
+        timing: SystemTiming {
+            fps: timing.frame_rate(),
+            sample_rate: f64::from(SAMPLE_RATE),
+        },
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_controller_port_device(port: c_uint, device: c_uint) {
+    if let Ok(port) = usize::try_from(port) {
+        with_frontend(|frontend| {
+            if let Some(port_device) = frontend.port_devices.get_mut(port) {
+                *port_device = device;
+            }
+        });
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_reset() {
+    with_core((), |core| core.nes_mut().reset());
+}
+
+#[no_mangle]
+pub extern "C" fn retro_run() {
+    let frontend = frontend();
+
+    let mut buttons = [Buttons::NONE; 2];
+    if let (Some(input_poll), Some(input_state)) = (frontend.input_poll, frontend.input_state) {
+        unsafe { input_poll() };
+
+        for ((port, buttons), device) in (0..).zip(&mut buttons).zip(frontend.port_devices) {
+            if device != RETRO_DEVICE_JOYPAD {
+                continue;
+            }
+
+            for (id, button, _) in JOYPAD_MAPPING {
+                if unsafe { input_state(port, RETRO_DEVICE_JOYPAD, 0, id) } != 0 {
+                    *buttons |= button;
+                }
+            }
+        }
+    }
+
+    with_core((), |core| {
+        // There's no way to report the error, the frontend keeps showing the last frame
+        let _ = core.run_frame(buttons);
+
+        if let Some(video_refresh) = frontend.video_refresh {
+            let video = core.video();
+            if !video.is_empty() {
+                // The screen dimensions are constants far below `u32::MAX`
+                #[allow(clippy::cast_possible_truncation)]
+                unsafe {
+                    video_refresh(
+                        video.as_ptr().cast(),
+                        SCREEN_WIDTH as c_uint,
+                        SCREEN_HEIGHT as c_uint,
+                        SCREEN_WIDTH * 4,
+                    );
+                }
+            }
+        }
+
+        if let Some(audio_sample_batch) = frontend.audio_sample_batch {
+            // Frontends may accept fewer frames than they're given
+            let mut audio = core.audio();
+            while !audio.is_empty() {
+                let accepted = unsafe { audio_sample_batch(audio.as_ptr(), audio.len() / 2) };
+                if accepted == 0 {
+                    break;
+                }
+                audio = &audio[(accepted * 2).min(audio.len())..];
+            }
+        }
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn retro_serialize_size() -> usize {
+    with_core(0, |core| core.nes().serialize_state().len())
+}
+
+/// # Safety
+///
+/// The pointer has to point to `size` writable bytes
+#[no_mangle]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+    with_core(false, |core| {
+        let state = core.nes().serialize_state();
+        if data.is_null() || state.len() > size {
+            return false;
+        }
+
+        let buffer = slice::from_raw_parts_mut(data.cast::<u8>(), size);
+        buffer[..state.len()].copy_from_slice(&state);
+        buffer[state.len()..].fill(0);
+        true
+    })
+}
+
+/// # Safety
+///
+/// The pointer has to point to `size` readable bytes
+#[no_mangle]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+    if data.is_null() {
+        return false;
+    }
+
+    let state = slice::from_raw_parts(data.cast::<u8>(), size);
+    with_core(false, |core| {
+        core.nes_mut().deserialize_state(state).is_ok()
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_reset() {
+    with_core((), Core::reset_cheats);
+}
+
+/// # Safety
+///
+/// The code has to be a null-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn retro_cheat_set(index: c_uint, enabled: bool, code: *const c_char) {
+    let code = if enabled && !code.is_null() {
+        CStr::from_ptr(code).to_str().ok()
+    } else {
+        None
+    };
+
+    // Invalid codes are ignored, the API has no way to report them
+    with_core((), |core| {
+        let _ = core.set_cheat(index, code);
+    });
+}
+
+/// # Safety
+///
+/// The pointer has to point to a valid `retro_game_info` or be null
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game(game: *const GameInfo) -> bool {
+    let Some(game) = game.as_ref() else {
+        return false;
+    };
+    if game.data.is_null() {
+        return false;
+    }
+
+    let Some(environment) = frontend().environment else {
+        return false;
+    };
+    let mut pixel_format = RETRO_PIXEL_FORMAT_XRGB8888;
+    if !environment(
+        RETRO_ENVIRONMENT_SET_PIXEL_FORMAT,
+        ptr::addr_of_mut!(pixel_format).cast(),
+    ) {
+        return false;
+    }
+
+    // The list ends with an empty descriptor
+    let mut descriptors: Vec<InputDescriptor> = (0..2)
+        .flat_map(|port| {
+            JOYPAD_MAPPING
+                .iter()
+                .map(move |&(id, _, description)| InputDescriptor {
+                    port,
+                    device: RETRO_DEVICE_JOYPAD,
+                    index: 0,
+                    id,
+                    description: c_str(description),
+                })
+        })
+        .collect();
+    descriptors.push(InputDescriptor {
+        port: 0,
+        device: 0,
+        index: 0,
+        id: 0,
+        description: ptr::null(),
+    });
+    environment(
+        RETRO_ENVIRONMENT_SET_INPUT_DESCRIPTORS,
+        descriptors.as_mut_ptr().cast(),
+    );
+
+    let rom = slice::from_raw_parts(game.data.cast::<u8>(), game.size);
+    match Core::load(rom) {
+        Ok(core) => {
+            CORE.with(|current| *current.borrow_mut() = Some(core));
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_load_game_special(
+    _game_type: c_uint,
+    _info: *const GameInfo,
+    _num_info: usize,
+) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unload_game() {
+    CORE.with(|core| core.borrow_mut().take());
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_region() -> c_uint {
+    match with_core(Timing::Ntsc, |core| core.timing()) {
+        Timing::Pal | Timing::Dendy => RETRO_REGION_PAL,
+        Timing::Ntsc | Timing::MultiRegion => RETRO_REGION_NTSC,
+    }
+}
+
+// The memory stays in place until the game gets unloaded, so frontends can read and write it directly
+#[no_mangle]
+pub extern "C" fn retro_get_memory_data(id: c_uint) -> *mut c_void {
+    with_core(ptr::null_mut(), |core| match id {
+        RETRO_MEMORY_SAVE_RAM => core
+            .nes_mut()
+            .cartridge_mut()
+            .battery_ram_mut()
+            .map_or(ptr::null_mut(), |memory| memory.as_mut_ptr().cast()),
+        RETRO_MEMORY_SYSTEM_RAM => core.nes_mut().ram_mut().as_mut_ptr().cast(),
+        _ => ptr::null_mut(),
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_size(id: c_uint) -> usize {
+    with_core(0, |core| match id {
+        RETRO_MEMORY_SAVE_RAM => core.nes().cartridge().battery_ram().map_or(0, <[u8]>::len),
+        RETRO_MEMORY_SYSTEM_RAM => core.nes().ram().len(),
+        _ => 0,
+    })
+}
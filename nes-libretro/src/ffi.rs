@@ -0,0 +1,109 @@
+//!
+//! Types and constants of `libretro.h`
+//!
+//! [API documentation](https://docs.libretro.com/development/cores/developing-cores/)
+//!
+
+use {
+    nes_emulator::Buttons,
+    std::os::raw::{c_char, c_uint, c_void},
+};
+
+pub const RETRO_API_VERSION: c_uint = 1;
+
+pub const RETRO_DEVICE_NONE: c_uint = 0;
+pub const RETRO_DEVICE_JOYPAD: c_uint = 1;
+
+pub const RETRO_DEVICE_ID_JOYPAD_B: c_uint = 0;
+pub const RETRO_DEVICE_ID_JOYPAD_SELECT: c_uint = 2;
+pub const RETRO_DEVICE_ID_JOYPAD_START: c_uint = 3;
+pub const RETRO_DEVICE_ID_JOYPAD_UP: c_uint = 4;
+pub const RETRO_DEVICE_ID_JOYPAD_DOWN: c_uint = 5;
+pub const RETRO_DEVICE_ID_JOYPAD_LEFT: c_uint = 6;
+pub const RETRO_DEVICE_ID_JOYPAD_RIGHT: c_uint = 7;
+pub const RETRO_DEVICE_ID_JOYPAD_A: c_uint = 8;
+
+pub const RETRO_REGION_NTSC: c_uint = 0;
+pub const RETRO_REGION_PAL: c_uint = 1;
+
+pub const RETRO_MEMORY_SAVE_RAM: c_uint = 0;
+pub const RETRO_MEMORY_SYSTEM_RAM: c_uint = 2;
+
+pub const RETRO_ENVIRONMENT_SET_PIXEL_FORMAT: c_uint = 10;
+pub const RETRO_ENVIRONMENT_SET_INPUT_DESCRIPTORS: c_uint = 11;
+
+pub const RETRO_PIXEL_FORMAT_XRGB8888: c_uint = 1;
+
+/// Buttons of the standard controller for the IDs of the libretro joypad
+///
+/// The libretro joypad is modelled after the SNES controller, so A and B keep their positions on the right side.
+pub const JOYPAD_MAPPING: [(c_uint, Buttons, &str); 8] = [
+    (RETRO_DEVICE_ID_JOYPAD_A, Buttons::A, "A\0"),
+    (RETRO_DEVICE_ID_JOYPAD_B, Buttons::B, "B\0"),
+    (RETRO_DEVICE_ID_JOYPAD_SELECT, Buttons::SELECT, "Select\0"),
+    (RETRO_DEVICE_ID_JOYPAD_START, Buttons::START, "Start\0"),
+    (RETRO_DEVICE_ID_JOYPAD_UP, Buttons::UP, "D-Pad Up\0"),
+    (RETRO_DEVICE_ID_JOYPAD_DOWN, Buttons::DOWN, "D-Pad Down\0"),
+    (RETRO_DEVICE_ID_JOYPAD_LEFT, Buttons::LEFT, "D-Pad Left\0"),
+    (
+        RETRO_DEVICE_ID_JOYPAD_RIGHT,
+        Buttons::RIGHT,
+        "D-Pad Right\0",
+    ),
+];
+
+pub type EnvironmentFn = unsafe extern "C" fn(cmd: c_uint, data: *mut c_void) -> bool;
+pub type VideoRefreshFn =
+    unsafe extern "C" fn(data: *const c_void, width: c_uint, height: c_uint, pitch: usize);
+pub type AudioSampleFn = unsafe extern "C" fn(left: i16, right: i16);
+pub type AudioSampleBatchFn = unsafe extern "C" fn(data: *const i16, frames: usize) -> usize;
+pub type InputPollFn = unsafe extern "C" fn();
+pub type InputStateFn =
+    unsafe extern "C" fn(port: c_uint, device: c_uint, index: c_uint, id: c_uint) -> i16;
+
+#[repr(C)]
+pub struct SystemInfo {
+    pub library_name: *const c_char,
+    pub library_version: *const c_char,
+    pub valid_extensions: *const c_char,
+    pub need_fullpath: bool,
+    pub block_extract: bool,
+}
+
+#[repr(C)]
+pub struct GameGeometry {
+    pub base_width: c_uint,
+    pub base_height: c_uint,
+    pub max_width: c_uint,
+    pub max_height: c_uint,
+    pub aspect_ratio: f32,
+}
+
+#[repr(C)]
+pub struct SystemTiming {
+    pub fps: f64,
+    pub sample_rate: f64,
+}
+
+#[repr(C)]
+pub struct SystemAvInfo {
+    pub geometry: GameGeometry,
+    pub timing: SystemTiming,
+}
+
+#[repr(C)]
+pub struct GameInfo {
+    pub path: *const c_char,
+    pub data: *const c_void,
+    pub size: usize,
+    pub meta: *const c_char,
+}
+
+#[repr(C)]
+pub struct InputDescriptor {
+    pub port: c_uint,
+    pub device: c_uint,
+    pub index: c_uint,
+    pub id: c_uint,
+    pub description: *const c_char,
+}
@@ -0,0 +1,51 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+//!
+//! libretro core running the NES emulator, loadable by any libretro frontend
+//!
+
+use std::fmt;
+
+mod emulator;
+#[cfg(feature = "exports")]
+mod exports;
+pub mod ffi;
+
+pub use emulator::{Core, SAMPLE_RATE};
+
+#[derive(Debug)]
+pub enum Error {
+    Ines(ines_parser::Error),
+    Emulator(nes_emulator::Error),
+    Cheat(nes_cheats::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ines(err) => write!(f, "INES error: {err}"),
+            Self::Emulator(err) => write!(f, "Emulator error: {err}"),
+            Self::Cheat(err) => write!(f, "Cheat error: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<ines_parser::Error> for Error {
+    fn from(err: ines_parser::Error) -> Self {
+        Self::Ines(err)
+    }
+}
+
+impl From<nes_emulator::Error> for Error {
+    fn from(err: nes_emulator::Error) -> Self {
+        Self::Emulator(err)
+    }
+}
+
+impl From<nes_cheats::Error> for Error {
+    fn from(err: nes_cheats::Error) -> Self {
+        Self::Cheat(err)
+    }
+}
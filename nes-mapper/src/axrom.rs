@@ -0,0 +1,132 @@
+use {
+    crate::{Mapper, Memory, MemoryMap, Mirroring, RegionKind},
+    nes_state::{Error, Savestate, StateReader, StateWriter},
+};
+
+const PRG_BANK_SIZE: usize = 0x8000;
+
+/// Mapper 7; switchable 32 KiB PRG ROM bank and single-screen mirroring
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Axrom {
+    memory: Memory,
+    prg_bank: u8,
+    mirroring: Mirroring,
+    bus_conflicts: bool,
+}
+
+impl Axrom {
+    #[must_use]
+    pub fn new(memory: Memory) -> Self {
+        Self {
+            memory,
+            prg_bank: 0,
+            mirroring: Mirroring::SingleScreenLower,
+            bus_conflicts: false,
+        }
+    }
+
+    /// Emulate the bus conflicts of boards where the ROM drives the data bus during register writes
+    ///
+    /// The mapper sees the bitwise AND of the written value and the ROM byte at the address
+    #[must_use]
+    pub fn with_bus_conflicts(mut self, enabled: bool) -> Self {
+        self.bus_conflicts = enabled;
+        self
+    }
+
+    #[must_use]
+    pub fn has_bus_conflicts(&self) -> bool {
+        self.bus_conflicts
+    }
+
+    // Value the mapper sees, masked by the ROM if there are bus conflicts
+    fn bus_value(&mut self, address: u16, value: u8) -> u8 {
+        if self.bus_conflicts {
+            value & self.cpu_read(address)
+        } else {
+            value
+        }
+    }
+}
+
+impl Mapper for Axrom {
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        match address {
+            0x6000..=0x7FFF => self.memory.read_prg_ram(address),
+            0x8000..=0xFFFF => {
+                self.memory
+                    .read_prg_rom(usize::from(self.prg_bank), PRG_BANK_SIZE, address)
+            }
+            _ => 0,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        match address {
+            0x6000..=0x7FFF => self.memory.write_prg_ram(address, value),
+            0x8000..=0xFFFF => {
+                let value = self.bus_value(address, value);
+                self.prg_bank = value & 0x07;
+                self.mirroring = if value & 0x10 == 0 {
+                    Mirroring::SingleScreenLower
+                } else {
+                    Mirroring::SingleScreenUpper
+                };
+            }
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        self.memory.read_chr(0, 0x2000, address)
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8) {
+        self.memory.write_chr(0, 0x2000, address, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn memory_map(&self) -> MemoryMap {
+        MemoryMap::new()
+            .prg_ram(&self.memory, true, true)
+            .prg_rom(
+                &self.memory,
+                0x8000,
+                usize::from(self.prg_bank),
+                PRG_BANK_SIZE,
+                true,
+            )
+            .cpu(
+                0x8000..=0xFFFF,
+                RegionKind::Registers("PRG bank and nametable"),
+            )
+            .chr(&self.memory, 0x0000, 0, 0x2000, false)
+    }
+
+    fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+}
+
+impl Savestate for Axrom {
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.memory.save_state(writer);
+        writer.write_u8(self.prg_bank);
+        self.mirroring.save_state(writer);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), Error> {
+        self.memory.load_state(reader)?;
+        self.prg_bank = reader.read_u8()?;
+        self.mirroring.load_state(reader)?;
+
+        Ok(())
+    }
+}
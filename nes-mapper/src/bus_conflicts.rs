@@ -0,0 +1,135 @@
+use {
+    alloc::vec::Vec,
+    ines_parser::{Header, Ines},
+};
+
+// Pairs of an immediate load and the absolute store of the same register
+const LOAD_STORE_OPCODES: [(u8, u8); 3] = [
+    (0xA9, 0x8D), // LDA #, STA
+    (0xA2, 0x8E), // LDX #, STX
+    (0xA0, 0x8C), // LDY #, STY
+];
+
+/// Whether the header says the board has bus conflicts
+///
+/// NES 2.0 headers state it with submapper 2 of `UxROM` (2), `CNROM` (3) and `AxROM` (7).
+/// Submapper 0 leaves it unspecified; the mappers then don't emulate the conflicts.
+#[must_use]
+pub fn has_bus_conflicts(header: &Header) -> bool {
+    header.is_nes2 && header.submapper == 2 && matches!(header.mapper_number, 2 | 3 | 7)
+}
+
+/// Write to a mapper register which gets changed by a bus conflict
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BusConflict {
+    /// Offset of the store instruction into the PRG ROM
+    pub offset: usize,
+    /// Address the value is written to
+    pub address: u16,
+    pub value: u8,
+    /// Byte of the ROM at the address, which masks the value
+    pub rom: u8,
+}
+
+impl BusConflict {
+    /// Value the mapper receives on boards with bus conflicts
+    #[must_use]
+    pub fn effective_value(&self) -> u8 {
+        self.value & self.rom
+    }
+}
+
+// Banking of the PRG ROM, to tell which ROM byte an instruction sees at an address
+struct Layout<'a> {
+    prg_rom: &'a [u8],
+    bank_size: usize,
+    // The last bank stays at `$C000`, the others are switched in at `$8000`
+    fixed_last_bank: bool,
+}
+
+impl Layout<'_> {
+    fn new(mapper_number: u8, prg_rom: &[u8]) -> Option<Layout<'_>> {
+        let (bank_size, fixed_last_bank) = match mapper_number {
+            2 => (0x4000, true),
+            3 | 7 => (0x8000, false),
+            _ => return None,
+        };
+
+        Some(Layout {
+            prg_rom,
+            bank_size,
+            fixed_last_bank,
+        })
+    }
+
+    fn last_bank(&self) -> usize {
+        (self.prg_rom.len().saturating_sub(1)) / self.bank_size
+    }
+
+    // ROM byte at the address while the instruction at the offset is executed, if it's known
+    fn rom_byte(&self, offset: usize, address: u16) -> Option<u8> {
+        let address = usize::from(address - 0x8000);
+        let bank = offset / self.bank_size;
+
+        let rom_offset = if !self.fixed_last_bank {
+            // The instruction and the register are in the same bank, 16 KiB ROMs are mirrored
+            bank * self.bank_size + address % self.bank_size
+        } else if address >= self.bank_size {
+            self.last_bank() * self.bank_size + address % self.bank_size
+        } else if bank != self.last_bank() {
+            // Code in the switchable bank writes to its own bank
+            bank * self.bank_size + address
+        } else {
+            // The switchable bank isn't known from the fixed bank
+            return None;
+        };
+
+        self.prg_rom.get(rom_offset % self.prg_rom.len()).copied()
+    }
+}
+
+/// Writes of immediate values to the registers of `UxROM`, `CNROM` or `AxROM` which a bus conflict would change
+///
+/// Boards with bus conflicts AND the written value with the ROM byte at the address. Games made for them
+/// write to an address holding the same value; writes where the ROM clears bits of the value mean the game
+/// only works as intended with (or without) the conflicts, so the accuracy mode of the mapper matters.
+///
+/// Only `LDA`/`LDX`/`LDY` of an immediate value directly followed by a store to `$8000`-`$FFFF` get checked,
+/// and only if the ROM byte at the address is known without running the code.
+/// Returns nothing for other mappers.
+#[must_use]
+pub fn find_bus_conflicts(mapper_number: u8, prg_rom: &[u8]) -> Vec<BusConflict> {
+    let layout = match Layout::new(mapper_number, prg_rom) {
+        Some(layout) if !prg_rom.is_empty() => layout,
+        _ => return Vec::new(),
+    };
+
+    prg_rom
+        .windows(5)
+        .enumerate()
+        .filter_map(|(offset, bytes)| {
+            LOAD_STORE_OPCODES
+                .iter()
+                .find(|(load, store)| bytes[0] == *load && bytes[2] == *store)?;
+            let address = u16::from_le_bytes([bytes[3], bytes[4]]);
+            if address < 0x8000 {
+                return None;
+            }
+
+            let value = bytes[1];
+            let rom = layout.rom_byte(offset + 2, address)?;
+            (value & rom != value).then_some(BusConflict {
+                offset: offset + 2,
+                address,
+                value,
+                rom,
+            })
+        })
+        .collect()
+}
+
+/// Whether the ROM writes values to the mapper registers which a bus conflict would change, see [`find_bus_conflicts`]
+#[must_use]
+pub fn relies_on_bus_conflicts(ines: &Ines<'_>) -> bool {
+    !find_bus_conflicts(ines.header.mapper_number, &ines.prg_rom).is_empty()
+}
@@ -0,0 +1,231 @@
+use {
+    crate::{
+        check_save, from_ines, is_self_flashable, save_size, DiskSystem, Error, Mapper, MemoryMap,
+        Mirroring, RegionKind, SaveFit, FDS_BIOS_SIZE,
+    },
+    alloc::{boxed::Box, vec, vec::Vec},
+    fds_parser::Fds,
+    ines_parser::{Header, Ines, VramLayout},
+    nes_ppu::PpuBus,
+    nes_state::{Savestate, StateReader, StateWriter},
+};
+
+// The console itself only has 2 KiB of VRAM, four-screen cartridges bring another 2 KiB
+const VRAM_SIZE: usize = 0x800;
+const FOUR_SCREEN_VRAM_SIZE: usize = 0x1000;
+
+// Mapper number emulators give the Famicom Disk System
+const FDS_MAPPER_NUMBER: u8 = 20;
+
+/// Cartridge built from an INES ROM
+///
+/// Owns the mapper together with the ROMs and RAMs, and the nametable memory the PPU sees through it
+pub struct Cartridge {
+    header: Header,
+    mapper: Box<dyn Mapper>,
+    vram: Vec<u8>,
+}
+
+impl Cartridge {
+    /// Instantiate the mapper the header asks for and allocate the RAMs
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UnsupportedMapper`] if the mapper isn't implemented
+    pub fn from_ines(ines: &Ines<'_>) -> Result<Self, Error> {
+        let mapper = from_ines(ines)?;
+        let vram_size = if mapper.mirroring() == Mirroring::FourScreen {
+            FOUR_SCREEN_VRAM_SIZE
+        } else {
+            VRAM_SIZE
+        };
+
+        Ok(Self {
+            header: ines.header.clone(),
+            mapper,
+            vram: vec![0; vram_size],
+        })
+    }
+
+    /// Plug in the RAM adapter of the Famicom Disk System with the BIOS, and the drive with the disk image
+    ///
+    /// No disk is inserted at first; see [`DiskSystem::insert_disk`]. The header is made up,
+    /// with the BIOS as the PRG ROM and the mapper number 20 emulators use for the Disk System.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidBiosSize`] if the BIOS isn't 8 KiB
+    pub fn from_fds(bios: &[u8], fds: &Fds<'_>) -> Result<Self, Error> {
+        let disk_system = DiskSystem::new(bios, fds).ok_or(Error::InvalidBiosSize(bios.len()))?;
+
+        Ok(Self {
+            header: Header::new(
+                FDS_BIOS_SIZE,
+                0,
+                FDS_MAPPER_NUMBER,
+                VramLayout::VerticalMirroring,
+            ),
+            mapper: Box::new(disk_system),
+            vram: vec![0; VRAM_SIZE],
+        })
+    }
+
+    /// Header of the ROM the cartridge was built from
+    #[must_use]
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Whether the PRG RAM is battery-backed and should be saved
+    #[must_use]
+    pub fn has_battery(&self) -> bool {
+        self.header.has_persistent_memory
+    }
+
+    /// Contents of the battery-backed PRG RAM, which is what gets written to a `.sav` file
+    ///
+    /// The battery-backed part comes after the volatile part of the PRG RAM.
+    /// For self-flashable boards it's the PRG ROM, which the game may have rewritten.
+    #[must_use]
+    pub fn battery_ram(&self) -> Option<&[u8]> {
+        let memory = self.mapper.memory();
+        let prg_ram = if is_self_flashable(&self.header) {
+            &memory.prg_rom
+        } else {
+            &memory.prg_ram
+        };
+        let size = save_size(&self.header).min(prg_ram.len());
+
+        if size == 0 {
+            None
+        } else {
+            Some(&prg_ram[prg_ram.len() - size..])
+        }
+    }
+
+    /// Battery-backed PRG RAM, mutable for frontends writing save files directly into the memory
+    pub fn battery_ram_mut(&mut self) -> Option<&mut [u8]> {
+        let memory = self.mapper.memory_mut();
+        let prg_ram = if is_self_flashable(&self.header) {
+            &mut memory.prg_rom
+        } else {
+            &mut memory.prg_ram
+        };
+        let size = save_size(&self.header).min(prg_ram.len());
+
+        if size == 0 {
+            None
+        } else {
+            let start = prg_ram.len() - size;
+            Some(&mut prg_ram[start..])
+        }
+    }
+
+    /// Load the contents of a `.sav` file into the battery-backed PRG RAM
+    ///
+    /// Files with a different size get truncated or padded with zeros. The returned fit tells whether that happened.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NoBatteryRam`] if the cartridge doesn't have battery-backed RAM
+    pub fn load_battery_ram(&mut self, data: &[u8]) -> Result<SaveFit, Error> {
+        let memory = self.mapper.memory_mut();
+        let prg_ram = if is_self_flashable(&self.header) {
+            &mut memory.prg_rom
+        } else {
+            &mut memory.prg_ram
+        };
+        let size = save_size(&self.header).min(prg_ram.len());
+        if size == 0 {
+            return Err(Error::NoBatteryRam);
+        }
+
+        let start = prg_ram.len() - size;
+        let battery_ram = &mut prg_ram[start..];
+        let length = data.len().min(size);
+        battery_ram[..length].copy_from_slice(&data[..length]);
+        battery_ram[length..].fill(0);
+
+        Ok(check_save(size, data))
+    }
+
+    #[must_use]
+    pub fn mapper(&self) -> &dyn Mapper {
+        self.mapper.as_ref()
+    }
+
+    pub fn mapper_mut(&mut self) -> &mut dyn Mapper {
+        self.mapper.as_mut()
+    }
+
+    /// Current layout of the mapper, including the nametables the cartridge maps for the PPU
+    #[must_use]
+    pub fn memory_map(&self) -> MemoryMap {
+        self.mapper.memory_map().ppu(
+            0x2000..=0x2FFF,
+            RegionKind::Nametables(self.mapper.mirroring()),
+        )
+    }
+
+    /// Contents of the nametable memory
+    #[must_use]
+    pub fn vram(&self) -> &[u8] {
+        &self.vram
+    }
+
+    /// Read from the cartridge space of the CPU (`$4020`-`$FFFF`)
+    pub fn cpu_read(&mut self, address: u16) -> u8 {
+        self.mapper.cpu_read(address)
+    }
+
+    /// Write to the cartridge space of the CPU (`$4020`-`$FFFF`)
+    pub fn cpu_write(&mut self, address: u16, value: u8) {
+        self.mapper.cpu_write(address, value);
+    }
+
+    /// Clock the mapper with one CPU cycle
+    pub fn clock_cpu(&mut self) {
+        self.mapper.clock_cpu();
+    }
+
+    /// Whether the cartridge is asserting the IRQ line
+    #[must_use]
+    pub fn irq(&self) -> bool {
+        self.mapper.irq()
+    }
+
+    fn vram_offset(&self, address: u16) -> usize {
+        self.mapper.mirroring().vram_offset(address) % self.vram.len()
+    }
+}
+
+impl PpuBus for Cartridge {
+    fn read(&mut self, address: u16) -> u8 {
+        if address < 0x2000 {
+            self.mapper.ppu_read(address)
+        } else {
+            self.vram[self.vram_offset(address)]
+        }
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        if address < 0x2000 {
+            self.mapper.ppu_write(address, value);
+        } else {
+            let offset = self.vram_offset(address);
+            self.vram[offset] = value;
+        }
+    }
+}
+
+impl Savestate for Cartridge {
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.mapper.save_state(writer);
+        writer.write_bytes(&self.vram);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), nes_state::Error> {
+        self.mapper.load_state(reader)?;
+        reader.read_bytes_into(&mut self.vram)
+    }
+}
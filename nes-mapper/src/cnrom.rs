@@ -0,0 +1,120 @@
+use {
+    crate::{Mapper, Memory, MemoryMap, Mirroring, RegionKind},
+    nes_state::{Error, Savestate, StateReader, StateWriter},
+};
+
+const CHR_BANK_SIZE: usize = 0x2000;
+
+/// Mapper 3; fixed PRG ROM with a switchable 8 KiB CHR ROM bank
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cnrom {
+    memory: Memory,
+    mirroring: Mirroring,
+    chr_bank: u8,
+    bus_conflicts: bool,
+}
+
+impl Cnrom {
+    #[must_use]
+    pub fn new(memory: Memory, mirroring: Mirroring) -> Self {
+        Self {
+            memory,
+            mirroring,
+            chr_bank: 0,
+            bus_conflicts: false,
+        }
+    }
+
+    /// Emulate the bus conflicts of boards where the ROM drives the data bus during register writes
+    ///
+    /// The mapper sees the bitwise AND of the written value and the ROM byte at the address
+    #[must_use]
+    pub fn with_bus_conflicts(mut self, enabled: bool) -> Self {
+        self.bus_conflicts = enabled;
+        self
+    }
+
+    #[must_use]
+    pub fn has_bus_conflicts(&self) -> bool {
+        self.bus_conflicts
+    }
+
+    // Value the mapper sees, masked by the ROM if there are bus conflicts
+    fn bus_value(&mut self, address: u16, value: u8) -> u8 {
+        if self.bus_conflicts {
+            value & self.cpu_read(address)
+        } else {
+            value
+        }
+    }
+}
+
+impl Mapper for Cnrom {
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        match address {
+            0x6000..=0x7FFF => self.memory.read_prg_ram(address),
+            0x8000..=0xFFFF => self.memory.read_prg_rom(0, 0x8000, address),
+            _ => 0,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        match address {
+            0x6000..=0x7FFF => self.memory.write_prg_ram(address, value),
+            0x8000..=0xFFFF => self.chr_bank = self.bus_value(address, value),
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        self.memory
+            .read_chr(usize::from(self.chr_bank), CHR_BANK_SIZE, address)
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8) {
+        self.memory
+            .write_chr(usize::from(self.chr_bank), CHR_BANK_SIZE, address, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn memory_map(&self) -> MemoryMap {
+        MemoryMap::new()
+            .prg_ram(&self.memory, true, true)
+            .fixed_prg_rom(&self.memory)
+            .cpu(0x8000..=0xFFFF, RegionKind::Registers("CHR bank"))
+            .chr(
+                &self.memory,
+                0x0000,
+                usize::from(self.chr_bank),
+                CHR_BANK_SIZE,
+                true,
+            )
+    }
+
+    fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+}
+
+impl Savestate for Cnrom {
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.memory.save_state(writer);
+        self.mirroring.save_state(writer);
+        writer.write_u8(self.chr_bank);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), Error> {
+        self.memory.load_state(reader)?;
+        self.mirroring.load_state(reader)?;
+        self.chr_bank = reader.read_u8()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,534 @@
+use {
+    crate::{Mapper, Memory, MemoryMap, Mirroring, RegionKind},
+    alloc::{vec, vec::Vec},
+    fds_parser::{Fds, SIDE_SIZE},
+    nes_state::{Error, Savestate, StateReader, StateWriter},
+};
+
+/// Size of the BIOS ROM of the Famicom Disk System
+pub const FDS_BIOS_SIZE: usize = 0x2000;
+
+const PRG_RAM_SIZE: usize = 0x8000;
+const CHR_RAM_SIZE: usize = 0x2000;
+
+// Gaps the drive expects in front of the first block and after every block, in bytes of zeros
+const LEADING_GAP: usize = 28300 / 8;
+const BLOCK_GAP: usize = 976 / 8;
+// Byte marking the end of a gap and the start of a block
+const BLOCK_START: u8 = 0x80;
+// The images don't store the CRCs, and the drive isn't checking them
+const FAKE_CRC: [u8; 2] = [0x4D, 0x62];
+
+// CPU cycles per byte at the 96.4 kbit/s of the drive
+const BYTE_CYCLES: u32 = 150;
+// CPU cycles the head takes to return to the start of the disk
+const REWIND_CYCLES: u32 = 50000;
+// CPU cycles a newly inserted disk takes to be detected, so games notice that the previous one was ejected
+const INSERT_CYCLES: u32 = 1_000_000;
+
+// Bits of the control register at `$4025`
+const MOTOR_ON: u8 = 0x01;
+const TRANSFER_RESET: u8 = 0x02;
+const READ_MODE: u8 = 0x04;
+const HORIZONTAL_MIRRORING: u8 = 0x08;
+const CRC_CONTROL: u8 = 0x10;
+const TRANSFER_ENABLED: u8 = 0x40;
+const TRANSFER_IRQ: u8 = 0x80;
+
+// Length of a block of the given type, starting at its type byte
+fn block_length(side: &[u8], position: usize, file_size: usize) -> Option<usize> {
+    match side.get(position)? {
+        1 => Some(56),
+        2 => Some(2),
+        3 => Some(16),
+        4 => Some(1 + file_size),
+        _ => None,
+    }
+}
+
+// Size of the file in a file header block
+fn file_size(block: &[u8]) -> usize {
+    usize::from(u16::from_le_bytes([block[13], block[14]]))
+}
+
+// Side as the drive reads it: the blocks separated by gaps and followed by their CRCs
+fn add_gaps(side: &[u8]) -> Vec<u8> {
+    let mut raw = vec![0; LEADING_GAP];
+    let mut position = 0;
+    let mut last_file_size = 0;
+    while let Some(length) = block_length(side, position, last_file_size) {
+        let Some(block) = side.get(position..position + length) else {
+            break;
+        };
+        if block[0] == 3 {
+            last_file_size = file_size(block);
+        }
+
+        raw.push(BLOCK_START);
+        raw.extend_from_slice(block);
+        raw.extend_from_slice(&FAKE_CRC);
+        raw.resize(raw.len() + BLOCK_GAP, 0);
+        position += length;
+    }
+
+    // Leave room for the files games save to the disk
+    raw.resize(raw.len().max(LEADING_GAP + SIDE_SIZE), 0);
+    raw
+}
+
+// Side in the format of the images, without the gaps and CRCs
+fn remove_gaps(raw: &[u8]) -> Vec<u8> {
+    let mut side = Vec::with_capacity(SIDE_SIZE);
+    let mut position = 0;
+    let mut last_file_size = 0;
+    while let Some(start) = raw[position..].iter().position(|&byte| byte != 0) {
+        position += start;
+        if raw[position] != BLOCK_START {
+            break;
+        }
+        position += 1;
+
+        let Some(length) = block_length(raw, position, last_file_size) else {
+            break;
+        };
+        let Some(block) = raw.get(position..position + length) else {
+            break;
+        };
+        if block[0] == 3 {
+            last_file_size = file_size(block);
+        }
+        if side.len() + length > SIDE_SIZE {
+            break;
+        }
+
+        side.extend_from_slice(block);
+        position += length + FAKE_CRC.len();
+        if position >= raw.len() {
+            break;
+        }
+    }
+
+    side.resize(SIDE_SIZE, 0);
+    side
+}
+
+/// RAM adapter and disk drive of the Famicom Disk System, which runs games from disks with the BIOS
+///
+/// The adapter has 32 KiB of PRG RAM at `$6000`-`$DFFF` that the BIOS loads the games into, the BIOS at `$E000`
+/// and 8 KiB of CHR RAM. The drive reads and writes the disk byte by byte at the speed of the real one, so games
+/// saving to the disk write to the inserted side. The disk registers at `$4020`-`$4033` include a CPU cycle timer IRQ;
+/// the audio at `$4040`-`$4092` is left to the APU.
+#[derive(Clone, Debug, PartialEq, Eq)]
+// The flags mirror the bits of the registers
+#[allow(clippy::struct_excessive_bools)]
+pub struct DiskSystem {
+    memory: Memory,
+    /// Sides as the drive reads them, with gaps between the blocks
+    sides: Vec<Vec<u8>>,
+    inserted: Option<usize>,
+    /// Cycles until the inserted disk is detected
+    insert_delay: u32,
+
+    disk_registers_enabled: bool,
+    timer_reload: u16,
+    timer_counter: u16,
+    timer_repeat: bool,
+    timer_enabled: bool,
+    timer_irq: bool,
+
+    /// Value of `$4025`
+    control: u8,
+    write_data: u8,
+    read_data: u8,
+    transfer_complete: bool,
+    transfer_irq: bool,
+
+    /// Offset of the head on the inserted side
+    position: usize,
+    /// Cycles until the next byte passes the head
+    delay: u32,
+    scanning: bool,
+    end_of_head: bool,
+    gap_ended: bool,
+}
+
+impl DiskSystem {
+    /// Build the RAM adapter with the BIOS and the disk image, without any disk inserted
+    ///
+    /// Returns `None` if the BIOS doesn't have the size of [`FDS_BIOS_SIZE`]
+    #[must_use]
+    pub fn new(bios: &[u8], fds: &Fds<'_>) -> Option<Self> {
+        if bios.len() != FDS_BIOS_SIZE {
+            return None;
+        }
+
+        let memory = Memory {
+            prg_rom: bios.to_vec(),
+            prg_ram: vec![0; PRG_RAM_SIZE],
+            chr: vec![0; CHR_RAM_SIZE],
+            chr_is_ram: true,
+        };
+
+        Some(Self {
+            memory,
+            sides: fds.sides.iter().map(|side| add_gaps(side)).collect(),
+            inserted: None,
+            insert_delay: 0,
+            disk_registers_enabled: false,
+            timer_reload: 0,
+            timer_counter: 0,
+            timer_repeat: false,
+            timer_enabled: false,
+            timer_irq: false,
+            control: 0,
+            write_data: 0,
+            read_data: 0,
+            transfer_complete: false,
+            transfer_irq: false,
+            position: 0,
+            delay: 0,
+            scanning: false,
+            end_of_head: true,
+            gap_ended: false,
+        })
+    }
+
+    /// Amount of disk sides in the image
+    #[must_use]
+    pub fn sides(&self) -> usize {
+        self.sides.len()
+    }
+
+    /// Side in the drive, if any
+    #[must_use]
+    pub fn inserted_side(&self) -> Option<usize> {
+        self.inserted
+    }
+
+    /// Put a side into the drive, replacing the inserted one
+    ///
+    /// The drive takes a moment to detect the side, so games see the previous one getting ejected.
+    /// Returns `false` if the image doesn't have the side.
+    pub fn insert_disk(&mut self, side: usize) -> bool {
+        if side >= self.sides.len() {
+            return false;
+        }
+
+        self.inserted = Some(side);
+        self.insert_delay = INSERT_CYCLES;
+        true
+    }
+
+    pub fn eject_disk(&mut self) {
+        self.inserted = None;
+    }
+
+    // Whether the drive reports a disk, which it doesn't while it's still detecting one
+    fn disk_present(&self) -> bool {
+        self.inserted.is_some() && self.insert_delay == 0
+    }
+
+    /// Disk image with the changes the game saved to it, in the format of the image it was built from
+    #[must_use]
+    pub fn to_fds(&self) -> Fds<'static> {
+        Fds {
+            sides: self
+                .sides
+                .iter()
+                .map(|raw| remove_gaps(raw).into())
+                .collect(),
+        }
+    }
+
+    fn clock_timer(&mut self) {
+        if !self.timer_enabled {
+            return;
+        }
+
+        if self.timer_counter == 0 {
+            self.timer_irq = true;
+            self.timer_counter = self.timer_reload;
+            self.timer_enabled = self.timer_repeat;
+        } else {
+            self.timer_counter -= 1;
+        }
+    }
+
+    // Move the disk under the head by one cycle, transferring a byte whenever one passes it
+    fn clock_drive(&mut self) {
+        if self.insert_delay > 0 {
+            self.insert_delay -= 1;
+        }
+
+        let Some(side) = self.inserted.filter(|_| self.insert_delay == 0) else {
+            self.end_of_head = true;
+            self.scanning = false;
+            return;
+        };
+        if self.control & MOTOR_ON == 0 {
+            self.end_of_head = true;
+            self.scanning = false;
+            return;
+        }
+        if self.control & TRANSFER_RESET != 0 && !self.scanning {
+            return;
+        }
+
+        if self.end_of_head {
+            self.delay = REWIND_CYCLES;
+            self.end_of_head = false;
+            self.position = 0;
+            self.gap_ended = false;
+            return;
+        }
+        if self.delay > 0 {
+            self.delay -= 1;
+            return;
+        }
+
+        self.scanning = true;
+        let transfer_enabled = self.control & TRANSFER_ENABLED != 0;
+        let mut irq = self.control & TRANSFER_IRQ != 0;
+        let raw = &mut self.sides[side];
+        if self.control & READ_MODE != 0 {
+            let value = raw[self.position];
+            if !transfer_enabled {
+                self.gap_ended = false;
+            } else if value != 0 && !self.gap_ended {
+                // The start mark of the block isn't announced
+                self.gap_ended = true;
+                irq = false;
+            }
+
+            if self.gap_ended {
+                self.transfer_complete = true;
+                self.read_data = value;
+                self.transfer_irq |= irq;
+            }
+        } else {
+            let value = if self.control & CRC_CONTROL == 0 {
+                self.transfer_complete = true;
+                self.transfer_irq |= irq;
+                self.write_data
+            } else {
+                FAKE_CRC[0]
+            };
+
+            raw[self.position] = if transfer_enabled { value } else { 0 };
+            self.gap_ended = false;
+        }
+
+        self.position += 1;
+        if self.position >= raw.len() {
+            self.control &= !MOTOR_ON;
+        } else {
+            self.delay = BYTE_CYCLES;
+        }
+    }
+
+    fn read_status(&mut self) -> u8 {
+        let mut status = u8::from(self.timer_irq) | u8::from(self.transfer_complete) << 1;
+        if self.end_of_head {
+            status |= 0x40;
+        }
+
+        self.timer_irq = false;
+        self.transfer_complete = false;
+        self.transfer_irq = false;
+        status
+    }
+
+    fn read_drive_status(&self) -> u8 {
+        let present = self.disk_present();
+        let ready = present && self.scanning;
+
+        // The upper bits are open bus, which usually holds the high byte of the address
+        0x40 | u8::from(!present) | u8::from(!ready) << 1 | u8::from(!present) << 2
+    }
+}
+
+impl Mapper for DiskSystem {
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        match address {
+            0x4030 if self.disk_registers_enabled => self.read_status(),
+            0x4031 if self.disk_registers_enabled => {
+                self.transfer_complete = false;
+                self.transfer_irq = false;
+                self.read_data
+            }
+            0x4032 if self.disk_registers_enabled => self.read_drive_status(),
+            // The battery of the drive is fine
+            0x4033 if self.disk_registers_enabled => 0x80,
+            0x6000..=0xDFFF => self.memory.read_prg_ram(address),
+            0xE000..=0xFFFF => self.memory.read_prg_rom(0, FDS_BIOS_SIZE, address),
+            _ => 0,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        match address {
+            0x4020 => self.timer_reload = (self.timer_reload & 0xFF00) | u16::from(value),
+            0x4021 => self.timer_reload = (self.timer_reload & 0x00FF) | u16::from(value) << 8,
+            0x4022 => {
+                self.timer_repeat = value & 0x01 != 0;
+                self.timer_enabled = value & 0x02 != 0 && self.disk_registers_enabled;
+                if self.timer_enabled {
+                    self.timer_counter = self.timer_reload;
+                } else {
+                    self.timer_irq = false;
+                }
+            }
+            0x4023 => {
+                self.disk_registers_enabled = value & 0x01 != 0;
+                if !self.disk_registers_enabled {
+                    self.timer_enabled = false;
+                    self.timer_irq = false;
+                    self.transfer_irq = false;
+                }
+            }
+            0x4024 if self.disk_registers_enabled => {
+                self.write_data = value;
+                self.transfer_complete = false;
+                self.transfer_irq = false;
+            }
+            0x4025 if self.disk_registers_enabled => {
+                self.control = value;
+                self.transfer_irq = false;
+            }
+            0x6000..=0xDFFF => self.memory.write_prg_ram(address, value),
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        self.memory.read_chr(0, CHR_RAM_SIZE, address)
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8) {
+        self.memory.write_chr(0, CHR_RAM_SIZE, address, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        if self.control & HORIZONTAL_MIRRORING == 0 {
+            Mirroring::Vertical
+        } else {
+            Mirroring::Horizontal
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.timer_irq || self.transfer_irq
+    }
+
+    fn clock_cpu(&mut self) {
+        self.clock_timer();
+        self.clock_drive();
+    }
+
+    fn disk_system(&self) -> Option<&DiskSystem> {
+        Some(self)
+    }
+
+    fn disk_system_mut(&mut self) -> Option<&mut DiskSystem> {
+        Some(self)
+    }
+
+    fn memory_map(&self) -> MemoryMap {
+        MemoryMap::new()
+            .cpu(0x4020..=0x403F, RegionKind::Registers("Disk drive"))
+            .cpu(0x4040..=0x409F, RegionKind::Registers("Audio"))
+            .cpu(
+                0x6000..=0xDFFF,
+                RegionKind::PrgRam {
+                    size: PRG_RAM_SIZE,
+                    enabled: true,
+                    writable: true,
+                },
+            )
+            .prg_rom(&self.memory, 0xE000, 0, FDS_BIOS_SIZE, false)
+            .chr(&self.memory, 0x0000, 0, CHR_RAM_SIZE, false)
+    }
+
+    fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+}
+
+// The disks are part of the state, since games write to them
+impl Savestate for DiskSystem {
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.memory.save_state(writer);
+        for side in &self.sides {
+            writer.write_bytes(side);
+        }
+        // Images have at most 255 sides
+        #[allow(clippy::cast_possible_truncation)]
+        writer.write_u8(self.inserted.map_or(0xFF, |side| side as u8));
+        writer.write_u32(self.insert_delay);
+
+        writer.write_bool(self.disk_registers_enabled);
+        writer.write_u16(self.timer_reload);
+        writer.write_u16(self.timer_counter);
+        writer.write_bool(self.timer_repeat);
+        writer.write_bool(self.timer_enabled);
+        writer.write_bool(self.timer_irq);
+
+        writer.write_u8(self.control);
+        writer.write_u8(self.write_data);
+        writer.write_u8(self.read_data);
+        writer.write_bool(self.transfer_complete);
+        writer.write_bool(self.transfer_irq);
+
+        // Sides are far smaller than 4 GiB
+        #[allow(clippy::cast_possible_truncation)]
+        writer.write_u32(self.position as u32);
+        writer.write_u32(self.delay);
+        writer.write_bool(self.scanning);
+        writer.write_bool(self.end_of_head);
+        writer.write_bool(self.gap_ended);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), Error> {
+        self.memory.load_state(reader)?;
+        for side in &mut self.sides {
+            reader.read_bytes_into(side)?;
+        }
+        self.inserted = match reader.read_u8()? {
+            0xFF => None,
+            side if usize::from(side) < self.sides.len() => Some(usize::from(side)),
+            _ => return Err(Error::InvalidValue),
+        };
+        self.insert_delay = reader.read_u32()?;
+
+        self.disk_registers_enabled = reader.read_bool()?;
+        self.timer_reload = reader.read_u16()?;
+        self.timer_counter = reader.read_u16()?;
+        self.timer_repeat = reader.read_bool()?;
+        self.timer_enabled = reader.read_bool()?;
+        self.timer_irq = reader.read_bool()?;
+
+        self.control = reader.read_u8()?;
+        self.write_data = reader.read_u8()?;
+        self.read_data = reader.read_u8()?;
+        self.transfer_complete = reader.read_bool()?;
+        self.transfer_irq = reader.read_bool()?;
+
+        self.position = reader.read_u32()? as usize;
+        if self
+            .inserted
+            .is_some_and(|side| self.position > self.sides[side].len())
+        {
+            return Err(Error::InvalidValue);
+        }
+        self.delay = reader.read_u32()?;
+        self.scanning = reader.read_bool()?;
+        self.end_of_head = reader.read_bool()?;
+        self.gap_ended = reader.read_bool()?;
+
+        Ok(())
+    }
+}
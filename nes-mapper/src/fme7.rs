@@ -0,0 +1,197 @@
+use {
+    crate::{CycleCounter, IrqCounter, Mapper, Memory, MemoryMap, Mirroring, RegionKind},
+    nes_state::{Error, Savestate, StateReader, StateWriter},
+};
+
+const PRG_BANK_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x0400;
+
+// Bits of the register selecting the memory at `$6000`
+const PRG_RAM_SELECTED: u8 = 0x40;
+const PRG_RAM_ENABLED: u8 = 0x80;
+
+/// Mapper 69; Sunsoft FME-7 with 8 KiB PRG banks, 1 KiB CHR banks and a CPU cycle IRQ counter
+///
+/// The registers are written through a command register at `$8000` and a parameter register at `$A000`.
+/// The audio of the Sunsoft 5B variant isn't emulated.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fme7 {
+    memory: Memory,
+    command: u8,
+    chr_banks: [u8; 8],
+    /// Bank at `$6000`, which selects ROM or RAM, followed by the banks at `$8000`, `$A000` and `$C000`
+    prg_banks: [u8; 4],
+    mirroring: Mirroring,
+    irq: CycleCounter,
+}
+
+impl Fme7 {
+    #[must_use]
+    pub fn new(memory: Memory) -> Self {
+        Self {
+            memory,
+            command: 0,
+            chr_banks: [0; 8],
+            prg_banks: [0; 4],
+            mirroring: Mirroring::Vertical,
+            irq: CycleCounter::new(),
+        }
+    }
+
+    fn write_parameter(&mut self, value: u8) {
+        match self.command {
+            0x0..=0x7 => self.chr_banks[usize::from(self.command)] = value,
+            0x8..=0xB => self.prg_banks[usize::from(self.command - 0x8)] = value,
+            0xC => {
+                self.mirroring = match value & 0x03 {
+                    0 => Mirroring::Vertical,
+                    1 => Mirroring::Horizontal,
+                    2 => Mirroring::SingleScreenLower,
+                    _ => Mirroring::SingleScreenUpper,
+                };
+            }
+            0xD => self.irq.write_control(value),
+            0xE => self.irq.set_low_byte(value),
+            _ => self.irq.set_high_byte(value),
+        }
+    }
+
+    fn prg_rom_bank(&self, address: u16) -> usize {
+        match address {
+            0x6000..=0xDFFF => {
+                usize::from(self.prg_banks[usize::from((address - 0x6000) / 0x2000)] & 0x3F)
+            }
+            _ => self.memory.prg_banks(PRG_BANK_SIZE) - 1,
+        }
+    }
+
+    fn chr_bank(&self, address: u16) -> usize {
+        usize::from(self.chr_banks[usize::from(address / 0x0400)])
+    }
+
+    fn ram_selected(&self) -> bool {
+        self.prg_banks[0] & PRG_RAM_SELECTED != 0
+    }
+
+    fn ram_enabled(&self) -> bool {
+        self.ram_selected() && self.prg_banks[0] & PRG_RAM_ENABLED != 0
+    }
+}
+
+impl Mapper for Fme7 {
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        match address {
+            0x6000..=0x7FFF if self.ram_enabled() => self.memory.read_prg_ram(address),
+            0x6000..=0x7FFF if self.ram_selected() => 0,
+            0x6000..=0xFFFF => {
+                let bank = self.prg_rom_bank(address);
+                self.memory.read_prg_rom(bank, PRG_BANK_SIZE, address)
+            }
+            _ => 0,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        match address {
+            0x6000..=0x7FFF if self.ram_enabled() => self.memory.write_prg_ram(address, value),
+            0x8000..=0x9FFF => self.command = value & 0x0F,
+            0xA000..=0xBFFF => self.write_parameter(value),
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        let bank = self.chr_bank(address);
+        self.memory.read_chr(bank, CHR_BANK_SIZE, address)
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8) {
+        let bank = self.chr_bank(address);
+        self.memory.write_chr(bank, CHR_BANK_SIZE, address, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.irq()
+    }
+
+    fn clock_cpu(&mut self) {
+        self.irq.clock_cpu();
+    }
+
+    fn memory_map(&self) -> MemoryMap {
+        let mut map = if self.ram_selected() {
+            let enabled = self.ram_enabled();
+            MemoryMap::new().prg_ram(&self.memory, enabled, enabled)
+        } else {
+            MemoryMap::new().prg_rom(
+                &self.memory,
+                0x6000,
+                self.prg_rom_bank(0x6000),
+                PRG_BANK_SIZE,
+                true,
+            )
+        };
+
+        for address in [0x8000, 0xA000, 0xC000, 0xE000] {
+            let bank = self.prg_rom_bank(address);
+            map = map.prg_rom(
+                &self.memory,
+                address,
+                bank,
+                PRG_BANK_SIZE,
+                address != 0xE000,
+            );
+        }
+
+        map = map
+            .cpu(0x8000..=0x9FFF, RegionKind::Registers("Command"))
+            .cpu(0xA000..=0xBFFF, RegionKind::Registers("Parameter"));
+
+        for slot in 0..8 {
+            let address = slot * 0x0400;
+            map = map.chr(
+                &self.memory,
+                address,
+                self.chr_bank(address),
+                CHR_BANK_SIZE,
+                true,
+            );
+        }
+
+        map
+    }
+
+    fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+}
+
+impl Savestate for Fme7 {
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.memory.save_state(writer);
+        self.mirroring.save_state(writer);
+        writer.write_u8(self.command);
+        writer.write_bytes(&self.chr_banks);
+        writer.write_bytes(&self.prg_banks);
+        self.irq.save_state(writer);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), Error> {
+        self.memory.load_state(reader)?;
+        self.mirroring.load_state(reader)?;
+        self.command = reader.read_u8()?;
+        reader.read_bytes_into(&mut self.chr_banks)?;
+        reader.read_bytes_into(&mut self.prg_banks)?;
+        self.irq.load_state(reader)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,245 @@
+use {alloc::vec::Vec, core::cmp::Reverse, ines_parser::Header};
+
+// Stores to absolute addresses, including the indexed ones
+const STORE_OPCODES: [u8; 5] = [
+    0x8D, // STA
+    0x8E, // STX
+    0x8C, // STY
+    0x9D, // STA ,X
+    0x99, // STA ,Y
+];
+const LSR_A: u8 = 0x4A;
+
+// Registers of the MMC3, the even and odd addresses of every 8 KiB page
+const MMC3_REGISTERS: [u16; 8] = [
+    0x8000, 0x8001, 0xA000, 0xA001, 0xC000, 0xC001, 0xE000, 0xE001,
+];
+
+// Address lines the Konami VRCs can use to select the register within a page
+const VRC_LINES: u16 = 0x00CF;
+
+const NROM_MAX_PRG_ROM_SIZE: usize = 0x8000;
+const NROM_MAX_CHR_ROM_SIZE: usize = 0x2000;
+const AXROM_BANK_SIZE: usize = 0x8000;
+// UxROM boards top out at 256 KiB, larger ones are UNROM 512
+const UXROM_MAX_PRG_ROM_SIZE: usize = 0x4_0000;
+
+/// Mapper the code of a ROM looks like it was written for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MapperCandidate {
+    pub mapper_number: u8,
+    /// Amount of register writes in the code pointing to the mapper
+    pub evidence: usize,
+    /// What gave the mapper away
+    pub reason: &'static str,
+}
+
+/// Mappers inferred from the register writes in the PRG ROM, see [`infer_mapper`]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MapperInference {
+    /// Candidates ordered by their evidence, the most likely one first
+    pub candidates: Vec<MapperCandidate>,
+}
+
+impl MapperInference {
+    /// Mapper with the most evidence; the first one if there are equally likely ones
+    #[must_use]
+    pub fn likely(&self) -> Option<u8> {
+        self.candidates
+            .first()
+            .map(|candidate| candidate.mapper_number)
+    }
+
+    /// Whether the mapper number is one of the candidates
+    #[must_use]
+    pub fn contains(&self, mapper_number: u8) -> bool {
+        self.candidates
+            .iter()
+            .any(|candidate| candidate.mapper_number == mapper_number)
+    }
+
+    /// Whether the code contradicts the mapper of the header, which points to a broken header
+    ///
+    /// Nothing gets contradicted if no mapper could be inferred.
+    #[must_use]
+    pub fn contradicts(&self, header: &Header) -> bool {
+        !self.candidates.is_empty() && !self.contains(header.mapper_number)
+    }
+}
+
+// Store instruction found in the PRG ROM
+struct Store {
+    offset: usize,
+    address: u16,
+}
+
+// Absolute stores to the cartridge space; data which happens to look like one is counted as well
+fn find_stores(prg_rom: &[u8]) -> Vec<Store> {
+    prg_rom
+        .windows(3)
+        .enumerate()
+        .filter(|(_, bytes)| STORE_OPCODES.contains(&bytes[0]))
+        .map(|(offset, bytes)| Store {
+            offset,
+            address: u16::from_le_bytes([bytes[1], bytes[2]]),
+        })
+        .filter(|store| store.address >= 0x8000)
+        .collect()
+}
+
+// `STA` followed by `LSR A` and another `STA` to the same register, the way MMC1 registers are written bit by bit
+fn mmc1_serial_writes(prg_rom: &[u8], stores: &[Store]) -> usize {
+    stores
+        .iter()
+        .filter(|store| {
+            let next = store.offset + 3;
+            prg_rom.get(next) == Some(&LSR_A)
+                && prg_rom.get(next + 1..next + 4).is_some_and(|bytes| {
+                    let address = u16::from_le_bytes([bytes[1], bytes[2]]);
+                    bytes[0] == 0x8D && address >> 13 == store.address >> 13
+                })
+        })
+        .count()
+}
+
+fn count_writes(stores: &[Store], address: u16) -> usize {
+    stores
+        .iter()
+        .filter(|store| store.address == address)
+        .count()
+}
+
+// Register writes of the Konami VRCs, which select the register with the lowest address lines of each 4 KiB page
+fn vrc_candidates(stores: &[Store]) -> Option<(&'static [u8], usize, &'static str)> {
+    let registers = stores
+        .iter()
+        .filter(|store| store.address & 0x0FFF & !VRC_LINES == 0)
+        .collect::<Vec<_>>();
+    let writes_page = |page: u16| registers.iter().any(|store| store.address & 0xF000 == page);
+
+    // Unlike the MMC3 and FME-7, the VRCs have registers in the odd pages
+    if !writes_page(0xB000) || !writes_page(0xD000) {
+        return None;
+    }
+
+    let lines = registers
+        .iter()
+        .fold(0, |lines, store| lines | store.address & VRC_LINES);
+    // The VRC6 has audio registers at `$A001` and `$A002`, the VRC4 only a PRG bank in the whole page
+    let audio = registers
+        .iter()
+        .any(|store| store.address & 0xF000 == 0xA000 && store.address & VRC_LINES != 0);
+
+    let candidates: (&[u8], _) = match lines {
+        0 => return None,
+        _ if lines & !0x03 == 0 && audio => (&[24, 26], "VRC6 registers and audio"),
+        _ if lines & !0x06 == 0 || lines & !0xC0 == 0 => {
+            (&[21], "VRC4 registers on A1/A2 or A6/A7")
+        }
+        _ if lines & !0x03 == 0 || lines & !0x0C == 0 => {
+            (&[23, 25], "VRC4 registers on A0/A1 or A2/A3")
+        }
+        _ => (&[21, 23, 25], "VRC4 registers"),
+    };
+
+    Some((candidates.0, registers.len(), candidates.1))
+}
+
+// Whether every 32 KiB bank has the same reset vector, which games without a fixed bank need
+fn vectors_in_every_bank(prg_rom: &[u8]) -> bool {
+    let reset_vector =
+        |bank: &[u8]| u16::from_le_bytes([bank[AXROM_BANK_SIZE - 4], bank[AXROM_BANK_SIZE - 3]]);
+    let mut banks = prg_rom.chunks_exact(AXROM_BANK_SIZE);
+    let Some(first) = banks.next().map(reset_vector) else {
+        return false;
+    };
+
+    // Padding looks like a vector shared by every bank as well
+    first >= 0x8000 && first != 0xFFFF && banks.all(|bank| reset_vector(bank) == first)
+}
+
+// Boards of discrete logic chips have a single register anywhere in `$8000`-`$FFFF`, so they're told apart by the sizes
+fn discrete_board(
+    prg_rom: &[u8],
+    chr_rom_size: usize,
+    writes: usize,
+) -> Option<(u8, &'static str)> {
+    if writes == 0 {
+        let fits_nrom =
+            prg_rom.len() <= NROM_MAX_PRG_ROM_SIZE && chr_rom_size <= NROM_MAX_CHR_ROM_SIZE;
+        return fits_nrom.then_some((0, "no bank switching"));
+    }
+
+    if chr_rom_size > NROM_MAX_CHR_ROM_SIZE {
+        (prg_rom.len() <= NROM_MAX_PRG_ROM_SIZE)
+            .then_some((3, "CHR ROM banking with fixed PRG ROM"))
+    } else if prg_rom.len() <= NROM_MAX_PRG_ROM_SIZE {
+        None
+    } else if vectors_in_every_bank(prg_rom) {
+        Some((7, "32 KiB banks with their own vectors"))
+    } else if prg_rom.len() > UXROM_MAX_PRG_ROM_SIZE {
+        Some((30, "PRG ROM banking beyond 256 KiB"))
+    } else {
+        Some((2, "PRG ROM banking with a fixed last bank"))
+    }
+}
+
+/// Infer the mapper from the way the code writes to the registers in the cartridge space
+///
+/// Serial writes to `$8000`-`$FFFF` interleaved with `LSR A` point to the MMC1, paired writes to the even and odd
+/// addresses of `$8000`, `$A000`, `$C000` and `$E000` to the MMC3, a command register at `$8000` with its parameter at
+/// `$A000` to the FME-7 and registers in every 4 KiB page to the VRCs. Boards of discrete logic chips are
+/// told apart by the ROM sizes. This helps with headerless dumps and to cross-check headers
+/// (see [`MapperInference::contradicts`]). It's a heuristic: data looking like stores is counted, and the wirings of
+/// VRC boards can't always be told apart, so equally likely candidates are reported together.
+#[must_use]
+pub fn infer_mapper(prg_rom: &[u8], chr_rom_size: usize) -> MapperInference {
+    let stores = find_stores(prg_rom);
+    let mut candidates = Vec::new();
+    let mut add = |mapper_number, evidence, reason| {
+        candidates.push(MapperCandidate {
+            mapper_number,
+            evidence,
+            reason,
+        });
+    };
+
+    let serial_writes = mmc1_serial_writes(prg_rom, &stores);
+    let mmc3_writes = MMC3_REGISTERS.map(|address| count_writes(&stores, address));
+    let vrc = vrc_candidates(&stores);
+    let discrete = serial_writes == 0 && mmc3_writes[0] == 0 && vrc.is_none();
+
+    if serial_writes > 0 {
+        add(1, serial_writes, "bitwise writes to the serial register");
+    } else if mmc3_writes[0] > 0 && mmc3_writes[2] > 0 && mmc3_writes[1] == 0 {
+        // The MMC1 writes to `$8000` and `$A000` as well
+        add(
+            69,
+            mmc3_writes[0] + mmc3_writes[2],
+            "command and parameter writes to $8000 and $A000",
+        );
+    }
+    if mmc3_writes[0] > 0 && mmc3_writes[1] > 0 {
+        add(
+            4,
+            mmc3_writes.iter().sum(),
+            "bank select and bank data writes to $8000 and $8001",
+        );
+    }
+
+    if let Some((mapper_numbers, evidence, reason)) = vrc {
+        for mapper_number in mapper_numbers {
+            add(*mapper_number, evidence, reason);
+        }
+    }
+
+    if discrete {
+        if let Some((mapper_number, reason)) = discrete_board(prg_rom, chr_rom_size, stores.len()) {
+            add(mapper_number, stores.len(), reason);
+        }
+    }
+
+    // Stable, so equally likely candidates keep their order
+    candidates.sort_by_key(|candidate| Reverse(candidate.evidence));
+    MapperInference { candidates }
+}
@@ -0,0 +1,338 @@
+use nes_state::{Error, Savestate, StateReader, StateWriter};
+
+// CPU cycles A12 has to stay low for before a rising edge clocks the MMC3 counter
+const A12_FILTER_CYCLES: u8 = 3;
+
+// PPU dots per scanline, which the VRC prescaler counts down in steps of the dots per CPU cycle
+const VRC_PRESCALER_PERIOD: u16 = 341;
+const VRC_PRESCALER_STEP: u16 = 3;
+
+/// Counter of a mapper which raises IRQs, shared between the mappers with the same kind of counter
+///
+/// Mappers forward [`Mapper::clock_cpu`](crate::Mapper::clock_cpu) and the pattern table addresses of the PPU to
+/// their counter and report its [`IrqCounter::irq`] as their own.
+pub trait IrqCounter: Savestate {
+    /// Clock the counter with one CPU cycle
+    fn clock_cpu(&mut self) {}
+
+    /// Watch an address the PPU accessed in the pattern tables (`$0000`-`$1FFF`)
+    fn watch_ppu_address(&mut self, _address: u16) {}
+
+    /// Whether the counter is asserting the IRQ line
+    fn irq(&self) -> bool;
+
+    /// Release the IRQ line
+    fn acknowledge(&mut self);
+}
+
+/// Scanline counter of the MMC3, clocked by rising edges of the PPU address line A12
+///
+/// The PPU fetches the background and the sprites from different pattern tables, so A12 rises once per scanline.
+/// Like the hardware, edges only count once A12 was low for a few CPU cycles, which filters out the quick
+/// changes of 8×16 sprites mixing both pattern tables.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ScanlineCounter {
+    latch: u8,
+    counter: u8,
+    reload: bool,
+    enabled: bool,
+    pending: bool,
+    last_a12: bool,
+    /// CPU cycles since A12 went low, up to the filter length
+    low_cycles: u8,
+}
+
+impl ScanlineCounter {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Value the counter gets reloaded with
+    pub fn set_latch(&mut self, value: u8) {
+        self.latch = value;
+    }
+
+    /// Reload the counter with the latch on the next clock
+    pub fn reload(&mut self) {
+        self.counter = 0;
+        self.reload = true;
+    }
+
+    /// Disable the IRQ and release the IRQ line
+    pub fn disable(&mut self) {
+        self.enabled = false;
+        self.pending = false;
+    }
+
+    pub fn enable(&mut self) {
+        self.enabled = true;
+    }
+
+    fn clock(&mut self) {
+        if self.counter == 0 || self.reload {
+            self.counter = self.latch;
+            self.reload = false;
+        } else {
+            self.counter -= 1;
+        }
+
+        if self.counter == 0 && self.enabled {
+            self.pending = true;
+        }
+    }
+}
+
+impl IrqCounter for ScanlineCounter {
+    fn clock_cpu(&mut self) {
+        if !self.last_a12 && self.low_cycles < A12_FILTER_CYCLES {
+            self.low_cycles += 1;
+        }
+    }
+
+    fn watch_ppu_address(&mut self, address: u16) {
+        let a12 = address & 0x1000 != 0;
+        if a12 && !self.last_a12 && self.low_cycles >= A12_FILTER_CYCLES {
+            self.clock();
+        }
+
+        if a12 != self.last_a12 {
+            self.low_cycles = 0;
+        }
+        self.last_a12 = a12;
+    }
+
+    fn irq(&self) -> bool {
+        self.pending
+    }
+
+    fn acknowledge(&mut self) {
+        self.pending = false;
+    }
+}
+
+impl Savestate for ScanlineCounter {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.latch);
+        writer.write_u8(self.counter);
+        writer.write_bool(self.reload);
+        writer.write_bool(self.enabled);
+        writer.write_bool(self.pending);
+        writer.write_bool(self.last_a12);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), Error> {
+        self.latch = reader.read_u8()?;
+        self.counter = reader.read_u8()?;
+        self.reload = reader.read_bool()?;
+        self.enabled = reader.read_bool()?;
+        self.pending = reader.read_bool()?;
+        self.last_a12 = reader.read_bool()?;
+        // States are taken between instructions, long after the last change of A12 within a scanline
+        self.low_cycles = if self.last_a12 { 0 } else { A12_FILTER_CYCLES };
+
+        Ok(())
+    }
+}
+
+/// IRQ counter of the Konami VRC4, VRC6 and VRC7
+///
+/// An 8-bit counter counting up to the overflow, either every CPU cycle or every scanline. Scanlines are timed
+/// by a prescaler dividing the CPU clock, since the VRCs don't watch the PPU.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VrcIrq {
+    latch: u8,
+    counter: u8,
+    prescaler: u16,
+    enable_after_acknowledge: bool,
+    enabled: bool,
+    cycle_mode: bool,
+    pending: bool,
+}
+
+impl Default for VrcIrq {
+    fn default() -> Self {
+        Self {
+            latch: 0,
+            counter: 0,
+            prescaler: VRC_PRESCALER_PERIOD,
+            enable_after_acknowledge: false,
+            enabled: false,
+            cycle_mode: false,
+            pending: false,
+        }
+    }
+}
+
+impl VrcIrq {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Value the counter gets reloaded with when it overflows
+    pub fn set_latch(&mut self, value: u8) {
+        self.latch = value;
+    }
+
+    /// Set one half of the latch, for the VRC4 which writes it in nibbles
+    pub fn set_latch_nibble(&mut self, value: u8, high: bool) {
+        self.latch = if high {
+            (self.latch & 0x0F) | (value << 4)
+        } else {
+            (self.latch & 0xF0) | (value & 0x0F)
+        };
+    }
+
+    /// Write the control register; enabling the counter reloads it
+    ///
+    /// Bit 0 re-enables the counter on acknowledgement, bit 1 enables it and bit 2 selects the CPU cycle mode.
+    pub fn write_control(&mut self, value: u8) {
+        self.enable_after_acknowledge = value & 0x01 != 0;
+        self.enabled = value & 0x02 != 0;
+        self.cycle_mode = value & 0x04 != 0;
+        self.pending = false;
+
+        if self.enabled {
+            self.counter = self.latch;
+            self.prescaler = VRC_PRESCALER_PERIOD;
+        }
+    }
+
+    fn clock(&mut self) {
+        if self.counter == 0xFF {
+            self.counter = self.latch;
+            self.pending = true;
+        } else {
+            self.counter += 1;
+        }
+    }
+}
+
+impl IrqCounter for VrcIrq {
+    fn clock_cpu(&mut self) {
+        if !self.enabled {
+            return;
+        }
+
+        if self.cycle_mode {
+            self.clock();
+        } else if self.prescaler <= VRC_PRESCALER_STEP {
+            self.prescaler += VRC_PRESCALER_PERIOD - VRC_PRESCALER_STEP;
+            self.clock();
+        } else {
+            self.prescaler -= VRC_PRESCALER_STEP;
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.pending
+    }
+
+    /// Release the IRQ line; the counter gets enabled or disabled as the control register asked for
+    fn acknowledge(&mut self) {
+        self.pending = false;
+        self.enabled = self.enable_after_acknowledge;
+    }
+}
+
+impl Savestate for VrcIrq {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.latch);
+        writer.write_u8(self.counter);
+        writer.write_u16(self.prescaler);
+        writer.write_bool(self.enable_after_acknowledge);
+        writer.write_bool(self.enabled);
+        writer.write_bool(self.cycle_mode);
+        writer.write_bool(self.pending);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), Error> {
+        self.latch = reader.read_u8()?;
+        self.counter = reader.read_u8()?;
+        self.prescaler = reader.read_u16()?;
+        if self.prescaler == 0 || self.prescaler > VRC_PRESCALER_PERIOD {
+            return Err(Error::InvalidValue);
+        }
+        self.enable_after_acknowledge = reader.read_bool()?;
+        self.enabled = reader.read_bool()?;
+        self.cycle_mode = reader.read_bool()?;
+        self.pending = reader.read_bool()?;
+
+        Ok(())
+    }
+}
+
+/// 16-bit CPU cycle counter of the Sunsoft FME-7, raising an IRQ when it wraps around from 0
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CycleCounter {
+    counter: u16,
+    counter_enabled: bool,
+    irq_enabled: bool,
+    pending: bool,
+}
+
+impl CycleCounter {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write the control register, which also acknowledges the IRQ
+    ///
+    /// Bit 0 enables the IRQ, bit 7 the counting.
+    pub fn write_control(&mut self, value: u8) {
+        self.irq_enabled = value & 0x01 != 0;
+        self.counter_enabled = value & 0x80 != 0;
+        self.pending = false;
+    }
+
+    pub fn set_low_byte(&mut self, value: u8) {
+        self.counter = (self.counter & 0xFF00) | u16::from(value);
+    }
+
+    pub fn set_high_byte(&mut self, value: u8) {
+        self.counter = (self.counter & 0x00FF) | u16::from(value) << 8;
+    }
+}
+
+impl IrqCounter for CycleCounter {
+    fn clock_cpu(&mut self) {
+        if !self.counter_enabled {
+            return;
+        }
+
+        self.counter = self.counter.wrapping_sub(1);
+        if self.counter == 0xFFFF && self.irq_enabled {
+            self.pending = true;
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.pending
+    }
+
+    fn acknowledge(&mut self) {
+        self.pending = false;
+    }
+}
+
+impl Savestate for CycleCounter {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u16(self.counter);
+        writer.write_bool(self.counter_enabled);
+        writer.write_bool(self.irq_enabled);
+        writer.write_bool(self.pending);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), Error> {
+        self.counter = reader.read_u16()?;
+        self.counter_enabled = reader.read_bool()?;
+        self.irq_enabled = reader.read_bool()?;
+        self.pending = reader.read_bool()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,384 @@
+#![no_std]
+#![warn(clippy::all, clippy::pedantic)]
+
+//!
+//! Emulation of the memory mappers found on NES cartridges
+//!
+//! [Mapper reference](https://www.nesdev.org/wiki/Mapper)
+//!
+
+extern crate alloc;
+
+use {
+    alloc::{boxed::Box, vec, vec::Vec},
+    core::fmt,
+    ines_parser::{Ines, VramLayout},
+    nes_state::{Savestate, StateReader, StateWriter},
+};
+
+mod axrom;
+mod bus_conflicts;
+mod cartridge;
+mod cnrom;
+mod disk_system;
+mod fme7;
+mod inference;
+mod irq;
+mod memory_map;
+mod mmc1;
+mod mmc3;
+mod nrom;
+mod sav;
+mod unrom512;
+mod uxrom;
+mod vrc4;
+mod vrc6;
+
+pub use {
+    axrom::Axrom,
+    bus_conflicts::{find_bus_conflicts, has_bus_conflicts, relies_on_bus_conflicts, BusConflict},
+    cartridge::Cartridge,
+    cnrom::Cnrom,
+    disk_system::{DiskSystem, FDS_BIOS_SIZE},
+    fme7::Fme7,
+    inference::{infer_mapper, MapperCandidate, MapperInference},
+    irq::{CycleCounter, IrqCounter, ScanlineCounter, VrcIrq},
+    memory_map::{MemoryMap, Region, RegionKind},
+    mmc1::Mmc1,
+    mmc3::Mmc3,
+    nrom::Nrom,
+    sav::{check_save, save_size, SaveFit, COMMON_SAVE_SIZE},
+    unrom512::{is_self_flashable, unrom_512_mirroring, Unrom512, UNROM_512_CHR_RAM_SIZE},
+    uxrom::Uxrom,
+    vrc4::Vrc4,
+    vrc6::Vrc6,
+};
+
+/// Size of the PRG RAM mappers get if the ROM doesn't say otherwise
+pub const DEFAULT_PRG_RAM_SIZE: usize = 8192;
+
+/// Size of the CHR RAM used when the ROM doesn't contain a CHR ROM
+pub const DEFAULT_CHR_RAM_SIZE: usize = 8192;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The mapper number isn't implemented
+    UnsupportedMapper(u8),
+    /// The cartridge doesn't have any battery-backed RAM
+    NoBatteryRam,
+    /// The BIOS of the Famicom Disk System doesn't have the size of [`FDS_BIOS_SIZE`]
+    InvalidBiosSize(usize),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedMapper(number) => write!(f, "Mapper {number} isn't supported"),
+            Self::NoBatteryRam => f.write_str("The cartridge doesn't have battery-backed RAM"),
+            Self::InvalidBiosSize(size) => {
+                write!(f, "The FDS BIOS has to be 8 KiB, got {size} bytes")
+            }
+        }
+    }
+}
+
+/// How the four logical nametables are mapped onto the VRAM
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Mirroring {
+    Horizontal,
+    Vertical,
+    /// All nametables show the first 1 KiB of the VRAM
+    SingleScreenLower,
+    /// All nametables show the second 1 KiB of the VRAM
+    SingleScreenUpper,
+    /// The cartridge provides 2 KiB of additional VRAM
+    FourScreen,
+}
+
+impl Mirroring {
+    /// Offset into the VRAM for an address in the nametable area (`$2000`-`$3EFF`)
+    #[must_use]
+    pub fn vram_offset(self, address: u16) -> usize {
+        let offset = usize::from(address & 0x0FFF);
+        let (table, offset) = (offset / 0x400, offset % 0x400);
+
+        let table = match self {
+            Self::Horizontal => table / 2,
+            Self::Vertical => table % 2,
+            Self::SingleScreenLower => 0,
+            Self::SingleScreenUpper => 1,
+            Self::FourScreen => table,
+        };
+
+        table * 0x400 + offset
+    }
+}
+
+impl Savestate for Mirroring {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(match self {
+            Self::Horizontal => 0,
+            Self::Vertical => 1,
+            Self::SingleScreenLower => 2,
+            Self::SingleScreenUpper => 3,
+            Self::FourScreen => 4,
+        });
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), nes_state::Error> {
+        *self = match reader.read_u8()? {
+            0 => Self::Horizontal,
+            1 => Self::Vertical,
+            2 => Self::SingleScreenLower,
+            3 => Self::SingleScreenUpper,
+            4 => Self::FourScreen,
+            _ => return Err(nes_state::Error::InvalidValue),
+        };
+
+        Ok(())
+    }
+}
+
+impl From<&VramLayout> for Mirroring {
+    fn from(layout: &VramLayout) -> Self {
+        match layout {
+            VramLayout::HorizontalMirroring => Self::Horizontal,
+            VramLayout::VerticalMirroring => Self::Vertical,
+            VramLayout::FourScreen => Self::FourScreen,
+        }
+    }
+}
+
+/// Memory on the cartridge which gets banked by the mapper
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Memory {
+    pub prg_rom: Vec<u8>,
+    pub prg_ram: Vec<u8>,
+    /// Either the CHR ROM or the CHR RAM
+    pub chr: Vec<u8>,
+    pub chr_is_ram: bool,
+}
+
+impl Memory {
+    /// Copy the ROMs of an INES ROM and allocate the RAMs its header asks for
+    ///
+    /// Volatile and battery-backed PRG RAM share one buffer.
+    /// Falls back to the default sizes if the header doesn't specify any RAM.
+    #[must_use]
+    pub fn from_ines(ines: &Ines<'_>) -> Self {
+        let header = &ines.header;
+
+        let (chr, chr_is_ram) = if let Some(chr_rom) = &ines.chr_rom {
+            (chr_rom.to_vec(), false)
+        } else {
+            let size = header.chr_ram_size + header.chr_nvram_size;
+            let size = if size == 0 {
+                DEFAULT_CHR_RAM_SIZE
+            } else {
+                size
+            };
+            (vec![0; size], true)
+        };
+
+        // NES 2.0 headers can explicitly state that there's no PRG RAM
+        let prg_ram_size = header.prg_ram_size + header.prg_nvram_size;
+        let prg_ram_size = if prg_ram_size == 0 && !header.is_nes2 {
+            DEFAULT_PRG_RAM_SIZE
+        } else {
+            prg_ram_size
+        };
+
+        Self {
+            prg_rom: ines.prg_rom.to_vec(),
+            prg_ram: vec![0; prg_ram_size],
+            chr,
+            chr_is_ram,
+        }
+    }
+
+    /// Amount of PRG ROM banks of the given size
+    #[must_use]
+    pub fn prg_banks(&self, bank_size: usize) -> usize {
+        (self.prg_rom.len() / bank_size).max(1)
+    }
+
+    /// Amount of CHR banks of the given size
+    #[must_use]
+    pub fn chr_banks(&self, bank_size: usize) -> usize {
+        (self.chr.len() / bank_size).max(1)
+    }
+
+    /// Read from a PRG ROM bank; banks past the end wrap around
+    #[must_use]
+    pub fn read_prg_rom(&self, bank: usize, bank_size: usize, address: u16) -> u8 {
+        read_banked(&self.prg_rom, bank, bank_size, address)
+    }
+
+    /// Read from a CHR bank; banks past the end wrap around
+    #[must_use]
+    pub fn read_chr(&self, bank: usize, bank_size: usize, address: u16) -> u8 {
+        read_banked(&self.chr, bank, bank_size, address)
+    }
+
+    /// Write to a CHR bank, if it's RAM
+    pub fn write_chr(&mut self, bank: usize, bank_size: usize, address: u16, value: u8) {
+        if self.chr_is_ram && !self.chr.is_empty() {
+            let offset = banked_offset(self.chr.len(), bank, bank_size, address);
+            self.chr[offset] = value;
+        }
+    }
+
+    /// Read from the PRG RAM at `$6000`-`$7FFF`; returns 0 if there's none
+    #[must_use]
+    pub fn read_prg_ram(&self, address: u16) -> u8 {
+        if self.prg_ram.is_empty() {
+            return 0;
+        }
+
+        self.prg_ram[usize::from(address - 0x6000) % self.prg_ram.len()]
+    }
+
+    /// Write to the PRG RAM at `$6000`-`$7FFF`
+    pub fn write_prg_ram(&mut self, address: u16, value: u8) {
+        if !self.prg_ram.is_empty() {
+            let length = self.prg_ram.len();
+            self.prg_ram[usize::from(address - 0x6000) % length] = value;
+        }
+    }
+}
+
+// The ROMs are part of the cartridge, not of its state
+impl Savestate for Memory {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bytes(&self.prg_ram);
+        if self.chr_is_ram {
+            writer.write_bytes(&self.chr);
+        }
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), nes_state::Error> {
+        reader.read_bytes_into(&mut self.prg_ram)?;
+        if self.chr_is_ram {
+            reader.read_bytes_into(&mut self.chr)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn banked_offset(length: usize, bank: usize, bank_size: usize, address: u16) -> usize {
+    (bank * bank_size + usize::from(address) % bank_size) % length
+}
+
+fn read_banked(data: &[u8], bank: usize, bank_size: usize, address: u16) -> u8 {
+    if data.is_empty() {
+        return 0;
+    }
+
+    data[banked_offset(data.len(), bank, bank_size, address)]
+}
+
+/// Memory mapper of a cartridge
+///
+/// The state of a mapper includes its registers and the RAMs of its memory
+pub trait Mapper: Savestate {
+    /// Read from the cartridge space of the CPU (`$4020`-`$FFFF`)
+    fn cpu_read(&mut self, address: u16) -> u8;
+
+    /// Write to the cartridge space of the CPU (`$4020`-`$FFFF`)
+    fn cpu_write(&mut self, address: u16, value: u8);
+
+    /// Read from the pattern tables (`$0000`-`$1FFF`)
+    fn ppu_read(&mut self, address: u16) -> u8;
+
+    /// Write to the pattern tables (`$0000`-`$1FFF`)
+    fn ppu_write(&mut self, address: u16, value: u8);
+
+    /// Current nametable mirroring
+    fn mirroring(&self) -> Mirroring;
+
+    /// Whether the mapper is asserting the IRQ line
+    fn irq(&self) -> bool {
+        false
+    }
+
+    /// Clocked once per CPU cycle, for mappers counting CPU cycles or timing the address lines of the PPU
+    fn clock_cpu(&mut self) {}
+
+    /// The RAM adapter of the Famicom Disk System, to insert and eject disks
+    fn disk_system(&self) -> Option<&DiskSystem> {
+        None
+    }
+
+    fn disk_system_mut(&mut self) -> Option<&mut DiskSystem> {
+        None
+    }
+
+    /// Current layout of the banks and registers, for memory viewers and documentation
+    fn memory_map(&self) -> MemoryMap;
+
+    /// ROMs and RAMs of the cartridge
+    fn memory(&self) -> &Memory;
+
+    /// ROMs and RAMs of the cartridge, mutable to load battery-backed RAM
+    fn memory_mut(&mut self) -> &mut Memory;
+}
+
+/// Create the mapper for the memory of a cartridge
+///
+/// # Errors
+///
+/// Returns [`Error::UnsupportedMapper`] if the mapper isn't implemented
+pub fn new_mapper(
+    mapper_number: u8,
+    memory: Memory,
+    mirroring: Mirroring,
+) -> Result<Box<dyn Mapper>, Error> {
+    let mapper: Box<dyn Mapper> = match mapper_number {
+        0 => Box::new(Nrom::new(memory, mirroring)),
+        1 => Box::new(Mmc1::new(memory)),
+        2 => Box::new(Uxrom::new(memory, mirroring)),
+        3 => Box::new(Cnrom::new(memory, mirroring)),
+        4 => Box::new(Mmc3::new(memory, mirroring)),
+        7 => Box::new(Axrom::new(memory)),
+        21 | 23 | 25 => Box::new(Vrc4::new(memory, mapper_number)),
+        24 | 26 => Box::new(Vrc6::new(memory, mapper_number)),
+        30 => Box::new(Unrom512::new(memory, mirroring, false)),
+        69 => Box::new(Fme7::new(memory)),
+        _ => return Err(Error::UnsupportedMapper(mapper_number)),
+    };
+
+    Ok(mapper)
+}
+
+/// Create the mapper of an INES ROM
+///
+/// # Errors
+///
+/// Returns [`Error::UnsupportedMapper`] if the mapper isn't implemented
+pub fn from_ines(ines: &Ines<'_>) -> Result<Box<dyn Mapper>, Error> {
+    // The header bits of mapper 30 mean more than the generic mirroring and RAM
+    if ines.header.mapper_number == 30 {
+        return Ok(Box::new(Unrom512::from_header(
+            Memory::from_ines(ines),
+            &ines.header,
+        )));
+    }
+
+    if has_bus_conflicts(&ines.header) {
+        let memory = Memory::from_ines(ines);
+        let mirroring = Mirroring::from(&ines.header.vram_layout);
+        let mapper: Box<dyn Mapper> = match ines.header.mapper_number {
+            2 => Box::new(Uxrom::new(memory, mirroring).with_bus_conflicts(true)),
+            3 => Box::new(Cnrom::new(memory, mirroring).with_bus_conflicts(true)),
+            _ => Box::new(Axrom::new(memory).with_bus_conflicts(true)),
+        };
+        return Ok(mapper);
+    }
+
+    new_mapper(
+        ines.header.mapper_number,
+        Memory::from_ines(ines),
+        Mirroring::from(&ines.header.vram_layout),
+    )
+}
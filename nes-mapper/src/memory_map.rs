@@ -0,0 +1,236 @@
+use {
+    crate::{Memory, Mirroring},
+    alloc::vec::Vec,
+    core::{fmt, ops::RangeInclusive},
+};
+
+/// What a range of addresses is connected to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RegionKind {
+    /// Bank of the PRG ROM, counted in banks of the given size
+    PrgRom {
+        bank: usize,
+        size: usize,
+        switchable: bool,
+    },
+    /// PRG RAM at `$6000`-`$7FFF`; reads of disabled RAM return open bus
+    PrgRam {
+        size: usize,
+        enabled: bool,
+        writable: bool,
+    },
+    /// Bank of the CHR ROM or RAM, counted in banks of the given size
+    Chr {
+        bank: usize,
+        size: usize,
+        switchable: bool,
+        ram: bool,
+    },
+    /// Nametable memory with the current mirroring
+    Nametables(Mirroring),
+    /// Write-only mapper registers, usually on top of the PRG ROM
+    Registers(&'static str),
+}
+
+fn kib(size: usize) -> usize {
+    size / 0x400
+}
+
+impl fmt::Display for RegionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::PrgRom {
+                bank,
+                size,
+                switchable,
+            } => {
+                let kind = if switchable { "switchable" } else { "fixed" };
+                write!(f, "PRG ROM bank {bank} ({kind}, {} KiB)", kib(size))
+            }
+            Self::PrgRam {
+                size,
+                enabled,
+                writable,
+            } => {
+                write!(f, "PRG RAM ({} KiB", kib(size))?;
+                if !enabled {
+                    f.write_str(", disabled")?;
+                } else if !writable {
+                    f.write_str(", write-protected")?;
+                }
+                f.write_str(")")
+            }
+            Self::Chr {
+                bank,
+                size,
+                switchable,
+                ram,
+            } => {
+                let memory = if ram { "CHR RAM" } else { "CHR ROM" };
+                let kind = if switchable { "switchable" } else { "fixed" };
+                write!(f, "{memory} bank {bank} ({kind}, {} KiB)", kib(size))
+            }
+            Self::Nametables(mirroring) => write!(f, "Nametables ({mirroring:?} mirroring)"),
+            Self::Registers(name) => write!(f, "Registers: {name}"),
+        }
+    }
+}
+
+/// Labeled range of an address space
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Region {
+    pub addresses: RangeInclusive<u16>,
+    pub kind: RegionKind,
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "${:04X}-${:04X}  {}",
+            self.addresses.start(),
+            self.addresses.end(),
+            self.kind
+        )
+    }
+}
+
+/// Layout of the cartridge space of the CPU (`$4020`-`$FFFF`) and the PPU (`$0000`-`$2FFF`) in the current state of a mapper
+///
+/// Registers overlap the memory they're written through. Addresses without a region are open bus.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MemoryMap {
+    pub cpu: Vec<Region>,
+    pub ppu: Vec<Region>,
+}
+
+impl MemoryMap {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a region to the CPU address space
+    #[must_use]
+    pub fn cpu(mut self, addresses: RangeInclusive<u16>, kind: RegionKind) -> Self {
+        self.cpu.push(Region { addresses, kind });
+        self
+    }
+
+    /// Add a region to the PPU address space
+    #[must_use]
+    pub fn ppu(mut self, addresses: RangeInclusive<u16>, kind: RegionKind) -> Self {
+        self.ppu.push(Region { addresses, kind });
+        self
+    }
+
+    /// Add the PRG RAM at `$6000`-`$7FFF`, if the cartridge has any
+    #[must_use]
+    pub fn prg_ram(self, memory: &Memory, enabled: bool, writable: bool) -> Self {
+        if memory.prg_ram.is_empty() {
+            return self;
+        }
+
+        self.cpu(
+            0x6000..=0x7FFF,
+            RegionKind::PrgRam {
+                size: memory.prg_ram.len(),
+                enabled,
+                writable,
+            },
+        )
+    }
+
+    /// Add a PRG ROM bank; bank numbers past the end wrap around like they do when reading
+    #[must_use]
+    pub fn prg_rom(
+        self,
+        memory: &Memory,
+        start: u16,
+        bank: usize,
+        size: usize,
+        switchable: bool,
+    ) -> Self {
+        let bank = bank % memory.prg_banks(size);
+        self.cpu(
+            bank_addresses(start, size),
+            RegionKind::PrgRom {
+                bank,
+                size,
+                switchable,
+            },
+        )
+    }
+
+    /// Add a CHR bank; bank numbers past the end wrap around like they do when reading
+    #[must_use]
+    pub fn chr(
+        self,
+        memory: &Memory,
+        start: u16,
+        bank: usize,
+        size: usize,
+        switchable: bool,
+    ) -> Self {
+        let bank = bank % memory.chr_banks(size);
+        self.ppu(
+            bank_addresses(start, size),
+            RegionKind::Chr {
+                bank,
+                size,
+                switchable,
+                ram: memory.chr_is_ram,
+            },
+        )
+    }
+
+    /// Add the 32 KiB of PRG ROM of boards without PRG banking; 16 KiB ROMs are mirrored
+    #[must_use]
+    pub fn fixed_prg_rom(self, memory: &Memory) -> Self {
+        if memory.prg_rom.len() <= 0x4000 {
+            self.prg_rom(memory, 0x8000, 0, 0x4000, false)
+                .prg_rom(memory, 0xC000, 0, 0x4000, false)
+        } else {
+            self.prg_rom(memory, 0x8000, 0, 0x8000, false)
+        }
+    }
+
+    /// Regions of the CPU address space containing the address, the memory first
+    pub fn cpu_regions_at(&self, address: u16) -> impl Iterator<Item = &Region> {
+        regions_at(&self.cpu, address)
+    }
+
+    /// Regions of the PPU address space containing the address
+    pub fn ppu_regions_at(&self, address: u16) -> impl Iterator<Item = &Region> {
+        regions_at(&self.ppu, address)
+    }
+}
+
+impl fmt::Display for MemoryMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "CPU:")?;
+        for region in &self.cpu {
+            writeln!(f, "  {region}")?;
+        }
+
+        write!(f, "PPU:")?;
+        for region in &self.ppu {
+            write!(f, "\n  {region}")?;
+        }
+
+        Ok(())
+    }
+}
+
+fn bank_addresses(start: u16, size: usize) -> RangeInclusive<u16> {
+    // Banks never reach past the end of the address space
+    #[allow(clippy::cast_possible_truncation)]
+    let end = start + (size - 1) as u16;
+    start..=end
+}
+
+fn regions_at(regions: &[Region], address: u16) -> impl Iterator<Item = &Region> {
+    regions
+        .iter()
+        .filter(move |region| region.addresses.contains(&address))
+}
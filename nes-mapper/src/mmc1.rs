@@ -0,0 +1,225 @@
+use {
+    crate::{Mapper, Memory, MemoryMap, Mirroring, RegionKind},
+    nes_state::{Error, Savestate, StateReader, StateWriter},
+};
+
+const PRG_BANK_SIZE: usize = 0x4000;
+const CHR_BANK_SIZE: usize = 0x1000;
+
+// PRG ROMs larger than this use the CHR bank registers to select the 256 KiB half (SUROM)
+const PRG_OUTER_BANK_SIZE: usize = 0x40000;
+
+/// Mapper 1; registers get written serially through a shift register
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mmc1 {
+    memory: Memory,
+    shift_register: u8,
+    shift_count: u8,
+    control: u8,
+    chr_bank_0: u8,
+    chr_bank_1: u8,
+    prg_bank: u8,
+}
+
+impl Mmc1 {
+    #[must_use]
+    pub fn new(memory: Memory) -> Self {
+        Self {
+            memory,
+            shift_register: 0,
+            shift_count: 0,
+            // The last PRG ROM bank is fixed at `$C000` on power-up
+            control: 0x0C,
+            chr_bank_0: 0,
+            chr_bank_1: 0,
+            prg_bank: 0,
+        }
+    }
+
+    fn write_register(&mut self, address: u16, value: u8) {
+        match address {
+            0x8000..=0x9FFF => self.control = value,
+            0xA000..=0xBFFF => self.chr_bank_0 = value,
+            0xC000..=0xDFFF => self.chr_bank_1 = value,
+            _ => self.prg_bank = value,
+        }
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        self.prg_bank & 0x10 == 0
+    }
+
+    fn prg_rom_bank(&self, address: u16) -> usize {
+        let outer = if self.memory.prg_rom.len() > PRG_OUTER_BANK_SIZE {
+            usize::from(self.chr_bank_0 & 0x10) // 0x10 * 16 KiB = 256 KiB
+        } else {
+            0
+        };
+        let bank = usize::from(self.prg_bank & 0x0F);
+        let last_bank = (self.memory.prg_banks(PRG_BANK_SIZE) - 1).min(0x0F);
+
+        let bank = match (self.control >> 2) & 0x03 {
+            // 32 KiB mode ignores the lowest bit
+            0 | 1 => (bank & !1) | usize::from(address >= 0xC000),
+            // First bank fixed at `$8000`
+            2 => {
+                if address < 0xC000 {
+                    0
+                } else {
+                    bank
+                }
+            }
+            // Last bank fixed at `$C000`
+            _ => {
+                if address < 0xC000 {
+                    bank
+                } else {
+                    last_bank
+                }
+            }
+        };
+
+        outer | bank
+    }
+
+    fn chr_bank(&self, address: u16) -> usize {
+        if self.control & 0x10 == 0 {
+            // 8 KiB mode ignores the lowest bit
+            usize::from(self.chr_bank_0 & !1) | usize::from(address >= 0x1000)
+        } else if address < 0x1000 {
+            usize::from(self.chr_bank_0)
+        } else {
+            usize::from(self.chr_bank_1)
+        }
+    }
+}
+
+impl Mapper for Mmc1 {
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        match address {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => self.memory.read_prg_ram(address),
+            0x8000..=0xFFFF => {
+                let bank = self.prg_rom_bank(address);
+                self.memory.read_prg_rom(bank, PRG_BANK_SIZE, address)
+            }
+            _ => 0,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        match address {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => {
+                self.memory.write_prg_ram(address, value);
+            }
+            0x8000..=0xFFFF => {
+                if value & 0x80 != 0 {
+                    self.shift_register = 0;
+                    self.shift_count = 0;
+                    self.control |= 0x0C;
+                    return;
+                }
+
+                self.shift_register |= (value & 0x01) << self.shift_count;
+                self.shift_count += 1;
+
+                if self.shift_count == 5 {
+                    self.write_register(address, self.shift_register);
+                    self.shift_register = 0;
+                    self.shift_count = 0;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        let bank = self.chr_bank(address);
+        self.memory.read_chr(bank, CHR_BANK_SIZE, address)
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8) {
+        let bank = self.chr_bank(address);
+        self.memory.write_chr(bank, CHR_BANK_SIZE, address, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.control & 0x03 {
+            0 => Mirroring::SingleScreenLower,
+            1 => Mirroring::SingleScreenUpper,
+            2 => Mirroring::Vertical,
+            _ => Mirroring::Horizontal,
+        }
+    }
+
+    fn memory_map(&self) -> MemoryMap {
+        let mode = (self.control >> 2) & 0x03;
+        let enabled = self.prg_ram_enabled();
+        let map = MemoryMap::new()
+            .prg_ram(&self.memory, enabled, enabled)
+            .prg_rom(
+                &self.memory,
+                0x8000,
+                self.prg_rom_bank(0x8000),
+                PRG_BANK_SIZE,
+                mode != 2,
+            )
+            .prg_rom(
+                &self.memory,
+                0xC000,
+                self.prg_rom_bank(0xC000),
+                PRG_BANK_SIZE,
+                mode != 3,
+            )
+            .cpu(0x8000..=0x9FFF, RegionKind::Registers("Control"))
+            .cpu(0xA000..=0xBFFF, RegionKind::Registers("CHR bank 0"))
+            .cpu(0xC000..=0xDFFF, RegionKind::Registers("CHR bank 1"))
+            .cpu(0xE000..=0xFFFF, RegionKind::Registers("PRG bank"));
+
+        map.chr(
+            &self.memory,
+            0x0000,
+            self.chr_bank(0x0000),
+            CHR_BANK_SIZE,
+            true,
+        )
+        .chr(
+            &self.memory,
+            0x1000,
+            self.chr_bank(0x1000),
+            CHR_BANK_SIZE,
+            true,
+        )
+    }
+
+    fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+}
+
+impl Savestate for Mmc1 {
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.memory.save_state(writer);
+        writer.write_u8(self.shift_register);
+        writer.write_u8(self.shift_count);
+        writer.write_u8(self.control);
+        writer.write_u8(self.chr_bank_0);
+        writer.write_u8(self.chr_bank_1);
+        writer.write_u8(self.prg_bank);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), Error> {
+        self.memory.load_state(reader)?;
+        self.shift_register = reader.read_u8()?;
+        self.shift_count = reader.read_u8()?;
+        self.control = reader.read_u8()?;
+        self.chr_bank_0 = reader.read_u8()?;
+        self.chr_bank_1 = reader.read_u8()?;
+        self.prg_bank = reader.read_u8()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,224 @@
+use {
+    crate::{IrqCounter, Mapper, Memory, MemoryMap, Mirroring, RegionKind, ScanlineCounter},
+    alloc::vec,
+    nes_state::{Error, Savestate, StateReader, StateWriter},
+};
+
+const PRG_BANK_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x0400;
+
+/// Mapper 4; fine-grained PRG and CHR banking and a scanline counter driven by the PPU address line A12
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mmc3 {
+    memory: Memory,
+    /// Mirroring of the header; four-screen VRAM can't be switched
+    header_mirroring: Mirroring,
+    mirroring: Mirroring,
+
+    bank_select: u8,
+    registers: [u8; 8],
+    prg_ram_enabled: bool,
+    prg_ram_writable: bool,
+
+    irq: ScanlineCounter,
+}
+
+impl Mmc3 {
+    #[must_use]
+    pub fn new(memory: Memory, mirroring: Mirroring) -> Self {
+        Self {
+            memory,
+            header_mirroring: mirroring,
+            mirroring,
+            bank_select: 0,
+            registers: [0, 2, 4, 5, 6, 7, 0, 1],
+            prg_ram_enabled: true,
+            prg_ram_writable: true,
+            irq: ScanlineCounter::new(),
+        }
+    }
+
+    fn prg_rom_bank(&self, address: u16) -> usize {
+        let second_last = self.memory.prg_banks(PRG_BANK_SIZE).saturating_sub(2);
+        let swapped = self.bank_select & 0x40 != 0;
+
+        match (address - 0x8000) / 0x2000 {
+            0 if swapped => second_last,
+            0 => usize::from(self.registers[6]),
+            1 => usize::from(self.registers[7]),
+            2 if swapped => usize::from(self.registers[6]),
+            2 => second_last,
+            _ => second_last + 1,
+        }
+    }
+
+    fn chr_bank(&self, address: u16) -> usize {
+        // The inversion swaps the 2 KiB and the 1 KiB halves
+        let address = if self.bank_select & 0x80 == 0 {
+            address
+        } else {
+            address ^ 0x1000
+        };
+
+        let slot = usize::from(address / 0x0400);
+        match slot {
+            0 | 1 => usize::from(self.registers[0] & !1) + slot,
+            2 | 3 => usize::from(self.registers[1] & !1) + slot - 2,
+            _ => usize::from(self.registers[slot - 2]),
+        }
+    }
+}
+
+impl Mapper for Mmc3 {
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        match address {
+            0x6000..=0x7FFF if self.prg_ram_enabled => self.memory.read_prg_ram(address),
+            0x8000..=0xFFFF => {
+                let bank = self.prg_rom_bank(address);
+                self.memory.read_prg_rom(bank, PRG_BANK_SIZE, address)
+            }
+            _ => 0,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        let is_even = address.is_multiple_of(2);
+
+        match address {
+            0x6000..=0x7FFF if self.prg_ram_enabled && self.prg_ram_writable => {
+                self.memory.write_prg_ram(address, value);
+            }
+            0x8000..=0x9FFF if is_even => self.bank_select = value,
+            0x8000..=0x9FFF => {
+                self.registers[usize::from(self.bank_select & 0x07)] = value;
+            }
+            0xA000..=0xBFFF if is_even && self.header_mirroring != Mirroring::FourScreen => {
+                self.mirroring = if value & 0x01 == 0 {
+                    Mirroring::Vertical
+                } else {
+                    Mirroring::Horizontal
+                };
+            }
+            0xA000..=0xBFFF if is_even => {}
+            0xA000..=0xBFFF => {
+                self.prg_ram_enabled = value & 0x80 != 0;
+                self.prg_ram_writable = value & 0x40 == 0;
+            }
+            0xC000..=0xDFFF if is_even => self.irq.set_latch(value),
+            0xC000..=0xDFFF => self.irq.reload(),
+            0xE000..=0xFFFF if is_even => self.irq.disable(),
+            0xE000..=0xFFFF => self.irq.enable(),
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        self.irq.watch_ppu_address(address);
+
+        let bank = self.chr_bank(address);
+        self.memory.read_chr(bank, CHR_BANK_SIZE, address)
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8) {
+        self.irq.watch_ppu_address(address);
+
+        let bank = self.chr_bank(address);
+        self.memory.write_chr(bank, CHR_BANK_SIZE, address, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.irq()
+    }
+
+    fn clock_cpu(&mut self) {
+        self.irq.clock_cpu();
+    }
+
+    fn memory_map(&self) -> MemoryMap {
+        let swapped = self.bank_select & 0x40 != 0;
+        let mut map =
+            MemoryMap::new().prg_ram(&self.memory, self.prg_ram_enabled, self.prg_ram_writable);
+
+        for (address, switchable) in [
+            (0x8000, !swapped),
+            (0xA000, true),
+            (0xC000, swapped),
+            (0xE000, false),
+        ] {
+            let bank = self.prg_rom_bank(address);
+            map = map.prg_rom(&self.memory, address, bank, PRG_BANK_SIZE, switchable);
+        }
+
+        map = map
+            .cpu(
+                0x8000..=0x9FFF,
+                RegionKind::Registers("Bank select and bank data"),
+            )
+            .cpu(
+                0xA000..=0xBFFF,
+                RegionKind::Registers("Mirroring and PRG RAM protect"),
+            )
+            .cpu(
+                0xC000..=0xDFFF,
+                RegionKind::Registers("IRQ latch and IRQ reload"),
+            )
+            .cpu(
+                0xE000..=0xFFFF,
+                RegionKind::Registers("IRQ disable and IRQ enable"),
+            );
+
+        // The 2 KiB banks are in the half the inversion bit selects
+        let (two_kib_half, one_kib_half) = if self.bank_select & 0x80 == 0 {
+            (0x0000, 0x1000)
+        } else {
+            (0x1000, 0x0000)
+        };
+        let mut chr_banks = vec![(two_kib_half, 0x0800), (two_kib_half + 0x0800, 0x0800)];
+        chr_banks.extend((0..4).map(|slot| (one_kib_half + slot * 0x0400, CHR_BANK_SIZE)));
+        chr_banks.sort_unstable();
+
+        for (address, size) in chr_banks {
+            // The bank numbers count 1 KiB banks
+            let bank = self.chr_bank(address) * CHR_BANK_SIZE / size;
+            map = map.chr(&self.memory, address, bank, size, true);
+        }
+
+        map
+    }
+
+    fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+}
+
+impl Savestate for Mmc3 {
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.memory.save_state(writer);
+        self.mirroring.save_state(writer);
+        writer.write_u8(self.bank_select);
+        writer.write_bytes(&self.registers);
+        writer.write_bool(self.prg_ram_enabled);
+        writer.write_bool(self.prg_ram_writable);
+        self.irq.save_state(writer);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), Error> {
+        self.memory.load_state(reader)?;
+        self.mirroring.load_state(reader)?;
+        self.bank_select = reader.read_u8()?;
+        reader.read_bytes_into(&mut self.registers)?;
+        self.prg_ram_enabled = reader.read_bool()?;
+        self.prg_ram_writable = reader.read_bool()?;
+        self.irq.load_state(reader)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+use {
+    crate::{Mapper, Memory, MemoryMap, Mirroring},
+    nes_state::{Error, Savestate, StateReader, StateWriter},
+};
+
+/// Mapper 0; up to 32 KiB of PRG ROM and 8 KiB of CHR without any banking
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Nrom {
+    memory: Memory,
+    mirroring: Mirroring,
+}
+
+impl Nrom {
+    #[must_use]
+    pub fn new(memory: Memory, mirroring: Mirroring) -> Self {
+        Self { memory, mirroring }
+    }
+}
+
+impl Mapper for Nrom {
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        match address {
+            0x6000..=0x7FFF => self.memory.read_prg_ram(address),
+            // 16 KiB PRG ROMs are mirrored
+            0x8000..=0xFFFF => self.memory.read_prg_rom(0, 0x8000, address),
+            _ => 0,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        if let 0x6000..=0x7FFF = address {
+            self.memory.write_prg_ram(address, value);
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        self.memory.read_chr(0, 0x2000, address)
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8) {
+        self.memory.write_chr(0, 0x2000, address, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn memory_map(&self) -> MemoryMap {
+        MemoryMap::new()
+            .prg_ram(&self.memory, true, true)
+            .fixed_prg_rom(&self.memory)
+            .chr(&self.memory, 0x0000, 0, 0x2000, false)
+    }
+
+    fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+}
+
+impl Savestate for Nrom {
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.memory.save_state(writer);
+        self.mirroring.save_state(writer);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), Error> {
+        self.memory.load_state(reader)?;
+        self.mirroring.load_state(reader)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,66 @@
+use {crate::is_self_flashable, core::cmp::Ordering, ines_parser::Header};
+
+/// Size of the save files most emulators write, regardless of the actual size of the battery-backed RAM
+pub const COMMON_SAVE_SIZE: usize = 8192;
+
+/// How the size of a save file relates to the battery-backed RAM of the cartridge
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SaveFit {
+    /// The save file has exactly the size of the RAM
+    Exact,
+    /// The save file is larger, but everything past the RAM is either zero or mirrors it
+    ///
+    /// That's usually an 8 KiB save written for a smaller RAM and safe to truncate.
+    Padded { expected: usize, actual: usize },
+    /// The save file is larger and the data past the RAM would get lost
+    Oversized { expected: usize, actual: usize },
+    /// The save file is smaller, the rest of the RAM stays cleared
+    ///
+    /// That's usually an 8 KiB save written for a larger RAM.
+    Undersized { expected: usize, actual: usize },
+}
+
+impl SaveFit {
+    /// Whether loading the save file keeps all of its data
+    #[must_use]
+    pub fn is_lossless(self) -> bool {
+        !matches!(self, Self::Oversized { .. })
+    }
+}
+
+/// Size of the battery-backed PRG RAM of a ROM, which is the size of its save file
+///
+/// INES 1 headers with the battery flag set are assumed to back all of their PRG RAM.
+/// Self-flashable boards save the whole PRG ROM instead.
+#[must_use]
+pub fn save_size(header: &Header) -> usize {
+    if is_self_flashable(header) {
+        header.prg_rom_size
+    } else if header.has_persistent_memory {
+        header.prg_nvram_size
+    } else {
+        0
+    }
+}
+
+/// Check how a save file fits the battery-backed RAM of the given size
+#[must_use]
+pub fn check_save(expected: usize, data: &[u8]) -> SaveFit {
+    let actual = data.len();
+
+    match actual.cmp(&expected) {
+        Ordering::Equal => SaveFit::Exact,
+        Ordering::Less => SaveFit::Undersized { expected, actual },
+        Ordering::Greater => {
+            let (ram, rest) = data.split_at(expected);
+            let is_padding = rest.iter().all(|byte| *byte == 0)
+                || (!ram.is_empty() && rest.chunks(expected).all(|chunk| ram.starts_with(chunk)));
+
+            if is_padding {
+                SaveFit::Padded { expected, actual }
+            } else {
+                SaveFit::Oversized { expected, actual }
+            }
+        }
+    }
+}
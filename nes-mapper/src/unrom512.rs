@@ -0,0 +1,300 @@
+use {
+    crate::{Mapper, Memory, MemoryMap, Mirroring, RegionKind},
+    alloc::vec,
+    ines_parser::{Header, VramLayout},
+    nes_state::{Error, Savestate, StateReader, StateWriter},
+};
+
+const PRG_BANK_SIZE: usize = 0x4000;
+const CHR_BANK_SIZE: usize = 0x2000;
+
+/// Size of the CHR RAM of boards with an INES 1 header, which can't state it
+pub const UNROM_512_CHR_RAM_SIZE: usize = 0x8000;
+
+// Sectors of the SST39SF0x0 flash chips are erased as a whole
+const FLASH_SECTOR_SIZE: usize = 0x1000;
+
+// Manufacturer ID of SST and the device IDs of the 128, 256 and 512 KiB chips
+const FLASH_MANUFACTURER_ID: u8 = 0xBF;
+const FLASH_DEVICE_IDS: [u8; 3] = [0xB5, 0xB6, 0xB7];
+
+/// Whether the game can rewrite its PRG ROM, which is what the battery flag means for mapper 30
+///
+/// `NESmaker` games save their progress this way instead of using battery-backed RAM.
+#[must_use]
+pub fn is_self_flashable(header: &Header) -> bool {
+    header.mapper_number == 30 && header.has_persistent_memory
+}
+
+/// Nametable mirroring of mapper 30, where the four-screen bit of the header selects the alternative layouts
+///
+/// With the four-screen bit set, a cleared mirroring bit means one-screen mirroring switched by the register,
+/// which is returned as [`Mirroring::SingleScreenLower`].
+#[must_use]
+pub fn unrom_512_mirroring(header: &Header) -> Mirroring {
+    match header.vram_layout {
+        VramLayout::FourScreen if !header.mirroring_flag => Mirroring::SingleScreenLower,
+        ref layout => Mirroring::from(layout),
+    }
+}
+
+// Progress through the command sequences of the flash chip
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FlashState {
+    Idle,
+    // `$AA` was written to `$5555`
+    Unlocked,
+    // `$55` was written to `$2AAA`, the next write is the command
+    Command,
+    // The next write programs a byte
+    Program,
+    // Erasing needs a second unlock sequence
+    EraseIdle,
+    EraseUnlocked,
+    EraseCommand,
+    SoftwareId,
+}
+
+impl FlashState {
+    fn to_u8(self) -> u8 {
+        match self {
+            Self::Idle => 0,
+            Self::Unlocked => 1,
+            Self::Command => 2,
+            Self::Program => 3,
+            Self::EraseIdle => 4,
+            Self::EraseUnlocked => 5,
+            Self::EraseCommand => 6,
+            Self::SoftwareId => 7,
+        }
+    }
+
+    fn from_u8(value: u8) -> Result<Self, Error> {
+        Ok(match value {
+            0 => Self::Idle,
+            1 => Self::Unlocked,
+            2 => Self::Command,
+            3 => Self::Program,
+            4 => Self::EraseIdle,
+            5 => Self::EraseUnlocked,
+            6 => Self::EraseCommand,
+            7 => Self::SoftwareId,
+            _ => return Err(Error::InvalidValue),
+        })
+    }
+}
+
+/// Mapper 30 (UNROM 512); `UxROM` with up to 512 KiB of PRG ROM and 32 KiB of banked CHR RAM
+///
+/// The register at `$8000`-`$FFFF` selects the PRG ROM bank with bits 0-4, the CHR RAM bank with bits 5-6
+/// and the nametable with bit 7 if the board uses one-screen mirroring.
+/// Self-flashable boards move the register to `$C000`-`$FFFF` and pass writes to `$8000`-`$BFFF` on to the
+/// flash chip, which lets games erase and program the PRG ROM.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Unrom512 {
+    memory: Memory,
+    mirroring: Mirroring,
+    self_flashable: bool,
+    register: u8,
+    flash_state: FlashState,
+}
+
+impl Unrom512 {
+    #[must_use]
+    pub fn new(memory: Memory, mirroring: Mirroring, self_flashable: bool) -> Self {
+        Self {
+            memory,
+            mirroring,
+            self_flashable,
+            register: 0,
+            flash_state: FlashState::Idle,
+        }
+    }
+
+    /// Configure the board from the header
+    ///
+    /// INES 1 headers get the 32 KiB of CHR RAM all boards have
+    #[must_use]
+    pub fn from_header(mut memory: Memory, header: &Header) -> Self {
+        if !header.is_nes2 && memory.chr_is_ram {
+            memory.chr = vec![0; UNROM_512_CHR_RAM_SIZE];
+        }
+
+        Self::new(
+            memory,
+            unrom_512_mirroring(header),
+            is_self_flashable(header),
+        )
+    }
+
+    /// Whether writes to `$8000`-`$BFFF` go to the flash chip
+    #[must_use]
+    pub fn is_self_flashable(&self) -> bool {
+        self.self_flashable
+    }
+
+    fn prg_bank(&self) -> usize {
+        usize::from(self.register & 0x1F)
+    }
+
+    fn chr_bank(&self) -> usize {
+        usize::from((self.register >> 5) & 0x03)
+    }
+
+    // Offset into the PRG ROM, which is the address the flash chip sees
+    fn flash_offset(&self, address: u16) -> usize {
+        let banks = self.memory.prg_banks(PRG_BANK_SIZE);
+        (self.prg_bank() % banks) * PRG_BANK_SIZE + usize::from(address) % PRG_BANK_SIZE
+    }
+
+    fn flash_write(&mut self, address: u16, value: u8) {
+        let offset = self.flash_offset(address);
+        // The command addresses only decode the lower 15 bits
+        let command_address = offset & 0x7FFF;
+
+        self.flash_state = match (self.flash_state, command_address, value) {
+            (FlashState::Program, ..) => {
+                // Programming can only clear bits, erasing sets them again
+                if let Some(byte) = self.memory.prg_rom.get_mut(offset) {
+                    *byte &= value;
+                }
+                FlashState::Idle
+            }
+            (_, _, 0xF0) => FlashState::Idle,
+            (FlashState::Idle, 0x5555, 0xAA) => FlashState::Unlocked,
+            (FlashState::EraseIdle, 0x5555, 0xAA) => FlashState::EraseUnlocked,
+            (FlashState::Unlocked, 0x2AAA, 0x55) => FlashState::Command,
+            (FlashState::EraseUnlocked, 0x2AAA, 0x55) => FlashState::EraseCommand,
+            (FlashState::Command, 0x5555, 0xA0) => FlashState::Program,
+            (FlashState::Command, 0x5555, 0x80) => FlashState::EraseIdle,
+            // Only the reset command leaves the software ID mode
+            (FlashState::Command, 0x5555, 0x90) | (FlashState::SoftwareId, ..) => {
+                FlashState::SoftwareId
+            }
+            (FlashState::EraseCommand, _, 0x30) => {
+                let start = offset - offset % FLASH_SECTOR_SIZE;
+                let end = (start + FLASH_SECTOR_SIZE).min(self.memory.prg_rom.len());
+                if let Some(sector) = self.memory.prg_rom.get_mut(start..end) {
+                    sector.fill(0xFF);
+                }
+                FlashState::Idle
+            }
+            (FlashState::EraseCommand, 0x5555, 0x10) => {
+                self.memory.prg_rom.fill(0xFF);
+                FlashState::Idle
+            }
+            _ => FlashState::Idle,
+        };
+    }
+
+    fn software_id(&self, address: u16) -> u8 {
+        if address & 1 == 0 {
+            FLASH_MANUFACTURER_ID
+        } else {
+            let size_index = match self.memory.prg_rom.len() {
+                0..=0x2_0000 => 0,
+                0x2_0001..=0x4_0000 => 1,
+                _ => 2,
+            };
+            FLASH_DEVICE_IDS[size_index]
+        }
+    }
+}
+
+impl Mapper for Unrom512 {
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        match address {
+            0x8000..=0xFFFF if self.flash_state == FlashState::SoftwareId => {
+                self.software_id(address)
+            }
+            0x8000..=0xBFFF => self
+                .memory
+                .read_prg_rom(self.prg_bank(), PRG_BANK_SIZE, address),
+            0xC000..=0xFFFF => {
+                let last_bank = self.memory.prg_banks(PRG_BANK_SIZE) - 1;
+                self.memory.read_prg_rom(last_bank, PRG_BANK_SIZE, address)
+            }
+            _ => 0,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        match address {
+            0x8000..=0xBFFF if self.self_flashable => self.flash_write(address, value),
+            0x8000..=0xFFFF => self.register = value,
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        self.memory
+            .read_chr(self.chr_bank(), CHR_BANK_SIZE, address)
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8) {
+        let bank = self.chr_bank();
+        self.memory.write_chr(bank, CHR_BANK_SIZE, address, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.mirroring {
+            Mirroring::SingleScreenLower | Mirroring::SingleScreenUpper => {
+                if self.register & 0x80 == 0 {
+                    Mirroring::SingleScreenLower
+                } else {
+                    Mirroring::SingleScreenUpper
+                }
+            }
+            mirroring => mirroring,
+        }
+    }
+
+    fn memory_map(&self) -> MemoryMap {
+        let last_bank = self.memory.prg_banks(PRG_BANK_SIZE) - 1;
+        let map = MemoryMap::new()
+            .prg_rom(&self.memory, 0x8000, self.prg_bank(), PRG_BANK_SIZE, true)
+            .prg_rom(&self.memory, 0xC000, last_bank, PRG_BANK_SIZE, false);
+
+        let map = if self.self_flashable {
+            map.cpu(0x8000..=0xBFFF, RegionKind::Registers("Flash commands"))
+                .cpu(0xC000..=0xFFFF, RegionKind::Registers("Bank select"))
+        } else {
+            map.cpu(0x8000..=0xFFFF, RegionKind::Registers("Bank select"))
+        };
+
+        map.chr(&self.memory, 0x0000, self.chr_bank(), CHR_BANK_SIZE, true)
+    }
+
+    fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+}
+
+impl Savestate for Unrom512 {
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.memory.save_state(writer);
+        self.mirroring.save_state(writer);
+        writer.write_u8(self.register);
+        writer.write_u8(self.flash_state.to_u8());
+        // The game may have rewritten any part of the flash
+        if self.self_flashable {
+            writer.write_bytes(&self.memory.prg_rom);
+        }
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), Error> {
+        self.memory.load_state(reader)?;
+        self.mirroring.load_state(reader)?;
+        self.register = reader.read_u8()?;
+        self.flash_state = FlashState::from_u8(reader.read_u8()?)?;
+        if self.self_flashable {
+            reader.read_bytes_into(&mut self.memory.prg_rom)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+use {
+    crate::{Mapper, Memory, MemoryMap, Mirroring, RegionKind},
+    nes_state::{Error, Savestate, StateReader, StateWriter},
+};
+
+const PRG_BANK_SIZE: usize = 0x4000;
+
+/// Mapper 2; switchable 16 KiB PRG ROM bank at `$8000`, the last bank is fixed at `$C000`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Uxrom {
+    memory: Memory,
+    mirroring: Mirroring,
+    prg_bank: u8,
+    bus_conflicts: bool,
+}
+
+impl Uxrom {
+    #[must_use]
+    pub fn new(memory: Memory, mirroring: Mirroring) -> Self {
+        Self {
+            memory,
+            mirroring,
+            prg_bank: 0,
+            bus_conflicts: false,
+        }
+    }
+
+    /// Emulate the bus conflicts of boards where the ROM drives the data bus during register writes
+    ///
+    /// The mapper sees the bitwise AND of the written value and the ROM byte at the address
+    #[must_use]
+    pub fn with_bus_conflicts(mut self, enabled: bool) -> Self {
+        self.bus_conflicts = enabled;
+        self
+    }
+
+    #[must_use]
+    pub fn has_bus_conflicts(&self) -> bool {
+        self.bus_conflicts
+    }
+
+    // Value the mapper sees, masked by the ROM if there are bus conflicts
+    fn bus_value(&mut self, address: u16, value: u8) -> u8 {
+        if self.bus_conflicts {
+            value & self.cpu_read(address)
+        } else {
+            value
+        }
+    }
+}
+
+impl Mapper for Uxrom {
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        match address {
+            0x6000..=0x7FFF => self.memory.read_prg_ram(address),
+            0x8000..=0xBFFF => {
+                self.memory
+                    .read_prg_rom(usize::from(self.prg_bank), PRG_BANK_SIZE, address)
+            }
+            0xC000..=0xFFFF => {
+                let last_bank = self.memory.prg_banks(PRG_BANK_SIZE) - 1;
+                self.memory.read_prg_rom(last_bank, PRG_BANK_SIZE, address)
+            }
+            _ => 0,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        match address {
+            0x6000..=0x7FFF => self.memory.write_prg_ram(address, value),
+            0x8000..=0xFFFF => self.prg_bank = self.bus_value(address, value),
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        self.memory.read_chr(0, 0x2000, address)
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8) {
+        self.memory.write_chr(0, 0x2000, address, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn memory_map(&self) -> MemoryMap {
+        let last_bank = self.memory.prg_banks(PRG_BANK_SIZE) - 1;
+        MemoryMap::new()
+            .prg_ram(&self.memory, true, true)
+            .prg_rom(
+                &self.memory,
+                0x8000,
+                usize::from(self.prg_bank),
+                PRG_BANK_SIZE,
+                true,
+            )
+            .prg_rom(&self.memory, 0xC000, last_bank, PRG_BANK_SIZE, false)
+            .cpu(0x8000..=0xFFFF, RegionKind::Registers("PRG bank"))
+            .chr(&self.memory, 0x0000, 0, 0x2000, false)
+    }
+
+    fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+}
+
+impl Savestate for Uxrom {
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.memory.save_state(writer);
+        self.mirroring.save_state(writer);
+        writer.write_u8(self.prg_bank);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), Error> {
+        self.memory.load_state(reader)?;
+        self.mirroring.load_state(reader)?;
+        self.prg_bank = reader.read_u8()?;
+
+        Ok(())
+    }
+}
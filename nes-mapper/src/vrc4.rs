@@ -0,0 +1,215 @@
+use {
+    crate::{IrqCounter, Mapper, Memory, MemoryMap, Mirroring, RegionKind, VrcIrq},
+    nes_state::{Error, Savestate, StateReader, StateWriter},
+};
+
+const PRG_BANK_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x0400;
+
+// Address lines selecting the lower and the upper bit of the register within a page, for mappers 21, 23 and 25.
+// Every mapper number covers two boards wired differently, so the lines of both are combined.
+const VRC4_21_LINES: (u16, u16) = (0x0002 | 0x0040, 0x0004 | 0x0080);
+const VRC4_23_LINES: (u16, u16) = (0x0001 | 0x0004, 0x0002 | 0x0008);
+const VRC4_25_LINES: (u16, u16) = (0x0002 | 0x0008, 0x0001 | 0x0004);
+
+/// Mappers 21, 23 and 25; Konami VRC4 with 8 KiB PRG banks, 1 KiB CHR banks and a scanline or CPU cycle IRQ counter
+///
+/// The boards of each mapper number connect different address lines to the register select pins.
+/// Both wirings of a mapper number are supported at the same time, like most emulators do.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Vrc4 {
+    memory: Memory,
+    lines: (u16, u16),
+    prg_banks: [u8; 2],
+    /// Swaps the switchable bank at `$8000` with the fixed one at `$C000`
+    prg_swap: bool,
+    chr_banks: [u16; 8],
+    mirroring: Mirroring,
+    irq: VrcIrq,
+}
+
+impl Vrc4 {
+    /// Create the VRC4 of the mapper number, which selects the address lines of the registers
+    #[must_use]
+    pub fn new(memory: Memory, mapper_number: u8) -> Self {
+        let lines = match mapper_number {
+            21 => VRC4_21_LINES,
+            23 => VRC4_23_LINES,
+            _ => VRC4_25_LINES,
+        };
+
+        Self {
+            memory,
+            lines,
+            prg_banks: [0; 2],
+            prg_swap: false,
+            chr_banks: [0; 8],
+            mirroring: Mirroring::Vertical,
+            irq: VrcIrq::new(),
+        }
+    }
+
+    // Page of the register together with the register within the page
+    fn register(&self, address: u16) -> u16 {
+        let (low, high) = self.lines;
+        (address & 0xF000) | u16::from(address & low != 0) | u16::from(address & high != 0) << 1
+    }
+
+    fn prg_rom_bank(&self, address: u16) -> usize {
+        let second_last = self.memory.prg_banks(PRG_BANK_SIZE).saturating_sub(2);
+        match (address - 0x8000) / 0x2000 {
+            0 if self.prg_swap => second_last,
+            0 => usize::from(self.prg_banks[0]),
+            1 => usize::from(self.prg_banks[1]),
+            2 if self.prg_swap => usize::from(self.prg_banks[0]),
+            2 => second_last,
+            _ => second_last + 1,
+        }
+    }
+
+    fn chr_bank(&self, address: u16) -> usize {
+        usize::from(self.chr_banks[usize::from(address / 0x0400)])
+    }
+}
+
+impl Mapper for Vrc4 {
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        match address {
+            0x6000..=0x7FFF => self.memory.read_prg_ram(address),
+            0x8000..=0xFFFF => {
+                let bank = self.prg_rom_bank(address);
+                self.memory.read_prg_rom(bank, PRG_BANK_SIZE, address)
+            }
+            _ => 0,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        if let 0x6000..=0x7FFF = address {
+            self.memory.write_prg_ram(address, value);
+            return;
+        }
+
+        match self.register(address) {
+            0x8000..=0x8003 => self.prg_banks[0] = value & 0x1F,
+            0x9000 => {
+                self.mirroring = match value & 0x03 {
+                    0 => Mirroring::Vertical,
+                    1 => Mirroring::Horizontal,
+                    2 => Mirroring::SingleScreenLower,
+                    _ => Mirroring::SingleScreenUpper,
+                };
+            }
+            0x9002 => self.prg_swap = value & 0x02 != 0,
+            0xA000..=0xA003 => self.prg_banks[1] = value & 0x1F,
+            register @ 0xB000..=0xE003 => {
+                // Every page holds two banks, each written as the lower four bits and the upper five bits
+                let page = usize::from((register >> 12) - 0xB);
+                let slot = page * 2 + usize::from(register & 0x02 != 0);
+                let bank = &mut self.chr_banks[slot];
+                *bank = if register & 0x01 == 0 {
+                    (*bank & 0x1F0) | u16::from(value & 0x0F)
+                } else {
+                    (*bank & 0x00F) | u16::from(value & 0x1F) << 4
+                };
+            }
+            0xF000 => self.irq.set_latch_nibble(value, false),
+            0xF001 => self.irq.set_latch_nibble(value, true),
+            0xF002 => self.irq.write_control(value),
+            0xF003 => self.irq.acknowledge(),
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        let bank = self.chr_bank(address);
+        self.memory.read_chr(bank, CHR_BANK_SIZE, address)
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8) {
+        let bank = self.chr_bank(address);
+        self.memory.write_chr(bank, CHR_BANK_SIZE, address, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.irq()
+    }
+
+    fn clock_cpu(&mut self) {
+        self.irq.clock_cpu();
+    }
+
+    fn memory_map(&self) -> MemoryMap {
+        let mut map = MemoryMap::new().prg_ram(&self.memory, true, true);
+        for (address, switchable) in [
+            (0x8000, !self.prg_swap),
+            (0xA000, true),
+            (0xC000, self.prg_swap),
+            (0xE000, false),
+        ] {
+            let bank = self.prg_rom_bank(address);
+            map = map.prg_rom(&self.memory, address, bank, PRG_BANK_SIZE, switchable);
+        }
+
+        map = map
+            .cpu(0x8000..=0x8FFF, RegionKind::Registers("PRG bank 0"))
+            .cpu(
+                0x9000..=0x9FFF,
+                RegionKind::Registers("Mirroring and PRG swap mode"),
+            )
+            .cpu(0xA000..=0xAFFF, RegionKind::Registers("PRG bank 1"))
+            .cpu(0xB000..=0xEFFF, RegionKind::Registers("CHR banks"))
+            .cpu(0xF000..=0xFFFF, RegionKind::Registers("IRQ control"));
+
+        for slot in 0..8 {
+            let address = slot * 0x0400;
+            map = map.chr(
+                &self.memory,
+                address,
+                self.chr_bank(address),
+                CHR_BANK_SIZE,
+                true,
+            );
+        }
+
+        map
+    }
+
+    fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+}
+
+impl Savestate for Vrc4 {
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.memory.save_state(writer);
+        self.mirroring.save_state(writer);
+        writer.write_bytes(&self.prg_banks);
+        writer.write_bool(self.prg_swap);
+        for bank in self.chr_banks {
+            writer.write_u16(bank);
+        }
+        self.irq.save_state(writer);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), Error> {
+        self.memory.load_state(reader)?;
+        self.mirroring.load_state(reader)?;
+        reader.read_bytes_into(&mut self.prg_banks)?;
+        self.prg_swap = reader.read_bool()?;
+        for bank in &mut self.chr_banks {
+            *bank = reader.read_u16()?;
+        }
+        self.irq.load_state(reader)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,210 @@
+use {
+    crate::{IrqCounter, Mapper, Memory, MemoryMap, Mirroring, RegionKind, VrcIrq},
+    nes_state::{Error, Savestate, StateReader, StateWriter},
+};
+
+const PRG_16K_BANK_SIZE: usize = 0x4000;
+const PRG_8K_BANK_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x0400;
+
+// Bit of the banking control register enabling the PRG RAM
+const PRG_RAM_ENABLED: u8 = 0x80;
+
+/// Mappers 24 and 26; Konami VRC6 with a 16 KiB and an 8 KiB PRG bank, 1 KiB CHR banks and a VRC IRQ counter
+///
+/// Mapper 26 swaps the address lines selecting the registers within a page.
+/// Only the CHR banking mode with eight 1 KiB banks is emulated, which is the one all VRC6 games use,
+/// and the expansion audio is left to the APU.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Vrc6 {
+    memory: Memory,
+    swapped_lines: bool,
+    prg_bank_16k: u8,
+    prg_bank_8k: u8,
+    chr_banks: [u8; 8],
+    /// PPU banking mode, mirroring and PRG RAM enable
+    control: u8,
+    irq: VrcIrq,
+}
+
+impl Vrc6 {
+    /// Create the VRC6 of the mapper number, which selects the address lines of the registers
+    #[must_use]
+    pub fn new(memory: Memory, mapper_number: u8) -> Self {
+        Self {
+            memory,
+            swapped_lines: mapper_number == 26,
+            prg_bank_16k: 0,
+            prg_bank_8k: 0,
+            chr_banks: [0; 8],
+            control: 0,
+            irq: VrcIrq::new(),
+        }
+    }
+
+    // Page of the register together with the register within the page
+    fn register(&self, address: u16) -> u16 {
+        let select = if self.swapped_lines {
+            (address & 0x01) << 1 | (address & 0x02) >> 1
+        } else {
+            address & 0x03
+        };
+
+        (address & 0xF000) | select
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        self.control & PRG_RAM_ENABLED != 0
+    }
+
+    fn chr_bank(&self, address: u16) -> usize {
+        usize::from(self.chr_banks[usize::from(address / 0x0400)])
+    }
+}
+
+impl Mapper for Vrc6 {
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        match address {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => self.memory.read_prg_ram(address),
+            0x8000..=0xBFFF => {
+                self.memory
+                    .read_prg_rom(usize::from(self.prg_bank_16k), PRG_16K_BANK_SIZE, address)
+            }
+            0xC000..=0xDFFF => {
+                self.memory
+                    .read_prg_rom(usize::from(self.prg_bank_8k), PRG_8K_BANK_SIZE, address)
+            }
+            0xE000..=0xFFFF => {
+                let last_bank = self.memory.prg_banks(PRG_8K_BANK_SIZE) - 1;
+                self.memory
+                    .read_prg_rom(last_bank, PRG_8K_BANK_SIZE, address)
+            }
+            _ => 0,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        if let 0x6000..=0x7FFF = address {
+            if self.prg_ram_enabled() {
+                self.memory.write_prg_ram(address, value);
+            }
+            return;
+        }
+
+        match self.register(address) {
+            0x8000..=0x8003 => self.prg_bank_16k = value & 0x0F,
+            0xB003 => self.control = value,
+            0xC000..=0xC003 => self.prg_bank_8k = value & 0x1F,
+            register @ (0xD000..=0xD003 | 0xE000..=0xE003) => {
+                let slot = usize::from(register & 0x03) + usize::from(register >= 0xE000) * 4;
+                self.chr_banks[slot] = value;
+            }
+            0xF000 => self.irq.set_latch(value),
+            0xF001 => self.irq.write_control(value),
+            0xF002 => self.irq.acknowledge(),
+            // The registers of the expansion audio
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        let bank = self.chr_bank(address);
+        self.memory.read_chr(bank, CHR_BANK_SIZE, address)
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8) {
+        let bank = self.chr_bank(address);
+        self.memory.write_chr(bank, CHR_BANK_SIZE, address, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.control & 0x0C {
+            0x00 => Mirroring::Vertical,
+            0x04 => Mirroring::Horizontal,
+            0x08 => Mirroring::SingleScreenLower,
+            _ => Mirroring::SingleScreenUpper,
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.irq()
+    }
+
+    fn clock_cpu(&mut self) {
+        self.irq.clock_cpu();
+    }
+
+    fn memory_map(&self) -> MemoryMap {
+        let enabled = self.prg_ram_enabled();
+        let last_bank = self.memory.prg_banks(PRG_8K_BANK_SIZE) - 1;
+        let mut map = MemoryMap::new()
+            .prg_ram(&self.memory, enabled, enabled)
+            .prg_rom(
+                &self.memory,
+                0x8000,
+                usize::from(self.prg_bank_16k),
+                PRG_16K_BANK_SIZE,
+                true,
+            )
+            .prg_rom(
+                &self.memory,
+                0xC000,
+                usize::from(self.prg_bank_8k),
+                PRG_8K_BANK_SIZE,
+                true,
+            )
+            .prg_rom(&self.memory, 0xE000, last_bank, PRG_8K_BANK_SIZE, false)
+            .cpu(0x8000..=0x8FFF, RegionKind::Registers("16 KiB PRG bank"))
+            .cpu(0x9000..=0xAFFF, RegionKind::Registers("Audio"))
+            .cpu(
+                0xB000..=0xBFFF,
+                RegionKind::Registers("Audio and banking control"),
+            )
+            .cpu(0xC000..=0xCFFF, RegionKind::Registers("8 KiB PRG bank"))
+            .cpu(0xD000..=0xEFFF, RegionKind::Registers("CHR banks"))
+            .cpu(0xF000..=0xFFFF, RegionKind::Registers("IRQ control"));
+
+        for slot in 0..8 {
+            let address = slot * 0x0400;
+            map = map.chr(
+                &self.memory,
+                address,
+                self.chr_bank(address),
+                CHR_BANK_SIZE,
+                true,
+            );
+        }
+
+        map
+    }
+
+    fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+}
+
+impl Savestate for Vrc6 {
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.memory.save_state(writer);
+        writer.write_u8(self.prg_bank_16k);
+        writer.write_u8(self.prg_bank_8k);
+        writer.write_bytes(&self.chr_banks);
+        writer.write_u8(self.control);
+        self.irq.save_state(writer);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), Error> {
+        self.memory.load_state(reader)?;
+        self.prg_bank_16k = reader.read_u8()?;
+        self.prg_bank_8k = reader.read_u8()?;
+        reader.read_bytes_into(&mut self.chr_banks)?;
+        self.control = reader.read_u8()?;
+        self.irq.load_state(reader)?;
+
+        Ok(())
+    }
+}
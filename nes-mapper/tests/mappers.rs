@@ -0,0 +1,196 @@
+use nes_mapper::{new_mapper, Error, Mapper, Memory, Mirroring, DEFAULT_PRG_RAM_SIZE};
+
+// Every byte of the PRG ROM holds the number of its 8 KiB bank, every byte of the CHR the number of its 1 KiB bank
+fn memory(prg_rom_size: usize, chr_size: usize, chr_is_ram: bool) -> Memory {
+    let bank_numbers = |size: usize, bank_size: usize| {
+        (0..size).map(|offset| (offset / bank_size) as u8).collect()
+    };
+
+    Memory {
+        prg_rom: bank_numbers(prg_rom_size, 0x2000),
+        prg_ram: vec![0; DEFAULT_PRG_RAM_SIZE],
+        chr: bank_numbers(chr_size, 0x400),
+        chr_is_ram,
+    }
+}
+
+fn mapper(number: u8, prg_rom_size: usize, chr_size: usize) -> Box<dyn Mapper> {
+    new_mapper(
+        number,
+        memory(prg_rom_size, chr_size, false),
+        Mirroring::Horizontal,
+    )
+    .unwrap()
+}
+
+// The 8 KiB PRG ROM banks at $8000, $A000, $C000 and $E000
+fn prg_banks(mapper: &mut dyn Mapper) -> [u8; 4] {
+    [0x8000, 0xA000, 0xC000, 0xE000].map(|address| mapper.cpu_read(address))
+}
+
+// MMC1 registers are written one bit at a time
+fn write_mmc1(mapper: &mut dyn Mapper, address: u16, value: u8) {
+    for bit in 0..5 {
+        mapper.cpu_write(address, (value >> bit) & 0x01);
+    }
+}
+
+#[test]
+fn mirroring() {
+    let offsets = |mirroring: Mirroring| {
+        [0x2000, 0x2400, 0x2800, 0x2C05].map(|address| mirroring.vram_offset(address))
+    };
+
+    assert_eq!(offsets(Mirroring::Horizontal), [0, 0, 0x400, 0x405]);
+    assert_eq!(offsets(Mirroring::Vertical), [0, 0x400, 0, 0x405]);
+    assert_eq!(
+        offsets(Mirroring::SingleScreenUpper),
+        [0x400, 0x400, 0x400, 0x405]
+    );
+    assert_eq!(offsets(Mirroring::FourScreen), [0, 0x400, 0x800, 0xC05]);
+}
+
+#[test]
+fn nrom() {
+    let mut nrom = mapper(0, 0x4000, 0x2000);
+
+    // 16 KiB of PRG ROM are mirrored
+    assert_eq!(prg_banks(&mut *nrom), [0, 1, 0, 1]);
+    assert_eq!(nrom.ppu_read(0x1C00), 7);
+
+    nrom.cpu_write(0x6010, 0x42);
+    assert_eq!(nrom.cpu_read(0x6010), 0x42);
+    // CHR ROM can't be written to
+    nrom.ppu_write(0x0000, 0x42);
+    assert_eq!(nrom.ppu_read(0x0000), 0);
+}
+
+#[test]
+fn chr_ram() {
+    let mut nrom = new_mapper(0, memory(0x8000, 0x2000, true), Mirroring::Vertical).unwrap();
+
+    nrom.ppu_write(0x1234, 0x42);
+    assert_eq!(nrom.ppu_read(0x1234), 0x42);
+    assert_eq!(nrom.mirroring(), Mirroring::Vertical);
+}
+
+#[test]
+fn uxrom() {
+    let mut uxrom = mapper(2, 0x20000, 0x2000);
+    assert_eq!(prg_banks(&mut *uxrom), [0, 1, 14, 15]);
+
+    uxrom.cpu_write(0x8000, 3);
+    assert_eq!(prg_banks(&mut *uxrom), [6, 7, 14, 15]);
+}
+
+#[test]
+fn cnrom() {
+    let mut cnrom = mapper(3, 0x8000, 0x8000);
+    cnrom.cpu_write(0x8000, 2);
+
+    assert_eq!(cnrom.ppu_read(0x0000), 16);
+    assert_eq!(cnrom.ppu_read(0x1FFF), 23);
+    assert_eq!(prg_banks(&mut *cnrom), [0, 1, 2, 3]);
+}
+
+#[test]
+fn axrom() {
+    let mut axrom = mapper(7, 0x40000, 0);
+    assert_eq!(axrom.mirroring(), Mirroring::SingleScreenLower);
+
+    axrom.cpu_write(0x8000, 0x13);
+    assert_eq!(prg_banks(&mut *axrom), [12, 13, 14, 15]);
+    assert_eq!(axrom.mirroring(), Mirroring::SingleScreenUpper);
+}
+
+#[test]
+fn mmc1() {
+    let mut mmc1 = mapper(1, 0x20000, 0x20000);
+    // The last bank is fixed at $C000 after power-up
+    assert_eq!(prg_banks(&mut *mmc1), [0, 1, 14, 15]);
+
+    write_mmc1(&mut *mmc1, 0xE000, 2);
+    assert_eq!(prg_banks(&mut *mmc1), [4, 5, 14, 15]);
+
+    // 32 KiB PRG ROM mode and 4 KiB CHR mode with vertical mirroring
+    write_mmc1(&mut *mmc1, 0x8000, 0x12);
+    write_mmc1(&mut *mmc1, 0xE000, 3);
+    assert_eq!(prg_banks(&mut *mmc1), [4, 5, 6, 7]);
+    assert_eq!(mmc1.mirroring(), Mirroring::Vertical);
+
+    write_mmc1(&mut *mmc1, 0xA000, 5);
+    write_mmc1(&mut *mmc1, 0xC000, 9);
+    assert_eq!(mmc1.ppu_read(0x0000), 20);
+    assert_eq!(mmc1.ppu_read(0x1000), 36);
+
+    // Writing a set high bit resets the shift register and fixes the last bank again
+    mmc1.cpu_write(0x8000, 0x01);
+    mmc1.cpu_write(0x8000, 0x80);
+    write_mmc1(&mut *mmc1, 0xE000, 1);
+    assert_eq!(prg_banks(&mut *mmc1), [2, 3, 14, 15]);
+}
+
+#[test]
+fn mmc3_banking() {
+    let mut mmc3 = mapper(4, 0x20000, 0x20000);
+
+    mmc3.cpu_write(0x8000, 6);
+    mmc3.cpu_write(0x8001, 3);
+    mmc3.cpu_write(0x8000, 7);
+    mmc3.cpu_write(0x8001, 9);
+    assert_eq!(prg_banks(&mut *mmc3), [3, 9, 14, 15]);
+
+    // Swap the switchable and the fixed bank at $8000 and $C000
+    mmc3.cpu_write(0x8000, 0x46);
+    assert_eq!(prg_banks(&mut *mmc3), [14, 9, 3, 15]);
+
+    // 2 KiB bank at $0000 and 1 KiB bank at $1000
+    mmc3.cpu_write(0x8000, 0);
+    mmc3.cpu_write(0x8001, 21);
+    mmc3.cpu_write(0x8000, 2);
+    mmc3.cpu_write(0x8001, 42);
+    assert_eq!(mmc3.ppu_read(0x0000), 20);
+    assert_eq!(mmc3.ppu_read(0x0400), 21);
+    assert_eq!(mmc3.ppu_read(0x1000), 42);
+
+    mmc3.cpu_write(0xA000, 0);
+    assert_eq!(mmc3.mirroring(), Mirroring::Vertical);
+}
+
+#[test]
+fn mmc3_irq() {
+    let mut mmc3 = mapper(4, 0x20000, 0x20000);
+    let scanline = |mmc3: &mut dyn Mapper| {
+        // Background fetches from $0000 and sprite fetches from $1000 give one rising edge of A12,
+        // which only counts once A12 was low for a few CPU cycles
+        mmc3.ppu_read(0x0000);
+        for _ in 0..100 {
+            mmc3.clock_cpu();
+        }
+        mmc3.ppu_read(0x1000);
+    };
+
+    mmc3.cpu_write(0xC000, 2);
+    mmc3.cpu_write(0xC001, 0);
+    mmc3.cpu_write(0xE001, 0);
+
+    // Reloading takes one scanline, then the counter counts down to zero
+    for _ in 0..2 {
+        scanline(&mut *mmc3);
+        assert!(!mmc3.irq());
+    }
+    scanline(&mut *mmc3);
+    assert!(mmc3.irq());
+
+    // Disabling the IRQ acknowledges it
+    mmc3.cpu_write(0xE000, 0);
+    assert!(!mmc3.irq());
+}
+
+#[test]
+fn unsupported_mapper() {
+    assert!(matches!(
+        new_mapper(5, memory(0x8000, 0x2000, false), Mirroring::Vertical),
+        Err(Error::UnsupportedMapper(5))
+    ));
+}
@@ -0,0 +1,422 @@
+//!
+//! Movie format of `BizHawk`
+//!
+//! BK2 files are ZIP archives containing the header, the input log and a few optional files.
+//!
+//! [Format documentation](https://tasvideos.org/Bizhawk/BK2Format)
+//!
+
+use {
+    crate::{
+        fm2::{self, Fm2},
+        Buttons, Commands, Error, Frame, Result,
+    },
+    std::{
+        fmt::Write as _,
+        io::{Read, Seek, Write},
+    },
+    zip::{write::FileOptions, ZipArchive, ZipWriter},
+};
+
+const HEADER_FILE: &str = "Header.txt";
+const INPUT_LOG_FILE: &str = "Input Log.txt";
+const COMMENTS_FILE: &str = "Comments.txt";
+const SUBTITLES_FILE: &str = "Subtitles.txt";
+const SYNC_SETTINGS_FILE: &str = "SyncSettings.json";
+
+// Buttons of a controller in the order of the log key, with their mnemonics
+const BUTTONS: [(&str, char, Buttons); 8] = [
+    ("Up", 'U', Buttons::UP),
+    ("Down", 'D', Buttons::DOWN),
+    ("Left", 'L', Buttons::LEFT),
+    ("Right", 'R', Buttons::RIGHT),
+    ("Start", 'S', Buttons::START),
+    ("Select", 's', Buttons::SELECT),
+    ("B", 'B', Buttons::B),
+    ("A", 'A', Buttons::A),
+];
+
+// Console buttons in front of the controllers, with their mnemonics
+const COMMANDS: [(&str, char, Commands); 2] = [
+    ("Reset", 'r', Commands::SOFT_RESET),
+    ("Power", 'P', Commands::HARD_RESET),
+];
+
+/// What a single input in the log controls
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Input {
+    Command(Commands),
+    Button(usize, Buttons),
+    /// Inputs of other devices are skipped
+    Unknown,
+}
+
+impl Input {
+    fn from_name(name: &str) -> Self {
+        if let Some((_, _, command)) = COMMANDS.iter().find(|(known, ..)| *known == name) {
+            return Self::Command(*command);
+        }
+
+        // Controller buttons are named like "P1 Up"
+        let button = name.strip_prefix('P').and_then(|name| {
+            let (port, button) = name.split_once(' ')?;
+            let port = port.parse::<usize>().ok()?.checked_sub(1)?;
+            let (_, _, buttons) = BUTTONS.iter().find(|(known, ..)| *known == button)?;
+
+            (port < 4).then_some((port, *buttons))
+        });
+
+        button.map_or(Self::Unknown, |(port, buttons)| Self::Button(port, buttons))
+    }
+}
+
+/// Text shown on top of the game for a few frames
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Subtitle {
+    pub frame: u64,
+    pub x: u32,
+    pub y: u32,
+    pub duration: u64,
+    /// ARGB colour of the text
+    pub colour: u32,
+    pub text: String,
+}
+
+/// BK2 movie of the NES core
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Bk2 {
+    /// Entries of the header in their original order
+    pub header: Vec<(String, String)>,
+    /// Amount of controllers in the input log
+    pub controllers: usize,
+    pub frames: Vec<Frame>,
+    pub comments: Vec<String>,
+    pub subtitles: Vec<Subtitle>,
+    /// Settings of the emulator core as JSON
+    pub sync_settings: Option<String>,
+}
+
+fn read_file<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> Result<Option<String>> {
+    let mut file = match archive.by_name(name) {
+        Ok(file) => file,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    Ok(Some(contents))
+}
+
+fn parse_subtitle(line: &str, number: usize) -> Result<Subtitle> {
+    let invalid = || Error::InvalidHeader(number);
+
+    let mut fields = line.splitn(7, ' ').skip(1);
+    let mut next = || fields.next().ok_or_else(invalid);
+    let (frame, x, y, duration, colour) = (next()?, next()?, next()?, next()?, next()?);
+
+    Ok(Subtitle {
+        frame: frame.parse().map_err(|_| invalid())?,
+        x: x.parse().map_err(|_| invalid())?,
+        y: y.parse().map_err(|_| invalid())?,
+        duration: duration.parse().map_err(|_| invalid())?,
+        colour: u32::from_str_radix(colour, 16).map_err(|_| invalid())?,
+        text: next().unwrap_or_default().to_string(),
+    })
+}
+
+impl Bk2 {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            controllers: 2,
+            ..Self::default()
+        }
+    }
+
+    /// Value of a header entry
+    #[must_use]
+    pub fn header_value(&self, key: &str) -> Option<&str> {
+        self.header
+            .iter()
+            .find(|(known, _)| known == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Set a header entry, replacing the previous value
+    pub fn set_header_value(&mut self, key: &str, value: &str) {
+        match self.header.iter_mut().find(|(known, _)| known == key) {
+            Some((_, previous)) => *previous = value.to_string(),
+            None => self.header.push((key.to_string(), value.to_string())),
+        }
+    }
+
+    /// Read a BK2 archive
+    pub fn from_reader<R: Read + Seek>(reader: R) -> Result<Self> {
+        let mut archive = ZipArchive::new(reader)?;
+
+        let header =
+            read_file(&mut archive, HEADER_FILE)?.ok_or(Error::MissingFile(HEADER_FILE))?;
+        let input_log =
+            read_file(&mut archive, INPUT_LOG_FILE)?.ok_or(Error::MissingFile(INPUT_LOG_FILE))?;
+
+        let mut movie = Self::parse_input_log(&input_log)?;
+        movie.header = header
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+                (key.to_string(), value.to_string())
+            })
+            .collect();
+
+        if let Some(comments) = read_file(&mut archive, COMMENTS_FILE)? {
+            movie.comments = comments.lines().map(ToString::to_string).collect();
+        }
+        if let Some(subtitles) = read_file(&mut archive, SUBTITLES_FILE)? {
+            movie.subtitles = subtitles
+                .lines()
+                .enumerate()
+                .filter(|(_, line)| line.starts_with("subtitle "))
+                .map(|(index, line)| parse_subtitle(line, index + 1))
+                .collect::<Result<_>>()?;
+        }
+        movie.sync_settings = read_file(&mut archive, SYNC_SETTINGS_FILE)?;
+
+        Ok(movie)
+    }
+
+    /// Parse the contents of the input log file
+    fn parse_input_log(input_log: &str) -> Result<Self> {
+        let mut inputs: Vec<Vec<Input>> = Vec::new();
+        let mut frames = Vec::new();
+
+        for (index, line) in input_log.lines().enumerate() {
+            if let Some(log_key) = line.strip_prefix("LogKey:") {
+                // Every group starts with a '#' and contains the inputs of one device
+                inputs = log_key
+                    .split('#')
+                    .filter(|group| !group.is_empty())
+                    .map(|group| {
+                        group
+                            .split('|')
+                            .filter(|name| !name.is_empty())
+                            .map(Input::from_name)
+                            .collect()
+                    })
+                    .collect();
+            } else if line.starts_with('|') {
+                if inputs.is_empty() {
+                    return Err(Error::InvalidLogKey);
+                }
+
+                let mut frame = Frame::default();
+                let groups = line.split('|').skip(1).zip(&inputs);
+                for (field, group) in groups {
+                    for (character, input) in field.chars().zip(group) {
+                        if character == '.' || character == ' ' {
+                            continue;
+                        }
+
+                        match input {
+                            Input::Command(command) => frame.commands |= *command,
+                            Input::Button(port, buttons) => frame.buttons[*port] |= *buttons,
+                            Input::Unknown => {}
+                        }
+                    }
+                }
+
+                if line.split('|').count() < inputs.len() + 2 {
+                    return Err(Error::InvalidInput(index + 1));
+                }
+                frames.push(frame);
+            }
+        }
+
+        let controllers = inputs
+            .iter()
+            .flatten()
+            .filter_map(|input| match input {
+                Input::Button(port, _) => Some(port + 1),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0);
+
+        Ok(Self {
+            controllers,
+            frames,
+            ..Self::default()
+        })
+    }
+
+    /// Write the BK2 archive
+    pub fn write<W: Write + Seek>(&self, writer: W) -> Result<()> {
+        let mut archive = ZipWriter::new(writer);
+        let options = FileOptions::default();
+
+        archive.start_file(HEADER_FILE, options)?;
+        for (key, value) in &self.header {
+            writeln!(archive, "{key} {value}")?;
+        }
+
+        archive.start_file(INPUT_LOG_FILE, options)?;
+        archive.write_all(self.input_log().as_bytes())?;
+
+        if !self.comments.is_empty() {
+            archive.start_file(COMMENTS_FILE, options)?;
+            for comment in &self.comments {
+                writeln!(archive, "{comment}")?;
+            }
+        }
+
+        if !self.subtitles.is_empty() {
+            archive.start_file(SUBTITLES_FILE, options)?;
+            for subtitle in &self.subtitles {
+                writeln!(
+                    archive,
+                    "subtitle {} {} {} {} {:08X} {}",
+                    subtitle.frame,
+                    subtitle.x,
+                    subtitle.y,
+                    subtitle.duration,
+                    subtitle.colour,
+                    subtitle.text
+                )?;
+            }
+        }
+
+        if let Some(sync_settings) = &self.sync_settings {
+            archive.start_file(SYNC_SETTINGS_FILE, options)?;
+            archive.write_all(sync_settings.as_bytes())?;
+        }
+
+        archive.finish()?;
+        Ok(())
+    }
+
+    /// Contents of the input log file
+    fn input_log(&self) -> String {
+        let controllers = self.controllers.min(4);
+        let mut log = String::from("[Input]\nLogKey:#");
+
+        for (name, ..) in COMMANDS {
+            let _ = write!(log, "{name}|");
+        }
+        for port in 1..=controllers {
+            log.push('#');
+            for (name, ..) in BUTTONS {
+                let _ = write!(log, "P{port} {name}|");
+            }
+        }
+        log.push('\n');
+
+        for frame in &self.frames {
+            log.push('|');
+            for (_, mnemonic, command) in COMMANDS {
+                log.push(if frame.commands.contains(command) {
+                    mnemonic
+                } else {
+                    '.'
+                });
+            }
+
+            for buttons in &frame.buttons[..controllers] {
+                log.push('|');
+                for (_, mnemonic, button) in BUTTONS {
+                    log.push(if buttons.contains(button) {
+                        mnemonic
+                    } else {
+                        '.'
+                    });
+                }
+            }
+            log.push_str("|\n");
+        }
+
+        log.push_str("[/Input]\n");
+        log
+    }
+
+    /// Convert an FM2 movie
+    ///
+    /// FM2 only stores an MD5 of the ROM, so the SHA1 `BizHawk` expects is missing from the header
+    #[must_use]
+    pub fn from_fm2(fm2: &Fm2) -> Self {
+        let header = &fm2.header;
+        let mut movie = Self::new();
+
+        movie.set_header_value("MovieVersion", "BizHawk v2.0.0");
+        movie.set_header_value("Platform", "NES");
+        movie.set_header_value("Core", "NesHawk");
+        movie.set_header_value("GameName", &header.rom_filename);
+        movie.set_header_value("rerecordCount", &header.rerecord_count.to_string());
+        if header.pal {
+            movie.set_header_value("PAL", "True");
+        }
+        if let Some(author) = header
+            .comments
+            .iter()
+            .find_map(|comment| comment.strip_prefix("author "))
+        {
+            movie.set_header_value("Author", author);
+        }
+
+        movie.controllers = if header.fourscore { 4 } else { 2 };
+        movie.frames.clone_from(&fm2.frames);
+        movie.comments.clone_from(&header.comments);
+        movie.subtitles = header
+            .subtitles
+            .iter()
+            .map(|subtitle| Subtitle {
+                frame: subtitle.frame,
+                x: 0,
+                y: 0,
+                duration: 120,
+                colour: 0xFFFF_FFFF,
+                text: subtitle.text.clone(),
+            })
+            .collect();
+
+        movie
+    }
+
+    /// Convert to an FM2 movie
+    #[must_use]
+    pub fn to_fm2(&self) -> Fm2 {
+        let mut movie = Fm2::new();
+        let header = &mut movie.header;
+
+        header.version = 3;
+        header.rom_filename = self
+            .header_value("GameName")
+            .unwrap_or_default()
+            .to_string();
+        header.rerecord_count = self
+            .header_value("rerecordCount")
+            .and_then(|count| count.parse().ok())
+            .unwrap_or(0);
+        header.pal = self
+            .header_value("PAL")
+            .is_some_and(|pal| pal.eq_ignore_ascii_case("true"));
+        header.fourscore = self.controllers > 2;
+        header.comments.clone_from(&self.comments);
+        if let Some(author) = self.header_value("Author") {
+            let author = format!("author {author}");
+            if !header.comments.contains(&author) {
+                header.comments.insert(0, author);
+            }
+        }
+        header.subtitles = self
+            .subtitles
+            .iter()
+            .map(|subtitle| fm2::Subtitle {
+                frame: subtitle.frame,
+                text: subtitle.text.clone(),
+            })
+            .collect();
+
+        movie.frames.clone_from(&self.frames);
+        movie
+    }
+}
@@ -0,0 +1,275 @@
+//!
+//! FCEUX movie format
+//!
+//! [Format documentation](https://fceux.com/web/FM2.html)
+//!
+
+use {
+    crate::{Buttons, Commands, Error, Frame, Result},
+    alloc::{
+        string::{String, ToString},
+        vec::Vec,
+    },
+    core::{fmt, str::FromStr},
+};
+
+// Button characters of an input record, from the highest to the lowest bit
+const BUTTON_CHARS: [char; 8] = ['R', 'L', 'D', 'U', 'T', 'S', 'B', 'A'];
+
+/// Device connected to one of the controller ports
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Device {
+    None,
+    #[default]
+    Gamepad,
+}
+
+impl Device {
+    fn from_number(number: u8) -> Result<Self> {
+        match number {
+            0 => Ok(Self::None),
+            1 => Ok(Self::Gamepad),
+            _ => Err(Error::UnsupportedDevice(number)),
+        }
+    }
+
+    fn number(self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Gamepad => 1,
+        }
+    }
+}
+
+/// Text shown starting with a frame
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Subtitle {
+    pub frame: u64,
+    pub text: String,
+}
+
+/// Key/value pairs at the start of the movie
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+// The flags mirror the ones of the format
+#[allow(clippy::struct_excessive_bools)]
+pub struct Header {
+    pub version: u32,
+    pub emu_version: u32,
+    pub rerecord_count: u32,
+    pub pal: bool,
+    pub new_ppu: bool,
+    pub fds: bool,
+    /// Four gamepads are connected via the Four Score adapter; the ports are ignored
+    pub fourscore: bool,
+    pub microphone: bool,
+    pub ports: [Device; 2],
+    /// Device connected to the expansion port of the Famicom, stored as its FCEUX number
+    pub expansion_port: u8,
+    pub rom_filename: String,
+    /// Base64 encoded MD5 of the ROM, prefixed with `base64:`
+    pub rom_checksum: String,
+    pub guid: String,
+    /// The movie starts from this (base64 encoded) save state instead of power-on
+    pub savestate: Option<String>,
+    pub comments: Vec<String>,
+    pub subtitles: Vec<Subtitle>,
+    /// Entries this parser doesn't know, kept so they survive a round-trip
+    pub unknown: Vec<(String, String)>,
+}
+
+/// FM2 movie
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Fm2 {
+    pub header: Header,
+    pub frames: Vec<Frame>,
+}
+
+fn parse_number<T: FromStr>(value: &str, line: usize) -> Result<T> {
+    value.trim().parse().map_err(|_| Error::InvalidHeader(line))
+}
+
+fn parse_flag(value: &str, line: usize) -> Result<bool> {
+    Ok(parse_number::<u8>(value, line)? != 0)
+}
+
+fn parse_buttons(field: &str, line: usize) -> Result<Buttons> {
+    if field.chars().count() != BUTTON_CHARS.len() {
+        return Err(Error::InvalidInput(line));
+    }
+
+    // Every character other than a dot or a space counts as pressed
+    let buttons = field
+        .chars()
+        .zip((0..8).rev())
+        .filter(|(character, _)| *character != '.' && *character != ' ')
+        .fold(0, |buttons, (_, bit)| buttons | (1 << bit));
+
+    Ok(Buttons(buttons))
+}
+
+impl Fm2 {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse an FM2 movie
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line));
+
+        let mut header = Header::default();
+        match lines.next() {
+            Some((line, entry)) if entry.starts_with("version ") => {
+                header.version = parse_number(&entry["version ".len()..], line)?;
+            }
+            _ => return Err(Error::MissingVersion),
+        }
+
+        let mut frames = Vec::new();
+        for (line, entry) in lines {
+            if entry.starts_with('|') {
+                frames.push(Self::parse_frame(&header, entry, line)?);
+            } else if !entry.trim().is_empty() {
+                Self::parse_header_entry(&mut header, entry, line)?;
+            }
+        }
+
+        Ok(Self { header, frames })
+    }
+
+    fn parse_header_entry(header: &mut Header, entry: &str, line: usize) -> Result<()> {
+        let (key, value) = entry.split_once(' ').unwrap_or((entry, ""));
+
+        match key {
+            "emuVersion" => header.emu_version = parse_number(value, line)?,
+            "rerecordCount" => header.rerecord_count = parse_number(value, line)?,
+            "palFlag" => header.pal = parse_flag(value, line)?,
+            "NewPPU" => header.new_ppu = parse_flag(value, line)?,
+            "FDS" => header.fds = parse_flag(value, line)?,
+            "fourscore" => header.fourscore = parse_flag(value, line)?,
+            "microphone" => header.microphone = parse_flag(value, line)?,
+            "port0" => header.ports[0] = Device::from_number(parse_number(value, line)?)?,
+            "port1" => header.ports[1] = Device::from_number(parse_number(value, line)?)?,
+            "port2" => header.expansion_port = parse_number(value, line)?,
+            "romFilename" => header.rom_filename = value.to_string(),
+            "romChecksum" => header.rom_checksum = value.to_string(),
+            "guid" => header.guid = value.to_string(),
+            "savestate" => header.savestate = Some(value.to_string()),
+            "comment" => header.comments.push(value.to_string()),
+            "subtitle" => {
+                let (frame, text) = value.split_once(' ').unwrap_or((value, ""));
+                header.subtitles.push(Subtitle {
+                    frame: parse_number(frame, line)?,
+                    text: text.to_string(),
+                });
+            }
+            "binary" if parse_flag(value, line)? => return Err(Error::BinaryInput),
+            // Only needed for the binary format
+            "binary" | "length" => {}
+            _ => header.unknown.push((key.to_string(), value.to_string())),
+        }
+
+        Ok(())
+    }
+
+    fn parse_frame(header: &Header, entry: &str, line: usize) -> Result<Frame> {
+        // The record starts and ends with a pipe, so the first field is empty
+        let mut fields = entry.split('|').skip(1);
+
+        let commands = fields
+            .next()
+            .and_then(|commands| commands.trim().parse().ok())
+            .ok_or(Error::InvalidInput(line))?;
+
+        let mut frame = Frame {
+            commands: Commands(commands),
+            buttons: [Buttons::NONE; 4],
+        };
+
+        let gamepads = if header.fourscore { 4 } else { 2 };
+        for (index, buttons) in frame.buttons.iter_mut().enumerate().take(gamepads) {
+            let field = fields.next().ok_or(Error::InvalidInput(line))?;
+            if header.fourscore || header.ports[index] == Device::Gamepad {
+                *buttons = parse_buttons(field, line)?;
+            }
+        }
+
+        Ok(frame)
+    }
+}
+
+impl FromStr for Fm2 {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        Self::parse(text)
+    }
+}
+
+fn write_buttons(f: &mut fmt::Formatter<'_>, buttons: Buttons) -> fmt::Result {
+    for (character, bit) in BUTTON_CHARS.iter().zip((0..8).rev()) {
+        let pressed = buttons.0 & (1 << bit) != 0;
+        write!(f, "{}", if pressed { *character } else { '.' })?;
+    }
+
+    Ok(())
+}
+
+impl fmt::Display for Fm2 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let header = &self.header;
+
+        writeln!(f, "version {}", header.version)?;
+        writeln!(f, "emuVersion {}", header.emu_version)?;
+        writeln!(f, "rerecordCount {}", header.rerecord_count)?;
+        writeln!(f, "palFlag {}", u8::from(header.pal))?;
+        writeln!(f, "romFilename {}", header.rom_filename)?;
+        writeln!(f, "romChecksum {}", header.rom_checksum)?;
+        writeln!(f, "guid {}", header.guid)?;
+        writeln!(f, "fourscore {}", u8::from(header.fourscore))?;
+        writeln!(f, "microphone {}", u8::from(header.microphone))?;
+        writeln!(f, "port0 {}", header.ports[0].number())?;
+        writeln!(f, "port1 {}", header.ports[1].number())?;
+        writeln!(f, "port2 {}", header.expansion_port)?;
+        writeln!(f, "FDS {}", u8::from(header.fds))?;
+        writeln!(f, "NewPPU {}", u8::from(header.new_ppu))?;
+        if let Some(savestate) = &header.savestate {
+            writeln!(f, "savestate {savestate}")?;
+        }
+        for (key, value) in &header.unknown {
+            writeln!(f, "{key} {value}")?;
+        }
+        for comment in &header.comments {
+            writeln!(f, "comment {comment}")?;
+        }
+        for subtitle in &header.subtitles {
+            writeln!(f, "subtitle {} {}", subtitle.frame, subtitle.text)?;
+        }
+
+        for frame in &self.frames {
+            write!(f, "|{}|", frame.commands.0)?;
+
+            if header.fourscore {
+                for buttons in frame.buttons {
+                    write_buttons(f, buttons)?;
+                    f.write_str("|")?;
+                }
+            } else {
+                for (device, buttons) in header.ports.iter().zip(frame.buttons) {
+                    if *device == Device::Gamepad {
+                        write_buttons(f, buttons)?;
+                    }
+                    f.write_str("|")?;
+                }
+            }
+
+            // The expansion port is always empty
+            writeln!(f, "|")?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![warn(clippy::all, clippy::pedantic)]
+#![allow(clippy::missing_errors_doc)]
+
+//!
+//! Parser and writer for input movies of NES emulators
+//!
+
+extern crate alloc;
+
+use core::ops::{BitOr, BitOrAssign};
+
+#[cfg(feature = "bk2")]
+pub mod bk2;
+pub mod fm2;
+pub mod project;
+
+#[cfg(feature = "bk2")]
+pub use bk2::Bk2;
+pub use {fm2::Fm2, project::Project};
+
+type Result<T> = core::result::Result<T, Error>;
+
+#[derive(Debug)]
+#[cfg_attr(feature = "std", derive(thiserror::Error))]
+pub enum Error {
+    #[cfg(feature = "std")]
+    #[error("IO error: {:?}", .0)]
+    Io(#[from] std::io::Error),
+
+    #[cfg(feature = "bk2")]
+    #[error("ZIP error: {}", .0)]
+    Zip(#[from] zip::result::ZipError),
+
+    #[cfg_attr(feature = "std", error("The movie doesn't contain the file {}", .0))]
+    MissingFile(&'static str),
+
+    #[cfg_attr(feature = "std", error("Invalid log key"))]
+    InvalidLogKey,
+
+    #[cfg_attr(feature = "std", error("The movie doesn't start with a version"))]
+    MissingVersion,
+
+    #[cfg_attr(feature = "std", error("Invalid header entry in line {}", .0))]
+    InvalidHeader(usize),
+
+    #[cfg_attr(feature = "std", error("Invalid input record in line {}", .0))]
+    InvalidInput(usize),
+
+    #[cfg_attr(feature = "std", error("Binary input logs aren't supported"))]
+    BinaryInput,
+
+    #[cfg_attr(feature = "std", error("Input device {} isn't supported", .0))]
+    UnsupportedDevice(u8),
+}
+
+/// Buttons of a standard controller
+///
+/// The bits are in the order the controller shifts them out, like the buttons of `nes-emulator`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Buttons(pub u8);
+
+impl Buttons {
+    pub const NONE: Self = Self(0x00);
+    pub const A: Self = Self(0x01);
+    pub const B: Self = Self(0x02);
+    pub const SELECT: Self = Self(0x04);
+    pub const START: Self = Self(0x08);
+    pub const UP: Self = Self(0x10);
+    pub const DOWN: Self = Self(0x20);
+    pub const LEFT: Self = Self(0x40);
+    pub const RIGHT: Self = Self(0x80);
+
+    #[must_use]
+    pub const fn contains(self, buttons: Self) -> bool {
+        self.0 & buttons.0 == buttons.0
+    }
+}
+
+impl BitOr for Buttons {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for Buttons {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Commands issued at the start of a frame
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Commands(pub u8);
+
+impl Commands {
+    pub const NONE: Self = Self(0x00);
+    pub const SOFT_RESET: Self = Self(0x01);
+    pub const HARD_RESET: Self = Self(0x02);
+    pub const FDS_INSERT: Self = Self(0x04);
+    pub const FDS_SELECT_SIDE: Self = Self(0x08);
+    pub const VS_INSERT_COIN: Self = Self(0x10);
+
+    #[must_use]
+    pub const fn contains(self, commands: Self) -> bool {
+        self.0 & commands.0 == commands.0
+    }
+}
+
+impl BitOr for Commands {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for Commands {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Input of one frame
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Frame {
+    pub commands: Commands,
+    /// Buttons of the gamepads; only the first two are used without a Four Score
+    pub buttons: [Buttons; 4],
+}
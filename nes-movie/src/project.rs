@@ -0,0 +1,314 @@
+//!
+//! Editable movie for TAS editors
+//!
+//! A [`Project`] holds the input of a movie together with the markers and branches of a piano roll editor.
+//! It's imported from and exported to FM2 or BK2; markers and branches only exist in the project.
+//!
+
+#[cfg(feature = "bk2")]
+use crate::Bk2;
+use {
+    crate::{
+        fm2::{Fm2, Header},
+        Buttons, Commands, Frame,
+    },
+    alloc::{string::String, vec::Vec},
+    core::{cmp, ops::Range},
+};
+
+/// Frame annotated with a note, like the start of a level
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Marker {
+    pub frame: usize,
+    pub note: String,
+}
+
+/// Saved copy of the input and the markers, to try out an alternative route and return to it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Branch {
+    pub name: String,
+    pub frames: Vec<Frame>,
+    pub markers: Vec<Marker>,
+}
+
+/// Input movie with the editing operations of a TAS editor
+///
+/// Every edit remembers the first frame it changed. Editors emulating the movie ask for it with
+/// [`Project::take_invalidated`] to know from which frame on their saved states are outdated.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Project {
+    /// Header of the exported movie; the rerecord count gets increased whenever a branch is loaded
+    pub header: Header,
+    frames: Vec<Frame>,
+    /// Markers ordered by frame, at most one per frame
+    markers: Vec<Marker>,
+    branches: Vec<Branch>,
+    invalidated: Option<usize>,
+}
+
+impl Project {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn from_fm2(fm2: &Fm2) -> Self {
+        Self {
+            header: fm2.header.clone(),
+            frames: fm2.frames.clone(),
+            ..Self::default()
+        }
+    }
+
+    /// Movie with the current input, dropping the markers and branches
+    #[must_use]
+    pub fn to_fm2(&self) -> Fm2 {
+        Fm2 {
+            header: self.header.clone(),
+            frames: self.frames.clone(),
+        }
+    }
+
+    /// Import a BK2 movie through its conversion to FM2, which keeps the input, the comments and the subtitles
+    #[cfg(feature = "bk2")]
+    #[must_use]
+    pub fn from_bk2(bk2: &Bk2) -> Self {
+        Self::from_fm2(&bk2.to_fm2())
+    }
+
+    #[cfg(feature = "bk2")]
+    #[must_use]
+    pub fn to_bk2(&self) -> Bk2 {
+        Bk2::from_fm2(&self.to_fm2())
+    }
+
+    #[must_use]
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    #[must_use]
+    pub fn frame(&self, index: usize) -> Option<&Frame> {
+        self.frames.get(index)
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    // Record that the frames starting at the index changed
+    fn invalidate(&mut self, index: usize) {
+        self.invalidated = Some(self.invalidated.map_or(index, |first| first.min(index)));
+    }
+
+    /// First frame changed since the last call, if any
+    pub fn take_invalidated(&mut self) -> Option<usize> {
+        self.invalidated.take()
+    }
+
+    // Frame at the index, appending empty frames to reach it
+    fn frame_mut(&mut self, index: usize) -> &mut Frame {
+        if index >= self.frames.len() {
+            self.frames.resize(index + 1, Frame::default());
+        }
+
+        &mut self.frames[index]
+    }
+
+    /// Insert frames in front of the index, shifting the following frames and their markers
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is past the end of the movie
+    pub fn insert_frames(&mut self, index: usize, frames: &[Frame]) {
+        self.frames.splice(index..index, frames.iter().copied());
+        for marker in &mut self.markers {
+            if marker.frame >= index {
+                marker.frame += frames.len();
+            }
+        }
+        self.invalidate(index);
+    }
+
+    /// Insert frames without any input in front of the index
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is past the end of the movie
+    pub fn insert_empty_frames(&mut self, index: usize, count: usize) {
+        self.insert_frames(index, &alloc::vec![Frame::default(); count]);
+    }
+
+    /// Delete the frames, shifting the following frames and their markers back
+    ///
+    /// Markers on deleted frames are removed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds
+    pub fn delete_frames(&mut self, frames: Range<usize>) {
+        self.frames.drain(frames.clone());
+        self.markers
+            .retain(|marker| !frames.contains(&marker.frame));
+        for marker in &mut self.markers {
+            if marker.frame >= frames.end {
+                marker.frame -= frames.len();
+            }
+        }
+        self.invalidate(frames.start);
+    }
+
+    /// Replace the input of a frame, extending the movie if the frame is past its end
+    pub fn set_frame(&mut self, index: usize, frame: Frame) {
+        if self.frames.get(index) != Some(&frame) {
+            *self.frame_mut(index) = frame;
+            self.invalidate(index);
+        }
+    }
+
+    /// Set the buttons held on a controller, extending the movie if the frame is past its end
+    ///
+    /// # Panics
+    ///
+    /// Panics if the controller isn't one of the four
+    pub fn set_buttons(&mut self, index: usize, controller: usize, buttons: Buttons) {
+        let mut frame = self.frames.get(index).copied().unwrap_or_default();
+        frame.buttons[controller] = buttons;
+        self.set_frame(index, frame);
+    }
+
+    /// Toggle buttons on a controller, returning whether they're held afterwards
+    ///
+    /// # Panics
+    ///
+    /// Panics if the controller isn't one of the four
+    pub fn toggle_buttons(&mut self, index: usize, controller: usize, buttons: Buttons) -> bool {
+        let current = self
+            .frames
+            .get(index)
+            .map_or(Buttons::NONE, |frame| frame.buttons[controller]);
+        let held = current.contains(buttons);
+
+        let toggled = if held {
+            Buttons(current.0 & !buttons.0)
+        } else {
+            current | buttons
+        };
+        self.set_buttons(index, controller, toggled);
+
+        !held
+    }
+
+    /// Set the commands (resets, FDS disk changes) of a frame, extending the movie if the frame is past its end
+    pub fn set_commands(&mut self, index: usize, commands: Commands) {
+        let mut frame = self.frames.get(index).copied().unwrap_or_default();
+        frame.commands = commands;
+        self.set_frame(index, frame);
+    }
+
+    /// Cut the movie down to the amount of frames
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.frames.len() {
+            self.delete_frames(len..self.frames.len());
+        }
+    }
+
+    /// Markers ordered by frame
+    #[must_use]
+    pub fn markers(&self) -> &[Marker] {
+        &self.markers
+    }
+
+    #[must_use]
+    pub fn marker(&self, frame: usize) -> Option<&Marker> {
+        self.markers
+            .binary_search_by_key(&frame, |marker| marker.frame)
+            .ok()
+            .map(|index| &self.markers[index])
+    }
+
+    /// Set the marker of a frame, replacing its previous note
+    pub fn set_marker(&mut self, frame: usize, note: String) {
+        match self
+            .markers
+            .binary_search_by_key(&frame, |marker| marker.frame)
+        {
+            Ok(index) => self.markers[index].note = note,
+            Err(index) => self.markers.insert(index, Marker { frame, note }),
+        }
+    }
+
+    pub fn remove_marker(&mut self, frame: usize) -> Option<Marker> {
+        self.markers
+            .binary_search_by_key(&frame, |marker| marker.frame)
+            .ok()
+            .map(|index| self.markers.remove(index))
+    }
+
+    /// Closest marker in front of the frame, for jumping back through the movie
+    #[must_use]
+    pub fn previous_marker(&self, frame: usize) -> Option<&Marker> {
+        self.markers
+            .iter()
+            .rev()
+            .find(|marker| marker.frame < frame)
+    }
+
+    /// Closest marker after the frame
+    #[must_use]
+    pub fn next_marker(&self, frame: usize) -> Option<&Marker> {
+        self.markers.iter().find(|marker| marker.frame > frame)
+    }
+
+    #[must_use]
+    pub fn branches(&self) -> &[Branch] {
+        &self.branches
+    }
+
+    /// Save the input and the markers as a new branch, returning its index
+    pub fn save_branch(&mut self, name: String) -> usize {
+        self.branches.push(Branch {
+            name,
+            frames: self.frames.clone(),
+            markers: self.markers.clone(),
+        });
+
+        self.branches.len() - 1
+    }
+
+    /// Replace the input and the markers with the ones of a branch, which counts as a rerecord
+    ///
+    /// Returns `false` if there's no branch with the index.
+    pub fn load_branch(&mut self, index: usize) -> bool {
+        let Some(branch) = self.branches.get(index) else {
+            return false;
+        };
+
+        let first_difference = (self.frames != branch.frames).then(|| {
+            self.frames
+                .iter()
+                .zip(&branch.frames)
+                .position(|(frame, other)| frame != other)
+                .unwrap_or_else(|| cmp::min(self.frames.len(), branch.frames.len()))
+        });
+        self.frames.clone_from(&branch.frames);
+        self.markers.clone_from(&branch.markers);
+        if let Some(first_difference) = first_difference {
+            self.invalidate(first_difference);
+        }
+        self.header.rerecord_count = self.header.rerecord_count.saturating_add(1);
+
+        true
+    }
+
+    pub fn remove_branch(&mut self, index: usize) -> Option<Branch> {
+        (index < self.branches.len()).then(|| self.branches.remove(index))
+    }
+}
@@ -0,0 +1,44 @@
+#![cfg(feature = "bk2")]
+
+use {
+    nes_movie::{Bk2, Buttons, Commands, Fm2},
+    std::io::Cursor,
+};
+
+const MOVIE: &str = "\
+version 3
+rerecordCount 7
+romFilename Tetris
+comment author somebody
+subtitle 1 Hello
+|2|........|........||
+|0|.......A|......B.||
+";
+
+#[test]
+fn archive_round_trip() {
+    let movie = Bk2::from_fm2(&Fm2::parse(MOVIE).unwrap());
+    assert_eq!(movie.header_value("GameName"), Some("Tetris"));
+    assert_eq!(movie.header_value("Author"), Some("somebody"));
+
+    let mut archive = Cursor::new(Vec::new());
+    movie.write(&mut archive).unwrap();
+    archive.set_position(0);
+
+    let read = Bk2::from_reader(archive).unwrap();
+    assert_eq!(read, movie);
+    assert!(read.frames[0].commands.contains(Commands::HARD_RESET));
+    assert_eq!(read.frames[1].buttons[..2], [Buttons::A, Buttons::B]);
+}
+
+#[test]
+fn fm2_conversion() {
+    let fm2 = Fm2::parse(MOVIE).unwrap();
+    let converted = Bk2::from_fm2(&fm2).to_fm2();
+
+    assert_eq!(converted.header.rom_filename, fm2.header.rom_filename);
+    assert_eq!(converted.header.rerecord_count, 7);
+    assert_eq!(converted.header.comments, fm2.header.comments);
+    assert_eq!(converted.header.subtitles, fm2.header.subtitles);
+    assert_eq!(converted.frames, fm2.frames);
+}
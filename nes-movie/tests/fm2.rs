@@ -0,0 +1,84 @@
+use nes_movie::{fm2::Device, Buttons, Commands, Error, Fm2};
+
+const MOVIE: &str = "\
+version 3
+emuVersion 22020
+rerecordCount 42
+palFlag 0
+romFilename Super Mario Bros.
+romChecksum base64:jjYwGG411HcjG/j9UOVM3Q==
+guid 452DE2C3-EF43-2FA9-77AC-0677FC51543B
+fourscore 0
+microphone 0
+port0 1
+port1 0
+port2 0
+FDS 0
+NewPPU 0
+comment author somebody
+subtitle 2 Jump!
+|1|........|||
+|0|.......A|||
+|0|R..U.S.A|||
+";
+
+#[test]
+fn parse() {
+    let movie = Fm2::parse(MOVIE).unwrap();
+    let header = &movie.header;
+
+    assert_eq!(header.version, 3);
+    assert_eq!(header.rerecord_count, 42);
+    assert_eq!(header.rom_filename, "Super Mario Bros.");
+    assert_eq!(header.ports, [Device::Gamepad, Device::None]);
+    assert_eq!(header.comments, ["author somebody"]);
+    assert_eq!(header.subtitles[0].frame, 2);
+    assert_eq!(header.subtitles[0].text, "Jump!");
+
+    assert_eq!(movie.frames.len(), 3);
+    assert!(movie.frames[0].commands.contains(Commands::SOFT_RESET));
+    assert_eq!(movie.frames[1].buttons[0], Buttons::A);
+    assert_eq!(
+        movie.frames[2].buttons[0],
+        Buttons::RIGHT | Buttons::UP | Buttons::SELECT | Buttons::A
+    );
+}
+
+#[test]
+fn round_trip() {
+    let movie = Fm2::parse(MOVIE).unwrap();
+
+    assert_eq!(movie.to_string(), MOVIE);
+    assert_eq!(Fm2::parse(&movie.to_string()).unwrap(), movie);
+}
+
+#[test]
+fn fourscore() {
+    let movie =
+        Fm2::parse("version 3\nfourscore 1\n|0|.......A|......B.|.....S..|....T...||\n").unwrap();
+
+    assert_eq!(
+        movie.frames[0].buttons,
+        [Buttons::A, Buttons::B, Buttons::SELECT, Buttons::START]
+    );
+}
+
+#[test]
+fn reject_invalid_movies() {
+    assert!(matches!(
+        Fm2::parse("emuVersion 22020\n"),
+        Err(Error::MissingVersion)
+    ));
+    assert!(matches!(
+        Fm2::parse("version 3\nbinary 1\n"),
+        Err(Error::BinaryInput)
+    ));
+    assert!(matches!(
+        Fm2::parse("version 3\nport0 2\n"),
+        Err(Error::UnsupportedDevice(2))
+    ));
+    assert!(matches!(
+        Fm2::parse("version 3\n|0|.......|||\n"),
+        Err(Error::InvalidInput(2))
+    ));
+}
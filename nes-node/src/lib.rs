@@ -0,0 +1,85 @@
+#![warn(clippy::all, clippy::pedantic)]
+#![allow(clippy::missing_errors_doc)]
+
+//!
+//! Node.js bindings to the parsers of this workspace via `napi-rs`, for tooling like Electron-based ROM managers
+//!
+//! The functions mirror the ones of `nes-wasm` and take `Buffer`s, the `*File` variants read the files themselves.
+//! Palettes are passed as 12 bytes (the RGB values of the background colour followed by the three palette colours).
+//! Invalid input is reported by throwing an `Error`.
+//!
+
+use {
+    napi::{bindgen_prelude::Buffer, Error, Result},
+    napi_derive::napi,
+    std::{fmt::Display, fs, path::Path},
+};
+
+mod rom;
+mod sprite;
+
+pub use {
+    rom::{
+        chr_rom, chr_rom_file, parse_header, parse_header_file, prg_rom, Header, Timing, VramLayout,
+    },
+    sprite::{decode_sprite, nes_palette, num_sprites, render_sheet, sprite_to_rgba},
+};
+
+// Magic bytes of the formats, which all end in the MS-DOS EOF delimiter except for NSFe
+const INES_MAGIC: &[u8] = b"NES\x1A";
+const NSF_MAGIC: &[u8] = b"NESM\x1A";
+const NSFE_MAGIC: &[u8] = b"NSFE";
+
+/// Formats `detectFormat` recognises
+#[napi]
+#[derive(Debug, PartialEq, Eq)]
+pub enum Format {
+    /// INES and NES 2.0 ROMs
+    Ines,
+    /// Disk images of the Famicom Disk System, with or without a fwNES header
+    Fds,
+    /// NSF and NSF2 files
+    Nsf,
+    Nsfe,
+}
+
+fn format(data: &[u8]) -> Option<Format> {
+    if data.starts_with(INES_MAGIC) {
+        Some(Format::Ines)
+    } else if data.starts_with(NSF_MAGIC) {
+        Some(Format::Nsf)
+    } else if data.starts_with(NSFE_MAGIC) {
+        Some(Format::Nsfe)
+    } else if fds_parser::has_header(data)
+        || (!data.is_empty() && data.len().is_multiple_of(fds_parser::SIDE_SIZE))
+    {
+        Some(Format::Fds)
+    } else {
+        None
+    }
+}
+
+/// Format of the file by its magic bytes, `undefined` if it isn't one of the supported formats
+///
+/// FDS images without a header are recognised by their size, which is a multiple of 65500 bytes
+#[napi]
+#[must_use]
+#[allow(clippy::needless_pass_by_value)]
+pub fn detect_format(data: Buffer) -> Option<Format> {
+    format(&data)
+}
+
+/// `detectFormat` for a file
+#[napi]
+#[allow(clippy::needless_pass_by_value)]
+pub fn detect_file_format(path: String) -> Result<Option<Format>> {
+    Ok(format(&read(&path)?))
+}
+
+fn error(err: impl Display) -> Error {
+    Error::from_reason(err.to_string())
+}
+
+fn read(path: &str) -> Result<Vec<u8>> {
+    fs::read(Path::new(path)).map_err(|err| error(format_args!("Failed to read {path}: {err}")))
+}
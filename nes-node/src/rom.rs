@@ -0,0 +1,150 @@
+use {
+    crate::{error, read},
+    ines_parser::Ines,
+    napi::{bindgen_prelude::Buffer, Error, Result},
+    napi_derive::napi,
+    std::{convert::TryFrom, fs::File, io::Read},
+};
+
+// The header is all `parseHeaderFile` reads of the file
+const HEADER_SIZE: usize = 16;
+
+/// Nametable layout the header asks for
+#[napi]
+#[derive(Debug, PartialEq, Eq)]
+pub enum VramLayout {
+    HorizontalMirroring,
+    VerticalMirroring,
+    FourScreen,
+}
+
+/// CPU/PPU timing of the console a game is made for
+#[napi]
+#[derive(Debug, PartialEq, Eq)]
+pub enum Timing {
+    Ntsc,
+    Pal,
+    MultiRegion,
+    Dendy,
+}
+
+/// Parsed header of an INES or NES 2.0 ROM
+#[napi(object)]
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug)]
+pub struct Header {
+    pub prg_rom_size: u32,
+    pub chr_rom_size: u32,
+    /// Size of the volatile PRG RAM
+    pub prg_ram_size: u32,
+    /// Size of the battery-backed PRG RAM
+    pub prg_nvram_size: u32,
+    /// Size of the volatile CHR RAM
+    pub chr_ram_size: u32,
+    /// Size of the battery-backed CHR RAM
+    pub chr_nvram_size: u32,
+    pub vram_layout: VramLayout,
+    /// INES 1 headers only tell NTSC and PAL apart
+    pub timing: Timing,
+    pub mapper: u8,
+    /// Submapper number (NES 2.0 only)
+    pub submapper: u8,
+    /// Input device the game expects, numbered like on the nesdev wiki (NES 2.0 only)
+    pub default_expansion_device: u8,
+    pub is_nes2: bool,
+    pub has_battery: bool,
+    pub has_trainer: bool,
+}
+
+impl TryFrom<&ines_parser::Header> for Header {
+    type Error = Error;
+
+    fn try_from(header: &ines_parser::Header) -> Result<Self> {
+        // NES 2.0 headers can describe sizes JavaScript numbers can't hold exactly, no real ROM is that big
+        let size = |size: usize| {
+            u32::try_from(size).map_err(|_| error(format_args!("{size} bytes is too big")))
+        };
+
+        Ok(Self {
+            prg_rom_size: size(header.prg_rom_size)?,
+            chr_rom_size: size(header.chr_rom_size)?,
+            prg_ram_size: size(header.prg_ram_size)?,
+            prg_nvram_size: size(header.prg_nvram_size)?,
+            chr_ram_size: size(header.chr_ram_size)?,
+            chr_nvram_size: size(header.chr_nvram_size)?,
+            vram_layout: match header.vram_layout {
+                ines_parser::VramLayout::HorizontalMirroring => VramLayout::HorizontalMirroring,
+                ines_parser::VramLayout::VerticalMirroring => VramLayout::VerticalMirroring,
+                ines_parser::VramLayout::FourScreen => VramLayout::FourScreen,
+            },
+            timing: match header.timing {
+                ines_parser::Timing::Ntsc => Timing::Ntsc,
+                ines_parser::Timing::Pal => Timing::Pal,
+                ines_parser::Timing::MultiRegion => Timing::MultiRegion,
+                ines_parser::Timing::Dendy => Timing::Dendy,
+            },
+            mapper: header.mapper_number,
+            submapper: header.submapper,
+            default_expansion_device: header.default_expansion_device,
+            is_nes2: header.is_nes2,
+            has_battery: header.has_persistent_memory,
+            has_trainer: header.has_trainer(),
+        })
+    }
+}
+
+fn header(data: &[u8]) -> Result<Header> {
+    Header::try_from(&ines_parser::Header::from_bytes(data).map_err(error)?)
+}
+
+fn parse_rom(data: &[u8]) -> Result<Ines<'_>> {
+    Ines::from_bytes(data).map_err(error)
+}
+
+fn chr(data: &[u8]) -> Result<Option<Buffer>> {
+    Ok(parse_rom(data)?
+        .chr_rom
+        .map(|chr_rom| chr_rom.to_vec().into()))
+}
+
+/// Parse the header of a ROM, which only needs the first 16 bytes of the file
+#[napi]
+#[allow(clippy::needless_pass_by_value)]
+pub fn parse_header(data: Buffer) -> Result<Header> {
+    header(&data)
+}
+
+/// `parseHeader` for a file, only reading its first 16 bytes
+#[napi]
+#[allow(clippy::needless_pass_by_value)]
+pub fn parse_header_file(path: String) -> Result<Header> {
+    let mut data = Vec::with_capacity(HEADER_SIZE);
+    File::open(&path)
+        .and_then(|file| file.take(HEADER_SIZE as u64).read_to_end(&mut data))
+        .map_err(|err| error(format_args!("Failed to read {path}: {err}")))?;
+
+    header(&data)
+}
+
+/// Copy the PRG ROM out of a ROM
+#[napi]
+#[allow(clippy::needless_pass_by_value)]
+pub fn prg_rom(data: Buffer) -> Result<Buffer> {
+    Ok(parse_rom(&data)?.prg_rom.to_vec().into())
+}
+
+/// Copy the CHR ROM out of a ROM, ready to be passed to the sprite functions
+///
+/// Returns `undefined` for games using CHR RAM, whose tiles are stored in the PRG ROM
+#[napi]
+#[allow(clippy::needless_pass_by_value)]
+pub fn chr_rom(data: Buffer) -> Result<Option<Buffer>> {
+    chr(&data)
+}
+
+/// `chrRom` for a file
+#[napi]
+#[allow(clippy::needless_pass_by_value)]
+pub fn chr_rom_file(path: String) -> Result<Option<Buffer>> {
+    chr(&read(&path)?)
+}
@@ -0,0 +1,107 @@
+use {
+    crate::error,
+    lemonade::{
+        Colour, ColourPalette, Lemonade, PixelFormat, Sprite, SPRITE_SIZE, SPRITE_WIDTH_HEIGHT,
+    },
+    napi::{bindgen_prelude::Buffer, Result},
+    napi_derive::napi,
+    std::convert::TryFrom,
+};
+
+fn parse_palette(palette: &[u8]) -> Result<ColourPalette> {
+    if palette.len() != 12 {
+        return Err(error("The palette has to consist of exactly 12 bytes"));
+    }
+
+    let colour = |index: usize| {
+        Colour::new(
+            palette[index * 3],
+            palette[index * 3 + 1],
+            palette[index * 3 + 2],
+        )
+    };
+
+    Ok(ColourPalette::new(
+        colour(0),
+        [colour(1), colour(2), colour(3)],
+    ))
+}
+
+fn parse_sprite(data: &[u8]) -> Result<Sprite<'_>> {
+    Lemonade::new(data)
+        .next()
+        .filter(|_| data.len() == SPRITE_SIZE)
+        .ok_or_else(|| error("A sprite has to consist of exactly 16 bytes"))
+}
+
+/// Turn four indices into the NES master palette into the 12 byte palette the sprite functions take
+///
+/// Only the lower six bits of the indices are taken into account
+#[napi]
+#[allow(clippy::needless_pass_by_value)]
+pub fn nes_palette(indices: Buffer) -> Result<Buffer> {
+    if indices.len() != 4 {
+        return Err(error(
+            "The palette has to consist of exactly 4 colour indices",
+        ));
+    }
+
+    Ok(indices
+        .iter()
+        .flat_map(|&index| lemonade::nes_colour(index).raw_colour())
+        .collect::<Vec<_>>()
+        .into())
+}
+
+/// Decode a sprite into its 64 palette indices
+#[napi]
+#[allow(clippy::needless_pass_by_value)]
+pub fn decode_sprite(data: Buffer) -> Result<Buffer> {
+    let indices = parse_sprite(&data)?.to_indices();
+
+    Ok(indices.iter().flatten().copied().collect::<Vec<_>>().into())
+}
+
+/// Decode a sprite and apply the palette, returning 8x8 RGBA pixels
+#[napi]
+#[allow(clippy::needless_pass_by_value)]
+pub fn sprite_to_rgba(data: Buffer, palette: Buffer) -> Result<Buffer> {
+    let mut buffer = vec![0; SPRITE_WIDTH_HEIGHT * SPRITE_WIDTH_HEIGHT * 4];
+    parse_sprite(&data)?
+        .decode_into(&mut buffer, parse_palette(&palette)?, PixelFormat::Rgba8)
+        .map_err(error)?;
+
+    Ok(buffer.into())
+}
+
+/// Count the sprites contained in the CHR data
+#[napi]
+#[must_use]
+#[allow(clippy::needless_pass_by_value)]
+pub fn num_sprites(chr_data: Buffer) -> u32 {
+    u32::try_from(Lemonade::new(&chr_data).num_sprites()).unwrap_or(u32::MAX)
+}
+
+/// Render all sprites of the CHR data into one RGBA sheet which is `sprites_per_row` sprites wide
+///
+/// The height of the sheet is `ceil(numSprites / spritesPerRow) * 8` pixels
+#[napi]
+#[allow(clippy::needless_pass_by_value)]
+pub fn render_sheet(chr_data: Buffer, palette: Buffer, sprites_per_row: u32) -> Result<Buffer> {
+    let sprites = Lemonade::new(&chr_data);
+    let sprites_per_row = (sprites_per_row as usize).max(1);
+    let rows = sprites.num_sprites().div_ceil(sprites_per_row);
+
+    let pixel_count = rows * sprites_per_row * SPRITE_WIDTH_HEIGHT * SPRITE_WIDTH_HEIGHT;
+    let mut buffer = vec![0; pixel_count * PixelFormat::Rgba8.bytes_per_pixel()];
+    sprites
+        .decode_all_into(
+            &mut buffer,
+            parse_palette(&palette)?,
+            PixelFormat::Rgba8,
+            sprites_per_row,
+        )
+        .map_err(error)?;
+
+    Ok(buffer.into())
+}
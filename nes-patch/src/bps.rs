@@ -0,0 +1,440 @@
+//!
+//! Beat patching system
+//!
+//! [Format documentation](https://github.com/blakesmith/rombp/blob/master/docs/bps_spec.md)
+//!
+
+use {
+    crate::{
+        checksum::crc32,
+        reader::{split_footer, write_footer, write_varint, Reader},
+        Error,
+    },
+    alloc::{vec, vec::Vec},
+    core::convert::TryFrom,
+};
+
+const MAGIC: &[u8] = b"BPS1";
+
+const HASH_TABLE_SIZE: usize = 1 << 16;
+
+// Shorter matches cost more to encode than the literal bytes
+const MIN_MATCH_LENGTH: usize = 4;
+
+/// Action producing the next bytes of the target
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    /// Copy the bytes at the same offset of the source
+    SourceRead { length: usize },
+    /// Bytes stored in the patch
+    TargetRead(Vec<u8>),
+    /// Copy bytes of the source; the offset is relative to the end of the last source copy
+    SourceCopy { length: usize, offset: i64 },
+    /// Copy already written bytes of the target; the offset is relative to the end of the last target copy
+    TargetCopy { length: usize, offset: i64 },
+}
+
+impl Action {
+    /// Amount of bytes the action writes to the target
+    #[must_use]
+    pub fn length(&self) -> usize {
+        match self {
+            Self::SourceRead { length }
+            | Self::SourceCopy { length, .. }
+            | Self::TargetCopy { length, .. } => *length,
+            Self::TargetRead(bytes) => bytes.len(),
+        }
+    }
+}
+
+/// BPS patch
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Bps {
+    pub source_size: usize,
+    pub target_size: usize,
+    /// Usually XML, but the format doesn't enforce any encoding
+    pub metadata: Vec<u8>,
+    pub actions: Vec<Action>,
+    pub source_checksum: u32,
+    pub target_checksum: u32,
+}
+
+fn read_offset(reader: &mut Reader<'_>) -> Result<i64, Error> {
+    let number = reader.varint()?;
+    let magnitude = i64::try_from(number >> 1).map_err(|_| Error::InvalidPatch)?;
+
+    Ok(if number & 1 == 0 {
+        magnitude
+    } else {
+        -magnitude
+    })
+}
+
+fn write_command(patch: &mut Vec<u8>, kind: u64, length: usize) {
+    write_varint(patch, ((length as u64 - 1) << 2) | kind);
+}
+
+fn write_offset(patch: &mut Vec<u8>, offset: i64) {
+    write_varint(patch, (offset.unsigned_abs() << 1) | u64::from(offset < 0));
+}
+
+fn common_length(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(a, b)| a == b).count()
+}
+
+fn hash(bytes: &[u8]) -> usize {
+    let word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    (word.wrapping_mul(0x9E37_79B1) >> 16) as usize
+}
+
+// Relative offset between two positions of a file
+#[allow(clippy::cast_possible_wrap)]
+fn relative_offset(from: usize, to: usize) -> i64 {
+    // Files can't be larger than `isize::MAX`
+    to as i64 - from as i64
+}
+
+fn apply_offset(position: usize, offset: i64) -> Result<usize, Error> {
+    let offset = isize::try_from(offset).map_err(|_| Error::InvalidPatch)?;
+    position
+        .checked_add_signed(offset)
+        .ok_or(Error::InvalidPatch)
+}
+
+impl Bps {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a BPS patch, validating the checksum of the patch
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the magic bytes are missing, the patch is malformed or its checksum doesn't match
+    pub fn parse(patch: &[u8]) -> Result<Self, Error> {
+        let (mut reader, footer) = split_footer(patch, MAGIC)?;
+        let source_size = reader.size()?;
+        let target_size = reader.size()?;
+        let metadata_size = reader.size()?;
+        let metadata = reader.take(metadata_size)?.to_vec();
+
+        let mut actions = Vec::new();
+        while !reader.data.is_empty() {
+            let command = reader.varint()?;
+            let length = usize::try_from((command >> 2) + 1).map_err(|_| Error::InvalidPatch)?;
+
+            actions.push(match command & 3 {
+                0 => Action::SourceRead { length },
+                1 => Action::TargetRead(reader.take(length)?.to_vec()),
+                2 => Action::SourceCopy {
+                    length,
+                    offset: read_offset(&mut reader)?,
+                },
+                _ => Action::TargetCopy {
+                    length,
+                    offset: read_offset(&mut reader)?,
+                },
+            });
+        }
+
+        Ok(Self {
+            source_size,
+            target_size,
+            metadata,
+            actions,
+            source_checksum: footer.source_checksum,
+            target_checksum: footer.target_checksum,
+        })
+    }
+
+    /// Serialize the patch, including its checksum
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut patch = MAGIC.to_vec();
+        write_varint(&mut patch, self.source_size as u64);
+        write_varint(&mut patch, self.target_size as u64);
+        write_varint(&mut patch, self.metadata.len() as u64);
+        patch.extend_from_slice(&self.metadata);
+
+        for action in &self.actions {
+            match action {
+                Action::SourceRead { length } => write_command(&mut patch, 0, *length),
+                Action::TargetRead(bytes) => {
+                    write_command(&mut patch, 1, bytes.len());
+                    patch.extend_from_slice(bytes);
+                }
+                Action::SourceCopy { length, offset } => {
+                    write_command(&mut patch, 2, *length);
+                    write_offset(&mut patch, *offset);
+                }
+                Action::TargetCopy { length, offset } => {
+                    write_command(&mut patch, 3, *length);
+                    write_offset(&mut patch, *offset);
+                }
+            }
+        }
+
+        write_footer(&mut patch, self.source_checksum, self.target_checksum);
+
+        patch
+    }
+
+    /// Check whether the patch was made for the source, by its size and checksum
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::SizeMismatch`] or [`Error::SourceChecksumMismatch`] if the patch was made for another file
+    pub fn validate_source(&self, source: &[u8]) -> Result<(), Error> {
+        if source.len() != self.source_size {
+            return Err(Error::SizeMismatch {
+                expected: self.source_size,
+                actual: source.len(),
+            });
+        }
+
+        let actual = crc32(source);
+        if actual != self.source_checksum {
+            return Err(Error::SourceChecksumMismatch {
+                expected: self.source_checksum,
+                actual,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Apply the patch to the source, after validating that it was made for it
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the patch wasn't made for the source, its actions are out of bounds or don't add up to
+    /// the target size, or the checksum of the result doesn't match
+    pub fn apply(&self, source: &[u8]) -> Result<Vec<u8>, Error> {
+        self.validate_source(source)?;
+
+        // The target size is only trusted once the actions add up to it
+        let produced = self
+            .actions
+            .iter()
+            .try_fold(0_usize, |produced, action| {
+                produced.checked_add(action.length())
+            })
+            .ok_or(Error::InvalidPatch)?;
+        if produced != self.target_size {
+            return Err(Error::SizeMismatch {
+                expected: self.target_size,
+                actual: produced,
+            });
+        }
+
+        let mut target = Vec::new();
+        target
+            .try_reserve_exact(self.target_size)
+            .map_err(|_| Error::TooLarge(self.target_size))?;
+        target.resize(self.target_size, 0);
+        let mut output = 0_usize;
+        let mut source_offset = 0;
+        let mut target_offset = 0;
+
+        for action in &self.actions {
+            let length = action.length();
+            let end = output
+                .checked_add(length)
+                .filter(|end| *end <= target.len())
+                .ok_or(Error::InvalidPatch)?;
+
+            match action {
+                Action::SourceRead { .. } => {
+                    let bytes = source.get(output..end).ok_or(Error::InvalidPatch)?;
+                    target[output..end].copy_from_slice(bytes);
+                }
+                Action::TargetRead(bytes) => target[output..end].copy_from_slice(bytes),
+                Action::SourceCopy { offset, .. } => {
+                    source_offset = apply_offset(source_offset, *offset)?;
+                    let bytes = source_offset
+                        .checked_add(length)
+                        .and_then(|end| source.get(source_offset..end))
+                        .ok_or(Error::InvalidPatch)?;
+                    target[output..end].copy_from_slice(bytes);
+                    source_offset += length;
+                }
+                Action::TargetCopy { offset, .. } => {
+                    target_offset = apply_offset(target_offset, *offset)?;
+                    if target_offset >= output {
+                        return Err(Error::InvalidPatch);
+                    }
+
+                    // The copy can overlap with the bytes it writes, so it has to go byte by byte
+                    for index in output..end {
+                        target[index] = target[target_offset];
+                        target_offset += 1;
+                    }
+                }
+            }
+
+            output = end;
+        }
+
+        let actual = crc32(&target);
+        if actual != self.target_checksum {
+            return Err(Error::TargetChecksumMismatch {
+                expected: self.target_checksum,
+                actual,
+            });
+        }
+
+        Ok(target)
+    }
+
+    /// Create a patch turning the source into the target
+    ///
+    /// Unchanged bytes are read from the source, moved data is copied from the source or the already written target
+    /// and everything else is stored in the patch
+    #[must_use]
+    pub fn create(source: &[u8], target: &[u8], metadata: &[u8]) -> Self {
+        let mut source_table = vec![None; HASH_TABLE_SIZE];
+        if source.len() >= MIN_MATCH_LENGTH {
+            // Iterating backwards keeps the first occurrence of every sequence
+            for position in (0..=source.len() - MIN_MATCH_LENGTH).rev() {
+                source_table[hash(&source[position..])] = Some(position);
+            }
+        }
+        let mut target_table = vec![None; HASH_TABLE_SIZE];
+
+        let mut actions = Vec::new();
+        let mut literal_start = 0;
+        let mut output = 0;
+        let mut source_offset = 0;
+        let mut target_offset = 0;
+
+        while output < target.len() {
+            let remaining = &target[output..];
+            let source_read = common_length(source.get(output..).unwrap_or_default(), remaining);
+
+            let (mut source_copy, mut target_copy) = ((0, 0), (0, 0));
+            if remaining.len() >= MIN_MATCH_LENGTH {
+                let key = hash(remaining);
+                if let Some(position) = source_table[key] {
+                    source_copy = (position, common_length(&source[position..], remaining));
+                }
+                if let Some(position) = target_table[key] {
+                    target_copy = (position, common_length(&target[position..], remaining));
+                }
+            }
+
+            let longest = source_read.max(source_copy.1).max(target_copy.1);
+            if longest < MIN_MATCH_LENGTH {
+                if remaining.len() >= MIN_MATCH_LENGTH {
+                    target_table[hash(remaining)] = Some(output);
+                }
+                output += 1;
+                continue;
+            }
+
+            if literal_start < output {
+                actions.push(Action::TargetRead(target[literal_start..output].to_vec()));
+            }
+
+            let action = if longest == source_read {
+                Action::SourceRead { length: longest }
+            } else if longest == source_copy.1 {
+                let (position, length) = source_copy;
+                let offset = relative_offset(source_offset, position);
+                source_offset = position + length;
+                Action::SourceCopy { length, offset }
+            } else {
+                let (position, length) = target_copy;
+                let offset = relative_offset(target_offset, position);
+                target_offset = position + length;
+                Action::TargetCopy { length, offset }
+            };
+            actions.push(action);
+
+            for position in output..output + longest {
+                if target.len() - position >= MIN_MATCH_LENGTH {
+                    target_table[hash(&target[position..])] = Some(position);
+                }
+            }
+            output += longest;
+            literal_start = output;
+        }
+
+        if literal_start < target.len() {
+            actions.push(Action::TargetRead(target[literal_start..].to_vec()));
+        }
+
+        Self {
+            source_size: source.len(),
+            target_size: target.len(),
+            metadata: metadata.to_vec(),
+            actions,
+            source_checksum: crc32(source),
+            target_checksum: crc32(target),
+        }
+    }
+}
+
+/// Apply a BPS patch to the source
+///
+/// # Errors
+///
+/// Returns an error if the patch is malformed, wasn't made for the source or produces the wrong target
+pub fn apply(source: &[u8], patch: &[u8]) -> Result<Vec<u8>, Error> {
+    Bps::parse(patch)?.apply(source)
+}
+
+/// Create a BPS patch turning the source into the target
+#[must_use]
+pub fn create(source: &[u8], target: &[u8]) -> Vec<u8> {
+    Bps::create(source, target, &[]).to_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let source = b"The quick brown fox jumps over the lazy dog";
+        let target = b"The quick brown cat jumps over the lazy dog, the lazy dog, the lazy dog";
+
+        let patch = create(source, target);
+        assert_eq!(apply(source, &patch).unwrap(), target);
+        assert_eq!(Bps::parse(&patch).unwrap().to_bytes(), patch);
+    }
+
+    #[test]
+    fn reject_target_size_without_actions() {
+        let patch = Bps {
+            target_size: 1 << 56,
+            ..Bps::new()
+        }
+        .to_bytes();
+
+        assert!(matches!(
+            apply(&[], &patch),
+            Err(Error::SizeMismatch {
+                expected: 0x0100_0000_0000_0000,
+                actual: 0,
+            })
+        ));
+    }
+
+    #[test]
+    fn reject_unallocatable_target() {
+        let patch = Bps {
+            target_size: 1 << 56,
+            actions: vec![
+                Action::TargetRead(vec![0]),
+                Action::TargetCopy {
+                    length: (1 << 56) - 1,
+                    offset: 0,
+                },
+            ],
+            ..Bps::new()
+        };
+
+        assert!(matches!(patch.apply(&[]), Err(Error::TooLarge(_))));
+    }
+}
@@ -0,0 +1,48 @@
+// Checksums used by the patch formats
+
+// CRC-32 (ISO-HDLC), also used by most ROM databases
+const POLYNOMIAL: u32 = 0xEDB8_8320;
+
+const TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+
+    let mut index = 0;
+    while index < 256 {
+        // The index is the byte, which always fits
+        #[allow(clippy::cast_possible_truncation)]
+        let mut crc = index as u32;
+
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 0 {
+                crc >> 1
+            } else {
+                (crc >> 1) ^ POLYNOMIAL
+            };
+            bit += 1;
+        }
+
+        table[index] = crc;
+        index += 1;
+    }
+
+    table
+};
+
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0, |crc, byte| {
+        TABLE[((crc ^ u32::from(*byte)) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+// Adler-32 as used by xdelta for the windows of VCDIFF patches
+const ADLER_MODULUS: u32 = 65521;
+
+pub fn adler32(data: &[u8]) -> u32 {
+    let (a, b) = data.iter().fold((1, 0), |(a, b), byte| {
+        let a = (a + u32::from(*byte)) % ADLER_MODULUS;
+        (a, (b + a) % ADLER_MODULUS)
+    });
+
+    (b << 16) | a
+}
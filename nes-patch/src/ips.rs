@@ -0,0 +1,306 @@
+//!
+//! International Patching System
+//!
+//! [Format documentation](http://www.zerosoft.zophar.net/ips.php)
+//!
+
+use {
+    crate::{reader::Reader, Error},
+    alloc::vec::Vec,
+    core::convert::TryFrom,
+};
+
+const MAGIC: &[u8] = b"PATCH";
+const EOF_MARKER: &[u8] = b"EOF";
+
+// A record at this offset would be mistaken for the end of the patch
+const EOF_OFFSET: usize = 0x45_4F46;
+
+const MAX_OFFSET: usize = 0xFF_FFFF;
+const MAX_RECORD_SIZE: usize = 0xFFFF;
+
+// Size of the offset and size fields, which makes merging shorter unchanged gaps into a record cheaper
+const RECORD_HEADER_SIZE: usize = 5;
+
+// Runs of equal bytes from this length on are smaller as RLE records
+const MIN_RLE_LENGTH: usize = 9;
+
+const INES_MAGIC: &[u8] = b"NES\x1A";
+const INES_HEADER_SIZE: usize = 16;
+
+/// Data written by a record
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RecordData {
+    Bytes(Vec<u8>),
+    /// The value repeated `count` times
+    Rle {
+        count: u16,
+        value: u8,
+    },
+}
+
+/// Record writing data at an offset into the file
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Record {
+    /// 24-bit offset from the start of the file
+    pub offset: u32,
+    pub data: RecordData,
+}
+
+/// IPS patch
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Ips {
+    pub records: Vec<Record>,
+    /// Size the file gets truncated to after applying the records (non-standard extension)
+    pub truncate: Option<u32>,
+}
+
+impl Ips {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse an IPS patch
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the magic bytes are missing or the patch ends in the middle of a record
+    pub fn parse(patch: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader { data: patch };
+        if reader
+            .take(MAGIC.len())
+            .map_err(|_| Error::MagicBytesMismatch)?
+            != MAGIC
+        {
+            return Err(Error::MagicBytesMismatch);
+        }
+
+        let mut records = Vec::new();
+        loop {
+            let offset = reader.take(3)?;
+            if offset == EOF_MARKER {
+                break;
+            }
+            let offset = Reader { data: offset }.number(3)?;
+
+            // The 16-bit numbers always fit
+            #[allow(clippy::cast_possible_truncation)]
+            let data = match reader.number(2)? {
+                0 => RecordData::Rle {
+                    count: reader.number(2)? as u16,
+                    value: reader.take(1)?[0],
+                },
+                size => RecordData::Bytes(reader.take(size as usize)?.to_vec()),
+            };
+
+            records.push(Record { offset, data });
+        }
+
+        // Anything but the three bytes of the extension is ignored
+        let truncate = reader.number(3).ok();
+
+        Ok(Self { records, truncate })
+    }
+
+    /// Serialize the patch
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut patch = MAGIC.to_vec();
+
+        for record in &self.records {
+            patch.extend_from_slice(&record.offset.to_be_bytes()[1..]);
+            match &record.data {
+                RecordData::Bytes(bytes) => {
+                    // Records are created with at most `MAX_RECORD_SIZE` bytes
+                    #[allow(clippy::cast_possible_truncation)]
+                    patch.extend_from_slice(&(bytes.len() as u16).to_be_bytes());
+                    patch.extend_from_slice(bytes);
+                }
+                RecordData::Rle { count, value } => {
+                    patch.extend_from_slice(&[0, 0]);
+                    patch.extend_from_slice(&count.to_be_bytes());
+                    patch.push(*value);
+                }
+            }
+        }
+
+        patch.extend_from_slice(EOF_MARKER);
+        if let Some(truncate) = self.truncate {
+            patch.extend_from_slice(&truncate.to_be_bytes()[1..]);
+        }
+
+        patch
+    }
+
+    /// Apply the patch to the file; records past its end extend it
+    pub fn apply(&self, file: &mut Vec<u8>) {
+        self.apply_at(file, 0);
+    }
+
+    /// Apply a patch made for a headerless dump to a file which may have an INES header
+    pub fn apply_headerless(&self, file: &mut Vec<u8>) {
+        let base = if file.starts_with(INES_MAGIC) {
+            INES_HEADER_SIZE
+        } else {
+            0
+        };
+
+        self.apply_at(file, base);
+    }
+
+    /// Apply the patch with all offsets shifted by `base` bytes
+    pub fn apply_at(&self, file: &mut Vec<u8>, base: usize) {
+        for record in &self.records {
+            let offset = base + record.offset as usize;
+            let length = match &record.data {
+                RecordData::Bytes(bytes) => bytes.len(),
+                RecordData::Rle { count, .. } => usize::from(*count),
+            };
+
+            if file.len() < offset + length {
+                file.resize(offset + length, 0);
+            }
+
+            let target = &mut file[offset..offset + length];
+            match &record.data {
+                RecordData::Bytes(bytes) => target.copy_from_slice(bytes),
+                RecordData::Rle { value, .. } => target.fill(*value),
+            }
+        }
+
+        if let Some(truncate) = self.truncate {
+            file.truncate(base + truncate as usize);
+        }
+    }
+
+    /// Create a patch turning the source into the target
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::TooLarge`] if the target is larger than the 16 MiB IPS can address
+    pub fn create(source: &[u8], target: &[u8]) -> Result<Self, Error> {
+        if target.len() > MAX_OFFSET + 1 {
+            return Err(Error::TooLarge(target.len()));
+        }
+
+        let differs = |offset: usize| source.get(offset) != Some(&target[offset]);
+
+        let mut records = Vec::new();
+        let mut offset = 0;
+        while offset < target.len() {
+            if !differs(offset) {
+                offset += 1;
+                continue;
+            }
+
+            // Moving the record one byte back keeps its offset from looking like the end marker
+            let start = if offset == EOF_OFFSET {
+                offset - 1
+            } else {
+                offset
+            };
+
+            // Short unchanged gaps are cheaper inside of the record than as a new record
+            let mut end = offset;
+            let mut unchanged = 0;
+            while end < target.len() && end - start < MAX_RECORD_SIZE {
+                if differs(end) {
+                    unchanged = 0;
+                } else {
+                    unchanged += 1;
+                }
+                end += 1;
+
+                if unchanged > RECORD_HEADER_SIZE {
+                    break;
+                }
+            }
+            end -= unchanged;
+
+            Self::push_records(&mut records, start, &target[start..end]);
+            offset = end;
+        }
+
+        let truncate = if target.len() < source.len() {
+            Some(u32::try_from(target.len()).map_err(|_| Error::TooLarge(target.len()))?)
+        } else {
+            None
+        };
+
+        Ok(Self { records, truncate })
+    }
+
+    // Split the changed bytes into plain and RLE records
+    #[allow(clippy::cast_possible_truncation)]
+    fn push_records(records: &mut Vec<Record>, start: usize, bytes: &[u8]) {
+        // Offsets and lengths were checked against the limits of the format by `create`
+        let mut push = |offset: usize, data: RecordData| {
+            records.push(Record {
+                offset: offset as u32,
+                data,
+            });
+        };
+
+        let mut plain_start = 0;
+        let mut index = 0;
+        while index < bytes.len() {
+            let run = bytes[index..]
+                .iter()
+                .take_while(|byte| **byte == bytes[index])
+                .count();
+            let rle_length = if start + index + run == EOF_OFFSET {
+                // The plain record after the run would start at the end marker
+                run - 1
+            } else {
+                run
+            };
+
+            // RLE records can't start at the end marker either
+            if rle_length >= MIN_RLE_LENGTH && start + index != EOF_OFFSET {
+                if plain_start < index {
+                    push(
+                        start + plain_start,
+                        RecordData::Bytes(bytes[plain_start..index].to_vec()),
+                    );
+                }
+                push(
+                    start + index,
+                    RecordData::Rle {
+                        count: rle_length as u16,
+                        value: bytes[index],
+                    },
+                );
+                plain_start = index + rle_length;
+            }
+
+            index += run;
+        }
+
+        if plain_start < bytes.len() {
+            push(
+                start + plain_start,
+                RecordData::Bytes(bytes[plain_start..].to_vec()),
+            );
+        }
+    }
+}
+
+/// Apply an IPS patch to the file
+///
+/// # Errors
+///
+/// Returns an error if the patch can't be parsed
+pub fn apply(file: &mut Vec<u8>, patch: &[u8]) -> Result<(), Error> {
+    Ips::parse(patch)?.apply(file);
+    Ok(())
+}
+
+/// Create an IPS patch turning the source into the target
+///
+/// # Errors
+///
+/// Returns [`Error::TooLarge`] if the target is larger than the 16 MiB IPS can address
+pub fn create(source: &[u8], target: &[u8]) -> Result<Vec<u8>, Error> {
+    Ips::create(source, target).map(|ips| ips.to_bytes())
+}
@@ -0,0 +1,71 @@
+#![no_std]
+#![warn(clippy::all, clippy::pedantic)]
+
+//!
+//! Applying and creating of ROM patches
+//!
+
+extern crate alloc;
+
+use core::fmt;
+
+pub mod bps;
+mod checksum;
+pub mod ips;
+mod reader;
+pub mod ups;
+pub mod vcdiff;
+
+pub use {bps::Bps, ips::Ips, ups::Ups};
+
+#[derive(Debug)]
+pub enum Error {
+    /// The patch doesn't start with the magic bytes of its format
+    MagicBytesMismatch,
+    /// The patch ended in the middle of a record
+    UnexpectedEof,
+    /// The file is too large to be addressed by the format
+    TooLarge(usize),
+    /// The patch contains invalid numbers or actions outside of the files
+    InvalidPatch,
+    /// The patch was made for a file of another size
+    SizeMismatch { expected: usize, actual: usize },
+    /// The patch was made for another file
+    SourceChecksumMismatch { expected: u32, actual: u32 },
+    /// Applying the patch didn't result in the expected file
+    TargetChecksumMismatch { expected: u32, actual: u32 },
+    /// The patch itself is corrupted
+    PatchChecksumMismatch { expected: u32, actual: u32 },
+    /// The patch uses a feature of its format which isn't implemented
+    UnsupportedFeature(&'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MagicBytesMismatch => f.write_str("Magic bytes mismatch"),
+            Self::UnexpectedEof => f.write_str("Unexpected end of the patch"),
+            Self::TooLarge(size) => write!(f, "Files with {size} bytes are too large"),
+            Self::InvalidPatch => f.write_str("Invalid patch"),
+            Self::SizeMismatch { expected, actual } => {
+                write!(
+                    f,
+                    "Expected a file with {expected} bytes, got {actual} bytes"
+                )
+            }
+            Self::SourceChecksumMismatch { expected, actual } => write!(
+                f,
+                "Source checksum mismatch (expected {expected:08X}, got {actual:08X})"
+            ),
+            Self::TargetChecksumMismatch { expected, actual } => write!(
+                f,
+                "Target checksum mismatch (expected {expected:08X}, got {actual:08X})"
+            ),
+            Self::PatchChecksumMismatch { expected, actual } => write!(
+                f,
+                "Patch checksum mismatch (expected {expected:08X}, got {actual:08X})"
+            ),
+            Self::UnsupportedFeature(feature) => write!(f, "Unsupported feature: {feature}"),
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use {
+    crate::{checksum::crc32, Error},
+    alloc::vec::Vec,
+    core::convert::TryFrom,
+};
+
+// Checksums of the source, the target and the patch itself at the end of BPS and UPS patches
+const FOOTER_SIZE: usize = 12;
+
+pub struct Reader<'a> {
+    pub data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn take(&mut self, length: usize) -> Result<&'a [u8], Error> {
+        if self.data.len() < length {
+            return Err(Error::UnexpectedEof);
+        }
+
+        let (taken, rest) = self.data.split_at(length);
+        self.data = rest;
+        Ok(taken)
+    }
+
+    /// Big-endian number with the given amount of bytes
+    pub fn number(&mut self, length: usize) -> Result<u32, Error> {
+        let bytes = self.take(length)?;
+        Ok(bytes
+            .iter()
+            .fold(0, |number, byte| (number << 8) | u32::from(*byte)))
+    }
+
+    /// Variable-length number storing seven bits per byte, with an implicit increment to avoid redundant encodings
+    pub fn varint(&mut self) -> Result<u64, Error> {
+        let mut number = 0_u64;
+        let mut shift = 1_u64;
+
+        loop {
+            let byte = self.take(1)?[0];
+            number = u64::from(byte & 0x7F)
+                .checked_mul(shift)
+                .and_then(|value| number.checked_add(value))
+                .ok_or(Error::InvalidPatch)?;
+
+            if byte & 0x80 != 0 {
+                return Ok(number);
+            }
+
+            shift = shift.checked_mul(0x80).ok_or(Error::InvalidPatch)?;
+            number = number.checked_add(shift).ok_or(Error::InvalidPatch)?;
+        }
+    }
+
+    pub fn size(&mut self) -> Result<usize, Error> {
+        usize::try_from(self.varint()?).map_err(|_| Error::InvalidPatch)
+    }
+}
+
+pub fn write_varint(patch: &mut Vec<u8>, mut number: u64) {
+    loop {
+        // Masked to seven bits
+        #[allow(clippy::cast_possible_truncation)]
+        let byte = (number & 0x7F) as u8;
+        number >>= 7;
+
+        if number == 0 {
+            patch.push(0x80 | byte);
+            break;
+        }
+
+        patch.push(byte);
+        number -= 1;
+    }
+}
+
+/// Checksums stored in the footer of BPS and UPS patches
+pub struct Footer {
+    pub source_checksum: u32,
+    pub target_checksum: u32,
+}
+
+/// Split the patch into the records after the magic bytes and the validated footer
+pub fn split_footer<'a>(patch: &'a [u8], magic: &[u8]) -> Result<(Reader<'a>, Footer), Error> {
+    if !patch.starts_with(magic) {
+        return Err(Error::MagicBytesMismatch);
+    }
+    if patch.len() < magic.len() + FOOTER_SIZE {
+        return Err(Error::UnexpectedEof);
+    }
+
+    let (body, footer) = patch.split_at(patch.len() - FOOTER_SIZE);
+    let checksum = |index: usize| {
+        let bytes = &footer[index * 4..index * 4 + 4];
+        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    };
+
+    let expected = checksum(2);
+    let actual = crc32(&patch[..patch.len() - 4]);
+    if expected != actual {
+        return Err(Error::PatchChecksumMismatch { expected, actual });
+    }
+
+    let reader = Reader {
+        data: &body[magic.len()..],
+    };
+    let footer = Footer {
+        source_checksum: checksum(0),
+        target_checksum: checksum(1),
+    };
+
+    Ok((reader, footer))
+}
+
+/// Append the checksums of the source and the target followed by the checksum of the whole patch
+pub fn write_footer(patch: &mut Vec<u8>, source_checksum: u32, target_checksum: u32) {
+    patch.extend_from_slice(&source_checksum.to_le_bytes());
+    patch.extend_from_slice(&target_checksum.to_le_bytes());
+    let checksum = crc32(patch);
+    patch.extend_from_slice(&checksum.to_le_bytes());
+}
@@ -0,0 +1,271 @@
+//!
+//! Universal patching system
+//!
+//! [Format documentation](https://www.romhacking.net/documents/392/)
+//!
+
+use {
+    crate::{
+        checksum::crc32,
+        reader::{split_footer, write_footer, write_varint},
+        Error,
+    },
+    alloc::vec::Vec,
+};
+
+const MAGIC: &[u8] = b"UPS1";
+
+/// Changed bytes, stored as the XOR of the source and the target
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Record {
+    /// Amount of unchanged bytes since the end of the last record
+    pub skip: usize,
+    /// Differences between the files; the record ends with the first zero
+    pub xor: Vec<u8>,
+}
+
+/// UPS patch
+///
+/// The records XOR the differences onto the file, so the same patch turns the target back into the source
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Ups {
+    pub source_size: usize,
+    pub target_size: usize,
+    pub records: Vec<Record>,
+    pub source_checksum: u32,
+    pub target_checksum: u32,
+}
+
+impl Ups {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a UPS patch, validating the checksum of the patch
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the magic bytes are missing, the patch is malformed or its checksum doesn't match
+    pub fn parse(patch: &[u8]) -> Result<Self, Error> {
+        let (mut reader, footer) = split_footer(patch, MAGIC)?;
+        let source_size = reader.size()?;
+        let target_size = reader.size()?;
+
+        let mut records = Vec::new();
+        while !reader.data.is_empty() {
+            let skip = reader.size()?;
+            let length = reader
+                .data
+                .iter()
+                .position(|byte| *byte == 0)
+                .ok_or(Error::UnexpectedEof)?;
+
+            let xor = reader.take(length)?.to_vec();
+            reader.take(1)?;
+
+            records.push(Record { skip, xor });
+        }
+
+        Ok(Self {
+            source_size,
+            target_size,
+            records,
+            source_checksum: footer.source_checksum,
+            target_checksum: footer.target_checksum,
+        })
+    }
+
+    /// Serialize the patch, including its checksum
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut patch = MAGIC.to_vec();
+        write_varint(&mut patch, self.source_size as u64);
+        write_varint(&mut patch, self.target_size as u64);
+
+        for record in &self.records {
+            write_varint(&mut patch, record.skip as u64);
+            patch.extend_from_slice(&record.xor);
+            patch.push(0);
+        }
+
+        write_footer(&mut patch, self.source_checksum, self.target_checksum);
+
+        patch
+    }
+
+    /// Apply the patch to the file
+    ///
+    /// Files matching the target of the patch get turned back into the source.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::SizeMismatch`] or [`Error::SourceChecksumMismatch`] if the patch wasn't made for the file,
+    /// [`Error::InvalidPatch`] if the records don't fit into the files
+    /// and [`Error::TargetChecksumMismatch`] if the result doesn't match the expected file
+    pub fn apply(&self, file: &[u8]) -> Result<Vec<u8>, Error> {
+        let checksum = crc32(file);
+        let (output_size, output_checksum) =
+            if file.len() == self.source_size && checksum == self.source_checksum {
+                (self.target_size, self.target_checksum)
+            } else if file.len() == self.target_size && checksum == self.target_checksum {
+                (self.source_size, self.source_checksum)
+            } else if file.len() == self.source_size {
+                return Err(Error::SourceChecksumMismatch {
+                    expected: self.source_checksum,
+                    actual: checksum,
+                });
+            } else {
+                return Err(Error::SizeMismatch {
+                    expected: self.source_size,
+                    actual: file.len(),
+                });
+            };
+
+        // The records have to fit into the larger file, which checks the declared sizes before allocating anything
+        let size = self.source_size.max(self.target_size);
+        let mut starts = Vec::with_capacity(self.records.len());
+        let mut offset = 0_usize;
+        for record in &self.records {
+            let start = offset.checked_add(record.skip).ok_or(Error::InvalidPatch)?;
+            let end = start
+                .checked_add(record.xor.len())
+                .filter(|end| *end <= size)
+                .ok_or(Error::InvalidPatch)?;
+            starts.push(start);
+
+            // The terminating zero covers one unchanged byte
+            offset = end.saturating_add(1);
+        }
+
+        // Bytes past the end of the input count as zero
+        let mut output = Vec::new();
+        output
+            .try_reserve_exact(output_size)
+            .map_err(|_| Error::TooLarge(output_size))?;
+        output.extend_from_slice(&file[..file.len().min(output_size)]);
+        output.resize(output_size, 0);
+
+        // Differences past the end of the output don't matter
+        for (record, start) in self.records.iter().zip(starts) {
+            if let Some(bytes) = output.get_mut(start..) {
+                for (byte, xor) in bytes.iter_mut().zip(&record.xor) {
+                    *byte ^= xor;
+                }
+            }
+        }
+
+        let actual = crc32(&output);
+        if actual != output_checksum {
+            return Err(Error::TargetChecksumMismatch {
+                expected: output_checksum,
+                actual,
+            });
+        }
+
+        Ok(output)
+    }
+
+    /// Create a patch turning the source into the target
+    #[must_use]
+    pub fn create(source: &[u8], target: &[u8]) -> Self {
+        let length = source.len().max(target.len());
+        let xor = |offset: usize| {
+            source.get(offset).copied().unwrap_or(0) ^ target.get(offset).copied().unwrap_or(0)
+        };
+
+        let mut records = Vec::new();
+        let mut position = 0;
+        let mut offset = 0;
+        while offset < length {
+            if xor(offset) == 0 {
+                offset += 1;
+                continue;
+            }
+
+            let start = offset;
+            while offset < length && xor(offset) != 0 {
+                offset += 1;
+            }
+
+            records.push(Record {
+                skip: start - position,
+                xor: (start..offset).map(xor).collect(),
+            });
+            position = offset + 1;
+        }
+
+        Self {
+            source_size: source.len(),
+            target_size: target.len(),
+            records,
+            source_checksum: crc32(source),
+            target_checksum: crc32(target),
+        }
+    }
+}
+
+/// Apply a UPS patch to the file
+///
+/// # Errors
+///
+/// Returns an error if the patch is malformed, wasn't made for the file or produces the wrong result
+pub fn apply(file: &[u8], patch: &[u8]) -> Result<Vec<u8>, Error> {
+    Ups::parse(patch)?.apply(file)
+}
+
+/// Create a UPS patch turning the source into the target
+#[must_use]
+pub fn create(source: &[u8], target: &[u8]) -> Vec<u8> {
+    Ups::create(source, target).to_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, alloc::vec};
+
+    #[test]
+    fn round_trip() {
+        let source = b"The quick brown fox jumps over the lazy dog";
+        let target = b"The quick brown cat jumps over the lazy dog, the lazy dog";
+
+        let patch = create(source, target);
+        assert_eq!(apply(source, &patch).unwrap(), target);
+        assert_eq!(apply(target, &patch).unwrap(), source);
+        assert_eq!(Ups::parse(&patch).unwrap().to_bytes(), patch);
+    }
+
+    #[test]
+    fn reject_records_past_the_end() {
+        let patch = Ups {
+            source_size: 4,
+            target_size: 4,
+            records: vec![Record {
+                skip: usize::MAX,
+                xor: vec![1],
+            }],
+            source_checksum: crc32(&[0; 4]),
+            ..Ups::new()
+        };
+        assert!(matches!(patch.apply(&[0; 4]), Err(Error::InvalidPatch)));
+
+        let patch = Ups {
+            records: vec![Record {
+                skip: 2,
+                xor: vec![1, 2, 3],
+            }],
+            ..patch
+        };
+        assert!(matches!(patch.apply(&[0; 4]), Err(Error::InvalidPatch)));
+    }
+
+    #[test]
+    fn reject_unallocatable_target() {
+        let patch = Ups {
+            target_size: 1 << 56,
+            ..Ups::new()
+        };
+
+        assert!(matches!(patch.apply(&[]), Err(Error::TooLarge(_))));
+    }
+}
@@ -0,0 +1,385 @@
+//!
+//! Generic differencing and compression data format, as produced by xdelta3
+//!
+//! Only applying patches is supported. Patches using secondary compression or custom code tables get rejected.
+//!
+//! [Format specification](https://www.rfc-editor.org/rfc/rfc3284)
+//!
+
+use {
+    crate::{checksum::adler32, reader::Reader, Error},
+    alloc::vec::Vec,
+};
+
+const MAGIC: &[u8] = &[0xD6, 0xC3, 0xC4, 0x00];
+
+// Flags of the header indicator
+const VCD_DECOMPRESS: u8 = 0x01;
+const VCD_CODETABLE: u8 = 0x02;
+// Extension of xdelta3
+const VCD_APPHEADER: u8 = 0x04;
+
+// Flags of the window indicator
+const VCD_SOURCE: u8 = 0x01;
+const VCD_TARGET: u8 = 0x02;
+// Extension of xdelta3
+const VCD_ADLER32: u8 = 0x04;
+
+// Sizes of the address caches of the default code table
+const NEAR_CACHE_SIZE: usize = 4;
+const SAME_CACHE_SIZE: usize = 3;
+
+// Address modes, followed by one mode per slot of the near cache and one per block of the same cache
+const MODE_SELF: usize = 0;
+const MODE_HERE: usize = 1;
+const MODE_NEAR: usize = 2;
+const MODE_SAME: usize = MODE_NEAR + NEAR_CACHE_SIZE;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Noop,
+    Add,
+    Run,
+    Copy,
+}
+
+#[derive(Clone, Copy)]
+struct Instruction {
+    kind: Kind,
+    /// Zero means that the size is stored in the instruction section
+    size: u8,
+    mode: u8,
+}
+
+const NOOP: Instruction = Instruction {
+    kind: Kind::Noop,
+    size: 0,
+    mode: 0,
+};
+
+fn add(size: u8) -> Instruction {
+    Instruction {
+        kind: Kind::Add,
+        size,
+        mode: 0,
+    }
+}
+
+fn copy(size: u8, mode: u8) -> Instruction {
+    Instruction {
+        kind: Kind::Copy,
+        size,
+        mode,
+    }
+}
+
+// Every instruction byte stands for up to two instructions (section 5.6 of the specification)
+fn default_code_table() -> Vec<[Instruction; 2]> {
+    let mut table = Vec::with_capacity(256);
+
+    table.push([
+        Instruction {
+            kind: Kind::Run,
+            size: 0,
+            mode: 0,
+        },
+        NOOP,
+    ]);
+    for size in 0..=17 {
+        table.push([add(size), NOOP]);
+    }
+
+    for mode in 0..=8 {
+        table.push([copy(0, mode), NOOP]);
+        for size in 4..=18 {
+            table.push([copy(size, mode), NOOP]);
+        }
+    }
+
+    for mode in 0..=5 {
+        for add_size in 1..=4 {
+            for copy_size in 4..=6 {
+                table.push([add(add_size), copy(copy_size, mode)]);
+            }
+        }
+    }
+    for mode in 6..=8 {
+        for add_size in 1..=4 {
+            table.push([add(add_size), copy(4, mode)]);
+        }
+    }
+
+    for mode in 0..=8 {
+        table.push([copy(4, mode), add(1)]);
+    }
+
+    table
+}
+
+// Integers are stored big-endian with seven bits per byte, the highest bit marks that more bytes follow
+fn read_integer(reader: &mut Reader<'_>) -> Result<usize, Error> {
+    let mut integer = 0_usize;
+
+    loop {
+        let byte = reader.take(1)?[0];
+        integer = integer
+            .checked_mul(0x80)
+            .and_then(|integer| integer.checked_add(usize::from(byte & 0x7F)))
+            .ok_or(Error::InvalidPatch)?;
+
+        if byte & 0x80 == 0 {
+            return Ok(integer);
+        }
+    }
+}
+
+/// Recently used addresses, which allow copy addresses to be stored in fewer bytes
+struct AddressCache {
+    near: [usize; NEAR_CACHE_SIZE],
+    next_slot: usize,
+    same: [usize; SAME_CACHE_SIZE * 256],
+}
+
+impl AddressCache {
+    fn new() -> Self {
+        Self {
+            near: [0; NEAR_CACHE_SIZE],
+            next_slot: 0,
+            same: [0; SAME_CACHE_SIZE * 256],
+        }
+    }
+
+    fn decode(
+        &mut self,
+        addresses: &mut Reader<'_>,
+        here: usize,
+        mode: u8,
+    ) -> Result<usize, Error> {
+        let mode = usize::from(mode);
+        let address = match mode {
+            MODE_SELF => read_integer(addresses)?,
+            MODE_HERE => here
+                .checked_sub(read_integer(addresses)?)
+                .ok_or(Error::InvalidPatch)?,
+            _ if mode < MODE_SAME => self.near[mode - MODE_NEAR]
+                .checked_add(read_integer(addresses)?)
+                .ok_or(Error::InvalidPatch)?,
+            _ => {
+                let index = (mode - MODE_SAME) * 256 + usize::from(addresses.take(1)?[0]);
+                *self.same.get(index).ok_or(Error::InvalidPatch)?
+            }
+        };
+
+        self.near[self.next_slot] = address;
+        self.next_slot = (self.next_slot + 1) % NEAR_CACHE_SIZE;
+        self.same[address % self.same.len()] = address;
+
+        Ok(address)
+    }
+}
+
+/// Apply a VCDIFF patch to the source
+///
+/// # Errors
+///
+/// Returns an error if the patch is malformed, uses unsupported features or a window checksum doesn't match
+pub fn apply(source: &[u8], patch: &[u8]) -> Result<Vec<u8>, Error> {
+    if !patch.starts_with(MAGIC) {
+        return Err(Error::MagicBytesMismatch);
+    }
+
+    let mut reader = Reader {
+        data: &patch[MAGIC.len()..],
+    };
+    let indicator = reader.take(1)?[0];
+    if indicator & VCD_DECOMPRESS != 0 {
+        return Err(Error::UnsupportedFeature("secondary compression"));
+    }
+    if indicator & VCD_CODETABLE != 0 {
+        return Err(Error::UnsupportedFeature("custom code tables"));
+    }
+    if indicator & VCD_APPHEADER != 0 {
+        // Usually the file names, which aren't needed
+        let length = read_integer(&mut reader)?;
+        reader.take(length)?;
+    }
+
+    let code_table = default_code_table();
+    let mut target = Vec::new();
+    while !reader.data.is_empty() {
+        let window = decode_window(&mut reader, &code_table, source, &target)?;
+        target.extend_from_slice(&window);
+    }
+
+    Ok(target)
+}
+
+fn decode_window(
+    reader: &mut Reader<'_>,
+    code_table: &[[Instruction; 2]],
+    source: &[u8],
+    target: &[u8],
+) -> Result<Vec<u8>, Error> {
+    let indicator = reader.take(1)?[0];
+    let segment = if indicator & (VCD_SOURCE | VCD_TARGET) == 0 {
+        &[]
+    } else {
+        let length = read_integer(reader)?;
+        let position = read_integer(reader)?;
+        let file = if indicator & VCD_SOURCE == 0 {
+            target
+        } else {
+            source
+        };
+
+        position
+            .checked_add(length)
+            .and_then(|end| file.get(position..end))
+            .ok_or(Error::InvalidPatch)?
+    };
+
+    let delta_length = read_integer(reader)?;
+    let mut delta = Reader {
+        data: reader.take(delta_length)?,
+    };
+    let window_size = read_integer(&mut delta)?;
+    if delta.take(1)?[0] != 0 {
+        return Err(Error::UnsupportedFeature("compressed sections"));
+    }
+
+    let data_length = read_integer(&mut delta)?;
+    let instructions_length = read_integer(&mut delta)?;
+    let addresses_length = read_integer(&mut delta)?;
+    let checksum = if indicator & VCD_ADLER32 == 0 {
+        None
+    } else {
+        Some(delta.number(4)?)
+    };
+
+    let mut data = Reader {
+        data: delta.take(data_length)?,
+    };
+    let mut instructions = Reader {
+        data: delta.take(instructions_length)?,
+    };
+    let mut addresses = Reader {
+        data: delta.take(addresses_length)?,
+    };
+
+    // The window size is only trusted as an upper bound, the window grows with its instructions
+    let mut window = Vec::new();
+    let mut cache = AddressCache::new();
+    while !instructions.data.is_empty() {
+        let code = instructions.take(1)?[0];
+
+        for instruction in code_table[usize::from(code)] {
+            if instruction.kind == Kind::Noop {
+                continue;
+            }
+
+            let size = if instruction.size == 0 {
+                read_integer(&mut instructions)?
+            } else {
+                usize::from(instruction.size)
+            };
+            let end = window
+                .len()
+                .checked_add(size)
+                .filter(|end| *end <= window_size)
+                .ok_or(Error::InvalidPatch)?;
+            window.try_reserve(size).map_err(|_| Error::TooLarge(end))?;
+
+            match instruction.kind {
+                Kind::Add => window.extend_from_slice(data.take(size)?),
+                Kind::Run => {
+                    let value = data.take(1)?[0];
+                    window.resize(end, value);
+                }
+                Kind::Copy => {
+                    // Addresses span the segment followed by the window
+                    let here = segment.len() + window.len();
+                    let address = cache.decode(&mut addresses, here, instruction.mode)?;
+                    if address >= here {
+                        return Err(Error::InvalidPatch);
+                    }
+
+                    // Copies from the window can overlap with the bytes they write
+                    for address in address..address + size {
+                        let byte = match address.checked_sub(segment.len()) {
+                            Some(offset) => window[offset],
+                            None => segment[address],
+                        };
+                        window.push(byte);
+                    }
+                }
+                Kind::Noop => {}
+            }
+        }
+    }
+
+    if window.len() != window_size {
+        return Err(Error::InvalidPatch);
+    }
+
+    if let Some(expected) = checksum {
+        let actual = adler32(&window);
+        if actual != expected {
+            return Err(Error::TargetChecksumMismatch { expected, actual });
+        }
+    }
+
+    Ok(window)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply_window() {
+        let patch = [
+            0xD6, 0xC3, 0xC4, 0x00, // Magic
+            0x04, 0x05, b'a', b'/', b'/', b'b', b'/', // Header with application data
+            0x05, 0x08, 0x00, 0x12, // Window copying from the whole source
+            0x0F, 0x00, 0x04, 0x04, 0x01, // Sizes of the window and its sections
+            0x20, 0x0D, 0x03, 0xB4, // Adler-32 checksum
+            b'X', b'Y', b'Z', b'!', // Data section
+            0x18, 0x04, 0x00, 0x04, // COPY 8, ADD 3, RUN 4
+            0x00, // Address section
+        ];
+
+        assert_eq!(apply(b"ABCDEFGH", &patch).unwrap(), b"ABCDEFGHXYZ!!!!");
+        assert!(matches!(
+            apply(b"ABCDEFGI", &patch),
+            Err(Error::TargetChecksumMismatch {
+                expected: 0x200D_03B4,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn reject_oversized_window() {
+        let patch = [
+            0xD6, 0xC3, 0xC4, 0x00, // Magic
+            0x00, 0x00, 0x0C, // Window without a segment
+            0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F, // Window size of 2^56 - 1 bytes
+            0x00, 0x00, 0x00, 0x00, // Empty sections
+        ];
+
+        assert!(matches!(apply(&[], &patch), Err(Error::InvalidPatch)));
+    }
+
+    #[test]
+    fn reject_window_overflow() {
+        let patch = [
+            0xD6, 0xC3, 0xC4, 0x00, // Magic
+            0x00, 0x00, 0x08, // Window without a segment
+            0x02, 0x00, 0x01, 0x02, 0x00, // Sizes of the window and its sections
+            b'!', // Data section
+            0x00, 0x03, // RUN 3
+        ];
+
+        assert!(matches!(apply(&[], &patch), Err(Error::InvalidPatch)));
+    }
+}
@@ -0,0 +1,164 @@
+use nes_patch::{
+    bps,
+    ips::{self, Ips, Record, RecordData},
+    ups, vcdiff, Error,
+};
+
+// Pseudo-random ROM-sized data, so the patches have to deal with more than a handful of bytes
+fn rom(size: usize, mut seed: u32) -> Vec<u8> {
+    (0..size)
+        .map(|_| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed.to_le_bytes()[0]
+        })
+        .collect()
+}
+
+// Pairs of sources and targets: changed bytes, moved data, a grown and a shrunk file
+fn cases() -> Vec<(Vec<u8>, Vec<u8>)> {
+    let source = rom(0x8000, 0x1234_5678);
+
+    let mut changed = source.clone();
+    for index in (0..changed.len()).step_by(0x101) {
+        changed[index] ^= 0xFF;
+    }
+
+    let mut moved = source.clone();
+    moved.copy_within(0x1000..0x3000, 0x5000);
+    moved[0x4000..0x4800].fill(0);
+
+    let mut grown = source.clone();
+    grown.extend_from_slice(&rom(0x4000, 0x8765_4321));
+    grown.extend_from_slice(&source[..0x2000]);
+
+    let shrunk = source[0x800..0x6000].to_vec();
+
+    vec![
+        (source.clone(), source.clone()),
+        (source.clone(), changed),
+        (source.clone(), moved),
+        (source.clone(), grown),
+        (source, shrunk),
+    ]
+}
+
+#[test]
+fn ips_round_trip() {
+    for (source, target) in cases() {
+        let patch = ips::create(&source, &target).unwrap();
+
+        let mut file = source.clone();
+        ips::apply(&mut file, &patch).unwrap();
+        assert_eq!(file, target);
+    }
+}
+
+#[test]
+fn bps_round_trip() {
+    for (source, target) in cases() {
+        let patch = bps::create(&source, &target);
+
+        assert_eq!(bps::apply(&source, &patch).unwrap(), target);
+    }
+}
+
+#[test]
+fn bps_checksums() {
+    let (source, target) = cases().swap_remove(1);
+    let mut patch = bps::create(&source, &target);
+
+    let mut other = source.clone();
+    other[0] ^= 0xFF;
+    assert!(matches!(
+        bps::apply(&other, &patch),
+        Err(Error::SourceChecksumMismatch { .. })
+    ));
+
+    // Corrupting the patch itself is caught by its own checksum
+    let last = patch.len() - 1;
+    patch[last] ^= 0xFF;
+    assert!(matches!(
+        bps::apply(&source, &patch),
+        Err(Error::PatchChecksumMismatch { .. })
+    ));
+}
+
+#[test]
+fn ups_round_trip() {
+    for (source, target) in cases() {
+        let patch = ups::create(&source, &target);
+
+        assert_eq!(ups::apply(&source, &patch).unwrap(), target);
+        // UPS patches work in both directions
+        assert_eq!(ups::apply(&target, &patch).unwrap(), source);
+    }
+}
+
+#[test]
+fn ips_records() {
+    #[rustfmt::skip]
+    let patch = [
+        b'P', b'A', b'T', b'C', b'H',
+        0x00, 0x00, 0x02, 0x00, 0x02, 0xAA, 0xBB, // Two bytes at 2
+        0x00, 0x00, 0x06, 0x00, 0x00, 0x00, 0x03, 0xCC, // Three times $CC at 6
+        b'E', b'O', b'F',
+        0x00, 0x00, 0x08, // Truncate to 8 bytes
+    ];
+
+    let ips = Ips::parse(&patch).unwrap();
+    assert_eq!(
+        ips.records[1],
+        Record {
+            offset: 6,
+            data: RecordData::Rle {
+                count: 3,
+                value: 0xCC,
+            },
+        }
+    );
+    assert_eq!(ips.truncate, Some(8));
+    assert_eq!(ips.to_bytes(), patch);
+
+    let mut file = vec![0; 4];
+    ips.apply(&mut file);
+    assert_eq!(file, [0, 0, 0xAA, 0xBB, 0, 0, 0xCC, 0xCC]);
+}
+
+// Laid out like the output of `xdelta3 -e -s source target`: the file names as application data,
+// Adler-32 checksums for every window, and copies using all kinds of address modes of the default code table
+#[rustfmt::skip]
+const VCDIFF_PATCH: &[u8] = &[
+    0xD6, 0xC3, 0xC4, 0x00, // Magic
+    0x04, 0x10, // Header with application data
+    b't', b'a', b'r', b'g', b'e', b't', b'/', b'/', b's', b'o', b'u', b'r', b'c', b'e', b'/', b'/',
+
+    0x05, 0x10, 0x00, 0x16, // Window copying from the whole source
+    0x20, 0x00, 0x02, 0x06, 0x05, // Sizes of the window and its sections
+    0x9A, 0x77, 0x09, 0x23, // Adler-32 checksum
+    b'x', b'y', // Data section
+    0x18, // COPY 8, address of the SELF mode
+    0x03, // ADD 2
+    0x28, // COPY 8, address relative to HERE
+    0x34, // COPY 4, address relative to the first slot of the NEAR cache
+    0x74, // COPY 4, address from the SAME cache
+    0x16, // COPY 6, overlapping with the bytes it writes
+    0x00, 0x12, 0x00, 0x00, 0x28, // Address section
+
+    0x06, 0x08, 0x08, 0x0E, // Window copying from the target written so far
+    0x0B, 0x00, 0x01, 0x03, 0x01, // Sizes of the window and its sections
+    0x16, 0x22, 0x03, 0x1A, // Adler-32 checksum
+    b'!', // Data section
+    0x18, 0x00, 0x03, // COPY 8, RUN 3
+    0x00, // Address section
+];
+
+#[test]
+fn vcdiff_fixture() {
+    assert_eq!(
+        vcdiff::apply(b"ABCDEFGHIJKLMNOP", VCDIFF_PATCH).unwrap(),
+        b"ABCDEFGHxyIJKLMNOPABCDABCDCDCDCDxyIJKLMN!!!"
+    );
+    assert!(vcdiff::apply(b"ABCDEFGHIJKLMNOQ", VCDIFF_PATCH).is_err());
+}
@@ -0,0 +1,261 @@
+//!
+//! Post-processing of frames for display
+//!
+//! Filters take an [`RgbaImage`] and return a new one, so they can be chained in any order with a [`FilterChain`].
+//! The usual order is blending the colours, scaling, adding the scanlines and bending the picture last.
+//!
+
+use {
+    crate::Ppu,
+    alloc::{boxed::Box, vec, vec::Vec},
+    lemonade::{SCREEN_HEIGHT, SCREEN_WIDTH},
+};
+
+const BYTES_PER_PIXEL: usize = 4;
+
+/// Image with 4 bytes per pixel (red, green, blue and alpha), stored row by row
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RgbaImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl RgbaImage {
+    /// Transparent black image
+    #[must_use]
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width * height * BYTES_PER_PIXEL],
+        }
+    }
+
+    /// Last rendered frame of the PPU
+    #[must_use]
+    pub fn from_ppu(ppu: &Ppu) -> Self {
+        Self {
+            width: SCREEN_WIDTH,
+            height: SCREEN_HEIGHT,
+            pixels: ppu.frame_rgba(),
+        }
+    }
+
+    /// Colour of the pixel, transparent black outside of the image
+    #[must_use]
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        if x >= self.width || y >= self.height {
+            return [0; 4];
+        }
+
+        let offset = (y * self.width + x) * BYTES_PER_PIXEL;
+        let mut pixel = [0; 4];
+        pixel.copy_from_slice(&self.pixels[offset..offset + BYTES_PER_PIXEL]);
+        pixel
+    }
+
+    /// Change the colour of the pixel; pixels outside of the image are ignored
+    pub fn set_pixel(&mut self, x: usize, y: usize, pixel: [u8; 4]) {
+        if x < self.width && y < self.height {
+            let offset = (y * self.width + x) * BYTES_PER_PIXEL;
+            self.pixels[offset..offset + BYTES_PER_PIXEL].copy_from_slice(&pixel);
+        }
+    }
+}
+
+/// Step of the post-processing
+pub trait Filter {
+    fn apply(&self, image: &RgbaImage) -> RgbaImage;
+}
+
+// Mix two colour channels, `weight` being the share of the second one out of 255
+fn mix(first: u8, second: u8, weight: u8) -> u8 {
+    let mixed =
+        (u16::from(first) * u16::from(255 - weight) + u16::from(second) * u16::from(weight)) / 255;
+    // Mixing never leaves the range of the channels
+    #[allow(clippy::cast_possible_truncation)]
+    let mixed = mixed as u8;
+    mixed
+}
+
+/// Nearest neighbour scaling by a whole factor, which keeps the pixels sharp and square
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct IntegerScale(pub usize);
+
+impl Filter for IntegerScale {
+    fn apply(&self, image: &RgbaImage) -> RgbaImage {
+        let factor = self.0.max(1);
+        let mut scaled = RgbaImage::new(image.width * factor, image.height * factor);
+
+        for y in 0..scaled.height {
+            for x in 0..scaled.width {
+                scaled.set_pixel(x, y, image.pixel(x / factor, y / factor));
+            }
+        }
+
+        scaled
+    }
+}
+
+/// Dark gaps between the lines like the ones of a CRT
+///
+/// The last row of every group of rows is darkened, so the group size should be the factor of a preceding [`IntegerScale`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Scanlines {
+    /// Rows per line of the original picture
+    pub rows: usize,
+    /// Brightness of the gaps, 0 being black and 255 leaving them untouched
+    pub brightness: u8,
+}
+
+impl Default for Scanlines {
+    fn default() -> Self {
+        Self {
+            rows: 2,
+            brightness: 128,
+        }
+    }
+}
+
+impl Filter for Scanlines {
+    fn apply(&self, image: &RgbaImage) -> RgbaImage {
+        let rows = self.rows.max(1);
+        let mut filtered = image.clone();
+
+        for y in (rows - 1..image.height).step_by(rows) {
+            for x in 0..image.width {
+                let [red, green, blue, alpha] = image.pixel(x, y);
+                let darken = |channel| mix(0, channel, self.brightness);
+                filtered.set_pixel(x, y, [darken(red), darken(green), darken(blue), alpha]);
+            }
+        }
+
+        filtered
+    }
+}
+
+/// Horizontal colour bleeding of a composite video signal, which the dithering of many games relies on
+///
+/// Every pixel gets mixed with the average of its neighbours to the left and right.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NtscBlend {
+    /// Share of the neighbours, 0 leaving the image untouched and 255 replacing every pixel by its neighbours
+    pub strength: u8,
+}
+
+impl Default for NtscBlend {
+    fn default() -> Self {
+        Self { strength: 96 }
+    }
+}
+
+impl Filter for NtscBlend {
+    fn apply(&self, image: &RgbaImage) -> RgbaImage {
+        let mut blended = image.clone();
+
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let pixel = image.pixel(x, y);
+                // The edges use the pixel itself as the missing neighbour
+                let left = if x == 0 { pixel } else { image.pixel(x - 1, y) };
+                let right = if x + 1 == image.width {
+                    pixel
+                } else {
+                    image.pixel(x + 1, y)
+                };
+
+                let mut result = pixel;
+                for channel in 0..3 {
+                    let neighbours = u8::midpoint(left[channel], right[channel]);
+                    result[channel] = mix(pixel[channel], neighbours, self.strength);
+                }
+                blended.set_pixel(x, y, result);
+            }
+        }
+
+        blended
+    }
+}
+
+/// Barrel distortion approximating the curved glass of a CRT; the corners outside of the picture turn black
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CrtCurvature {
+    /// How far the edges bend, 0.0 being flat and 0.1 already clearly curved
+    pub amount: f32,
+}
+
+impl Default for CrtCurvature {
+    fn default() -> Self {
+        Self { amount: 0.05 }
+    }
+}
+
+impl Filter for CrtCurvature {
+    // Every pixel of the result is looked up in the flat image, with its position pushed outwards the further
+    // it is from the centre on the other axis
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    fn apply(&self, image: &RgbaImage) -> RgbaImage {
+        let mut curved = RgbaImage::new(image.width, image.height);
+        let (width, height) = (image.width as f32, image.height as f32);
+
+        for y in 0..image.height {
+            for x in 0..image.width {
+                // Position relative to the centre, from -1.0 to 1.0
+                let u = (x as f32 + 0.5) / width * 2.0 - 1.0;
+                let v = (y as f32 + 0.5) / height * 2.0 - 1.0;
+
+                let source_u = u * (1.0 + self.amount * v * v);
+                let source_v = v * (1.0 + self.amount * u * u);
+                if source_u.abs() >= 1.0 || source_v.abs() >= 1.0 {
+                    curved.set_pixel(x, y, [0, 0, 0, 0xFF]);
+                    continue;
+                }
+
+                let source_x = ((source_u * 0.5 + 0.5) * width) as usize;
+                let source_y = ((source_v * 0.5 + 0.5) * height) as usize;
+                curved.set_pixel(x, y, image.pixel(source_x, source_y));
+            }
+        }
+
+        curved
+    }
+}
+
+/// Filters applied one after another
+#[derive(Default)]
+pub struct FilterChain {
+    filters: Vec<Box<dyn Filter>>,
+}
+
+impl FilterChain {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a filter to the chain
+    #[must_use]
+    pub fn with<F: Filter + 'static>(mut self, filter: F) -> Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    /// Run the last rendered frame of the PPU through the chain
+    #[must_use]
+    pub fn apply_to_frame(&self, ppu: &Ppu) -> RgbaImage {
+        self.apply(&RgbaImage::from_ppu(ppu))
+    }
+}
+
+impl Filter for FilterChain {
+    fn apply(&self, image: &RgbaImage) -> RgbaImage {
+        self.filters
+            .iter()
+            .fold(image.clone(), |image, filter| filter.apply(&image))
+    }
+}
@@ -0,0 +1,39 @@
+#![no_std]
+#![warn(clippy::all, clippy::pedantic)]
+
+//!
+//! Emulation core for the 2C02 PPU of the NES
+//!
+//! [PPU reference](https://www.nesdev.org/wiki/PPU)
+//!
+
+extern crate alloc;
+
+pub mod filter;
+#[cfg(feature = "png")]
+mod png;
+mod ppu;
+
+#[cfg(feature = "png")]
+pub use png::encode_png;
+pub use ppu::{Ppu, PpuRegisters, DOTS_PER_SCANLINE, OAM_SIZE, SCANLINES_PER_FRAME};
+
+/// Memory the PPU is connected to
+///
+/// Covers the pattern tables (`$0000`-`$1FFF`) and the nametables (`$2000`-`$3EFF`).
+/// Mirroring the nametables is up to the cartridge, the palette RAM is part of the PPU.
+pub trait PpuBus {
+    fn read(&mut self, address: u16) -> u8;
+
+    fn write(&mut self, address: u16, value: u8);
+}
+
+impl<B: PpuBus + ?Sized> PpuBus for &mut B {
+    fn read(&mut self, address: u16) -> u8 {
+        (**self).read(address)
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        (**self).write(address, value);
+    }
+}
@@ -0,0 +1,91 @@
+//!
+//! Minimal PNG encoder for frame captures
+//!
+//! [Format documentation](https://www.w3.org/TR/png/)
+//!
+
+use {
+    crate::{filter::RgbaImage, Ppu},
+    alloc::vec::Vec,
+    lemonade::{SCREEN_HEIGHT, SCREEN_WIDTH},
+    miniz_oxide::deflate::compress_to_vec_zlib,
+};
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+const BIT_DEPTH: u8 = 8;
+const COLOUR_TYPE_RGBA: u8 = 6;
+const BYTES_PER_PIXEL: usize = 4;
+// Rows are stored without a filter, which compresses the flat colours of NES graphics well enough
+const FILTER_NONE: u8 = 0;
+const COMPRESSION_LEVEL: u8 = 6;
+
+fn write_chunk(png: &mut Vec<u8>, kind: [u8; 4], data: &[u8]) {
+    // Chunks are far smaller than 4 GiB
+    #[allow(clippy::cast_possible_truncation)]
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+
+    let start = png.len();
+    png.extend_from_slice(&kind);
+    png.extend_from_slice(data);
+    let crc = crc32fast::hash(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Encode an RGBA image (4 bytes per pixel, row by row) as a PNG file
+///
+/// Missing pixels are transparent, surplus ones get ignored.
+#[must_use]
+pub fn encode_png(rgba: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let row_size = width as usize * BYTES_PER_PIXEL;
+
+    let mut raw = Vec::with_capacity((row_size + 1) * height as usize);
+    for row in 0..height as usize {
+        raw.push(FILTER_NONE);
+        let start = (row * row_size).min(rgba.len());
+        let end = (start + row_size).min(rgba.len());
+        raw.extend_from_slice(&rgba[start..end]);
+        raw.resize(raw.len() + row_size - (end - start), 0);
+    }
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // Compression, filter and interlace methods are all the default ones
+    header.extend_from_slice(&[BIT_DEPTH, COLOUR_TYPE_RGBA, 0, 0, 0]);
+
+    let mut png = SIGNATURE.to_vec();
+    write_chunk(&mut png, *b"IHDR", &header);
+    write_chunk(
+        &mut png,
+        *b"IDAT",
+        &compress_to_vec_zlib(&raw, COMPRESSION_LEVEL),
+    );
+    write_chunk(&mut png, *b"IEND", &[]);
+
+    png
+}
+
+impl Ppu {
+    /// Last rendered frame as a PNG file (256×240), for example to compare it with a golden image
+    #[must_use]
+    pub fn frame_png(&self) -> Vec<u8> {
+        // The screen dimensions are constants far below `u32::MAX`
+        #[allow(clippy::cast_possible_truncation)]
+        encode_png(
+            &self.frame_rgba(),
+            SCREEN_WIDTH as u32,
+            SCREEN_HEIGHT as u32,
+        )
+    }
+}
+
+impl RgbaImage {
+    /// Encode the image as a PNG file
+    #[must_use]
+    pub fn to_png(&self) -> Vec<u8> {
+        // Larger images wouldn't fit into memory
+        #[allow(clippy::cast_possible_truncation)]
+        encode_png(&self.pixels, self.width as u32, self.height as u32)
+    }
+}
@@ -0,0 +1,857 @@
+use {
+    crate::PpuBus,
+    alloc::{vec, vec::Vec},
+    ines_parser::Timing,
+    lemonade::{nes_colour, SCREEN_HEIGHT, SCREEN_WIDTH},
+    nes_state::{Savestate, StateReader, StateWriter},
+};
+
+/// Size of the object attribute memory (64 sprites with 4 bytes each)
+pub const OAM_SIZE: usize = 256;
+
+/// Amount of dots (PPU cycles) per scanline
+pub const DOTS_PER_SCANLINE: u16 = 341;
+
+/// Amount of scanlines per frame of NTSC consoles, including the pre-render scanline
+pub const SCANLINES_PER_FRAME: u16 = 262;
+
+// First scanline after the visible ones, on all consoles
+const POST_RENDER_SCANLINE: u16 = 240;
+const MAX_SPRITES_PER_SCANLINE: usize = 8;
+
+// PPUCTRL
+const CTRL_SPRITE_TABLE: u8 = 0x08;
+const CTRL_BACKGROUND_TABLE: u8 = 0x10;
+const CTRL_TALL_SPRITES: u8 = 0x20;
+const CTRL_INCREMENT_32: u8 = 0x04;
+const CTRL_NMI: u8 = 0x80;
+
+// PPUMASK
+const MASK_GREYSCALE: u8 = 0x01;
+const MASK_BACKGROUND_LEFT: u8 = 0x02;
+const MASK_SPRITES_LEFT: u8 = 0x04;
+const MASK_BACKGROUND: u8 = 0x08;
+const MASK_SPRITES: u8 = 0x10;
+
+// PPUSTATUS
+const STATUS_SPRITE_OVERFLOW: u8 = 0x20;
+const STATUS_SPRITE_ZERO_HIT: u8 = 0x40;
+const STATUS_VBLANK: u8 = 0x80;
+
+// Sprite attributes
+const SPRITE_BEHIND_BACKGROUND: u8 = 0x20;
+const SPRITE_FLIP_HORIZONTAL: u8 = 0x40;
+const SPRITE_FLIP_VERTICAL: u8 = 0x80;
+
+// Flags of the pixels of the sprite line, next to the colour in the lower 4 bits
+const SPRITE_LINE_OPAQUE: u8 = 0x10;
+const SPRITE_LINE_BEHIND_BACKGROUND: u8 = 0x20;
+const SPRITE_LINE_SPRITE_ZERO: u8 = 0x40;
+
+/// Sprite which was selected for the current scanline
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct ScanlineSprite {
+    /// 4 bits per pixel (palette and colour index), leftmost pixel in the highest bits
+    pattern: u32,
+    x: u8,
+    behind_background: bool,
+    is_sprite_zero: bool,
+}
+
+/// Registers of the PPU, for debuggers and state comparisons
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PpuRegisters {
+    /// `PPUCTRL` (`$2000`)
+    pub ctrl: u8,
+    /// `PPUMASK` (`$2001`)
+    pub mask: u8,
+    /// `PPUSTATUS` (`$2002`)
+    pub status: u8,
+    /// `OAMADDR` (`$2003`)
+    pub oam_address: u8,
+    /// Current VRAM address, which holds the scroll position while rendering
+    pub v: u16,
+    /// Temporary VRAM address, the scroll position of the next frame
+    pub t: u16,
+    pub fine_x: u8,
+    /// Whether the next write to `$2005` or `$2006` is the second one
+    pub write_toggle: bool,
+}
+
+/// State of the PPU
+///
+/// The internal registers follow the naming of the nesdev wiki (`v`, `t`, `x` and `w`)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ppu {
+    ctrl: u8,
+    mask: u8,
+    status: u8,
+    oam_address: u8,
+    oam: [u8; OAM_SIZE],
+    palette: [u8; 32],
+
+    v: u16,
+    t: u16,
+    fine_x: u8,
+    write_toggle: bool,
+    read_buffer: u8,
+    open_bus: u8,
+
+    scanline: u16,
+    dot: u16,
+    frame: u64,
+    nmi_line: bool,
+    nmi_pending: bool,
+
+    nametable_byte: u8,
+    attribute_bits: u8,
+    pattern_low: u8,
+    pattern_high: u8,
+    // 4 bits per pixel for the current and the next tile, current tile in the upper half
+    tile_data: u64,
+
+    sprites: [ScanlineSprite; MAX_SPRITES_PER_SCANLINE],
+    sprite_count: usize,
+    // Pixels of the selected sprites laid out over the scanline, the first opaque one of every position
+    // (not part of the state, it's rebuilt from the sprites)
+    sprite_line: [u8; SCREEN_WIDTH],
+
+    framebuffer: Vec<u8>,
+
+    timing: Timing,
+}
+
+impl Default for Ppu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Ppu {
+    /// Create a PPU in the power-up state, right before the first `VBlank`
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            ctrl: 0,
+            mask: 0,
+            status: 0,
+            oam_address: 0,
+            oam: [0; OAM_SIZE],
+            palette: [0; 32],
+            v: 0,
+            t: 0,
+            fine_x: 0,
+            write_toggle: false,
+            read_buffer: 0,
+            open_bus: 0,
+            scanline: POST_RENDER_SCANLINE,
+            dot: DOTS_PER_SCANLINE - 1,
+            frame: 0,
+            nmi_line: false,
+            nmi_pending: false,
+            nametable_byte: 0,
+            attribute_bits: 0,
+            pattern_low: 0,
+            pattern_high: 0,
+            tile_data: 0,
+            sprites: [ScanlineSprite::default(); MAX_SPRITES_PER_SCANLINE],
+            sprite_count: 0,
+            sprite_line: [0; SCREEN_WIDTH],
+            framebuffer: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            timing: Timing::Ntsc,
+        }
+    }
+
+    /// Use the scanline counts of the console; PPUs start out with the ones of NTSC
+    ///
+    /// PAL and Dendy PPUs have 312 scanlines and don't skip a dot on odd frames.
+    /// The Dendy starts the `VBlank` 50 scanlines late, at scanline 291.
+    #[must_use]
+    pub fn with_timing(mut self, timing: Timing) -> Self {
+        self.timing = timing;
+        self.scanline = self.vblank_scanline() - 1;
+        self.dot = DOTS_PER_SCANLINE - 1;
+        self
+    }
+
+    #[must_use]
+    pub fn timing(&self) -> Timing {
+        self.timing
+    }
+
+    /// Amount of scanlines per frame, including the pre-render scanline
+    #[must_use]
+    pub fn scanlines_per_frame(&self) -> u16 {
+        self.timing.scanlines()
+    }
+
+    fn vblank_scanline(&self) -> u16 {
+        self.pre_render_scanline() - self.timing.vblank_scanlines()
+    }
+
+    fn pre_render_scanline(&self) -> u16 {
+        self.scanlines_per_frame() - 1
+    }
+
+    /// Current scanline; 0-239 are visible, 241 (291 on the Dendy) starts the `VBlank` and the last one is the pre-render scanline
+    #[must_use]
+    pub fn scanline(&self) -> u16 {
+        self.scanline
+    }
+
+    /// Current dot of the scanline
+    #[must_use]
+    pub fn dot(&self) -> u16 {
+        self.dot
+    }
+
+    /// Amount of frames started since power-up
+    #[must_use]
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    #[must_use]
+    pub fn registers(&self) -> PpuRegisters {
+        PpuRegisters {
+            ctrl: self.ctrl,
+            mask: self.mask,
+            status: self.status,
+            oam_address: self.oam_address,
+            v: self.v,
+            t: self.t,
+            fine_x: self.fine_x,
+            write_toggle: self.write_toggle,
+        }
+    }
+
+    /// Object attribute memory
+    #[must_use]
+    pub fn oam(&self) -> &[u8; OAM_SIZE] {
+        &self.oam
+    }
+
+    /// Object attribute memory, mutable for OAM DMA and debuggers
+    pub fn oam_mut(&mut self) -> &mut [u8; OAM_SIZE] {
+        &mut self.oam
+    }
+
+    /// Palette RAM (`$3F00`-`$3F1F`)
+    #[must_use]
+    pub fn palette_ram(&self) -> &[u8; 32] {
+        &self.palette
+    }
+
+    /// Whether a rising edge on the NMI output happened since the last call
+    pub fn take_nmi(&mut self) -> bool {
+        core::mem::take(&mut self.nmi_pending)
+    }
+
+    /// Last rendered frame as master palette indices (256×240)
+    #[must_use]
+    pub fn framebuffer(&self) -> &[u8] {
+        &self.framebuffer
+    }
+
+    /// Last rendered frame as RGB (256×240, 3 bytes per pixel)
+    ///
+    /// Colour emphasis isn't applied
+    #[must_use]
+    pub fn to_rgb(&self) -> Vec<u8> {
+        self.framebuffer
+            .iter()
+            .flat_map(|index| nes_colour(*index).raw_colour())
+            .collect()
+    }
+
+    /// Last rendered frame as RGBA (256×240, 4 bytes per pixel, all opaque)
+    ///
+    /// Colour emphasis isn't applied
+    #[must_use]
+    pub fn frame_rgba(&self) -> Vec<u8> {
+        self.framebuffer
+            .iter()
+            .flat_map(|index| {
+                let [red, green, blue] = nes_colour(*index).raw_colour();
+                [red, green, blue, 0xFF]
+            })
+            .collect()
+    }
+
+    fn is_rendering_enabled(&self) -> bool {
+        self.mask & (MASK_BACKGROUND | MASK_SPRITES) != 0
+    }
+
+    fn update_nmi(&mut self) {
+        let line = self.ctrl & CTRL_NMI != 0 && self.status & STATUS_VBLANK != 0;
+        if line && !self.nmi_line {
+            self.nmi_pending = true;
+        }
+        self.nmi_line = line;
+    }
+
+    /// Read one of the registers at `$2000`-`$2007` (mirrored up to `$3FFF`)
+    pub fn read_register<B: PpuBus + ?Sized>(&mut self, bus: &mut B, address: u16) -> u8 {
+        let value = match address & 0x07 {
+            // PPUSTATUS; the lower bits are open bus
+            2 => {
+                let value = (self.status & 0xE0) | (self.open_bus & 0x1F);
+                self.status &= !STATUS_VBLANK;
+                self.write_toggle = false;
+                self.update_nmi();
+                value
+            }
+            // OAMDATA
+            4 => self.oam[usize::from(self.oam_address)],
+            // PPUDATA
+            7 => {
+                let address = self.v & 0x3FFF;
+                let value = if address >= 0x3F00 {
+                    // Palette reads aren't buffered, the buffer gets the nametable byte "below" instead
+                    self.read_buffer = bus.read(address - 0x1000);
+                    self.palette[palette_index(address)]
+                } else {
+                    let buffered = self.read_buffer;
+                    self.read_buffer = bus.read(address);
+                    buffered
+                };
+
+                self.increment_address();
+                value
+            }
+            // Write-only registers
+            _ => self.open_bus,
+        };
+
+        self.open_bus = value;
+        value
+    }
+
+    /// Write to one of the registers at `$2000`-`$2007` (mirrored up to `$3FFF`)
+    pub fn write_register<B: PpuBus + ?Sized>(&mut self, bus: &mut B, address: u16, value: u8) {
+        self.open_bus = value;
+
+        match address & 0x07 {
+            // PPUCTRL
+            0 => {
+                self.ctrl = value;
+                self.t = (self.t & 0xF3FF) | (u16::from(value & 0x03) << 10);
+                self.update_nmi();
+            }
+            // PPUMASK
+            1 => self.mask = value,
+            // OAMADDR
+            3 => self.oam_address = value,
+            // OAMDATA
+            4 => {
+                self.oam[usize::from(self.oam_address)] = value;
+                self.oam_address = self.oam_address.wrapping_add(1);
+            }
+            // PPUSCROLL
+            5 => {
+                if self.write_toggle {
+                    self.t = (self.t & 0x8FFF) | (u16::from(value & 0x07) << 12);
+                    self.t = (self.t & 0xFC1F) | (u16::from(value & 0xF8) << 2);
+                } else {
+                    self.t = (self.t & 0xFFE0) | u16::from(value >> 3);
+                    self.fine_x = value & 0x07;
+                }
+                self.write_toggle = !self.write_toggle;
+            }
+            // PPUADDR
+            6 => {
+                if self.write_toggle {
+                    self.t = (self.t & 0xFF00) | u16::from(value);
+                    self.v = self.t;
+                } else {
+                    self.t = (self.t & 0x80FF) | (u16::from(value & 0x3F) << 8);
+                }
+                self.write_toggle = !self.write_toggle;
+            }
+            // PPUDATA
+            7 => {
+                let address = self.v & 0x3FFF;
+                if address >= 0x3F00 {
+                    self.palette[palette_index(address)] = value & 0x3F;
+                } else {
+                    bus.write(address, value);
+                }
+
+                self.increment_address();
+            }
+            // PPUSTATUS is read-only
+            _ => {}
+        }
+    }
+
+    fn increment_address(&mut self) {
+        let increment = if self.ctrl & CTRL_INCREMENT_32 == 0 {
+            1
+        } else {
+            32
+        };
+        self.v = self.v.wrapping_add(increment) & 0x7FFF;
+    }
+
+    /// Advance the PPU by one dot
+    pub fn tick<B: PpuBus + ?Sized>(&mut self, bus: &mut B) {
+        self.advance();
+
+        let dot = self.dot;
+        let is_pre_render = self.scanline == self.pre_render_scanline();
+        let is_visible = self.scanline < POST_RENDER_SCANLINE;
+        let is_render_line = is_pre_render || is_visible;
+        let is_visible_dot = (1..=256).contains(&dot);
+        let is_fetch_dot = is_visible_dot || (321..=336).contains(&dot);
+
+        if self.is_rendering_enabled() {
+            if is_visible && is_visible_dot {
+                self.render_pixel();
+            }
+
+            if is_render_line && is_fetch_dot {
+                self.tile_data <<= 4;
+                match dot % 8 {
+                    1 => self.fetch_nametable_byte(bus),
+                    3 => self.fetch_attribute_bits(bus),
+                    5 => self.pattern_low = bus.read(self.background_pattern_address()),
+                    7 => self.pattern_high = bus.read(self.background_pattern_address() + 8),
+                    0 => self.store_tile_data(),
+                    _ => {}
+                }
+            }
+
+            if is_pre_render && (280..=304).contains(&dot) {
+                self.copy_y();
+            }
+
+            if is_render_line {
+                if is_fetch_dot && dot.is_multiple_of(8) {
+                    self.increment_x();
+                }
+                if dot == 256 {
+                    self.increment_y();
+                }
+                if dot == 257 {
+                    self.copy_x();
+                }
+            }
+
+            if dot == 257 {
+                if is_visible {
+                    self.evaluate_sprites(bus);
+                } else {
+                    self.sprite_count = 0;
+                    self.sprite_line = [0; SCREEN_WIDTH];
+                }
+            }
+        }
+
+        if self.scanline == self.vblank_scanline() && dot == 1 {
+            self.status |= STATUS_VBLANK;
+            self.update_nmi();
+        }
+
+        if is_pre_render && dot == 1 {
+            self.status &= !(STATUS_VBLANK | STATUS_SPRITE_ZERO_HIT | STATUS_SPRITE_OVERFLOW);
+            self.update_nmi();
+        }
+    }
+
+    fn advance(&mut self) {
+        // The last dot of the pre-render scanline gets skipped on odd frames while rendering, only by NTSC PPUs
+        if self.is_rendering_enabled()
+            && matches!(self.timing, Timing::Ntsc | Timing::MultiRegion)
+            && self.frame % 2 == 1
+            && self.scanline == self.pre_render_scanline()
+            && self.dot == DOTS_PER_SCANLINE - 2
+        {
+            self.dot = 0;
+            self.scanline = 0;
+            self.frame += 1;
+            return;
+        }
+
+        self.dot += 1;
+        if self.dot == DOTS_PER_SCANLINE {
+            self.dot = 0;
+            self.scanline += 1;
+
+            if self.scanline == self.scanlines_per_frame() {
+                self.scanline = 0;
+                self.frame += 1;
+            }
+        }
+    }
+
+    fn fetch_nametable_byte<B: PpuBus + ?Sized>(&mut self, bus: &mut B) {
+        self.nametable_byte = bus.read(0x2000 | (self.v & 0x0FFF));
+    }
+
+    fn fetch_attribute_bits<B: PpuBus + ?Sized>(&mut self, bus: &mut B) {
+        let v = self.v;
+        let address = 0x23C0 | (v & 0x0C00) | ((v >> 4) & 0x38) | ((v >> 2) & 0x07);
+        let shift = ((v >> 4) & 0x04) | (v & 0x02);
+
+        self.attribute_bits = ((bus.read(address) >> shift) & 0x03) << 2;
+    }
+
+    fn background_pattern_address(&self) -> u16 {
+        let table = if self.ctrl & CTRL_BACKGROUND_TABLE == 0 {
+            0x0000
+        } else {
+            0x1000
+        };
+        let fine_y = (self.v >> 12) & 0x07;
+
+        table + u16::from(self.nametable_byte) * 16 + fine_y
+    }
+
+    fn store_tile_data(&mut self) {
+        let data = pattern_pixels(
+            self.pattern_low,
+            self.pattern_high,
+            self.attribute_bits,
+            false,
+        );
+        self.tile_data |= u64::from(data);
+    }
+
+    fn increment_x(&mut self) {
+        if self.v & 0x001F == 31 {
+            self.v &= !0x001F;
+            self.v ^= 0x0400;
+        } else {
+            self.v += 1;
+        }
+    }
+
+    fn increment_y(&mut self) {
+        if self.v & 0x7000 != 0x7000 {
+            self.v += 0x1000;
+            return;
+        }
+
+        self.v &= !0x7000;
+        let mut coarse_y = (self.v & 0x03E0) >> 5;
+        match coarse_y {
+            29 => {
+                coarse_y = 0;
+                self.v ^= 0x0800;
+            }
+            // Out of bounds values wrap around without switching the nametable
+            31 => coarse_y = 0,
+            _ => coarse_y += 1,
+        }
+        self.v = (self.v & !0x03E0) | (coarse_y << 5);
+    }
+
+    fn copy_x(&mut self) {
+        self.v = (self.v & 0xFBE0) | (self.t & 0x041F);
+    }
+
+    fn copy_y(&mut self) {
+        self.v = (self.v & 0x841F) | (self.t & 0x7BE0);
+    }
+
+    // The shifts only ever keep 4 bits
+    #[allow(clippy::cast_possible_truncation)]
+    fn background_pixel(&self, x: usize) -> u8 {
+        if self.mask & MASK_BACKGROUND == 0 || (x < 8 && self.mask & MASK_BACKGROUND_LEFT == 0) {
+            return 0;
+        }
+
+        let data = (self.tile_data >> 32) >> ((7 - self.fine_x) * 4);
+        (data & 0x0F) as u8
+    }
+
+    /// Pixel of the sprite line at the position, with its colour in the lower 4 bits and the flags above
+    fn sprite_pixel(&self, x: usize) -> Option<u8> {
+        if self.mask & MASK_SPRITES == 0 || (x < 8 && self.mask & MASK_SPRITES_LEFT == 0) {
+            return None;
+        }
+
+        Some(self.sprite_line[x]).filter(|pixel| pixel & SPRITE_LINE_OPAQUE != 0)
+    }
+
+    fn render_pixel(&mut self) {
+        let x = usize::from(self.dot - 1);
+        let y = usize::from(self.scanline);
+
+        let background = self.background_pixel(x);
+        let sprite = self.sprite_pixel(x);
+
+        let index = match (background & 0x03 != 0, sprite) {
+            // Transparent pixels show the backdrop colour
+            (false, None) => 0,
+            (true, None) => background,
+            (false, Some(sprite)) => 0x10 | (sprite & 0x0F),
+            (true, Some(sprite)) => {
+                if sprite & SPRITE_LINE_SPRITE_ZERO != 0 && x < 255 {
+                    self.status |= STATUS_SPRITE_ZERO_HIT;
+                }
+
+                if sprite & SPRITE_LINE_BEHIND_BACKGROUND != 0 {
+                    background
+                } else {
+                    0x10 | (sprite & 0x0F)
+                }
+            }
+        };
+
+        let mut colour = self.palette[palette_index(u16::from(index))];
+        if self.mask & MASK_GREYSCALE != 0 {
+            colour &= 0x30;
+        }
+
+        self.framebuffer[y * SCREEN_WIDTH + x] = colour;
+    }
+
+    /// Select the sprites of the next scanline and fetch their patterns
+    fn evaluate_sprites<B: PpuBus + ?Sized>(&mut self, bus: &mut B) {
+        let height = if self.ctrl & CTRL_TALL_SPRITES == 0 {
+            8
+        } else {
+            16
+        };
+
+        let mut count = 0;
+        for (index, entry) in self.oam.chunks_exact(4).enumerate() {
+            // Sprites are delayed by one scanline, so the row gets compared to the current one
+            let row = match self.scanline.checked_sub(u16::from(entry[0])) {
+                Some(row) if row < height => row,
+                _ => continue,
+            };
+
+            if count == MAX_SPRITES_PER_SCANLINE {
+                self.status |= STATUS_SPRITE_OVERFLOW;
+                break;
+            }
+
+            let (tile, attributes, x) = (entry[1], entry[2], entry[3]);
+            self.sprites[count] = ScanlineSprite {
+                pattern: self.fetch_sprite_pattern(bus, tile, attributes, row, height),
+                x,
+                behind_background: attributes & SPRITE_BEHIND_BACKGROUND != 0,
+                is_sprite_zero: index == 0,
+            };
+            count += 1;
+        }
+
+        // Unused slots fetch tile $FF, which mappers watching the address lines (like the MMC3) rely on
+        for _ in count..MAX_SPRITES_PER_SCANLINE {
+            self.fetch_sprite_pattern(bus, 0xFF, 0, 0, height);
+        }
+
+        self.sprite_count = count;
+        self.layout_sprites();
+    }
+
+    // Lay the pixels of the selected sprites out over the scanline, so rendering only looks them up
+    fn layout_sprites(&mut self) {
+        self.sprite_line = [0; SCREEN_WIDTH];
+        for sprite in &self.sprites[..self.sprite_count] {
+            for offset in 0..8 {
+                let x = usize::from(sprite.x) + offset;
+                let colour = (sprite.pattern >> ((7 - offset) * 4)) & 0x0F;
+                // Earlier sprites win, transparent pixels let the ones behind them through
+                let is_opaque = colour & 0x03 != 0;
+                if x >= SCREEN_WIDTH || !is_opaque || self.sprite_line[x] != 0 {
+                    continue;
+                }
+
+                // The pattern only ever keeps 4 bits
+                #[allow(clippy::cast_possible_truncation)]
+                let mut pixel = SPRITE_LINE_OPAQUE | colour as u8;
+                if sprite.behind_background {
+                    pixel |= SPRITE_LINE_BEHIND_BACKGROUND;
+                }
+                if sprite.is_sprite_zero {
+                    pixel |= SPRITE_LINE_SPRITE_ZERO;
+                }
+                self.sprite_line[x] = pixel;
+            }
+        }
+    }
+
+    fn fetch_sprite_pattern<B: PpuBus + ?Sized>(
+        &self,
+        bus: &mut B,
+        tile: u8,
+        attributes: u8,
+        mut row: u16,
+        height: u16,
+    ) -> u32 {
+        if attributes & SPRITE_FLIP_VERTICAL != 0 {
+            row = height - 1 - row;
+        }
+
+        let address = if height == 8 {
+            let table = if self.ctrl & CTRL_SPRITE_TABLE == 0 {
+                0x0000
+            } else {
+                0x1000
+            };
+            table + u16::from(tile) * 16 + row
+        } else {
+            // 8x16 sprites select the pattern table with the lowest bit of the tile
+            let table = u16::from(tile & 0x01) * 0x1000;
+            let tile = u16::from(tile & 0xFE) + row / 8;
+            table + tile * 16 + row % 8
+        };
+
+        pattern_pixels(
+            bus.read(address),
+            bus.read(address + 8),
+            (attributes & 0x03) << 2,
+            attributes & SPRITE_FLIP_HORIZONTAL != 0,
+        )
+    }
+}
+
+// Pixel of every bit of a byte in the lowest bit of its nibble, leftmost pixel in the highest nibble
+const fn spread_bits(byte: u32) -> u32 {
+    let mut spread = 0;
+    let mut bit = 0;
+    while bit < 8 {
+        spread |= ((byte >> bit) & 0x01) << (bit * 4);
+        bit += 1;
+    }
+    spread
+}
+
+// Only ever evaluated at compile time
+#[allow(clippy::large_stack_arrays)]
+const fn pattern_table() -> [u32; 0x1_0000] {
+    let mut spread = [0; 0x100];
+    let mut byte = 0;
+    while byte < 0x100 {
+        spread[byte as usize] = spread_bits(byte);
+        byte += 1;
+    }
+
+    let mut table = [0; 0x1_0000];
+    let mut index = 0;
+    while index < 0x1_0000 {
+        table[index] = spread[index & 0xFF] | (spread[index >> 8] << 1);
+        index += 1;
+    }
+    table
+}
+
+/// Colours of the 8 pixels of every combination of the two bit planes of a tile row, indexed by the high plane
+/// in the upper byte and the low plane in the lower byte
+///
+/// Decoding a tile row is a single lookup instead of extracting the bits of every pixel, which the background
+/// fetches do on every eighth dot.
+static PATTERN_PIXELS: [u32; 0x1_0000] = pattern_table();
+
+/// Combine the bit planes of a tile row with the palette bits, 4 bits per pixel with the leftmost pixel in the highest bits
+fn pattern_pixels(low: u8, high: u8, palette_bits: u8, flip_horizontal: bool) -> u32 {
+    let (low, high) = if flip_horizontal {
+        (low.reverse_bits(), high.reverse_bits())
+    } else {
+        (low, high)
+    };
+
+    // Copy the palette bits into every nibble
+    PATTERN_PIXELS[usize::from(u16::from_le_bytes([low, high]))]
+        | (u32::from(palette_bits) * 0x1111_1111)
+}
+
+/// Index into the palette RAM; the backdrop entries of the sprite palettes mirror the ones of the background palettes
+fn palette_index(address: u16) -> usize {
+    let index = usize::from(address & 0x1F);
+    if index >= 0x10 && index % 4 == 0 {
+        index - 0x10
+    } else {
+        index
+    }
+}
+
+impl Savestate for Ppu {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.ctrl);
+        writer.write_u8(self.mask);
+        writer.write_u8(self.status);
+        writer.write_u8(self.oam_address);
+        writer.write_bytes(&self.oam);
+        writer.write_bytes(&self.palette);
+
+        writer.write_u16(self.v);
+        writer.write_u16(self.t);
+        writer.write_u8(self.fine_x);
+        writer.write_bool(self.write_toggle);
+        writer.write_u8(self.read_buffer);
+        writer.write_u8(self.open_bus);
+
+        writer.write_u16(self.scanline);
+        writer.write_u16(self.dot);
+        writer.write_u64(self.frame);
+        writer.write_bool(self.nmi_line);
+        writer.write_bool(self.nmi_pending);
+
+        writer.write_u8(self.nametable_byte);
+        writer.write_u8(self.attribute_bits);
+        writer.write_u8(self.pattern_low);
+        writer.write_u8(self.pattern_high);
+        writer.write_u64(self.tile_data);
+
+        // At most eight sprites fit on a scanline
+        #[allow(clippy::cast_possible_truncation)]
+        writer.write_u8(self.sprite_count as u8);
+        for sprite in &self.sprites {
+            writer.write_u32(sprite.pattern);
+            writer.write_u8(sprite.x);
+            writer.write_bool(sprite.behind_background);
+            writer.write_bool(sprite.is_sprite_zero);
+        }
+
+        writer.write_bytes(&self.framebuffer);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), nes_state::Error> {
+        self.ctrl = reader.read_u8()?;
+        self.mask = reader.read_u8()?;
+        self.status = reader.read_u8()?;
+        self.oam_address = reader.read_u8()?;
+        reader.read_bytes_into(&mut self.oam)?;
+        reader.read_bytes_into(&mut self.palette)?;
+
+        self.v = reader.read_u16()?;
+        self.t = reader.read_u16()?;
+        self.fine_x = reader.read_u8()?;
+        self.write_toggle = reader.read_bool()?;
+        self.read_buffer = reader.read_u8()?;
+        self.open_bus = reader.read_u8()?;
+
+        self.scanline = reader.read_u16()?;
+        self.dot = reader.read_u16()?;
+        if self.scanline >= self.scanlines_per_frame() || self.dot >= DOTS_PER_SCANLINE {
+            return Err(nes_state::Error::InvalidValue);
+        }
+        self.frame = reader.read_u64()?;
+        self.nmi_line = reader.read_bool()?;
+        self.nmi_pending = reader.read_bool()?;
+
+        self.nametable_byte = reader.read_u8()?;
+        self.attribute_bits = reader.read_u8()?;
+        self.pattern_low = reader.read_u8()?;
+        self.pattern_high = reader.read_u8()?;
+        self.tile_data = reader.read_u64()?;
+
+        self.sprite_count = usize::from(reader.read_u8()?);
+        if self.sprite_count > MAX_SPRITES_PER_SCANLINE {
+            return Err(nes_state::Error::InvalidValue);
+        }
+        for sprite in &mut self.sprites {
+            sprite.pattern = reader.read_u32()?;
+            sprite.x = reader.read_u8()?;
+            sprite.behind_background = reader.read_bool()?;
+            sprite.is_sprite_zero = reader.read_bool()?;
+        }
+        self.layout_sprites();
+
+        reader.read_bytes_into(&mut self.framebuffer)
+    }
+}
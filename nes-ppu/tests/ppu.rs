@@ -0,0 +1,163 @@
+use nes_ppu::{Ppu, PpuBus, DOTS_PER_SCANLINE, SCANLINES_PER_FRAME};
+
+const PPUCTRL: u16 = 0x2000;
+const PPUMASK: u16 = 0x2001;
+const PPUSTATUS: u16 = 0x2002;
+const OAMADDR: u16 = 0x2003;
+const OAMDATA: u16 = 0x2004;
+const PPUADDR: u16 = 0x2006;
+const PPUDATA: u16 = 0x2007;
+
+const DOTS_PER_FRAME: u32 = DOTS_PER_SCANLINE as u32 * SCANLINES_PER_FRAME as u32;
+
+// Pattern tables and one nametable, which is mirrored into all four
+struct Vram {
+    chr: [u8; 0x2000],
+    nametable: [u8; 0x400],
+}
+
+impl Vram {
+    fn new() -> Self {
+        let mut chr = [0; 0x2000];
+        // Tile 1 is filled with colour 1
+        chr[0x10..0x18].fill(0xFF);
+
+        Self {
+            chr,
+            nametable: [0; 0x400],
+        }
+    }
+}
+
+impl PpuBus for Vram {
+    fn read(&mut self, address: u16) -> u8 {
+        match address {
+            0x0000..=0x1FFF => self.chr[usize::from(address)],
+            _ => self.nametable[usize::from(address) % 0x400],
+        }
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => self.chr[usize::from(address)] = value,
+            _ => self.nametable[usize::from(address) % 0x400] = value,
+        }
+    }
+}
+
+fn set_address(ppu: &mut Ppu, vram: &mut Vram, address: u16) {
+    let [low, high] = address.to_le_bytes();
+    ppu.write_register(vram, PPUADDR, high);
+    ppu.write_register(vram, PPUADDR, low);
+}
+
+fn run(ppu: &mut Ppu, vram: &mut Vram, dots: u32) {
+    for _ in 0..dots {
+        ppu.tick(vram);
+    }
+}
+
+#[test]
+fn vblank_nmi() {
+    let (mut ppu, mut vram) = (Ppu::new(), Vram::new());
+    ppu.write_register(&mut vram, PPUCTRL, 0x80);
+
+    // The PPU starts right before the VBlank
+    run(&mut ppu, &mut vram, 2);
+    assert_eq!((ppu.scanline(), ppu.dot()), (241, 1));
+    assert!(ppu.take_nmi());
+    assert!(!ppu.take_nmi());
+
+    // Reading the status clears the VBlank flag
+    assert_eq!(ppu.read_register(&mut vram, PPUSTATUS) & 0x80, 0x80);
+    assert_eq!(ppu.read_register(&mut vram, PPUSTATUS) & 0x80, 0);
+}
+
+#[test]
+fn frame_timing() {
+    let (mut ppu, mut vram) = (Ppu::new(), Vram::new());
+    run(&mut ppu, &mut vram, 2);
+
+    run(&mut ppu, &mut vram, DOTS_PER_FRAME);
+    assert_eq!((ppu.frame(), ppu.scanline(), ppu.dot()), (1, 241, 1));
+
+    // Odd frames are one dot shorter while rendering
+    ppu.write_register(&mut vram, PPUMASK, 0x08);
+    run(&mut ppu, &mut vram, DOTS_PER_FRAME - 1);
+    assert_eq!((ppu.frame(), ppu.scanline(), ppu.dot()), (2, 241, 1));
+}
+
+#[test]
+fn vram_access() {
+    let (mut ppu, mut vram) = (Ppu::new(), Vram::new());
+
+    set_address(&mut ppu, &mut vram, 0x2000);
+    ppu.write_register(&mut vram, PPUDATA, 0x12);
+    ppu.write_register(&mut vram, PPUDATA, 0x34);
+    assert_eq!(vram.nametable[..2], [0x12, 0x34]);
+
+    // Reads are delayed by one through the read buffer
+    set_address(&mut ppu, &mut vram, 0x2000);
+    ppu.read_register(&mut vram, PPUDATA);
+    assert_eq!(ppu.read_register(&mut vram, PPUDATA), 0x12);
+    assert_eq!(ppu.read_register(&mut vram, PPUDATA), 0x34);
+
+    // Increment by 32 goes down one row of tiles
+    ppu.write_register(&mut vram, PPUCTRL, 0x04);
+    set_address(&mut ppu, &mut vram, 0x2100);
+    ppu.write_register(&mut vram, PPUDATA, 0x56);
+    ppu.write_register(&mut vram, PPUDATA, 0x78);
+    assert_eq!(vram.nametable[0x100], 0x56);
+    assert_eq!(vram.nametable[0x120], 0x78);
+}
+
+#[test]
+fn palette_ram() {
+    let (mut ppu, mut vram) = (Ppu::new(), Vram::new());
+
+    set_address(&mut ppu, &mut vram, 0x3F10);
+    ppu.write_register(&mut vram, PPUDATA, 0x0F);
+    ppu.write_register(&mut vram, PPUDATA, 0xD6);
+
+    // $3F10 mirrors the backdrop colour at $3F00 and only six bits are stored
+    assert_eq!(ppu.palette_ram()[0x00], 0x0F);
+    assert_eq!(ppu.palette_ram()[0x11], 0x16);
+
+    // Palette reads aren't buffered
+    set_address(&mut ppu, &mut vram, 0x3F00);
+    assert_eq!(ppu.read_register(&mut vram, PPUDATA), 0x0F);
+}
+
+#[test]
+fn oam_access() {
+    let (mut ppu, mut vram) = (Ppu::new(), Vram::new());
+
+    ppu.write_register(&mut vram, OAMADDR, 0xFE);
+    for value in 1..=3 {
+        ppu.write_register(&mut vram, OAMDATA, value);
+    }
+
+    // The address wraps around
+    assert_eq!(ppu.oam()[0xFE..], [1, 2]);
+    assert_eq!(ppu.oam()[0], 3);
+}
+
+#[test]
+fn render_background_and_sprite_zero() {
+    let (mut ppu, mut vram) = (Ppu::new(), Vram::new());
+    vram.nametable[..0x3C0].fill(1);
+
+    set_address(&mut ppu, &mut vram, 0x3F00);
+    for colour in [0x0F, 0x16] {
+        ppu.write_register(&mut vram, PPUDATA, colour);
+    }
+    // Sprite zero at (16, 16) behind the background, drawn one line below its Y coordinate
+    ppu.oam_mut()[..4].copy_from_slice(&[15, 1, 0x20, 16]);
+
+    // Background and sprites including the leftmost column
+    ppu.write_register(&mut vram, PPUMASK, 0x1E);
+    run(&mut ppu, &mut vram, 2 + DOTS_PER_FRAME);
+
+    assert!(ppu.framebuffer().iter().all(|colour| *colour == 0x16));
+    assert_eq!(ppu.read_register(&mut vram, PPUSTATUS) & 0xC0, 0xC0);
+}
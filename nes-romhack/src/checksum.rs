@@ -0,0 +1,346 @@
+//!
+//! Locating and fixing of checksums stored in the PRG ROM
+//!
+//! Some games verify sums of their PRG banks and refuse to run after being patched.
+//! Checksums known for a title can be described with [`Checksum`] directly;
+//! [`locate`] finds candidates by looking for stored sums that the code reads.
+//!
+//! [Nintendo header documentation](https://www.nesdev.org/wiki/Nintendo_header)
+//!
+
+use {
+    crate::Error,
+    alloc::{collections::BTreeSet, vec::Vec},
+    core::{convert::TryFrom, ops::Range},
+};
+
+// Addressing modes reading an absolute address: LDA, LDX, LDY, CMP, CPX, CPY, EOR, ADC, SBC and the indexed LDA/CMP
+const ABSOLUTE_READS: [u8; 13] = [
+    0xAD, 0xAE, 0xAC, 0xCD, 0xEC, 0xCC, 0x4D, 0x6D, 0xED, 0xBD, 0xB9, 0xDD, 0xD9,
+];
+
+const PRG_ROM_START: usize = 0x8000;
+const ADDRESS_SPACE_SIZE: usize = 0x1_0000;
+
+// Offsets of the Nintendo header fields from the end of the PRG ROM
+const FOOTER_SIZE: usize = 0x20;
+const FOOTER_PRG_CHECKSUM: usize = 0x10;
+const FOOTER_CHR_CHECKSUM: usize = 0x0E;
+const FOOTER_HEADER_CHECKSUM: usize = 0x07;
+
+/// How the bytes are summed up
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    /// 8-bit sum, stored in a single byte
+    Sum8,
+    /// 16-bit sum of the bytes, stored in a word
+    Sum16 { big_endian: bool },
+}
+
+/// Checksum stored in the data
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Checksum {
+    pub algorithm: Algorithm,
+    /// Summed bytes; the stored checksum is skipped if it's inside of the range
+    pub range: Range<usize>,
+    /// Offset of the stored checksum
+    pub location: usize,
+    /// The stored value is the negated sum, so adding it to the sum results in zero
+    pub complement: bool,
+}
+
+fn sum(bytes: &[u8]) -> u16 {
+    bytes
+        .iter()
+        .fold(0_u16, |sum, byte| sum.wrapping_add(u16::from(*byte)))
+}
+
+impl Checksum {
+    /// Amount of bytes of the stored value
+    #[must_use]
+    pub fn size(&self) -> usize {
+        match self.algorithm {
+            Algorithm::Sum8 => 1,
+            Algorithm::Sum16 { .. } => 2,
+        }
+    }
+
+    fn location(&self) -> Range<usize> {
+        self.location..self.location + self.size()
+    }
+
+    fn check_bounds(&self, data: &[u8]) -> Result<(), Error> {
+        let end = self.range.end.max(self.location().end);
+        if end > data.len() || self.range.start > self.range.end {
+            return Err(Error::OutOfRange(end));
+        }
+
+        Ok(())
+    }
+
+    /// Checksum the data should have stored
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::OutOfRange`] if the range or the location are outside of the data
+    pub fn compute(&self, data: &[u8]) -> Result<u16, Error> {
+        self.check_bounds(data)?;
+
+        let location = self.location();
+        let skipped = location.start.max(self.range.start)..location.end.min(self.range.end);
+        let mut value = sum(&data[self.range.clone()]);
+        if skipped.start < skipped.end {
+            value = value.wrapping_sub(sum(&data[skipped]));
+        }
+
+        if self.algorithm == Algorithm::Sum8 {
+            value &= 0xFF;
+        }
+        if self.complement {
+            value = value.wrapping_neg();
+            if self.algorithm == Algorithm::Sum8 {
+                value &= 0xFF;
+            }
+        }
+
+        Ok(value)
+    }
+
+    /// Checksum stored in the data
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::OutOfRange`] if the location is outside of the data
+    pub fn stored(&self, data: &[u8]) -> Result<u16, Error> {
+        let bytes = data
+            .get(self.location())
+            .ok_or(Error::OutOfRange(self.location().end))?;
+
+        Ok(match self.algorithm {
+            Algorithm::Sum8 => u16::from(bytes[0]),
+            Algorithm::Sum16 { big_endian: true } => u16::from_be_bytes([bytes[0], bytes[1]]),
+            Algorithm::Sum16 { big_endian: false } => u16::from_le_bytes([bytes[0], bytes[1]]),
+        })
+    }
+
+    /// Whether the stored checksum matches the data
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::OutOfRange`] if the range or the location are outside of the data
+    pub fn is_valid(&self, data: &[u8]) -> Result<bool, Error> {
+        Ok(self.compute(data)? == self.stored(data)?)
+    }
+
+    /// Recompute the checksum after editing the data and store it, returning whether it changed
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::OutOfRange`] if the range or the location are outside of the data
+    pub fn fix(&self, data: &mut [u8]) -> Result<bool, Error> {
+        let value = self.compute(data)?;
+        if value == self.stored(data)? {
+            return Ok(false);
+        }
+
+        let location = self.location();
+        match self.algorithm {
+            // Only the low byte is left for 8-bit sums
+            #[allow(clippy::cast_possible_truncation)]
+            Algorithm::Sum8 => data[location.start] = value as u8,
+            Algorithm::Sum16 { big_endian: true } => {
+                data[location].copy_from_slice(&value.to_be_bytes());
+            }
+            Algorithm::Sum16 { big_endian: false } => {
+                data[location].copy_from_slice(&value.to_le_bytes());
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+/// Possible checksum found by [`locate`]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Candidate {
+    pub checksum: Checksum,
+    /// The stored value is read by an instruction, which makes it far more likely to be a checksum
+    pub referenced: bool,
+}
+
+// Operands of every instruction that might read an absolute address
+fn read_addresses(prg_rom: &[u8]) -> BTreeSet<u16> {
+    prg_rom
+        .windows(3)
+        .filter(|instruction| ABSOLUTE_READS.contains(&instruction[0]))
+        .map(|instruction| u16::from_le_bytes([instruction[1], instruction[2]]))
+        .collect()
+}
+
+/// Find words storing the 16-bit sum of their bank, or of the whole PRG ROM
+///
+/// Every bank gets checked for words matching the sum of the rest of the bank, in both byte orders and
+/// negated. Matches happen by chance about twice per 64 KiB, so candidates read by the code are sorted first.
+#[must_use]
+pub fn locate(prg_rom: &[u8], bank_size: usize) -> Vec<Candidate> {
+    let bank_size = bank_size.clamp(1, prg_rom.len().max(1));
+    let reads = read_addresses(prg_rom);
+
+    let mut ranges: Vec<Range<usize>> = (0..prg_rom.len())
+        .step_by(bank_size)
+        .map(|start| start..(start + bank_size).min(prg_rom.len()))
+        .collect();
+    if ranges.len() > 1 {
+        ranges.push(0..prg_rom.len());
+    }
+
+    let mut candidates = Vec::new();
+    for range in ranges {
+        let total = sum(&prg_rom[range.clone()]);
+
+        for location in range.start..range.end.saturating_sub(1) {
+            let word = [prg_rom[location], prg_rom[location + 1]];
+            let rest = total.wrapping_sub(sum(&word));
+
+            for big_endian in [false, true] {
+                let stored = if big_endian {
+                    u16::from_be_bytes(word)
+                } else {
+                    u16::from_le_bytes(word)
+                };
+
+                for complement in [false, true] {
+                    let expected = if complement {
+                        rest.wrapping_neg()
+                    } else {
+                        rest
+                    };
+                    if stored != expected {
+                        continue;
+                    }
+
+                    // The bank of the checksum could be mapped to any window of its size
+                    let bank_offset = location % bank_size;
+                    let referenced = (PRG_ROM_START..ADDRESS_SPACE_SIZE)
+                        .step_by(bank_size)
+                        .map(|base| base + bank_offset)
+                        .filter_map(|address| u16::try_from(address).ok())
+                        .any(|address| {
+                            reads.contains(&address) || reads.contains(&address.wrapping_add(1))
+                        });
+
+                    candidates.push(Candidate {
+                        checksum: Checksum {
+                            algorithm: Algorithm::Sum16 { big_endian },
+                            range: range.clone(),
+                            location,
+                            complement,
+                        },
+                        referenced,
+                    });
+                }
+            }
+        }
+    }
+
+    candidates.sort_by_key(|candidate| (!candidate.referenced, candidate.checksum.location));
+    candidates
+}
+
+/// Header at `$FFE0`-`$FFF9` of some licensed games, containing sums of the PRG and CHR ROM
+///
+/// The consoles never verify the sums, but they're part of the cartridge as Nintendo produced it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NintendoHeader {
+    /// Offset of the header into the PRG ROM
+    pub offset: usize,
+}
+
+impl NintendoHeader {
+    /// Find the header at the end of the PRG ROM, if the checksum of the header is valid
+    #[must_use]
+    pub fn find(prg_rom: &[u8]) -> Option<Self> {
+        let offset = prg_rom.len().checked_sub(FOOTER_SIZE)?;
+        let header = Self { offset };
+
+        // Empty or filled space has a valid checksum as well
+        let fields = &prg_rom[header.header_checksum().range];
+        if fields.iter().all(|byte| *byte == fields[0]) {
+            return None;
+        }
+
+        header
+            .header_checksum()
+            .is_valid(prg_rom)
+            .unwrap_or(false)
+            .then_some(header)
+    }
+
+    /// Big-endian sum of the PRG ROM, without the sum itself
+    #[must_use]
+    pub fn prg_checksum(&self) -> Checksum {
+        let end = self.offset + FOOTER_SIZE;
+        Checksum {
+            algorithm: Algorithm::Sum16 { big_endian: true },
+            range: 0..end,
+            location: end - FOOTER_PRG_CHECKSUM,
+            complement: false,
+        }
+    }
+
+    /// Sum of the CHR ROM stored in the PRG ROM and the actual sum of the CHR ROM
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::OutOfRange`] if the header is outside of the PRG ROM
+    pub fn chr_checksum(&self, prg_rom: &[u8], chr_rom: &[u8]) -> Result<(u16, u16), Error> {
+        let location = self.offset + FOOTER_SIZE - FOOTER_CHR_CHECKSUM;
+        let stored = prg_rom
+            .get(location..location + 2)
+            .ok_or(Error::OutOfRange(location + 2))?;
+
+        Ok((u16::from_be_bytes([stored[0], stored[1]]), sum(chr_rom)))
+    }
+
+    /// 8-bit checksum making the fields from the CHR sum on add up to zero
+    #[must_use]
+    pub fn header_checksum(&self) -> Checksum {
+        let end = self.offset + FOOTER_SIZE;
+        Checksum {
+            algorithm: Algorithm::Sum8,
+            range: end - FOOTER_CHR_CHECKSUM..end - FOOTER_HEADER_CHECKSUM + 1,
+            location: end - FOOTER_HEADER_CHECKSUM,
+            complement: true,
+        }
+    }
+
+    /// Whether every sum matches the data
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::OutOfRange`] if the header is outside of the PRG ROM
+    pub fn is_valid(&self, prg_rom: &[u8], chr_rom: &[u8]) -> Result<bool, Error> {
+        let (stored, chr_sum) = self.chr_checksum(prg_rom, chr_rom)?;
+
+        Ok(stored == chr_sum
+            && self.header_checksum().is_valid(prg_rom)?
+            && self.prg_checksum().is_valid(prg_rom)?)
+    }
+
+    /// Recompute every sum after editing the ROM, returning whether anything changed
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::OutOfRange`] if the header is outside of the PRG ROM
+    pub fn fix(&self, prg_rom: &mut [u8], chr_rom: &[u8]) -> Result<bool, Error> {
+        let (stored, chr_sum) = self.chr_checksum(prg_rom, chr_rom)?;
+        let location = self.offset + FOOTER_SIZE - FOOTER_CHR_CHECKSUM;
+        prg_rom[location..location + 2].copy_from_slice(&chr_sum.to_be_bytes());
+
+        // The header checksum covers the CHR sum and the PRG sum covers both
+        let header_changed = self.header_checksum().fix(prg_rom)?;
+        let prg_changed = self.prg_checksum().fix(prg_rom)?;
+
+        Ok(stored != chr_sum || header_changed || prg_changed)
+    }
+}
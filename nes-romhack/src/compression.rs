@@ -0,0 +1,365 @@
+//!
+//! Decompression of the schemes commonly used by NES games
+//!
+//! Games store graphics, nametables and level data compressed in the PRG ROM,
+//! so it can't be found by looking at the CHR ROM or the raw PRG ROM.
+//!
+
+use {crate::Error, alloc::vec::Vec};
+
+const KONAMI_END: u8 = 0xFF;
+const KONAMI_LITERAL: u8 = 0x80;
+
+const HAL_END: u8 = 0xFF;
+const HAL_LONG_COMMAND: u8 = 0xE0;
+
+const LZSS_WINDOW_SIZE: usize = 0x1000;
+const LZSS_MAX_LENGTH: usize = 18;
+// References shorter than this are stored as literals, so the lengths start above it
+const LZSS_THRESHOLD: usize = 2;
+const LZSS_FILL: u8 = b' ';
+
+// Outputs smaller than a single tile aren't worth reporting
+const MIN_DETECTED_SIZE: usize = 16;
+const TILE_SIZE: usize = 16;
+const NAMETABLE_SIZE: usize = 0x400;
+const NAMETABLE_TILES_SIZE: usize = 0x3C0;
+
+/// Compression scheme
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Scheme {
+    /// Run-length encoding of Konami games
+    ///
+    /// `$01`-`$80` repeat the next byte as often, `$81`-`$FE` are followed by as many literal bytes
+    /// (minus `$80`) and `$FF` ends the data.
+    KonamiRle,
+    /// Pairs of a count and a byte to repeat, ended by a count of zero
+    RlePairs,
+    /// Apple's `PackBits`, which is also used by a bunch of NES games
+    ///
+    /// `$00`-`$7F` are followed by as many literal bytes (plus one), `$81`-`$FF` repeat the next byte
+    /// 257 minus the value times and `$80` is skipped. The data doesn't have an end marker.
+    PackBits,
+    /// LZ scheme of HAL Laboratory games, like Kirby's Adventure
+    ///
+    /// Besides literals and back references it has commands for runs of bytes, runs of words and
+    /// increasing bytes; back references can be bit-reversed or read backwards. `$FF` ends the data.
+    HalLz,
+    /// LZSS with a 4 KiB window as published by Haruhiko Okumura, which many games use as is
+    ///
+    /// The window starts filled with spaces. The data doesn't have an end marker.
+    Lzss,
+}
+
+impl Scheme {
+    pub const ALL: [Self; 5] = [
+        Self::KonamiRle,
+        Self::RlePairs,
+        Self::PackBits,
+        Self::HalLz,
+        Self::Lzss,
+    ];
+
+    /// Whether the data ends with a marker; data without one is decompressed until the input or the limit ends
+    #[must_use]
+    pub fn is_terminated(self) -> bool {
+        matches!(self, Self::KonamiRle | Self::RlePairs | Self::HalLz)
+    }
+}
+
+/// Decompressed data
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Decompressed {
+    pub data: Vec<u8>,
+    /// Amount of compressed bytes read, including the end marker
+    pub consumed: usize,
+}
+
+/// Scheme that decompressed the data at an offset into plausible output
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Candidate {
+    pub scheme: Scheme,
+    pub decompressed: Decompressed,
+}
+
+impl Candidate {
+    /// Whether the output has the size of whole tiles or a nametable, which graphics usually have
+    #[must_use]
+    pub fn has_graphics_size(&self) -> bool {
+        let size = self.decompressed.data.len();
+        size.is_multiple_of(TILE_SIZE) || size == NAMETABLE_SIZE || size == NAMETABLE_TILES_SIZE
+    }
+
+    /// Ratio of the output size to the compressed size
+    #[must_use]
+    pub fn ratio(&self) -> f64 {
+        // Both sizes are far below the precision of a f64
+        #[allow(clippy::cast_precision_loss)]
+        let ratio = self.decompressed.data.len() as f64 / self.decompressed.consumed as f64;
+        ratio
+    }
+}
+
+struct Decoder<'a> {
+    input: &'a [u8],
+    position: usize,
+    output: Vec<u8>,
+    limit: usize,
+}
+
+impl Decoder<'_> {
+    fn is_empty(&self) -> bool {
+        self.position >= self.input.len()
+    }
+
+    fn byte(&mut self) -> Result<u8, Error> {
+        let byte = *self.input.get(self.position).ok_or(Error::UnexpectedEof)?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn push(&mut self, byte: u8) -> Result<(), Error> {
+        if self.output.len() >= self.limit {
+            return Err(Error::TooLarge(self.limit));
+        }
+
+        self.output.push(byte);
+        Ok(())
+    }
+
+    fn is_full(&self) -> bool {
+        self.output.len() >= self.limit
+    }
+
+    fn finish(self) -> Decompressed {
+        Decompressed {
+            data: self.output,
+            consumed: self.position,
+        }
+    }
+}
+
+/// Decompress the data at the start of the input, producing at most `limit` bytes
+///
+/// Schemes without an end marker stop at the limit or the end of the input.
+///
+/// # Errors
+///
+/// Returns an error if the input ends in the middle of a command, a back reference points outside of the
+/// output or a terminated scheme produces more than `limit` bytes
+pub fn decompress(scheme: Scheme, input: &[u8], limit: usize) -> Result<Decompressed, Error> {
+    let mut decoder = Decoder {
+        input,
+        position: 0,
+        output: Vec::new(),
+        limit,
+    };
+
+    match scheme {
+        Scheme::KonamiRle => konami_rle(&mut decoder)?,
+        Scheme::RlePairs => rle_pairs(&mut decoder)?,
+        Scheme::PackBits => pack_bits(&mut decoder)?,
+        Scheme::HalLz => hal_lz(&mut decoder)?,
+        Scheme::Lzss => lzss(&mut decoder)?,
+    }
+
+    Ok(decoder.finish())
+}
+
+fn konami_rle(decoder: &mut Decoder<'_>) -> Result<(), Error> {
+    loop {
+        match decoder.byte()? {
+            KONAMI_END => return Ok(()),
+            count if count > KONAMI_LITERAL => {
+                for _ in 0..count - KONAMI_LITERAL {
+                    let byte = decoder.byte()?;
+                    decoder.push(byte)?;
+                }
+            }
+            count => {
+                let byte = decoder.byte()?;
+                for _ in 0..count {
+                    decoder.push(byte)?;
+                }
+            }
+        }
+    }
+}
+
+fn rle_pairs(decoder: &mut Decoder<'_>) -> Result<(), Error> {
+    loop {
+        let count = decoder.byte()?;
+        if count == 0 {
+            return Ok(());
+        }
+
+        let byte = decoder.byte()?;
+        for _ in 0..count {
+            decoder.push(byte)?;
+        }
+    }
+}
+
+fn pack_bits(decoder: &mut Decoder<'_>) -> Result<(), Error> {
+    while !decoder.is_empty() && !decoder.is_full() {
+        let header = decoder.byte()?;
+        match header {
+            0x80 => (),
+            0x00..=0x7F => {
+                for _ in 0..=header {
+                    let byte = decoder.byte()?;
+                    decoder.push(byte)?;
+                }
+            }
+            _ => {
+                let byte = decoder.byte()?;
+                for _ in 0..=header.wrapping_neg() {
+                    decoder.push(byte)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn hal_lz(decoder: &mut Decoder<'_>) -> Result<(), Error> {
+    loop {
+        let header = decoder.byte()?;
+        if header == HAL_END {
+            return Ok(());
+        }
+
+        let (command, length) = if header & HAL_LONG_COMMAND == HAL_LONG_COMMAND {
+            let low = decoder.byte()?;
+            let length = (usize::from(header & 0x03) << 8) | usize::from(low);
+            ((header >> 2) & 0x07, length + 1)
+        } else {
+            (header >> 5, usize::from(header & 0x1F) + 1)
+        };
+
+        match command {
+            0 => {
+                for _ in 0..length {
+                    let byte = decoder.byte()?;
+                    decoder.push(byte)?;
+                }
+            }
+            1 => {
+                let byte = decoder.byte()?;
+                for _ in 0..length {
+                    decoder.push(byte)?;
+                }
+            }
+            2 => {
+                let first = decoder.byte()?;
+                let second = decoder.byte()?;
+                for _ in 0..length {
+                    decoder.push(first)?;
+                    decoder.push(second)?;
+                }
+            }
+            3 => {
+                let mut byte = decoder.byte()?;
+                for _ in 0..length {
+                    decoder.push(byte)?;
+                    byte = byte.wrapping_add(1);
+                }
+            }
+            _ => {
+                let offset = (usize::from(decoder.byte()?) << 8) | usize::from(decoder.byte()?);
+                for index in 0..length {
+                    let source = match command {
+                        // Read backwards
+                        6 => offset.checked_sub(index),
+                        _ => Some(offset + index),
+                    };
+                    let byte = source
+                        .and_then(|source| decoder.output.get(source))
+                        .copied()
+                        .ok_or(Error::InvalidReference(offset))?;
+
+                    // Bit-reversed copy
+                    let byte = if command == 5 {
+                        byte.reverse_bits()
+                    } else {
+                        byte
+                    };
+                    decoder.push(byte)?;
+                }
+            }
+        }
+    }
+}
+
+fn lzss(decoder: &mut Decoder<'_>) -> Result<(), Error> {
+    let mut window = [LZSS_FILL; LZSS_WINDOW_SIZE];
+    let mut position = LZSS_WINDOW_SIZE - LZSS_MAX_LENGTH;
+    let mut write = |decoder: &mut Decoder<'_>, window: &mut [u8], byte: u8| {
+        window[position] = byte;
+        position = (position + 1) % LZSS_WINDOW_SIZE;
+        decoder.push(byte)
+    };
+
+    while !decoder.is_empty() && !decoder.is_full() {
+        // Every bit tells whether the next item is a literal (set) or a reference (clear)
+        let flags = decoder.byte()?;
+        for bit in 0..8 {
+            if decoder.is_empty() || decoder.is_full() {
+                break;
+            }
+
+            if flags & (1 << bit) != 0 {
+                let byte = decoder.byte()?;
+                write(decoder, &mut window, byte)?;
+            } else {
+                let low = decoder.byte()?;
+                let high = decoder.byte()?;
+                let offset = usize::from(low) | (usize::from(high & 0xF0) << 4);
+                let length = usize::from(high & 0x0F) + LZSS_THRESHOLD + 1;
+
+                for index in 0..length {
+                    if decoder.is_full() {
+                        break;
+                    }
+
+                    let byte = window[(offset + index) % LZSS_WINDOW_SIZE];
+                    write(decoder, &mut window, byte)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Try every scheme with an end marker at the start of the input
+///
+/// Only outputs of at least one tile which are larger than the compressed data are kept. The candidates are
+/// sorted by plausibility, preferring outputs with the size of graphics and then higher compression ratios.
+/// Schemes without an end marker decompress almost any data, so they aren't tried.
+#[must_use]
+pub fn detect(input: &[u8], limit: usize) -> Vec<Candidate> {
+    let mut candidates: Vec<Candidate> = Scheme::ALL
+        .iter()
+        .filter(|scheme| scheme.is_terminated())
+        .filter_map(|scheme| {
+            let decompressed = decompress(*scheme, input, limit).ok()?;
+            let plausible = decompressed.data.len() >= MIN_DETECTED_SIZE
+                && decompressed.data.len() > decompressed.consumed;
+
+            plausible.then_some(Candidate {
+                scheme: *scheme,
+                decompressed,
+            })
+        })
+        .collect();
+
+    candidates.sort_by(|a, b| {
+        b.has_graphics_size()
+            .cmp(&a.has_graphics_size())
+            .then(b.ratio().total_cmp(&a.ratio()))
+    });
+
+    candidates
+}
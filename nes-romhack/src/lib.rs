@@ -0,0 +1,44 @@
+#![no_std]
+#![warn(clippy::all, clippy::pedantic)]
+
+//!
+//! Building blocks for ROM hacking, like decompressing the data of games
+//!
+
+extern crate alloc;
+
+use core::fmt;
+
+pub mod checksum;
+pub mod compression;
+pub mod multicart;
+pub mod pointers;
+pub mod text;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The data ended in the middle of a command
+    UnexpectedEof,
+    /// The output grew past the given limit
+    TooLarge(usize),
+    /// A back reference points outside of the output
+    InvalidReference(usize),
+    /// The line of a table file isn't an entry
+    InvalidTable(usize),
+    /// The checked range reaches past the end of the data
+    OutOfRange(usize),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEof => f.write_str("Unexpected end of the data"),
+            Self::TooLarge(limit) => write!(f, "The output is larger than {limit} bytes"),
+            Self::InvalidReference(offset) => {
+                write!(f, "Reference to offset {offset} outside of the output")
+            }
+            Self::InvalidTable(line) => write!(f, "Invalid table entry in line {line}"),
+            Self::OutOfRange(end) => write!(f, "Offset {end} is outside of the data"),
+        }
+    }
+}
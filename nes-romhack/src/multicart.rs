@@ -0,0 +1,200 @@
+//!
+//! Splitting of multicarts into their individual games
+//!
+//! Most N-in-1 cartridges consist of NROM games of 16 or 32 KiB, each mapped as a whole by the multicart mapper.
+//! Every game ends with its own interrupt vectors, so the 16 KiB banks of the PRG ROM ending with plausible vectors
+//! mark the ends of the games.
+//!
+
+use {
+    alloc::vec::Vec,
+    core::ops::Range,
+    ines_parser::{Header, Ines, VramLayout},
+};
+
+const BANK_SIZE: usize = 0x4000;
+const CHR_BANK_SIZE: usize = 0x2000;
+const NROM_SIZE: usize = 0x8000;
+const VECTORS_SIZE: usize = 6;
+
+const NROM: u8 = 0;
+const UXROM: u8 = 2;
+
+// Instructions games commonly start their reset handler with: SEI, CLD, LDA/LDX/LDY immediate, JMP and JSR
+const RESET_OPCODES: [u8; 7] = [0x78, 0xD8, 0xA9, 0xA2, 0xA0, 0x4C, 0x20];
+
+/// Mappers of the common pirate multicarts which map whole NROM games
+pub const MULTICART_MAPPERS: [u8; 18] = [
+    15, 41, 46, 57, 58, 60, 61, 62, 200, 201, 202, 203, 212, 213, 225, 226, 227, 231,
+];
+
+/// Game found inside of the multicart
+#[derive(Clone, Debug)]
+pub struct Game {
+    /// Section of the PRG ROM
+    pub prg_rom: Range<usize>,
+    /// Section of the CHR ROM, if one could be assigned to the game
+    pub chr_rom: Option<Range<usize>>,
+    pub nmi: u16,
+    pub reset: u16,
+    pub irq: u16,
+    /// Index of an earlier game with the same ROM data
+    pub duplicate_of: Option<usize>,
+    /// Header the game gets exported with
+    ///
+    /// The mirroring can't be known without running the game, so it's left at horizontal mirroring
+    pub header: Header,
+}
+
+/// Games found inside of a multicart
+#[derive(Clone, Debug)]
+pub struct Multicart<'a> {
+    prg_rom: &'a [u8],
+    chr_rom: &'a [u8],
+    pub games: Vec<Game>,
+}
+
+// Interrupt vectors at the end of the bank, if the reset handler starts with a plausible instruction
+fn vectors(prg_rom: &[u8], index: usize) -> Option<[u16; 3]> {
+    let bank = prg_rom.get(index * BANK_SIZE..(index + 1) * BANK_SIZE)?;
+    let vectors = &bank[BANK_SIZE - VECTORS_SIZE..];
+    let word = |offset: usize| u16::from_le_bytes([vectors[offset], vectors[offset + 1]]);
+    let (nmi, reset, irq) = (word(0), word(2), word(4));
+
+    if nmi < 0x8000 || reset < 0x8000 {
+        return None;
+    }
+
+    // Handlers below `$C000` are either in the previous bank of a 32 KiB game or in the mirror of a 16 KiB game
+    let offset = usize::from(reset) % BANK_SIZE;
+    let is_plausible = |bank: &[u8]| RESET_OPCODES.contains(&bank[offset]);
+    let handler_bank = index
+        .checked_sub(1)
+        .filter(|_| reset < 0xC000)
+        .and_then(|previous| prg_rom.get(previous * BANK_SIZE..index * BANK_SIZE));
+
+    (is_plausible(bank) || handler_bank.is_some_and(is_plausible)).then_some([nmi, reset, irq])
+}
+
+fn is_padding(bank: &[u8]) -> bool {
+    bank.iter().all(|byte| *byte == bank[0])
+}
+
+impl<'a> Multicart<'a> {
+    /// Find the games inside of the PRG ROM
+    ///
+    /// Banks without vectors belong to the game whose vectors follow them, so games larger than 32 KiB get
+    /// exported as `UxROM` games. The CHR ROM is split into one 8 KiB bank per game if the amount of banks
+    /// matches the amount of games, or assigned by the index of the first PRG ROM bank of a game if there's
+    /// one CHR ROM bank per 16 KiB or 32 KiB of PRG ROM.
+    #[must_use]
+    pub fn analyze(ines: &'a Ines<'_>) -> Self {
+        let prg_rom = &*ines.prg_rom;
+        let chr_rom = ines.chr_rom.as_deref().unwrap_or_default();
+
+        let mut games = Vec::new();
+        let mut first_bank = None;
+        for index in 0..prg_rom.len() / BANK_SIZE {
+            let bank = &prg_rom[index * BANK_SIZE..(index + 1) * BANK_SIZE];
+            if is_padding(bank) {
+                first_bank = None;
+                continue;
+            }
+
+            let Some([nmi, reset, irq]) = vectors(prg_rom, index) else {
+                first_bank.get_or_insert(index);
+                continue;
+            };
+
+            let start = first_bank.take().unwrap_or(index) * BANK_SIZE;
+            let end = (index + 1) * BANK_SIZE;
+            let mapper = if end - start > NROM_SIZE { UXROM } else { NROM };
+
+            games.push(Game {
+                prg_rom: start..end,
+                chr_rom: None,
+                nmi,
+                reset,
+                irq,
+                duplicate_of: None,
+                header: Header::new(end - start, 0, mapper, VramLayout::HorizontalMirroring),
+            });
+        }
+
+        let chr_banks = chr_rom.len() / CHR_BANK_SIZE;
+        let prg_banks = prg_rom.len() / BANK_SIZE;
+        let game_count = games.len();
+        for (number, game) in games.iter_mut().enumerate() {
+            let first_bank = game.prg_rom.start / BANK_SIZE;
+            let chr_bank = if chr_banks == game_count {
+                Some(number)
+            } else if chr_banks == prg_banks {
+                Some(first_bank)
+            } else if chr_banks * 2 == prg_banks {
+                Some(first_bank / 2)
+            } else {
+                None
+            };
+
+            if let Some(chr_bank) = chr_bank.filter(|_| chr_banks > 0) {
+                game.chr_rom = Some(chr_bank * CHR_BANK_SIZE..(chr_bank + 1) * CHR_BANK_SIZE);
+                game.header.chr_rom_size = CHR_BANK_SIZE;
+                game.header.chr_ram_size = 0;
+            }
+        }
+
+        let mut multicart = Self {
+            prg_rom,
+            chr_rom,
+            games,
+        };
+        for index in 0..multicart.games.len() {
+            multicart.games[index].duplicate_of = (0..index).find(|earlier| {
+                multicart.prg_rom_of(*earlier) == multicart.prg_rom_of(index)
+                    && multicart.chr_rom_of(*earlier) == multicart.chr_rom_of(index)
+            });
+        }
+
+        multicart
+    }
+
+    fn prg_rom_of(&self, index: usize) -> &'a [u8] {
+        &self.prg_rom[self.games[index].prg_rom.clone()]
+    }
+
+    fn chr_rom_of(&self, index: usize) -> &'a [u8] {
+        self.games[index]
+            .chr_rom
+            .clone()
+            .map_or(&[], |range| &self.chr_rom[range])
+    }
+
+    /// Games which aren't a copy of an earlier game
+    pub fn unique_games(&self) -> impl Iterator<Item = (usize, &Game)> {
+        self.games
+            .iter()
+            .enumerate()
+            .filter(|(_, game)| game.duplicate_of.is_none())
+    }
+
+    /// Standalone `.nes` file of the game with the index, with its header in front of its PRG and CHR ROM
+    #[must_use]
+    pub fn export(&self, index: usize) -> Option<Vec<u8>> {
+        let game = self.games.get(index)?;
+        let prg_rom = self.prg_rom_of(index);
+        let chr_rom = self.chr_rom_of(index);
+
+        let mut file = Vec::with_capacity(16 + prg_rom.len() + chr_rom.len());
+        file.extend_from_slice(&game.header.to_bytes());
+        file.extend_from_slice(prg_rom);
+        file.extend_from_slice(chr_rom);
+
+        Some(file)
+    }
+}
+
+/// Whether the mapper is used by common multicarts which map whole NROM games
+#[must_use]
+pub fn is_multicart_mapper(mapper_number: u8) -> bool {
+    MULTICART_MAPPERS.contains(&mapper_number)
+}
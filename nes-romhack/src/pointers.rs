@@ -0,0 +1,171 @@
+//!
+//! Scanning of the PRG ROM for pointer tables
+//!
+//! Games reference levels, text and other data through tables of little-endian addresses.
+//! Any sequence of words targeting the mapped PRG ROM is a candidate for such a table.
+//!
+
+use {alloc::vec::Vec, core::ops::RangeInclusive};
+
+// Addresses the PRG ROM is mapped to
+const PRG_ROM_RANGE: RangeInclusive<u16> = 0x8000..=0xFFFF;
+// Largest window of the PRG ROM a mapper can have switched in at once
+const MAX_BANK_SIZE: usize = 0x8000;
+
+const DEFAULT_MIN_ENTRIES: usize = 4;
+
+/// Entry of a pointer table
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Pointer {
+    /// Target address in the CPU address space
+    pub address: u16,
+    /// Offset of the target into the PRG ROM, assuming it's in the same bank as the table
+    pub offset: Option<usize>,
+}
+
+/// Likely pointer table
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PointerTable {
+    /// Offset of the table into the PRG ROM
+    pub offset: usize,
+    pub pointers: Vec<Pointer>,
+}
+
+impl PointerTable {
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.pointers.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.pointers.is_empty()
+    }
+
+    /// Size of the table in bytes
+    #[must_use]
+    pub fn size(&self) -> usize {
+        self.pointers.len() * 2
+    }
+}
+
+/// Scanner for pointer tables
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Scanner {
+    target_range: RangeInclusive<u16>,
+    min_entries: usize,
+    bank_size: Option<usize>,
+}
+
+impl Default for Scanner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scanner {
+    /// Scanner for tables of at least four pointers into `$8000`-`$FFFF`
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            target_range: PRG_ROM_RANGE,
+            min_entries: DEFAULT_MIN_ENTRIES,
+            bank_size: None,
+        }
+    }
+
+    /// Only accept pointers into this range, like the window of a single bank
+    #[must_use]
+    pub fn target_range(mut self, target_range: RangeInclusive<u16>) -> Self {
+        self.target_range = target_range;
+        self
+    }
+
+    /// Minimum amount of consecutive pointers to report a table
+    #[must_use]
+    pub fn min_entries(mut self, min_entries: usize) -> Self {
+        self.min_entries = min_entries.max(1);
+        self
+    }
+
+    /// Size of the banks the mapper switches, used to find the targets in the ROM
+    ///
+    /// Defaults to the size of the PRG ROM for ROMs of up to 32 KiB and 32 KiB otherwise.
+    #[must_use]
+    pub fn bank_size(mut self, bank_size: usize) -> Self {
+        self.bank_size = Some(bank_size);
+        self
+    }
+
+    /// Scan the PRG ROM for tables
+    ///
+    /// Runs of the same word (like the `$FF` filling unused space) aren't reported,
+    /// since at least half of the pointers of a table have to be different.
+    #[must_use]
+    pub fn scan(&self, prg_rom: &[u8]) -> Vec<PointerTable> {
+        let bank_size = self
+            .bank_size
+            .unwrap_or_else(|| prg_rom.len().min(MAX_BANK_SIZE))
+            .max(1);
+
+        let mut tables = Vec::new();
+        let mut offset = 0;
+        while offset + 1 < prg_rom.len() {
+            let mut addresses: Vec<u16> = prg_rom[offset..]
+                .chunks_exact(2)
+                .map(|word| u16::from_le_bytes([word[0], word[1]]))
+                .take_while(|address| self.target_range.contains(address))
+                .collect();
+
+            // Filler before the table gets skipped one word at a time, filler after it is cut off
+            let previous = offset
+                .checked_sub(2)
+                .map(|previous| u16::from_le_bytes([prg_rom[previous], prg_rom[previous + 1]]));
+            let first = addresses.first().copied();
+            if first.is_some() && (addresses.get(1).copied() == first || previous == first) {
+                offset += 2;
+                continue;
+            }
+            let last = addresses.last().copied();
+            let filler = addresses
+                .iter()
+                .rev()
+                .take_while(|address| Some(**address) == last)
+                .count();
+            if filler > 1 {
+                addresses.truncate(addresses.len() - filler);
+            }
+
+            if addresses.len() < self.min_entries || !has_distinct_entries(&addresses) {
+                offset += 1;
+                continue;
+            }
+
+            let bank_start = offset - offset % bank_size;
+            let pointers = addresses
+                .iter()
+                .map(|address| {
+                    let target = bank_start + usize::from(*address) % bank_size;
+                    Pointer {
+                        address: *address,
+                        offset: (target < prg_rom.len()).then_some(target),
+                    }
+                })
+                .collect();
+
+            let table = PointerTable { offset, pointers };
+            offset += table.size();
+            tables.push(table);
+        }
+
+        tables
+    }
+}
+
+fn has_distinct_entries(addresses: &[u16]) -> bool {
+    let mut sorted = addresses.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+
+    sorted.len() * 2 >= addresses.len() && sorted.len() > 1
+}
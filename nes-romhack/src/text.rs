@@ -0,0 +1,442 @@
+//!
+//! Scanning of the PRG ROM for text, using table files
+//!
+//! Games rarely store text as ASCII, so the mapping of bytes to characters gets described by a table file:
+//!
+//! ```text
+//! 0A=A
+//! 0B=B
+//! 5C5D=the
+//! *FE
+//! /FF
+//! ```
+//!
+//! Lines with `*` mark line breaks and lines with `/` mark the end of a string.
+//!
+
+use {
+    crate::Error,
+    alloc::{collections::BTreeMap, string::String, vec::Vec},
+    core::{cmp::Reverse, fmt::Write as _, mem},
+};
+
+const DEFAULT_MIN_LENGTH: usize = 4;
+
+// Frequent trigrams of English text, whose spacing between the letters stays the same in any table keeping the
+// alphabet in order
+const TRIGRAMS: [&[u8; 3]; 47] = [
+    b"THE", b"AND", b"ING", b"ION", b"TIO", b"ENT", b"ATI", b"FOR", b"HER", b"TER", b"HAT", b"THA",
+    b"ERE", b"ATE", b"HIS", b"CON", b"RES", b"VER", b"ONS", b"NCE", b"MEN", b"ITH", b"TED", b"ERS",
+    b"PRO", b"THI", b"WIT", b"ARE", b"NOT", b"IVE", b"WAS", b"ECT", b"REA", b"COM", b"EVE", b"PER",
+    b"INT", b"EST", b"STA", b"CTI", b"IST", b"EAR", b"AIN", b"ONE", b"OUR", b"RAT", b"YOU",
+];
+const ALPHABET_SIZE: u8 = 26;
+// Trigrams found for an alphabet before it's suggested
+const MIN_ENCODING_SCORE: usize = 8;
+// Distances of the lowercase letters from the uppercase ones: directly after them or like in ASCII
+// Capitalized trigrams found before an alphabet is paired with the other case
+const MIN_CAPITALIZED_SCORE: usize = 2;
+const LOWERCASE_DISTANCES: [u8; 2] = [ALPHABET_SIZE, 0x20];
+
+/// Mapping of bytes to characters
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Table {
+    entries: BTreeMap<Vec<u8>, String>,
+    terminators: Vec<Vec<u8>>,
+    longest_entry: usize,
+}
+
+fn parse_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.is_empty() || !hex.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+impl Table {
+    /// Printable ASCII characters
+    #[must_use]
+    pub fn ascii() -> Self {
+        let mut table = Self::default();
+        for byte in 0x20..=0x7E_u8 {
+            table.insert(alloc::vec![byte], char::from(byte).into());
+        }
+
+        table
+    }
+
+    /// Parse a table file
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidTable`] with the line number if a line isn't an entry, line break or end marker
+    pub fn parse(table: &str) -> Result<Self, Error> {
+        let mut parsed = Self::default();
+
+        for (index, line) in table.lines().enumerate() {
+            let line = line.trim_end_matches('\r');
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let invalid = || Error::InvalidTable(index + 1);
+            if let Some(marker) = line.strip_prefix('/') {
+                // End markers may have a text, like "/FF=<end>"
+                let hex = marker.split('=').next().unwrap_or_default();
+                parsed.terminators.push(parse_hex(hex).ok_or_else(invalid)?);
+            } else if let Some(marker) = line.strip_prefix('*') {
+                let hex = marker.split('=').next().unwrap_or_default();
+                parsed.insert(parse_hex(hex).ok_or_else(invalid)?, "\n".into());
+            } else {
+                // The text may contain further equal signs
+                let (hex, text) = line.split_once('=').ok_or_else(invalid)?;
+                parsed.insert(parse_hex(hex).ok_or_else(invalid)?, text.into());
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    /// Map the bytes to the text
+    pub fn insert(&mut self, bytes: Vec<u8>, text: String) {
+        self.longest_entry = self.longest_entry.max(bytes.len());
+        self.entries.insert(bytes, text);
+    }
+
+    /// Write the table in the format of table files, ordered by bytes
+    #[must_use]
+    pub fn to_tbl(&self) -> String {
+        // Writing to a string can't fail
+        fn write_hex(tbl: &mut String, bytes: &[u8]) {
+            for byte in bytes {
+                let _ = write!(tbl, "{byte:02X}");
+            }
+        }
+
+        let mut tbl = String::new();
+        for (bytes, text) in &self.entries {
+            if text == "\n" {
+                tbl.push('*');
+                write_hex(&mut tbl, bytes);
+            } else {
+                write_hex(&mut tbl, bytes);
+                tbl.push('=');
+                tbl.push_str(text);
+            }
+            tbl.push('\n');
+        }
+        for terminator in &self.terminators {
+            tbl.push('/');
+            write_hex(&mut tbl, terminator);
+            tbl.push('\n');
+        }
+
+        tbl
+    }
+
+    /// Byte sequences ending a string
+    #[must_use]
+    pub fn terminators(&self) -> &[Vec<u8>] {
+        &self.terminators
+    }
+
+    /// Longest entry matching the start of the data, with the amount of bytes it covers
+    #[must_use]
+    pub fn lookup(&self, data: &[u8]) -> Option<(&str, usize)> {
+        (1..=self.longest_entry.min(data.len()))
+            .rev()
+            .find_map(|length| {
+                self.entries
+                    .get(&data[..length])
+                    .map(|text| (text.as_str(), length))
+            })
+    }
+
+    /// Decode the data, stopping at the first byte without an entry
+    ///
+    /// Returns the text and the amount of decoded bytes
+    #[must_use]
+    pub fn decode(&self, data: &[u8]) -> (String, usize) {
+        let mut text = String::new();
+        let mut offset = 0;
+        while let Some((entry, length)) = self.lookup(&data[offset..]) {
+            text.push_str(entry);
+            offset += length;
+        }
+
+        (text, offset)
+    }
+
+    fn terminator_at(&self, data: &[u8]) -> Option<usize> {
+        self.terminators
+            .iter()
+            .find(|terminator| data.starts_with(terminator))
+            .map(Vec::len)
+    }
+}
+
+/// String found in the ROM
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Text {
+    pub offset: usize,
+    /// Amount of bytes of the string, without the terminator
+    pub length: usize,
+    pub text: String,
+}
+
+/// Scanner for strings decodable with a table
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Scanner<'a> {
+    table: &'a Table,
+    min_length: usize,
+    terminator: Option<Vec<u8>>,
+}
+
+impl<'a> Scanner<'a> {
+    /// Scanner for strings of at least four characters
+    #[must_use]
+    pub fn new(table: &'a Table) -> Self {
+        Self {
+            table,
+            min_length: DEFAULT_MIN_LENGTH,
+            terminator: None,
+        }
+    }
+
+    /// Minimum amount of characters of a string
+    #[must_use]
+    pub fn min_length(mut self, min_length: usize) -> Self {
+        self.min_length = min_length.max(1);
+        self
+    }
+
+    /// Only report strings followed by this byte sequence, in addition to the end markers of the table
+    #[must_use]
+    pub fn terminator(mut self, terminator: Vec<u8>) -> Self {
+        self.terminator = Some(terminator);
+        self
+    }
+
+    fn requires_terminator(&self) -> bool {
+        self.terminator.is_some() || !self.table.terminators.is_empty()
+    }
+
+    fn terminator_at(&self, data: &[u8]) -> Option<usize> {
+        self.terminator
+            .as_ref()
+            .filter(|terminator| data.starts_with(terminator))
+            .map(Vec::len)
+            .or_else(|| self.table.terminator_at(data))
+    }
+
+    /// Scan the data for strings
+    ///
+    /// If the table has end markers or a terminator was set, only strings ending with one of them are reported.
+    #[must_use]
+    pub fn scan(&self, data: &[u8]) -> Vec<Text> {
+        let mut texts = Vec::new();
+
+        let mut offset = 0;
+        while offset < data.len() {
+            // Terminators take precedence over entries with the same bytes
+            let mut text = String::new();
+            let mut end = offset;
+            while self.terminator_at(&data[end..]).is_none() {
+                match self.table.lookup(&data[end..]) {
+                    Some((entry, length)) => {
+                        text.push_str(entry);
+                        end += length;
+                    }
+                    None => break,
+                }
+            }
+
+            let terminator = self.terminator_at(&data[end..]);
+            let terminated = terminator.is_some() || !self.requires_terminator();
+            if terminated && end > offset && text.chars().count() >= self.min_length {
+                texts.push(Text {
+                    offset,
+                    length: end - offset,
+                    text,
+                });
+                offset = end + terminator.unwrap_or(0);
+            } else {
+                offset += 1;
+            }
+        }
+
+        texts
+    }
+}
+
+/// Likely encoding of the text of a ROM, see [`detect_encodings`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct EncodingGuess {
+    /// Byte of the letter `A`, which the rest of the alphabet follows
+    pub letters: u8,
+    /// Byte of the letter `a`, if the text has lowercase letters
+    pub lowercase: Option<u8>,
+    /// Byte of the digit `0`, guessed from the usual layouts: like ASCII, or directly in front of the letters
+    pub digits: Option<u8>,
+    /// Byte most often found between words
+    pub space: Option<u8>,
+    /// Amount of common English trigrams found with the encoding
+    pub score: usize,
+}
+
+impl EncodingGuess {
+    /// Table with the letters, digits and space of the encoding, as a starting point for a table file
+    #[must_use]
+    pub fn table(&self) -> Table {
+        let mut table = Table::default();
+        let mut insert = |start: u8, characters: core::ops::RangeInclusive<char>| {
+            for (byte, character) in (start..=u8::MAX).zip(characters) {
+                table.insert(alloc::vec![byte], character.into());
+            }
+        };
+
+        if let Some(digits) = self.digits {
+            insert(digits, '0'..='9');
+        }
+        insert(self.letters, 'A'..='Z');
+        if let Some(lowercase) = self.lowercase {
+            insert(lowercase, 'a'..='z');
+        }
+        if let Some(space) = self.space {
+            table.insert(alloc::vec![space], " ".into());
+        }
+
+        table
+    }
+
+    // Whether the byte is one of the letters
+    fn is_letter(&self, byte: u8) -> bool {
+        let in_alphabet = |start: u8| byte.wrapping_sub(start) < ALPHABET_SIZE;
+        in_alphabet(self.letters) || self.lowercase.is_some_and(in_alphabet)
+    }
+}
+
+// Trigrams found for every possible byte of the first letter of the alphabet
+fn alphabet_scores(data: &[u8]) -> [usize; 256] {
+    let mut scores = [0; 256];
+    for bytes in data.windows(3) {
+        for trigram in &TRIGRAMS {
+            let matches = bytes[1].wrapping_sub(bytes[0]) == trigram[1].wrapping_sub(trigram[0])
+                && bytes[2].wrapping_sub(bytes[1]) == trigram[2].wrapping_sub(trigram[1]);
+            let start = bytes[0].wrapping_sub(trigram[0] - b'A');
+            // The alphabet can't wrap around
+            if matches && start <= u8::MAX - (ALPHABET_SIZE - 1) {
+                scores[usize::from(start)] += 1;
+            }
+        }
+    }
+
+    scores
+}
+
+// Trigrams with an uppercase first letter followed by lowercase ones, like at the start of sentences
+fn capitalized_trigrams(data: &[u8], letters: u8, lowercase: u8) -> usize {
+    data.windows(3)
+        .filter(|bytes| {
+            TRIGRAMS.iter().any(|trigram| {
+                bytes[0] == letters.wrapping_add(trigram[0] - b'A')
+                    && bytes[1] == lowercase.wrapping_add(trigram[1] - b'A')
+                    && bytes[2] == lowercase.wrapping_add(trigram[2] - b'A')
+            })
+        })
+        .count()
+}
+
+// Byte most often found between two letters on both sides, which is what separates words
+fn find_space(data: &[u8], guess: &EncodingGuess) -> Option<u8> {
+    let mut counts = [0_usize; 256];
+    for bytes in data.windows(5) {
+        let [first, second, separator, third, fourth] =
+            [bytes[0], bytes[1], bytes[2], bytes[3], bytes[4]];
+        if [first, second, third, fourth]
+            .iter()
+            .all(|byte| guess.is_letter(*byte))
+            && !guess.is_letter(separator)
+        {
+            counts[usize::from(separator)] += 1;
+        }
+    }
+
+    (0..=u8::MAX)
+        .zip(counts)
+        .filter(|(_, count)| *count > 0)
+        .max_by_key(|(_, count)| *count)
+        .map(|(byte, _)| byte)
+}
+
+/// Find the encodings the data could hold English text in, the most likely one first
+///
+/// Games rarely use ASCII, but almost always keep the alphabet in order. Common trigrams like "THE" keep
+/// the spacing between their letters in any such table, so searching for the spacing finds where the alphabet starts,
+/// like the relative search of ROM hacking tools. Lowercase letters are detected after the uppercase ones
+/// and like in ASCII, and the space is the byte most often separating words.
+/// [`EncodingGuess::table`] turns a guess into a table to start a translation with. Text in other languages,
+/// like the kana of Japanese games, isn't detected.
+#[must_use]
+pub fn detect_encodings(data: &[u8]) -> Vec<EncodingGuess> {
+    let mut scores = alphabet_scores(data);
+    let mut starts = (0..=u8::MAX)
+        .filter(|start| scores[usize::from(*start)] >= MIN_ENCODING_SCORE)
+        .collect::<Vec<_>>();
+    starts.sort_by_key(|start| Reverse(scores[usize::from(*start)]));
+
+    let mut guesses = Vec::new();
+    for start in starts {
+        let score = mem::take(&mut scores[usize::from(start)]);
+        if score == 0 {
+            // Already paired up as the other case of a better alphabet
+            continue;
+        }
+
+        // Mixed case text mostly finds the lowercase alphabet, since only the first letters of sentences and names
+        // are uppercase, so the other case is confirmed by capitalized words like "The"
+        let uppercase = LOWERCASE_DISTANCES.iter().find_map(|distance| {
+            let letters = start.checked_sub(*distance)?;
+            (capitalized_trigrams(data, letters, start) >= MIN_CAPITALIZED_SCORE).then_some(letters)
+        });
+        let (letters, lowercase) = if let Some(letters) = uppercase {
+            (letters, Some(start))
+        } else {
+            let lowercase = LOWERCASE_DISTANCES.iter().find_map(|distance| {
+                let lowercase = start.checked_add(*distance)?;
+                (lowercase <= u8::MAX - (ALPHABET_SIZE - 1)
+                    && capitalized_trigrams(data, start, lowercase) >= MIN_CAPITALIZED_SCORE)
+                    .then_some(lowercase)
+            });
+            (start, lowercase)
+        };
+        // The other case doesn't make for an alphabet of its own
+        let other = if lowercase == Some(start) {
+            letters
+        } else {
+            lowercase.unwrap_or(start)
+        };
+        let score = score + mem::take(&mut scores[usize::from(other)]);
+
+        let digits = match letters {
+            b'A' => Some(b'0'),
+            _ => letters.checked_sub(10),
+        };
+        let mut guess = EncodingGuess {
+            letters,
+            lowercase,
+            digits,
+            space: None,
+            score,
+        };
+        guess.space = find_space(data, &guess);
+        guesses.push(guess);
+    }
+
+    guesses.sort_by_key(|guess| Reverse(guess.score));
+    guesses
+}
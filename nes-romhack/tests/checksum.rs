@@ -0,0 +1,105 @@
+use nes_romhack::{
+    checksum::{locate, Algorithm, Checksum, NintendoHeader},
+    Error,
+};
+
+// Pseudo-random data, so sums don't match by accident as easily as with repeating data
+fn data(size: usize) -> Vec<u8> {
+    let mut seed = 0x2545_F491_u32;
+    (0..size)
+        .map(|_| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed.to_le_bytes()[0]
+        })
+        .collect()
+}
+
+#[test]
+fn sum8() {
+    let mut data = vec![0x10, 0x20, 0x30, 0x00];
+    let checksum = Checksum {
+        algorithm: Algorithm::Sum8,
+        range: 0..3,
+        location: 3,
+        complement: true,
+    };
+
+    assert_eq!(checksum.compute(&data).unwrap(), 0xA0);
+    assert!(!checksum.is_valid(&data).unwrap());
+    assert!(checksum.fix(&mut data).unwrap());
+    assert_eq!(data[3], 0xA0);
+    assert!(!checksum.fix(&mut data).unwrap());
+}
+
+#[test]
+fn sum16() {
+    let mut data = vec![0xFF; 0x100];
+    // The stored checksum is inside of the summed range, so it gets skipped
+    let checksum = Checksum {
+        algorithm: Algorithm::Sum16 { big_endian: false },
+        range: 0..0x100,
+        location: 0x80,
+        complement: false,
+    };
+
+    assert_eq!(checksum.compute(&data).unwrap(), 0xFE * 0xFF);
+    checksum.fix(&mut data).unwrap();
+    assert_eq!(data[0x80..0x82], 0xFD02_u16.to_le_bytes());
+    assert!(checksum.is_valid(&data).unwrap());
+
+    let big_endian = Checksum {
+        algorithm: Algorithm::Sum16 { big_endian: true },
+        ..checksum.clone()
+    };
+    assert_eq!(big_endian.stored(&data).unwrap(), 0x02FD);
+
+    let outside = Checksum {
+        location: 0xFF,
+        ..checksum
+    };
+    assert!(matches!(
+        outside.compute(&data),
+        Err(Error::OutOfRange(0x101))
+    ));
+}
+
+#[test]
+fn locate_referenced_checksum() {
+    let mut prg_rom = data(0x4000);
+    // LDA $F000, which reads the checksum through the mirror at $C000
+    prg_rom[0x100..0x103].copy_from_slice(&[0xAD, 0x00, 0xF0]);
+
+    let checksum = Checksum {
+        algorithm: Algorithm::Sum16 { big_endian: false },
+        range: 0..0x4000,
+        location: 0x3000,
+        complement: true,
+    };
+    checksum.fix(&mut prg_rom).unwrap();
+
+    let candidates = locate(&prg_rom, 0x4000);
+    assert!(candidates[0].referenced);
+    assert_eq!(candidates[0].checksum, checksum);
+}
+
+#[test]
+fn nintendo_header() {
+    let mut prg_rom = data(0x8000);
+    let chr_rom = data(0x2000);
+    prg_rom[0x7FE0..0x7FFA].fill(0);
+    assert_eq!(NintendoHeader::find(&prg_rom), None);
+
+    let header = NintendoHeader { offset: 0x7FE0 };
+    assert!(header.fix(&mut prg_rom, &chr_rom).unwrap());
+    assert!(header.is_valid(&prg_rom, &chr_rom).unwrap());
+    assert!(!header.fix(&mut prg_rom, &chr_rom).unwrap());
+    assert_eq!(NintendoHeader::find(&prg_rom), Some(header.clone()));
+
+    // Editing the ROM breaks the PRG ROM sum
+    prg_rom[0x1234] ^= 0xFF;
+    assert!(!header.is_valid(&prg_rom, &chr_rom).unwrap());
+    assert!(header.fix(&mut prg_rom, &chr_rom).unwrap());
+    assert!(header.prg_checksum().is_valid(&prg_rom).unwrap());
+}
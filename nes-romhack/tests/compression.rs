@@ -0,0 +1,118 @@
+use nes_romhack::{
+    compression::{decompress, detect, Scheme},
+    Error,
+};
+
+const LIMIT: usize = 0x1000;
+
+fn decompressed(scheme: Scheme, input: &[u8]) -> (Vec<u8>, usize) {
+    let decompressed = decompress(scheme, input, LIMIT).unwrap();
+    (decompressed.data, decompressed.consumed)
+}
+
+#[test]
+fn konami_rle() {
+    assert_eq!(
+        decompressed(
+            Scheme::KonamiRle,
+            &[0x03, 0xAA, 0x82, 0x01, 0x02, 0xFF, 0x42]
+        ),
+        (vec![0xAA, 0xAA, 0xAA, 0x01, 0x02], 6)
+    );
+}
+
+#[test]
+fn rle_pairs() {
+    assert_eq!(
+        decompressed(Scheme::RlePairs, &[0x02, 0x05, 0x03, 0x06, 0x00, 0x42]),
+        (vec![0x05, 0x05, 0x06, 0x06, 0x06], 5)
+    );
+}
+
+// The example of Apple's documentation
+#[test]
+fn pack_bits() {
+    let input = [
+        0xFE, 0xAA, 0x02, 0x80, 0x00, 0x2A, 0xFD, 0xAA, 0x03, 0x80, 0x00, 0x2A, 0x22, 0xF7, 0xAA,
+    ];
+    let mut expected = vec![
+        0xAA, 0xAA, 0xAA, 0x80, 0x00, 0x2A, 0xAA, 0xAA, 0xAA, 0xAA, 0x80, 0x00, 0x2A, 0x22,
+    ];
+    expected.extend_from_slice(&[0xAA; 10]);
+
+    assert_eq!(
+        decompressed(Scheme::PackBits, &input),
+        (expected, input.len())
+    );
+}
+
+#[test]
+fn hal_lz() {
+    #[rustfmt::skip]
+    let input = [
+        0x02, 0x01, 0x02, 0x03, // Three literals
+        0x21, 0x09, // Run of two bytes
+        0x41, 0x07, 0x08, // Run of two words
+        0x62, 0x10, // Three increasing bytes
+        0x82, 0x00, 0x00, // Copy three bytes from the start
+        0xA0, 0x00, 0x00, // Copy the first byte bit-reversed
+        0xC1, 0x00, 0x02, // Copy two bytes backwards
+        0xE4, 0x27, 0x55, // Run of 40 bytes with a long command
+        0xFF,
+    ];
+
+    let mut expected = vec![
+        0x01, 0x02, 0x03, 0x09, 0x09, 0x07, 0x08, 0x07, 0x08, 0x10, 0x11, 0x12, 0x01, 0x02, 0x03,
+        0x80, 0x03, 0x02,
+    ];
+    expected.extend_from_slice(&[0x55; 40]);
+
+    assert_eq!(decompressed(Scheme::HalLz, &input), (expected, input.len()));
+}
+
+#[test]
+fn lzss() {
+    #[rustfmt::skip]
+    let input = [
+        0b0000_0111, // Three literals, then two references
+        b'A', b'B', b'C',
+        0xEE, 0xF3, // Six bytes from where the literals were written, overlapping the copy
+        0x00, 0x00, // Three bytes of the initial spaces
+    ];
+
+    assert_eq!(
+        decompressed(Scheme::Lzss, &input),
+        (b"ABCABCABC   ".to_vec(), input.len())
+    );
+
+    // Schemes without an end marker stop at the limit
+    let limited = decompress(Scheme::Lzss, &input, 4).unwrap();
+    assert_eq!(limited.data, b"ABCA");
+}
+
+#[test]
+fn reject_invalid_data() {
+    assert!(matches!(
+        decompress(Scheme::KonamiRle, &[0x03], LIMIT),
+        Err(Error::UnexpectedEof)
+    ));
+    assert!(matches!(
+        decompress(Scheme::HalLz, &[0x80, 0x00, 0x05, 0xFF], LIMIT),
+        Err(Error::InvalidReference(5))
+    ));
+    assert!(matches!(
+        decompress(Scheme::RlePairs, &[200, 0x01, 0x00], 100),
+        Err(Error::TooLarge(100))
+    ));
+}
+
+#[test]
+fn detect_scheme() {
+    // 32 zeroes as Konami RLE, which is too short for the other schemes
+    let candidates = detect(&[0x20, 0x00, 0xFF], LIMIT);
+
+    assert_eq!(candidates.len(), 1);
+    assert_eq!(candidates[0].scheme, Scheme::KonamiRle);
+    assert_eq!(candidates[0].decompressed.data, [0; 32]);
+    assert!(candidates[0].has_graphics_size());
+}
@@ -0,0 +1,61 @@
+use nes_romhack::pointers::{Pointer, Scanner};
+
+fn write_words(prg_rom: &mut [u8], offset: usize, words: &[u16]) {
+    for (index, word) in words.iter().enumerate() {
+        prg_rom[offset + index * 2..][..2].copy_from_slice(&word.to_le_bytes());
+    }
+}
+
+// 16 KiB PRG ROM with a table of four pointers, one of three and a run of filler
+fn prg_rom() -> Vec<u8> {
+    let mut prg_rom = vec![0; 0x4000];
+    write_words(&mut prg_rom, 0x100, &[0x8200, 0x8210, 0xC220, 0x8230]);
+    write_words(&mut prg_rom, 0x300, &[0x9000, 0x9100, 0x9200]);
+    prg_rom[0x2000..0x2040].fill(0xFF);
+    prg_rom
+}
+
+#[test]
+fn scan() {
+    let tables = Scanner::new().scan(&prg_rom());
+
+    assert_eq!(tables.len(), 1);
+    assert_eq!(tables[0].offset, 0x100);
+    assert_eq!(tables[0].size(), 8);
+    // 16 KiB are mirrored at $C000
+    assert_eq!(
+        tables[0].pointers[2],
+        Pointer {
+            address: 0xC220,
+            offset: Some(0x220),
+        }
+    );
+}
+
+#[test]
+fn min_entries() {
+    let tables = Scanner::new().min_entries(3).scan(&prg_rom());
+
+    let offsets: Vec<usize> = tables.iter().map(|table| table.offset).collect();
+    assert_eq!(offsets, [0x100, 0x300]);
+    assert_eq!(tables[1].len(), 3);
+}
+
+#[test]
+fn target_range() {
+    let scanner = Scanner::new().min_entries(3).target_range(0x9000..=0x9FFF);
+    let tables = scanner.scan(&prg_rom());
+
+    assert_eq!(tables.len(), 1);
+    assert_eq!(tables[0].offset, 0x300);
+}
+
+#[test]
+fn bank_size() {
+    let mut prg_rom = vec![0; 0x10000];
+    write_words(&mut prg_rom, 0x4100, &[0x8000, 0x8100, 0x8200, 0x8300]);
+
+    // The targets are in the same 8 KiB bank as the table
+    let tables = Scanner::new().bank_size(0x2000).scan(&prg_rom);
+    assert_eq!(tables[0].pointers[1].offset, Some(0x4100));
+}
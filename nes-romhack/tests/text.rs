@@ -0,0 +1,81 @@
+use nes_romhack::{
+    text::{Scanner, Table, Text},
+    Error,
+};
+
+const TABLE: &str = "\
+0A=A
+0B=B
+0C=C
+0D=D
+0E=E
+10= 
+5C5D=the
+*FE
+/FF=<end>
+";
+
+#[test]
+fn parse_table() {
+    let table = Table::parse(TABLE).unwrap();
+
+    assert_eq!(table.lookup(&[0x0A, 0x0B]), Some(("A", 1)));
+    // The longest matching entry wins
+    assert_eq!(table.lookup(&[0x5C, 0x5D, 0x0A]), Some(("the", 2)));
+    assert_eq!(table.lookup(&[0x5C]), None);
+    assert_eq!(table.terminators(), [vec![0xFF]]);
+
+    assert_eq!(
+        table.decode(&[0x5C, 0x5D, 0x10, 0x0C, 0x0A, 0x0B, 0xFE, 0x0E, 0xFF]),
+        ("the CAB\nE".to_string(), 8)
+    );
+}
+
+#[test]
+fn reject_invalid_tables() {
+    assert!(matches!(
+        Table::parse("0A=A\nnot an entry\n"),
+        Err(Error::InvalidTable(2))
+    ));
+    assert!(matches!(Table::parse("0=A"), Err(Error::InvalidTable(1))));
+    assert!(matches!(Table::parse("/XY"), Err(Error::InvalidTable(1))));
+}
+
+#[test]
+fn scan_with_table() {
+    let table = Table::parse(TABLE).unwrap();
+    #[rustfmt::skip]
+    let data = [
+        0x00, 0x0B, 0x0A, 0x0D, 0xFF, // Terminated, but too short
+        0x0D, 0x0E, 0x0A, 0x0D, 0x0B, 0x0E, 0x0E, 0x0E, // Not terminated
+        0x00, 0x5C, 0x5D, 0x10, 0x0C, 0x0A, 0x0B, 0xFF,
+    ];
+
+    assert_eq!(
+        Scanner::new(&table).scan(&data),
+        [Text {
+            offset: 14,
+            length: 6,
+            text: "the CAB".to_string(),
+        }]
+    );
+    assert_eq!(Scanner::new(&table).min_length(3).scan(&data).len(), 2);
+}
+
+#[test]
+fn scan_ascii() {
+    let table = Table::ascii();
+    let data = b"\x00\x01HELLO\x80WORLD\x00\x02OK\x00";
+
+    let texts: Vec<String> = Scanner::new(&table)
+        .scan(data)
+        .into_iter()
+        .map(|text| text.text)
+        .collect();
+    assert_eq!(texts, ["HELLO", "WORLD"]);
+
+    // With a terminator only strings followed by it are reported
+    let texts = Scanner::new(&table).terminator(vec![0x80]).scan(data);
+    assert_eq!(texts.len(), 1);
+    assert_eq!(texts[0].offset, 2);
+}
@@ -0,0 +1,249 @@
+#![no_std]
+#![warn(clippy::all, clippy::pedantic)]
+
+//!
+//! Serialization of the state of the emulation cores
+//!
+//! All values are stored in little endian. Byte slices are prefixed with their length.
+//!
+
+extern crate alloc;
+
+use {
+    alloc::vec::Vec,
+    core::{convert::TryFrom, fmt},
+};
+
+#[derive(Debug)]
+pub enum Error {
+    /// The state ended in the middle of a value
+    UnexpectedEof,
+    /// A byte slice in the state doesn't have the size of the buffer it's loaded into
+    LengthMismatch { expected: usize, actual: usize },
+    /// A value in the state is out of range
+    InvalidValue,
+    /// The magic bytes of a container don't match
+    MagicBytesMismatch([u8; 4]),
+    /// The state was created by a newer version of the format
+    UnsupportedVersion(u16),
+    /// A chunk the container requires is missing
+    MissingChunk([u8; 4]),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEof => f.write_str("Unexpected end of the state"),
+            Self::LengthMismatch { expected, actual } => {
+                write!(f, "Expected {expected} bytes, got {actual} bytes")
+            }
+            Self::InvalidValue => f.write_str("Invalid value in the state"),
+            Self::MagicBytesMismatch(magic) => write!(f, "Magic bytes mismatch: {magic:X?}"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "Version {version} of the state isn't supported")
+            }
+            Self::MissingChunk(tag) => {
+                write!(
+                    f,
+                    "Missing chunk {:?}",
+                    core::str::from_utf8(tag).unwrap_or("?")
+                )
+            }
+        }
+    }
+}
+
+/// Component whose state can be saved and restored
+///
+/// Loading a state only restores what was saved; configuration passed to the constructor
+/// (like the sample rate) stays the same.
+pub trait Savestate {
+    fn save_state(&self, writer: &mut StateWriter);
+
+    /// # Errors
+    ///
+    /// Returns an error if the state is truncated or contains invalid values
+    fn load_state(&mut self, reader: &mut StateReader<'_>) -> Result<(), Error>;
+}
+
+/// Serializes values into a state
+#[derive(Clone, Debug, Default)]
+pub struct StateWriter {
+    data: Vec<u8>,
+}
+
+impl StateWriter {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.write_u8(u8::from(value));
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_f32(&mut self, value: f32) {
+        self.write_u32(value.to_bits());
+    }
+
+    /// Write a byte slice prefixed with its length
+    ///
+    /// # Panics
+    ///
+    /// Panics if the slice is larger than 4 GiB
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.write_u32(u32::try_from(bytes.len()).expect("Byte slice too large"));
+        self.data.extend_from_slice(bytes);
+    }
+
+    /// Write a tagged chunk containing the state of the component
+    pub fn write_chunk<S: Savestate + ?Sized>(&mut self, tag: [u8; 4], component: &S) {
+        let mut chunk = Self::new();
+        component.save_state(&mut chunk);
+
+        self.data.extend_from_slice(&tag);
+        self.write_bytes(&chunk.data);
+    }
+
+    #[must_use]
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+/// Deserializes values from a state
+#[derive(Clone, Debug)]
+pub struct StateReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    #[must_use]
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    /// Bytes which weren't read yet
+    #[must_use]
+    pub fn remaining(&self) -> &'a [u8] {
+        self.data
+    }
+
+    fn take(&mut self, count: usize) -> Result<&'a [u8], Error> {
+        if self.data.len() < count {
+            return Err(Error::UnexpectedEof);
+        }
+
+        let (taken, rest) = self.data.split_at(count);
+        self.data = rest;
+        Ok(taken)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if the state ended
+    pub fn read_u8(&mut self) -> Result<u8, Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if the state ended or the value isn't 0 or 1
+    pub fn read_bool(&mut self) -> Result<bool, Error> {
+        match self.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(Error::InvalidValue),
+        }
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if the state ended
+    pub fn read_u16(&mut self) -> Result<u16, Error> {
+        Ok(u16::from_le_bytes(self.take_array()?))
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if the state ended
+    pub fn read_u32(&mut self) -> Result<u32, Error> {
+        Ok(u32::from_le_bytes(self.take_array()?))
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if the state ended
+    pub fn read_u64(&mut self) -> Result<u64, Error> {
+        Ok(u64::from_le_bytes(self.take_array()?))
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if the state ended
+    pub fn read_f32(&mut self) -> Result<f32, Error> {
+        Ok(f32::from_bits(self.read_u32()?))
+    }
+
+    /// Read a byte slice prefixed with its length
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the state ended
+    pub fn read_bytes(&mut self) -> Result<&'a [u8], Error> {
+        let length = self.read_u32()? as usize;
+        self.take(length)
+    }
+
+    /// Read a byte slice into a buffer of the same size
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the state ended or the size doesn't match
+    pub fn read_bytes_into(&mut self, buffer: &mut [u8]) -> Result<(), Error> {
+        let bytes = self.read_bytes()?;
+        if bytes.len() != buffer.len() {
+            return Err(Error::LengthMismatch {
+                expected: buffer.len(),
+                actual: bytes.len(),
+            });
+        }
+
+        buffer.copy_from_slice(bytes);
+        Ok(())
+    }
+
+    /// Read the next tagged chunk, returning its tag and a reader over its contents
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the state ended
+    pub fn read_chunk(&mut self) -> Result<([u8; 4], StateReader<'a>), Error> {
+        let tag = self.take_array()?;
+        let data = self.read_bytes()?;
+
+        Ok((tag, StateReader::new(data)))
+    }
+}
@@ -0,0 +1,159 @@
+use {
+    crate::format::{self, Format},
+    anyhow::{bail, Result},
+    clap::ValueEnum,
+    fds_parser::Fds,
+    ines_parser::{Ines, Timing},
+    nsf_parser::{nsfe, Nsf},
+    std::{borrow::Cow, convert::TryFrom, path::PathBuf},
+};
+
+// NSF2 files store the length of the program in the header, followed by the metadata
+const NSF_HEADER_SIZE: usize = 0x80;
+const NSF2_DATA_LENGTH: usize = 0x7D;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Target {
+    /// ROM with an INES header
+    Ines,
+    /// ROM with a NES 2.0 header
+    Nes2,
+    /// Disk image with a fwNES header
+    Fds,
+    /// Disk image without a header
+    FdsRaw,
+    Nsf,
+    Nsfe,
+    Nsf2,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Region {
+    Ntsc,
+    Pal,
+    Multi,
+    Dendy,
+}
+
+impl From<Region> for Timing {
+    fn from(region: Region) -> Self {
+        match region {
+            Region::Ntsc => Self::Ntsc,
+            Region::Pal => Self::Pal,
+            Region::Multi => Self::MultiRegion,
+            Region::Dendy => Self::Dendy,
+        }
+    }
+}
+
+/// Convert between the header formats of ROMs, disk images and NSF files, or change the region of a ROM
+#[derive(clap::Args)]
+pub struct Args {
+    input: PathBuf,
+    output: PathBuf,
+    /// Format to convert to
+    #[arg(long, value_enum)]
+    to: Option<Target>,
+    /// Region to set in the header of a ROM
+    #[arg(long, value_enum)]
+    region: Option<Region>,
+}
+
+fn convert_ines(data: &[u8], target: Option<Target>, region: Option<Region>) -> Result<Vec<u8>> {
+    let mut ines = Ines::from_bytes(data)?;
+    match target {
+        Some(Target::Ines) => {
+            if ines.header.submapper != 0 {
+                eprintln!(
+                    "Warning: INES headers can't store the submapper {}",
+                    ines.header.submapper
+                );
+            }
+            ines.header.is_nes2 = false;
+        }
+        Some(Target::Nes2) => ines.header.is_nes2 = true,
+        Some(target) => bail!("iNES ROMs can't be converted to {target:?}"),
+        None => (),
+    }
+    let mut data = ines.to_bytes();
+
+    if let Some(region) = region {
+        let conversion = ines_parser::convert_region(&data, region.into())?;
+        data[..conversion.header.len()].copy_from_slice(&conversion.header);
+
+        println!(
+            "Converted from {:?} to {:?}: the music plays at {:.3}x the pitch, frame-based logic runs at {:.3}x the speed",
+            conversion.from,
+            conversion.to,
+            conversion.cpu_clock_ratio(),
+            conversion.frame_rate_ratio()
+        );
+        if conversion.shortens_vblank() {
+            println!(
+                "The vertical blanking gets shorter, which can break the PPU updates of the game"
+            );
+        }
+    }
+
+    Ok(data)
+}
+
+// The program of an NSF file and its metadata, either from NSF2 or NSFe
+fn parse_nsf(data: &[u8], format: Format) -> Result<(Nsf<'_>, nsfe::Metadata)> {
+    if format == Format::Nsfe {
+        return Ok(nsfe::parse(data)?);
+    }
+
+    let mut nsf = Nsf::from_bytes(data)?;
+    let Some(metadata) = nsfe::Metadata::from_nsf2(data)? else {
+        return Ok((nsf, nsfe::Metadata::default()));
+    };
+
+    // The metadata follows the program, which was checked while reading it
+    let length = u32::from_le_bytes([
+        data[NSF2_DATA_LENGTH],
+        data[NSF2_DATA_LENGTH + 1],
+        data[NSF2_DATA_LENGTH + 2],
+        0,
+    ]);
+    let end = NSF_HEADER_SIZE + usize::try_from(length)?;
+    nsf.data = Cow::Borrowed(&data[NSF_HEADER_SIZE..end]);
+
+    Ok((nsf, metadata))
+}
+
+pub fn run(args: &Args) -> Result<()> {
+    if args.to.is_none() && args.region.is_none() {
+        bail!("Pass the format to convert to with --to or the region with --region");
+    }
+
+    let (data, format) = format::read(&args.input)?;
+    if args.region.is_some() && format != Format::Ines {
+        bail!("Only the region of iNES ROMs can be changed");
+    }
+
+    let converted = if format == Format::Ines {
+        convert_ines(&data, args.to, args.region)?
+    } else {
+        let Some(target) = args.to else {
+            bail!("Only the region of iNES ROMs can be changed");
+        };
+
+        match (format, target) {
+            (Format::Fds, Target::Fds | Target::FdsRaw) => {
+                Fds::from_bytes(&data)?.to_bytes(target == Target::Fds)
+            }
+            (Format::Nsf | Format::Nsfe, Target::Nsf | Target::Nsfe | Target::Nsf2) => {
+                let (nsf, metadata) = parse_nsf(&data, format)?;
+                match target {
+                    Target::Nsfe => nsf.to_nsfe(&metadata),
+                    Target::Nsf2 => nsf.to_nsf2(&metadata)?,
+                    _ => nsf.to_bytes(),
+                }
+            }
+            _ => bail!("{} files can't be converted to {target:?}", format.name()),
+        }
+    };
+
+    format::write(&args.output, &converted)
+}
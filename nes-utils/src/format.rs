@@ -0,0 +1,74 @@
+use {
+    anyhow::{Context, Result},
+    std::{fs, path::Path},
+};
+
+// Magic bytes of the formats, which all end in the MS-DOS EOF delimiter except for NSFe
+const INES_MAGIC: &[u8] = b"NES\x1A";
+const NSF_MAGIC: &[u8] = b"NESM\x1A";
+const NSFE_MAGIC: &[u8] = b"NSFE";
+
+/// Formats of the files the subcommands work on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// INES and NES 2.0 ROMs
+    Ines,
+    /// Disk images of the Famicom Disk System, with or without a fwNES header
+    Fds,
+    /// NSF and NSF2 files
+    Nsf,
+    Nsfe,
+}
+
+impl Format {
+    /// Format of the data by its magic bytes; FDS images without a header are recognised by their size
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        if data.starts_with(INES_MAGIC) {
+            Some(Self::Ines)
+        } else if data.starts_with(NSF_MAGIC) {
+            Some(Self::Nsf)
+        } else if data.starts_with(NSFE_MAGIC) {
+            Some(Self::Nsfe)
+        } else if fds_parser::has_header(data)
+            || (!data.is_empty() && data.len().is_multiple_of(fds_parser::SIDE_SIZE))
+        {
+            Some(Self::Fds)
+        } else {
+            None
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Ines => "iNES",
+            Self::Fds => "FDS",
+            Self::Nsf => "NSF",
+            Self::Nsfe => "NSFe",
+        }
+    }
+}
+
+/// Read a file and detect its format
+pub fn read(path: &Path) -> Result<(Vec<u8>, Format)> {
+    let data = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let format = Format::detect(&data).with_context(|| {
+        format!(
+            "{} is neither a ROM, FDS image nor NSF file",
+            path.display()
+        )
+    })?;
+
+    Ok((data, format))
+}
+
+/// Write a file without printing anything, for the `--json` flags
+pub fn write_quietly(path: &Path, data: &[u8]) -> Result<()> {
+    fs::write(path, data).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+pub fn write(path: &Path, data: &[u8]) -> Result<()> {
+    write_quietly(path, data)?;
+    println!("Wrote {} ({} bytes)", path.display(), data.len());
+
+    Ok(())
+}
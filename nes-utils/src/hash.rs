@@ -0,0 +1,77 @@
+use {
+    crate::format::{self, Format},
+    anyhow::Result,
+    nes_catalog::{HashReport, Hashes},
+    serde::Serialize,
+    std::{fmt::Write as _, path::Path},
+};
+
+#[derive(Serialize)]
+struct FileHashes<'a> {
+    path: &'a Path,
+    #[serde(flatten)]
+    report: HashReport,
+    /// Disk image without its fwNES header
+    #[serde(skip_serializing_if = "Option::is_none")]
+    disk: Option<Hashes>,
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+fn print_hashes(label: &str, hashes: Option<Hashes>) {
+    if let Some(hashes) = hashes {
+        println!(
+            "  {label:<8}  {:08x}  {}  {}",
+            hashes.crc32,
+            hex(&hashes.md5),
+            hex(&hashes.sha1)
+        );
+    }
+}
+
+/// Print the checksums of the file and its parts
+///
+/// ROM databases like No-Intro hash ROMs without their header, so those are printed as well.
+pub fn run(paths: &[impl AsRef<Path>], json: bool) -> Result<()> {
+    let mut files = Vec::new();
+    for path in paths {
+        let path = path.as_ref();
+        let (data, format) = format::read(path)?;
+
+        let report = if format == Format::Ines {
+            HashReport::of(&data)
+        } else {
+            // Only ROMs are split into their parts
+            HashReport {
+                file: Hashes::of(&data),
+                rom: None,
+                prg_rom: None,
+                chr_rom: None,
+            }
+        };
+        let disk = (format == Format::Fds && fds_parser::has_header(&data))
+            .then(|| Hashes::of(fds_parser::strip_header(&data)));
+
+        if !json {
+            println!("{}", path.display());
+            println!("  {:<8}  {:<8}  {:<32}  SHA-1", "", "CRC32", "MD5");
+            print_hashes("File", Some(report.file));
+            print_hashes("ROM", report.rom);
+            print_hashes("PRG ROM", report.prg_rom);
+            print_hashes("CHR ROM", report.chr_rom);
+            print_hashes("Disk", disk);
+        }
+        files.push(FileHashes { path, report, disk });
+    }
+
+    if json {
+        crate::print_json(&files)?;
+    }
+
+    Ok(())
+}
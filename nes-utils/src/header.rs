@@ -0,0 +1,217 @@
+use {
+    crate::{
+        format::{self, Format},
+        info,
+    },
+    anyhow::{bail, Context, Result},
+    clap::Subcommand,
+    ines_parser::{Header, Ines},
+    nes_catalog::{
+        recommend::{Field, Source},
+        HeaderValidation, Nes20Db,
+    },
+    serde::Serialize,
+    std::{
+        fs,
+        path::{Path, PathBuf},
+    },
+};
+
+const HEADER_SIZE: usize = 16;
+
+/// Check and repair the headers of ROMs
+#[derive(Subcommand)]
+pub enum Command {
+    /// Correct the header of a ROM with the NES 2.0 header database or heuristics, and write a fixed copy
+    Fix(FixArgs),
+}
+
+#[derive(clap::Args)]
+pub struct FixArgs {
+    rom: PathBuf,
+    /// File to write the fixed ROM to, `<name>.fixed.nes` next to the ROM by default
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// NES 2.0 header database (nes20db.xml); ROMs which aren't in there are checked with heuristics
+    #[arg(short, long)]
+    database: Option<PathBuf>,
+    /// Write a NES 2.0 header, which can store the submapper and the exact RAM sizes
+    #[arg(long)]
+    nes2: bool,
+    /// Only show the changes without writing the fixed ROM
+    #[arg(long)]
+    dry_run: bool,
+    /// Print the changes as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Serialize)]
+struct FixReport<'a> {
+    #[serde(flatten)]
+    validation: &'a HeaderValidation,
+    upgrades_to_nes2: bool,
+    original_header: String,
+    fixed_header: String,
+    /// File the fixed ROM was written to, none if the header is correct or for dry runs
+    output: Option<PathBuf>,
+}
+
+fn field_name(field: Field) -> &'static str {
+    match field {
+        Field::Mapper => "Mapper",
+        Field::Submapper => "Submapper",
+        Field::VramLayout => "Mirroring",
+        Field::Battery => "Battery",
+        Field::PrgRamSize => "PRG RAM",
+        Field::PrgNvramSize => "PRG NVRAM",
+        Field::ChrRamSize => "CHR RAM",
+        Field::ChrNvramSize => "CHR NVRAM",
+    }
+}
+
+fn field_value(field: Field, header: &Header) -> String {
+    match field {
+        Field::Mapper => header.mapper_number.to_string(),
+        Field::Submapper => header.submapper.to_string(),
+        Field::VramLayout => info::mirroring(header.vram_layout).into(),
+        Field::Battery => info::yes_no(header.has_persistent_memory).into(),
+        Field::PrgRamSize => info::size(header.prg_ram_size),
+        Field::PrgNvramSize => info::size(header.prg_nvram_size),
+        Field::ChrRamSize => info::size(header.chr_ram_size),
+        Field::ChrNvramSize => info::size(header.chr_nvram_size),
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{byte:02X}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn version(header: &Header) -> &'static str {
+    if header.is_nes2 {
+        "NES 2.0"
+    } else {
+        "iNES"
+    }
+}
+
+fn read_database(path: &Path) -> Result<Nes20Db> {
+    let text =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+
+    Nes20Db::parse(&text).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+fn print_changes(
+    validation: &HeaderValidation,
+    original: &Header,
+    header: &Header,
+    has_database: bool,
+) {
+    let recommendation = &validation.recommendation;
+    match (recommendation.source, &recommendation.name) {
+        (Source::Database, Some(name)) => println!("Found in the database as {name}"),
+        (Source::Database, None) => println!("Found in the database"),
+        (Source::Heuristics, _) if has_database => {
+            println!("Not in the database, the recommendation is based on heuristics");
+        }
+        (Source::Heuristics, _) => println!("The recommendation is based on heuristics"),
+    }
+    if !header.is_nes2 && recommendation.submapper != 0 {
+        println!(
+            "INES headers can't store the submapper {}, use --nes2",
+            recommendation.submapper
+        );
+    }
+
+    if header.is_nes2 != original.is_nes2 {
+        println!(
+            "  {:<10} {} -> {}",
+            "Format",
+            version(original),
+            version(header)
+        );
+    }
+    for &field in &validation.differences {
+        println!(
+            "  {:<10} {} -> {}",
+            field_name(field),
+            field_value(field, original),
+            field_value(field, header)
+        );
+    }
+}
+
+fn fix(args: &FixArgs) -> Result<()> {
+    let (data, format) = format::read(&args.rom)?;
+    if format != Format::Ines {
+        bail!("{} files have no header to fix", format.name());
+    }
+    let database = args.database.as_deref().map(read_database).transpose()?;
+
+    let ines = Ines::from_bytes(&data)?;
+    let original = ines.header.clone();
+    let mut header = original.clone();
+    header.is_nes2 |= args.nes2;
+    let validation = nes_catalog::recommend_header(&ines, database.as_ref()).validate(&header);
+    let recommendation = &validation.recommendation;
+    recommendation.apply(&mut header).with_context(|| {
+        format!(
+            "Mapper {} doesn't fit into the header",
+            recommendation.mapper
+        )
+    })?;
+
+    let upgrades_to_nes2 = header.is_nes2 != original.is_nes2;
+    let needs_fix = upgrades_to_nes2 || !validation.is_valid();
+    let fixed = Ines {
+        header: header.clone(),
+        ..ines
+    }
+    .to_bytes();
+    let output = (needs_fix && !args.dry_run).then(|| {
+        args.output.clone().unwrap_or_else(|| {
+            let stem = args
+                .rom
+                .file_stem()
+                .map_or_else(|| "rom".into(), |stem| stem.to_string_lossy());
+            args.rom.with_file_name(format!("{stem}.fixed.nes"))
+        })
+    });
+
+    if args.json {
+        if let Some(output) = &output {
+            format::write_quietly(output, &fixed)?;
+        }
+        return crate::print_json(&FixReport {
+            validation: &validation,
+            upgrades_to_nes2,
+            original_header: hex(&data[..HEADER_SIZE]),
+            fixed_header: hex(&fixed[..HEADER_SIZE]),
+            output,
+        });
+    }
+
+    print_changes(&validation, &original, &header, database.is_some());
+    if !needs_fix {
+        println!("The header is already correct");
+        return Ok(());
+    }
+    println!("- {}", hex(&data[..HEADER_SIZE]));
+    println!("+ {}", hex(&fixed[..HEADER_SIZE]));
+
+    match output {
+        Some(output) => format::write(&output, &fixed),
+        None => Ok(()),
+    }
+}
+
+pub fn run(command: &Command) -> Result<()> {
+    match command {
+        Command::Fix(args) => fix(args),
+    }
+}
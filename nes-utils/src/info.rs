@@ -0,0 +1,299 @@
+use {
+    crate::format::{self, Format},
+    anyhow::Result,
+    fds_parser::{Disk, Fds},
+    ines_parser::{Header, HeaderReport, Ines, Vectors, VramLayout},
+    nsf_parser::{nsfe, ExpansionChip, Nsf},
+    serde::Serialize,
+    std::{collections::BTreeSet, path::Path},
+};
+
+#[derive(Serialize)]
+struct InesInfo<'a> {
+    format: &'static str,
+    header: &'a Header,
+    vectors: Option<Vectors>,
+    /// Every field of the header with its meaning, like `--explain` prints it
+    fields: HeaderReport,
+}
+
+#[derive(Serialize)]
+struct FdsInfo {
+    format: &'static str,
+    header: bool,
+    sides: Vec<SideInfo>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum SideInfo {
+    Disk {
+        game_name: String,
+        disk: u8,
+        side: u8,
+        revision: u8,
+        files: Vec<FileInfo>,
+    },
+    Invalid {
+        error: String,
+    },
+}
+
+#[derive(Serialize)]
+struct FileInfo {
+    number: u8,
+    name: String,
+    kind: String,
+    address: u16,
+    size: usize,
+    hidden: bool,
+}
+
+#[derive(Serialize)]
+struct NsfInfo<'a> {
+    format: &'static str,
+    version: u8,
+    name: &'a str,
+    artist: &'a str,
+    copyright: &'a str,
+    total_songs: u8,
+    starting_song: u8,
+    load_address: u16,
+    init_address: u16,
+    play_address: u16,
+    region: String,
+    bankswitch: Option<[u8; 8]>,
+    expansion_audio: Vec<String>,
+    undeclared_expansion_audio: Vec<String>,
+}
+
+// Sizes in whole kilobytes where possible, like they're usually given
+pub fn size(bytes: usize) -> String {
+    if bytes >= 1024 && bytes.is_multiple_of(1024) {
+        format!("{} KiB", bytes / 1024)
+    } else {
+        format!("{bytes} bytes")
+    }
+}
+
+pub fn yes_no(flag: bool) -> &'static str {
+    if flag {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+pub fn mirroring(vram_layout: VramLayout) -> &'static str {
+    match vram_layout {
+        VramLayout::HorizontalMirroring => "horizontal",
+        VramLayout::VerticalMirroring => "vertical",
+        VramLayout::FourScreen => "four-screen",
+    }
+}
+
+fn print_ines(ines: &Ines<'_>, explain: bool) {
+    let header = &ines.header;
+    if explain {
+        print!("{}", header.explain());
+        return;
+    }
+
+    let version = if header.is_nes2 { "NES 2.0" } else { "iNES" };
+    println!("Format:     {version}");
+    if header.is_nes2 {
+        println!(
+            "Mapper:     {} (submapper {})",
+            header.mapper_number, header.submapper
+        );
+    } else {
+        println!("Mapper:     {}", header.mapper_number);
+    }
+    println!("PRG ROM:    {}", size(header.prg_rom_size));
+    if header.chr_rom_size > 0 {
+        println!("CHR ROM:    {}", size(header.chr_rom_size));
+    }
+    if header.chr_ram_size + header.chr_nvram_size > 0 {
+        println!(
+            "CHR RAM:    {}",
+            size(header.chr_ram_size + header.chr_nvram_size)
+        );
+    }
+    if header.prg_ram_size + header.prg_nvram_size > 0 {
+        println!(
+            "PRG RAM:    {}",
+            size(header.prg_ram_size + header.prg_nvram_size)
+        );
+    }
+    println!("Mirroring:  {}", mirroring(header.vram_layout));
+    println!("Battery:    {}", yes_no(header.has_persistent_memory));
+    println!("Trainer:    {}", yes_no(header.has_trainer()));
+    println!("Timing:     {:?}", header.timing);
+
+    if let Ok(vectors) = ines.vectors() {
+        println!(
+            "Vectors:    NMI ${:04X}, RESET ${:04X}, IRQ ${:04X}",
+            vectors.nmi.address, vectors.reset.address, vectors.irq.address
+        );
+    }
+}
+
+fn print_fds(data: &[u8], fds: &Fds<'_>) {
+    println!("Format:     FDS");
+    println!("Header:     {}", yes_no(fds_parser::has_header(data)));
+    println!("Sides:      {}", fds.sides.len());
+
+    for (index, side) in fds.sides.iter().enumerate() {
+        println!();
+        let disk = match Disk::parse(side) {
+            Ok(disk) => disk,
+            Err(err) => {
+                println!("Side {index}: {err}");
+                continue;
+            }
+        };
+
+        let info = &disk.info;
+        println!(
+            "Side {index}: {} (disk {}, side {}, revision {})",
+            info.game_name,
+            info.disk + 1,
+            if info.side == 0 { 'A' } else { 'B' },
+            info.revision
+        );
+        for file in &disk.files {
+            println!(
+                "  {:>3}  {:<8}  {:<12}  ${:04X}  {:>6} bytes{}",
+                file.number,
+                file.name(),
+                format!("{:?}", file.kind),
+                file.address,
+                file.data.len(),
+                if file.hidden { "  (hidden)" } else { "" }
+            );
+        }
+    }
+}
+
+fn print_nsf(nsf: &Nsf<'_>, format: Format) {
+    let header = &nsf.header;
+    println!("Format:     {} (version {})", format.name(), header.version);
+    println!("Name:       {}", header.name);
+    println!("Artist:     {}", header.artist);
+    println!("Copyright:  {}", header.copyright);
+    println!(
+        "Songs:      {} (starting with {})",
+        header.total_songs, header.starting_song
+    );
+    println!("Load:       ${:04X}", header.load_address);
+    println!("Init:       ${:04X}", header.init_address);
+    println!("Play:       ${:04X}", header.play_address);
+    println!("Region:     {:?}", header.region);
+    if let Some(banks) = header.bankswitch {
+        println!("Banks:      {banks:02X?}");
+    }
+
+    let audio = nsf.expansion_audio();
+    println!("Expansion:  {:?}", audio.declared);
+    if !audio.undeclared().is_empty() {
+        println!("Undeclared: {:?}", audio.undeclared());
+    }
+}
+
+fn ines_info<'a>(ines: &'a Ines<'_>) -> InesInfo<'a> {
+    let header = &ines.header;
+    InesInfo {
+        format: if header.is_nes2 { "NES 2.0" } else { "iNES" },
+        header,
+        vectors: ines.vectors().ok(),
+        fields: header.explain(),
+    }
+}
+
+fn fds_info(data: &[u8], fds: &Fds<'_>) -> FdsInfo {
+    let sides = fds
+        .sides
+        .iter()
+        .map(|side| match Disk::parse(side) {
+            Ok(disk) => SideInfo::Disk {
+                game_name: disk.info.game_name,
+                disk: disk.info.disk,
+                side: disk.info.side,
+                revision: disk.info.revision,
+                files: disk
+                    .files
+                    .iter()
+                    .map(|file| FileInfo {
+                        number: file.number,
+                        name: file.name().into_owned(),
+                        kind: format!("{:?}", file.kind),
+                        address: file.address,
+                        size: file.data.len(),
+                        hidden: file.hidden,
+                    })
+                    .collect(),
+            },
+            Err(err) => SideInfo::Invalid {
+                error: err.to_string(),
+            },
+        })
+        .collect();
+
+    FdsInfo {
+        format: Format::Fds.name(),
+        header: fds_parser::has_header(data),
+        sides,
+    }
+}
+
+fn chip_names(chips: &BTreeSet<ExpansionChip>) -> Vec<String> {
+    chips.iter().map(|chip| format!("{chip:?}")).collect()
+}
+
+fn nsf_info<'a>(nsf: &'a Nsf<'_>, format: Format) -> NsfInfo<'a> {
+    let header = &nsf.header;
+    let audio = nsf.expansion_audio();
+
+    NsfInfo {
+        format: format.name(),
+        version: header.version,
+        name: &header.name,
+        artist: &header.artist,
+        copyright: &header.copyright,
+        total_songs: header.total_songs,
+        starting_song: header.starting_song,
+        load_address: header.load_address,
+        init_address: header.init_address,
+        play_address: header.play_address,
+        region: format!("{:?}", header.region),
+        bankswitch: header.bankswitch,
+        expansion_audio: chip_names(&audio.declared),
+        undeclared_expansion_audio: chip_names(&audio.undeclared()),
+    }
+}
+
+fn print_json(data: &[u8], format: Format) -> Result<()> {
+    match format {
+        Format::Ines => crate::print_json(&ines_info(&Ines::from_bytes(data)?)),
+        Format::Fds => crate::print_json(&fds_info(data, &Fds::from_bytes(data)?)),
+        Format::Nsf => crate::print_json(&nsf_info(&Nsf::from_bytes(data)?, format)),
+        Format::Nsfe => crate::print_json(&nsf_info(&nsfe::parse(data)?.0, format)),
+    }
+}
+
+/// Print what's in the file
+pub fn run(path: &Path, explain: bool, json: bool) -> Result<()> {
+    let (data, format) = format::read(path)?;
+    if json {
+        return print_json(&data, format);
+    }
+
+    match format {
+        Format::Ines => print_ines(&Ines::from_bytes(&data)?, explain),
+        Format::Fds => print_fds(&data, &Fds::from_bytes(&data)?),
+        Format::Nsf => print_nsf(&Nsf::from_bytes(&data)?, format),
+        Format::Nsfe => print_nsf(&nsfe::parse(&data)?.0, format),
+    }
+
+    Ok(())
+}
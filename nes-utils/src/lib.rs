@@ -0,0 +1,95 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![warn(clippy::all, clippy::pedantic)]
+
+//!
+//! All crates of this repository behind one dependency
+//!
+//! Every crate is re-exported as a module when its feature is enabled (`ines` for `ines-parser`, `cpu` for `mos6502-cpu`, ...),
+//! `full` enables all of them. The `alloc`, `std`, `serde`, `proptest` and `quickcheck` features are forwarded to the enabled crates which have them.
+//! The [`prelude`] contains the central types of the enabled crates.
+//!
+//! The `cli` feature builds the `nes-utils` command line tool, libraries don't need it.
+//!
+
+#[cfg(feature = "fds")]
+pub use fds_parser as fds;
+#[cfg(feature = "ftm")]
+pub use ftm_parser as ftm;
+#[cfg(feature = "ines")]
+pub use ines_parser as ines;
+#[cfg(feature = "lemonade")]
+pub use lemonade;
+#[cfg(feature = "cpu")]
+pub use mos6502_cpu as cpu;
+#[cfg(feature = "dasm")]
+pub use mos6502_dasm as dasm;
+#[cfg(feature = "apu")]
+pub use nes_apu as apu;
+#[cfg(feature = "catalog")]
+pub use nes_catalog as catalog;
+#[cfg(feature = "cheats")]
+pub use nes_cheats as cheats;
+#[cfg(feature = "corruptor")]
+pub use nes_corruptor as corruptor;
+#[cfg(feature = "emulator")]
+pub use nes_emulator as emulator;
+#[cfg(feature = "mapper")]
+pub use nes_mapper as mapper;
+#[cfg(feature = "movie")]
+pub use nes_movie as movie;
+#[cfg(feature = "patch")]
+pub use nes_patch as patch;
+#[cfg(feature = "ppu")]
+pub use nes_ppu as ppu;
+#[cfg(feature = "romhack")]
+pub use nes_romhack as romhack;
+#[cfg(feature = "state")]
+pub use nes_state as state;
+#[cfg(feature = "nsf")]
+pub use nsf_parser as nsf;
+#[cfg(feature = "nsf-player")]
+pub use nsf_player;
+
+/// The central types of the enabled crates, for glob imports
+///
+/// Types whose names several crates use (like `Header` or `Error`) are left out, they are reachable through the modules
+pub mod prelude {
+    #[cfg(feature = "fds")]
+    pub use fds_parser::{Disk, Fds};
+    #[cfg(feature = "ftm")]
+    pub use ftm_parser::Module;
+    #[cfg(feature = "ines")]
+    pub use ines_parser::{Ines, Timing, VramLayout};
+    #[cfg(feature = "lemonade")]
+    pub use lemonade::{Colour, ColourPalette, Lemonade, PixelFormat, Sprite};
+    #[cfg(all(feature = "lemonade", feature = "alloc"))]
+    pub use lemonade::Sheet;
+    #[cfg(feature = "cpu")]
+    pub use mos6502_cpu::{Bus, Cpu};
+    #[cfg(feature = "dasm")]
+    pub use mos6502_dasm::{Disassembler, Instruction};
+    #[cfg(feature = "apu")]
+    pub use nes_apu::Apu;
+    #[cfg(feature = "catalog")]
+    pub use nes_catalog::{recommend_header, CartDb, Dat, Nes20Db};
+    #[cfg(feature = "cheats")]
+    pub use nes_cheats::{Cheat, GameGenie, ProActionRocky};
+    #[cfg(feature = "corruptor")]
+    pub use nes_corruptor::Corruptor;
+    #[cfg(feature = "emulator")]
+    pub use nes_emulator::{Harness, Nes};
+    #[cfg(feature = "mapper")]
+    pub use nes_mapper::Mapper;
+    #[cfg(feature = "movie")]
+    pub use nes_movie::Fm2;
+    #[cfg(feature = "patch")]
+    pub use nes_patch::{Bps, Ips, Ups};
+    #[cfg(feature = "ppu")]
+    pub use nes_ppu::{Ppu, PpuBus};
+    #[cfg(feature = "state")]
+    pub use nes_state::Savestate;
+    #[cfg(feature = "nsf")]
+    pub use nsf_parser::Nsf;
+    #[cfg(feature = "nsf-player")]
+    pub use nsf_player::Player;
+}
@@ -0,0 +1,79 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+//!
+//! Command line interface to the parsers of nes-utils
+//!
+
+use {
+    clap::{Parser, Subcommand},
+    serde::Serialize,
+    std::path::PathBuf,
+};
+
+mod convert;
+mod format;
+mod hash;
+mod header;
+mod info;
+mod merge;
+mod split;
+mod sprites;
+
+/// Inspect, hash, split, merge and convert NES ROMs, FDS disk images and NSF files, and export the tiles of ROMs
+#[derive(Parser)]
+#[command(name = "nes-utils", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Show the header of a ROM, the files of a disk image or the tags of an NSF file
+    Info {
+        file: PathBuf,
+        /// Explain every field of the header of a ROM
+        #[arg(long, conflicts_with = "json")]
+        explain: bool,
+        /// Print the information as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the CRC32, MD5 and SHA-1 of files, and of ROMs without their header
+    Hash {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Print the checksums as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    Split(split::Args),
+    Merge(merge::Args),
+    Convert(convert::Args),
+    #[command(subcommand)]
+    Header(header::Command),
+    Sprites(sprites::Args),
+}
+
+/// Print the value as pretty-printed JSON, for the `--json` flags
+fn print_json(value: &impl Serialize) -> anyhow::Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+
+    Ok(())
+}
+
+fn main() -> anyhow::Result<()> {
+    match Cli::parse().command {
+        Command::Info {
+            file,
+            explain,
+            json,
+        } => info::run(&file, explain, json),
+        Command::Hash { files, json } => hash::run(&files, json),
+        Command::Split(args) => split::run(&args),
+        Command::Merge(args) => merge::run(&args),
+        Command::Convert(args) => convert::run(&args),
+        Command::Header(command) => header::run(&command),
+        Command::Sprites(args) => sprites::run(&args),
+    }
+}
@@ -0,0 +1,133 @@
+use {
+    crate::format,
+    anyhow::{bail, Context, Result},
+    clap::ValueEnum,
+    fds_parser::Fds,
+    ines_parser::{Header, Ines, VramLayout},
+    std::{borrow::Cow, fs, path::PathBuf},
+};
+
+// Sizes INES 1 headers count the ROMs in
+const PRG_ROM_CHUNK_SIZE: usize = 0x4000;
+const CHR_ROM_CHUNK_SIZE: usize = 0x2000;
+const TRAINER_SIZE: usize = 512;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Mirroring {
+    Horizontal,
+    Vertical,
+    FourScreen,
+}
+
+impl From<Mirroring> for VramLayout {
+    fn from(mirroring: Mirroring) -> Self {
+        match mirroring {
+            Mirroring::Horizontal => Self::HorizontalMirroring,
+            Mirroring::Vertical => Self::VerticalMirroring,
+            Mirroring::FourScreen => Self::FourScreen,
+        }
+    }
+}
+
+/// Build a ROM from its PRG ROM, CHR ROM and trainer, or merge disk images and single sides into one image
+#[derive(clap::Args)]
+pub struct Args {
+    /// File to write the ROM or disk image to
+    output: PathBuf,
+    /// Disk images or single sides to merge, in the order of their sides
+    images: Vec<PathBuf>,
+
+    /// PRG ROM of the ROM to build
+    #[arg(long, conflicts_with = "images")]
+    prg: Option<PathBuf>,
+    /// CHR ROM of the ROM to build; games without one use CHR RAM
+    #[arg(long, requires = "prg")]
+    chr: Option<PathBuf>,
+    /// 512 byte trainer of the ROM to build
+    #[arg(long, requires = "prg")]
+    trainer: Option<PathBuf>,
+    #[arg(long, default_value_t = 0)]
+    mapper: u8,
+    #[arg(long, default_value_t = 0, requires = "nes2")]
+    submapper: u8,
+    #[arg(long, value_enum, default_value_t = Mirroring::Horizontal)]
+    mirroring: Mirroring,
+    /// The PRG RAM is battery-backed
+    #[arg(long)]
+    battery: bool,
+    /// Write a NES 2.0 header instead of an INES one
+    #[arg(long)]
+    nes2: bool,
+
+    /// Put a fwNES header in front of the merged disk image
+    #[arg(long, conflicts_with = "prg")]
+    fds_header: bool,
+}
+
+fn read(path: &PathBuf) -> Result<Vec<u8>> {
+    fs::read(path).with_context(|| format!("Failed to read {}", path.display()))
+}
+
+fn build_ines(args: &Args, prg: &PathBuf) -> Result<Vec<u8>> {
+    let prg_rom = read(prg)?;
+    let chr_rom = args.chr.as_ref().map(read).transpose()?;
+    let trainer = args.trainer.as_ref().map(read).transpose()?;
+    let chr_rom_size = chr_rom.as_ref().map_or(0, Vec::len);
+
+    if prg_rom.is_empty() {
+        bail!("The PRG ROM is empty");
+    }
+    // NES 2.0 can store other sizes, INES 1 only counts whole chunks
+    if !args.nes2
+        && (!prg_rom.len().is_multiple_of(PRG_ROM_CHUNK_SIZE)
+            || !chr_rom_size.is_multiple_of(CHR_ROM_CHUNK_SIZE))
+    {
+        bail!("INES headers need the PRG ROM in 16 KiB and the CHR ROM in 8 KiB chunks, use --nes2 for other sizes");
+    }
+    if let Some(trainer) = &trainer {
+        if trainer.len() != TRAINER_SIZE {
+            bail!(
+                "The trainer has to be {TRAINER_SIZE} bytes, got {}",
+                trainer.len()
+            );
+        }
+    }
+
+    let mut header = Header::new(
+        prg_rom.len(),
+        chr_rom_size,
+        args.mapper,
+        args.mirroring.into(),
+    );
+    header.has_persistent_memory = args.battery;
+    header.is_nes2 = args.nes2;
+    header.submapper = args.submapper;
+    if args.battery && args.nes2 {
+        header.prg_nvram_size = header.prg_ram_size;
+        header.prg_ram_size = 0;
+    }
+
+    let ines = Ines {
+        header,
+        trainer: trainer.map(Cow::Owned),
+        prg_rom: Cow::Owned(prg_rom),
+        chr_rom: chr_rom.map(Cow::Owned),
+    };
+
+    Ok(ines.to_bytes())
+}
+
+pub fn run(args: &Args) -> Result<()> {
+    let data = if let Some(prg) = &args.prg {
+        build_ines(args, prg)?
+    } else {
+        if args.images.is_empty() {
+            bail!("Either pass the disk images to merge or the PRG ROM with --prg");
+        }
+
+        let images = args.images.iter().map(read).collect::<Result<Vec<_>>>()?;
+        Fds::merge(images.iter().map(Vec::as_slice))?.to_bytes(args.fds_header)
+    };
+
+    format::write(&args.output, &data)
+}
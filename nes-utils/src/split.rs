@@ -0,0 +1,55 @@
+use {
+    crate::format::{self, Format},
+    anyhow::{bail, Result},
+    fds_parser::Fds,
+    ines_parser::Ines,
+    std::path::{Path, PathBuf},
+};
+
+/// Split a ROM into its PRG ROM, CHR ROM and trainer, or a disk image into its sides
+#[derive(clap::Args)]
+pub struct Args {
+    input: PathBuf,
+    /// Directory to write the parts to, the one of the input by default
+    #[arg(short, long)]
+    output_dir: Option<PathBuf>,
+}
+
+pub fn run(args: &Args) -> Result<()> {
+    let (data, format) = format::read(&args.input)?;
+    let directory = match &args.output_dir {
+        Some(directory) => directory.as_path(),
+        None => args.input.parent().unwrap_or_else(|| Path::new("")),
+    };
+    let stem = args
+        .input
+        .file_stem()
+        .map_or_else(|| "rom".into(), |stem| stem.to_string_lossy());
+    let output = |extension: &str| directory.join(format!("{stem}.{extension}"));
+
+    match format {
+        Format::Ines => {
+            let ines = Ines::from_bytes(&data)?;
+            format::write(&output("prg"), &ines.prg_rom)?;
+            if let Some(chr_rom) = &ines.chr_rom {
+                format::write(&output("chr"), chr_rom)?;
+            }
+            if let Some(trainer) = &ines.trainer {
+                format::write(&output("trainer"), trainer)?;
+            }
+        }
+        Format::Fds => {
+            let fds = Fds::from_bytes(&data)?;
+            let with_header = fds_parser::has_header(&data);
+            for (index, side) in fds.split().iter().enumerate() {
+                format::write(
+                    &output(&format!("side{index}.fds")),
+                    &side.to_bytes(with_header),
+                )?;
+            }
+        }
+        Format::Nsf | Format::Nsfe => bail!("{} files can't be split", format.name()),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,175 @@
+use {
+    crate::format::Format,
+    anyhow::{bail, Context, Result},
+    image::ColorType,
+    ines_parser::Ines,
+    lemonade::{ColourPalette, Lemonade, PaletteRam, Sheet, PALETTE_RAM_SIZE},
+    std::{
+        convert::TryFrom,
+        fs,
+        ops::Range,
+        path::{Path, PathBuf},
+    },
+};
+
+// A palette file holds either the four colour indices of one palette or a whole palette RAM dump
+const PALETTE_SIZE: usize = 4;
+
+/// Export the tiles of the CHR ROM of a ROM, or of a raw CHR dump, as PNG images
+#[derive(clap::Args)]
+pub struct Args {
+    /// ROM or raw CHR dump (as written by `split`)
+    input: PathBuf,
+    /// Directory to write the images to, the one of the input by default
+    #[arg(short, long)]
+    output_dir: Option<PathBuf>,
+    /// Four NES colour indices, or a 32 byte palette RAM dump
+    #[arg(short, long)]
+    palette: Option<PathBuf>,
+    /// Palette of the palette RAM dump to use, 0 - 3 are the background and 4 - 7 the sprite palettes
+    #[arg(long, default_value_t = 0, value_parser = clap::value_parser!(u8).range(0..8), requires = "palette")]
+    palette_number: u8,
+    /// Write every tile into its own image instead of one sheet
+    #[arg(long)]
+    per_tile: bool,
+    /// Tiles per row of the sheet
+    #[arg(long, default_value_t = 16, conflicts_with = "per_tile")]
+    per_row: usize,
+    /// Enlarge the images by this factor
+    #[arg(short, long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..=16))]
+    scale: u8,
+    /// Tiles to export as `START..END`, numbers can be hexadecimal with a `0x` or `$` prefix
+    #[arg(short, long, value_parser = parse_range)]
+    tiles: Option<Range<usize>>,
+}
+
+fn parse_number(number: &str) -> Result<usize> {
+    let number = number.trim();
+    let parsed = match number
+        .strip_prefix("0x")
+        .or_else(|| number.strip_prefix('$'))
+    {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => number.parse(),
+    };
+
+    parsed.with_context(|| format!("{number:?} isn't a number"))
+}
+
+fn parse_range(range: &str) -> Result<Range<usize>> {
+    let (start, end) = range
+        .split_once("..")
+        .context("Expected a range like 0..256")?;
+    let range = parse_number(start)?..parse_number(end)?;
+    if range.is_empty() {
+        bail!("The range is empty");
+    }
+
+    Ok(range)
+}
+
+fn read_palette(path: &Path, number: u8) -> Result<ColourPalette> {
+    let data = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+
+    if let Ok(indices) = <[u8; PALETTE_SIZE]>::try_from(data.as_slice()) {
+        let [background, colours @ ..] = indices.map(lemonade::nes_colour);
+        Ok(ColourPalette::new(background, colours))
+    } else if let Ok(dump) = <[u8; PALETTE_RAM_SIZE]>::try_from(data.as_slice()) {
+        let palette_ram = PaletteRam::new(dump);
+        let number = usize::from(number);
+        Ok(if number < 4 {
+            palette_ram.background_palettes()[number]
+        } else {
+            palette_ram.sprite_palettes()[number - 4]
+        })
+    } else {
+        bail!(
+            "{} has to contain {PALETTE_SIZE} colour indices or a {PALETTE_RAM_SIZE} byte palette RAM dump, got {} bytes",
+            path.display(),
+            data.len()
+        );
+    }
+}
+
+fn save(path: &Path, sheet: &Sheet) -> Result<()> {
+    image::save_buffer(
+        path,
+        &sheet.to_rgb(),
+        u32::try_from(sheet.width())?,
+        u32::try_from(sheet.height())?,
+        ColorType::Rgb8,
+    )
+    .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+pub fn run(args: &Args) -> Result<()> {
+    let data = fs::read(&args.input)
+        .with_context(|| format!("Failed to read {}", args.input.display()))?;
+    let ines;
+    let sprites = match Format::detect(&data) {
+        Some(Format::Ines) => {
+            ines = Ines::from_bytes(&data)?;
+            lemonade::from_ines(&ines).map_err(|_| {
+                anyhow::anyhow!(
+                    "{} uses CHR RAM, its tiles are stored in the PRG ROM",
+                    args.input.display()
+                )
+            })?
+        }
+        Some(format) => bail!("{} files don't contain tiles", format.name()),
+        None => Lemonade::new(&data),
+    };
+
+    let num_sprites = sprites.num_sprites();
+    let range = args.tiles.clone().unwrap_or(0..num_sprites);
+    let sprites = sprites.range(range.clone()).with_context(|| {
+        format!(
+            "Tiles {range:?} are out of bounds, {} has {num_sprites}",
+            args.input.display()
+        )
+    })?;
+    if sprites.num_sprites() == 0 {
+        bail!("{} contains no tiles", args.input.display());
+    }
+
+    let palette = match &args.palette {
+        Some(path) => read_palette(path, args.palette_number)?,
+        None => ColourPalette::CLASSIC_MARIO,
+    };
+    let scale = usize::from(args.scale);
+
+    let directory = match &args.output_dir {
+        Some(directory) => directory.as_path(),
+        None => args.input.parent().unwrap_or_else(|| Path::new("")),
+    };
+    let stem = args
+        .input
+        .file_stem()
+        .map_or_else(|| "rom".into(), |stem| stem.to_string_lossy());
+    fs::create_dir_all(directory)
+        .with_context(|| format!("Failed to create {}", directory.display()))?;
+
+    if args.per_tile {
+        for (index, sprite) in range.zip(sprites.clone()) {
+            let sheet = Sheet::render(Lemonade::new(sprite.buffer()), palette, 1).scale(scale);
+            save(&directory.join(format!("{stem}.tile{index}.png")), &sheet)?;
+        }
+        println!(
+            "Wrote {} tiles to {}",
+            sprites.num_sprites(),
+            directory.display()
+        );
+    } else {
+        let sheet = Sheet::render(sprites, palette, args.per_row).scale(scale);
+        let path = directory.join(format!("{stem}.png"));
+        save(&path, &sheet)?;
+        println!(
+            "Wrote {} ({}x{} pixels)",
+            path.display(),
+            sheet.width(),
+            sheet.height()
+        );
+    }
+
+    Ok(())
+}
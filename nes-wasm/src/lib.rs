@@ -0,0 +1,75 @@
+#![warn(clippy::all, clippy::pedantic)]
+#![allow(clippy::missing_errors_doc)]
+
+//!
+//! JavaScript bindings to the parsers of this workspace via `wasm-bindgen`, for ROM inspectors running in the browser
+//!
+//! Files are passed as `Uint8Array`s and never leave the page.
+//! The sprite functions of `lemonade` (`decodeSprite`, `spriteToRgba`, `numSprites` and `renderSheet`) are re-exported,
+//! they take palettes as 12 bytes (the RGB values of the background colour followed by the three palette colours).
+//!
+
+use wasm_bindgen::prelude::*;
+
+mod rom;
+
+pub use {
+    lemonade::wasm::{decode_sprite, num_sprites, render_sheet, sprite_to_rgba},
+    rom::{chr_rom, parse_header, prg_rom, Header, Timing, VramLayout},
+};
+
+// Magic bytes of the formats, which all end in the MS-DOS EOF delimiter except for NSFe
+const INES_MAGIC: &[u8] = b"NES\x1A";
+const NSF_MAGIC: &[u8] = b"NESM\x1A";
+const NSFE_MAGIC: &[u8] = b"NSFE";
+
+/// Formats `detectFormat` recognises
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// INES and NES 2.0 ROMs
+    Ines,
+    /// Disk images of the Famicom Disk System, with or without a fwNES header
+    Fds,
+    /// NSF and NSF2 files
+    Nsf,
+    Nsfe,
+}
+
+/// Format of the file by its magic bytes, `undefined` if it isn't one of the supported formats
+///
+/// FDS images without a header are recognised by their size, which is a multiple of 65500 bytes
+#[wasm_bindgen(js_name = detectFormat)]
+#[must_use]
+pub fn detect_format(data: &[u8]) -> Option<Format> {
+    if data.starts_with(INES_MAGIC) {
+        Some(Format::Ines)
+    } else if data.starts_with(NSF_MAGIC) {
+        Some(Format::Nsf)
+    } else if data.starts_with(NSFE_MAGIC) {
+        Some(Format::Nsfe)
+    } else if fds_parser::has_header(data)
+        || (!data.is_empty() && data.len().is_multiple_of(fds_parser::SIDE_SIZE))
+    {
+        Some(Format::Fds)
+    } else {
+        None
+    }
+}
+
+/// Turn four indices into the NES master palette into the 12 byte palette the sprite functions take
+///
+/// Only the lower six bits of the indices are taken into account
+#[wasm_bindgen(js_name = nesPalette)]
+pub fn nes_palette(indices: &[u8]) -> Result<Vec<u8>, JsError> {
+    if indices.len() != 4 {
+        return Err(JsError::new(
+            "The palette has to consist of exactly 4 colour indices",
+        ));
+    }
+
+    Ok(indices
+        .iter()
+        .flat_map(|&index| lemonade::nes_colour(index).raw_colour())
+        .collect())
+}
@@ -0,0 +1,118 @@
+use {ines_parser::Ines, std::borrow::Cow, wasm_bindgen::prelude::*};
+
+/// Nametable layout the header asks for
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VramLayout {
+    HorizontalMirroring,
+    VerticalMirroring,
+    FourScreen,
+}
+
+/// CPU/PPU timing of the console a game is made for
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Timing {
+    Ntsc,
+    Pal,
+    MultiRegion,
+    Dendy,
+}
+
+/// Parsed header of an INES or NES 2.0 ROM
+#[wasm_bindgen]
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone, Copy, Debug)]
+pub struct Header {
+    #[wasm_bindgen(readonly, js_name = prgRomSize)]
+    pub prg_rom_size: usize,
+    #[wasm_bindgen(readonly, js_name = chrRomSize)]
+    pub chr_rom_size: usize,
+    /// Size of the volatile PRG RAM
+    #[wasm_bindgen(readonly, js_name = prgRamSize)]
+    pub prg_ram_size: usize,
+    /// Size of the battery-backed PRG RAM
+    #[wasm_bindgen(readonly, js_name = prgNvramSize)]
+    pub prg_nvram_size: usize,
+    /// Size of the volatile CHR RAM
+    #[wasm_bindgen(readonly, js_name = chrRamSize)]
+    pub chr_ram_size: usize,
+    /// Size of the battery-backed CHR RAM
+    #[wasm_bindgen(readonly, js_name = chrNvramSize)]
+    pub chr_nvram_size: usize,
+    #[wasm_bindgen(readonly, js_name = vramLayout)]
+    pub vram_layout: VramLayout,
+    /// INES 1 headers only tell NTSC and PAL apart
+    #[wasm_bindgen(readonly)]
+    pub timing: Timing,
+    #[wasm_bindgen(readonly)]
+    pub mapper: u8,
+    /// Submapper number (NES 2.0 only)
+    #[wasm_bindgen(readonly)]
+    pub submapper: u8,
+    /// Input device the game expects, numbered like on the nesdev wiki (NES 2.0 only)
+    #[wasm_bindgen(readonly, js_name = defaultExpansionDevice)]
+    pub default_expansion_device: u8,
+    #[wasm_bindgen(readonly, js_name = isNes2)]
+    pub is_nes2: bool,
+    #[wasm_bindgen(readonly, js_name = hasBattery)]
+    pub has_battery: bool,
+    #[wasm_bindgen(readonly, js_name = hasTrainer)]
+    pub has_trainer: bool,
+}
+
+impl From<&ines_parser::Header> for Header {
+    fn from(header: &ines_parser::Header) -> Self {
+        Self {
+            prg_rom_size: header.prg_rom_size,
+            chr_rom_size: header.chr_rom_size,
+            prg_ram_size: header.prg_ram_size,
+            prg_nvram_size: header.prg_nvram_size,
+            chr_ram_size: header.chr_ram_size,
+            chr_nvram_size: header.chr_nvram_size,
+            vram_layout: match header.vram_layout {
+                ines_parser::VramLayout::HorizontalMirroring => VramLayout::HorizontalMirroring,
+                ines_parser::VramLayout::VerticalMirroring => VramLayout::VerticalMirroring,
+                ines_parser::VramLayout::FourScreen => VramLayout::FourScreen,
+            },
+            timing: match header.timing {
+                ines_parser::Timing::Ntsc => Timing::Ntsc,
+                ines_parser::Timing::Pal => Timing::Pal,
+                ines_parser::Timing::MultiRegion => Timing::MultiRegion,
+                ines_parser::Timing::Dendy => Timing::Dendy,
+            },
+            mapper: header.mapper_number,
+            submapper: header.submapper,
+            default_expansion_device: header.default_expansion_device,
+            is_nes2: header.is_nes2,
+            has_battery: header.has_persistent_memory,
+            has_trainer: header.has_trainer(),
+        }
+    }
+}
+
+fn parse_rom(data: &[u8]) -> Result<Ines<'_>, JsError> {
+    Ines::from_bytes(data).map_err(|err| JsError::new(&err.to_string()))
+}
+
+/// Parse the header of a ROM, which only needs the first 16 bytes of the file
+#[wasm_bindgen(js_name = parseHeader)]
+pub fn parse_header(data: &[u8]) -> Result<Header, JsError> {
+    ines_parser::Header::from_bytes(data)
+        .map(|header| Header::from(&header))
+        .map_err(|err| JsError::new(&err.to_string()))
+}
+
+/// Copy the PRG ROM out of a ROM
+#[wasm_bindgen(js_name = prgRom)]
+pub fn prg_rom(data: &[u8]) -> Result<Vec<u8>, JsError> {
+    Ok(parse_rom(data)?.prg_rom.into_owned())
+}
+
+/// Copy the CHR ROM out of a ROM, ready to be passed to the sprite functions
+///
+/// Returns `undefined` for games using CHR RAM, whose tiles are stored in the PRG ROM
+#[wasm_bindgen(js_name = chrRom)]
+pub fn chr_rom(data: &[u8]) -> Result<Option<Vec<u8>>, JsError> {
+    Ok(parse_rom(data)?.chr_rom.map(Cow::into_owned))
+}
@@ -0,0 +1,190 @@
+//!
+//! Expansion audio chips declared in the header and used by the program
+//!
+//! [Expansion audio documentation](https://www.nesdev.org/wiki/Expansion_audio)
+//!
+
+use {
+    crate::{bit_at, Header, Nsf},
+    alloc::collections::BTreeSet,
+    core::ops::RangeInclusive,
+};
+
+// Stores with absolute addressing, including the indexed variants
+const STA_ABSOLUTE: u8 = 0x8D;
+const STX_ABSOLUTE: u8 = 0x8E;
+const STY_ABSOLUTE: u8 = 0x8C;
+const STA_ABSOLUTE_X: u8 = 0x9D;
+const STA_ABSOLUTE_Y: u8 = 0x99;
+
+// Any byte of data can look like a store, so a chip only counts as used once this many of its registers are written to
+const MIN_REGISTERS: usize = 2;
+
+/// Expansion audio chip
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ExpansionChip {
+    /// Konami VRC6 (two pulse channels and a sawtooth channel)
+    Vrc6,
+    /// Konami VRC7 (six FM channels)
+    Vrc7,
+    /// Famicom Disk System (a wavetable channel)
+    Fds,
+    /// Nintendo MMC5 (two pulse channels and a PCM channel)
+    Mmc5,
+    /// Namco 163 (up to eight wavetable channels)
+    N163,
+    /// Sunsoft 5B (three square wave channels)
+    Sunsoft5B,
+}
+
+impl ExpansionChip {
+    /// All chips in the order of their bits in the header
+    pub const ALL: [Self; 6] = [
+        Self::Vrc6,
+        Self::Vrc7,
+        Self::Fds,
+        Self::Mmc5,
+        Self::N163,
+        Self::Sunsoft5B,
+    ];
+
+    /// Bit of the chip in the expansion audio byte of the header
+    #[must_use]
+    pub fn bit(self) -> u8 {
+        match self {
+            Self::Vrc6 => 0,
+            Self::Vrc7 => 1,
+            Self::Fds => 2,
+            Self::Mmc5 => 3,
+            Self::N163 => 4,
+            Self::Sunsoft5B => 5,
+        }
+    }
+
+    /// Registers of the sound hardware
+    ///
+    /// Registers the chip shares with the mapper, like the bank registers of the MMC5, aren't included
+    #[must_use]
+    pub fn registers(self) -> &'static [RangeInclusive<u16>] {
+        match self {
+            Self::Vrc6 => &[0x9000..=0x9003, 0xA000..=0xA002, 0xB000..=0xB002],
+            // Register select and data
+            Self::Vrc7 => &[0x9010..=0x9010, 0x9030..=0x9030],
+            // Wavetable RAM, then the channel and modulation registers
+            Self::Fds => &[0x4040..=0x407F, 0x4080..=0x408A],
+            Self::Mmc5 => &[0x5000..=0x5007, 0x5010..=0x5011, 0x5015..=0x5015],
+            // Data port and address port
+            Self::N163 => &[0x4800..=0x4800, 0xF800..=0xF800],
+            // Register select and data
+            Self::Sunsoft5B => &[0xC000..=0xC000, 0xE000..=0xE000],
+        }
+    }
+
+    fn is_register(self, address: u16) -> bool {
+        self.registers()
+            .iter()
+            .any(|registers| registers.contains(&address))
+    }
+}
+
+/// Chips set in the expansion audio byte of a header
+#[must_use]
+pub fn chips_from_flags(flags: u8) -> BTreeSet<ExpansionChip> {
+    ExpansionChip::ALL
+        .iter()
+        .copied()
+        .filter(|chip| bit_at(flags, chip.bit()))
+        .collect()
+}
+
+/// Expansion audio byte of a header with the chips set
+#[must_use]
+pub fn flags_from_chips<'a, I>(chips: I) -> u8
+where
+    I: IntoIterator<Item = &'a ExpansionChip>,
+{
+    chips
+        .into_iter()
+        .fold(0, |flags, chip| flags | (1 << chip.bit()))
+}
+
+/// Expansion chips declared in the header compared with the ones the program writes to
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExpansionAudio {
+    pub declared: BTreeSet<ExpansionChip>,
+    pub detected: BTreeSet<ExpansionChip>,
+}
+
+impl ExpansionAudio {
+    /// Chips a player has to emulate to play the tune
+    ///
+    /// Players only enable the chips of the header, but detected chips are included in case the header is wrong
+    #[must_use]
+    pub fn required(&self) -> BTreeSet<ExpansionChip> {
+        self.declared.union(&self.detected).copied().collect()
+    }
+
+    /// Chips the program writes to without them being declared in the header
+    #[must_use]
+    pub fn undeclared(&self) -> BTreeSet<ExpansionChip> {
+        self.detected.difference(&self.declared).copied().collect()
+    }
+
+    /// Chips declared in the header without any writes to them being found
+    ///
+    /// Writes through pointers (`STA ($00),Y`) can't be found, so these aren't necessarily unused
+    #[must_use]
+    pub fn unused(&self) -> BTreeSet<ExpansionChip> {
+        self.declared.difference(&self.detected).copied().collect()
+    }
+}
+
+impl Header {
+    /// Expansion chips declared in the header
+    #[must_use]
+    pub fn expansion_chips(&self) -> BTreeSet<ExpansionChip> {
+        chips_from_flags(self.expansion_audio)
+    }
+}
+
+impl Nsf<'_> {
+    /// Expansion chips the program writes to
+    ///
+    /// The program data is scanned for absolute stores to the registers of the chips,
+    /// which finds the writes of all common sound drivers without having to run them.
+    #[must_use]
+    pub fn detect_expansion_chips(&self) -> BTreeSet<ExpansionChip> {
+        let targets: BTreeSet<u16> = self
+            .data
+            .windows(3)
+            .filter(|window| {
+                matches!(
+                    window[0],
+                    STA_ABSOLUTE | STX_ABSOLUTE | STY_ABSOLUTE | STA_ABSOLUTE_X | STA_ABSOLUTE_Y
+                )
+            })
+            .map(|window| u16::from_le_bytes([window[1], window[2]]))
+            .collect();
+
+        ExpansionChip::ALL
+            .iter()
+            .copied()
+            .filter(|chip| {
+                targets
+                    .iter()
+                    .filter(|address| chip.is_register(**address))
+                    .count()
+                    >= MIN_REGISTERS
+            })
+            .collect()
+    }
+
+    /// Expansion chips of the header together with the ones detected in the program
+    #[must_use]
+    pub fn expansion_audio(&self) -> ExpansionAudio {
+        ExpansionAudio {
+            declared: self.header.expansion_chips(),
+            detected: self.detect_expansion_chips(),
+        }
+    }
+}
@@ -0,0 +1,274 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![warn(clippy::all, clippy::pedantic)]
+#![allow(clippy::missing_errors_doc)]
+
+//!
+//! Parser for the NSF (NES Sound Format) file format  
+//!
+//! [File format documentation](https://www.nesdev.org/wiki/NSF)
+//!
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+use std::io::{self, Read};
+
+use {
+    alloc::{borrow::Cow, string::String, vec::Vec},
+    core::{array::TryFromSliceError, convert::TryInto},
+};
+
+pub mod expansion;
+pub mod nsfe;
+
+pub use expansion::{ExpansionAudio, ExpansionChip};
+
+// The word "NESM" followed by the MS-DOS EOF delimiter
+const MAGIC_BYTES: [u8; 5] = [0x4E, 0x45, 0x53, 0x4D, 0x1A];
+
+const HEADER_SIZE: usize = 0x80;
+const TEXT_SIZE: usize = 32;
+
+const NSF2_VERSION: u8 = 2;
+// Flag in the NSF2 header telling that metadata chunks follow the program data
+const NSF2_METADATA_FLAG: u8 = 7;
+
+type Result<T> = core::result::Result<T, Error>;
+
+#[derive(Debug)]
+#[cfg_attr(feature = "std", derive(thiserror::Error))]
+pub enum Error {
+    #[cfg(feature = "std")]
+    #[error("IO error: {:?}", .0)]
+    Io(#[from] io::Error),
+
+    #[cfg_attr(feature = "std", error("Magic bytes didn't match; expected {:?}, got {:?}", MAGIC_BYTES, .0))]
+    MagicBytesMismatch([u8; 5]),
+
+    #[cfg_attr(feature = "std", error("File is shorter than the header"))]
+    UnexpectedEof,
+
+    #[cfg_attr(feature = "std", error("TryFromSliceError"))]
+    TryFromSlice(TryFromSliceError),
+
+    #[cfg_attr(feature = "std", error("Magic bytes didn't match; expected NSFE, got {:?}", .0))]
+    InvalidNsfeMagic([u8; 4]),
+
+    #[cfg_attr(feature = "std", error("The file doesn't contain the chunk {:?}", .0))]
+    MissingChunk([u8; 4]),
+
+    #[cfg_attr(feature = "std", error("The chunk {:?} is too short", .0))]
+    InvalidChunk([u8; 4]),
+
+    #[cfg_attr(feature = "std", error("The program data is too large ({} bytes)", .0))]
+    TooLarge(usize),
+}
+
+impl From<TryFromSliceError> for Error {
+    fn from(err: TryFromSliceError) -> Self {
+        Self::TryFromSlice(err)
+    }
+}
+
+/// TV system the music was written for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Region {
+    Ntsc,
+    Pal,
+    /// Plays on both systems (the init routine gets told which one is used)
+    Dual,
+}
+
+impl Region {
+    // Flags of the region byte in the header and the INFO chunk of NSFe files
+    fn from_flags(flags: u8) -> Self {
+        if bit_at(flags, 1) {
+            Self::Dual
+        } else if bit_at(flags, 0) {
+            Self::Pal
+        } else {
+            Self::Ntsc
+        }
+    }
+
+    fn flags(self) -> u8 {
+        match self {
+            Self::Ntsc => 0b00,
+            Self::Pal => 0b01,
+            Self::Dual => 0b10,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Header {
+    pub version: u8,
+    pub total_songs: u8,
+    /// Song to play first (1-based)
+    pub starting_song: u8,
+
+    pub load_address: u16,
+    pub init_address: u16,
+    pub play_address: u16,
+
+    pub name: String,
+    pub artist: String,
+    pub copyright: String,
+
+    /// Interval between two calls of the play routine on NTSC consoles in microseconds
+    pub ntsc_speed: u16,
+    /// Interval between two calls of the play routine on PAL consoles in microseconds
+    pub pal_speed: u16,
+    /// Initial banks of the 4 KiB slots at `$8000`-`$FFFF`, if the tune uses bankswitching
+    pub bankswitch: Option<[u8; 8]>,
+
+    pub region: Region,
+    /// Expansion audio chips used by the tune, one bit per chip (see [`ExpansionChip::bit`])
+    pub expansion_audio: u8,
+    /// Feature flags of NSF2 files, zero in older versions
+    pub flags: u8,
+}
+
+// Same as with INES ROMs, the data can either be borrowed or owned
+pub struct Nsf<'a> {
+    /// Header
+    pub header: Header,
+    /// Program data, loaded at the load address
+    pub data: Cow<'a, [u8]>,
+}
+
+fn bit_at(num: u8, offset: u8) -> bool {
+    (num >> offset) & 1 == 1
+}
+
+fn parse_text(data: &[u8]) -> String {
+    // The fields are padded with null bytes
+    let end = data
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).into_owned()
+}
+
+// Text fields are cut off at their size and padded with null bytes
+fn write_text(output: &mut [u8], text: &str) {
+    let length = text.len().min(TEXT_SIZE - 1);
+    output[..length].copy_from_slice(&text.as_bytes()[..length]);
+}
+
+fn parse_word(data: &[u8], offset: usize) -> Result<u16> {
+    Ok(u16::from_le_bytes(data[offset..offset + 2].try_into()?))
+}
+
+// Length of the program data of NSF2 files, zero if it runs until the end of the file
+fn nsf2_program_length(header_data: &[u8]) -> usize {
+    if header_data[0x05] < NSF2_VERSION {
+        return 0;
+    }
+
+    u32::from_le_bytes([header_data[0x7D], header_data[0x7E], header_data[0x7F], 0]) as usize
+}
+
+fn parse_header(header_data: &[u8]) -> Result<Header> {
+    let header_data = header_data.get(..HEADER_SIZE).ok_or(Error::UnexpectedEof)?;
+
+    let magic_bytes = header_data[0..5].try_into()?;
+    if magic_bytes != MAGIC_BYTES {
+        return Err(Error::MagicBytesMismatch(magic_bytes));
+    }
+
+    let bankswitch: [u8; 8] = header_data[0x70..0x78].try_into()?;
+    let bankswitch = if bankswitch.iter().any(|bank| *bank != 0) {
+        Some(bankswitch)
+    } else {
+        None
+    };
+
+    Ok(Header {
+        version: header_data[0x05],
+        total_songs: header_data[0x06],
+        starting_song: header_data[0x07],
+        load_address: parse_word(header_data, 0x08)?,
+        init_address: parse_word(header_data, 0x0A)?,
+        play_address: parse_word(header_data, 0x0C)?,
+        name: parse_text(&header_data[0x0E..0x0E + TEXT_SIZE]),
+        artist: parse_text(&header_data[0x2E..0x2E + TEXT_SIZE]),
+        copyright: parse_text(&header_data[0x4E..0x4E + TEXT_SIZE]),
+        ntsc_speed: parse_word(header_data, 0x6E)?,
+        bankswitch,
+        pal_speed: parse_word(header_data, 0x78)?,
+        region: Region::from_flags(header_data[0x7A]),
+        expansion_audio: header_data[0x7B],
+        flags: header_data[0x7C],
+    })
+}
+
+impl<'a> Nsf<'a> {
+    /// Parse an NSF file from a byte slice
+    pub fn from_bytes(data: &'a [u8]) -> Result<Self> {
+        let header = parse_header(data)?;
+        // Metadata chunks can follow the program data of NSF2 files
+        let data = match nsf2_program_length(data) {
+            0 => &data[HEADER_SIZE..],
+            length => data
+                .get(HEADER_SIZE..HEADER_SIZE + length)
+                .ok_or(Error::UnexpectedEof)?,
+        };
+        let data = Cow::Borrowed(data);
+
+        Ok(Nsf { header, data })
+    }
+
+    #[cfg(feature = "std")]
+    /// Parse an NSF file from a file stream
+    pub fn from_reader<T: Read>(input_stream: &mut T) -> Result<Self> {
+        let mut header = [0; HEADER_SIZE];
+        input_stream.read_exact(&mut header)?;
+
+        let length = nsf2_program_length(&header);
+        let header = parse_header(&header)?;
+
+        // Only NSF2 headers can contain the size of the data, otherwise everything until the end gets read
+        let mut data = Vec::new();
+        if length == 0 {
+            input_stream.read_to_end(&mut data)?;
+        } else {
+            data.resize(length, 0);
+            input_stream.read_exact(&mut data)?;
+        }
+
+        Ok(Nsf {
+            header,
+            data: Cow::Owned(data),
+        })
+    }
+
+    /// Serialize the file
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let header = &self.header;
+        let mut data = Vec::with_capacity(HEADER_SIZE + self.data.len());
+        data.resize(HEADER_SIZE, 0);
+
+        data[0x00..0x05].copy_from_slice(&MAGIC_BYTES);
+        data[0x05] = header.version;
+        data[0x06] = header.total_songs;
+        data[0x07] = header.starting_song;
+        data[0x08..0x0A].copy_from_slice(&header.load_address.to_le_bytes());
+        data[0x0A..0x0C].copy_from_slice(&header.init_address.to_le_bytes());
+        data[0x0C..0x0E].copy_from_slice(&header.play_address.to_le_bytes());
+        write_text(&mut data[0x0E..0x0E + TEXT_SIZE], &header.name);
+        write_text(&mut data[0x2E..0x2E + TEXT_SIZE], &header.artist);
+        write_text(&mut data[0x4E..0x4E + TEXT_SIZE], &header.copyright);
+        data[0x6E..0x70].copy_from_slice(&header.ntsc_speed.to_le_bytes());
+        data[0x70..0x78].copy_from_slice(&header.bankswitch.unwrap_or_default());
+        data[0x78..0x7A].copy_from_slice(&header.pal_speed.to_le_bytes());
+        data[0x7A] = header.region.flags();
+        data[0x7B] = header.expansion_audio;
+        // The metadata isn't written, so the program length stays zero and the data runs until the end of the file
+        data[0x7C] = header.flags & !(1 << NSF2_METADATA_FLAG);
+
+        data.extend_from_slice(&self.data);
+        data
+    }
+}
@@ -0,0 +1,425 @@
+//!
+//! Metadata chunks of `NSFe` and NSF2 files
+//!
+//! [`NSFe` documentation](https://www.nesdev.org/wiki/`NSFe`),
+//! [NSF2 documentation](https://www.nesdev.org/wiki/NSF2)
+//!
+
+use {
+    crate::{
+        bit_at, nsf2_program_length, parse_word, Error, Header, Nsf, Region, Result, HEADER_SIZE,
+        NSF2_METADATA_FLAG, NSF2_VERSION,
+    },
+    alloc::{borrow::Cow, string::String, vec::Vec},
+    core::convert::{TryFrom, TryInto},
+};
+
+const MAGIC_BYTES: [u8; 4] = *b"NSFE";
+
+const INFO: [u8; 4] = *b"INFO";
+const DATA: [u8; 4] = *b"DATA";
+const BANK: [u8; 4] = *b"BANK";
+const RATE: [u8; 4] = *b"RATE";
+const AUTH: [u8; 4] = *b"auth";
+const TLBL: [u8; 4] = *b"tlbl";
+const TIME: [u8; 4] = *b"time";
+const FADE: [u8; 4] = *b"fade";
+const PLST: [u8; 4] = *b"plst";
+const NEND: [u8; 4] = *b"NEND";
+
+// INFO chunks without the total and starting song fields
+const MIN_INFO_SIZE: usize = 8;
+
+const MAX_NSF2_DATA_SIZE: usize = 0xFF_FFFF;
+
+// Players use their default length if a track doesn't have a time
+const DEFAULT_TIME: i32 = -1;
+
+const NTSC_SPEED: u16 = 16639;
+const PAL_SPEED: u16 = 19997;
+
+/// Chunk of an `NSFe` file or of the metadata of an NSF2 file
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Chunk<'a> {
+    /// Four character ID; chunks starting with an uppercase letter are required to play the file
+    pub id: [u8; 4],
+    pub data: Cow<'a, [u8]>,
+}
+
+impl Chunk<'_> {
+    #[must_use]
+    pub fn is_required(&self) -> bool {
+        self.id[0].is_ascii_uppercase()
+    }
+}
+
+/// Metadata of a single track
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Track {
+    pub title: Option<String>,
+    /// Length in milliseconds
+    pub time: Option<u32>,
+    /// Length of the fade-out after the track in milliseconds
+    pub fade: Option<u32>,
+}
+
+/// Metadata stored in the optional chunks
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Metadata {
+    /// Names of the `auth` chunk, which aren't limited to the 32 bytes of the header
+    pub name: Option<String>,
+    pub artist: Option<String>,
+    pub copyright: Option<String>,
+    pub ripper: Option<String>,
+
+    pub tracks: Vec<Track>,
+    /// Order of the tracks (0-based) to play them in
+    pub playlist: Option<Vec<u8>>,
+
+    /// Chunks the metadata doesn't know about, kept as is
+    pub chunks: Vec<Chunk<'static>>,
+}
+
+/// Parse chunks until the `NEND` chunk
+pub fn parse_chunks(data: &[u8]) -> Result<Vec<Chunk<'_>>> {
+    Ok(raw_chunks(data)?
+        .into_iter()
+        .map(|(id, data)| Chunk {
+            id,
+            data: Cow::Borrowed(data),
+        })
+        .collect())
+}
+
+fn raw_chunks(mut data: &[u8]) -> Result<Vec<([u8; 4], &[u8])>> {
+    let mut chunks = Vec::new();
+
+    loop {
+        let length = data.get(..4).ok_or(Error::UnexpectedEof)?;
+        let length = u32::from_le_bytes(length.try_into()?) as usize;
+        let id: [u8; 4] = data.get(4..8).ok_or(Error::UnexpectedEof)?.try_into()?;
+        if id == NEND {
+            break;
+        }
+
+        let end = length.checked_add(8).ok_or(Error::InvalidChunk(id))?;
+        let chunk = data.get(8..end).ok_or(Error::UnexpectedEof)?;
+        chunks.push((id, chunk));
+        data = &data[end..];
+    }
+
+    Ok(chunks)
+}
+
+/// Serialize the chunks, followed by the `NEND` chunk
+pub fn write_chunks<'a, I>(chunks: I) -> Vec<u8>
+where
+    I: IntoIterator<Item = &'a Chunk<'a>>,
+{
+    let mut data = Vec::new();
+    for chunk in chunks {
+        write_chunk(&mut data, chunk.id, &chunk.data);
+    }
+    write_chunk(&mut data, NEND, &[]);
+
+    data
+}
+
+fn write_chunk(output: &mut Vec<u8>, id: [u8; 4], data: &[u8]) {
+    // Chunks of NES music are nowhere near 4 GiB
+    #[allow(clippy::cast_possible_truncation)]
+    output.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    output.extend_from_slice(&id);
+    output.extend_from_slice(data);
+}
+
+// Strings of the chunks are terminated with null bytes
+fn parse_strings(data: &[u8]) -> Vec<String> {
+    let data = data.strip_suffix(&[0]).unwrap_or(data);
+    if data.is_empty() {
+        return Vec::new();
+    }
+
+    data.split(|byte| *byte == 0)
+        .map(|text| String::from_utf8_lossy(text).into_owned())
+        .collect()
+}
+
+fn write_strings<'a, I>(strings: I) -> Vec<u8>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut data = Vec::new();
+    for text in strings {
+        data.extend_from_slice(text.as_bytes());
+        data.push(0);
+    }
+
+    data
+}
+
+fn parse_times(data: &[u8]) -> Vec<Option<u32>> {
+    data.chunks_exact(4)
+        .map(|time| {
+            let time = i32::from_le_bytes([time[0], time[1], time[2], time[3]]);
+            u32::try_from(time).ok()
+        })
+        .collect()
+}
+
+fn write_times<I>(times: I) -> Vec<u8>
+where
+    I: IntoIterator<Item = Option<u32>>,
+{
+    times
+        .into_iter()
+        .flat_map(|time| {
+            time.and_then(|time| i32::try_from(time).ok())
+                .unwrap_or(DEFAULT_TIME)
+                .to_le_bytes()
+        })
+        .collect()
+}
+
+impl Metadata {
+    /// Collect the metadata of the chunks, ignoring the chunks describing the program
+    #[must_use]
+    pub fn from_chunks(chunks: &[Chunk<'_>]) -> Self {
+        let mut metadata = Self::default();
+
+        for chunk in chunks {
+            match chunk.id {
+                INFO | DATA | BANK | RATE => (),
+                AUTH => {
+                    let mut names = parse_strings(&chunk.data).into_iter();
+                    metadata.name = names.next();
+                    metadata.artist = names.next();
+                    metadata.copyright = names.next();
+                    metadata.ripper = names.next();
+                }
+                TLBL => {
+                    for (track, title) in parse_strings(&chunk.data).into_iter().enumerate() {
+                        // Tracks without a title are written as empty strings
+                        if !title.is_empty() {
+                            metadata.track_mut(track).title = Some(title);
+                        }
+                    }
+                }
+                TIME => {
+                    for (track, time) in parse_times(&chunk.data).into_iter().enumerate() {
+                        metadata.track_mut(track).time = time;
+                    }
+                }
+                FADE => {
+                    for (track, fade) in parse_times(&chunk.data).into_iter().enumerate() {
+                        metadata.track_mut(track).fade = fade;
+                    }
+                }
+                PLST => metadata.playlist = Some(chunk.data.to_vec()),
+                id => metadata.chunks.push(Chunk {
+                    id,
+                    data: Cow::Owned(chunk.data.to_vec()),
+                }),
+            }
+        }
+
+        metadata
+    }
+
+    /// Read the metadata of an NSF2 file, if it has any
+    pub fn from_nsf2(data: &[u8]) -> Result<Option<Self>> {
+        let header = data.get(..HEADER_SIZE).ok_or(Error::UnexpectedEof)?;
+        if header[0x05] < NSF2_VERSION || !bit_at(header[0x7C], NSF2_METADATA_FLAG) {
+            return Ok(None);
+        }
+
+        let chunks = data
+            .get(HEADER_SIZE + nsf2_program_length(header)..)
+            .ok_or(Error::UnexpectedEof)?;
+
+        Ok(Some(Self::from_chunks(&parse_chunks(chunks)?)))
+    }
+
+    /// Metadata of the track, adding empty tracks up to it if needed
+    pub fn track_mut(&mut self, track: usize) -> &mut Track {
+        if self.tracks.len() <= track {
+            self.tracks.resize_with(track + 1, Track::default);
+        }
+
+        &mut self.tracks[track]
+    }
+
+    /// Chunks of the metadata; the `auth` chunk uses the names of the header where the metadata has none
+    #[must_use]
+    pub fn to_chunks(&self, header: &Header) -> Vec<Chunk<'static>> {
+        let mut chunks = Vec::new();
+        let mut push = |id, data| {
+            chunks.push(Chunk {
+                id,
+                data: Cow::Owned(data),
+            });
+        };
+
+        let names = [
+            self.name.as_deref().unwrap_or(&header.name),
+            self.artist.as_deref().unwrap_or(&header.artist),
+            self.copyright.as_deref().unwrap_or(&header.copyright),
+            self.ripper.as_deref().unwrap_or_default(),
+        ];
+        push(AUTH, write_strings(names.iter().copied()));
+
+        if self.tracks.iter().any(|track| track.title.is_some()) {
+            push(
+                TLBL,
+                write_strings(
+                    self.tracks
+                        .iter()
+                        .map(|track| track.title.as_deref().unwrap_or_default()),
+                ),
+            );
+        }
+        if self.tracks.iter().any(|track| track.time.is_some()) {
+            push(
+                TIME,
+                write_times(self.tracks.iter().map(|track| track.time)),
+            );
+        }
+        if self.tracks.iter().any(|track| track.fade.is_some()) {
+            push(
+                FADE,
+                write_times(self.tracks.iter().map(|track| track.fade)),
+            );
+        }
+        if let Some(playlist) = &self.playlist {
+            push(PLST, playlist.clone());
+        }
+
+        chunks.extend(self.chunks.iter().cloned());
+        chunks
+    }
+}
+
+/// Parse an `NSFe` file into the program and its metadata
+pub fn parse(data: &[u8]) -> Result<(Nsf<'_>, Metadata)> {
+    let magic_bytes: [u8; 4] = data.get(..4).ok_or(Error::UnexpectedEof)?.try_into()?;
+    if magic_bytes != MAGIC_BYTES {
+        return Err(Error::InvalidNsfeMagic(magic_bytes));
+    }
+
+    let chunks = raw_chunks(&data[4..])?;
+    let find = |id: [u8; 4]| {
+        chunks
+            .iter()
+            .find(|(chunk_id, _)| *chunk_id == id)
+            .map(|(_, chunk)| *chunk)
+    };
+
+    let info = find(INFO).ok_or(Error::MissingChunk(INFO))?;
+    if info.len() < MIN_INFO_SIZE {
+        return Err(Error::InvalidChunk(INFO));
+    }
+    let program = find(DATA).ok_or(Error::MissingChunk(DATA))?;
+
+    let bankswitch = find(BANK).map(|banks| {
+        let mut bankswitch = [0; 8];
+        for (bank, value) in bankswitch.iter_mut().zip(banks.iter()) {
+            *bank = *value;
+        }
+        bankswitch
+    });
+    let rate = find(RATE);
+    let speed = |offset: usize, default: u16| {
+        rate.filter(|rate| rate.len() >= offset + 2)
+            .map_or(Ok(default), |rate| parse_word(rate, offset))
+    };
+
+    let metadata = Metadata::from_chunks(&parse_chunks(&data[4..])?);
+    let header = Header {
+        version: 1,
+        total_songs: info.get(8).copied().unwrap_or(1),
+        starting_song: info.get(9).map_or(1, |song| song.saturating_add(1)),
+        load_address: parse_word(info, 0)?,
+        init_address: parse_word(info, 2)?,
+        play_address: parse_word(info, 4)?,
+        name: metadata.name.clone().unwrap_or_default(),
+        artist: metadata.artist.clone().unwrap_or_default(),
+        copyright: metadata.copyright.clone().unwrap_or_default(),
+        ntsc_speed: speed(0, NTSC_SPEED)?,
+        pal_speed: speed(2, PAL_SPEED)?,
+        bankswitch,
+        region: Region::from_flags(info[6]),
+        expansion_audio: info[7],
+        flags: 0,
+    };
+
+    let nsf = Nsf {
+        header,
+        data: Cow::Borrowed(program),
+    };
+
+    Ok((nsf, metadata))
+}
+
+impl Nsf<'_> {
+    /// Serialize the program into an `NSFe` file with the metadata
+    #[must_use]
+    pub fn to_nsfe(&self, metadata: &Metadata) -> Vec<u8> {
+        let header = &self.header;
+
+        let mut info = Vec::with_capacity(10);
+        info.extend_from_slice(&header.load_address.to_le_bytes());
+        info.extend_from_slice(&header.init_address.to_le_bytes());
+        info.extend_from_slice(&header.play_address.to_le_bytes());
+        info.extend_from_slice(&[
+            header.region.flags(),
+            header.expansion_audio,
+            header.total_songs,
+            header.starting_song.saturating_sub(1),
+        ]);
+
+        let mut rate = header.ntsc_speed.to_le_bytes().to_vec();
+        rate.extend_from_slice(&header.pal_speed.to_le_bytes());
+
+        let mut chunks = Vec::new();
+        chunks.push(Chunk {
+            id: INFO,
+            data: Cow::Owned(info),
+        });
+        if let Some(bankswitch) = header.bankswitch {
+            chunks.push(Chunk {
+                id: BANK,
+                data: Cow::Owned(bankswitch.to_vec()),
+            });
+        }
+        chunks.push(Chunk {
+            id: RATE,
+            data: Cow::Owned(rate),
+        });
+        chunks.extend(metadata.to_chunks(header));
+        chunks.push(Chunk {
+            id: DATA,
+            data: Cow::Borrowed(&self.data),
+        });
+
+        let mut data = MAGIC_BYTES.to_vec();
+        data.extend(write_chunks(&chunks));
+        data
+    }
+
+    /// Serialize the program into an NSF2 file with the metadata appended
+    pub fn to_nsf2(&self, metadata: &Metadata) -> Result<Vec<u8>> {
+        if self.data.len() > MAX_NSF2_DATA_SIZE {
+            return Err(Error::TooLarge(self.data.len()));
+        }
+
+        let mut data = self.to_bytes();
+        data[0x05] = data[0x05].max(NSF2_VERSION);
+        data[0x7C] |= 1 << NSF2_METADATA_FLAG;
+        // The size was checked to fit into the 24 bits of the field
+        #[allow(clippy::cast_possible_truncation)]
+        data[0x7D..0x80].copy_from_slice(&(self.data.len() as u32).to_le_bytes()[..3]);
+
+        data.extend(write_chunks(&metadata.to_chunks(&self.header)));
+        Ok(data)
+    }
+}
@@ -0,0 +1,81 @@
+use nsf_parser::{Error, Nsf, Region};
+
+fn nsf() -> Vec<u8> {
+    let mut file = vec![0; 0x80];
+    file[..5].copy_from_slice(b"NESM\x1A");
+    file[0x05] = 1;
+    file[0x06] = 3;
+    file[0x07] = 2;
+    file[0x08..0x0E].copy_from_slice(&[0x00, 0x80, 0x03, 0x80, 0x06, 0x80]);
+    file[0x0E..0x12].copy_from_slice(b"Song");
+    file[0x2E..0x34].copy_from_slice(b"Artist");
+    file[0x4E..0x52].copy_from_slice(b"2026");
+    file[0x6E..0x70].copy_from_slice(&16_639_u16.to_le_bytes());
+    file[0x78..0x7A].copy_from_slice(&19_997_u16.to_le_bytes());
+    file[0x7A] = 0x02;
+    file[0x7B] = 0x01;
+    file.extend_from_slice(&[0x4C, 0x00, 0x80, 0x60]);
+    file
+}
+
+#[test]
+fn parse() {
+    let file = nsf();
+    let nsf = Nsf::from_bytes(&file).unwrap();
+    let header = &nsf.header;
+
+    assert_eq!(
+        (header.version, header.total_songs, header.starting_song),
+        (1, 3, 2)
+    );
+    assert_eq!(
+        (
+            header.load_address,
+            header.init_address,
+            header.play_address
+        ),
+        (0x8000, 0x8003, 0x8006)
+    );
+    assert_eq!(
+        (&*header.name, &*header.artist, &*header.copyright),
+        ("Song", "Artist", "2026")
+    );
+    assert_eq!((header.ntsc_speed, header.pal_speed), (16_639, 19_997));
+    assert_eq!(header.bankswitch, None);
+    assert_eq!(header.region, Region::Dual);
+    assert_eq!(header.expansion_audio, 0x01);
+    assert_eq!(*nsf.data, [0x4C, 0x00, 0x80, 0x60]);
+
+    #[cfg(feature = "std")]
+    assert_eq!(
+        *Nsf::from_reader(&mut file.as_slice()).unwrap().data,
+        *nsf.data
+    );
+}
+
+#[test]
+fn bankswitching() {
+    let mut file = nsf();
+    file[0x70..0x78].copy_from_slice(&[0, 1, 2, 3, 4, 5, 6, 7]);
+
+    assert_eq!(
+        Nsf::from_bytes(&file).unwrap().header.bankswitch,
+        Some([0, 1, 2, 3, 4, 5, 6, 7])
+    );
+}
+
+#[test]
+fn reject_invalid_files() {
+    let file = nsf();
+    assert!(matches!(
+        Nsf::from_bytes(&file[..0x7F]),
+        Err(Error::UnexpectedEof)
+    ));
+
+    let mut file = file;
+    file[3] = b'N';
+    assert!(matches!(
+        Nsf::from_bytes(&file),
+        Err(Error::MagicBytesMismatch(magic)) if magic == *b"NESN\x1A"
+    ));
+}
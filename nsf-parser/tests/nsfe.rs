@@ -0,0 +1,129 @@
+use nsf_parser::{
+    nsfe::{self, Metadata, Track},
+    Error, Nsf,
+};
+
+fn nsf() -> Vec<u8> {
+    let mut file = vec![0; 0x80];
+    file[..5].copy_from_slice(b"NESM\x1A");
+    file[0x05] = 1;
+    file[0x06] = 2;
+    file[0x07] = 1;
+    file[0x08..0x0E].copy_from_slice(&[0x00, 0x80, 0x03, 0x80, 0x06, 0x80]);
+    file[0x0E..0x12].copy_from_slice(b"Song");
+    file[0x2E..0x34].copy_from_slice(b"Artist");
+    file[0x4E..0x52].copy_from_slice(b"2026");
+    file[0x6E..0x70].copy_from_slice(&16_639_u16.to_le_bytes());
+    file[0x70..0x78].copy_from_slice(&[0, 1, 2, 3, 4, 5, 6, 7]);
+    file[0x78..0x7A].copy_from_slice(&19_997_u16.to_le_bytes());
+    file.extend_from_slice(&[0x4C, 0x00, 0x80, 0x60]);
+    file
+}
+
+fn metadata() -> Metadata {
+    let mut metadata = Metadata {
+        ripper: Some("Ripper".into()),
+        playlist: Some(vec![1, 0]),
+        ..Metadata::default()
+    };
+    *metadata.track_mut(1) = Track {
+        title: Some("Second".into()),
+        time: Some(90_000),
+        fade: Some(2_000),
+    };
+    metadata
+}
+
+#[test]
+fn nsf2_round_trip() {
+    let file = nsf();
+    let nsf = Nsf::from_bytes(&file).unwrap();
+    let nsf2 = nsf.to_nsf2(&metadata()).unwrap();
+    assert_eq!(nsf2[0x05], 2);
+    assert_eq!(nsf2[0x7C], 0x80);
+    assert_eq!(nsf2[0x7D..0x80], [4, 0, 0]);
+
+    // The metadata chunks aren't part of the program data
+    let parsed = Nsf::from_bytes(&nsf2).unwrap();
+    assert_eq!(parsed.data, nsf.data);
+    assert_eq!(parsed.header.flags, 0x80);
+    #[cfg(feature = "std")]
+    assert_eq!(
+        Nsf::from_reader(&mut nsf2.as_slice()).unwrap().data,
+        nsf.data
+    );
+
+    let expected = Metadata {
+        name: Some("Song".into()),
+        artist: Some("Artist".into()),
+        copyright: Some("2026".into()),
+        ..metadata()
+    };
+    assert_eq!(Metadata::from_nsf2(&nsf2).unwrap(), Some(expected));
+
+    // Converting again doesn't append the metadata a second time
+    assert_eq!(parsed.to_nsf2(&metadata()).unwrap(), nsf2);
+
+    let mut plain = file;
+    plain[0x05] = 2;
+    assert_eq!(parsed.to_bytes(), plain);
+    assert_eq!(Metadata::from_nsf2(&plain).unwrap(), None);
+}
+
+#[test]
+fn nsf2_keeps_flags() {
+    let mut file = nsf();
+    file[0x05] = 2;
+    file[0x7C] = 0x01;
+
+    let nsf = Nsf::from_bytes(&file).unwrap();
+    assert_eq!(nsf.header.flags, 0x01);
+    assert_eq!(nsf.to_bytes(), file);
+    assert_eq!(nsf.to_nsf2(&Metadata::default()).unwrap()[0x7C], 0x81);
+}
+
+#[test]
+fn nsfe_round_trip() {
+    let file = nsf();
+    let nsf = Nsf::from_bytes(&file).unwrap();
+    let nsfe = nsf.to_nsfe(&metadata());
+
+    let (parsed, parsed_metadata) = nsfe::parse(&nsfe).unwrap();
+    assert_eq!(parsed.data, nsf.data);
+    assert_eq!(parsed.header.name, "Song");
+    assert_eq!(parsed.header.bankswitch, nsf.header.bankswitch);
+    assert_eq!(parsed.header.total_songs, 2);
+    assert_eq!(parsed.header.starting_song, 1);
+    assert_eq!(parsed_metadata.ripper.as_deref(), Some("Ripper"));
+    assert_eq!(parsed_metadata.tracks, metadata().tracks);
+    assert_eq!(parsed_metadata.playlist, Some(vec![1, 0]));
+
+    assert_eq!(parsed.to_nsfe(&parsed_metadata), nsfe);
+    assert_eq!(parsed.to_bytes(), file);
+}
+
+#[test]
+fn reject_invalid_chunks() {
+    let nsfe = Nsf::from_bytes(&nsf())
+        .unwrap()
+        .to_nsfe(&Metadata::default());
+
+    assert!(matches!(
+        nsfe::parse(&nsfe[..nsfe.len() - 8]),
+        Err(Error::UnexpectedEof)
+    ));
+    assert!(matches!(
+        nsfe::parse_chunks(b"\xFF\xFF\xFF\xFFDATA"),
+        Err(Error::UnexpectedEof) | Err(Error::InvalidChunk(_))
+    ));
+    assert!(matches!(
+        nsfe::parse(b"NSFE\x00\x00\x00\x00NEND"),
+        Err(Error::MissingChunk(id)) if id == *b"INFO"
+    ));
+
+    // The program length of NSF2 files can't point past the end of the file
+    let mut file = nsf();
+    file[0x05] = 2;
+    file[0x7D] = 5;
+    assert!(matches!(Nsf::from_bytes(&file), Err(Error::UnexpectedEof)));
+}
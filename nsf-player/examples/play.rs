@@ -0,0 +1,35 @@
+use {
+    nsf_player::Player,
+    std::{
+        env,
+        fs::File,
+        io::{BufWriter, Write},
+    },
+};
+
+const SAMPLE_RATE: u32 = 44_100;
+
+// Usage: play <nsf> <song> <seconds> <output>
+// The output contains raw 32-bit float samples (`ffplay -f f32le -ar 44100 -ac 1 <output>`)
+fn main() {
+    let mut args = env::args().skip(1);
+    let mut file = File::open(args.next().unwrap()).unwrap();
+    let song: u8 = args.next().map_or(1, |song| song.parse().unwrap());
+    let seconds: usize = args.next().map_or(30, |seconds| seconds.parse().unwrap());
+    let output = args.next().unwrap_or_else(|| "song.raw".into());
+
+    let nsf = nsf_parser::Nsf::from_reader(&mut file).unwrap();
+    println!(
+        "{} - {} ({} songs)",
+        nsf.header.artist, nsf.header.name, nsf.header.total_songs
+    );
+
+    let mut player = Player::new(&nsf, SAMPLE_RATE);
+    player.start_song(song - 1).unwrap();
+
+    let samples = player.samples(SAMPLE_RATE as usize * seconds).unwrap();
+    let mut output = BufWriter::new(File::create(output).unwrap());
+    for sample in samples {
+        output.write_all(&sample.to_le_bytes()).unwrap();
+    }
+}
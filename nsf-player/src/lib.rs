@@ -0,0 +1,354 @@
+#![no_std]
+#![warn(clippy::all, clippy::pedantic)]
+
+//!
+//! Player for NSF files
+//!
+//! [Playback documentation](https://www.nesdev.org/wiki/NSF#Initializing_a_tune)
+//!
+
+extern crate alloc;
+
+mod render;
+mod wav;
+
+pub use {render::RenderLength, wav::encode_wav};
+
+use {
+    alloc::{collections::BTreeSet, vec, vec::Vec},
+    core::fmt,
+    mos6502_cpu::{Bus, Cpu},
+    nes_apu::{Apu, Timing, NTSC_CPU_CLOCK},
+    nsf_parser::{ExpansionChip, Nsf, Region},
+};
+
+/// Clock rate of the CPU of PAL consoles in Hz
+pub const PAL_CPU_CLOCK: u32 = 1_662_607;
+
+const BANK_SIZE: usize = 0x1000;
+const BANK_COUNT: usize = 8;
+const PRG_RAM_SIZE: usize = 0x2000;
+
+// The init and play routines are called with a `JSR` to this address, which isn't mapped to anything
+const RETURN_ADDRESS: u16 = 0x5FF6;
+
+// Upper limit for the init routine; a few frames worth of cycles
+const INIT_CYCLE_LIMIT: u64 = 1_000_000;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The song number is higher than the amount of songs in the file
+    InvalidSong(u8),
+    /// The init or play routine didn't return in time
+    Timeout,
+    Cpu(mos6502_cpu::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidSong(song) => write!(f, "Song {song} doesn't exist"),
+            Self::Timeout => f.write_str("Routine didn't return in time"),
+            Self::Cpu(err) => write!(f, "CPU error: {err}"),
+        }
+    }
+}
+
+impl From<mos6502_cpu::Error> for Error {
+    fn from(err: mos6502_cpu::Error) -> Self {
+        Self::Cpu(err)
+    }
+}
+
+/// Everything the CPU of the player can access
+struct Memory {
+    ram: [u8; 0x800],
+    prg_ram: Vec<u8>,
+    /// Program data, padded so it starts at the beginning of a bank
+    rom: Vec<u8>,
+    banks: [u8; BANK_COUNT],
+    /// The FDS has RAM at `$6000`-`$DFFF`, with the program data loaded into it
+    fds: bool,
+    apu: Apu,
+}
+
+impl Memory {
+    fn rom_offset(&self, address: u16) -> usize {
+        let slot = usize::from(address - 0x8000) / BANK_SIZE;
+        usize::from(self.banks[slot]) * BANK_SIZE + usize::from(address) % BANK_SIZE
+    }
+
+    // Bankswitching of FDS tunes copies the bank into the RAM at `$6000`-`$7FFF`
+    fn load_ram_bank(&mut self, slot: usize, bank: u8) {
+        let start = usize::from(bank) * BANK_SIZE;
+        let data = self.rom.get(start..).unwrap_or_default();
+        let data = &data[..data.len().min(BANK_SIZE)];
+
+        let ram = &mut self.prg_ram[slot * BANK_SIZE..(slot + 1) * BANK_SIZE];
+        ram.fill(0);
+        ram[..data.len()].copy_from_slice(data);
+    }
+}
+
+impl Bus for Memory {
+    fn read(&mut self, address: u16) -> u8 {
+        #[cfg(any(feature = "fds", feature = "n163"))]
+        if let Some(value) = self.apu.read_expansion(address) {
+            return value;
+        }
+
+        match address {
+            0x0000..=0x1FFF => self.ram[usize::from(address) % self.ram.len()],
+            0x4015 => self.apu.read_status(),
+            0x6000..=0x7FFF => self.prg_ram[usize::from(address - 0x6000)],
+            0x8000..=0xFFFF => {
+                let offset = self.rom_offset(address);
+                self.rom.get(offset).copied().unwrap_or(0)
+            }
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram[usize::from(address) % self.ram.len()] = value,
+            0x4000..=0x4017 => self.apu.write_register(address, value),
+            0x5FF6..=0x5FF7 if self.fds => self.load_ram_bank(usize::from(address - 0x5FF6), value),
+            0x5FF8..=0x5FFF => self.banks[usize::from(address - 0x5FF8)] = value,
+            0x6000..=0x7FFF => self.prg_ram[usize::from(address - 0x6000)] = value,
+            0x8000..=0xDFFF if self.fds => {
+                let offset = self.rom_offset(address);
+                if let Some(byte) = self.rom.get_mut(offset) {
+                    *byte = value;
+                }
+            }
+            #[cfg(any(feature = "vrc6", feature = "fds", feature = "n163"))]
+            _ => self.apu.write_expansion(address, value),
+            #[cfg(not(any(feature = "vrc6", feature = "fds", feature = "n163")))]
+            _ => {}
+        }
+    }
+}
+
+// APU with the expansion chips the tune needs, as far as they're enabled through the features of this crate
+#[allow(unused_mut, unused_variables)]
+fn new_apu(sample_rate: u32, is_pal: bool, expansion_chips: &BTreeSet<ExpansionChip>) -> Apu {
+    let timing = if is_pal { Timing::Pal } else { Timing::Ntsc };
+    let mut apu = Apu::new(sample_rate).with_timing(timing);
+
+    #[cfg(feature = "vrc6")]
+    if expansion_chips.contains(&ExpansionChip::Vrc6) {
+        apu.enable_vrc6();
+    }
+    #[cfg(feature = "fds")]
+    if expansion_chips.contains(&ExpansionChip::Fds) {
+        apu.enable_fds();
+    }
+    #[cfg(feature = "n163")]
+    if expansion_chips.contains(&ExpansionChip::N163) {
+        apu.enable_n163();
+    }
+
+    apu
+}
+
+/// NSF player streaming the samples of one song at a time
+pub struct Player {
+    cpu: Cpu,
+    memory: Memory,
+    /// Memory contents before the init routine, which FDS tunes can overwrite
+    initial_rom: Vec<u8>,
+    initial_prg_ram: Vec<u8>,
+    initial_banks: [u8; BANK_COUNT],
+    expansion_chips: BTreeSet<ExpansionChip>,
+    init_address: u16,
+    play_address: u16,
+    total_songs: u8,
+    is_pal: bool,
+    /// Interval between two calls of the play routine in CPU cycles
+    play_interval: u64,
+    /// Cycles since the song was started, including the ones the CPU was idle
+    cycle: u64,
+    next_play: u64,
+    buffer: Vec<f32>,
+}
+
+impl Player {
+    /// Create a player for the tune, generating samples at the sample rate (in Hz)
+    ///
+    /// Tunes which support both TV systems are played at the NTSC rate.
+    /// The expansion chips declared in the header or detected in the program get emulated if the feature of the chip
+    /// (`vrc6`, `fds` or `n163`) is enabled; the VRC7, MMC5 and Sunsoft 5B aren't supported.
+    #[must_use]
+    pub fn new(nsf: &Nsf<'_>, sample_rate: u32) -> Self {
+        let header = &nsf.header;
+        let expansion_chips = nsf.expansion_audio().required();
+        let fds = cfg!(feature = "fds") && expansion_chips.contains(&ExpansionChip::Fds);
+
+        // Without bankswitching the data gets placed at the load address inside of the 32 KiB window,
+        // FDS tunes can also start in the RAM at `$6000`-`$7FFF`
+        let mut prg_ram = vec![0; PRG_RAM_SIZE];
+        let (padding, initial_banks, data) = match header.bankswitch {
+            Some(banks) => (
+                usize::from(header.load_address) % BANK_SIZE,
+                banks,
+                &*nsf.data,
+            ),
+            None if fds && header.load_address < 0x8000 => {
+                let start = usize::from(header.load_address.saturating_sub(0x6000));
+                let (ram_part, rest) = nsf.data.split_at(nsf.data.len().min(PRG_RAM_SIZE - start));
+                prg_ram[start..start + ram_part.len()].copy_from_slice(ram_part);
+                (0, [0, 1, 2, 3, 4, 5, 6, 7], rest)
+            }
+            None => (
+                usize::from(header.load_address.saturating_sub(0x8000)),
+                [0, 1, 2, 3, 4, 5, 6, 7],
+                &*nsf.data,
+            ),
+        };
+        let mut rom = vec![0; padding];
+        rom.extend_from_slice(data);
+
+        let is_pal = header.region == Region::Pal;
+        let (speed, clock_rate, default_speed) = if is_pal {
+            (header.pal_speed, PAL_CPU_CLOCK, 20_000)
+        } else {
+            (header.ntsc_speed, NTSC_CPU_CLOCK, 16_639)
+        };
+        // Some files leave the speed empty and expect the VBlank rate
+        let speed = if speed == 0 { default_speed } else { speed };
+
+        let mut memory = Memory {
+            ram: [0; 0x800],
+            prg_ram,
+            rom: rom.clone(),
+            banks: initial_banks,
+            fds,
+            apu: new_apu(sample_rate, is_pal, &expansion_chips),
+        };
+        // The banks of `$6000`-`$7FFF` start out as the ones of `$E000`-`$FFFF`
+        if fds && header.bankswitch.is_some() {
+            memory.load_ram_bank(0, initial_banks[6]);
+            memory.load_ram_bank(1, initial_banks[7]);
+        }
+
+        Self {
+            cpu: Cpu::new(),
+            initial_rom: rom,
+            initial_prg_ram: memory.prg_ram.clone(),
+            memory,
+            initial_banks,
+            expansion_chips,
+            init_address: header.init_address,
+            play_address: header.play_address,
+            total_songs: header.total_songs,
+            is_pal,
+            play_interval: u64::from(speed) * u64::from(clock_rate) / 1_000_000,
+            cycle: 0,
+            next_play: 0,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Initialise the song (0-based) and start playing it
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the song doesn't exist or the init routine fails
+    pub fn start_song(&mut self, song: u8) -> Result<(), Error> {
+        if song >= self.total_songs {
+            return Err(Error::InvalidSong(song));
+        }
+
+        let sample_rate = self.memory.apu.sample_rate();
+        self.memory.ram = [0; 0x800];
+        self.memory.prg_ram.clone_from(&self.initial_prg_ram);
+        self.memory.rom.clone_from(&self.initial_rom);
+        self.memory.banks = self.initial_banks;
+        let logging = self.memory.apu.is_logging();
+        self.memory.apu = new_apu(sample_rate, self.is_pal, &self.expansion_chips);
+        self.memory.apu.set_logging(logging);
+        self.buffer.clear();
+
+        for address in 0x4000..=0x4013 {
+            self.memory.apu.write_register(address, 0);
+        }
+        self.memory.apu.write_register(0x4015, 0x0F);
+        self.memory.apu.write_register(0x4017, 0x40);
+
+        self.cpu = Cpu::new();
+        self.cpu.s = 0xFD;
+        self.cpu.a = song;
+        self.cpu.x = u8::from(self.is_pal);
+        self.cycle = 0;
+        self.call(self.init_address, INIT_CYCLE_LIMIT)?;
+
+        // Whatever the init routine generated is part of the song
+        self.next_play = self.cycle;
+        Ok(())
+    }
+
+    /// Generate the next samples of the current song
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the play routine fails
+    pub fn samples(&mut self, count: usize) -> Result<Vec<f32>, Error> {
+        while self.buffer.len() < count {
+            self.play_frame()?;
+        }
+
+        let rest = self.buffer.split_off(count);
+        Ok(core::mem::replace(&mut self.buffer, rest))
+    }
+
+    /// Call the play routine once and wait until it's time for the next call
+    fn play_frame(&mut self) -> Result<(), Error> {
+        self.call(self.play_address, self.play_interval)?;
+
+        // The CPU is idle until the next call
+        self.next_play += self.play_interval;
+        if self.cycle < self.next_play {
+            self.tick_apu(self.next_play - self.cycle);
+        }
+
+        let samples = self.memory.apu.take_samples();
+        self.buffer.extend(samples);
+        Ok(())
+    }
+
+    /// Run a subroutine until it returns
+    fn call(&mut self, address: u16, cycle_limit: u64) -> Result<(), Error> {
+        // Push the return address the same way `JSR` does
+        for byte in RETURN_ADDRESS.wrapping_sub(1).to_be_bytes() {
+            self.memory.ram[0x0100 | usize::from(self.cpu.s)] = byte;
+            self.cpu.s = self.cpu.s.wrapping_sub(1);
+        }
+        self.cpu.pc = address;
+
+        let start = self.cycle;
+        while self.cpu.pc != RETURN_ADDRESS {
+            if self.cycle - start > cycle_limit {
+                return Err(Error::Timeout);
+            }
+
+            let cycles = self.cpu.step(&mut self.memory)?;
+            self.tick_apu(cycles);
+        }
+
+        Ok(())
+    }
+
+    fn tick_apu(&mut self, cycles: u64) {
+        self.cycle += cycles;
+
+        for _ in 0..cycles {
+            self.memory.apu.tick();
+
+            if let Some(address) = self.memory.apu.dmc_dma_address() {
+                let value = self.memory.read(address);
+                self.memory.apu.load_dmc_sample(value);
+            }
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use {
+    crate::{Error, Player},
+    alloc::{collections::BTreeMap, vec::Vec},
+    core::convert::TryFrom,
+    mos6502_cpu::Bus,
+    nes_apu::vgm::VgmExport,
+};
+
+// FNV-1a, which is plenty to tell the states of a song apart
+const FNV_OFFSET: u64 = 0xCBF2_9CE4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01B3;
+
+/// How long [`Player::render`] plays the song
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RenderLength {
+    /// A fixed amount of seconds
+    Seconds(u32),
+    /// Until the song starts over or ends, for at most the amount of seconds
+    ///
+    /// The song counts as starting over once the memory of the tune is the same as it was on an earlier call
+    /// of the play routine. Songs ending in silence get cut off once the driver stops changing its memory.
+    /// Drivers counting the frames since the start never repeat their state and play until the limit.
+    UntilLoop { max_seconds: u32 },
+}
+
+impl Player {
+    /// Play the current song offline, returning all samples at once
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the play routine fails
+    pub fn render(&mut self, length: RenderLength) -> Result<Vec<f32>, Error> {
+        self.render_until(length).map(|(samples, _)| samples)
+    }
+
+    /// Play the song (0-based) from the start like [`Player::render`] and log the APU writes to a VGM file
+    ///
+    /// A detected loop becomes the loop of the file. The memory at `$C000`-`$FFFF` is included for the DMC samples,
+    /// in the banks selected at the end of the recording.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the song doesn't exist or the init or play routine fails
+    pub fn render_vgm(&mut self, song: u8, length: RenderLength) -> Result<Vec<u8>, Error> {
+        // Starting the song keeps the logging enabled, so the init routine is part of the log
+        self.memory.apu.set_logging(true);
+        let result = self
+            .start_song(song)
+            .and_then(|()| self.render_until(length));
+        let log = self.memory.apu.take_log();
+        self.memory.apu.set_logging(false);
+        let (_, loop_cycle) = result?;
+
+        let dpcm_memory: Vec<u8> = (0xC000..=0xFFFF)
+            .map(|address| self.memory.read(address))
+            .collect();
+        let mut export =
+            VgmExport::new(self.memory.apu.clock_rate()).with_dpcm_memory(&dpcm_memory);
+        if let Some(cycle) = loop_cycle {
+            export = export.with_loop(cycle);
+        }
+
+        Ok(export.export(&log, self.cycle))
+    }
+
+    // Render the song, returning the cycle its loop starts at if one was detected
+    fn render_until(&mut self, length: RenderLength) -> Result<(Vec<f32>, Option<u64>), Error> {
+        let (seconds, detect_loop) = match length {
+            RenderLength::Seconds(seconds) => (seconds, false),
+            RenderLength::UntilLoop { max_seconds } => (max_seconds, true),
+        };
+        let max_samples = u64::from(seconds) * u64::from(self.memory.apu.sample_rate());
+        let max_samples = usize::try_from(max_samples).unwrap_or(usize::MAX);
+
+        let mut states = BTreeMap::new();
+        let mut loop_cycle = None;
+        while self.buffer.len() < max_samples {
+            if detect_loop {
+                let state = self.state_hash();
+                if let Some(&cycle) = states.get(&state) {
+                    loop_cycle = Some(cycle);
+                    break;
+                }
+                states.insert(state, self.cycle);
+            }
+
+            self.play_frame()?;
+        }
+
+        self.buffer.truncate(max_samples);
+        Ok((core::mem::take(&mut self.buffer), loop_cycle))
+    }
+
+    // Hash of the memory the driver keeps its state in
+    fn state_hash(&self) -> u64 {
+        let memory = &self.memory;
+        // FDS tunes can also change their program data
+        let rom: &[u8] = if memory.fds { &memory.rom } else { &[] };
+
+        [&memory.ram[..], &memory.prg_ram, &memory.banks, rom]
+            .iter()
+            .flat_map(|part| part.iter())
+            .fold(FNV_OFFSET, |hash, &byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+            })
+    }
+}
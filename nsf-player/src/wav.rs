@@ -0,0 +1,55 @@
+use {alloc::vec::Vec, core::convert::TryFrom};
+
+const HEADER_SIZE: u32 = 44;
+const CHANNELS: u16 = 1;
+const BITS_PER_SAMPLE: u16 = 16;
+const BYTES_PER_SAMPLE: u16 = BITS_PER_SAMPLE / 8;
+
+// Pole of the high-pass filter removing the DC offset, about 20 Hz at common sample rates
+const DC_FILTER_POLE: f32 = 0.997;
+
+/// Encode samples of the player as a mono 16-bit PCM WAV file
+///
+/// The APU only outputs positive values, so the samples get centered around zero by a high-pass filter
+/// like the one of the console's audio output before being scaled to the full range.
+#[must_use]
+pub fn encode_wav(samples: &[f32], sample_rate: u32) -> Vec<u8> {
+    let data_size = u32::try_from(samples.len())
+        .unwrap_or(u32::MAX)
+        .saturating_mul(u32::from(BYTES_PER_SAMPLE))
+        .min(u32::MAX - HEADER_SIZE);
+
+    let mut wav = Vec::with_capacity((HEADER_SIZE + data_size) as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(HEADER_SIZE - 8 + data_size).to_le_bytes());
+    wav.extend_from_slice(b"WAVE");
+
+    wav.extend_from_slice(b"fmt ");
+    wav.extend_from_slice(&16_u32.to_le_bytes());
+    // Uncompressed PCM
+    wav.extend_from_slice(&1_u16.to_le_bytes());
+    wav.extend_from_slice(&CHANNELS.to_le_bytes());
+    wav.extend_from_slice(&sample_rate.to_le_bytes());
+    wav.extend_from_slice(&(sample_rate * u32::from(CHANNELS * BYTES_PER_SAMPLE)).to_le_bytes());
+    wav.extend_from_slice(&(CHANNELS * BYTES_PER_SAMPLE).to_le_bytes());
+    wav.extend_from_slice(&BITS_PER_SAMPLE.to_le_bytes());
+
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_size.to_le_bytes());
+
+    let mut previous_input = samples.first().copied().unwrap_or(0.0);
+    let mut previous_output = 0.0;
+    for &sample in samples.iter().take((data_size / u32::from(BYTES_PER_SAMPLE)) as usize) {
+        let output = sample - previous_input + DC_FILTER_POLE * previous_output;
+        previous_input = sample;
+        previous_output = output;
+
+        // The value is clamped into the range of `i16` first
+        #[allow(clippy::cast_possible_truncation)]
+        let value =
+            (output * f32::from(i16::MAX)).clamp(f32::from(i16::MIN), f32::from(i16::MAX)) as i16;
+        wav.extend_from_slice(&value.to_le_bytes());
+    }
+
+    wav
+}